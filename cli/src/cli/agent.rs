@@ -1,21 +1,198 @@
-use bollard::{container::ListContainersOptions, API_DEFAULT_VERSION};
+use bollard::{
+    container::{DownloadFromContainerOptions, InspectContainerOptions, ListContainersOptions},
+    models::{ContainerInspectResponse, ContainerSummary},
+    service::ListServicesOptions,
+    API_DEFAULT_VERSION,
+};
+use clap::Subcommand;
 use konarr::{
     bom::{BomParser, Parsers},
     client::{
-        projects::{agent::KonarrProjectSnapshotData, KonarrProject, KonarrProjects},
+        projects::{
+            agent::{KonarrProjectSnapshotData, LABEL_DESCRIPTION, LABEL_TITLE},
+            cache::{AgentCache, AGENT_CACHE_FILE},
+            swarm::{SwarmRole, SwarmService, LABEL_SERVICE_ID},
+            KonarrProject, KonarrProjects,
+        },
+        server::AgentSettings,
         snapshot::KonarrSnapshot,
     },
+    utils::containers::{networks_metadata, ContainerPort},
     Config, KonarrError,
 };
-use log::{debug, info};
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Instant};
 use tokio::{spawn, sync::Mutex};
 use tokio_schedule::{every, Job};
 
+#[derive(Subcommand, Debug, Clone)]
+pub enum AgentCommands {
+    /// Show the effective agent configuration (local and server settings)
+    ShowConfig,
+}
+
+/// Summary of an agent run
+#[derive(Debug, Serialize)]
+pub struct AgentSummary {
+    /// Konarr server URL
+    pub server: String,
+    /// Version of the agent
+    pub agent_version: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    /// Overall duration of the run
+    pub duration_ms: u64,
+    pub totals: AgentSummaryTotals,
+    pub containers: Vec<AgentContainerSummary>,
+    /// Error that stopped the run (not related to a single container)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Run skipped as the server is in maintenance mode
+    pub maintenance: bool,
+    #[serde(skip)]
+    started: Instant,
+}
+
+/// Totals of an agent run
+#[derive(Debug, Default, Serialize)]
+pub struct AgentSummaryTotals {
+    pub discovered: usize,
+    pub scanned: usize,
+    pub skipped: usize,
+    /// Containers not due to be scanned (`scan.interval_hours`)
+    pub interval: usize,
+    pub uploaded: usize,
+    pub linked: usize,
+    pub failed: usize,
+}
+
+/// Result of a single container
+#[derive(Debug, Default, Serialize)]
+pub struct AgentContainerSummary {
+    pub name: String,
+    pub image: String,
+    pub status: AgentContainerStatus,
+    /// If the SBOM tool was run for the container
+    pub scanned: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<u32>,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentContainerStatus {
+    /// SBOM was scanned and uploaded
+    Uploaded,
+    /// Snapshot is unchanged (only metadata updated)
+    #[default]
+    Skipped,
+    /// Not due to be scanned (scan interval of the project)
+    Interval,
+    /// Snapshot of another project tracking the same image was linked
+    Linked,
+    /// Scanning or uploading failed
+    Failed,
+}
+
+impl AgentSummary {
+    pub fn new(client: &konarr::client::KonarrClient) -> Self {
+        Self {
+            server: client.url().to_string(),
+            agent_version: konarr::KONARR_VERSION.to_string(),
+            started_at: chrono::Utc::now(),
+            duration_ms: 0,
+            totals: AgentSummaryTotals::default(),
+            containers: Vec::new(),
+            error: None,
+            maintenance: false,
+            started: Instant::now(),
+        }
+    }
+
+    /// Add the result of a container to the summary
+    pub fn push(&mut self, container: AgentContainerSummary) {
+        if container.scanned {
+            self.totals.scanned += 1;
+        }
+        match container.status {
+            AgentContainerStatus::Uploaded => self.totals.uploaded += 1,
+            AgentContainerStatus::Skipped => self.totals.skipped += 1,
+            AgentContainerStatus::Interval => self.totals.interval += 1,
+            AgentContainerStatus::Linked => self.totals.linked += 1,
+            AgentContainerStatus::Failed => self.totals.failed += 1,
+        }
+        self.containers.push(container);
+    }
+
+    /// Stop the timer for the run
+    pub fn finish(&mut self) {
+        self.duration_ms = self.started.elapsed().as_millis() as u64;
+    }
+
+    /// If any container (or the run itself) failed
+    pub fn has_failures(&self) -> bool {
+        self.totals.failed > 0 || self.error.is_some()
+    }
+
+    /// Exit status of the run
+    ///
+    /// With `--best-effort` failed containers don't fail the run, errors stopping the run
+    /// (e.g. Docker can not be reached) always do.
+    pub fn exit_status(&self, best_effort: bool) -> anyhow::Result<()> {
+        if let Some(error) = &self.error {
+            return Err(anyhow::anyhow!("Agent run failed: {}", error));
+        }
+        if self.totals.failed > 0 && !best_effort {
+            return Err(anyhow::anyhow!(
+                "Agent run failed ({} of {} containers failed)",
+                self.totals.failed,
+                self.totals.discovered
+            ));
+        }
+        Ok(())
+    }
+
+    /// Write the summary as JSON
+    pub fn write(&self, path: &PathBuf) -> Result<(), konarr::KonarrError> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Apply the server-authoritative agent settings to the local configuration
+///
+/// Returns the configuration keys set by the server.
+pub fn apply_server_settings(
+    config: &mut Config,
+    settings: Option<&AgentSettings>,
+) -> Vec<&'static str> {
+    let Some(settings) = settings else {
+        debug!("No agent settings from the server, using the local configuration");
+        return vec![];
+    };
+    let (keys, overrides) = settings.apply(&mut config.agent);
+    for setting in overrides {
+        info!(
+            "Agent setting `{}` is managed by the server: `{}` (local: `{}`)",
+            setting.key, setting.server, setting.local
+        );
+    }
+    keys
+}
+
+/// Print the effective agent configuration and the source of each value
+pub fn show_config(config: &Config, server: &[&str]) {
+    info!("Agent configuration (server settings replace the local ones)");
+    super::config::print_sources(&config.agent_sources(server));
+}
+
 pub async fn setup(
     config: &Config,
     client: &konarr::client::KonarrClient,
-) -> Result<(), konarr::KonarrError> {
+) -> Result<AgentSummary, konarr::KonarrError> {
     // ID -> Hostname -> New Project
     let mut project = if let Some(project_id) = config.agent.project_id {
         log::debug!("Project ID :: {}", project_id);
@@ -49,14 +226,14 @@ pub async fn setup(
                 // Auto-Create Projects
                 log::info!("Auto-Create mode enabled");
                 KonarrProject::new(hostname, "Server")
-                    .create(&client)
+                    .find_or_create(&client)
                     .await?
             }
         }
     } else {
         // TODO: Auto-Create Projects
         log::error!("Failed to get project by ID or Name");
-        return Err(KonarrError::UnknownError(
+        return Err(KonarrError::ProjectNotFound(
             "Unknown project id / name".to_string(),
         ));
     };
@@ -69,12 +246,22 @@ pub async fn setup(
     let client = Arc::new(client.clone());
 
     log::info!("Running agent!");
-    run(&config, &client, &mut project).await?;
+    let summary = run(&config, &client, &mut project).await?;
+    info!(
+        "Agent run :: {} discovered, {} uploaded, {} skipped, {} not due, {} linked, {} failed ({}ms)",
+        summary.totals.discovered,
+        summary.totals.uploaded,
+        summary.totals.skipped,
+        summary.totals.interval,
+        summary.totals.linked,
+        summary.totals.failed,
+        summary.duration_ms
+    );
 
     if config.agent.monitoring {
         info!("Monitoring mode enabled");
 
-        let task = every(1).minutes().perform(move || {
+        let task = every(config.agent.interval()).minutes().perform(move || {
             // Only allow one task to run at a time, skip if already running
             let active = Mutex::new(false);
 
@@ -90,59 +277,117 @@ pub async fn setup(
                     info!("Task already running... Skipping");
                     return;
                 }
-                run(&config, &client, &mut project)
+                let summary = run(&config, &client, &mut project)
                     .await
                     .expect("Panic in monitoring mode...");
+                if summary.has_failures() {
+                    log::warn!("{} containers failed in this run", summary.totals.failed);
+                }
 
                 info!("Finishing task... Waiting for next");
             }
         });
         spawn(task).await.expect("Panic in monitoring mode...");
     }
-    Ok(())
+    Ok(summary)
 }
 
 async fn run(
     config: &Config,
     client: &konarr::client::KonarrClient,
     project: &mut KonarrProject,
-) -> Result<(), konarr::KonarrError> {
+) -> Result<AgentSummary, konarr::KonarrError> {
+    let mut summary = AgentSummary::new(client);
+
+    // Back off while the server is read-only (or can not be reached)
+    match client.server().await {
+        Ok(server) if server.maintenance => {
+            warn!("Server is in maintenance mode, skipping this run");
+            summary.maintenance = true;
+            summary.finish();
+            return Ok(summary);
+        }
+        Ok(_) => {}
+        Err(e) => {
+            warn!("Failed to reach the server, skipping this run: {}", e);
+            summary.error = Some(e.to_string());
+            summary.finish();
+            return Ok(summary);
+        }
+    }
+
     // The host
     debug!("Host Project :: {:?}", project);
     let snapshot = if let Some(snap) = project.snapshot.clone() {
         snap
     } else {
         info!("Creating Host Snapshot...");
-        KonarrSnapshot::create(client, project.id).await?
+        konarr::client::with_retries(|| KonarrSnapshot::create(client, project.id)).await?
     };
 
     debug!("Snapshot: {:#?}", snapshot);
     project.snapshot = Some(snapshot);
 
+    // Local cache of the uploaded SBOMs (reloaded every run)
+    let mut cache = if config.agent.no_cache {
+        debug!("Agent cache is disabled");
+        None
+    } else {
+        Some(AgentCache::load(
+            config.data_path()?.join(AGENT_CACHE_FILE),
+            config.agent.cache_expires(),
+        ))
+    };
+
     info!("Auto-Discover mode...");
 
     // Docker
     match std::env::var("DOCKER_HOST") {
         Ok(socket) => {
             info!("Using Docker Socket: {}", socket);
-            run_docker(config, Some(socket), client, project).await?;
+            if let Err(e) = run_docker(
+                config,
+                Some(socket),
+                client,
+                project,
+                &mut cache,
+                &mut summary,
+            )
+            .await
+            {
+                error!("Docker Error: {}", e);
+                summary.error = Some(e.to_string());
+            }
         }
         Err(_) => {
             let docker_socket = PathBuf::from("/var/run/docker.sock");
             if docker_socket.exists() {
                 info!("Using Docker Socket: {:?}", docker_socket);
-                run_docker(
+                if let Err(e) = run_docker(
                     config,
                     Some(docker_socket.to_str().unwrap().to_string()),
                     client,
                     project,
+                    &mut cache,
+                    &mut summary,
                 )
-                .await?;
+                .await
+                {
+                    error!("Docker Error: {}", e);
+                    summary.error = Some(e.to_string());
+                }
             }
         }
     }
 
-    Ok(())
+    if let Some(cache) = &cache {
+        if let Err(e) = cache.save() {
+            warn!("Failed to save the agent cache: {}", e);
+        }
+    }
+
+    summary.finish();
+    Ok(summary)
 }
 
 async fn run_docker(
@@ -150,6 +395,8 @@ async fn run_docker(
     socket: Option<String>,
     client: &konarr::client::KonarrClient,
     server_project: &KonarrProject,
+    cache: &mut Option<AgentCache>,
+    summary: &mut AgentSummary,
 ) -> Result<(), konarr::KonarrError> {
     let docker = if let Some(socket) = socket {
         bollard::Docker::connect_with_local(&socket, 120, API_DEFAULT_VERSION)?
@@ -192,145 +439,842 @@ async fn run_docker(
         }))
         .await?;
 
+    // Swarm services are scanned once by a manager node (not per replica)
+    let swarm = if config.agent.swarm.enabled {
+        let role = SwarmRole::from_info(&docker.info().await?);
+        info!("Docker Swarm mode :: {:?}", role);
+        role
+    } else {
+        SwarmRole::Inactive
+    };
+    let containers: Vec<ContainerSummary> = containers
+        .into_iter()
+        .filter(|container| {
+            let task = container
+                .labels
+                .as_ref()
+                .is_some_and(|labels| labels.contains_key(LABEL_SERVICE_ID));
+            if swarm.is_active() && task {
+                debug!("Skipping Swarm task container: {:?}", container.names);
+            }
+            !(swarm.is_active() && task)
+        })
+        .filter(|container| {
+            let image = container.image.clone().unwrap_or_default();
+            let excluded = container.names.iter().flatten().any(|name| {
+                config
+                    .agent
+                    .is_excluded(name.trim_start_matches('/'), &image)
+            });
+            if excluded {
+                info!("Skipping excluded container: {:?}", container.names);
+            }
+            !excluded
+        })
+        .collect();
+
     let prefix = server_project.name.clone();
 
+    summary.totals.discovered += containers.len();
+
     for container in containers {
-        let labels = container.labels.clone().unwrap_or_default();
+        let started = Instant::now();
+        let mut entry = AgentContainerSummary::default();
 
-        let name: String = if let Some(project) = labels.get("com.docker.compose.project") {
-            // From Compose metadata (`project` is folder, `service` is name)
-            if let Some(service) = labels.get("com.docker.compose.service") {
-                format!("{}/{}/{}", prefix, project, service)
-            } else {
-                format!("{}/{}", prefix, project)
+        match process_container(
+            config,
+            client,
+            &docker,
+            server_project,
+            &prefix,
+            container,
+            cache.as_mut(),
+            &mut entry,
+        )
+        .await
+        {
+            Ok(status) => entry.status = status,
+            Err(e) => {
+                error!("Failed to process container `{}`: {}", entry.name, e);
+                entry.status = AgentContainerStatus::Failed;
+                entry.error = Some(e.to_string());
             }
-        } else if let Some(title) = labels.get("org.opencontainers.image.title") {
-            // Name of the container
-            format!("{}/{}", prefix, title.clone())
-        } else if let Some(names) = &container.names {
-            names.first().unwrap().replacen("/", "", 1)
-        } else if let Some(image) = &container.image {
-            image.to_string()
+        }
+        entry.duration_ms = started.elapsed().as_millis() as u64;
+        summary.push(entry);
+    }
+
+    match swarm {
+        SwarmRole::Manager => run_swarm(config, client, &docker, cache, summary).await?,
+        SwarmRole::Worker => info!("Not a Swarm manager node, the services are skipped"),
+        SwarmRole::Inactive => {}
+    }
+
+    Ok(())
+}
+
+/// Scan (if needed) and update the metadata of a single container
+async fn process_container(
+    config: &Config,
+    client: &konarr::client::KonarrClient,
+    docker: &bollard::Docker,
+    server_project: &KonarrProject,
+    prefix: &str,
+    container: ContainerSummary,
+    mut cache: Option<&mut AgentCache>,
+    entry: &mut AgentContainerSummary,
+) -> Result<AgentContainerStatus, konarr::KonarrError> {
+    let labels = container.labels.clone().unwrap_or_default();
+
+    let name: String = if let Some(project) = labels.get("com.docker.compose.project") {
+        // From Compose metadata (`project` is folder, `service` is name)
+        if let Some(service) = labels.get("com.docker.compose.service") {
+            format!("{}/{}/{}", prefix, project, service)
         } else {
-            return Err(KonarrError::UnknownError("Container Name".to_string()));
-        };
+            format!("{}/{}", prefix, project)
+        }
+    } else if let Some(title) = labels.get(LABEL_TITLE) {
+        // Name of the container
+        format!("{}/{}", prefix, title.clone())
+    } else if let Some(names) = &container.names {
+        names.first().unwrap().replacen("/", "", 1)
+    } else if let Some(image) = &container.image {
+        image.to_string()
+    } else {
+        return Err(KonarrError::InvalidData(
+            "Container has no name".to_string(),
+        ));
+    };
+
+    info!("Container: {:?}", name);
+    entry.name = name.clone();
+    entry.image = container.image.clone().unwrap_or_default();
+
+    let description: Option<String> = labels.get(LABEL_DESCRIPTION).cloned();
+
+    let mut project = KonarrProject::new(name.clone(), "container".to_string());
+    project.parent = Some(server_project.id);
+    if let Some(title) = labels.get(LABEL_TITLE).filter(|title| !title.is_empty()) {
+        project.title = title.clone();
+    }
+    project.description = description.clone();
+    project.find_or_create(client).await?;
+    info!("Project for Container: {} ({})", project.name, project.id);
+
+    project.get(client).await?;
+    info!("Project: {} - {}", project.name, project.project_type);
 
-        info!("Container: {:?}", name);
+    if config.agent.sync_labels() {
+        project.sync_labels(client, &labels).await?;
+    }
+
+    let digest = container.image_id.clone().unwrap_or_default();
+    let mut scan_metadata = Vec::new();
+    let mut extra_metadata = Vec::new();
 
-        let description: Option<String> =
-            labels.get("org.opencontainers.image.description").cloned();
+    // Not due to be scanned, only the metadata of the latest snapshot is refreshed
+    let interval = project
+        .snapshot
+        .clone()
+        .filter(|_| !project.scan_due(chrono::Utc::now(), Some(&digest)));
+
+    let (container_snapshot, status) = match interval {
+        Some(snapshot) => {
+            info!(
+                "Container `{}` is not due to be scanned (every {}h)",
+                name,
+                project.scan_interval_hours.unwrap_or_default()
+            );
+            (snapshot, AgentContainerStatus::Interval)
+        }
+        None => {
+            let container_image = container.image.clone().unwrap_or_default();
 
-        let mut project: KonarrProject = if let Some(children) = &server_project.children {
-            match children.iter().find(|p| p.name == name) {
-                Some(project) => {
-                    info!("Found Project for Container: {}", project.name);
-                    project.clone()
+            let snapshot_data = KonarrProjectSnapshotData {
+                container_sha: container.image_id.clone(),
+                ..Default::default()
+            };
+
+            let cached = match cache.as_deref_mut() {
+                Some(cache) if !digest.is_empty() => {
+                    cached_snapshot(client, cache, project.id, &digest).await
                 }
-                None => {
-                    info!("Creating new Project for Container: {}", name);
-                    let mut proj = KonarrProject::new(name.clone(), "container".to_string());
-                    proj.parent = Some(server_project.id);
-                    proj.description = description.clone();
-                    proj.create(client).await?;
-                    proj
+                _ => None,
+            };
+            let shared = match cached {
+                None if config.agent.share_snapshots => {
+                    shared_snapshot(client, &mut project, &digest).await
                 }
-            }
-        } else {
-            info!("Creating new Project for Container: {}", name);
-            let mut proj = KonarrProject::new(name.clone(), "container".to_string());
-            proj.parent = Some(server_project.id);
-            proj.description = description.clone();
-            proj.create(client).await?;
-            proj
-        };
+                _ => None,
+            };
+            let linked = shared.is_some();
+            let container_snapshot = match (cached, shared) {
+                (Some(snapshot), _) => {
+                    info!("Using cached SBOM for Container: {}", name);
+                    snapshot
+                }
+                (None, Some(snapshot)) => {
+                    info!("Linked shared Snapshot for Container: {}", name);
+                    snapshot
+                }
+                (None, None) => project.snapshot(client, &snapshot_data).await?,
+            };
+
+            info!("Container Snapshot: {}", container_snapshot.id);
+
+            // TODO: Auto-install tool
+            let status = if container_snapshot.new {
+                entry.scanned = true;
+                let scan = konarr::tools::scan(&config, container_image).await?;
+                let mut results = scan.sbom.clone();
+                scan_metadata = scan.metadata();
+
+                let extra_paths = config.agent.extra_scan_paths(&project.name);
+                if !extra_paths.is_empty() {
+                    let mut sbom: serde_json::Value = serde_json::from_str(&results)?;
+                    extra_metadata = extra_scans(
+                        config,
+                        docker,
+                        container.id.as_deref().unwrap_or_default(),
+                        &mut sbom,
+                        &extra_paths,
+                    )
+                    .await;
+                    results = serde_json::to_string(&sbom)?;
+                }
+
+                upload_sbom(client, &container_snapshot, &results).await?;
+                if let Some(cache) = cache.as_deref_mut() {
+                    cache.insert(
+                        project.id,
+                        &digest,
+                        konarr::bom::sha256(results.as_bytes()),
+                        container_snapshot.id,
+                        chrono::Utc::now(),
+                    );
+                }
+                AgentContainerStatus::Uploaded
+            } else if linked {
+                AgentContainerStatus::Linked
+            } else {
+                info!("Container Snapshot already exists for Container: {}", name);
+                if let (Some(cache), Some(sha)) = (
+                    cache.as_deref_mut(),
+                    container_snapshot.metadata.get("bom.sha"),
+                ) {
+                    if !digest.is_empty() && cache.get(project.id, &digest).is_none() {
+                        cache.insert(
+                            project.id,
+                            &digest,
+                            sha,
+                            container_snapshot.id,
+                            container_snapshot.created_at,
+                        );
+                    }
+                }
+                AgentContainerStatus::Skipped
+            };
+            (container_snapshot, status)
+        }
+    };
+    entry.snapshot = Some(container_snapshot.id);
+
+    // TODO: Docker Compose metadata
+    // TODO: Creation time of the container
+
+    // Ports and networks (updated every cycle, even if the SBOM is unchanged)
+    let networking = container_networking(&container);
+
+    // We always update the metadata for the container snapshot
+    let mut snapshot_metadata = HashMap::from([
+        ("container", "true".to_string()),
+        ("container.image", container.image.unwrap_or_default()),
+        (
+            "container.sha",
+            container.image_id.clone().unwrap_or_default(),
+        ),
+        ("container.description", description.unwrap_or_default()),
+        (
+            "container.url",
+            labels
+                .get("org.opencontainers.image.url")
+                .cloned()
+                .unwrap_or_default(),
+        ),
+        (
+            "container.licenses",
+            labels
+                .get("org.opencontainers.image.licenses")
+                .cloned()
+                .unwrap_or_default(),
+        ),
+        (
+            "container.version",
+            labels
+                .get("org.opencontainers.image.version")
+                .cloned()
+                .unwrap_or_default(),
+        ),
+        (
+            "container.authors",
+            labels
+                .get("org.opencontainers.image.authors")
+                .cloned()
+                .unwrap_or_default(),
+        ),
+    ]);
+    snapshot_metadata.extend(networking);
+    // Runtime information (updated every cycle, even if the SBOM is unchanged)
+    match docker
+        .inspect_container(
+            container.id.as_deref().unwrap_or_default(),
+            None::<InspectContainerOptions>,
+        )
+        .await
+    {
+        Ok(inspect) => snapshot_metadata.extend(container_runtime(&inspect)),
+        Err(e) => warn!("Failed to inspect container `{}`: {}", name, e),
+    }
+    if !scan_metadata.is_empty() {
+        snapshot_metadata.extend(scan_metadata);
+        snapshot_metadata.insert("scan.agent.host", hostname());
+        snapshot_metadata.insert("scan.agent.version", konarr::KONARR_VERSION.to_string());
+        snapshot_metadata.insert(
+            "scan.container.sha",
+            container.image_id.clone().unwrap_or_default(),
+        );
+        snapshot_metadata.extend(
+            extra_metadata
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.clone())),
+        );
+    }
+    container_snapshot
+        .update_metadata(client, snapshot_metadata)
+        .await?;
 
-        project.get(client).await?;
-        info!("Project: {} - {}", project.name, project.project_type);
+    info!("Done with Container: {}", name);
+    Ok(status)
+}
+
+/// Validate an SBOM with the Konarr parsers and upload it to the snapshot
+async fn upload_sbom(
+    client: &konarr::client::KonarrClient,
+    snapshot: &KonarrSnapshot,
+    results: &str,
+) -> Result<(), konarr::KonarrError> {
+    log::info!("Parsing and validating SBOM with Konarr...");
+    match Parsers::parse(results.as_bytes()) {
+        Ok(bom) => {
+            info!("Validate SBOM spec supported by Konarr: {}", bom.sbom_type);
+        }
+        Err(e) => {
+            return Err(KonarrError::InvalidSbom {
+                reason: e.to_string(),
+            });
+        }
+    }
 
-        let container_image = container.image.clone().unwrap_or_default();
+    info!("Uploading BOM to Server...");
+    let json_data: serde_json::Value = serde_json::from_slice(results.as_bytes())?;
+
+    let result =
+        konarr::client::with_retries(|| snapshot.upload_bom(client, json_data.clone())).await?;
+    info!("Uploaded BOM to Server");
+    debug!("Snapshot: {:#?}", result);
+    Ok(())
+}
+
+/// Create the projects of the Swarm services (manager node) and scan their images
+async fn run_swarm(
+    config: &Config,
+    client: &konarr::client::KonarrClient,
+    docker: &bollard::Docker,
+    cache: &mut Option<AgentCache>,
+    summary: &mut AgentSummary,
+) -> Result<(), konarr::KonarrError> {
+    let mut cluster = KonarrProject::new(config.agent.swarm.cluster(), "Cluster");
+    cluster.find_or_create(client).await?;
+    info!("Swarm Cluster Project: {} ({})", cluster.name, cluster.id);
 
-        let snapshot_data = KonarrProjectSnapshotData {
-            container_sha: container.image_id.clone(),
+    info!("Getting Swarm Services...");
+    let services = docker
+        .list_services(Some(ListServicesOptions::<String> {
+            status: true,
             ..Default::default()
+        }))
+        .await?;
+    summary.totals.discovered += services.len();
+
+    for service in services {
+        let started = Instant::now();
+        let mut entry = AgentContainerSummary::default();
+
+        let Some(service) = SwarmService::from_service(&cluster.name, &service) else {
+            warn!(
+                "Skipping Swarm service without a name or image: {:?}",
+                service.id
+            );
+            continue;
         };
+        if config.agent.is_excluded(&service.name, &service.image) {
+            info!("Skipping excluded Swarm service: {}", service.name);
+            continue;
+        }
+        entry.name = service.name.clone();
+        entry.image = service.image.clone();
+
+        match process_service(
+            config,
+            client,
+            &cluster,
+            &service,
+            cache.as_mut(),
+            &mut entry,
+        )
+        .await
+        {
+            Ok(status) => entry.status = status,
+            Err(e) => {
+                error!("Failed to process service `{}`: {}", entry.name, e);
+                entry.status = AgentContainerStatus::Failed;
+                entry.error = Some(e.to_string());
+            }
+        }
+        entry.duration_ms = started.elapsed().as_millis() as u64;
+        summary.push(entry);
+    }
+    Ok(())
+}
+
+/// Scan (if needed) the image of a Swarm service and update its metadata
+async fn process_service(
+    config: &Config,
+    client: &konarr::client::KonarrClient,
+    cluster: &KonarrProject,
+    service: &SwarmService,
+    mut cache: Option<&mut AgentCache>,
+    entry: &mut AgentContainerSummary,
+) -> Result<AgentContainerStatus, konarr::KonarrError> {
+    info!("Swarm Service: {:?}", service.name);
 
-        let container_snapshot = project.snapshot(client, &snapshot_data).await?;
+    let mut project = KonarrProject::new(service.name.clone(), "container".to_string());
+    project.parent = Some(cluster.id);
+    project.find_or_create(client).await?;
+    project.get(client).await?;
+    if config.agent.sync_labels() {
+        project.sync_labels(client, &service.labels).await?;
+    }
 
-        info!("Container Snapshot: {}", container_snapshot.id);
+    // Services are pinned to the digest of the image (unless `--no-resolve-image`)
+    let digest = service.digest.clone().unwrap_or_default();
+    let mut snapshot_metadata = HashMap::from([
+        ("container", "true".to_string()),
+        ("container.image", service.image.clone()),
+        ("container.sha", digest.clone()),
+    ]);
 
-        // TODO: Auto-install tool
-        if container_snapshot.new {
-            let results = konarr::tools::run(&config, container_image).await?;
+    // Not due to be scanned, only the metadata of the latest snapshot is refreshed
+    let interval = project
+        .snapshot
+        .clone()
+        .filter(|_| !project.scan_due(chrono::Utc::now(), Some(&digest)));
 
-            log::info!("Parsing and validating SBOM with Konarr...");
-            match Parsers::parse(&results.as_bytes()) {
-                Ok(bom) => {
-                    info!("Validate SBOM spec supported by Konarr: {}", bom.sbom_type);
+    let (snapshot, status) = match interval {
+        Some(snapshot) => {
+            info!(
+                "Service `{}` is not due to be scanned (every {}h)",
+                service.name,
+                project.scan_interval_hours.unwrap_or_default()
+            );
+            (snapshot, AgentContainerStatus::Interval)
+        }
+        None => {
+            let cached = match cache.as_deref_mut() {
+                Some(cache) if !digest.is_empty() => {
+                    cached_snapshot(client, cache, project.id, &digest).await
                 }
-                Err(e) => {
-                    return Err(KonarrError::UnknownError(
-                        format!("Error parsing SBOM: {:?}", e).to_string(),
-                    ));
+                _ => None,
+            };
+            let snapshot = match cached {
+                Some(snapshot) => {
+                    info!("Using cached SBOM for Service: {}", service.name);
+                    snapshot
+                }
+                None => {
+                    let snapshot_data = KonarrProjectSnapshotData {
+                        container_sha: service.digest.clone(),
+                        ..Default::default()
+                    };
+                    project.snapshot(client, &snapshot_data).await?
                 }
+            };
+
+            let status = if snapshot.new {
+                entry.scanned = true;
+                let scan = konarr::tools::scan(config, service.reference()).await?;
+                upload_sbom(client, &snapshot, &scan.sbom).await?;
+                if let Some(cache) = cache.as_deref_mut().filter(|_| !digest.is_empty()) {
+                    cache.insert(
+                        project.id,
+                        &digest,
+                        konarr::bom::sha256(scan.sbom.as_bytes()),
+                        snapshot.id,
+                        chrono::Utc::now(),
+                    );
+                }
+                snapshot_metadata.extend(scan.metadata());
+                snapshot_metadata.insert("scan.agent.host", hostname());
+                snapshot_metadata.insert("scan.agent.version", konarr::KONARR_VERSION.to_string());
+                AgentContainerStatus::Uploaded
+            } else {
+                info!("Snapshot already exists for Service: {}", service.name);
+                AgentContainerStatus::Skipped
+            };
+            (snapshot, status)
+        }
+    };
+    entry.snapshot = Some(snapshot.id);
+
+    // Replicas and placement are updated every cycle
+    snapshot_metadata.extend(service.metadata());
+    snapshot.update_metadata(client, snapshot_metadata).await?;
+
+    info!("Done with Service: {}", service.name);
+    Ok(status)
+}
+
+/// Scan the extra paths of a container and merge the results into its SBOM
+///
+/// Failures are not fatal, they are logged and returned as `scan.extra.<path>.error`
+/// metadata (`scan.extra.<path>.components` has the number of merged components).
+async fn extra_scans(
+    config: &Config,
+    docker: &bollard::Docker,
+    container_id: &str,
+    sbom: &mut serde_json::Value,
+    paths: &[&str],
+) -> Vec<(String, String)> {
+    let mut metadata = Vec::new();
+    for (index, path) in paths.iter().enumerate() {
+        let result = match extra_scan(config, docker, container_id, path, index).await {
+            Ok(extra) => konarr::bom::cyclonedx::merge(sbom, &extra, path),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(count) => {
+                info!(
+                    "Merged {} components from the extra scan of `{}`",
+                    count, path
+                );
+                metadata.push((format!("scan.extra.{}.components", path), count.to_string()));
             }
+            Err(e) => {
+                warn!("Extra scan of `{}` failed: {}", path, e);
+                metadata.push((format!("scan.extra.{}.error", path), e.to_string()));
+            }
+        }
+    }
+    metadata
+}
 
-            info!("Uploading BOM to Server...");
-            let json_data: serde_json::Value = serde_json::from_slice(&results.as_bytes())?;
+/// Copy a path out of the container (`docker cp`) and scan it with Syft
+async fn extra_scan(
+    config: &Config,
+    docker: &bollard::Docker,
+    container_id: &str,
+    path: &str,
+    index: usize,
+) -> Result<serde_json::Value, KonarrError> {
+    use futures_util::StreamExt;
 
-            let result = container_snapshot.upload_bom(client, json_data).await?;
-            info!("Uploaded BOM to Server");
-            debug!("Snapshot: {:#?}", result);
-        } else {
-            info!("Container Snapshot already exists for Container: {}", name);
-        }
-
-        // TODO: Docker Compose metadata
-        // TODO: Creation time of the container
-
-        // We always update the metadata for the container snapshot
-        let snapshot_metadata = HashMap::from([
-            ("container", "true".to_string()),
-            ("container.image", container.image.unwrap_or_default()),
-            (
-                "container.sha",
-                container.image_id.clone().unwrap_or_default(),
-            ),
-            ("container.description", description.unwrap_or_default()),
-            (
-                "container.url",
-                labels
-                    .get("org.opencontainers.image.url")
-                    .cloned()
-                    .unwrap_or_default(),
-            ),
-            (
-                "container.licenses",
-                labels
-                    .get("org.opencontainers.image.licenses")
-                    .cloned()
-                    .unwrap_or_default(),
-            ),
-            (
-                "container.version",
-                labels
-                    .get("org.opencontainers.image.version")
-                    .cloned()
-                    .unwrap_or_default(),
-            ),
-            (
-                "container.authors",
-                labels
-                    .get("org.opencontainers.image.authors")
-                    .cloned()
-                    .unwrap_or_default(),
-            ),
-        ]);
-        container_snapshot
-            .update_metadata(client, snapshot_metadata)
-            .await?;
-
-        info!("Done with Container: {}", name);
+    let mut stream =
+        std::pin::pin!(docker
+            .download_from_container(container_id, Some(DownloadFromContainerOptions { path }),));
+    let mut archive = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        archive.extend_from_slice(&chunk?);
     }
+    debug!(
+        "Copied `{}` from the container ({} bytes)",
+        path,
+        archive.len()
+    );
 
-    Ok(())
+    let target = std::env::temp_dir().join(format!(
+        "konarr-extra-{}-{}",
+        container_id.chars().take(12).collect::<String>(),
+        index
+    ));
+    let unpack_target = target.clone();
+    tokio::task::spawn_blocking(move || {
+        tar::Archive::new(archive.as_slice()).unpack(unpack_target)
+    })
+    .await
+    .map_err(|e| KonarrError::unknown(e.to_string()))??;
+
+    let scan = konarr::tools::scan_dir(config, &target).await;
+    if let Err(e) = std::fs::remove_dir_all(&target) {
+        warn!("Failed to remove `{}`: {}", target.display(), e);
+    }
+    Ok(serde_json::from_str(&scan?.sbom)?)
+}
+
+/// Link the snapshot of another project tracking the same image digest
+///
+/// Only snapshots scanned in the last 24 hours are linked (the age a project snapshot is
+/// rescanned at), nothing is linked if the latest snapshot of the project is already
+/// for the digest.
+async fn shared_snapshot(
+    client: &konarr::client::KonarrClient,
+    project: &mut KonarrProject,
+    digest: &str,
+) -> Option<KonarrSnapshot> {
+    if digest.is_empty() {
+        return None;
+    }
+    let fresh = |snapshot: &KonarrSnapshot| {
+        chrono::Utc::now()
+            .signed_duration_since(snapshot.created_at)
+            .num_hours()
+            < 24
+    };
+    if let Some(latest) = &project.snapshot {
+        if latest
+            .metadata
+            .get("scan.container.sha")
+            .map(String::as_str)
+            == Some(digest)
+            && fresh(latest)
+        {
+            return None;
+        }
+    }
+
+    let snapshot = match KonarrSnapshot::by_digest(client, digest).await {
+        Ok(snapshot) if fresh(&snapshot) => snapshot,
+        Ok(snapshot) => {
+            debug!("Shared snapshot `{}` is older than 24 hours", snapshot.id);
+            return None;
+        }
+        Err(e) => {
+            debug!("No shared snapshot for `{}`: {}", digest, e);
+            return None;
+        }
+    };
+    if project.snapshot.as_ref().map(|s| s.id) == Some(snapshot.id) {
+        return None;
+    }
+    match project.link_snapshot(client, snapshot.id).await {
+        Ok(_) => Some(snapshot),
+        Err(e) => {
+            warn!("Failed to link snapshot `{}`: {}", snapshot.id, e);
+            None
+        }
+    }
+}
+
+/// Get the cached snapshot of a project for an image digest (if it still exists on the server)
+///
+/// Entries for snapshots which were removed, or which have no confirmed upload or a
+/// different SBOM, are dropped from the cache so the container is rescanned.
+async fn cached_snapshot(
+    client: &konarr::client::KonarrClient,
+    cache: &mut AgentCache,
+    project: u32,
+    digest: &str,
+) -> Option<KonarrSnapshot> {
+    let entry = cache.get(project, digest)?.clone();
+    match KonarrSnapshot::by_id(client, entry.snapshot).await {
+        Ok(snapshot)
+            if snapshot
+                .metadata
+                .get("bom.sha")
+                .is_some_and(|sha| *sha == entry.sbom_sha) =>
+        {
+            Some(snapshot)
+        }
+        Ok(_) => {
+            debug!(
+                "Cached snapshot `{}` has no or a different SBOM",
+                entry.snapshot
+            );
+            cache.remove(project, digest);
+            None
+        }
+        Err(e) => {
+            debug!("Cached snapshot `{}` not found: {}", entry.snapshot, e);
+            cache.remove(project, digest);
+            None
+        }
+    }
+}
+
+/// Runtime metadata for a container (status, health, restarts and uptime)
+fn container_runtime(inspect: &ContainerInspectResponse) -> HashMap<&'static str, String> {
+    let mut metadata = HashMap::new();
+
+    if let Some(state) = &inspect.state {
+        if let Some(status) = &state.status {
+            metadata.insert("container.status", status.to_string());
+        }
+        // Containers without a health check report `none`
+        let health = state
+            .health
+            .as_ref()
+            .and_then(|h| h.status)
+            .map(|s| s.to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "none".to_string());
+        metadata.insert("container.health", health);
+        if let Some(started_at) = &state.started_at {
+            metadata.insert("container.started_at", started_at.clone());
+            // Stopped containers keep the time they were last started
+            if state.running.unwrap_or_default() {
+                if let Ok(started) = chrono::DateTime::parse_from_rfc3339(started_at) {
+                    let uptime = chrono::Utc::now().signed_duration_since(started);
+                    metadata.insert("container.uptime", uptime.num_seconds().max(0).to_string());
+                }
+            }
+        }
+    }
+    metadata.insert(
+        "container.restart_count",
+        inspect.restart_count.unwrap_or_default().to_string(),
+    );
+    metadata
+}
+
+/// Ports (exposed and published) and networks of a container
+///
+/// The metadata is capped (see [ContainerPort::to_metadata] and [networks_metadata]).
+fn container_networking(container: &ContainerSummary) -> HashMap<&'static str, String> {
+    let ports = container.ports.iter().flatten().map(|port| {
+        let protocol = port.typ.map(|typ| typ.to_string()).unwrap_or_default();
+        let exposed = ContainerPort::new(protocol, port.private_port);
+        match port.public_port {
+            Some(host_port) => exposed.published(port.ip.clone(), host_port),
+            None => exposed,
+        }
+    });
+    let networks = container
+        .network_settings
+        .as_ref()
+        .and_then(|settings| settings.networks.as_ref())
+        .map(|networks| networks.keys().cloned().collect::<Vec<String>>())
+        .unwrap_or_default();
+
+    HashMap::from([
+        ("container.ports", ContainerPort::to_metadata(ports)),
+        ("container.networks", networks_metadata(networks)),
+    ])
+}
+
+/// Get the hostname of the machine the agent is running on
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary() -> AgentSummary {
+        let client = konarr::client::KonarrClient::init()
+            .base("http://localhost:9000")
+            .unwrap()
+            .build()
+            .unwrap();
+        AgentSummary::new(&client)
+    }
+
+    fn container(name: &str, status: AgentContainerStatus, scanned: bool) -> AgentContainerSummary {
+        AgentContainerSummary {
+            name: name.to_string(),
+            image: format!("{}:latest", name),
+            status,
+            scanned,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_summary_totals() {
+        let mut summary = summary();
+        summary.totals.discovered = 5;
+        summary.push(container("web", AgentContainerStatus::Uploaded, true));
+        summary.push(container("api", AgentContainerStatus::Uploaded, true));
+        summary.push(container("db", AgentContainerStatus::Skipped, false));
+        summary.push(container("cache", AgentContainerStatus::Linked, false));
+        summary.push(AgentContainerSummary {
+            error: Some("Failed to run tool".to_string()),
+            ..container("proxy", AgentContainerStatus::Failed, true)
+        });
+
+        assert_eq!(summary.totals.uploaded, 2);
+        assert_eq!(summary.totals.skipped, 1);
+        assert_eq!(summary.totals.linked, 1);
+        assert_eq!(summary.totals.failed, 1);
+        assert_eq!(summary.totals.interval, 0);
+        // Failed scans are still counted as scanned
+        assert_eq!(summary.totals.scanned, 3);
+        assert_eq!(summary.containers.len(), 5);
+        assert!(summary.has_failures());
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["totals"]["failed"], 1);
+        assert_eq!(json["containers"][4]["status"], "failed");
+        assert_eq!(json["containers"][4]["error"], "Failed to run tool");
+        assert!(json["containers"][0].get("error").is_none());
+    }
+
+    #[test]
+    fn test_summary_interval() {
+        let mut summary = summary();
+        summary.totals.discovered = 3;
+        summary.push(container("web", AgentContainerStatus::Interval, false));
+        summary.push(container("api", AgentContainerStatus::Interval, false));
+        summary.push(container("db", AgentContainerStatus::Skipped, false));
+
+        // Not due (interval) is reported separately from unchanged (skipped)
+        assert_eq!(summary.totals.interval, 2);
+        assert_eq!(summary.totals.skipped, 1);
+        assert_eq!(summary.totals.scanned, 0);
+        assert!(!summary.has_failures());
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["totals"]["interval"], 2);
+        assert_eq!(json["containers"][0]["status"], "interval");
+        assert_eq!(json["containers"][2]["status"], "skipped");
+    }
+
+    #[test]
+    fn test_summary_exit_status() {
+        let mut summary = summary();
+        summary.push(container("db", AgentContainerStatus::Skipped, false));
+        assert!(!summary.has_failures());
+        assert!(summary.exit_status(false).is_ok());
+        assert!(summary.exit_status(true).is_ok());
+
+        summary.totals.discovered = 2;
+        summary.push(container("web", AgentContainerStatus::Failed, true));
+        let err = summary.exit_status(false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Agent run failed (1 of 2 containers failed)"
+        );
+        // `--best-effort` exits successfully
+        assert!(summary.exit_status(true).is_ok());
+
+        // Errors stopping the run (not a single container)
+        let mut summary = self::summary();
+        summary.error = Some("Docker socket not found".to_string());
+        assert!(summary.exit_status(false).is_err());
+        let err = summary.exit_status(true).unwrap_err();
+        assert_eq!(err.to_string(), "Agent run failed: Docker socket not found");
+    }
 }
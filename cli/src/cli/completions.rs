@@ -0,0 +1,170 @@
+//! Shell completions and the markdown help of the CLI
+//!
+//! Both are generated from the clap definitions of [Arguments].
+use clap::{Arg, ArgAction, Command, CommandFactory};
+use clap_complete::Shell;
+use std::io::Write;
+
+use super::Arguments;
+
+/// Name of the binary the completions are generated for
+pub const BIN_NAME: &str = env!("CARGO_BIN_NAME");
+
+/// Write the completion script for the shell to stdout
+pub fn completions(shell: Shell) -> std::io::Result<()> {
+    let mut command = Arguments::command();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, BIN_NAME, &mut script);
+    std::io::stdout().write_all(&script)
+}
+
+/// Help of every command (and subcommand) as markdown
+pub fn markdown_help() -> String {
+    let mut command = Arguments::command().bin_name(BIN_NAME);
+    command.build();
+
+    let mut markdown = format!("# Command-Line Help for `{}`\n\n", BIN_NAME);
+    markdown.push_str("This document contains the help content for the command-line program.\n\n");
+    markdown.push_str("**Command Overview:**\n\n");
+    command_index(&command, &mut markdown);
+    markdown.push('\n');
+    command_help(&command, &mut markdown);
+    markdown
+}
+
+/// Subcommands which are not hidden (without the generated `help` subcommand)
+fn visible_subcommands(command: &Command) -> impl Iterator<Item = &Command> {
+    command
+        .get_subcommands()
+        .filter(|c| !c.is_hide_set() && c.get_name() != "help")
+}
+
+fn command_path(command: &Command) -> String {
+    command
+        .get_bin_name()
+        .unwrap_or(command.get_name())
+        .to_string()
+}
+
+fn command_index(command: &Command, markdown: &mut String) {
+    let path = command_path(command);
+    markdown.push_str(&format!("* [`{}`↴](#{})\n", path, path.replace(' ', "-")));
+    for subcommand in visible_subcommands(command) {
+        command_index(subcommand, markdown);
+    }
+}
+
+fn command_help(command: &Command, markdown: &mut String) {
+    let path = command_path(command);
+    markdown.push_str(&format!("## `{}`\n\n", path));
+    if let Some(about) = command.get_long_about().or(command.get_about()) {
+        markdown.push_str(&format!("{}\n\n", about));
+    }
+    let usage = command
+        .clone()
+        .render_usage()
+        .to_string()
+        .replace("Usage: ", "");
+    markdown.push_str(&format!("**Usage:** `{}`\n\n", usage));
+
+    let subcommands: Vec<&Command> = visible_subcommands(command).collect();
+    if !subcommands.is_empty() {
+        markdown.push_str("###### **Subcommands:**\n\n");
+        for subcommand in subcommands.iter() {
+            markdown.push_str(&format!("* `{}`", subcommand.get_name()));
+            if let Some(about) = subcommand.get_about() {
+                markdown.push_str(&format!(" — {}", about));
+            }
+            markdown.push('\n');
+        }
+        markdown.push('\n');
+    }
+
+    let (positionals, options): (Vec<&Arg>, Vec<&Arg>) = command
+        .get_arguments()
+        .filter(|arg| {
+            !arg.is_hide_set()
+                && !matches!(
+                    arg.get_action(),
+                    ArgAction::Help
+                        | ArgAction::HelpShort
+                        | ArgAction::HelpLong
+                        | ArgAction::Version
+                )
+        })
+        .partition(|arg| arg.is_positional());
+    if !positionals.is_empty() {
+        markdown.push_str("###### **Arguments:**\n\n");
+        for arg in positionals {
+            argument_help(arg, markdown);
+        }
+        markdown.push('\n');
+    }
+    if !options.is_empty() {
+        markdown.push_str("###### **Options:**\n\n");
+        for arg in options {
+            argument_help(arg, markdown);
+        }
+        markdown.push('\n');
+    }
+
+    for subcommand in subcommands {
+        command_help(subcommand, markdown);
+    }
+}
+
+fn argument_help(arg: &Arg, markdown: &mut String) {
+    let name = match (arg.get_short(), arg.get_long()) {
+        (Some(short), Some(long)) => format!("-{}, --{}", short, long),
+        (None, Some(long)) => format!("--{}", long),
+        (Some(short), None) => format!("-{}", short),
+        (None, None) => format!("<{}>", arg.get_id().as_str().to_uppercase()),
+    };
+    let takes_value = !matches!(
+        arg.get_action(),
+        ArgAction::SetTrue | ArgAction::SetFalse | ArgAction::Count
+    );
+    let value = match arg.get_value_names() {
+        Some(names) if takes_value && !arg.is_positional() => format!(" <{}>", names.join(" ")),
+        _ if takes_value && !arg.is_positional() => {
+            format!(" <{}>", arg.get_id().as_str().to_uppercase())
+        }
+        _ => String::new(),
+    };
+    markdown.push_str(&format!("* `{}{}`", name, value));
+    if let Some(help) = arg.get_long_help().or(arg.get_help()) {
+        markdown.push_str(&format!(" — {}", help));
+    }
+    markdown.push('\n');
+
+    if let Some(env) = arg.get_env() {
+        markdown.push_str(&format!(
+            "\n  Environment variable: `{}`\n",
+            env.to_string_lossy()
+        ));
+    }
+    let defaults: Vec<String> = arg
+        .get_default_values()
+        .iter()
+        .map(|value| value.to_string_lossy().to_string())
+        .collect();
+    if !defaults.is_empty() && takes_value {
+        markdown.push_str(&format!("\n  Default value: `{}`\n", defaults.join(", ")));
+    }
+    let possible: Vec<String> = arg
+        .get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| match value.get_help() {
+            Some(help) => format!("  - `{}`: {}", value.get_name(), help),
+            None => format!("  - `{}`", value.get_name()),
+        })
+        .collect();
+    if !possible.is_empty() && takes_value {
+        markdown.push_str("\n  Possible values:\n");
+        for value in possible {
+            markdown.push_str(&format!("{}\n", value));
+        }
+    }
+    markdown.push('\n');
+}
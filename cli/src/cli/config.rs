@@ -0,0 +1,63 @@
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+use console::style;
+use konarr::{Config, ConfigIssueLevel, ConfigSource, ConfigValue};
+use log::{error, info, warn};
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigCommands {
+    /// Validate the effective configuration
+    Validate {
+        /// Validate the configuration for agent mode
+        #[clap(long)]
+        agent: bool,
+    },
+}
+
+pub async fn run(config: &Config, subcommands: Option<ConfigCommands>) -> Result<()> {
+    match subcommands {
+        Some(ConfigCommands::Validate { agent }) => {
+            if config.is_env_only() {
+                info!("Configuration :: environment only");
+            } else {
+                info!("Configuration :: {}", config.path().display());
+            }
+
+            print_sources(&config.sources());
+
+            let issues = config.validate(agent);
+            let errors = issues
+                .iter()
+                .filter(|i| i.level == ConfigIssueLevel::Error)
+                .count();
+            for issue in issues.iter() {
+                match issue.level {
+                    ConfigIssueLevel::Error => error!("{} :: {}", issue.key, issue.message),
+                    ConfigIssueLevel::Warning => warn!("{} :: {}", issue.key, issue.message),
+                }
+            }
+
+            if errors > 0 {
+                return Err(anyhow!("Configuration has {} error(s)", errors));
+            }
+            info!("Configuration is valid ({} warning(s))", issues.len());
+        }
+        None => {
+            info!("No subcommand provided");
+        }
+    }
+    Ok(())
+}
+
+/// Print the effective configuration values and their source
+pub fn print_sources(values: &[ConfigValue]) {
+    for value in values {
+        let source = match value.source {
+            ConfigSource::File => style(value.source.to_string()).blue(),
+            ConfigSource::Env => style(value.source.to_string()).green(),
+            ConfigSource::Default => style(value.source.to_string()).dim(),
+            ConfigSource::Server => style(value.source.to_string()).magenta(),
+        };
+        println!(" > {:<32} = {} ({})", value.key, value.value, source);
+    }
+}
@@ -1,29 +1,146 @@
-use anyhow::Result;
-use clap::Subcommand;
+use anyhow::{anyhow, Result};
+use clap::{Subcommand, ValueEnum};
 use geekorm::prelude::*;
 use log::{debug, info};
+use std::path::PathBuf;
 
-use konarr::{models::UserRole, Config};
+use konarr::{
+    models::{
+        migrations::MigrationPlan,
+        seed::{self, SeedProfile},
+        ComponentVersion, ServerSettings, UserRole,
+    },
+    Config,
+};
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum DatabaseCommands {
     Create {},
+    /// Migrate the database to the latest schema
+    Migrate {
+        /// Only show the migration plan (no changes are made)
+        #[clap(long)]
+        dry_run: bool,
+        /// Backup the SQLite database file to this path before migrating
+        #[clap(long)]
+        backup: Option<PathBuf>,
+    },
+    /// Merge duplicate component versions (safe to re-run)
+    DedupeVersions {},
+    /// Encrypt the secret settings stored in plain text (`KONARR_SERVER_ENCRYPTION_KEY`)
+    EncryptSecrets {},
+    /// Populate the database with deterministic demo data
+    Seed {
+        /// Seed profile
+        #[clap(long, value_enum, default_value_t = SeedProfileArg::Demo)]
+        profile: SeedProfileArg,
+        /// Remove all the data before seeding
+        #[clap(long)]
+        wipe: bool,
+    },
     /// Create a new user
     #[clap(visible_alias = "create-user")]
     User {},
 }
 
+/// Seed profiles
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedProfileArg {
+    /// Two servers with containers, alerts at each severity and a user per role
+    Demo,
+}
+
+impl From<SeedProfileArg> for SeedProfile {
+    fn from(value: SeedProfileArg) -> Self {
+        match value {
+            SeedProfileArg::Demo => SeedProfile::Demo,
+        }
+    }
+}
+
 pub async fn run(config: &Config, subcommands: Option<DatabaseCommands>) -> Result<()> {
     println!("Config :: {:#?}", config.database);
     let db = config.database().await?;
     let connection = db.connect()?;
 
     info!("Connected!");
+    konarr::models::settings::secrets::init(config.server.encryption_key.as_deref())?;
 
     match subcommands {
         Some(DatabaseCommands::Create {}) => {
             konarr::models::database_create(&connection).await?;
         }
+        Some(DatabaseCommands::Migrate { dry_run, backup }) => {
+            let plan = MigrationPlan::plan(&connection).await?;
+
+            info!("Current schema version :: v{}", plan.current);
+            info!("Target schema version  :: v{}", plan.target);
+
+            if !plan.is_required() {
+                info!("Database is up to date");
+                return Ok(());
+            }
+            info!("Migration statements   :: {}", plan.statements.len());
+            for statement in plan.statements.iter() {
+                info!(" > [{}] {}", statement.table, statement.description);
+                debug!("   {}", statement.sql);
+                if dry_run {
+                    println!("{}", statement.sql);
+                }
+            }
+
+            if dry_run {
+                info!("Dry run, no changes made");
+                return Ok(());
+            }
+
+            if let Some(backup) = backup {
+                let path = config
+                    .database
+                    .path
+                    .clone()
+                    .map(PathBuf::from)
+                    .filter(|p| p.is_file())
+                    .ok_or_else(|| anyhow!("Only SQLite database files can be backed up"))?;
+                info!("Backing up database to :: {}", backup.display());
+                std::fs::copy(&path, &backup)?;
+            }
+
+            plan.apply(&connection).await?;
+            info!("Database migrated successfully");
+        }
+        Some(DatabaseCommands::DedupeVersions {}) => {
+            let merged = ComponentVersion::dedupe(&connection).await?;
+            info!("Merged duplicate versions :: {}", merged);
+        }
+        Some(DatabaseCommands::EncryptSecrets {}) => {
+            if config.server.encryption_key.is_none() {
+                return Err(anyhow!(
+                    "Set `KONARR_SERVER_ENCRYPTION_KEY` to encrypt the secret settings"
+                ));
+            }
+            // Fails if the settings were encrypted with another key
+            ServerSettings::verify_secrets(&connection).await?;
+            let encrypted = ServerSettings::encrypt_secrets(&connection).await?;
+            info!("Encrypted secret settings :: {}", encrypted);
+        }
+        Some(DatabaseCommands::Seed { profile, wipe }) => {
+            let profile = SeedProfile::from(profile);
+            if wipe {
+                seed::wipe(&connection).await?;
+            }
+            let summary = seed::seed(&connection, profile).await?;
+
+            info!("Seeded database ({})", profile);
+            info!(" > Projects     :: {}", summary.projects);
+            info!(" > Snapshots    :: {}", summary.snapshots);
+            info!(" > Dependencies :: {}", summary.dependencies);
+            info!(
+                " > Users        :: {} (password `{}`)",
+                summary.users,
+                seed::SEED_PASSWORD
+            );
+        }
         Some(DatabaseCommands::User {}) => {
             let username = crate::utils::interactive::prompt_input("Username")?;
             let password = crate::utils::interactive::prompt_password("Password")?;
@@ -0,0 +1,149 @@
+use std::io::Read;
+
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+use console::style;
+use konarr::{
+    client::dependencies::{KonarrAnnotation, KonarrComponentTag},
+    KonarrClient,
+};
+use log::info;
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum DependencyCommands {
+    /// Annotate a dependency (component), prints the ID of the annotation
+    Annotate {
+        /// Component ID
+        component: u32,
+        /// Text of the annotation (markdown, `-` to read it from stdin)
+        text: String,
+        /// Project the annotation applies to (all projects if not set)
+        #[clap(short, long)]
+        project: Option<u32>,
+    },
+    /// List the annotations of a dependency (component)
+    Annotations {
+        /// Component ID
+        component: u32,
+        /// Only the global annotations and the annotations of the project
+        #[clap(short, long)]
+        project: Option<u32>,
+    },
+    /// Delete an annotation (author or admin)
+    DeleteAnnotation {
+        /// Component ID
+        component: u32,
+        /// Annotation ID
+        annotation: u32,
+    },
+    /// Tag a dependency (component) or every component matching a PURL pattern (admin)
+    Tag {
+        /// Tag (for example `internal-sdk`)
+        tag: String,
+        /// Component ID
+        #[clap(short, long)]
+        component: Option<u32>,
+        /// PURL pattern (`*` wildcards, for example `pkg:npm/@acme/*`)
+        #[clap(short, long)]
+        purl: Option<String>,
+    },
+    /// Remove a tag from a dependency (component, admin)
+    Untag {
+        /// Component ID
+        component: u32,
+        /// Tag
+        tag: String,
+    },
+    /// List the tags of a dependency (component)
+    Tags {
+        /// Component ID
+        component: u32,
+    },
+}
+
+pub async fn run(client: &KonarrClient, subcommands: Option<DependencyCommands>) -> Result<()> {
+    match subcommands {
+        Some(DependencyCommands::Annotate {
+            component,
+            text,
+            project,
+        }) => {
+            let text = if text == "-" {
+                let mut text = String::new();
+                std::io::stdin().read_to_string(&mut text)?;
+                text
+            } else {
+                text
+            };
+
+            let mut annotation = KonarrAnnotation::new(component, text);
+            annotation.project = project;
+            annotation.create(client).await?;
+            info!("Annotated Component({}) :: {}", component, annotation.id);
+            println!("{}", annotation.id);
+        }
+        Some(DependencyCommands::Annotations { component, project }) => {
+            let annotations = KonarrAnnotation::list(client, component, project).await?;
+
+            info!("Annotations :: {}", annotations.len());
+            for annotation in annotations.iter() {
+                let scope = match annotation.project {
+                    Some(project) => format!("project {}", project),
+                    None => "global".to_string(),
+                };
+                println!(
+                    " > {} {} ({}) {}",
+                    style(annotation.id).green(),
+                    style(&annotation.author).blue(),
+                    scope,
+                    annotation.updated_at.format("%Y-%m-%d %H:%M")
+                );
+                for line in annotation.text.lines() {
+                    println!("   {}", line);
+                }
+            }
+        }
+        Some(DependencyCommands::DeleteAnnotation {
+            component,
+            annotation,
+        }) => {
+            let mut annotation = KonarrAnnotation {
+                id: annotation,
+                component_id: component,
+                ..Default::default()
+            };
+            annotation.delete(client).await?;
+            info!("Deleted Annotation :: {}", annotation.id);
+        }
+        Some(DependencyCommands::Tag {
+            tag,
+            component,
+            purl,
+        }) => {
+            let result = match (component, purl) {
+                (Some(component), None) => {
+                    KonarrComponentTag::assign(client, component, tag).await?
+                }
+                (None, Some(purl)) => KonarrComponentTag::assign_by_purl(client, purl, tag).await?,
+                _ => return Err(anyhow!("Provide either a component or a PURL pattern")),
+            };
+            info!("Tagged `{}` components with `{}`", result.count, result.tag);
+            println!("{}", result.count);
+        }
+        Some(DependencyCommands::Untag { component, tag }) => {
+            let result = KonarrComponentTag::remove(client, component, &tag).await?;
+            info!("Untagged Component({}) :: {}", component, result.tag);
+        }
+        Some(DependencyCommands::Tags { component }) => {
+            let tags = KonarrComponentTag::list(client, component).await?;
+            info!("Tags :: {}", tags.len());
+            for tag in tags.iter() {
+                println!(" > {}", style(tag).green());
+            }
+        }
+        None => {
+            return Err(anyhow!("No subcommand provided"));
+        }
+    }
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use clap::Subcommand;
 use console::style;
 use konarr::{
-    models::{Dependencies, Snapshot},
+    models::{ComponentType, Dependencies, Snapshot},
     Config,
 };
 use log::{debug, info};
@@ -11,6 +11,9 @@ pub enum DisplayCommands {
     Snapshots {
         #[clap(short, long)]
         id: Option<u32>,
+        /// Only display the dependencies of a component type
+        #[clap(short = 't', long, value_parser = super::search::parse_component_type)]
+        component_type: Option<ComponentType>,
     },
 }
 
@@ -25,7 +28,7 @@ pub async fn run(
     info!("Connected to database!");
 
     match subcommands {
-        Some(DisplayCommands::Snapshots { id }) => {
+        Some(DisplayCommands::Snapshots { id, component_type }) => {
             info!("Displaying Snapshots");
 
             if let Some(id) = id {
@@ -35,15 +38,43 @@ pub async fn run(
                 println!("Snapshot ID: {:?}", snapshot.id);
                 // Display Snapshot Details
                 for (name, md) in snapshot.metadata.iter() {
+                    if name.is_scan() {
+                        continue;
+                    }
                     println!(" > {}: {}", name, md.as_string());
                 }
 
-                let dependencies =
-                    Dependencies::fetch_dependencies_by_snapshop(&connection, snapshot.id).await?;
+                let mut scan: Vec<_> = snapshot
+                    .metadata
+                    .iter()
+                    .filter(|(name, _)| name.is_scan())
+                    .collect();
+                if !scan.is_empty() {
+                    scan.sort_by_key(|(name, _)| name.to_string());
+                    println!("Scan ::");
+                    for (name, md) in scan {
+                        let name = name.to_string();
+                        println!(
+                            " > {:<16}: {}",
+                            style(name.trim_start_matches("scan.")).blue(),
+                            style(md.as_string()).green()
+                        );
+                    }
+                }
+
+                let dependencies = super::search::filter_component_type(
+                    Dependencies::fetch_dependencies_by_snapshop(&connection, snapshot.id).await?,
+                    component_type,
+                );
 
                 println!("Dependencies :: {}", dependencies.len());
                 for dep in dependencies.iter() {
-                    println!(" > [{}] {}", dep.component_type(), dep.purl());
+                    println!(
+                        " > [{}] {} ({})",
+                        dep.component_type(),
+                        dep.display_name(),
+                        dep.purl()
+                    );
                 }
 
                 Ok(())
@@ -0,0 +1,438 @@
+//! Diagnose the agent environment (`konarr-cli doctor`)
+use anyhow::{anyhow, Result};
+use bollard::API_DEFAULT_VERSION;
+use console::style;
+use konarr::{tools::ToolConfig, Config, KonarrClient, KonarrError};
+use std::path::Path;
+
+/// Result of a doctor check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoctorStatus {
+    Ok,
+    /// Not required for the agent to work
+    Warning,
+    Failed,
+}
+
+/// A single doctor check with a remediation hint when it did not pass
+#[derive(Debug, Clone)]
+pub struct DoctorCheck {
+    pub name: String,
+    pub status: DoctorStatus,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl DoctorCheck {
+    fn ok(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: DoctorStatus::Ok,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn warning(name: impl Into<String>, message: impl Into<String>, hint: &str) -> Self {
+        Self {
+            name: name.into(),
+            status: DoctorStatus::Warning,
+            message: message.into(),
+            hint: Some(hint.to_string()),
+        }
+    }
+
+    fn failed(name: impl Into<String>, message: impl Into<String>, hint: &str) -> Self {
+        Self {
+            name: name.into(),
+            status: DoctorStatus::Failed,
+            message: message.into(),
+            hint: Some(hint.to_string()),
+        }
+    }
+
+    fn print(&self) {
+        let icon = match self.status {
+            DoctorStatus::Ok => "✅",
+            DoctorStatus::Warning => "⚠️ ",
+            DoctorStatus::Failed => "❌",
+        };
+        println!("{} {:<16} {}", icon, style(&self.name).bold(), self.message);
+        if let Some(hint) = &self.hint {
+            println!("   {:<16} {}", "", style(hint).dim());
+        }
+    }
+}
+
+/// Run the checks in order, the error lists the failed checks (non-zero exit code)
+pub async fn run(config: &Config, docker_socket: Option<String>) -> Result<()> {
+    let mut checks = vec![];
+
+    let (server, client) = check_server(config).await;
+    server.print();
+    checks.push(server);
+
+    let auth = match &client {
+        Some(client) => check_auth(client, config.agent.token.is_some()).await,
+        None => DoctorCheck::failed(
+            "Authentication",
+            "Skipped, the server is not reachable",
+            "Fix the server connection first",
+        ),
+    };
+    auth.print();
+    checks.push(auth);
+
+    let docker = check_docker(docker_socket.or(config.agent.docker_socket.clone())).await;
+    docker.print();
+    checks.push(docker);
+
+    for check in check_tools(config.agent.tool.as_deref()).await {
+        check.print();
+        checks.push(check);
+    }
+
+    let output = std::env::temp_dir().join("konarr");
+    for check in [
+        check_writable("Tool output", &output),
+        match config.data_path() {
+            Ok(path) => check_writable("Agent cache", path),
+            Err(e) => DoctorCheck::failed(
+                "Agent cache",
+                format!("Failed to create the data path: {}", e),
+                "Set `KONARR_DATA_PATH` to a writable directory",
+            ),
+        },
+    ] {
+        check.print();
+        checks.push(check);
+    }
+
+    let grypedb = check_grypedb(config).await;
+    grypedb.print();
+    checks.push(grypedb);
+
+    let failed: Vec<&str> = checks
+        .iter()
+        .filter(|check| check.status == DoctorStatus::Failed)
+        .map(|check| check.name.as_str())
+        .collect();
+    println!();
+    if failed.is_empty() {
+        println!("{}", style("All checks passed").green());
+        Ok(())
+    } else {
+        Err(anyhow!("Checks failed: {}", failed.join(", ")))
+    }
+}
+
+/// Server reachability and version (the client is returned to run the other checks)
+pub async fn check_server(config: &Config) -> (DoctorCheck, Option<KonarrClient>) {
+    const NAME: &str = "Server";
+    let client = match &config.agent.token {
+        Some(token) => config
+            .agent_client(token.to_string())
+            .map_err(|e| e.to_string()),
+        // Never prompt for a password (the session is checked by the authentication check)
+        None => match super::login::stored_session(config, false).await {
+            Ok(Some(client)) => Ok(client),
+            Ok(None) => config.server.client().map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        },
+    };
+    let client = match client {
+        Ok(client) => client,
+        Err(e) => {
+            return (
+                DoctorCheck::failed(
+                    NAME,
+                    format!("Invalid configuration: {}", e),
+                    "Set the server URL with `--instance` or `KONARR_INSTANCE`",
+                ),
+                None,
+            )
+        }
+    };
+
+    match client.server().await {
+        Ok(info) if info.version != client.version() => (
+            DoctorCheck::warning(
+                NAME,
+                format!(
+                    "{} is running v{} (client v{})",
+                    client.url(),
+                    info.version,
+                    client.version()
+                ),
+                "Update the agent to the same version as the server",
+            ),
+            Some(client),
+        ),
+        Ok(info) => (
+            DoctorCheck::ok(
+                NAME,
+                format!("{} is running v{}", client.url(), info.version),
+            ),
+            Some(client),
+        ),
+        Err(e) => (
+            DoctorCheck::failed(
+                NAME,
+                format!("Can not reach {}: {}", client.url(), e),
+                "Check the server URL, the network / proxy settings and that the server is running",
+            ),
+            None,
+        ),
+    }
+}
+
+/// The token (or the stored session of `konarr-cli login`) is valid and allowed to upload
+/// snapshots
+pub async fn check_auth(client: &KonarrClient, agent_token: bool) -> DoctorCheck {
+    const NAME: &str = "Authentication";
+    match client.whoami().await {
+        Ok(whoami) if !whoami.capabilities.can_upload => DoctorCheck::failed(
+            NAME,
+            format!(
+                "Authenticated as {} ({}) but can not upload snapshots",
+                whoami.user.username, whoami.auth
+            ),
+            "Use an agent token (`KONARR_AGENT_TOKEN`) from the server's admin settings",
+        ),
+        Ok(whoami) => DoctorCheck::ok(
+            NAME,
+            format!(
+                "Authenticated as {} ({})",
+                whoami.user.username, whoami.auth
+            ),
+        ),
+        Err(KonarrError::AuthenticationError(e)) if !agent_token => DoctorCheck::failed(
+            NAME,
+            format!("Not authenticated (no or expired session): {}", e),
+            "Login again with `konarr-cli login` or set an agent token with `KONARR_AGENT_TOKEN`",
+        ),
+        Err(e) => DoctorCheck::failed(
+            NAME,
+            format!("Not authenticated: {}", e),
+            "Set a valid agent token with `--agent-token` or `KONARR_AGENT_TOKEN`",
+        ),
+    }
+}
+
+/// The Docker / Podman socket is reachable and the user has the permission to use it
+pub async fn check_docker(socket: Option<String>) -> DoctorCheck {
+    const NAME: &str = "Docker";
+    let docker = match &socket {
+        Some(socket) => bollard::Docker::connect_with_local(socket, 120, API_DEFAULT_VERSION),
+        None => bollard::Docker::connect_with_unix_defaults(),
+    };
+    let socket = socket.unwrap_or_else(|| "/var/run/docker.sock".to_string());
+    let docker = match docker {
+        Ok(docker) => docker,
+        Err(e) => {
+            return DoctorCheck::failed(
+                NAME,
+                format!("Invalid socket {}: {}", socket, e),
+                "Set the socket with `--docker-socket` or `DOCKER_HOST`",
+            )
+        }
+    };
+    match docker.version().await {
+        Ok(version) => DoctorCheck::ok(
+            NAME,
+            format!(
+                "{} v{} ({})",
+                version.platform.map(|p| p.name).unwrap_or_default(),
+                version.version.unwrap_or_default(),
+                socket
+            ),
+        ),
+        Err(e) if e.to_string().contains("ermission denied") => DoctorCheck::failed(
+            NAME,
+            format!("Permission denied on {}", socket),
+            "Add the user to the `docker` group or mount the socket into the agent container",
+        ),
+        Err(e) => DoctorCheck::failed(
+            NAME,
+            format!("Can not connect to {}: {}", socket, e),
+            "Check Docker / Podman is running (`systemctl status docker` or `podman.socket`)",
+        ),
+    }
+}
+
+/// Every known tool is discovered and its `--version` runs
+///
+/// Missing tools only fail if they are the configured tool or if no tool is installed.
+pub async fn check_tools(configured: Option<&str>) -> Vec<DoctorCheck> {
+    let tools = ToolConfig::tools().await.unwrap_or_default();
+    let any_available = tools.iter().any(|tool| tool.is_available());
+    tools
+        .iter()
+        .map(|tool| check_tool(tool, configured, any_available))
+        .collect()
+}
+
+/// Check a single discovered tool
+pub fn check_tool(tool: &ToolConfig, configured: Option<&str>, any_available: bool) -> DoctorCheck {
+    let name = format!("Tool {}", tool.name);
+    let required = configured == Some(tool.name.as_str()) || !any_available;
+    match &tool.path {
+        Some(path) if tool.version.is_empty() => DoctorCheck::failed(
+            name,
+            format!("{} --version failed", path.display()),
+            "Reinstall the tool (`konarr-cli --auto-install --auto-update agent`)",
+        ),
+        Some(path) => DoctorCheck::ok(name, format!("v{} ({})", tool.version, path.display())),
+        None if required => DoctorCheck::failed(
+            name,
+            "Not installed",
+            "Install it or run the agent with `--auto-install`",
+        ),
+        None => DoctorCheck::warning(name, "Not installed", "Optional, another tool is available"),
+    }
+}
+
+/// A file can be created in the directory (it is created if missing)
+pub fn check_writable(name: &str, path: &Path) -> DoctorCheck {
+    let hint = "Check the permissions of the directory (or the volume mounted in the container)";
+    if let Err(e) = std::fs::create_dir_all(path) {
+        return DoctorCheck::failed(
+            name,
+            format!("Can not create {}: {}", path.display(), e),
+            hint,
+        );
+    }
+    let probe = path.join(format!(".konarr-doctor-{}", std::process::id()));
+    match std::fs::write(&probe, b"").and_then(|_| std::fs::remove_file(&probe)) {
+        Ok(_) => DoctorCheck::ok(name, format!("{} is writable", path.display())),
+        Err(e) => DoctorCheck::failed(
+            name,
+            format!("{} is not writable: {}", path.display(), e),
+            hint,
+        ),
+    }
+}
+
+/// Local GrypeDB (optional, only used by the server / tasks)
+#[cfg(feature = "tasks")]
+pub async fn check_grypedb(config: &Config) -> DoctorCheck {
+    const NAME: &str = "GrypeDB";
+    let hint = "Optional, download it with `konarr-cli tasks run advisories-sync`";
+    let path = match config.grype_path() {
+        Ok(path) => path,
+        Err(e) => return DoctorCheck::warning(NAME, e.to_string(), hint),
+    };
+    let status = konarr::utils::grypedb::GrypeDatabase::status(&path).await;
+    match (status.present, status.build) {
+        (true, Some(build)) => DoctorCheck::ok(NAME, format!("Built {}", build)),
+        (true, None) => DoctorCheck::ok(NAME, format!("{}", path.display())),
+        (false, _) => DoctorCheck::warning(NAME, format!("Not found in {}", path.display()), hint),
+    }
+}
+
+/// Local GrypeDB (optional, requires the `tasks` feature)
+#[cfg(not(feature = "tasks"))]
+pub async fn check_grypedb(_config: &Config) -> DoctorCheck {
+    DoctorCheck::warning(
+        "GrypeDB",
+        "Not checked",
+        "Optional, requires the `tasks` feature",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "konarr-test-doctor-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    /// Fake tool printing the output (or failing) on `--version`
+    fn fake_tool(dir: &Path, name: &str, script: &str) -> ToolConfig {
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        ToolConfig::new(name, path)
+    }
+
+    #[tokio::test]
+    async fn test_tool_versions() {
+        let dir = temp_dir("tools");
+
+        let mut syft = fake_tool(&dir, "syft", "echo 'syft 1.14.0'");
+        syft.version = syft.version().await.unwrap();
+        assert_eq!(syft.version, "1.14.0");
+        let check = check_tool(&syft, Some("syft"), true);
+        assert_eq!(check.status, DoctorStatus::Ok);
+        assert!(check.message.starts_with("v1.14.0 ("));
+
+        // Only the first line of Trivy's output is the version
+        let mut trivy = fake_tool(
+            &dir,
+            "trivy",
+            "printf 'Version: 0.56.1\\nVulnerability DB:\\n  Version: 2\\n'",
+        );
+        trivy.version = trivy.version().await.unwrap();
+        assert_eq!(trivy.version, "0.56.1");
+
+        // Installed but `--version` fails
+        let grype = fake_tool(&dir, "grype", "exit 1");
+        assert!(grype.version().await.is_err());
+        let check = check_tool(&grype, None, true);
+        assert_eq!(check.status, DoctorStatus::Failed);
+        assert!(check.message.ends_with("--version failed"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_tool() {
+        let tool = ToolConfig {
+            name: "trivy".to_string(),
+            ..Default::default()
+        };
+        // Another tool is available
+        let check = check_tool(&tool, Some("syft"), true);
+        assert_eq!(check.status, DoctorStatus::Warning);
+        assert_eq!(check.message, "Not installed");
+        // Configured tool
+        assert_eq!(
+            check_tool(&tool, Some("trivy"), true).status,
+            DoctorStatus::Failed
+        );
+        // No tool installed at all
+        assert_eq!(check_tool(&tool, None, false).status, DoctorStatus::Failed);
+    }
+
+    #[test]
+    fn test_check_writable() {
+        let dir = temp_dir("writable");
+
+        // Missing directories are created
+        let cache = dir.join("data").join("cache");
+        let check = check_writable("Agent cache", &cache);
+        assert_eq!(check.status, DoctorStatus::Ok);
+        assert!(check.hint.is_none());
+        assert!(cache.is_dir());
+        assert_eq!(std::fs::read_dir(&cache).unwrap().count(), 0);
+
+        // Can not create a directory under a file
+        let file = dir.join("file");
+        std::fs::write(&file, b"").unwrap();
+        let check = check_writable("Tool output", &file.join("output"));
+        assert_eq!(check.status, DoctorStatus::Failed);
+        assert!(check.message.starts_with("Can not create"));
+        assert!(check.hint.is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+use konarr::{client::snapshot::KonarrSnapshot, KonarrClient};
+use log::{info, warn};
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum GenerateCommands {
+    /// GitHub dependency submission snapshot of a Konarr snapshot
+    GithubSnapshot {
+        /// Snapshot ID
+        #[clap(long)]
+        snapshot_id: u32,
+        /// GitHub repository (`owner/name`)
+        #[clap(long)]
+        repo: String,
+        /// Commit SHA the snapshot is for
+        #[clap(long)]
+        sha: String,
+        /// Git reference
+        #[clap(long = "ref", default_value = "refs/heads/main")]
+        git_ref: String,
+        /// Write the snapshot to a file (printed to stdout by default)
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+        /// Submit the snapshot to the GitHub dependency graph
+        #[clap(long)]
+        submit: bool,
+        /// GitHub token (needs `contents: write` on the repository)
+        #[clap(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+        github_token: Option<String>,
+    },
+}
+
+pub async fn run(client: &KonarrClient, subcommands: Option<GenerateCommands>) -> Result<()> {
+    match subcommands {
+        Some(GenerateCommands::GithubSnapshot {
+            snapshot_id,
+            repo,
+            sha,
+            git_ref,
+            output,
+            submit,
+            github_token,
+        }) => {
+            let snapshot =
+                KonarrSnapshot::export_github(client, snapshot_id, &sha, &git_ref).await?;
+            let dependencies: usize = snapshot
+                .manifests
+                .values()
+                .map(|manifest| manifest.resolved.len())
+                .sum();
+            info!(
+                "Snapshot({}) :: {} dependencies exported",
+                snapshot_id, dependencies
+            );
+            if snapshot.skipped() > 0 {
+                warn!(
+                    "Snapshot({}) :: {} dependencies are not supported by GitHub (skipped)",
+                    snapshot_id,
+                    snapshot.skipped()
+                );
+            }
+
+            let data = serde_json::to_string_pretty(&snapshot)?;
+            match &output {
+                Some(path) => {
+                    std::fs::write(path, &data)?;
+                    info!("Written GitHub snapshot to {}", path.display());
+                }
+                None if !submit => println!("{}", data),
+                None => {}
+            }
+
+            if submit {
+                let token = github_token.ok_or_else(|| {
+                    anyhow!("A GitHub token is required to submit (GITHUB_TOKEN)")
+                })?;
+                let id = snapshot.submit(&repo, &token).await?;
+                info!("Submitted the dependency snapshot to {} :: {}", repo, id);
+            }
+        }
+        None => {
+            return Err(anyhow!("No subcommand provided"));
+        }
+    }
+    Ok(())
+}
@@ -38,9 +38,10 @@ pub async fn run(
             info!("Running SBOM Command");
 
             if !path.exists() {
-                return Err(konarr::KonarrError::UnknownError(
-                    path.display().to_string(),
-                ));
+                return Err(konarr::KonarrError::NotFound(format!(
+                    "SBOM file `{}`",
+                    path.display()
+                )));
             }
 
             if path.is_file() {
@@ -48,13 +49,18 @@ pub async fn run(
                 let mut project = if let Some(project_id) = config.agent.project_id {
                     Projects::fetch_by_primary_key(&connection, project_id as i32).await?
                 } else if let Some(project_name) = &config.agent.host {
-                    Projects::fetch_by_name(&connection, project_name).await?
+                    Projects::fetch_by_normalized_name(&connection, project_name).await?
                 } else {
                     let input = crate::utils::interactive::prompt_input("Project Name")
                         .expect("Failed to get input");
-                    let mut proj = Projects::new(input, ProjectType::Container);
-                    proj.fetch_or_create(&connection).await?;
-                    proj
+                    match Projects::fetch_by_normalized_name(&connection, &input).await {
+                        Ok(proj) => proj,
+                        Err(_) => {
+                            let mut proj = Projects::with_title(input, ProjectType::Container);
+                            proj.save(&connection).await?;
+                            proj
+                        }
+                    }
                 };
                 info!("Project Name :: {:?}", project);
 
@@ -0,0 +1,127 @@
+use anyhow::{anyhow, Result};
+use konarr::{Config, KonarrClient, KonarrError};
+use log::{debug, info, warn};
+
+use crate::utils::{
+    credentials,
+    interactive::{prompt_input, prompt_password},
+};
+
+/// Server key used for the stored credentials
+fn server_key(config: &Config) -> Result<String> {
+    Ok(config.server.api_url()?.to_string())
+}
+
+/// Create a client using a session token
+fn session_client(config: &Config, token: &str) -> Result<KonarrClient> {
+    Ok(config
+        .server
+        .client_with_token(format!("Bearer {}", token))?)
+}
+
+/// Prompt for the user credentials and login, returning the session token
+pub async fn login_prompt(config: &Config) -> Result<String> {
+    let username = prompt_input("Username:")?;
+    let password = prompt_password("Password:")?;
+
+    let token = config
+        .server
+        .client()?
+        .login_session(&username, &password)
+        .await
+        .map_err(|e| anyhow!("Failed to login with credentials: {}", e))?;
+    info!("Logged in successfully with credentials");
+    Ok(token)
+}
+
+/// Get a client for the stored session (if any)
+///
+/// If the server rejects the stored session (401), the user is prompted to login again
+/// and the stored session is refreshed. Other errors (e.g. the server can not be reached)
+/// are returned and the stored session is kept.
+///
+/// Without `prompt` the stored session is returned as is (the caller checks it).
+pub async fn stored_session(config: &Config, prompt: bool) -> Result<Option<KonarrClient>> {
+    let server = server_key(config)?;
+    let Some(token) = credentials::load(&server) else {
+        return Ok(None);
+    };
+
+    let client = session_client(config, &token)?;
+    if !prompt {
+        return Ok(Some(client));
+    }
+    match client.whoami().await {
+        Ok(_) => {
+            debug!("Using stored session for authentication");
+            return Ok(Some(client));
+        }
+        Err(KonarrError::AuthenticationError(_)) => {}
+        Err(e) => return Err(anyhow!("Failed to check the stored session: {}", e)),
+    }
+
+    warn!("Stored session has expired, please login again");
+    let token = login_prompt(config).await?;
+    credentials::store(&server, &token)?;
+    Ok(Some(session_client(config, &token)?))
+}
+
+/// Login and store the session
+pub async fn login(config: &Config) -> Result<()> {
+    let server = server_key(config)?;
+    let token = login_prompt(config).await?;
+    credentials::store(&server, &token)?;
+
+    let user = session_client(config, &token)?.user().await?;
+    if let Some(user) = user {
+        info!("Logged into `{}` as: {}", server, user.username);
+    }
+    info!("Session stored in: {}", credentials::path()?.display());
+    Ok(())
+}
+
+/// Logout and remove the stored session
+pub async fn logout(config: &Config) -> Result<()> {
+    let server = server_key(config)?;
+    let Some(token) = credentials::load(&server) else {
+        info!("Not logged into `{}`", server);
+        return Ok(());
+    };
+
+    if let Err(e) = session_client(config, &token)?.logout().await {
+        warn!("Failed to end the session on the server: {}", e);
+    }
+    credentials::remove(&server)?;
+    info!("Logged out of `{}`", server);
+    Ok(())
+}
+
+/// Show who the client is authenticated as and its effective permissions
+pub async fn whoami(client: &KonarrClient) -> Result<()> {
+    let whoami = client
+        .whoami()
+        .await
+        .map_err(|e| anyhow!("Failed to get the authenticated principal: {}", e))?;
+
+    info!("Authentication  :: {}", whoami.auth);
+    info!(
+        "User            :: {} ({}, id: {})",
+        whoami.user.username, whoami.user.role, whoami.user.id
+    );
+    if let Some(agent) = &whoami.agent {
+        match &agent.token {
+            Some(token) => info!("Agent Token     :: {}", token),
+            None => warn!("Agent Token     :: legacy agent key (deprecated)"),
+        }
+    }
+    match whoami.expires_at {
+        Some(expires_at) => info!("Expires         :: {}", expires_at.to_rfc3339()),
+        None => info!("Expires         :: never"),
+    }
+    info!("Can Upload      :: {}", whoami.capabilities.can_upload);
+    info!("Can Admin       :: {}", whoami.capabilities.can_admin);
+    if let Some(scope) = whoami.capabilities.project_scope {
+        info!("Project Scope   :: {}", scope);
+    }
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+use konarr::{client::server::MaintenanceStatus, KonarrClient};
+use log::{info, warn};
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum MaintenanceCommands {
+    /// Put the server in maintenance mode (read-only)
+    Enable,
+    /// Leave the maintenance mode
+    Disable,
+    /// Show if the server is in maintenance mode
+    Status,
+}
+
+pub async fn run(client: &KonarrClient, subcommands: Option<MaintenanceCommands>) -> Result<()> {
+    let status = match subcommands {
+        Some(MaintenanceCommands::Enable) => MaintenanceStatus::set(client, true).await?,
+        Some(MaintenanceCommands::Disable) => MaintenanceStatus::set(client, false).await?,
+        Some(MaintenanceCommands::Status) => MaintenanceStatus::fetch(client).await?,
+        None => {
+            return Err(anyhow!("No subcommand provided"));
+        }
+    };
+
+    if status.enabled {
+        warn!("Server is in maintenance mode (read-only)");
+    } else {
+        info!("Server is not in maintenance mode");
+    }
+    Ok(())
+}
@@ -1,17 +1,26 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use console::style;
 use konarr::{Config, KONARR_BANNER, KONARR_VERSION};
 use std::path::PathBuf;
 
 pub mod agent;
+pub mod completions;
+pub mod config;
 #[cfg(feature = "database")]
 pub mod database;
+pub mod dependencies;
 #[cfg(feature = "database")]
 pub mod display;
+pub mod doctor;
+pub mod generate;
 #[cfg(feature = "database")]
 pub mod index;
+pub mod login;
+pub mod maintenance;
 #[cfg(feature = "database")]
 pub mod search;
+#[cfg(feature = "database")]
+pub mod security;
 #[cfg(feature = "tasks")]
 pub mod tasks;
 
@@ -32,6 +41,10 @@ pub struct Arguments {
     #[clap(short, long, env, default_value = "./konarr.yml")]
     pub config: PathBuf,
 
+    /// Only load the configuration from the environment (no configuration file)
+    #[clap(long, env = "KONARR_NO_CONFIG", default_value_t = false)]
+    pub no_config: bool,
+
     /// Working Directory
     #[clap(short, long, env, default_value = "./")]
     pub working_dir: PathBuf,
@@ -64,8 +77,8 @@ pub struct Arguments {
     pub hostname: Option<String>,
 
     /// Tool to use
-    #[clap(short, long, env = "KONARR_AGENT_TOOL")]
-    pub tool: Option<String>,
+    #[clap(short, long, env = "KONARR_AGENT_TOOL", value_enum, ignore_case = true)]
+    pub tool: Option<ToolArg>,
     /// Auto-Install Tools
     #[clap(long, env = "KONARR_AGENT_AUTO_INSTALL", default_value = "false")]
     pub auto_install: bool,
@@ -81,6 +94,26 @@ pub struct Arguments {
     pub commands: Option<ArgumentCommands>,
 }
 
+/// Tools the SBOMs can be generated with
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolArg {
+    /// Syft (Anchore)
+    Syft,
+    /// Grype (Anchore, SBOM with the vulnerabilities)
+    Grype,
+    /// Trivy (Aqua Security)
+    Trivy,
+}
+
+impl std::fmt::Display for ToolArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => write!(f, "{}", value.get_name()),
+            None => Ok(()),
+        }
+    }
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum ArgumentCommands {
     /// Database actions and commands
@@ -89,17 +122,63 @@ pub enum ArgumentCommands {
         #[clap(subcommand)]
         subcommands: Option<database::DatabaseCommands>,
     },
+    /// Dependencies (components) on the Konarr server
+    Dependencies {
+        #[clap(subcommand)]
+        subcommands: Option<dependencies::DependencyCommands>,
+    },
     /// Display data
     #[cfg(feature = "database")]
     Display {
         #[clap(subcommand)]
         subcommands: Option<display::DisplayCommands>,
     },
+    /// Diagnose the agent environment (server, token, Docker socket, tools and paths)
+    ///
+    /// The exit code is non-zero if any check failed.
+    Doctor {
+        /// Docker Socket Path
+        #[clap(short, long, env = "DOCKER_HOST")]
+        docker_socket: Option<String>,
+    },
+    /// Generate exports of the data on the Konarr server
+    Generate {
+        #[clap(subcommand)]
+        subcommands: Option<generate::GenerateCommands>,
+    },
+    /// Configuration actions and commands
+    Config {
+        #[clap(subcommand)]
+        subcommands: Option<config::ConfigCommands>,
+    },
+    /// Login to the Konarr server and store the session
+    Login,
+    /// Logout of the Konarr server and remove the stored session
+    Logout,
+    /// Show who the client is authenticated as and its permissions
+    Whoami,
+    /// Maintenance mode of the Konarr server (admin)
+    Maintenance {
+        #[clap(subcommand)]
+        subcommands: Option<maintenance::MaintenanceCommands>,
+    },
     /// Agent mode
     Agent {
         /// Docker Socket Path
         #[clap(short, long, env = "DOCKER_HOST")]
         docker_socket: Option<String>,
+        /// Write a JSON summary of the run to this path
+        #[clap(long)]
+        summary_output: Option<PathBuf>,
+        /// Exit successfully even if some containers failed to scan / upload
+        #[clap(long)]
+        best_effort: bool,
+        /// Ignore the local cache of uploaded SBOMs and rescan every container
+        #[clap(long)]
+        no_cache: bool,
+
+        #[clap(subcommand)]
+        subcommands: Option<agent::AgentCommands>,
     },
     /// Scan a container image
     Scan {
@@ -112,6 +191,15 @@ pub enum ArgumentCommands {
         /// Output
         #[clap(short, long)]
         output: Option<String>,
+        /// Upload the SBOM to the server (new snapshot for the project)
+        #[clap(long)]
+        upload: bool,
+        /// Project ID to upload the SBOM to
+        #[clap(long)]
+        project_id: Option<u32>,
+        /// Project name to upload the SBOM to (created if it does not exist)
+        #[clap(long, conflicts_with = "project_id")]
+        project_name: Option<String>,
     },
     /// Upload a SBOM file
     UploadSbom {
@@ -134,12 +222,43 @@ pub enum ArgumentCommands {
         #[clap(subcommand)]
         subcommands: Option<search::SearchCommands>,
     },
+    /// Security alerts
+    #[cfg(feature = "database")]
+    Security {
+        #[clap(subcommand)]
+        subcommands: Option<security::SecurityCommands>,
+    },
     /// Run various tasks
     #[cfg(feature = "tasks")]
     Tasks {
         #[clap(subcommand)]
         subcommands: Option<tasks::TaskCommands>,
     },
+    /// Run various tasks (requires the `tasks` feature)
+    #[cfg(not(feature = "tasks"))]
+    Tasks {
+        #[clap(trailing_var_arg = true, allow_hyphen_values = true, hide = true)]
+        args: Vec<String>,
+    },
+    /// Generate the shell completion script (`konarr-cli completions bash > ...`)
+    Completions {
+        /// Shell to generate the completions for
+        #[clap(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Help of every command as markdown (for the documentation)
+    #[clap(hide = true)]
+    MarkdownHelp,
+}
+
+impl ArgumentCommands {
+    /// If the command writes its output to stdout (no banner)
+    pub fn is_stdout(&self) -> bool {
+        matches!(
+            self,
+            ArgumentCommands::Completions { .. } | ArgumentCommands::MarkdownHelp
+        )
+    }
 }
 
 pub fn init() -> Arguments {
@@ -157,7 +276,8 @@ pub fn init() -> Arguments {
         .filter_level(log_level)
         .init();
 
-    if !arguments.disable_banner {
+    let stdout = arguments.commands.as_ref().is_some_and(|c| c.is_stdout());
+    if !arguments.disable_banner && !stdout {
         println!(
             "{}    by {} - v{}\n",
             style(KONARR_BANNER).green(),
@@ -1,5 +1,16 @@
-use clap::Subcommand;
-use konarr::{models::Dependencies, Config};
+use clap::{
+    builder::{PossibleValue, TypedValueParser},
+    error::ErrorKind,
+    Subcommand,
+};
+use console::style;
+use konarr::{
+    client::{
+        dependencies::KonarrComponentVersions, projects::KonarrProjects, search::KonarrSearch,
+    },
+    models::{ComponentManager, ComponentType, Dependencies, COMPONENT_MANAGERS},
+    Config,
+};
 use log::{debug, info};
 
 use crate::utils::interactive::prompt_input;
@@ -9,17 +20,137 @@ pub enum SearchCommands {
     Name {
         #[clap(short, long)]
         name: String,
+        /// Filter by package manager
+        #[clap(short, long, value_parser = ManagerParser)]
+        manager: Option<ComponentManager>,
+        /// Filter by component type (`library`, `application`, `os`, ...)
+        #[clap(short = 't', long, value_parser = parse_component_type)]
+        component_type: Option<ComponentType>,
     },
     Purl {
         #[clap(short, long)]
         purl: String,
+        /// Filter by package manager
+        #[clap(short, long, value_parser = ManagerParser)]
+        manager: Option<ComponentManager>,
+        /// Filter by component type (`library`, `application`, `os`, ...)
+        #[clap(short = 't', long, value_parser = parse_component_type)]
+        component_type: Option<ComponentType>,
+    },
+    /// Search projects, components and advisories on the Konarr server
+    All {
+        /// Search term (project name, component, purl or advisory ID)
+        term: String,
+    },
+    /// Projects whose latest snapshot matches the metadata (case-insensitive)
+    Projects {
+        /// Image registry (`docker.io` for images without a registry)
+        #[clap(long)]
+        registry: Option<String>,
+        /// Image repository
+        #[clap(long)]
+        repository: Option<String>,
+        /// Image tag
+        #[clap(long)]
+        tag: Option<String>,
+        /// Operating system
+        #[clap(long)]
+        os: Option<String>,
+        /// Architecture
+        #[clap(long)]
+        arch: Option<String>,
+        /// Container engine
+        #[clap(long)]
+        engine: Option<String>,
+        /// Page
+        #[clap(long, default_value_t = 0)]
+        page: u32,
+        /// Number of projects per page
+        #[clap(long, default_value_t = 25)]
+        limit: u32,
+    },
+    /// Versions of a component used across the projects and the recommended version
+    Versions {
+        /// Component ID
+        component: u32,
     },
 }
 
+/// Package manager argument parser
+///
+/// The names of [COMPONENT_MANAGERS] are completed and parsed with [ComponentManager]
+/// (so its aliases are accepted), unknown package managers are rejected.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ManagerParser;
+
+impl TypedValueParser for ManagerParser {
+    type Value = ComponentManager;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        _arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let value = value.to_string_lossy().to_lowercase();
+        match ComponentManager::from(value.clone()) {
+            ComponentManager::Unknown if value != ComponentManager::Unknown.to_string() => {
+                Err(clap::Error::raw(
+                    ErrorKind::InvalidValue,
+                    format!("unknown package manager `{}`\n", value),
+                )
+                .with_cmd(cmd))
+            }
+            manager => Ok(manager),
+        }
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(
+            COMPONENT_MANAGERS
+                .iter()
+                .map(|manager| PossibleValue::new(manager.to_string())),
+        ))
+    }
+}
+
+/// Parse a component type argument (unknown types are rejected)
+pub(crate) fn parse_component_type(value: &str) -> Result<ComponentType, String> {
+    ComponentType::parse(value).ok_or_else(|| format!("unknown component type `{}`", value))
+}
+
 pub async fn run(
     config: &Config,
     subcommands: Option<SearchCommands>,
 ) -> Result<(), konarr::KonarrError> {
+    if let Some(SearchCommands::All { term }) = subcommands {
+        return search_all(config, term).await;
+    }
+    if let Some(SearchCommands::Versions { component }) = subcommands {
+        return search_versions(config, component).await;
+    }
+    if let Some(SearchCommands::Projects {
+        registry,
+        repository,
+        tag,
+        os,
+        arch,
+        engine,
+        page,
+        limit,
+    }) = subcommands
+    {
+        let filters = [
+            ("registry", registry),
+            ("repository", repository),
+            ("tag", tag),
+            ("os", os),
+            ("arch", arch),
+            ("engine", engine),
+        ];
+        return search_projects(config, &filters, page, limit).await;
+    }
+
     debug!("Connecting to Database: {:?}", config.database);
 
     let connection = config.database().await?.connect()?;
@@ -27,26 +158,43 @@ pub async fn run(
     info!("Connected to database!");
 
     match subcommands {
-        Some(SearchCommands::Name { name }) => {
+        Some(SearchCommands::Name {
+            name,
+            manager,
+            component_type,
+        }) => {
             info!("Searching for Name: {}", name);
 
             let dependencies = Dependencies::find_by_name(&connection, name).await?;
-            display_results(&dependencies);
+            display_results(&filter_component_type(
+                filter_manager(dependencies, manager),
+                component_type,
+            ));
 
             Ok(())
         }
-        Some(SearchCommands::Purl { purl }) => {
+        Some(SearchCommands::Purl {
+            purl,
+            manager,
+            component_type,
+        }) => {
             info!("Searching for PURL: {}", purl);
 
             let dependencies = Dependencies::find_by_purl(&connection, purl).await?;
 
-            display_results(&dependencies);
+            display_results(&filter_component_type(
+                filter_manager(dependencies, manager),
+                component_type,
+            ));
 
             Ok(())
         }
+        Some(SearchCommands::All { .. })
+        | Some(SearchCommands::Versions { .. })
+        | Some(SearchCommands::Projects { .. }) => Ok(()),
         None => {
             let search = prompt_input("Search for Name or PURL: ")
-                .map_err(|e| konarr::KonarrError::UnknownError(e.to_string()))?;
+                .map_err(|e| konarr::KonarrError::unknown(e.to_string()))?;
 
             if search.starts_with("pkg:") {
                 let dependencies = Dependencies::find_by_purl(&connection, search).await?;
@@ -61,14 +209,198 @@ pub async fn run(
     }
 }
 
+fn filter_manager(
+    dependencies: Vec<Dependencies>,
+    manager: Option<ComponentManager>,
+) -> Vec<Dependencies> {
+    if let Some(manager) = manager {
+        dependencies
+            .into_iter()
+            .filter(|dep| dep.manager() == manager)
+            .collect()
+    } else {
+        dependencies
+    }
+}
+
+pub(crate) fn filter_component_type(
+    dependencies: Vec<Dependencies>,
+    component_type: Option<ComponentType>,
+) -> Vec<Dependencies> {
+    if let Some(component_type) = component_type {
+        dependencies
+            .into_iter()
+            .filter(|dep| dep.component_type() == component_type)
+            .collect()
+    } else {
+        dependencies
+    }
+}
+
 fn display_results(dependencies: &Vec<Dependencies>) {
     info!("Instances :: {}", dependencies.len());
     for dep in dependencies.iter() {
         info!(
-            " > [{}] {} - {}",
+            " > [{}] {} - {} ({})",
             dep.snapshot_id,
             dep.component_type(),
+            dep.display_name(),
             dep.purl()
         );
     }
 }
+
+/// Search using the server API
+async fn search_all(config: &Config, term: String) -> Result<(), konarr::KonarrError> {
+    let (client, _) = crate::client(config)
+        .await
+        .map_err(|e| konarr::KonarrError::KonarrClient(e.to_string()))?;
+
+    info!("Searching for: {}", term);
+    let search = KonarrSearch::search(&client, term).await?;
+
+    for category in search.results.iter() {
+        println!(
+            "{} ({} of {})",
+            style(&category.category).bold(),
+            category.data.len(),
+            category.total
+        );
+        for result in category.data.iter() {
+            println!(
+                " > [{}] {} {}",
+                result.id,
+                style(&result.name).blue(),
+                result.description.clone().unwrap_or_default()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Projects filtered by the metadata of their latest snapshot (server API)
+async fn search_projects(
+    config: &Config,
+    filters: &[(&str, Option<String>)],
+    page: u32,
+    limit: u32,
+) -> Result<(), konarr::KonarrError> {
+    let filters: Vec<(&str, &str)> = filters
+        .iter()
+        .filter_map(|(name, value)| value.as_deref().map(|value| (*name, value)))
+        .collect();
+    if filters.is_empty() {
+        return Err(konarr::KonarrError::InvalidData(
+            "At least one metadata filter is required".to_string(),
+        ));
+    }
+
+    let (client, _) = crate::client(config)
+        .await
+        .map_err(|e| konarr::KonarrError::KonarrClient(e.to_string()))?;
+
+    let projects = KonarrProjects::filter_metadata(&client, &filters, page, limit).await?;
+    println!(
+        "{} ({} of {}, page {} of {})",
+        style("Projects").bold(),
+        projects.data.len(),
+        projects.count,
+        page + 1,
+        projects.pages.max(1)
+    );
+    for project in projects.data.iter() {
+        println!(
+            " > [{}] {} ({})",
+            project.id,
+            style(&project.name).blue(),
+            project.project_type
+        );
+    }
+    Ok(())
+}
+
+/// Versions of a component (server API)
+async fn search_versions(config: &Config, component: u32) -> Result<(), konarr::KonarrError> {
+    let (client, _) = crate::client(config)
+        .await
+        .map_err(|e| konarr::KonarrError::KonarrClient(e.to_string()))?;
+
+    let versions = KonarrComponentVersions::fetch(&client, component).await?;
+    println!(
+        "{} ({})",
+        style(&versions.display_name).bold(),
+        versions.purl
+    );
+    println!(
+        " {:<24} {:>8} {:>8}  {}",
+        "Version", "Projects", "Alerts", "Severities"
+    );
+    for version in versions.versions.iter() {
+        let recommended = versions.recommended.as_deref() == Some(version.version.as_str());
+        let severities = version
+            .severities
+            .iter()
+            .map(|(severity, count)| format!("{}: {}", severity, count))
+            .collect::<Vec<String>>()
+            .join(", ");
+        println!(
+            "{}{:<24} {:>8} {:>8}  {}",
+            if recommended { "*" } else { " " },
+            version.version,
+            version.projects,
+            version.alerts,
+            severities
+        );
+    }
+    if let Some(recommended) = &versions.recommended {
+        println!("Recommended version: {}", style(recommended).green());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn manager(value: &str) -> Result<Option<ComponentManager>, clap::Error> {
+        let arguments = crate::cli::Arguments::try_parse_from([
+            "konarr-cli",
+            "search",
+            "name",
+            "-n",
+            "serde",
+            "-m",
+            value,
+        ])?;
+        match arguments.commands {
+            Some(crate::cli::ArgumentCommands::Search {
+                subcommands: Some(SearchCommands::Name { manager, .. }),
+            }) => Ok(manager),
+            _ => panic!("Unexpected command"),
+        }
+    }
+
+    #[test]
+    fn test_manager_parser() {
+        assert_eq!(manager("cargo").unwrap(), Some(ComponentManager::Cargo));
+        assert_eq!(manager("PyPi").unwrap(), Some(ComponentManager::PyPi));
+        // Aliases of the component manager
+        assert_eq!(manager("jvm").unwrap(), Some(ComponentManager::Maven));
+        assert_eq!(manager("rustc").unwrap(), Some(ComponentManager::Cargo));
+        assert_eq!(manager("rustlang").unwrap(), Some(ComponentManager::Cargo));
+        assert_eq!(manager("unknown").unwrap(), Some(ComponentManager::Unknown));
+
+        let err = manager("cobol").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidValue);
+
+        // Completed values
+        let values: Vec<String> = ManagerParser
+            .possible_values()
+            .unwrap()
+            .map(|value| value.get_name().to_string())
+            .collect();
+        assert_eq!(values.len(), COMPONENT_MANAGERS.len());
+        assert!(values.contains(&"pypi".to_string()));
+    }
+}
@@ -0,0 +1,155 @@
+use clap::Subcommand;
+use console::style;
+use geekorm::prelude::*;
+use konarr::{
+    client::security::KonarrAlertsBulk,
+    models::security::{AlertIgnoreRules, Alerts},
+    Config,
+};
+use log::{debug, info};
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum SecurityCommands {
+    /// Top vulnerable components across all projects
+    Top {
+        /// Number of components to show
+        #[clap(short, long, default_value_t = 20)]
+        limit: u32,
+    },
+    /// List the alert ignore rules
+    IgnoreRules {},
+    /// Acknowledge the alerts matching a filter (server API)
+    Ack {
+        /// Advisory ID or glob (`CVE-2024-1234`, `GHSA-*`)
+        #[clap(long)]
+        advisory: Option<String>,
+        /// Severity of the advisory
+        #[clap(long)]
+        severity: Option<String>,
+        /// Project ID (including its children)
+        #[clap(long)]
+        project: Option<u32>,
+        /// Package URL glob (`pkg:deb/debian/perl*`)
+        #[clap(long)]
+        purl: Option<String>,
+        /// Reason of the change (recorded in the audit log)
+        #[clap(long)]
+        reason: String,
+        /// Action (`acknowledge`, `false-positive` or `reopen`)
+        #[clap(long, default_value = "acknowledge")]
+        action: String,
+        /// Only show the alerts which would change
+        #[clap(long)]
+        dry_run: bool,
+    },
+}
+
+pub async fn run(
+    config: &Config,
+    subcommands: Option<SecurityCommands>,
+) -> Result<(), konarr::KonarrError> {
+    if let Some(SecurityCommands::Ack {
+        advisory,
+        severity,
+        project,
+        purl,
+        reason,
+        action,
+        dry_run,
+    }) = subcommands
+    {
+        let bulk = KonarrAlertsBulk {
+            action,
+            reason,
+            advisory,
+            severity,
+            project,
+            purl,
+            dry_run,
+        };
+        return bulk_alerts(config, bulk).await;
+    }
+
+    debug!("Connecting to Database: {:?}", config.database);
+
+    let connection = config.database().await?.connect()?;
+
+    info!("Connected to database!");
+
+    match subcommands {
+        Some(SecurityCommands::Top { limit }) => {
+            let components = Alerts::top_components(&connection, limit).await?;
+
+            info!("Top Vulnerable Components :: {}", components.len());
+            for summary in components.iter() {
+                println!(
+                    " > {} [{}] alerts: {}, projects: {}",
+                    style(summary.component.purl()).blue(),
+                    style(&summary.severity).red(),
+                    style(summary.alerts).green(),
+                    style(summary.projects).green()
+                );
+            }
+        }
+        Some(SecurityCommands::IgnoreRules {}) => {
+            let rules = AlertIgnoreRules::fetch_all(&connection).await?;
+            let suppressed = AlertIgnoreRules::suppressed(&connection).await?;
+
+            info!("Alert Ignore Rules :: {}", rules.len());
+            for rule in rules.iter() {
+                let id: i32 = rule.id.into();
+                let scope = match rule.project_id {
+                    Some(project) => format!("project {}", project),
+                    None => "global".to_string(),
+                };
+                println!(
+                    " > {} {} [{}] ({}) suppressing: {}{}",
+                    style(id).green(),
+                    style(&rule.purl).blue(),
+                    style(&rule.advisory).red(),
+                    scope,
+                    style(suppressed.get(&id).copied().unwrap_or_default()).green(),
+                    if rule.is_expired() { " (expired)" } else { "" }
+                );
+                if !rule.reason.is_empty() {
+                    println!("   - {}", rule.reason);
+                }
+            }
+        }
+        Some(SecurityCommands::Ack { .. }) => unreachable!(),
+        None => {
+            info!("No subcommand provided");
+        }
+    }
+    Ok(())
+}
+
+/// Change the alerts matching a filter using the server API
+async fn bulk_alerts(config: &Config, bulk: KonarrAlertsBulk) -> Result<(), konarr::KonarrError> {
+    let (client, _) = crate::client(config)
+        .await
+        .map_err(|e| konarr::KonarrError::KonarrClient(e.to_string()))?;
+
+    let result = bulk.apply(&client).await?;
+    println!(
+        "{} {} of {} matching alerts{}",
+        style(&result.action).bold(),
+        style(result.count).green(),
+        result.matched,
+        if result.dry_run { " (dry run)" } else { "" }
+    );
+    for alert in result.alerts.iter() {
+        println!(
+            " > {} {} [{}] {}",
+            style(alert.id).green(),
+            style(&alert.name).red(),
+            alert.severity,
+            alert
+                .component
+                .as_ref()
+                .map(|c| c.purl.as_str())
+                .unwrap_or_default()
+        );
+    }
+    Ok(())
+}
@@ -1,7 +1,15 @@
+use std::path::PathBuf;
+
 use clap::Subcommand;
+use geekorm::prelude::*;
 use konarr::{
-    tasks::{advisories::scan_projects, alert_calculator, catalogue},
-    utils::grypedb::GrypeDatabase,
+    models::{tasks::TASK_RUNS_HISTORY, Projects, ServerSettings, Setting, TaskRuns},
+    tasks::{
+        advisories::scan_projects, alert_calculator, catalogue, cleanup, instrument, integrity,
+        outbox, stale_scans, statistics, storage, sync_advisories, AdvisoriesSyncTask,
+        GrypeSyncResult, TaskStats,
+    },
+    utils::grypedb::{GrypeDatabase, GrypeDatabaseStatus},
     Config,
 };
 use log::info;
@@ -21,6 +29,179 @@ pub enum TaskCommands {
         #[clap(short, long, default_value = "false")]
         alerts: bool,
     },
+    /// Check for containers running an image which has not been scanned
+    Stale {},
+    /// Verify the stored SBOMs and database references
+    Integrity {
+        /// Repair the issues found (re-derive metadata and remove orphaned rows)
+        #[clap(long, default_value = "false")]
+        repair: bool,
+    },
+    /// Remove the data outside of the retention (alert timeline events)
+    Cleanup {},
+    /// Show the history of the task runs
+    Status {
+        /// Number of runs to show per task
+        #[clap(short, long, default_value_t = 5)]
+        limit: u32,
+    },
+    /// Run one of the tasks of the server scheduler
+    Run {
+        #[clap(subcommand)]
+        task: RunTask,
+    },
+}
+
+/// Tasks of the server scheduler
+#[derive(Subcommand, Debug, Clone)]
+pub enum RunTask {
+    /// Sync the advisories (based on the `security.advisories.*` settings)
+    Advisories {},
+    /// Sync the Grype advisories database
+    AdvisoriesSync {
+        /// Path to the Grype database directory (defaults to `<data>/grypedb`)
+        #[clap(long)]
+        db_path: Option<PathBuf>,
+        /// Download the latest database even if the current one is up to date
+        #[clap(long, default_value = "false")]
+        force_download: bool,
+    },
+    /// Calculate the alert summaries of the projects
+    Alerts {},
+    /// Calculate the server statistics
+    Statistics {},
+    /// Scan the projects for security alerts
+    Projects {},
+    /// Sync the catalogue
+    Catalogue {
+        #[clap(short, long, default_value = "false")]
+        force: bool,
+    },
+    /// Remove the data outside of the retention
+    Cleanup {},
+    /// Check for containers running an image which has not been scanned
+    Stale {},
+    /// Collect the storage diagnostics
+    Storage {},
+    /// Deliver the pending events of the outbox to the webhooks
+    Outbox {},
+}
+
+/// Run a task (instrumented like the server scheduler) and return its summary
+async fn run_task(
+    config: &Config,
+    connection: &libsql::Connection,
+    task: RunTask,
+) -> Result<TaskStats, konarr::KonarrError> {
+    match task {
+        RunTask::Advisories {} => {
+            instrument(connection, "advisories", async {
+                sync_advisories(config, connection).await?;
+                let version =
+                    ServerSettings::fetch_by_name(connection, Setting::SecurityAdvisoriesVersion)
+                        .await?
+                        .value;
+                Ok(TaskStats::from_iter([("version", version)]))
+            })
+            .await
+        }
+        RunTask::AdvisoriesSync {
+            db_path,
+            force_download,
+        } => {
+            instrument(connection, "advisories-sync", async {
+                let grype_path = match &db_path {
+                    Some(path) => path.clone(),
+                    None => config.grype_path()?,
+                };
+                info!("Grype data path: {:?}", grype_path);
+
+                let task = AdvisoriesSyncTask::acquire(grype_path)
+                    .ok_or_else(|| {
+                        konarr::KonarrError::Conflict(
+                            "Advisories database sync is already running".to_string(),
+                        )
+                    })?
+                    .force(force_download);
+                // Only a sync of the configured database updates the server settings
+                let new = if db_path.is_none() {
+                    task.run(connection).await?
+                } else {
+                    task.download().await?
+                };
+                Ok(TaskStats::from_iter([("new", new)]))
+            })
+            .await
+        }
+        RunTask::Alerts {} => {
+            instrument(connection, "alerts", async {
+                alert_calculator(connection).await?;
+                Ok(TaskStats::from_iter([(
+                    "projects",
+                    Projects::row_count(connection, Projects::query_count().build()?).await?,
+                )]))
+            })
+            .await
+        }
+        RunTask::Statistics {} => {
+            instrument(connection, "statistics", async {
+                statistics(connection)
+                    .await
+                    .map(|summary| TaskStats::from(&summary))
+            })
+            .await
+        }
+        RunTask::Projects {} => {
+            instrument(connection, "projects", async {
+                scan_projects(config, connection).await?;
+                Ok(TaskStats::from_iter([(
+                    "projects",
+                    Projects::row_count(connection, Projects::query_count().build()?).await?,
+                )]))
+            })
+            .await
+        }
+        RunTask::Catalogue { force } => {
+            instrument(connection, "catalogue", async {
+                catalogue(connection, force)
+                    .await
+                    .map(|summary| TaskStats::from(&summary))
+            })
+            .await
+        }
+        RunTask::Cleanup {} => {
+            instrument(connection, "cleanup", async {
+                cleanup(config, connection)
+                    .await
+                    .map(|summary| TaskStats::from(&summary))
+            })
+            .await
+        }
+        RunTask::Stale {} => {
+            instrument(connection, "stale", async {
+                stale_scans(connection)
+                    .await
+                    .map(|summary| TaskStats::from(&summary))
+            })
+            .await
+        }
+        RunTask::Storage {} => {
+            instrument(connection, "storage", async {
+                storage(config, connection)
+                    .await
+                    .map(|summary| TaskStats::from(&summary))
+            })
+            .await
+        }
+        RunTask::Outbox {} => {
+            instrument(connection, "outbox", async {
+                outbox(connection)
+                    .await
+                    .map(|summary| TaskStats::from(&summary))
+            })
+            .await
+        }
+    }
 }
 
 pub async fn run(
@@ -28,13 +209,29 @@ pub async fn run(
     subcommands: Option<TaskCommands>,
 ) -> Result<(), konarr::KonarrError> {
     let connection = config.database().await?.connect()?;
+    konarr::models::connection_init(&connection).await?;
+    konarr::models::settings::secrets::init(config.server.encryption_key.as_deref())?;
 
     match subcommands {
         Some(TaskCommands::Alerts {}) => {
-            alert_calculator(&connection).await?;
+            run_task(config, &connection, RunTask::Alerts {}).await?;
         }
         Some(TaskCommands::Catalogue { force }) => {
-            catalogue(&connection, force).await?;
+            let mut result = None;
+            instrument(&connection, "catalogue", async {
+                let summary = catalogue(&connection, force).await?;
+                let stats = TaskStats::from(&summary);
+                result = Some(summary);
+                Ok(stats)
+            })
+            .await?;
+            let summary = result.unwrap_or_default();
+            info!(
+                "Catalogue coverage: {}% ({} reclassified, {} unclassified)",
+                summary.coverage(),
+                summary.reclassified,
+                summary.unclassified
+            );
         }
         Some(TaskCommands::Grype { alerts }) => {
             info!("Running Grype Sync Task");
@@ -52,6 +249,89 @@ pub async fn run(
 
             konarr::tasks::alert_calculator(&connection).await?;
         }
+        Some(TaskCommands::Stale {}) => {
+            let stats = run_task(config, &connection, RunTask::Stale {}).await?;
+            info!(
+                "Stale container scans: {}",
+                stats.get("stale").unwrap_or("0")
+            );
+        }
+        Some(TaskCommands::Cleanup {}) => {
+            let stats = run_task(config, &connection, RunTask::Cleanup {}).await?;
+            info!(
+                "Alert events removed: {}",
+                stats.get("alert_events").unwrap_or("0")
+            );
+        }
+        Some(TaskCommands::Integrity { repair }) => {
+            let mut result = None;
+            instrument(&connection, "integrity", async {
+                let report = integrity(config, &connection, repair).await?;
+                let stats = TaskStats::from(&report);
+                result = Some(report);
+                Ok(stats)
+            })
+            .await?;
+            let report = result.unwrap_or_default();
+            for issue in report.issues.iter() {
+                info!(
+                    "{:<20} {:>6} {}{}",
+                    issue.kind.to_string(),
+                    issue.id,
+                    issue.message,
+                    if issue.repaired { " (repaired)" } else { "" }
+                );
+            }
+            info!(
+                "Integrity: {} SBOMs checked, {} issues ({} repaired)",
+                report.sboms_checked,
+                report.issues.len(),
+                report.repaired()
+            );
+        }
+        Some(TaskCommands::Status { limit }) => {
+            let history =
+                TaskRuns::fetch_history(&connection, limit.min(TASK_RUNS_HISTORY)).await?;
+            if history.is_empty() {
+                info!("No task runs recorded");
+            }
+            for (name, runs) in history.iter() {
+                info!(
+                    "----- {:^26} ----- (streak: {})",
+                    name,
+                    TaskRuns::streak(runs)
+                );
+                for run in runs.iter() {
+                    info!(
+                        " > {} {} {:>6}ms {}",
+                        if run.success { "✅" } else { "❌" },
+                        run.started_at.format("%Y-%m-%d %H:%M:%S"),
+                        run.duration_ms,
+                        run.summary
+                    );
+                }
+            }
+        }
+        Some(TaskCommands::Run { task }) => {
+            let grypedb = match &task {
+                RunTask::AdvisoriesSync { db_path, .. } => Some(db_path.clone()),
+                _ => None,
+            };
+            let stats = run_task(config, &connection, task).await?;
+            info!("Summary: {}", stats);
+
+            if let Some(db_path) = grypedb {
+                let sync = match db_path {
+                    Some(_) => None,
+                    None => Some(GrypeSyncResult::load(&connection).await?),
+                };
+                let path = match db_path {
+                    Some(path) => path,
+                    None => config.grype_path()?,
+                };
+                grypedb_status(&GrypeDatabase::status(&path).await, sync.as_ref());
+            }
+        }
         None => {
             info!("No subcommand provided, running interactive mode");
         }
@@ -60,3 +340,30 @@ pub async fn run(
 
     Ok(())
 }
+
+/// Print the status of the advisories (Grype) database (same as `GET /api/admin/grypedb`)
+fn grypedb_status(status: &GrypeDatabaseStatus, sync: Option<&GrypeSyncResult>) {
+    info!("----- {:^26} -----", "Grype DB");
+    info!(" > {:<16}: {}", "Path", status.path.display());
+    info!(" > {:<16}: {}", "Present", status.present);
+    if let Some(schema) = status.schema_version {
+        info!(" > {:<16}: {}", "Schema", schema);
+    }
+    if let Some(build) = status.build {
+        info!(" > {:<16}: {}", "Build", build.to_rfc3339());
+    }
+    if let Some(vulnerabilities) = status.vulnerabilities {
+        info!(" > {:<16}: {}", "Vulnerabilities", vulnerabilities);
+    }
+    if let Some(sync) = sync {
+        if let Some(attempted_at) = sync.attempted_at {
+            info!(" > {:<16}: {}", "Last sync", attempted_at.to_rfc3339());
+        }
+        if let Some(outcome) = sync.outcome {
+            info!(" > {:<16}: {}", "Result", outcome);
+        }
+        if let Some(error) = &sync.error {
+            info!(" > {:<16}: {}", "Error", error);
+        }
+    }
+}
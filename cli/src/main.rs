@@ -10,30 +10,26 @@ mod utils;
 use cli::{init, update_config};
 use konarr::{
     bom::{BomParser, Parsers},
-    client::snapshot::KonarrSnapshot,
+    client::{
+        projects::{KonarrProject, KonarrProjects},
+        snapshot::KonarrSnapshot,
+    },
     Config,
 };
-use utils::interactive::{prompt_input, prompt_password};
+use utils::interactive::prompt_input;
 
 async fn client(config: &Config) -> Result<(konarr::KonarrClient, konarr::client::ServerInfo)> {
     let client = if let Some(token) = &config.agent.token {
         debug!("Using token for authentication");
-        config.server.client_with_token(token.to_string())?
+        config.agent_client(token.to_string())?
+    } else if let Some(client) = cli::login::stored_session(config, true).await? {
+        client
     } else {
         debug!("Interactively logging in");
-        let username = prompt_input("Username:")?;
-        let password = prompt_password("Password:")?;
-
-        let mut client = config
+        let token = cli::login::login_prompt(config).await?;
+        config
             .server
-            .client_with_credentials(username, password)
-            .expect("Could not create client");
-        client
-            .login()
-            .await
-            .map_err(|e| anyhow!("Failed to login with credentials: {}", e))?;
-        info!("Logged in successfully with credentials");
-        client
+            .client_with_token(format!("Bearer {}", token))?
     };
 
     let serverinfo = client.server().await?;
@@ -51,12 +47,50 @@ async fn client(config: &Config) -> Result<(konarr::KonarrClient, konarr::client
     Ok((client, serverinfo))
 }
 
+/// Resolve the project to upload a scan to (by ID, or by name creating it if needed)
+async fn upload_project(
+    client: &konarr::KonarrClient,
+    project_id: Option<u32>,
+    project_name: Option<String>,
+    image: &str,
+) -> Result<KonarrProject> {
+    if let Some(project_id) = project_id {
+        return KonarrProjects::by_id(client, project_id)
+            .await?
+            .ok_or_else(|| anyhow!("Project `{}` not found", project_id));
+    }
+    let name = match project_name {
+        Some(name) => name,
+        None => prompt_input(&format!("Project name for `{}`", image))?,
+    };
+    Ok(KonarrProject::new(name, "Container")
+        .find_or_create(client)
+        .await?)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let arguments = init();
 
+    match &arguments.commands {
+        Some(cli::ArgumentCommands::Completions { shell }) => {
+            cli::completions::completions(*shell)?;
+            return Ok(());
+        }
+        Some(cli::ArgumentCommands::MarkdownHelp) => {
+            print!("{}", cli::completions::markdown_help());
+            return Ok(());
+        }
+        _ => {}
+    }
+
     #[allow(unused_mut)]
-    let mut config = match Config::load(&arguments.config) {
+    let config = if arguments.no_config {
+        Config::load_env()
+    } else {
+        Config::load(&arguments.config)
+    };
+    let mut config = match config {
         Ok(config) => config,
         Err(error) => {
             warn!("Failed to load configuration: {}", error);
@@ -67,8 +101,15 @@ async fn main() -> Result<()> {
     update_config(&mut config, &arguments)?;
 
     match arguments.commands {
-        Some(cli::ArgumentCommands::Agent { docker_socket }) => {
+        Some(cli::ArgumentCommands::Agent {
+            docker_socket,
+            summary_output,
+            best_effort,
+            no_cache,
+            subcommands,
+        }) => {
             config.agent.docker_socket = docker_socket;
+            config.agent.no_cache |= no_cache;
 
             let (client, serverinfo) = client(&config).await?;
 
@@ -81,23 +122,35 @@ async fn main() -> Result<()> {
                 "Logged into server as: {}",
                 serverinfo.user.unwrap().username
             );
+            // Misconfigured tokens are obvious in the first lines of output
+            match client.whoami().await {
+                Ok(whoami) => info!("Authenticated as: {}", whoami),
+                Err(e) => warn!("Failed to get the authenticated principal: {}", e),
+            }
+
+            let server_settings =
+                cli::agent::apply_server_settings(&mut config, serverinfo.agent.as_ref());
+            if let Some(cli::agent::AgentCommands::ShowConfig) = subcommands {
+                cli::agent::show_config(&config, &server_settings);
+                return Ok(());
+            }
 
-            if let Some(agent_config) = &serverinfo.agent {
-                info!(
-                    "Loading agent configuration from server: {:?}",
-                    agent_config
-                );
-                config.agent.tool = Some(agent_config.tool.to_lowercase());
-                config.agent.tool_auto_install = agent_config.auto_install;
-                config.agent.tool_auto_update = agent_config.auto_update;
+            let summary = cli::agent::setup(&config, &client).await?;
+
+            if let Some(path) = summary_output {
+                info!("Writing agent summary to: {}", path.display());
+                summary.write(&path)?;
             }
 
-            Ok(cli::agent::setup(&config, &client).await?)
+            summary.exit_status(best_effort)
         }
         Some(cli::ArgumentCommands::Scan {
             image,
             list,
             output,
+            upload,
+            project_id,
+            project_name,
         }) => {
             let tools = konarr::tools::ToolConfig::tools().await?;
 
@@ -118,20 +171,72 @@ async fn main() -> Result<()> {
                 return Ok(());
             }
 
-            if let Some(image) = image {
-                let result = konarr::tools::run(&config, image).await?;
+            let Some(image) = image else {
+                return Err(anyhow!("No image provided"));
+            };
 
-                if let Some(output) = output {
-                    info!("Writing output to: {}", output);
-                    std::fs::write(output, result)?;
-                } else {
-                    let parse = Parsers::parse(&result.as_bytes())?;
-                    log::info!("SBOM Summary:");
-                    log::info!(" > Dependencies     : {}", parse.components.len());
-                    log::info!(" > Vulnerabilities  : {}", parse.vulnerabilities.len());
+            // Authenticate and resolve the project before running the (slow) scan
+            let target = if upload {
+                let (client, serverinfo) = client(&config).await?;
+                if serverinfo.user.is_none() {
+                    return Err(anyhow!("User is not authenticated, unable to upload"));
                 }
+                let project = upload_project(
+                    &client,
+                    project_id.or(arguments.project_id),
+                    project_name,
+                    &image,
+                )
+                .await?;
+                info!("Uploading to project: {} ({})", project.name, project.id);
+                Some((client, project))
             } else {
-                return Err(anyhow!("No image provided"));
+                None
+            };
+
+            let result = konarr::tools::run(&config, image).await?;
+
+            if let Some(output) = &output {
+                info!("Writing output to: {}", output);
+                std::fs::write(output, &result)?;
+            }
+            let bom = match Parsers::parse(result.as_bytes()) {
+                Ok(bom) => bom,
+                Err(e) => return Err(anyhow!("Failed to parse SBOM: {}", e)),
+            };
+            if output.is_none() || target.is_some() {
+                info!("SBOM Summary:");
+                info!(" > Dependencies     : {}", bom.components.len());
+                info!(" > Vulnerabilities  : {}", bom.vulnerabilities.len());
+                let mut severities = std::collections::BTreeMap::new();
+                for vulnerability in bom.vulnerabilities.iter() {
+                    *severities
+                        .entry(format!("{:?}", vulnerability.severity))
+                        .or_insert(0) += 1;
+                }
+                for (severity, count) in severities {
+                    info!("   > {:<14} : {}", severity, count);
+                }
+            }
+
+            if let Some((client, project)) = target {
+                let snapshot = KonarrSnapshot::create(&client, project.id).await?;
+                info!("Created snapshot: {}", snapshot.id);
+
+                let json_data: serde_json::Value = serde_json::from_str(&result)?;
+                let snapshot = snapshot.upload_bom(&client, json_data).await?;
+                info!("Uploaded SBOM to snapshot: {}", snapshot.id);
+                if let Some(security) = snapshot.security {
+                    info!(
+                        "Security: {} total ({} critical, {} high, {} medium, {} low)",
+                        security.total,
+                        security.critical,
+                        security.high,
+                        security.medium,
+                        security.low
+                    );
+                }
+                println!("{}", snapshot.id);
             }
 
             Ok(())
@@ -182,6 +287,39 @@ async fn main() -> Result<()> {
 
             Ok(())
         }
+        Some(cli::ArgumentCommands::Config { subcommands }) => {
+            cli::config::run(&config, subcommands).await
+        }
+        Some(cli::ArgumentCommands::Dependencies { subcommands }) => {
+            let (client, serverinfo) = client(&config).await?;
+            if serverinfo.user.is_none() {
+                return Err(anyhow!("User is not authenticated"));
+            }
+            cli::dependencies::run(&client, subcommands).await
+        }
+        Some(cli::ArgumentCommands::Generate { subcommands }) => {
+            let (client, serverinfo) = client(&config).await?;
+            if serverinfo.user.is_none() {
+                return Err(anyhow!("User is not authenticated"));
+            }
+            cli::generate::run(&client, subcommands).await
+        }
+        Some(cli::ArgumentCommands::Login) => cli::login::login(&config).await,
+        Some(cli::ArgumentCommands::Logout) => cli::login::logout(&config).await,
+        Some(cli::ArgumentCommands::Doctor { docker_socket }) => {
+            cli::doctor::run(&config, docker_socket).await
+        }
+        Some(cli::ArgumentCommands::Whoami) => {
+            let (client, _) = client(&config).await?;
+            cli::login::whoami(&client).await
+        }
+        Some(cli::ArgumentCommands::Maintenance { subcommands }) => {
+            let (client, serverinfo) = client(&config).await?;
+            if serverinfo.user.is_none() {
+                return Err(anyhow!("User is not authenticated"));
+            }
+            cli::maintenance::run(&client, subcommands).await
+        }
         #[cfg(feature = "database")]
         Some(cli::ArgumentCommands::Database { subcommands }) => {
             if let Some(url) = arguments.database_url {
@@ -201,10 +339,20 @@ async fn main() -> Result<()> {
         Some(cli::ArgumentCommands::Search { subcommands }) => {
             Ok(cli::search::run(&config, subcommands).await?)
         }
+        #[cfg(feature = "database")]
+        Some(cli::ArgumentCommands::Security { subcommands }) => {
+            Ok(cli::security::run(&config, subcommands).await?)
+        }
         #[cfg(feature = "tasks")]
         Some(cli::ArgumentCommands::Tasks { subcommands }) => {
             Ok(cli::tasks::run(&config, subcommands).await?)
         }
+        #[cfg(not(feature = "tasks"))]
+        Some(cli::ArgumentCommands::Tasks { .. }) => Err(anyhow!(
+            "Tasks are not available, the Konarr CLI was built without the `tasks` feature"
+        )),
+        Some(cli::ArgumentCommands::Completions { .. })
+        | Some(cli::ArgumentCommands::MarkdownHelp) => Ok(()),
         None => {
             debug!("No command provided, showing server info");
 
@@ -33,6 +33,10 @@ async fn database_statistics(config: &Config) -> Result<()> {
                 "Containers",
                 find_stat("stats.projects.containers", &statistics),
             ),
+            (
+                "Stale scans",
+                find_stat("stats.projects.stale", &statistics),
+            ),
         ],
     );
 
@@ -112,13 +116,24 @@ async fn database_statistics(config: &Config) -> Result<()> {
 
 async fn server_statistics(config: &Config) -> Result<()> {
     debug!("Server Statistics");
-    let (_client, serverinfo) = crate::client(&config).await?;
+    let (client, serverinfo) = crate::client(&config).await?;
     // Check if the user is authenticated
     if !serverinfo.user.is_some() {
         info!("User is not authenticated");
     } else {
         info!("User is authenticated!");
     }
+    let admin = serverinfo
+        .user
+        .as_ref()
+        .is_some_and(|u| u.role.eq_ignore_ascii_case("admin"));
+    if let Some(build) = &serverinfo.build {
+        info!("----- {:^26} -----", "Server Build");
+        info!(" > {:<24}: {}", "Commit", build.commit);
+        info!(" > {:<24}: {}", "Build Date", build.date);
+        info!(" > {:<24}: {}", "Compiler", build.rustc);
+        info!(" > {:<24}: {}", "Features", build.features.join(", "));
+    }
     if let Some(psummary) = serverinfo.projects {
         print_stats(
             "Projects Statistics",
@@ -146,6 +161,16 @@ async fn server_statistics(config: &Config) -> Result<()> {
                 ("Middleware", dsummary.middleware),
             ],
         );
+        if !dsummary.tags.is_empty() {
+            print_stats(
+                "Dependency Tags",
+                dsummary
+                    .tags
+                    .iter()
+                    .map(|(tag, count)| (tag.as_str(), *count))
+                    .collect(),
+            );
+        }
     }
     if let Some(security) = serverinfo.security {
         print_stats(
@@ -164,6 +189,13 @@ async fn server_statistics(config: &Config) -> Result<()> {
     }
     // info!("Dependencies :: {}", serverinfo.dependencies.total);
 
+    if admin {
+        match konarr::client::server::ServerStatus::fetch(&client).await {
+            Ok(status) => print_storage(&status.storage),
+            Err(e) => debug!("Failed to get server status: {}", e),
+        }
+    }
+
     if let Some(agent_settings) = serverinfo.agent {
         info!("----- {:^26} -----", "Agent Settings");
         let tools = konarr::tools::ToolConfig::tools().await?;
@@ -196,6 +228,54 @@ async fn server_statistics(config: &Config) -> Result<()> {
     Ok(())
 }
 
+#[cfg_attr(feature = "database", allow(dead_code))]
+fn print_storage(storage: &konarr::client::server::StorageStatus) {
+    info!("----- {:^26} -----", "Storage");
+    info!(
+        " > {:<24}: {}",
+        "Database",
+        human_bytes(storage.database_size)
+    );
+    info!(
+        " > {:<24}: {}",
+        "Free Disk Space",
+        human_bytes(storage.disk_free)
+    );
+    info!(
+        " > {:<24}: {} ({})",
+        "Grype DB",
+        human_bytes(storage.grypedb_size),
+        storage.grypedb_built.as_deref().unwrap_or("unknown build")
+    );
+    info!(
+        " > {:<24}: {} ({} files)",
+        "SBOMs",
+        human_bytes(storage.sboms_size),
+        storage.sboms_count
+    );
+    for snapshot in storage.largest_snapshots.iter() {
+        info!(
+            "   - Snapshot({}) :: {}",
+            snapshot.snapshot_id,
+            human_bytes(snapshot.size_bytes)
+        );
+    }
+    if let Some(updated) = storage.updated_at {
+        info!(" > {:<24}: {}", "Last Updated", updated);
+    }
+}
+
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
 fn print_stats(title: &str, stats: Vec<(&str, u32)>) {
     info!("----- {:^26} -----", title);
     for (name, value) in stats.iter() {
@@ -0,0 +1,80 @@
+//! Stored CLI session credentials
+//!
+//! Session tokens are stored per server URL in a separate file from the configuration
+//! (`$XDG_CONFIG_HOME/konarr/credentials.json`), readable only by the current user.
+use anyhow::{anyhow, Result};
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// Credentials file path
+pub fn path() -> Result<PathBuf> {
+    if let Ok(path) = std::env::var("KONARR_CREDENTIALS") {
+        return Ok(PathBuf::from(path));
+    }
+    let config = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => std::env::var("HOME")
+            .map(|home| PathBuf::from(home).join(".config"))
+            .map_err(|_| anyhow!("Unable to find the user configuration directory"))?,
+    };
+    Ok(config.join("konarr").join("credentials.json"))
+}
+
+fn load_all() -> Result<BTreeMap<String, String>> {
+    let path = path()?;
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(serde_json::from_slice(&std::fs::read(&path)?)?)
+}
+
+fn save_all(credentials: &BTreeMap<String, String>) -> Result<()> {
+    let path = path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(&path)?;
+    // Existing files keep their permissions when opened
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    serde_json::to_writer_pretty(file, credentials)?;
+    Ok(())
+}
+
+/// Load the stored session token for the server
+pub fn load(server: &str) -> Option<String> {
+    load_all()
+        .map_err(|e| log::warn!("Failed to load stored credentials: {}", e))
+        .ok()?
+        .get(server)
+        .cloned()
+}
+
+/// Store the session token for the server
+pub fn store(server: &str, token: &str) -> Result<()> {
+    let mut credentials = load_all()?;
+    credentials.insert(server.to_string(), token.to_string());
+    save_all(&credentials)?;
+    log::debug!("Stored session for `{}` in {}", server, path()?.display());
+    Ok(())
+}
+
+/// Remove the stored session token for the server
+pub fn remove(server: &str) -> Result<bool> {
+    let mut credentials = load_all()?;
+    let removed = credentials.remove(server).is_some();
+    if removed {
+        save_all(&credentials)?;
+    }
+    Ok(removed)
+}
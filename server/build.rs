@@ -11,6 +11,36 @@ fn main() {
             .expect("Failed to execute git command");
         String::from_utf8(git_output.stdout).unwrap()
     };
-
     println!("cargo:rustc-env=KONARR_GIT_COMMIT={}", commit);
+
+    // Build date (reproducible builds set `SOURCE_DATE_EPOCH`)
+    let date = if let Ok(epoch) = std::env::var("SOURCE_DATE_EPOCH") {
+        command_output(
+            "date",
+            &["-u", "-d", &format!("@{}", epoch), "+%Y-%m-%dT%H:%M:%SZ"],
+        )
+    } else {
+        command_output("date", &["-u", "+%Y-%m-%dT%H:%M:%SZ"])
+    };
+    println!("cargo:rustc-env=KONARR_BUILD_DATE={}", date);
+
+    // Rust compiler version
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    println!(
+        "cargo:rustc-env=KONARR_RUSTC_VERSION={}",
+        command_output(&rustc, &["--version"])
+    );
+}
+
+/// Output of a command (or `unknown` if it fails)
+fn command_output(command: &str, args: &[&str]) -> String {
+    Command::new(command)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|output| output.trim().to_string())
+        .filter(|output| !output.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
 }
@@ -1,15 +1,22 @@
 use geekorm::prelude::*;
 use konarr::models::{
     auth::users::UserState,
-    settings::{keys::Setting, ServerSettings, SettingType},
+    reports::{ReportDigest, Reports},
+    security::Alerts,
+    settings::{keys::Setting, ServerSettings, SettingNamespace, SettingType},
+    tasks::TASK_RUNS_HISTORY,
+    AgentCertificates, AgentTokens, AlertIgnoreRules, AuditLog, Component, ComponentTags,
+    EventsOutbox, OutboxState, Projects, SbomUploads, TaskRuns,
 };
+use konarr::tasks::{AdvisoriesSyncTask, GrypeSyncResult, TaskStats};
+use konarr::utils::grypedb::GrypeDatabase;
 use log::{info, warn};
 use rocket::{serde::json::Json, State};
 use std::collections::HashMap;
 
-use crate::{error::KonarrServerError, guards::AdminSession, AppState};
+use crate::{checks::CheckResult, error::KonarrServerError, guards::AdminSession, AppState};
 
-use super::ApiResult;
+use super::{projects::ContainerPortResp, security::TopComponentResp, ApiResult};
 
 pub fn routes() -> Vec<rocket::Route> {
     routes![
@@ -18,6 +25,50 @@ pub fn routes() -> Vec<rocket::Route> {
         // Users
         get_users,
         update_users,
+        // Agent Tokens
+        get_agent_tokens,
+        create_agent_token,
+        revoke_agent_token,
+        // Agent Certificates (mTLS)
+        get_agent_certificates,
+        create_agent_certificate,
+        delete_agent_certificate,
+        // Alert Ignore Rules
+        get_ignore_rules,
+        create_ignore_rule,
+        update_ignore_rule,
+        delete_ignore_rule,
+        // Catalogue
+        get_catalogue_unclassified,
+        // Component Tags
+        assign_component_tags,
+        remove_component_tag,
+        // Status / Diagnostics
+        get_status,
+        // Tasks
+        get_tasks,
+        run_integrity,
+        run_statistics,
+        // Advisories (Grype) database
+        get_grypedb,
+        sync_grypedb,
+        // Reconciliation
+        get_duplicates,
+        // Exposure
+        get_exposure,
+        // Retention
+        get_retention_plan,
+        // Audit Log
+        get_audit_log,
+        // Reports
+        get_reports,
+        get_report,
+        // Events Outbox
+        get_outbox,
+        redeliver_outbox,
+        // Maintenance Mode
+        get_maintenance,
+        set_maintenance,
     ]
 }
 
@@ -45,6 +96,7 @@ pub struct AdminProjectStats {
     total: u64,
     inactive: u64,
     archived: i64,
+    stale: u64,
 }
 
 #[derive(Default, serde::Serialize, serde::Deserialize)]
@@ -56,6 +108,9 @@ pub struct AdminResponse {
 
     pub users: Vec<AdminUserSummary>,
     pub user_stats: AdminUserStats,
+
+    /// Top 5 vulnerable components (worst offenders)
+    pub top_components: Vec<TopComponentResp>,
 }
 
 #[get("/")]
@@ -72,6 +127,7 @@ pub async fn settings(state: &State<AppState>, _session: AdminSession) -> ApiRes
 
     let user_stats = AdminUserStats::from(&stats);
     let project_stats = AdminProjectStats::from(&stats);
+    let top_components = Alerts::top_components(&state.connection, 5).await?;
 
     Ok(Json(AdminResponse {
         settings: settings
@@ -80,6 +136,7 @@ pub async fn settings(state: &State<AppState>, _session: AdminSession) -> ApiRes
             .collect(),
         project_stats,
         user_stats,
+        top_components: top_components.into_iter().map(|c| c.into()).collect(),
         users: users
             .into_iter()
             .map(|user| AdminUserSummary {
@@ -102,12 +159,14 @@ pub async fn update_settings(
     info!("Updating settings: {:?}", settings);
 
     for (name, value) in settings.iter() {
-        let mut setting = ServerSettings::fetch_by_name(&state.connection, name).await?;
+        let mut setting = ServerSettings::get(&state.connection, Setting::parse(name)?).await?;
 
         match setting.setting_type {
             SettingType::Toggle | SettingType::Regenerate | SettingType::SetString => {
-                setting.set(value);
-                setting.update(&state.connection).await?;
+                setting.set_update(&state.connection, value).await?;
+                if setting.name == Setting::MaintenanceEnabled {
+                    state.maintenance.set(setting.boolean());
+                }
             }
             _ => {
                 warn!("Read-only Server Setting is being updated: {}", name);
@@ -116,6 +175,8 @@ pub async fn update_settings(
         }
     }
 
+    state.cache.clear().await;
+
     // TODO: Return updated settings
     let stats = konarr::models::ServerSettings::fetch_statistics(&state.connection).await?;
     let settings = ServerSettings::fetch_settings(&state.connection).await?;
@@ -125,6 +186,7 @@ pub async fn update_settings(
 
     let user_stats = AdminUserStats::from(&stats);
     let project_stats = AdminProjectStats::from(&stats);
+    let top_components = Alerts::top_components(&state.connection, 5).await?;
 
     Ok(Json(AdminResponse {
         settings: settings
@@ -133,6 +195,7 @@ pub async fn update_settings(
             .collect(),
         project_stats,
         user_stats,
+        top_components: top_components.into_iter().map(|c| c.into()).collect(),
         users: users
             .into_iter()
             .map(|user| AdminUserSummary {
@@ -225,6 +288,1273 @@ pub(crate) async fn update_users(
     }))
 }
 
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct AgentTokenResp {
+    id: i32,
+    name: String,
+    state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Konarr version reported by the agent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    agent_version: Option<String>,
+    /// Project whose subtree the token is restricted to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project_scope: Option<i32>,
+    /// The agent is behind the server (see `agent.version.max-minor-gap`)
+    outdated: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct AgentTokenCreatedResp {
+    #[serde(flatten)]
+    agent: AgentTokenResp,
+    /// Plain text token (only returned once)
+    token: String,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct AgentTokenReq {
+    /// Name of the token (machine / host)
+    name: String,
+    /// Number of hours until the token expires (never expires if not set)
+    expires: Option<u32>,
+    /// Project whose subtree the token is restricted to (every project if not set)
+    #[serde(default)]
+    project_scope: Option<i32>,
+}
+
+#[get("/agents/tokens")]
+pub(crate) async fn get_agent_tokens(
+    state: &State<AppState>,
+    _session: AdminSession,
+) -> ApiResult<Vec<AgentTokenResp>> {
+    let tokens = AgentTokens::query(
+        &state.connection,
+        AgentTokens::query_select()
+            .order_by("id", QueryOrder::Asc)
+            .build()?,
+    )
+    .await?;
+
+    // Outdated agents first
+    let gap = konarr::tasks::agents::agent_max_minor_gap(&state.connection).await;
+    let mut tokens: Vec<AgentTokenResp> = tokens
+        .into_iter()
+        .map(|token| {
+            let outdated = token.agent_version.as_ref().is_some_and(|version| {
+                konarr::utils::version::is_outdated(version, konarr::KONARR_VERSION, gap)
+            });
+            AgentTokenResp {
+                outdated,
+                ..token.into()
+            }
+        })
+        .collect();
+    tokens.sort_by_key(|token| !token.outdated);
+
+    Ok(Json(tokens))
+}
+
+#[post("/agents/tokens", data = "<data>")]
+pub(crate) async fn create_agent_token(
+    state: &State<AppState>,
+    session: AdminSession,
+    data: Json<AgentTokenReq>,
+) -> ApiResult<AgentTokenCreatedResp> {
+    let expires_at = data
+        .expires
+        .map(|hours| chrono::Utc::now() + chrono::TimeDelta::hours(hours.into()));
+
+    let (agent_token, token) = AgentTokens::create(
+        &state.connection,
+        data.name.trim(),
+        expires_at,
+        data.project_scope,
+    )
+    .await?;
+    info!(
+        "Agent token `{}` created by User({}) (scope: {:?})",
+        agent_token.name, session.user.id, agent_token.project_scope
+    );
+
+    Ok(Json(AgentTokenCreatedResp {
+        agent: agent_token.into(),
+        token,
+    }))
+}
+
+#[delete("/agents/tokens/<id>")]
+pub(crate) async fn revoke_agent_token(
+    state: &State<AppState>,
+    session: AdminSession,
+    id: i32,
+) -> ApiResult<AgentTokenResp> {
+    let mut agent_token = AgentTokens::fetch_by_primary_key(&state.connection, id).await?;
+    agent_token.revoke(&state.connection).await?;
+    warn!(
+        "Agent token `{}` revoked by User({})",
+        agent_token.name, session.user.id
+    );
+
+    if let Ok(mut cache) = state.agent_tokens.write() {
+        cache.invalidate(&agent_token.token_hash);
+    }
+
+    Ok(Json(agent_token.into()))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct AgentCertificateResp {
+    id: i32,
+    name: String,
+    /// SHA256 fingerprint of the certificate
+    fingerprint: String,
+    created_by: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct AgentCertificateReq {
+    /// Name of the certificate (machine / host)
+    name: String,
+    /// SHA256 fingerprint of the client certificate (`AB:CD:..` or hex)
+    fingerprint: String,
+}
+
+#[get("/agents/certificates")]
+pub(crate) async fn get_agent_certificates(
+    state: &State<AppState>,
+    _session: AdminSession,
+) -> ApiResult<Vec<AgentCertificateResp>> {
+    let certificates = AgentCertificates::query(
+        &state.connection,
+        AgentCertificates::query_select()
+            .order_by("id", QueryOrder::Asc)
+            .build()?,
+    )
+    .await?;
+
+    Ok(Json(certificates.into_iter().map(|c| c.into()).collect()))
+}
+
+#[post("/agents/certificates", data = "<data>")]
+pub(crate) async fn create_agent_certificate(
+    state: &State<AppState>,
+    session: AdminSession,
+    data: Json<AgentCertificateReq>,
+) -> ApiResult<AgentCertificateResp> {
+    let certificate = AgentCertificates::register(
+        &state.connection,
+        data.name.trim(),
+        &data.fingerprint,
+        session.user.username.clone(),
+    )
+    .await?;
+    info!(
+        "Agent certificate `{}` registered by User({})",
+        certificate.name, session.user.id
+    );
+
+    Ok(Json(certificate.into()))
+}
+
+#[delete("/agents/certificates/<id>")]
+pub(crate) async fn delete_agent_certificate(
+    state: &State<AppState>,
+    session: AdminSession,
+    id: i32,
+) -> ApiResult<AgentCertificateResp> {
+    let certificate = AgentCertificates::fetch_by_primary_key(&state.connection, id).await?;
+    certificate.delete(&state.connection).await?;
+    warn!(
+        "Agent certificate `{}` removed by User({})",
+        certificate.name, session.user.id
+    );
+
+    Ok(Json(certificate.into()))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct IgnoreRuleResp {
+    id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<i32>,
+    purl: String,
+    advisory: String,
+    reason: String,
+    created_by: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    expired: bool,
+    /// Number of alerts the rule is suppressing
+    suppressed: i64,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct IgnoreRuleReq {
+    /// Project the rule applies to (global rule if not set)
+    project: Option<i32>,
+    /// Package URL glob
+    purl: Option<String>,
+    /// Advisory glob (defaults to every advisory)
+    advisory: Option<String>,
+    /// Reason for ignoring the alerts
+    reason: Option<String>,
+    /// Number of hours until the rule expires (never expires if not set)
+    expires: Option<u32>,
+}
+
+impl IgnoreRuleResp {
+    fn new(rule: AlertIgnoreRules, suppressed: &HashMap<i32, i64>) -> Self {
+        let id: i32 = rule.id.into();
+        Self {
+            id,
+            expired: rule.is_expired(),
+            project: rule.project_id,
+            purl: rule.purl,
+            advisory: rule.advisory,
+            reason: rule.reason,
+            created_by: rule.created_by,
+            expires_at: rule.expires_at,
+            suppressed: suppressed.get(&id).copied().unwrap_or_default(),
+            created_at: rule.created_at,
+        }
+    }
+}
+
+#[get("/alerts/ignore-rules")]
+pub(crate) async fn get_ignore_rules(
+    state: &State<AppState>,
+    _session: AdminSession,
+) -> ApiResult<Vec<IgnoreRuleResp>> {
+    let suppressed = AlertIgnoreRules::suppressed(&state.connection).await?;
+    let rules = AlertIgnoreRules::query(
+        &state.connection,
+        AlertIgnoreRules::query_select()
+            .order_by("id", QueryOrder::Asc)
+            .build()?,
+    )
+    .await?;
+
+    Ok(Json(
+        rules
+            .into_iter()
+            .map(|rule| IgnoreRuleResp::new(rule, &suppressed))
+            .collect(),
+    ))
+}
+
+#[post("/alerts/ignore-rules", data = "<data>")]
+pub(crate) async fn create_ignore_rule(
+    state: &State<AppState>,
+    session: AdminSession,
+    data: Json<IgnoreRuleReq>,
+) -> ApiResult<IgnoreRuleResp> {
+    let data = data.into_inner();
+    let purl = data.purl.unwrap_or_default().trim().to_string();
+    if purl.is_empty() {
+        return Err(konarr::KonarrError::InvalidData(
+            "Ignore rule Package URL can not be empty".to_string(),
+        )
+        .into());
+    }
+    if let Some(project) = data.project {
+        konarr::models::Projects::fetch_by_primary_key(&state.connection, project).await?;
+    }
+
+    let mut rule = AlertIgnoreRules::new(
+        purl,
+        data.advisory
+            .filter(|a| !a.trim().is_empty())
+            .unwrap_or_else(|| "*".to_string()),
+        data.reason.unwrap_or_default(),
+        session.user.username.clone(),
+    );
+    rule.project_id = data.project;
+    rule.expires_at = data
+        .expires
+        .map(|hours| chrono::Utc::now() + chrono::TimeDelta::hours(hours.into()));
+    rule.save(&state.connection).await?;
+    info!(
+        "Alert ignore rule `{}` ({}) created by User({})",
+        rule.purl, rule.advisory, session.user.id
+    );
+
+    Ok(Json(IgnoreRuleResp::new(rule, &HashMap::new())))
+}
+
+#[patch("/alerts/ignore-rules/<id>", data = "<data>")]
+pub(crate) async fn update_ignore_rule(
+    state: &State<AppState>,
+    _session: AdminSession,
+    id: i32,
+    data: Json<IgnoreRuleReq>,
+) -> ApiResult<IgnoreRuleResp> {
+    let mut rule = AlertIgnoreRules::fetch_by_primary_key(&state.connection, id).await?;
+    if let Some(reason) = &data.reason {
+        rule.reason = reason.clone();
+    }
+    if let Some(hours) = data.expires {
+        rule.expires_at = Some(chrono::Utc::now() + chrono::TimeDelta::hours(hours.into()));
+    }
+    rule.update(&state.connection).await?;
+
+    let suppressed = AlertIgnoreRules::suppressed(&state.connection).await?;
+    Ok(Json(IgnoreRuleResp::new(rule, &suppressed)))
+}
+
+#[delete("/alerts/ignore-rules/<id>")]
+pub(crate) async fn delete_ignore_rule(
+    state: &State<AppState>,
+    session: AdminSession,
+    id: i32,
+) -> ApiResult<IgnoreRuleResp> {
+    let rule = AlertIgnoreRules::fetch_by_primary_key(&state.connection, id).await?;
+    let suppressed = AlertIgnoreRules::suppressed(&state.connection).await?;
+    rule.remove(&state.connection).await?;
+    warn!(
+        "Alert ignore rule `{}` ({}) deleted by User({})",
+        rule.purl, rule.advisory, session.user.id
+    );
+
+    Ok(Json(IgnoreRuleResp::new(rule, &suppressed)))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct UnclassifiedComponentResp {
+    /// Component name
+    name: String,
+    /// Number of dependencies using the component
+    count: i64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct CatalogueCoverageResp {
+    /// Percentage of classified components
+    coverage: i64,
+    /// Components reclassified in the last catalogue run
+    reclassified: i64,
+    /// Components still classified as a library or unknown
+    unclassified: i64,
+    /// Top unclassified component names
+    components: Vec<UnclassifiedComponentResp>,
+}
+
+#[get("/catalogue/unclassified?<limit>")]
+pub(crate) async fn get_catalogue_unclassified(
+    state: &State<AppState>,
+    _session: AdminSession,
+    limit: Option<u32>,
+) -> ApiResult<CatalogueCoverageResp> {
+    let limit = limit.unwrap_or(25).min(100);
+
+    let statistic = |setting: Setting| {
+        let connection = &state.connection;
+        async move {
+            ServerSettings::fetch_by_name(connection, setting)
+                .await
+                .map(|s| s.value.parse().unwrap_or_default())
+                .unwrap_or_default()
+        }
+    };
+
+    let components = Component::top_unclassified(&state.connection, limit)
+        .await?
+        .into_iter()
+        .map(|(name, count)| UnclassifiedComponentResp { name, count })
+        .collect();
+
+    Ok(Json(CatalogueCoverageResp {
+        coverage: statistic(Setting::CatalogueCoveragePercent).await,
+        reclassified: statistic(Setting::CatalogueReclassified).await,
+        unclassified: statistic(Setting::CatalogueUnclassified).await,
+        components,
+    }))
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct ComponentTagReq {
+    /// Tag to assign
+    tag: String,
+    /// Component to tag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    component: Option<i32>,
+    /// Tag every component matching the PURL pattern (`*` wildcards)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    purl: Option<String>,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct ComponentTagResp {
+    tag: String,
+    /// Number of newly tagged (or untagged) components
+    count: usize,
+}
+
+/// Assign a tag to a component or to every component matching a PURL pattern
+#[post("/dependencies/tags", data = "<data>", format = "json")]
+pub(crate) async fn assign_component_tags(
+    state: &State<AppState>,
+    session: AdminSession,
+    data: Json<ComponentTagReq>,
+) -> ApiResult<ComponentTagResp> {
+    let data = data.into_inner();
+    let tag = ComponentTags::normalize(&data.tag)?;
+
+    let (count, resource_id, details) = match (data.component, data.purl) {
+        (Some(component), None) => {
+            let tagged = ComponentTags::assign(&state.connection, component, &tag).await?;
+            (tagged as usize, component, None)
+        }
+        (None, Some(pattern)) => {
+            let tagged = ComponentTags::assign_by_purl(&state.connection, &pattern, &tag).await?;
+            (tagged, 0, Some(format!("purl={}", pattern)))
+        }
+        _ => {
+            return Err(konarr::KonarrError::InvalidData(
+                "Either a component or a PURL pattern is required".to_string(),
+            )
+            .into())
+        }
+    };
+    info!(
+        "Tag `{}` assigned to {} components by {}",
+        tag, count, session.user.username
+    );
+    AuditLog::record(
+        &state.connection,
+        "component.tag",
+        session.user.username.clone(),
+        "component",
+        resource_id,
+        Some(match details {
+            Some(details) => format!("tag={} {}", tag, details),
+            None => format!("tag={}", tag),
+        }),
+    )
+    .await?;
+
+    Ok(Json(ComponentTagResp { tag, count }))
+}
+
+/// Remove a tag from a component
+#[delete("/dependencies/<id>/tags/<tag>")]
+pub(crate) async fn remove_component_tag(
+    state: &State<AppState>,
+    session: AdminSession,
+    id: i32,
+    tag: &str,
+) -> ApiResult<ComponentTagResp> {
+    let tag = ComponentTags::normalize(tag)?;
+    let removed = ComponentTags::remove(&state.connection, id, &tag).await?;
+    if removed {
+        AuditLog::record(
+            &state.connection,
+            "component.untag",
+            session.user.username.clone(),
+            "component",
+            id,
+            Some(format!("tag={}", tag)),
+        )
+        .await?;
+    }
+    Ok(Json(ComponentTagResp {
+        tag,
+        count: removed as usize,
+    }))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct AdminStatusResp {
+    storage: StorageStatusResp,
+    /// Results of the startup checks
+    checks: Vec<CheckResult>,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct StorageStatusResp {
+    /// Size of the database file (bytes)
+    database_size: i64,
+    /// Free disk space on the data path (bytes)
+    disk_free: i64,
+    /// Size of the Grype database (bytes)
+    grypedb_size: i64,
+    /// Build of the Grype database
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grypedb_built: Option<String>,
+    /// Number of stored SBOMs
+    sboms_count: i64,
+    /// Total size of the stored SBOMs (bytes)
+    sboms_size: i64,
+    /// Snapshots with the largest SBOMs
+    largest_snapshots: Vec<SnapshotSizeResp>,
+    /// When the storage diagnostics last changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct SnapshotSizeResp {
+    snapshot_id: i32,
+    size_bytes: i64,
+}
+
+/// Server status, startup checks and storage diagnostics (collected hourly by the storage task)
+#[get("/status")]
+pub(crate) async fn get_status(
+    state: &State<AppState>,
+    _session: AdminSession,
+) -> ApiResult<AdminStatusResp> {
+    let mut storage = StorageStatusResp::default();
+
+    let statistics =
+        ServerSettings::get_namespace(&state.connection, SettingNamespace::Storage).await?;
+    for setting in statistics.iter() {
+        let value = setting.value.parse().unwrap_or_default();
+        match setting.name {
+            Setting::StorageDatabaseSize => storage.database_size = value,
+            Setting::StorageDiskFree => storage.disk_free = value,
+            Setting::StorageGrypeSize => storage.grypedb_size = value,
+            Setting::StorageSbomsCount => storage.sboms_count = value,
+            Setting::StorageSbomsSize => storage.sboms_size = value,
+            _ => continue,
+        }
+        storage.updated_at = storage.updated_at.max(Some(setting.updated_at));
+    }
+    storage.grypedb_built =
+        ServerSettings::fetch_by_name(&state.connection, Setting::SecurityAdvisoriesVersion)
+            .await
+            .ok()
+            .map(|s| s.value)
+            .filter(|v| !v.is_empty() && v != "Unknown");
+
+    storage.largest_snapshots = SbomUploads::largest(&state.connection, 5)
+        .await?
+        .into_iter()
+        .map(|s| SnapshotSizeResp {
+            snapshot_id: s.snapshot_id,
+            size_bytes: s.size_bytes,
+        })
+        .collect();
+
+    Ok(Json(AdminStatusResp {
+        storage,
+        checks: state.checks.as_ref().clone(),
+    }))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct TaskStatusResp {
+    name: String,
+    /// Consecutive successful runs
+    streak: usize,
+    runs: Vec<TaskRunResp>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct TaskRunResp {
+    success: bool,
+    duration_ms: i32,
+    summary: String,
+    started_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// History of the background task runs (most recent first)
+#[get("/tasks?<limit>")]
+pub(crate) async fn get_tasks(
+    state: &State<AppState>,
+    _session: AdminSession,
+    limit: Option<u32>,
+) -> ApiResult<Vec<TaskStatusResp>> {
+    let limit = limit.unwrap_or(10).min(TASK_RUNS_HISTORY);
+    let history = TaskRuns::fetch_history(&state.connection, limit).await?;
+
+    Ok(Json(
+        history
+            .into_iter()
+            .map(|(name, runs)| TaskStatusResp {
+                name,
+                streak: TaskRuns::streak(&runs),
+                runs: runs
+                    .into_iter()
+                    .map(|run| TaskRunResp {
+                        success: run.success,
+                        duration_ms: run.duration_ms,
+                        summary: run.summary,
+                        started_at: run.started_at,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    ))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct IntegrityReportResp {
+    sboms_checked: u64,
+    repaired: usize,
+    issues: Vec<IntegrityIssueResp>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct IntegrityIssueResp {
+    kind: String,
+    id: i32,
+    message: String,
+    repaired: bool,
+}
+
+/// Run the integrity check (issues are only repaired if `repair` is set)
+#[post("/tasks/integrity?<repair>")]
+pub(crate) async fn run_integrity(
+    state: &State<AppState>,
+    session: AdminSession,
+    repair: Option<bool>,
+) -> ApiResult<IntegrityReportResp> {
+    let repair = repair.unwrap_or(false);
+    info!(
+        "Integrity check requested by `{}` (repair: {})",
+        session.user.username, repair
+    );
+    let mut report = None;
+    konarr::tasks::instrument(&state.connection, "integrity", async {
+        let result = konarr::tasks::integrity(&state.config, &state.connection, repair).await?;
+        let stats = TaskStats::from(&result);
+        report = Some(result);
+        Ok(stats)
+    })
+    .await?;
+    let report = report.unwrap_or_default();
+
+    Ok(Json(IntegrityReportResp {
+        sboms_checked: report.sboms_checked,
+        repaired: report.repaired(),
+        issues: report
+            .issues
+            .into_iter()
+            .map(|issue| IntegrityIssueResp {
+                kind: issue.kind.to_string(),
+                id: issue.id,
+                message: issue.message,
+                repaired: issue.repaired,
+            })
+            .collect(),
+    }))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct StatisticsRecomputeResp {
+    statistics: usize,
+    corrected: Vec<StatisticDriftResp>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct StatisticDriftResp {
+    name: String,
+    previous: Option<i64>,
+    value: i64,
+}
+
+/// Recompute every statistic from the source of truth (corrects drifted statistics)
+#[post("/tasks/statistics")]
+pub(crate) async fn run_statistics(
+    state: &State<AppState>,
+    session: AdminSession,
+) -> ApiResult<StatisticsRecomputeResp> {
+    info!(
+        "Statistics recompute requested by `{}`",
+        session.user.username
+    );
+    let mut summary = None;
+    konarr::tasks::instrument(&state.connection, "statistics", async {
+        let result = konarr::tasks::Statistics::recompute_all(&state.connection).await?;
+        let stats = TaskStats::from(&result);
+        summary = Some(result);
+        Ok(stats)
+    })
+    .await?;
+    let summary = summary.unwrap_or_default();
+
+    Ok(Json(StatisticsRecomputeResp {
+        statistics: summary.statistics,
+        corrected: summary
+            .corrected
+            .into_iter()
+            .map(|drift| StatisticDriftResp {
+                name: drift.name.to_string(),
+                previous: drift.previous,
+                value: drift.value,
+            })
+            .collect(),
+    }))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct GrypeDbResp {
+    /// If the database file exists
+    present: bool,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schema_version: Option<i32>,
+    /// When the database was built
+    #[serde(skip_serializing_if = "Option::is_none")]
+    build: Option<chrono::DateTime<chrono::Utc>>,
+    /// Number of vulnerability rows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vulnerabilities: Option<u64>,
+    /// If a sync is running
+    syncing: bool,
+    /// Last sync attempt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_sync: Option<GrypeDbSyncResp>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct GrypeDbSyncResp {
+    attempted_at: chrono::DateTime<chrono::Utc>,
+    /// `updated`, `up-to-date` or `failed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Status of the advisories (Grype) database
+#[get("/grypedb")]
+pub(crate) async fn get_grypedb(
+    state: &State<AppState>,
+    _session: AdminSession,
+) -> ApiResult<GrypeDbResp> {
+    let status = GrypeDatabase::status(&state.config.grype_path()?).await;
+    let sync = GrypeSyncResult::load(&state.connection).await?;
+
+    Ok(Json(GrypeDbResp {
+        present: status.present,
+        path: status.path.display().to_string(),
+        schema_version: status.schema_version,
+        build: status.build,
+        vulnerabilities: status.vulnerabilities,
+        syncing: AdvisoriesSyncTask::is_running(),
+        last_sync: sync.attempted_at.map(|attempted_at| GrypeDbSyncResp {
+            attempted_at,
+            result: sync.outcome.map(|outcome| outcome.to_string()),
+            error: sync.error,
+        }),
+    }))
+}
+
+/// Sync the advisories (Grype) database now (in the background)
+///
+/// The projects are rescanned if a new build is downloaded, the result is reported
+/// by `GET /grypedb`.
+#[post("/grypedb/sync")]
+pub(crate) async fn sync_grypedb(
+    state: &State<AppState>,
+    session: AdminSession,
+) -> Result<rocket::response::status::Accepted<Json<GrypeDbResp>>, KonarrServerError> {
+    let task = AdvisoriesSyncTask::acquire(state.config.grype_path()?).ok_or_else(|| {
+        konarr::KonarrError::Conflict("Advisories database sync is already running".to_string())
+    })?;
+    info!(
+        "Advisories database sync requested by `{}`",
+        session.user.username
+    );
+
+    let connection = std::sync::Arc::clone(&state.connection);
+    let config = state.config.clone();
+    tokio::spawn(async move {
+        konarr::tasks::instrument(&connection, "advisories-sync", async {
+            let new = task.sync_and_rescan(&config, &connection).await?;
+            Ok(TaskStats::from_iter([("new", new)]))
+        })
+        .await
+        .ok();
+    });
+
+    let Json(status) = get_grypedb(state, session).await?;
+    Ok(rocket::response::status::Accepted(Json(status)))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct ExposedProjectResp {
+    id: i32,
+    name: String,
+    title: String,
+    parent: i32,
+    /// Ports published on the host
+    ports: Vec<ContainerPortResp>,
+    networks: Vec<String>,
+}
+
+/// Projects with ports published on the host (latest snapshot of each project)
+#[get("/exposure")]
+pub(crate) async fn get_exposure(
+    state: &State<AppState>,
+    _session: AdminSession,
+) -> ApiResult<Vec<ExposedProjectResp>> {
+    let exposed = Projects::fetch_exposed(&state.connection).await?;
+
+    Ok(Json(
+        exposed
+            .into_iter()
+            .map(|exposed| ExposedProjectResp {
+                id: exposed.project.id.into(),
+                title: exposed
+                    .project
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| exposed.project.name.clone()),
+                name: exposed.project.name,
+                parent: exposed.project.parent,
+                ports: exposed
+                    .ports
+                    .into_iter()
+                    .map(ContainerPortResp::from)
+                    .collect(),
+                networks: exposed.networks,
+            })
+            .collect(),
+    ))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct DuplicateImageResp {
+    /// Image digest
+    digest: String,
+    /// Latest snapshots of the projects (shared snapshots are listed once)
+    snapshots: Vec<i32>,
+    projects: Vec<DuplicateProjectResp>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct DuplicateProjectResp {
+    id: i32,
+    name: String,
+    title: String,
+    parent: i32,
+}
+
+/// Container images (by digest) tracked by more than one project
+#[get("/duplicates")]
+pub(crate) async fn get_duplicates(
+    state: &State<AppState>,
+    _session: AdminSession,
+) -> ApiResult<Vec<DuplicateImageResp>> {
+    let duplicates = Projects::fetch_duplicate_images(&state.connection).await?;
+    if !duplicates.is_empty() {
+        warn!(
+            "{} container images are tracked by more than one project",
+            duplicates.len()
+        );
+    }
+
+    Ok(Json(
+        duplicates
+            .into_iter()
+            .map(|duplicate| DuplicateImageResp {
+                digest: duplicate.digest,
+                snapshots: duplicate.snapshots,
+                projects: duplicate
+                    .projects
+                    .into_iter()
+                    .map(|project| DuplicateProjectResp {
+                        id: project.id.into(),
+                        title: project
+                            .title
+                            .clone()
+                            .unwrap_or_else(|| project.name.clone()),
+                        name: project.name,
+                        parent: project.parent,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    ))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct RetentionPlanResp {
+    id: i32,
+    name: String,
+    #[serde(rename = "type")]
+    project_type: String,
+    max_snapshots: u32,
+    max_age_days: u32,
+    /// Snapshots which would be removed (newest first)
+    snapshots: Vec<i32>,
+}
+
+/// Dry-run of the snapshot retention policies (what the cleanup task would remove)
+#[get("/retention")]
+pub(crate) async fn get_retention_plan(
+    state: &State<AppState>,
+    _session: AdminSession,
+) -> ApiResult<Vec<RetentionPlanResp>> {
+    Ok(Json(
+        konarr::tasks::retention_plan(&state.connection)
+            .await?
+            .into_iter()
+            .map(|plan| RetentionPlanResp {
+                id: plan.project_id,
+                name: plan.project,
+                project_type: plan.project_type.to_string(),
+                max_snapshots: plan.policy.max_snapshots,
+                max_age_days: plan.policy.max_age_days,
+                snapshots: plan.snapshots,
+            })
+            .collect(),
+    ))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct AuditLogResp {
+    id: i32,
+    action: String,
+    actor: String,
+    resource: String,
+    resource_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Latest entries of the audit log (most recent first)
+#[get("/audit?<limit>&<resource>&<resource_id>")]
+pub(crate) async fn get_audit_log(
+    state: &State<AppState>,
+    _session: AdminSession,
+    limit: Option<u32>,
+    resource: Option<String>,
+    resource_id: Option<i32>,
+) -> ApiResult<Vec<AuditLogResp>> {
+    let limit = limit.unwrap_or(50).min(500) as usize;
+    let resource = match (resource.as_deref(), resource_id) {
+        (Some(resource), Some(id)) => Some((resource, id)),
+        _ => None,
+    };
+
+    Ok(Json(
+        AuditLog::fetch_latest(&state.connection, resource, limit)
+            .await?
+            .into_iter()
+            .map(|entry| AuditLogResp {
+                id: entry.id.into(),
+                action: entry.action,
+                actor: entry.actor,
+                resource: entry.resource,
+                resource_id: entry.resource_id,
+                details: entry.details,
+                created_at: entry.created_at,
+            })
+            .collect(),
+    ))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct ReportResp {
+    id: i32,
+    period: String,
+    period_start: chrono::DateTime<chrono::Utc>,
+    period_end: chrono::DateTime<chrono::Utc>,
+    generated_at: chrono::DateTime<chrono::Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    delivered_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Digest (only when fetching a single report)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    digest: Option<ReportDigest>,
+    /// Digest rendered as Markdown (only when fetching a single report)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    markdown: Option<String>,
+}
+
+/// Latest security digest reports (most recent period first)
+#[get("/reports?<limit>")]
+pub(crate) async fn get_reports(
+    state: &State<AppState>,
+    _session: AdminSession,
+    limit: Option<u32>,
+) -> ApiResult<Vec<ReportResp>> {
+    let limit = limit.unwrap_or(20).min(100) as usize;
+
+    Ok(Json(
+        Reports::fetch_latest(&state.connection, limit)
+            .await?
+            .into_iter()
+            .map(ReportResp::from)
+            .collect(),
+    ))
+}
+
+/// Security digest report (with the digest and Markdown)
+#[get("/reports/<id>")]
+pub(crate) async fn get_report(
+    state: &State<AppState>,
+    _session: AdminSession,
+    id: i32,
+) -> ApiResult<ReportResp> {
+    let report = Reports::fetch_by_primary_key(&state.connection, id).await?;
+    let digest = report.digest()?;
+    let markdown = report.markdown.clone();
+
+    Ok(Json(ReportResp {
+        digest: Some(digest),
+        markdown: Some(markdown),
+        ..ReportResp::from(report)
+    }))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct OutboxResp {
+    id: i32,
+    event: String,
+    version: i32,
+    endpoint: String,
+    state: String,
+    attempts: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    next_attempt_at: chrono::DateTime<chrono::Utc>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    delivered_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Envelope posted to the webhook
+    payload: serde_json::Value,
+}
+
+/// Latest events of the outbox (most recent first), optionally in a state
+/// (`pending`, `delivered` or `failed`)
+#[get("/outbox?<state>&<limit>")]
+pub(crate) async fn get_outbox(
+    app_state: &State<AppState>,
+    _session: AdminSession,
+    state: Option<String>,
+    limit: Option<u32>,
+) -> ApiResult<Vec<OutboxResp>> {
+    let limit = limit.unwrap_or(50).min(500) as usize;
+    let outbox_state = match state.as_deref().map(|s| s.to_lowercase()) {
+        None => None,
+        Some(s) if s == "pending" => Some(OutboxState::Pending),
+        Some(s) if s == "delivered" => Some(OutboxState::Delivered),
+        Some(s) if s == "failed" => Some(OutboxState::Failed),
+        Some(s) => {
+            return Err(
+                konarr::KonarrError::InvalidData(format!("Unknown outbox state `{}`", s)).into(),
+            )
+        }
+    };
+
+    Ok(Json(
+        EventsOutbox::fetch_latest(&app_state.connection, outbox_state, limit)
+            .await?
+            .into_iter()
+            .map(OutboxResp::from)
+            .collect(),
+    ))
+}
+
+/// Deliver an event of the outbox again (the attempts are reset)
+#[post("/outbox/<id>/redeliver")]
+pub(crate) async fn redeliver_outbox(
+    state: &State<AppState>,
+    session: AdminSession,
+    id: i32,
+) -> ApiResult<OutboxResp> {
+    let mut event = EventsOutbox::fetch_by_primary_key(&state.connection, id).await?;
+    if event.state == OutboxState::Pending {
+        return Err(konarr::KonarrError::Conflict(format!(
+            "Event `{}` is already waiting to be delivered",
+            id
+        ))
+        .into());
+    }
+
+    info!(
+        "Redelivering the `{}` event ({}) requested by {}",
+        event.event, id, session.user.username
+    );
+    event.redeliver(&state.connection).await?;
+    let max_attempts = konarr::tasks::outbox::max_attempts(&state.connection).await?;
+    konarr::tasks::outbox::deliver(&state.connection, &mut event, max_attempts).await?;
+
+    AuditLog::record(
+        &state.connection,
+        "outbox.redeliver",
+        session.user.username.clone(),
+        "outbox",
+        id,
+        Some(
+            serde_json::json!({ "event": event.event, "state": event.state.to_string() })
+                .to_string(),
+        ),
+    )
+    .await?;
+
+    Ok(Json(OutboxResp::from(event)))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct MaintenanceResp {
+    enabled: bool,
+}
+
+/// Maintenance mode of the server
+#[get("/maintenance")]
+pub(crate) async fn get_maintenance(
+    state: &State<AppState>,
+    _session: AdminSession,
+) -> ApiResult<MaintenanceResp> {
+    Ok(Json(MaintenanceResp {
+        enabled: state.maintenance.enabled(),
+    }))
+}
+
+/// Enable or disable the maintenance mode (allowed while in maintenance mode)
+#[post("/maintenance", data = "<data>", format = "json")]
+pub(crate) async fn set_maintenance(
+    state: &State<AppState>,
+    session: AdminSession,
+    data: Json<MaintenanceResp>,
+) -> ApiResult<MaintenanceResp> {
+    let enabled = data.enabled;
+    ServerSettings::get(&state.connection, Setting::MaintenanceEnabled)
+        .await?
+        .set_update(
+            &state.connection,
+            if enabled { "enabled" } else { "disabled" },
+        )
+        .await?;
+    state.maintenance.set(enabled);
+
+    if enabled {
+        warn!(
+            "Maintenance mode enabled by {}, the server is read-only",
+            session.user.username
+        );
+    } else {
+        info!("Maintenance mode disabled by {}", session.user.username);
+    }
+    AuditLog::record(
+        &state.connection,
+        if enabled {
+            "server.maintenance.enable"
+        } else {
+            "server.maintenance.disable"
+        },
+        session.user.username.clone(),
+        "server",
+        0,
+        None,
+    )
+    .await?;
+
+    Ok(Json(MaintenanceResp { enabled }))
+}
+
+impl From<EventsOutbox> for OutboxResp {
+    fn from(value: EventsOutbox) -> Self {
+        Self {
+            payload: value.envelope(),
+            id: value.id.into(),
+            event: value.event,
+            version: value.version,
+            endpoint: value.endpoint,
+            state: value.state.to_string(),
+            attempts: value.attempts,
+            status_code: value.status_code,
+            error: value.error,
+            next_attempt_at: value.next_attempt_at,
+            created_at: value.created_at,
+            delivered_at: value.delivered_at,
+        }
+    }
+}
+
+impl From<Reports> for ReportResp {
+    fn from(value: Reports) -> Self {
+        Self {
+            id: value.id.into(),
+            period: value.period,
+            period_start: value.period_start,
+            period_end: value.period_end,
+            generated_at: value.generated_at,
+            delivered_at: value.delivered_at,
+            digest: None,
+            markdown: None,
+        }
+    }
+}
+
+impl From<AgentTokens> for AgentTokenResp {
+    fn from(value: AgentTokens) -> Self {
+        Self {
+            id: value.id.into(),
+            name: value.name,
+            state: value.state.to_string(),
+            expires_at: value.expires_at,
+            created_at: value.created_at,
+            revoked_at: value.revoked_at,
+            last_used_at: value.last_used_at,
+            agent_version: value.agent_version,
+            project_scope: value.project_scope,
+            outdated: false,
+        }
+    }
+}
+
+impl From<AgentCertificates> for AgentCertificateResp {
+    fn from(value: AgentCertificates) -> Self {
+        Self {
+            id: value.id.into(),
+            name: value.name,
+            fingerprint: value.fingerprint,
+            created_by: value.created_by,
+            created_at: value.created_at,
+            last_used_at: value.last_used_at,
+        }
+    }
+}
+
 impl From<&Vec<ServerSettings>> for AdminUserStats {
     fn from(value: &Vec<ServerSettings>) -> Self {
         let mut stats = AdminUserStats::default();
@@ -252,6 +1582,7 @@ impl From<&Vec<ServerSettings>> for AdminProjectStats {
                 Setting::StatsProjectsArchived => {
                     stats.archived = setting.value.parse().unwrap_or(0)
                 }
+                Setting::StatsProjectsStale => stats.stale = setting.value.parse().unwrap_or(0),
                 _ => {}
             }
         }
@@ -1,15 +1,22 @@
 use geekorm::prelude::*;
-use konarr::models::{self, settings::ServerSettings, SessionState, SessionType, UserRole, Users};
+use konarr::models::{
+    self,
+    settings::{ServerSettings, Setting},
+    SessionState, SessionType, UserRole, Users,
+};
 use log::info;
 use rocket::{http::CookieJar, serde::json::Json, State};
 use rocket_governor::RocketGovernor;
 
-use crate::{guards::Session, AppState};
+use crate::{
+    guards::{AgentIdentity, AuthMethod, Session},
+    AppState,
+};
 
 use super::ApiResult;
 
 pub fn routes() -> Vec<rocket::Route> {
-    routes![login, logout, register]
+    routes![login, logout, register, whoami]
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -17,6 +24,9 @@ pub struct LoginResponse {
     status: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     reason: Option<String>,
+    /// Session token (only returned when requested, used by the CLI)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -28,6 +38,10 @@ pub struct LogoutResponse {
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
+    /// Create an application session and return its token in the response instead of
+    /// setting the session cookie (for non-browser clients)
+    #[serde(default)]
+    pub token: bool,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -37,6 +51,83 @@ pub struct RegisterRequest {
     pub password_confirm: String,
 }
 
+/// Authenticated principal and its effective permissions
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct WhoAmIResponse {
+    /// How the request was authenticated (`cookie`, `bearer` or `agent`)
+    auth: String,
+    user: WhoAmIUser,
+    /// Agent key / token (agent requests only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    agent: Option<WhoAmIAgent>,
+    /// When the session or agent token expires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    capabilities: WhoAmICapabilities,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct WhoAmIUser {
+    id: i32,
+    username: String,
+    role: String,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct WhoAmIAgent {
+    /// Name of the scoped agent token (not set for the legacy agent key)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+    /// The deprecated shared agent key was used
+    legacy: bool,
+    /// Konarr version reported by the agent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct WhoAmICapabilities {
+    /// Can create snapshots and upload SBOMs (not in maintenance mode)
+    can_upload: bool,
+    /// Can use the admin API
+    can_admin: bool,
+    /// Project whose subtree the agent token is restricted to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project_scope: Option<i32>,
+}
+
+#[get("/whoami")]
+pub async fn whoami(state: &State<AppState>, session: Session) -> ApiResult<WhoAmIResponse> {
+    let agent = session.agent.as_ref().map(|agent| WhoAmIAgent {
+        token: match agent {
+            AgentIdentity::Token { name, .. } => Some(name.clone()),
+            AgentIdentity::Legacy => None,
+        },
+        legacy: *agent == AgentIdentity::Legacy,
+        version: session.agent_version.clone(),
+    });
+
+    Ok(Json(WhoAmIResponse {
+        auth: session.auth.to_string(),
+        user: WhoAmIUser {
+            id: session.user.id.into(),
+            username: session.user.username.clone(),
+            role: session.user.role.to_string(),
+        },
+        agent,
+        expires_at: session.expires_at(state.config.sessions()),
+        capabilities: WhoAmICapabilities {
+            can_upload: !state.maintenance.enabled(),
+            can_admin: session.agent.is_none() && session.user.role == UserRole::Admin,
+            project_scope: session.project_scope(),
+        },
+    }))
+}
+
 #[post("/login", data = "<payload>", format = "json")]
 pub async fn login(
     state: &State<AppState>,
@@ -46,22 +137,50 @@ pub async fn login(
 ) -> ApiResult<LoginResponse> {
     let connection = std::sync::Arc::clone(&state.connection);
 
-    let (user, session) = Users::login(
-        &connection,
-        payload.username.clone(),
-        payload.password.clone(),
-    )
-    .await?;
-
-    cookies.add_private(("x-konarr-token", session.token.clone()));
+    // Non-browser clients get their own session so the browser login does not rotate it
+    let (user, session, auth) = if payload.token {
+        let (user, session) = Users::login_application(
+            &connection,
+            payload.username.clone(),
+            payload.password.clone(),
+        )
+        .await?;
+        (user, session, AuthMethod::Bearer)
+    } else {
+        let (user, session) = Users::login(
+            &connection,
+            payload.username.clone(),
+            payload.password.clone(),
+        )
+        .await?;
+        cookies.add_private(("x-konarr-token", session.token.clone()));
+        (user, session, AuthMethod::Cookie)
+    };
+    let token = payload.token.then(|| session.token.clone());
+    let invalidated = models::Sessions::limit_user(&connection, &user, &session).await?;
 
     log::info!("Successfull logged in: {:?}", user.id);
     if let Ok(mut sessions) = state.sessions.write() {
+        for old in invalidated.iter() {
+            sessions.remove(&old.token);
+        }
         log::debug!("Adding user session to in-memory cache - User({})", user.id);
-        sessions.push(Session { user, session });
+        sessions.insert(
+            Session {
+                user,
+                session,
+                agent: None,
+                agent_version: None,
+                auth,
+            },
+            state.config.sessions(),
+        );
     }
 
-    Ok(Json(LoginResponse::success()))
+    Ok(Json(LoginResponse {
+        token,
+        ..LoginResponse::success()
+    }))
 }
 
 #[post("/logout")]
@@ -72,17 +191,20 @@ pub async fn logout(
 ) -> ApiResult<LogoutResponse> {
     let connection = std::sync::Arc::clone(&state.connection);
 
+    // Only the session used for the request is revoked (browser or application)
     let mut user = session.user.clone();
     user.logout(&connection).await?;
 
-    cookies.remove_private("x-konarr-token");
+    if session.auth == AuthMethod::Cookie {
+        cookies.remove_private("x-konarr-token");
+    }
 
     if let Ok(mut sessions) = state.sessions.write() {
         log::debug!(
             "Removing user session from in-memory cache - User({})",
             user.id
         );
-        sessions.retain(|s| s.user.id != user.id);
+        sessions.remove(&session.session.token);
     }
 
     Ok(Json(LogoutResponse {
@@ -100,9 +222,10 @@ pub async fn register(
     if session.is_some() {
         return Ok(Json(LoginResponse::failed("Already logged in")));
     }
-    let registration: String = ServerSettings::fetch_by_name(&state.connection, "registration")
-        .await?
-        .value;
+    let registration: String =
+        ServerSettings::fetch_by_name(&state.connection, Setting::Registration)
+            .await?
+            .value;
 
     if registration == "enabled".to_string() {
         if payload.password != payload.password_confirm {
@@ -128,9 +251,10 @@ pub async fn register(
 
         if !state.init {
             let mut deinit =
-                ServerSettings::fetch_by_name(&state.connection, "initialized").await?;
+                ServerSettings::fetch_by_name(&state.connection, Setting::Initialized).await?;
             deinit.set_boolean("true");
             deinit.update(&state.connection).await?;
+            state.cache.clear().await;
             info!("Server is now initialized");
         }
 
@@ -155,12 +279,202 @@ impl LoginResponse {
         Self {
             status: String::from("success"),
             reason: None,
+            token: None,
         }
     }
     pub fn failed(reason: &str) -> Self {
         Self {
             status: String::from("failed"),
             reason: Some(reason.to_string()),
+            token: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guards::{maintenance::Maintenance, AgentTokenCache, SessionCache};
+    use konarr::models::{AgentTokens, ProjectType, Projects};
+    use rocket::{
+        http::{Cookie, Header, Status},
+        local::asynchronous::Client,
+    };
+    use std::sync::{Arc, RwLock};
+    use tokio::sync::Mutex;
+
+    async fn whoami(client: &Client, auth: Option<Header<'static>>) -> serde_json::Value {
+        let mut request = client.get("/api/auth/whoami");
+        if let Some(auth) = auth {
+            request = request.header(auth);
         }
+        let response = request.dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        response.into_json().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_whoami() -> Result<(), konarr::KonarrError> {
+        let connection = crate::api::database_test().await?;
+
+        let mut admin_session = models::Sessions::new(SessionType::User, SessionState::Active);
+        admin_session.save(&connection).await?;
+        let mut admin = Users::new("admin", "password", UserRole::Admin, admin_session.id);
+        admin.save(&connection).await?;
+
+        let mut project = Projects::new("ci", ProjectType::Group);
+        project.save(&connection).await?;
+        let (_, token) =
+            AgentTokens::create(&connection, "ci", None, Some(project.id.into())).await?;
+
+        let state = AppState {
+            connection: Arc::new(Mutex::new(connection)),
+            sessions: Arc::new(RwLock::new(SessionCache::default())),
+            agent_tokens: Arc::new(RwLock::new(AgentTokenCache::new(
+                "legacy-agent-key".to_string(),
+            ))),
+            config: konarr::Config::default(),
+            init: true,
+            maintenance: Maintenance::default(),
+            cache: Default::default(),
+            checks: Default::default(),
+        };
+        let rocket = rocket::build().manage(state).mount("/api/auth", routes());
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+
+        // Personal (Bearer) token
+        let body = whoami(
+            &client,
+            Some(Header::new(
+                "Authorization",
+                format!("Bearer {}", admin_session.token),
+            )),
+        )
+        .await;
+        assert_eq!(body["auth"], "bearer");
+        assert_eq!(body["user"]["username"], "admin");
+        assert_eq!(body["capabilities"]["canAdmin"], true);
+        assert_eq!(body["capabilities"]["canUpload"], true);
+        assert!(body["expiresAt"].is_string());
+        assert!(body.get("agent").is_none());
+
+        // Session cookie
+        let response = client
+            .get("/api/auth/whoami")
+            .private_cookie(Cookie::new("x-konarr-token", admin_session.token.clone()))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["auth"], "cookie");
+        assert_eq!(body["user"]["role"], "Admin");
+
+        // Scoped agent token
+        let body = whoami(&client, Some(Header::new("Authorization", token))).await;
+        assert_eq!(body["auth"], "agent");
+        assert_eq!(body["agent"]["token"], "ci");
+        assert_eq!(body["agent"]["legacy"], false);
+        assert_eq!(body["capabilities"]["canAdmin"], false);
+        assert_eq!(body["capabilities"]["projectScope"], i32::from(project.id));
+
+        // Legacy agent key
+        let body = whoami(
+            &client,
+            Some(Header::new("Authorization", "legacy-agent-key")),
+        )
+        .await;
+        assert_eq!(body["agent"]["legacy"], true);
+        assert!(body.get("expiresAt").is_none());
+
+        // Unauthenticated
+        let response = client.get("/api/auth/whoami").dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_login_sessions() -> Result<(), konarr::KonarrError> {
+        let connection = crate::api::database_test().await?;
+        Users::create(&connection, "user", "password").await?;
+
+        let state = AppState {
+            connection: Arc::new(Mutex::new(connection)),
+            sessions: Arc::new(RwLock::new(SessionCache::default())),
+            agent_tokens: Arc::new(RwLock::new(AgentTokenCache::default())),
+            config: konarr::Config::default(),
+            init: true,
+            maintenance: Maintenance::default(),
+            cache: Default::default(),
+            checks: Default::default(),
+        };
+        let rocket = rocket::build().manage(state).mount("/api/auth", routes());
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+
+        // Each login from a different address (rate limited)
+        let login = |token: bool, address: u8| {
+            client
+                .post("/api/auth/login")
+                .remote(format!("127.0.0.{}:8000", address).parse().unwrap())
+                .json(&serde_json::json!({
+                    "username": "user",
+                    "password": "password",
+                    "token": token,
+                }))
+                .dispatch()
+        };
+
+        // Browser login (session cookie)
+        let response = login(false, 1).await;
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.cookies().get_private("x-konarr-token").is_some());
+
+        // CLI login (application session token, no cookie)
+        let response = login(true, 2).await;
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.cookies().get("x-konarr-token").is_none());
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        let token = body["token"].as_str().unwrap().to_string();
+        let bearer = || Header::new("Authorization", format!("Bearer {}", token));
+
+        // A new browser login does not rotate the CLI token
+        assert_eq!(login(false, 3).await.status(), Status::Ok);
+        assert_eq!(whoami(&client, Some(bearer())).await["auth"], "bearer");
+        assert_eq!(whoami(&client, None).await["auth"], "cookie");
+
+        // The CLI logout only revokes the application session
+        let response = client
+            .post("/api/auth/logout")
+            .header(bearer())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client
+            .get("/api/auth/whoami")
+            .header(bearer())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(whoami(&client, None).await["auth"], "cookie");
+
+        // The browser logout does not revoke a CLI session
+        let token = login(true, 4)
+            .await
+            .into_json::<serde_json::Value>()
+            .await
+            .unwrap()["token"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let response = client.post("/api/auth/logout").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client.get("/api/auth/whoami").dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+        let body = whoami(
+            &client,
+            Some(Header::new("Authorization", format!("Bearer {}", token))),
+        )
+        .await;
+        assert_eq!(body["user"]["username"], "user");
+        Ok(())
     }
 }
@@ -0,0 +1,96 @@
+//! # Project Badges
+//!
+//! SVG badges for the projects (mounted under `/api/projects`). Badges are only
+//! available without authentication when the `badges.public` project setting is enabled.
+
+use konarr::{
+    models::{self, ProjectSetting, ProjectSettings},
+    utils::badges::{Badge, BadgeStyle},
+};
+use rocket::{http::Header, State};
+
+use crate::{error::KonarrServerError, guards::Session, AppState};
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![get_alerts_badge, get_dependencies_badge]
+}
+
+/// SVG badge response
+///
+/// Badges are cached for a few minutes so proxies (GitHub's camo) refresh them.
+#[derive(Responder)]
+#[response(content_type = "image/svg+xml")]
+pub(crate) struct BadgeResp {
+    svg: String,
+    cache_control: Header<'static>,
+}
+
+impl BadgeResp {
+    fn new(badge: Badge, style: Option<&str>) -> Self {
+        Self {
+            svg: badge.render(style.map(BadgeStyle::from).unwrap_or_default()),
+            cache_control: Header::new("Cache-Control", "max-age=300, s-maxage=300"),
+        }
+    }
+}
+
+/// Latest snapshot (with metadata) of the project, checking access to its badges
+async fn badge_snapshot(
+    state: &State<AppState>,
+    session: Option<Session>,
+    id: i32,
+) -> Result<Option<models::Snapshot>, KonarrServerError> {
+    let mut project = models::Projects::fetch_by_primary_key(&state.connection, id)
+        .await
+        .map_err(|_| KonarrServerError::ProjectNotFoundError(id))?;
+    if project.status == models::ProjectStatus::Archived {
+        return Err(KonarrServerError::ProjectNotFoundError(id));
+    }
+
+    if session.is_none()
+        && !ProjectSettings::get_bool(&state.connection, id, ProjectSetting::BadgesPublic).await?
+    {
+        return Err(KonarrServerError::Unauthorized);
+    }
+
+    Ok(project
+        .refresh_latest_snapshot(&state.connection)
+        .await?
+        .cloned())
+}
+
+/// Alerts badge (coloured by the highest severity)
+#[get("/<id>/badge/alerts.svg?<style>")]
+pub(crate) async fn get_alerts_badge(
+    state: &State<AppState>,
+    session: Option<Session>,
+    id: i32,
+    style: Option<&str>,
+) -> Result<BadgeResp, KonarrServerError> {
+    let badge = match badge_snapshot(state, session, id).await? {
+        Some(snapshot) => Badge::alerts(
+            snapshot.find_metadata_usize("security.alerts.total") as u32,
+            snapshot.find_metadata_usize("security.alerts.critical") as u32,
+            snapshot.find_metadata_usize("security.alerts.high") as u32,
+        ),
+        None => Badge::unknown("alerts"),
+    };
+    Ok(BadgeResp::new(badge, style))
+}
+
+/// Dependencies badge
+#[get("/<id>/badge/dependencies.svg?<style>")]
+pub(crate) async fn get_dependencies_badge(
+    state: &State<AppState>,
+    session: Option<Session>,
+    id: i32,
+    style: Option<&str>,
+) -> Result<BadgeResp, KonarrServerError> {
+    let badge = match badge_snapshot(state, session, id).await? {
+        Some(snapshot) => {
+            Badge::dependencies(snapshot.find_metadata_usize("dependencies.total") as u32)
+        }
+        None => Badge::unknown("dependencies"),
+    };
+    Ok(BadgeResp::new(badge, style))
+}
@@ -1,10 +1,14 @@
 use konarr::{
-    models::settings::{find_statistic, keys::Setting, ServerSettings},
+    models::{
+        settings::{find_statistic, keys::Setting, ServerSettings, SettingNamespace},
+        ComponentTags, COMPONENT_MANAGERS,
+    },
     KONARR_VERSION,
 };
 use rocket::{serde::json::Json, State};
+use std::collections::BTreeMap;
 
-use crate::{guards::Session, AppState};
+use crate::{cache::CacheKey, error::KonarrServerError, guards::Session, AppState};
 
 use super::ApiResult;
 
@@ -17,6 +21,8 @@ pub struct BaseResponse {
     pub commit: String,
     /// Base configuration
     pub config: ConfigResponse,
+    /// Server is in maintenance mode (read-only, agents skip their uploads)
+    pub maintenance: bool,
     /// Current User
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<UserResponse>,
@@ -32,6 +38,36 @@ pub struct BaseResponse {
     /// Agent Settings
     #[serde(skip_serializing_if = "Option::is_none")]
     pub agent: Option<AgentResponse>,
+    /// Build information (authenticated users, unless `build.public` is enabled)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build: Option<BuildResponse>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct BuildResponse {
+    /// Commit SHA of the build
+    pub commit: String,
+    /// Date of the build
+    pub date: String,
+    /// Version of the Rust compiler
+    pub rustc: String,
+    /// Enabled cargo features
+    pub features: Vec<String>,
+}
+
+impl Default for BuildResponse {
+    fn default() -> Self {
+        Self {
+            commit: env!("KONARR_GIT_COMMIT").to_string(),
+            date: env!("KONARR_BUILD_DATE").to_string(),
+            rustc: env!("KONARR_RUSTC_VERSION").to_string(),
+            features: konarr::build_features()
+                .into_iter()
+                .map(|f| f.to_string())
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -61,6 +97,10 @@ pub struct ProjectsSummary {
     pub total: u64,
     pub servers: u64,
     pub containers: u64,
+    /// Containers running an image which has not been scanned
+    pub stale: u64,
+    /// Active projects by health (`green`, `amber` and `red`)
+    pub health: BTreeMap<String, u64>,
 }
 
 #[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
@@ -83,6 +123,12 @@ pub struct DependenciesSummary {
     #[serde(rename = "operating-environments")]
     pub operating_environments: u64,
     pub middleware: u64,
+    /// Dependencies by package manager (ecosystem)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub managers: BTreeMap<String, u64>,
+    /// Dependencies by custom tag
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, u64>,
 }
 
 #[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
@@ -98,6 +144,10 @@ pub struct SecuritySummary {
     pub malware: u64,
     pub unmaintained: u64,
     pub unknown: u64,
+    /// Alerts resolved by a new snapshot (all time)
+    pub resolved: u64,
+    /// Alerts resolved in the last 30 days
+    pub resolved_recent: u64,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -109,6 +159,12 @@ pub struct AgentResponse {
     pub auto_install: bool,
     /// Auto-update setting
     pub auto_update: bool,
+    /// Containers the agents do not scan (names or images, globs)
+    pub exclude: Vec<String>,
+    /// Minutes between two runs in monitoring mode
+    pub interval: u32,
+    /// Days before a cached SBOM is rescanned
+    pub cache_expires: u32,
 }
 
 #[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
@@ -132,17 +188,25 @@ impl Default for BaseResponse {
                 initialised: true,
                 registration: true,
             },
+            maintenance: false,
             user: None,
             projects: None,
             dependencies: None,
             security: None,
             agent: None,
+            build: None,
         }
     }
 }
 
-#[get("/")]
-pub async fn base(state: &State<AppState>, session: Option<Session>) -> ApiResult<BaseResponse> {
+/// Server info (the summaries are cached, `fresh=true` bypasses the cache)
+#[get("/?<fresh>")]
+pub async fn base(
+    state: &State<AppState>,
+    session: Option<Session>,
+    fresh: Option<bool>,
+) -> ApiResult<BaseResponse> {
+    let fresh = fresh.unwrap_or(false);
     let init: bool = ServerSettings::fetch_by_name(&state.connection, Setting::Initialized)
         .await?
         .boolean();
@@ -152,17 +216,27 @@ pub async fn base(state: &State<AppState>, session: Option<Session>) -> ApiResul
             .boolean();
 
     if let Some(session) = &session {
-        let stats = ServerSettings::fetch_statistics(&state.connection).await?;
-
-        let security: Option<SecuritySummary> =
-            if ServerSettings::get_bool(&state.connection, Setting::Security).await? {
-                let security_counts =
-                    ServerSettings::get_namespace(&state.connection, "security.alerts").await?;
+        let (projects, dependencies) = server_info(state, fresh).await?;
+        let security: Option<SecuritySummary> = state
+            .cache
+            .get_or_load(CacheKey::SecuritySummary, fresh, || async {
+                if !ServerSettings::get_bool(&state.connection, Setting::Security).await? {
+                    return Ok(None);
+                }
+                let stats = statistics(state, fresh).await?;
+                let security_counts = ServerSettings::get_namespace(
+                    &state.connection,
+                    SettingNamespace::SecurityAlerts,
+                )
+                .await?;
 
-                Some(SecuritySummary::from(security_counts))
-            } else {
-                None
-            };
+                Ok(Some(SecuritySummary {
+                    resolved: find_statistic(&stats, Setting::StatsAlertsResolved),
+                    resolved_recent: find_statistic(&stats, Setting::StatsAlertsResolvedRecent),
+                    ..SecuritySummary::from(security_counts)
+                }))
+            })
+            .await?;
 
         let agent: Option<AgentResponse> = if session.user.username == "konarr-agent" {
             Some(AgentResponse {
@@ -183,6 +257,28 @@ pub async fn base(state: &State<AppState>, session: Option<Session>) -> ApiResul
                 )
                 .await?
                 .boolean(),
+                exclude: ServerSettings::fetch_by_name(&state.connection, Setting::AgentExclude)
+                    .await?
+                    .value
+                    .split(',')
+                    .map(|pattern| pattern.trim().to_string())
+                    .filter(|pattern| !pattern.is_empty())
+                    .collect(),
+                interval: ServerSettings::fetch_by_name(&state.connection, Setting::AgentInterval)
+                    .await?
+                    .value
+                    .trim()
+                    .parse()
+                    .unwrap_or(1),
+                cache_expires: ServerSettings::fetch_by_name(
+                    &state.connection,
+                    Setting::AgentCacheExpires,
+                )
+                .await?
+                .value
+                .trim()
+                .parse()
+                .unwrap_or(7),
             })
         } else {
             None
@@ -198,29 +294,98 @@ pub async fn base(state: &State<AppState>, session: Option<Session>) -> ApiResul
                 avatar: None,
                 role: session.user.role.to_string(),
             }),
-            projects: Some(ProjectsSummary {
-                total: find_statistic(&stats, Setting::StatsProjectsTotal),
-                containers: find_statistic(&stats, Setting::StatsProjectsContainers),
-                servers: find_statistic(&stats, Setting::StatsProjectsServers),
-                ..Default::default()
-            }),
-            dependencies: Some(DependenciesSummary::from(stats)),
+            projects: Some(projects),
+            dependencies: Some(dependencies),
             security,
             agent,
+            build: Some(BuildResponse::default()),
+            maintenance: state.maintenance.enabled(),
             ..Default::default()
         }))
     } else {
         info!("No Active Session");
+        let build = ServerSettings::get_bool(&state.connection, Setting::BuildPublic)
+            .await
+            .unwrap_or(false)
+            .then(BuildResponse::default);
+
         Ok(Json(BaseResponse {
             config: ConfigResponse {
                 initialised: !init,
                 registration,
             },
+            build,
+            maintenance: state.maintenance.enabled(),
             ..Default::default()
         }))
     }
 }
 
+/// Statistics settings (cached)
+async fn statistics(
+    state: &AppState,
+    fresh: bool,
+) -> Result<Vec<ServerSettings>, KonarrServerError> {
+    state
+        .cache
+        .get_or_load(CacheKey::Statistics, fresh, || async {
+            Ok(ServerSettings::fetch_statistics(&state.connection).await?)
+        })
+        .await
+}
+
+/// Projects and dependencies summaries of the server info (cached)
+async fn server_info(
+    state: &AppState,
+    fresh: bool,
+) -> Result<(ProjectsSummary, DependenciesSummary), KonarrServerError> {
+    state
+        .cache
+        .get_or_load(CacheKey::ServerInfo, fresh, || async {
+            let stats = statistics(state, fresh).await?;
+            let projects = ProjectsSummary {
+                total: find_statistic(&stats, Setting::StatsProjectsTotal),
+                containers: find_statistic(&stats, Setting::StatsProjectsContainers),
+                servers: find_statistic(&stats, Setting::StatsProjectsServers),
+                stale: find_statistic(&stats, Setting::StatsProjectsStale),
+                health: BTreeMap::from([
+                    (
+                        "green".to_string(),
+                        find_statistic(&stats, Setting::StatsProjectsHealthGreen),
+                    ),
+                    (
+                        "amber".to_string(),
+                        find_statistic(&stats, Setting::StatsProjectsHealthAmber),
+                    ),
+                    (
+                        "red".to_string(),
+                        find_statistic(&stats, Setting::StatsProjectsHealthRed),
+                    ),
+                ]),
+            };
+            let dependencies = DependenciesSummary {
+                managers: COMPONENT_MANAGERS
+                    .iter()
+                    .map(|manager| {
+                        (
+                            manager.to_string(),
+                            find_statistic(&stats, manager.statistic()),
+                        )
+                    })
+                    .filter(|(_, count)| *count > 0)
+                    .collect(),
+                tags: ComponentTags::counts(&state.connection)
+                    .await?
+                    .into_iter()
+                    .map(|(tag, count)| (tag, count as u64))
+                    .collect(),
+                ..DependenciesSummary::from(stats)
+            };
+            Ok((projects, dependencies))
+        })
+        .await
+}
+
 impl From<Vec<ServerSettings>> for SecuritySummary {
     fn from(value: Vec<ServerSettings>) -> Self {
         let mut summary = SecuritySummary::default();
@@ -296,3 +461,97 @@ impl From<String> for AgentTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::ResponseCache;
+    use crate::guards::{maintenance::Maintenance, AgentTokenCache, SessionCache};
+    use rocket::{
+        http::{Header, Status},
+        local::asynchronous::Client,
+    };
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    };
+    use tokio::sync::Mutex;
+
+    static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+    #[tokio::test]
+    async fn test_base_cache() -> Result<(), konarr::KonarrError> {
+        let connection = libsql::Builder::new_local(":memory:")
+            .build()
+            .await?
+            .connect()?;
+        konarr::models::seed::seed(&connection, konarr::models::seed::SeedProfile::Demo).await?;
+        let (_, session) = konarr::models::Users::login(
+            &connection,
+            "demo-user",
+            konarr::models::seed::SEED_PASSWORD,
+        )
+        .await?;
+        ServerSettings::fetch_by_name(&connection, Setting::StatsProjectsTotal)
+            .await?
+            .set_update(&connection, "3")
+            .await?;
+
+        let connection = Arc::new(Mutex::new(connection));
+        let cache = Arc::new(ResponseCache::with_generation(|| {
+            GENERATION.load(Ordering::Relaxed)
+        }));
+        let state = AppState {
+            connection: Arc::clone(&connection),
+            sessions: Arc::new(RwLock::new(SessionCache::default())),
+            agent_tokens: Arc::new(RwLock::new(AgentTokenCache::new(String::new()))),
+            config: konarr::Config::default(),
+            init: true,
+            maintenance: Maintenance::default(),
+            cache: Arc::clone(&cache),
+            checks: Default::default(),
+        };
+        let rocket = rocket::build().manage(state).mount("/api", routes![base]);
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+        let total = |uri: &'static str| {
+            let client = &client;
+            let token = session.token.clone();
+            async move {
+                let response = client
+                    .get(uri)
+                    .header(Header::new("Authorization", format!("Bearer {}", token)))
+                    .dispatch()
+                    .await;
+                assert_eq!(response.status(), Status::Ok);
+                let body: serde_json::Value = response.into_json().await.unwrap();
+                body["projects"]["total"].as_u64().unwrap()
+            }
+        };
+
+        assert_eq!(total("/api").await, 3);
+        let loads = cache.loads();
+        assert!(loads > 0);
+
+        // Second request within the TTL does not hit the database
+        ServerSettings::fetch_by_name(&connection, Setting::StatsProjectsTotal)
+            .await?
+            .set_update(&connection, "4")
+            .await?;
+        assert_eq!(total("/api").await, 3);
+        assert_eq!(cache.loads(), loads);
+
+        // Bypassed for debugging
+        assert_eq!(total("/api?fresh=true").await, 4);
+        assert!(cache.loads() > loads);
+
+        // Invalidated when the statistics / alerts are recalculated
+        ServerSettings::fetch_by_name(&connection, Setting::StatsProjectsTotal)
+            .await?
+            .set_update(&connection, "5")
+            .await?;
+        assert_eq!(total("/api").await, 4);
+        GENERATION.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(total("/api").await, 5);
+        Ok(())
+    }
+}
@@ -1,13 +1,42 @@
 use geekorm::prelude::*;
 
-use konarr::models;
+use konarr::{
+    bom::sbom::BomEvidence,
+    models::{self, ComponentAnnotations, ComponentTags, ComponentType, UserRole},
+};
+use log::info;
 use rocket::{serde::json::Json, State};
+use std::collections::BTreeMap;
 
-use super::{projects::ProjectResp, ApiResponse, ApiResult};
-use crate::{guards::Session, AppState};
+use super::{projects::ProjectResp, security::AlertResp, ApiResponse, ApiResult};
+use crate::{
+    error::KonarrServerError,
+    guards::{Session, SnapshotAccess},
+    AppState,
+};
 
 pub fn routes() -> Vec<rocket::Route> {
-    routes![get_dependency, get_dependencies]
+    routes![
+        get_dependency,
+        get_dependency_alerts,
+        get_dependency_versions,
+        get_dependency_tags,
+        get_dependencies,
+        get_autocomplete,
+        get_dependency_stats,
+        // GET / POST / PATCH / DELETE /dependencies/<id>/annotations
+        get_annotations,
+        create_annotation,
+        update_annotation,
+        delete_annotation,
+    ]
+}
+
+/// Parse the `component_type` query parameter (unknown types are a 422)
+pub(crate) fn parse_component_type(value: &str) -> Result<ComponentType, KonarrServerError> {
+    ComponentType::parse(value).ok_or_else(|| {
+        KonarrServerError::InvalidQuery(format!("Unknown component type `{}`", value))
+    })
 }
 
 #[derive(Default, serde::Serialize, serde::Deserialize)]
@@ -16,9 +45,13 @@ pub(crate) struct DependencyResp {
     id: i32,
     r#type: String,
     manager: String,
+    /// Ecosystem of the package manager (`os`, `language` or `other`)
+    ecosystem: String,
     name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     namespace: Option<String>,
+    /// Full coordinate of the package (`group:artifact`, `@scope/name`, `namespace/name`)
+    display_name: String,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     purl: Option<String>,
@@ -29,8 +62,78 @@ pub(crate) struct DependencyResp {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     versions: Vec<String>,
 
+    /// Scope of the dependency (if provided by the SBOM)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    /// Direct dependency of the snapshot (if the SBOM has dependency graph data)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direct: Option<bool>,
+    /// Evidence of how the dependency was found (if the SBOM provided any)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    evidence: Option<BomEvidence>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     projects: Option<Vec<ProjectResp>>,
+
+    /// Number of annotations of the component
+    #[serde(default)]
+    annotation_count: i64,
+    /// Annotations of the component (only for a single dependency)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<AnnotationResp>,
+    /// Custom tags of the component (only for a single dependency)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct AnnotationResp {
+    id: i32,
+    component_id: i32,
+    /// Project the annotation applies to (all projects if not set)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<i32>,
+    author: String,
+    /// Raw text (markdown) of the annotation
+    text: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct AnnotationReq {
+    /// Text (markdown) of the annotation
+    text: String,
+    /// Project the annotation applies to (ignored on update)
+    project: Option<i32>,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct DependencyVersionsResp {
+    id: i32,
+    display_name: String,
+    purl: String,
+    /// Versions in the latest snapshot of the projects (newest first)
+    versions: Vec<DependencyVersionResp>,
+    /// Newest version without alerts (or with the fewest alerts)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recommended: Option<String>,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct DependencyVersionResp {
+    id: i32,
+    version: String,
+    projects: i64,
+    alerts: i64,
+    /// Highest severity of the alerts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    severity: Option<String>,
+    severities: BTreeMap<String, i64>,
 }
 
 #[derive(Default, serde::Serialize, serde::Deserialize)]
@@ -39,15 +142,50 @@ pub(crate) struct DependencySummaryResp {
     pub count: i32,
 }
 
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct DependencyAutocompleteResp {
+    id: i32,
+    purl: String,
+    r#type: String,
+    projects: i64,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct DependencyStatsResp {
+    total: i64,
+    managers: BTreeMap<String, i64>,
+    types: BTreeMap<String, i64>,
+}
+
+/// Fetch a snapshot the session can read (denied snapshots are not found)
+async fn fetch_snapshot(
+    state: &State<AppState>,
+    session: &Session,
+    id: i32,
+) -> Result<models::Snapshot, KonarrServerError> {
+    let snapshot = models::Snapshot::fetch_by_primary_key(&state.connection, id)
+        .await
+        .map_err(|_| KonarrServerError::SnapshotNotFoundError(id))?;
+    session
+        .authorize_snapshot(&state.connection, &snapshot, SnapshotAccess::Read)
+        .await?;
+    Ok(snapshot)
+}
+
 /// Get single Dependency by ID
+///
+/// Without a snapshot, only the projects the session can access are listed.
 #[get("/<id>?<snapshot>")]
 pub(crate) async fn get_dependency(
     state: &State<AppState>,
-    _session: Session,
+    session: Session,
     id: i32,
     snapshot: Option<u32>,
 ) -> ApiResult<DependencyResp> {
     if let Some(snapshot_id) = snapshot {
+        fetch_snapshot(state, &session, snapshot_id as i32).await?;
         let mut dep = models::Dependencies::fetch_dependency_by_snapshot(
             &state.connection,
             snapshot_id as i32,
@@ -56,17 +194,25 @@ pub(crate) async fn get_dependency(
         .await?;
         dep.fetch(&state.connection).await?;
 
-        Ok(Json(dep.into()))
+        let mut resp: DependencyResp = dep.into();
+        resp.set_annotations(state, None).await?;
+        Ok(Json(resp))
     } else {
         let mut dep = models::Component::fetch_by_primary_key(&state.connection, id).await?;
         dep.fetch(&state.connection).await?;
 
-        let projects: Vec<ProjectResp> =
-            models::Projects::find_project_by_component(&state.connection, dep.id.into())
-                .await?
-                .iter()
-                .map(|p| p.clone().into())
-                .collect();
+        let mut projects: Vec<ProjectResp> = Vec::new();
+        for project in
+            models::Projects::find_project_by_component(&state.connection, dep.id.into()).await?
+        {
+            if session
+                .authorize_project(&state.connection, &project)
+                .await
+                .is_ok()
+            {
+                projects.push(project.into());
+            }
+        }
 
         let versions: Vec<String> =
             models::ComponentVersion::fetch_by_component_id(&state.connection, dep.id)
@@ -75,73 +221,376 @@ pub(crate) async fn get_dependency(
                 .map(|v| v.clone().version)
                 .collect();
 
-        Ok(Json(DependencyResp {
+        let mut resp = DependencyResp {
             id: dep.id.into(),
             r#type: dep.component_type.to_string(),
             manager: dep.manager.to_string(),
+            ecosystem: dep.manager.ecosystem().to_string(),
             name: dep.name.to_string(),
+            namespace: dep.namespace.clone(),
+            display_name: dep.display_name(),
             purl: Some(dep.purl()),
             projects: Some(projects),
             versions,
+            tags: ComponentTags::fetch_for_component(&state.connection, id).await?,
             ..Default::default()
-        }))
+        };
+        resp.set_annotations(state, None).await?;
+        Ok(Json(resp))
     }
 }
 
+/// Get all the alerts for a dependency (snapshot dependency row)
+#[get("/<id>/alerts")]
+pub(crate) async fn get_dependency_alerts(
+    state: &State<AppState>,
+    session: Session,
+    id: i32,
+) -> ApiResult<ApiResponse<AlertResp>> {
+    let dependency = models::Dependencies::fetch_by_primary_key(&state.connection, id).await?;
+    fetch_snapshot(state, &session, dependency.snapshot_id.key).await?;
+    let alerts =
+        models::security::Alerts::fetch_by_dependency(&state.connection, dependency.id).await?;
+    let total = alerts.len() as u64;
+
+    Ok(Json(ApiResponse::new(
+        alerts.into_iter().map(|a| a.into()).collect(),
+        total,
+        total,
+        total,
+    )))
+}
+
+/// Versions of a component used across the projects (latest snapshots) and the
+/// recommended version to pin to
+#[get("/<id>/versions")]
+pub(crate) async fn get_dependency_versions(
+    state: &State<AppState>,
+    _session: Session,
+    id: i32,
+) -> ApiResult<DependencyVersionsResp> {
+    let component = models::Component::fetch_by_primary_key(&state.connection, id).await?;
+    let versions = models::ComponentVersion::usage(&state.connection, id).await?;
+    let recommended =
+        models::ComponentVersion::recommended(&versions).map(|version| version.version.clone());
+
+    Ok(Json(DependencyVersionsResp {
+        id: component.id.into(),
+        display_name: component.display_name(),
+        purl: component.purl(),
+        versions: versions.into_iter().map(|v| v.into()).collect(),
+        recommended,
+    }))
+}
+
+/// Custom tags of a component
+#[get("/<id>/tags")]
+pub(crate) async fn get_dependency_tags(
+    state: &State<AppState>,
+    _session: Session,
+    id: i32,
+) -> ApiResult<Vec<String>> {
+    models::Component::fetch_by_primary_key(&state.connection, id).await?;
+    Ok(Json(
+        ComponentTags::fetch_for_component(&state.connection, id).await?,
+    ))
+}
+
 /// Get all Dependencies (components)
-#[get("/?<search>&<top>&<deptype>&<page>&<limit>")]
+#[get("/?<search>&<top>&<deptype>&<tag>&<page>&<limit>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn get_dependencies(
     state: &State<AppState>,
     _session: Session,
     search: Option<String>,
     top: Option<bool>,
     deptype: Option<String>,
+    tag: Option<String>,
     page: Option<u32>,
     limit: Option<u32>,
-) -> ApiResult<ApiResponse<Vec<DependencyResp>>> {
+) -> ApiResult<ApiResponse<DependencyResp>> {
     let page = Pagination::from((page, limit));
 
-    let deps = if let Some(search) = search {
-        models::Component::find_by_name(&state.connection, search, &page).await?
+    let total =
+        models::Component::row_count(&state.connection, models::Component::query_count().build()?)
+            .await? as u64;
+
+    let (deps, count) = if let Some(search) = search {
+        (
+            models::Component::find_by_name(&state.connection, &search, &page).await?,
+            models::Component::count_by_name(&state.connection, &search).await?,
+        )
+    } else if let Some(tag) = tag {
+        (
+            models::Component::find_by_tag(&state.connection, &tag, &page).await?,
+            models::Component::count_by_tag(&state.connection, &tag).await?,
+        )
     } else if let Some(dtyp) = deptype {
-        models::Component::find_by_component_type(
-            &state.connection,
-            models::ComponentType::from(dtyp),
-            &page,
+        let dtyp = models::ComponentType::from(dtyp);
+        (
+            models::Component::find_by_component_type(&state.connection, dtyp.clone(), &page)
+                .await?,
+            models::Component::count_of_component_type(&state.connection, dtyp).await?,
         )
-        .await?
     } else if top.unwrap_or(false) {
-        models::Component::top(&state.connection, &page).await?
+        (
+            models::Component::top(&state.connection, &page).await?,
+            models::Component::count_top(&state.connection).await?,
+        )
     } else {
         // Fetch all
-        models::Component::query(
-            &state.connection,
-            models::Component::query_select().page(&page).build()?,
+        (
+            models::Component::query(
+                &state.connection,
+                models::Component::query_select().page(&page).build()?,
+            )
+            .await?,
+            total,
         )
-        .await?
     };
 
-    let total: u32 =
-        models::Component::row_count(&state.connection, models::Component::query_count().build()?)
-            .await? as u32;
-    let pages = (total as f64 / page.limit() as f64).ceil() as u32;
+    let annotations = ComponentAnnotations::counts(&state.connection).await?;
 
     Ok(Json(ApiResponse::new(
-        deps.iter().map(|dep| dep.clone().into()).collect(),
+        deps.iter()
+            .map(|dep| {
+                let mut resp: DependencyResp = dep.clone().into();
+                resp.annotation_count = annotations.get(&resp.id).copied().unwrap_or_default();
+                resp
+            })
+            .collect(),
         total,
-        pages,
+        count,
+        page.limit() as u64,
     )))
 }
 
+/// Dependency statistics grouped by package manager and component type
+///
+/// Statistics are instance-wide unless a project (latest snapshot) or snapshot is provided.
+#[get("/stats?<project_id>&<snapshot_id>")]
+pub async fn get_dependency_stats(
+    state: &State<AppState>,
+    session: Session,
+    project_id: Option<u32>,
+    snapshot_id: Option<u32>,
+) -> ApiResult<DependencyStatsResp> {
+    let snapshot: Option<i32> = if let Some(snapshot_id) = snapshot_id {
+        Some(
+            fetch_snapshot(state, &session, snapshot_id as i32)
+                .await?
+                .id
+                .into(),
+        )
+    } else if let Some(project_id) = project_id {
+        let project_id = project_id as i32;
+        let project = models::Projects::fetch_by_primary_key(&state.connection, project_id)
+            .await
+            .map_err(|_| KonarrServerError::ProjectNotFoundError(project_id))?;
+        session
+            .authorize_project(&state.connection, &project)
+            .await
+            .map_err(|_| KonarrServerError::ProjectNotFoundError(project_id))?;
+        match project.fetch_latest_snapshot(&state.connection).await? {
+            Some(snap) => Some(snap.id.into()),
+            None => return Ok(Json(DependencyStatsResp::default())),
+        }
+    } else {
+        None
+    };
+
+    let managers: BTreeMap<String, i64> =
+        models::Component::count_by_manager(&state.connection, snapshot)
+            .await?
+            .into_iter()
+            .map(|(manager, count)| (manager.to_string(), count))
+            .collect();
+    let types: BTreeMap<String, i64> =
+        models::Component::count_by_component_type(&state.connection, snapshot)
+            .await?
+            .into_iter()
+            .map(|(ctype, count)| (ctype.to_string(), count))
+            .collect();
+
+    Ok(Json(DependencyStatsResp {
+        total: managers.values().sum(),
+        managers,
+        types,
+    }))
+}
+
+/// Autocomplete Dependencies (components) by name prefix
+#[get("/autocomplete?<q>&<limit>")]
+pub async fn get_autocomplete(
+    state: &State<AppState>,
+    _session: Session,
+    q: String,
+    limit: Option<u32>,
+) -> ApiResult<Vec<DependencyAutocompleteResp>> {
+    let limit = limit.unwrap_or(10).clamp(1, 50) as usize;
+
+    let comps = models::Component::autocomplete(&state.connection, q.trim(), limit).await?;
+
+    let ids: Vec<i32> = comps.iter().map(|comp| comp.id.into()).collect();
+    let projects = models::Component::project_counts(&state.connection, &ids).await?;
+
+    Ok(Json(
+        comps
+            .into_iter()
+            .map(|comp| {
+                let id: i32 = comp.id.into();
+                DependencyAutocompleteResp {
+                    id,
+                    purl: comp.purl(),
+                    r#type: comp.component_type.to_string(),
+                    projects: projects.get(&id).copied().unwrap_or_default(),
+                }
+            })
+            .collect(),
+    ))
+}
+
+/// Annotations of a component (global and, if provided, the annotations of a project)
+#[get("/<id>/annotations?<project>")]
+pub(crate) async fn get_annotations(
+    state: &State<AppState>,
+    _session: Session,
+    id: i32,
+    project: Option<i32>,
+) -> ApiResult<Vec<AnnotationResp>> {
+    Ok(Json(
+        ComponentAnnotations::fetch_for_component(&state.connection, id, project)
+            .await?
+            .into_iter()
+            .map(|a| a.into())
+            .collect(),
+    ))
+}
+
+/// Annotate a component
+#[post("/<id>/annotations", data = "<data>", format = "json")]
+pub(crate) async fn create_annotation(
+    state: &State<AppState>,
+    session: Session,
+    id: i32,
+    data: Json<AnnotationReq>,
+) -> ApiResult<AnnotationResp> {
+    if session.agent.is_some() || session.user.role == UserRole::Agent {
+        return Err(KonarrServerError::Unauthorized);
+    }
+    let data = data.into_inner();
+    let annotation = ComponentAnnotations::create(
+        &state.connection,
+        id,
+        data.project,
+        session.user.username.clone(),
+        data.text,
+    )
+    .await?;
+    info!("Component({}) annotated by User({})", id, session.user.id);
+    Ok(Json(annotation.into()))
+}
+
+/// Update the text of an annotation (author or admin)
+#[patch("/<id>/annotations/<annotation_id>", data = "<data>", format = "json")]
+pub(crate) async fn update_annotation(
+    state: &State<AppState>,
+    session: Session,
+    id: i32,
+    annotation_id: i32,
+    data: Json<AnnotationReq>,
+) -> ApiResult<AnnotationResp> {
+    let mut annotation = fetch_annotation(state, &session, id, annotation_id).await?;
+    annotation
+        .set_text(&state.connection, data.into_inner().text)
+        .await?;
+    Ok(Json(annotation.into()))
+}
+
+/// Delete an annotation (author or admin)
+#[delete("/<id>/annotations/<annotation_id>")]
+pub(crate) async fn delete_annotation(
+    state: &State<AppState>,
+    session: Session,
+    id: i32,
+    annotation_id: i32,
+) -> ApiResult<AnnotationResp> {
+    let annotation = fetch_annotation(state, &session, id, annotation_id).await?;
+    annotation.delete(&state.connection).await?;
+    info!(
+        "Component({}) annotation {} deleted by User({})",
+        id, annotation_id, session.user.id
+    );
+    Ok(Json(annotation.into()))
+}
+
+/// Fetch an annotation of a component the user can modify
+async fn fetch_annotation(
+    state: &State<AppState>,
+    session: &Session,
+    component_id: i32,
+    annotation_id: i32,
+) -> Result<ComponentAnnotations, KonarrServerError> {
+    let annotation =
+        ComponentAnnotations::fetch_by_primary_key(&state.connection, annotation_id).await?;
+    if annotation.component_id != component_id {
+        return Err(KonarrServerError::DependencyNotFoundError(component_id));
+    }
+    if session.agent.is_some()
+        || !annotation.can_modify(&session.user.username, session.user.role == UserRole::Admin)
+    {
+        return Err(KonarrServerError::Unauthorized);
+    }
+    Ok(annotation)
+}
+
+impl DependencyResp {
+    /// Add the annotations (and their count) of the component
+    async fn set_annotations(
+        &mut self,
+        state: &State<AppState>,
+        project: Option<i32>,
+    ) -> Result<(), KonarrServerError> {
+        self.annotations =
+            ComponentAnnotations::fetch_for_component(&state.connection, self.id, project)
+                .await?
+                .into_iter()
+                .map(|a| a.into())
+                .collect();
+        self.annotation_count = self.annotations.len() as i64;
+        Ok(())
+    }
+}
+
+impl From<ComponentAnnotations> for AnnotationResp {
+    fn from(annotation: ComponentAnnotations) -> Self {
+        AnnotationResp {
+            id: annotation.id.into(),
+            component_id: annotation.component_id,
+            project: annotation.project_id,
+            author: annotation.author,
+            text: annotation.text,
+            created_at: annotation.created_at,
+            updated_at: annotation.updated_at,
+        }
+    }
+}
+
 impl From<models::Dependencies> for DependencyResp {
     fn from(dep: models::Dependencies) -> Self {
         DependencyResp {
             id: dep.component_id().into(),
             r#type: dep.component_type().to_string(),
             manager: dep.manager().to_string(),
+            ecosystem: dep.ecosystem().to_string(),
             name: dep.name(),
+            namespace: dep.namespace(),
+            display_name: dep.display_name(),
             version: dep.version(),
             purl: Some(dep.purl()),
+            scope: dep.scope.clone(),
+            direct: dep.direct,
+            evidence: dep.evidence(),
             ..Default::default()
         }
     }
@@ -153,10 +602,144 @@ impl From<models::Component> for DependencyResp {
             id: comp.id.into(),
             r#type: comp.component_type.to_string(),
             manager: comp.manager.to_string(),
+            ecosystem: comp.manager.ecosystem().to_string(),
             name: comp.name.to_string(),
+            namespace: comp.namespace.clone(),
+            display_name: comp.display_name(),
             version: None,
             purl: Some(comp.purl()),
             ..Default::default()
         }
     }
 }
+
+impl From<models::ComponentVersionUsage> for DependencyVersionResp {
+    fn from(usage: models::ComponentVersionUsage) -> Self {
+        DependencyVersionResp {
+            id: usage.version_id,
+            severity: usage.severity().map(|s| s.to_string()),
+            severities: usage
+                .severities
+                .iter()
+                .map(|(severity, count)| (severity.to_string(), *count))
+                .collect(),
+            version: usage.version,
+            projects: usage.projects,
+            alerts: usage.alerts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guards::{maintenance::Maintenance, AgentTokenCache, SessionCache};
+    use konarr::models::{AgentTokens, ProjectType, Projects};
+    use rocket::{
+        http::{Header, Status},
+        local::asynchronous::Client,
+    };
+    use std::sync::{Arc, RwLock};
+    use tokio::sync::Mutex;
+
+    async fn client(connection: libsql::Connection) -> Client {
+        let state = AppState {
+            connection: Arc::new(Mutex::new(connection)),
+            sessions: Arc::new(RwLock::new(SessionCache::default())),
+            agent_tokens: Arc::new(RwLock::new(AgentTokenCache::new(String::new()))),
+            config: konarr::Config::default(),
+            init: true,
+            maintenance: Maintenance::default(),
+            cache: Default::default(),
+            checks: Default::default(),
+        };
+        let rocket = rocket::build()
+            .manage(state)
+            .mount("/api/dependencies", routes());
+        Client::tracked(rocket).await.expect("valid rocket")
+    }
+
+    /// Snapshot of a project with a single dependency (returns the snapshot and dependency)
+    async fn snapshot_with(
+        connection: &libsql::Connection,
+        project: &mut Projects,
+        component: &models::Component,
+    ) -> Result<(models::Snapshot, models::Dependencies), konarr::KonarrError> {
+        let snapshot = models::Snapshot::create(connection).await?;
+        let mut version = models::ComponentVersion::new(component.id, "1.0.0".to_string());
+        version.save(connection).await?;
+        let mut dependency = models::Dependencies::new(snapshot.id, component.id, version.id);
+        dependency.save(connection).await?;
+        project.add_snapshot(connection, snapshot.clone()).await?;
+        Ok((snapshot, dependency))
+    }
+
+    #[tokio::test]
+    async fn test_agent_token_scope() -> Result<(), konarr::KonarrError> {
+        let connection = crate::api::database_test().await?;
+
+        let mut group = Projects::new("ci", ProjectType::Group);
+        group.save(&connection).await?;
+        let mut web = Projects::new("ci/web", ProjectType::Container);
+        web.parent = group.id.into();
+        web.save(&connection).await?;
+        let mut other = Projects::new("homelab/db", ProjectType::Container);
+        other.save(&connection).await?;
+
+        let (mut component, _) = models::Component::from_purl("pkg:npm/react@1.0.0")?;
+        component.find_or_create(&connection).await?;
+        let (inside, inside_dep) = snapshot_with(&connection, &mut web, &component).await?;
+        let (outside, outside_dep) = snapshot_with(&connection, &mut other, &component).await?;
+
+        let (_, token) =
+            AgentTokens::create(&connection, "ci", None, Some(group.id.into())).await?;
+        let client = client(connection).await;
+        let get = |path: String| {
+            client
+                .get(path)
+                .header(Header::new("Authorization", token.clone()))
+                .dispatch()
+        };
+
+        // Dependency of a snapshot
+        let path = |snapshot: &models::Snapshot| {
+            format!(
+                "/api/dependencies/{}?snapshot={}",
+                component.id, snapshot.id
+            )
+        };
+        assert_eq!(get(path(&inside)).await.status(), Status::Ok);
+        assert_eq!(get(path(&outside)).await.status(), Status::NotFound);
+
+        // Only the projects in the scope are listed
+        let response = get(format!("/api/dependencies/{}", component.id)).await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        let projects: Vec<&str> = body["projects"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(projects, vec!["ci/web"]);
+
+        // Alerts of a snapshot dependency
+        let path = |dependency: &models::Dependencies| {
+            format!("/api/dependencies/{}/alerts", dependency.id)
+        };
+        assert_eq!(get(path(&inside_dep)).await.status(), Status::Ok);
+        assert_eq!(get(path(&outside_dep)).await.status(), Status::NotFound);
+
+        // Statistics of a snapshot or project
+        for (path, status) in [
+            (format!("snapshot_id={}", inside.id), Status::Ok),
+            (format!("snapshot_id={}", outside.id), Status::NotFound),
+            (format!("project_id={}", web.id), Status::Ok),
+            (format!("project_id={}", other.id), Status::NotFound),
+        ] {
+            let response = get(format!("/api/dependencies/stats?{}", path)).await;
+            assert_eq!(response.status(), status, "{}", path);
+        }
+        Ok(())
+    }
+}
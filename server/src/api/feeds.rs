@@ -0,0 +1,228 @@
+//! # Project Feeds
+//!
+//! Atom feed of the newly detected alerts of a project (mounted under `/api/projects`).
+//! Feed readers without cookie support authenticate with the feed token of the project
+//! (`?token=`) instead of a session.
+
+use konarr::{
+    models::{
+        self,
+        security::{events::ALERT_FEED_LIMIT, AlertEvents, AlertFeedEntry},
+        UserRole,
+    },
+    utils::feeds::{AtomEntry, AtomFeed},
+    Config,
+};
+use log::info;
+use rocket::{http::Header, serde::json::Json, State};
+
+use super::ApiResult;
+use crate::{error::KonarrServerError, guards::Session, AppState};
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![get_alerts_feed, regenerate_feed_token]
+}
+
+/// Atom feed response
+#[derive(Responder)]
+#[response(content_type = "application/atom+xml")]
+pub(crate) struct FeedResp {
+    xml: String,
+    cache_control: Header<'static>,
+}
+
+/// Feed token response
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct FeedTokenResp {
+    /// Feed token of the project
+    token: String,
+    /// URL of the feed (with the token)
+    url: String,
+}
+
+/// Alerts feed of the project (newest first)
+#[get("/<id>/alerts.atom?<token>")]
+pub(crate) async fn get_alerts_feed(
+    state: &State<AppState>,
+    session: Option<Session>,
+    id: i32,
+    token: Option<&str>,
+) -> Result<FeedResp, KonarrServerError> {
+    let project = models::Projects::fetch_by_primary_key(&state.connection, id)
+        .await
+        .map_err(|_| KonarrServerError::ProjectNotFoundError(id))?;
+    if project.status == models::ProjectStatus::Archived {
+        return Err(KonarrServerError::ProjectNotFoundError(id));
+    }
+    if !feed_access(&project, session.is_some(), token) {
+        return Err(KonarrServerError::Unauthorized);
+    }
+
+    let entries = AlertEvents::fetch_feed(&state.connection, id, ALERT_FEED_LIMIT).await?;
+    let feed = alerts_feed(&state.config, &project, token, entries)?;
+
+    Ok(FeedResp {
+        xml: feed.render(),
+        cache_control: Header::new("Cache-Control", "private, max-age=300"),
+    })
+}
+
+/// Regenerate the feed token of the project (the previous token is revoked)
+#[post("/<id>/feed/token")]
+pub(crate) async fn regenerate_feed_token(
+    state: &State<AppState>,
+    session: Session,
+    id: i32,
+) -> ApiResult<FeedTokenResp> {
+    if session.agent.is_some() || session.user.role == UserRole::Agent {
+        return Err(KonarrServerError::Unauthorized);
+    }
+    let mut project = models::Projects::fetch_by_primary_key(&state.connection, id)
+        .await
+        .map_err(|_| KonarrServerError::ProjectNotFoundError(id))?;
+
+    info!(
+        "Regenerating the feed token of Project({}) by {}",
+        id, session.user.username
+    );
+    let token = project.regenerate_feed_token(&state.connection).await?;
+    let url = feed_url(&state.config, id, Some(&token))?;
+    Ok(Json(FeedTokenResp { token, url }))
+}
+
+/// The feed can be read with a session or the feed token of the project
+fn feed_access(project: &models::Projects, session: bool, token: Option<&str>) -> bool {
+    session || token.is_some_and(|token| project.check_feed_token(token))
+}
+
+/// URL of the alerts feed of a project
+fn feed_url(config: &Config, id: i32, token: Option<&str>) -> Result<String, KonarrServerError> {
+    let mut url = config
+        .server
+        .url()?
+        .join(&format!("api/projects/{}/alerts.atom", id))
+        .map_err(|e| konarr::KonarrError::ConfigParseError(e.to_string()))?;
+    if let Some(token) = token {
+        url.query_pairs_mut().append_pair("token", token);
+    }
+    Ok(url.to_string())
+}
+
+/// Build the Atom feed from the alert events
+fn alerts_feed(
+    config: &Config,
+    project: &models::Projects,
+    token: Option<&str>,
+    entries: Vec<AlertFeedEntry>,
+) -> Result<AtomFeed, KonarrServerError> {
+    let id: i32 = project.id.into();
+    let base = match config.frontend_url()? {
+        Some(url) => url,
+        None => config.server.url()?,
+    };
+    let link = base
+        .join(&format!("projects/{}", id))
+        .map_err(|e| konarr::KonarrError::ConfigParseError(e.to_string()))?
+        .to_string();
+
+    Ok(AtomFeed {
+        id: link.clone(),
+        title: format!(
+            "Konarr - {} alerts",
+            project.title.clone().unwrap_or(project.name.clone())
+        ),
+        link: link.clone(),
+        self_link: feed_url(config, id, token)?,
+        updated: project.created_at,
+        entries: entries
+            .into_iter()
+            .map(|entry| AtomEntry {
+                id: format!("{}#alert-{}", link, entry.id),
+                title: match &entry.component {
+                    Some(component) => {
+                        format!("[{}] {} in {}", entry.severity, entry.advisory, component)
+                    }
+                    None => format!("[{}] {}", entry.severity, entry.advisory),
+                },
+                link: format!("{}#advisory-{}", link, entry.advisory_id),
+                updated: entry.created_at,
+                summary: Some(format!(
+                    "{} ({}) detected in {}{}",
+                    entry.advisory,
+                    entry.severity,
+                    project.name,
+                    entry
+                        .component
+                        .as_ref()
+                        .map(|c| format!(" ({})", c))
+                        .unwrap_or_default()
+                )),
+                categories: vec![entry.severity.to_string()],
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use konarr::models::{security::SecuritySeverity, ProjectType};
+
+    fn project() -> models::Projects {
+        let mut project = models::Projects::new("myhost/app", ProjectType::Container);
+        project.id = 1.into();
+        project.title = Some("App".to_string());
+        project
+    }
+
+    #[test]
+    fn test_feed_access() {
+        let mut project = project();
+        // Session (cookie) or no access without a feed token
+        assert!(feed_access(&project, true, None));
+        assert!(!feed_access(&project, false, None));
+        assert!(!feed_access(&project, false, Some("")));
+
+        project.feed_token = Some("konarr-feed-secret".to_string());
+        assert!(feed_access(&project, false, Some("konarr-feed-secret")));
+        assert!(!feed_access(&project, false, Some("konarr-feed-other")));
+        assert!(!feed_access(&project, false, None));
+    }
+
+    #[test]
+    fn test_alerts_feed() {
+        let config = Config::default();
+        let created_at = chrono::Utc::now();
+        let entries = vec![AlertFeedEntry {
+            id: 7,
+            advisory_id: 3,
+            advisory: "CVE-2024-0001".to_string(),
+            severity: SecuritySeverity::High,
+            component: Some("openssl@3.0.1".to_string()),
+            snapshot_id: Some(2),
+            created_at,
+        }];
+        let feed = alerts_feed(&config, &project(), Some("konarr-feed-secret"), entries).unwrap();
+
+        assert_eq!(feed.id, "http://localhost:9000/projects/1");
+        assert_eq!(
+            feed.self_link,
+            "http://localhost:9000/api/projects/1/alerts.atom?token=konarr-feed-secret"
+        );
+        assert_eq!(feed.last_updated(), created_at);
+        assert_eq!(feed.entries.len(), 1);
+        assert_eq!(
+            feed.entries[0].title,
+            "[High] CVE-2024-0001 in openssl@3.0.1"
+        );
+        assert_eq!(
+            feed.entries[0].id,
+            "http://localhost:9000/projects/1#alert-7"
+        );
+
+        let xml = feed.render();
+        assert!(xml.contains("<title>Konarr - App alerts</title>"));
+        assert!(xml.contains("<category term=\"High\"/>"));
+    }
+}
@@ -12,53 +12,44 @@ use crate::error::KonarrServerError;
 
 pub mod admin;
 pub mod auth;
+pub mod badges;
 pub mod base;
 pub mod dependencies;
+pub mod feeds;
 pub mod projects;
+pub mod public;
+pub mod search;
 pub mod security;
 pub mod snapshots;
 pub mod websock;
 
-/// API Response Wrapper
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-#[serde(crate = "rocket::serde")]
-pub struct ApiResponse<T>
-where
-    T: serde::Serialize,
-{
-    pub data: T,
-    /// Total amount
-    pub total: u32,
-    /// Count of the search results
-    pub count: u32,
-    /// Page count
-    pub pages: u32,
-}
-
-impl<T> ApiResponse<T>
-where
-    T: serde::Serialize,
-{
-    pub fn new(data: T, total: u32, pages: u32) -> Self {
-        Self {
-            data,
-            total,
-            count: total,
-            pages,
-        }
-    }
-}
+/// API Response Wrapper (shared with the Konarr client)
+pub use konarr::api::Pagination as ApiResponse;
 
 #[derive(Responder)]
 pub enum ApiErrorResponse {
+    #[response(status = 400, content_type = "json")]
+    BadRequest { inner: (Status, Json<ApiError>) },
     #[response(status = 401, content_type = "json")]
     Unauthorized { inner: (Status, Json<ApiError>) },
+    #[response(status = 403, content_type = "json")]
+    Forbidden { inner: (Status, Json<ApiError>) },
     #[response(status = 404, content_type = "json")]
     NotFound { inner: (Status, Json<ApiError>) },
+    #[response(status = 409, content_type = "json")]
+    Conflict { inner: (Status, Json<ApiError>) },
+    #[response(status = 413, content_type = "json")]
+    PayloadTooLarge { inner: (Status, Json<ApiError>) },
+    #[response(status = 422, content_type = "json")]
+    UnprocessableEntity { inner: (Status, Json<ApiError>) },
     #[response(status = 500, content_type = "json")]
     InternalServerError { inner: (Status, Json<ApiError>) },
     #[response(status = 429, content_type = "json")]
     TooManyRequests { inner: (Status, Json<ApiError>) },
+    #[response(status = 502, content_type = "json")]
+    BadGateway { inner: (Status, Json<ApiError>) },
+    #[response(status = 503, content_type = "json")]
+    ServiceUnavailable { inner: (Status, Json<ApiError>) },
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -68,66 +59,85 @@ pub struct ApiError {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub details: Option<String>,
     pub status: i16,
+    /// ID of the conflicting resource (if any)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+    /// Current state of the resource (if the request conflicts with it)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
 }
 
 pub type ApiResult<T> = Result<Json<T>, KonarrServerError>;
 
 impl<'r> Responder<'r, 'r> for KonarrServerError {
     fn respond_to(self, request: &'r Request<'_>) -> response::Result<'r> {
-        match self {
-            // Not Found
-            KonarrServerError::GeekOrmError(geekorm::Error::NoRowsFound)
-            | KonarrServerError::KonarrError(KonarrError::GeekOrm(geekorm::Error::NoRowsFound)) => {
-                ApiErrorResponse::NotFound {
-                    inner: (
-                        Status::NotFound,
-                        Json(ApiError {
-                            message: "Not Found".to_string(),
-                            details: Some(self.to_string()),
-                            status: 404,
-                        }),
-                    ),
-                }
-            }
-            // Unauthorized
-            KonarrServerError::Unauthorized
-            | KonarrServerError::KonarrError(KonarrError::AuthenticationError(_))
-            | KonarrServerError::KonarrError(KonarrError::Unauthorized) => {
-                ApiErrorResponse::Unauthorized {
-                    inner: (
-                        Status::Unauthorized,
-                        Json(ApiError {
-                            message: "Unauthorized".to_string(),
-                            details: Some(self.to_string()),
-                            status: 401,
-                        }),
-                    ),
-                }
+        let status = self.status_code();
+        let id = match &self {
+            KonarrServerError::ProjectExistsError { id, .. } => Some(*id),
+            _ => None,
+        };
+        let state = match &self {
+            KonarrServerError::SnapshotStateError { state, .. } => Some(state.clone()),
+            _ => None,
+        };
+        let retry_after = match &self {
+            KonarrServerError::KonarrError(KonarrError::RateLimited { retry_after }) => {
+                *retry_after
             }
-            _ => ApiErrorResponse::InternalServerError {
-                inner: (
-                    Status::InternalServerError,
-                    Json(ApiError {
-                        message: "Internal Server Error".to_string(),
-                        details: Some(self.to_string()),
-                        status: 500,
-                    }),
-                ),
-            },
+            error if error.is_database_busy() => Some(1),
+            _ => None,
+        };
+
+        let response = ApiErrorResponse::from(ApiError {
+            message: status_message(status),
+            details: Some(self.to_string()),
+            status: status as i16,
+            id,
+            state,
+        })
+        .respond_to(request)?;
+        match retry_after {
+            Some(seconds) => response::Response::build_from(response)
+                .raw_header("Retry-After", seconds.to_string())
+                .ok(),
+            None => Ok(response),
         }
-        .respond_to(request)
     }
 }
 
 impl From<ApiError> for ApiErrorResponse {
     fn from(value: ApiError) -> ApiErrorResponse {
         match value.status {
+            400 => ApiErrorResponse::BadRequest {
+                inner: (Status::BadRequest, Json(value)),
+            },
             401 => ApiErrorResponse::Unauthorized {
                 inner: (Status::Unauthorized, Json(value)),
             },
+            403 => ApiErrorResponse::Forbidden {
+                inner: (Status::Forbidden, Json(value)),
+            },
             404 => ApiErrorResponse::NotFound {
                 inner: (Status::NotFound, Json(value)),
             },
+            409 => ApiErrorResponse::Conflict {
+                inner: (Status::Conflict, Json(value)),
+            },
+            413 => ApiErrorResponse::PayloadTooLarge {
+                inner: (Status::PayloadTooLarge, Json(value)),
+            },
+            422 => ApiErrorResponse::UnprocessableEntity {
+                inner: (Status::UnprocessableEntity, Json(value)),
+            },
+            429 => ApiErrorResponse::TooManyRequests {
+                inner: (Status::TooManyRequests, Json(value)),
+            },
+            502 => ApiErrorResponse::BadGateway {
+                inner: (Status::BadGateway, Json(value)),
+            },
+            503 => ApiErrorResponse::ServiceUnavailable {
+                inner: (Status::ServiceUnavailable, Json(value)),
+            },
             _ => ApiErrorResponse::InternalServerError {
                 inner: (Status::InternalServerError, Json(value)),
             },
@@ -143,25 +153,92 @@ impl From<konarr::KonarrError> for ApiError {
         } else {
             None
         };
+        let status = value.status_code();
 
-        match value {
-            konarr::KonarrError::GeekOrm(geekorm::Error::NoRowsFound) => ApiError {
-                message: "Not Found".to_string(),
-                details,
-                status: 404,
-            },
-            konarr::KonarrError::Unauthorized | konarr::KonarrError::AuthenticationError(_) => {
-                ApiError {
-                    message: "Unauthorized".to_string(),
-                    details,
-                    status: 401,
-                }
-            }
-            _ => ApiError {
-                message: "Internal Server Error".to_string(),
-                details,
-                status: 500,
-            },
+        ApiError {
+            message: status_message(status),
+            details,
+            status: status as i16,
+            id: None,
+            state: None,
         }
     }
 }
+
+/// Message of the API errors (reason phrase of the status code)
+fn status_message(status: u16) -> String {
+    Status::from_code(status)
+        .and_then(|status| status.reason())
+        .unwrap_or("Internal Server Error")
+        .to_string()
+}
+
+/// Create a new in-memory database with all the tables (test fixture)
+#[cfg(test)]
+pub(crate) async fn database_test() -> Result<libsql::Connection, KonarrError> {
+    let connection = libsql::Builder::new_local(":memory:")
+        .build()
+        .await?
+        .connect()?;
+    konarr::models::database_create(&connection).await?;
+    Ok(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::serde::json::{self, Value};
+
+    /// Shared fixture with the Konarr client
+    const PAGINATION: &str = include_str!("../../../src/api/testdata/pagination.json");
+
+    #[derive(serde::Serialize)]
+    #[serde(crate = "rocket::serde")]
+    struct Item {
+        id: u32,
+    }
+
+    #[test]
+    fn test_api_response_wire_format() {
+        let response = ApiResponse::new(vec![Item { id: 1 }, Item { id: 2 }], 42u64, 12u64, 2);
+
+        let value = json::to_value(&response).unwrap();
+        let fixture: Value = json::from_str(PAGINATION).unwrap();
+        assert_eq!(value, fixture);
+    }
+
+    #[test]
+    fn test_api_error_status() {
+        let error = ApiError::from(KonarrError::InvalidData("bad".into()));
+        assert_eq!(error.status, 400);
+        assert_eq!(error.message, "Bad Request");
+
+        let error = ApiError::from(KonarrError::ProjectNotFound("web".into()));
+        assert_eq!((error.status, error.message.as_str()), (404, "Not Found"));
+
+        let error = ApiError::from(KonarrError::Disabled("advisories".into()));
+        assert_eq!(error.status, 503);
+        assert!(matches!(
+            ApiErrorResponse::from(error),
+            ApiErrorResponse::ServiceUnavailable { .. }
+        ));
+
+        assert_eq!(
+            KonarrServerError::ProjectNotFoundError(1).status_code(),
+            404
+        );
+        assert_eq!(
+            KonarrServerError::KonarrError(KonarrError::RateLimited { retry_after: None })
+                .status_code(),
+            429
+        );
+        assert_eq!(KonarrServerError::InternalServerError.status_code(), 500);
+        assert_eq!(
+            KonarrServerError::GeekOrmError(geekorm::Error::LibSQLError(
+                "SQLite failure: `database is locked`".to_string()
+            ))
+            .status_code(),
+            503
+        );
+    }
+}
@@ -1,12 +1,26 @@
 use geekorm::prelude::*;
-use konarr::models::{self, ProjectType};
-use log::info;
+use konarr::{
+    models::{
+        self,
+        security::{events::ALERTS_RESOLVED_RECENT_DAYS, ProjectHealth, SecuritySeverity},
+        ProjectChildren, ProjectChildrenSort, ProjectSettings, ProjectType, UserRole,
+    },
+    utils::{
+        containers::{parse_networks, ContainerPort},
+        names::{normalize_project_name, project_name_title},
+    },
+};
+use log::{debug, info};
 use rocket::{serde::json::Json, State};
+use std::collections::HashMap;
 
-use super::{security::SecuritySummary, ApiResponse, ApiResult};
+use super::{
+    security::{SecurityResolved, SecuritySummary},
+    ApiResponse, ApiResult,
+};
 use crate::{
     error::KonarrServerError,
-    guards::{AdminSession, Session},
+    guards::{AdminSession, Session, SnapshotAccess},
     AppState,
 };
 
@@ -20,6 +34,21 @@ pub fn routes() -> Vec<rocket::Route> {
         create_project,
         patch_project,
         delete_project,
+        // POST /projects/<id>/restore
+        restore_project,
+        // GET /projects/<id>/alerts/<advisory_id>/timeline
+        get_alert_timeline,
+        // GET / PATCH /projects/<id>/settings
+        get_project_settings,
+        update_project_settings,
+        // POST /projects/<id>/transfer
+        transfer_project,
+        // GET /projects/<id>/transfers
+        get_project_transfers,
+        // POST /projects/<id>/merge
+        merge_project,
+        // POST /projects/<id>/snapshots
+        link_project_snapshot,
     ]
 }
 
@@ -46,17 +75,119 @@ pub(crate) struct ProjectResp {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     security: Option<super::security::SecuritySummary>,
+    /// Health of the project from the alert thresholds (`green`, `amber` or `red`)
+    health: String,
+
+    /// Container runtime information (reported by the agent)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    container: Option<ContainerResp>,
 
     created_at: chrono::DateTime<chrono::Utc>,
+    /// Time the project was archived (archived projects only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archived_at: Option<chrono::DateTime<chrono::Utc>>,
 
     #[serde(skip_serializing_if = "Vec::is_empty")]
-    children: Vec<ProjectResp>,
+    children: Vec<ProjectChildResp>,
+
+    /// The title or description was edited by a user
+    edited_by_user: bool,
+
+    /// The running container image differs from the scanned image or the project was not
+    /// scanned in the last `scan.freshness.days` days
+    stale: bool,
+    /// Last time the project was scanned by the agent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_scanned_at: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// The latest snapshot was reported by an outdated agent
+    agent_outdated: bool,
+
+    /// Minimum number of hours between two scans (`scan.interval_hours` override, not set
+    /// when the agent's scan cycle is used)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scan_interval_hours: Option<u32>,
+
+    /// Metrics used by the filters (only set when filtering)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metrics: Option<ProjectMetricsResp>,
+}
+
+/// Child of a project (`children=summary` or `children=full`)
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged, crate = "rocket::serde")]
+pub(crate) enum ProjectChildResp {
+    Summary(ProjectChildSummaryResp),
+    Full(ProjectResp),
+}
+
+/// Summary of a child project (no snapshot)
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct ProjectChildSummaryResp {
+    id: i32,
+    name: String,
+    #[serde(rename = "type")]
+    project_type: String,
+    health: String,
+}
+
+/// Metrics of the latest snapshot used by the project filters
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct ProjectMetricsResp {
+    /// Number of alerts (of `severity_at_least` or more severe if set)
+    alerts: i64,
+    /// Number of dependencies
+    dependencies: i64,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct ContainerResp {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    health: Option<String>,
+    restart_count: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    started_at: Option<String>,
+    /// Seconds the container has been running for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uptime: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    ports: Vec<ContainerPortResp>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    networks: Vec<String>,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct ContainerPortResp {
+    protocol: String,
+    port: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host_ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host_port: Option<u16>,
+}
+
+impl From<ContainerPort> for ContainerPortResp {
+    fn from(port: ContainerPort) -> Self {
+        Self {
+            protocol: port.protocol,
+            port: port.port,
+            host_ip: port.host_ip,
+            host_port: port.host_port,
+        }
+    }
 }
 
 #[derive(serde::Deserialize)]
 #[serde(rename_all = "camelCase", crate = "rocket::serde")]
 pub(crate) struct ProjectReq {
     name: String,
+    title: Option<String>,
     #[serde(rename = "type")]
     r#type: String,
     description: Option<String>,
@@ -64,64 +195,302 @@ pub(crate) struct ProjectReq {
     parent: Option<i32>,
 }
 
-#[get("/<id>")]
+/// Get a Project
+///
+/// The children are included with their latest snapshot by default (`children=full`),
+/// see [project_children] for the `children` and `children_sort` parameters.
+#[get("/<id>?<children>&<children_sort>")]
 pub(crate) async fn get_project(
     state: &State<AppState>,
-    _session: Session,
+    session: Session,
     id: i32,
+    children: Option<String>,
+    children_sort: Option<String>,
 ) -> ApiResult<ProjectResp> {
+    let include = children
+        .map(|children| children.parse::<ProjectChildren>())
+        .transpose()?
+        .unwrap_or(ProjectChildren::Full);
+    let sort = children_sort
+        .map(|sort| sort.parse::<ProjectChildrenSort>())
+        .transpose()?
+        .unwrap_or_default();
+
     let mut project = models::Projects::fetch_by_primary_key(&state.connection, id).await?;
 
     if project.status == models::ProjectStatus::Archived {
         info!("Tried accessing an archived project: {}", project.id);
         Err(KonarrServerError::ProjectNotFoundError(id))
+    } else if !in_scope(state, &session, &project).await? {
+        Err(KonarrServerError::ProjectNotFoundError(id))
     } else {
         // Fetch Children and Latest Snapshot
-        project.fetch_children(&state.connection).await?;
+        let mut children =
+            project_children(state, std::slice::from_mut(&mut project), include, sort).await?;
         project.fetch_snapshots(&state.connection).await?;
 
         info!("{:?} (snapshots: {})", project.id, project.snapshots.len());
 
-        Ok(Json(project.into()))
+        let scan_interval_hours =
+            ProjectSettings::scan_interval_hours(&state.connection, project.id.into()).await?;
+        let project_id: i32 = project.id.into();
+        let since = chrono::Utc::now() - chrono::Duration::days(ALERTS_RESOLVED_RECENT_DAYS);
+        let resolved = SecurityResolved {
+            total: models::AlertEvents::count_resolved(&state.connection, Some(project_id), None)
+                .await? as u32,
+            recent: models::AlertEvents::count_resolved(
+                &state.connection,
+                Some(project_id),
+                Some(since),
+            )
+            .await? as u32,
+        };
+
+        let mut resp: ProjectResp = project.into();
+        resp.children = children.remove(&resp.id).unwrap_or_default();
+        resp.scan_interval_hours = scan_interval_hours;
+        if let Some(security) = resp.security.as_mut() {
+            security.resolved = Some(resolved);
+        }
+        Ok(Json(resp))
+    }
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct AlertEventResp {
+    id: i32,
+    /// Event (detected, acknowledged, reopened or resolved)
+    event: String,
+    /// User who changed the state (`system` for the background tasks)
+    actor: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alert: Option<i32>,
+    /// Vulnerable component (the advisory is resolved per component)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    component: Option<i32>,
+    /// Snapshot the transition was observed in (the resolving snapshot for `resolved`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snapshot: Option<i32>,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[get("/<id>/alerts/<advisory_id>/timeline")]
+pub(crate) async fn get_alert_timeline(
+    state: &State<AppState>,
+    session: Session,
+    id: i32,
+    advisory_id: i32,
+) -> ApiResult<Vec<AlertEventResp>> {
+    let project = models::Projects::fetch_by_primary_key(&state.connection, id).await?;
+    if !in_scope(state, &session, &project).await? {
+        return Err(KonarrServerError::ProjectNotFoundError(id));
     }
+    let events =
+        models::AlertEvents::fetch_timeline(&state.connection, project.id.into(), advisory_id)
+            .await?;
+    info!(
+        "Project({}) :: Advisory({}) timeline ({} events)",
+        project.id,
+        advisory_id,
+        events.len()
+    );
+
+    Ok(Json(
+        events
+            .into_iter()
+            .map(|event| AlertEventResp {
+                id: event.id.into(),
+                event: event.event.to_string().to_lowercase(),
+                actor: event.actor,
+                alert: event.alert_id,
+                component: event.component_id,
+                snapshot: event.snapshot_id,
+                created_at: event.created_at,
+            })
+            .collect(),
+    ))
 }
 
-#[get("/?<page>&<limit>&<search>&<type>&<top>&<parents>")]
+/// Get the Projects
+///
+/// The children are included as a summary by default (`children=summary`), see
+/// [project_children] for the `children` and `children_sort` parameters.
+#[get(
+    "/?<page>&<limit>&<search>&<type>&<top>&<parents>&<policy_violations>&<min_alerts>&<severity_at_least>&<min_dependencies>&<status>&<stale>&<health>&<metadata>&<children>&<children_sort>"
+)]
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn get_projects(
     state: &State<AppState>,
-    _session: Session,
+    session: Session,
     page: Option<u32>,
     limit: Option<u32>,
     search: Option<String>,
     top: Option<bool>,
     r#type: Option<String>,
     parents: Option<bool>,
-) -> ApiResult<ApiResponse<Vec<ProjectResp>>> {
+    policy_violations: Option<bool>,
+    min_alerts: Option<u32>,
+    severity_at_least: Option<String>,
+    min_dependencies: Option<u32>,
+    status: Option<String>,
+    stale: Option<bool>,
+    health: Option<String>,
+    metadata: Option<HashMap<String, String>>,
+    children: Option<String>,
+    children_sort: Option<String>,
+) -> ApiResult<ApiResponse<ProjectResp>> {
     let limit = limit.unwrap_or(10) as usize;
     let offset = page.unwrap_or(0) as usize * limit as usize;
+    let include = children
+        .map(|children| children.parse::<ProjectChildren>())
+        .transpose()?
+        .unwrap_or(ProjectChildren::Summary);
+    let sort = children_sort
+        .map(|sort| sort.parse::<ProjectChildrenSort>())
+        .transpose()?
+        .unwrap_or_default();
+
+    match status.as_deref() {
+        None | Some("active") => {}
+        Some("archived") => {
+            info!("Fetching the archived projects");
+            let projects =
+                models::Projects::fetch_archived(&state.connection, limit, offset).await?;
+            let mut projects = scoped_projects(state, &session, projects).await?;
+            let projects = project_responses(state, &mut projects, include, sort).await?;
+            let count = models::Projects::count_archived(&state.connection).await?;
+            return Ok(Json(ApiResponse::new(
+                projects,
+                count as u64,
+                count as u64,
+                limit as u64,
+            )));
+        }
+        Some(status) => {
+            return Err(konarr::KonarrError::InvalidData(format!(
+                "Unknown project status: {}",
+                status
+            ))
+            .into())
+        }
+    }
 
     let total = models::Projects::count_active(&state.connection).await?;
-    let pages = (total as f32 / limit as f32).ceil() as u32;
 
-    let projects = if let Some(search) = search {
+    let mut filters = models::ProjectFilters {
+        project_type: r#type
+            .as_ref()
+            .filter(|prjtype| prjtype.as_str() != "all")
+            .map(|prjtype| ProjectType::from(prjtype.clone())),
+        min_alerts,
+        severity_at_least: severity_at_least
+            .map(|severity| {
+                severity.parse::<SecuritySeverity>().map_err(|_| {
+                    konarr::KonarrError::InvalidData(format!("Unknown severity: {}", severity))
+                })
+            })
+            .transpose()?,
+        min_dependencies,
+        health: health
+            .map(|health| {
+                health.parse::<ProjectHealth>().map_err(|_| {
+                    konarr::KonarrError::InvalidData(format!("Unknown health: {}", health))
+                })
+            })
+            .transpose()?,
+        ..Default::default()
+    };
+    // `metadata.<name>=<value>` (only the whitelisted names)
+    for (name, value) in metadata.unwrap_or_default() {
+        filters = filters.with_metadata(&name, value)?;
+    }
+
+    if !filters.is_empty() {
+        info!("Fetching the projects matching the filters: {:?}", filters);
+        let mut metrics = Vec::new();
+        for project in
+            models::Projects::fetch_filtered(&state.connection, &filters, limit, offset).await?
+        {
+            if in_scope(state, &session, &project.project).await? {
+                metrics.push(project);
+            }
+        }
+        let mut projects: Vec<models::Projects> = metrics
+            .iter()
+            .map(|metrics| metrics.project.clone())
+            .collect();
+        let projects = project_responses(state, &mut projects, include, sort)
+            .await?
+            .into_iter()
+            .zip(metrics)
+            .map(|(mut project, metrics)| {
+                project.metrics = Some(ProjectMetricsResp {
+                    alerts: metrics.alerts,
+                    dependencies: metrics.dependencies,
+                });
+                project
+            })
+            .collect();
+        let count = models::Projects::count_filtered(&state.connection, &filters).await?;
+        return Ok(Json(ApiResponse::new(
+            projects,
+            total as u64,
+            count as u64,
+            limit as u64,
+        )));
+    }
+
+    let (projects, count) = if let Some(search) = search {
         info!("Searching for projects with name: '{}'", search);
-        models::Projects::search_title(&state.connection, search).await?
+        let projects = models::Projects::search_title(&state.connection, search).await?;
+        let count = projects.len() as i64;
+        (projects, count)
+    } else if stale.unwrap_or(false) {
+        info!("Fetching the stale projects");
+        (
+            models::Projects::fetch_stale(&state.connection, limit, offset).await?,
+            models::Projects::count_stale(&state.connection).await?,
+        )
+    } else if policy_violations.unwrap_or(false) {
+        info!("Fetching the projects with image policy violations");
+        (
+            models::Projects::fetch_policy_violations(&state.connection, limit, offset).await?,
+            models::Projects::count_policy_violations(&state.connection).await?,
+        )
     } else if parents.unwrap_or(false) {
         info!("Get the parent projects");
-        models::Projects::find_parents(&state.connection).await?
+        let projects = models::Projects::find_parents(&state.connection).await?;
+        let count = projects.len() as i64;
+        (projects, count)
     } else if top.unwrap_or(false) {
         info!("Fetching the top level projects");
-        models::Projects::fetch_top_level(&state.connection, limit, offset).await?
+        (
+            models::Projects::fetch_top_level(&state.connection, limit, offset).await?,
+            models::Projects::count_top_level(&state.connection).await?,
+        )
     } else if let Some(prjtype) = r#type {
         if prjtype.as_str() == "all" {
             info!("Fetching all projects");
-            models::Projects::all(&state.connection, limit, offset).await?
+            (
+                models::Projects::all(&state.connection, limit, offset).await?,
+                total,
+            )
         } else {
             info!("Fetching by type: {}", prjtype);
-            models::Projects::fetch_project_type(&state.connection, prjtype, limit, offset).await?
+            (
+                models::Projects::fetch_project_type(
+                    &state.connection,
+                    prjtype.clone(),
+                    limit,
+                    offset,
+                )
+                .await?,
+                models::Projects::count_project_type(&state.connection, prjtype).await?,
+            )
         }
     } else {
-        models::Projects::query(
+        let projects = models::Projects::query(
             &state.connection,
             models::Projects::query_select()
                 .order_by("created_at", geekorm::QueryOrder::Desc)
@@ -129,25 +498,159 @@ pub(crate) async fn get_projects(
                 .offset(offset)
                 .build()?,
         )
-        .await?
+        .await?;
+        (projects, total)
     };
+    let mut projects = scoped_projects(state, &session, projects).await?;
 
     Ok(Json(ApiResponse::new(
-        projects.into_iter().map(|p| p.into()).collect(),
-        total as u32,
-        pages,
+        project_responses(state, &mut projects, include, sort).await?,
+        total as u64,
+        count as u64,
+        limit as u64,
     )))
 }
 
-#[post("/", data = "<project_req>", format = "json")]
+/// If the session can read the project (scoped agent tokens only see their subtree)
+async fn in_scope(
+    state: &State<AppState>,
+    session: &Session,
+    project: &models::Projects,
+) -> Result<bool, KonarrServerError> {
+    match session.project_scope() {
+        Some(scope) => Ok(project.is_within(&state.connection, scope).await?),
+        None => Ok(true),
+    }
+}
+
+/// Keep the projects the session can read (see [in_scope])
+///
+/// The children of the projects are in the same subtree, so they are in the scope too.
+async fn scoped_projects(
+    state: &State<AppState>,
+    session: &Session,
+    projects: Vec<models::Projects>,
+) -> Result<Vec<models::Projects>, KonarrServerError> {
+    let mut scoped = Vec::with_capacity(projects.len());
+    for project in projects {
+        if in_scope(state, session, &project).await? {
+            scoped.push(project);
+        }
+    }
+    Ok(scoped)
+}
+
+/// Load the children of the projects by parent ID (`children` and `children_sort`)
+///
+/// - `none`: no children
+/// - `summary`: ID, name, type and health of the children (no snapshot is loaded)
+/// - `full`: the children with their latest snapshot (loaded in batches)
+///
+/// The children are sorted by `name` (default), `created` (newest first) or `alerts`.
+async fn project_children(
+    state: &State<AppState>,
+    projects: &mut [models::Projects],
+    include: ProjectChildren,
+    sort: ProjectChildrenSort,
+) -> Result<HashMap<i32, Vec<ProjectChildResp>>, KonarrServerError> {
+    let mut children: HashMap<i32, Vec<ProjectChildResp>> = HashMap::new();
+    match include {
+        ProjectChildren::None => {}
+        ProjectChildren::Summary => {
+            let parents: Vec<i32> = projects.iter().map(|project| project.id.into()).collect();
+            for child in
+                models::Projects::fetch_child_summaries(&state.connection, &parents, sort).await?
+            {
+                children
+                    .entry(child.project.parent)
+                    .or_default()
+                    .push(ProjectChildResp::Summary(ProjectChildSummaryResp {
+                        id: child.project.id.into(),
+                        name: child.project.name,
+                        project_type: child.project.project_type.to_string(),
+                        health: child.health.to_string(),
+                    }));
+            }
+        }
+        ProjectChildren::Full => {
+            models::Projects::fetch_children_batch(&state.connection, projects, sort).await?;
+            for project in projects.iter_mut() {
+                children.insert(
+                    project.id.into(),
+                    std::mem::take(&mut project.children)
+                        .into_iter()
+                        .map(|child| ProjectChildResp::Full(child.into()))
+                        .collect(),
+                );
+            }
+        }
+    }
+    Ok(children)
+}
+
+/// Convert the projects to responses with their children (see [project_children])
+async fn project_responses(
+    state: &State<AppState>,
+    projects: &mut [models::Projects],
+    include: ProjectChildren,
+    sort: ProjectChildrenSort,
+) -> Result<Vec<ProjectResp>, KonarrServerError> {
+    let mut children = project_children(state, projects, include, sort).await?;
+    Ok(projects
+        .iter_mut()
+        .map(|project| {
+            let mut resp = ProjectResp::from(std::mem::take(project));
+            resp.children = children.remove(&resp.id).unwrap_or_default();
+            resp
+        })
+        .collect())
+}
+
+/// Create a Project
+///
+/// If a project with the same name already exists, it is returned when `find_or_create`
+/// is set (otherwise a `409 Conflict` with the existing project ID is returned).
+#[post("/?<find_or_create>", data = "<project_req>", format = "json")]
 pub async fn create_project(
     state: &State<AppState>,
     _session: Session,
     project_req: Json<ProjectReq>,
+    find_or_create: Option<bool>,
 ) -> ApiResult<ProjectResp> {
     log::info!("Creating Project: `{}`", project_req.name);
+    let find_or_create = find_or_create.unwrap_or(false);
     let mut project: models::Projects = project_req.into_inner().into();
-    project.fetch_or_create(&state.connection).await?;
+    if project.name.is_empty() {
+        return Err(konarr::KonarrError::InvalidData("Invalid project name".to_string()).into());
+    }
+
+    if let Ok(existing) =
+        models::Projects::fetch_by_normalized_name(&state.connection, &project.name).await
+    {
+        return existing_project(existing, find_or_create);
+    }
+    if find_or_create {
+        // Agents still using the name / parent from before the project was transferred
+        if let Ok(existing) =
+            models::Projects::fetch_transferred(&state.connection, &project.name, project.parent)
+                .await
+        {
+            log::info!(
+                "Project `{}` was transferred, using `{}` ({})",
+                project.name,
+                existing.name,
+                existing.id
+            );
+            return existing_project(existing, find_or_create);
+        }
+    }
+    if let Err(err) = project.save(&state.connection).await {
+        // Another client created the project concurrently (unique name)
+        return match models::Projects::fetch_by_name(&state.connection, &project.name).await {
+            Ok(existing) => existing_project(existing, find_or_create),
+            Err(_) => Err(err.into()),
+        };
+    }
 
     // Run the statistics task in the background
     let connection = std::sync::Arc::clone(&state.connection);
@@ -163,6 +666,18 @@ pub async fn create_project(
     Ok(Json(project.into()))
 }
 
+fn existing_project(project: models::Projects, find_or_create: bool) -> ApiResult<ProjectResp> {
+    if find_or_create {
+        log::debug!("Project `{}` already exists: {}", project.name, project.id);
+        Ok(Json(project.into()))
+    } else {
+        Err(KonarrServerError::ProjectExistsError {
+            name: project.name,
+            id: project.id.into(),
+        })
+    }
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase", crate = "rocket::serde")]
 pub struct ProjectUpdateRequest {
@@ -172,12 +687,16 @@ pub struct ProjectUpdateRequest {
     pub(crate) project_type: Option<String>,
     pub(crate) description: Option<String>,
     pub(crate) parent: Option<u32>,
+    /// Retention override (maximum snapshots, negative removes the override)
+    pub(crate) retention_max_snapshots: Option<i32>,
+    /// Retention override (maximum age in days, negative removes the override)
+    pub(crate) retention_max_age_days: Option<i32>,
 }
 
 #[patch("/<id>", data = "<project_req>", format = "json")]
 pub async fn patch_project(
     state: &State<AppState>,
-    _session: Session,
+    session: Session,
     project_req: Json<ProjectUpdateRequest>,
     id: Option<u32>,
 ) -> ApiResult<ProjectResp> {
@@ -193,25 +712,40 @@ pub async fn patch_project(
 
     let mut project =
         models::Projects::fetch_by_primary_key(&connection, project_id as i32).await?;
+    session.authorize_project(&connection, &project).await?;
 
-    if let Some(title) = &project_req.title {
-        info!("Updating Project (title) :: {}", title);
-        project.title = Some(title.clone());
+    if session.agent.is_some() || session.user.role == UserRole::Agent {
+        // Agents sync the details from the container labels (manual edits win)
+        if project.sync_labels(project_req.title.clone(), project_req.description.clone()) {
+            info!("Updated Project (labels) :: {}", project.name);
+        }
+    } else {
+        if project.edit(project_req.title.clone(), project_req.description.clone()) {
+            info!("Updated Project (title / description) :: {}", project.name);
+        }
+        if let Some(max) = project_req.retention_max_snapshots {
+            info!("Updating Project (retention snapshots) :: {}", max);
+            project.retention_max_snapshots = (max >= 0).then_some(max);
+        }
+        if let Some(days) = project_req.retention_max_age_days {
+            info!("Updating Project (retention days) :: {}", days);
+            project.retention_max_age_days = (days >= 0).then_some(days);
+        }
     }
     if let Some(typ) = &project_req.project_type {
         info!("Updating Project (type) :: {}", typ);
         project.project_type = ProjectType::from(typ.clone());
     }
-    if let Some(desc) = &project_req.description {
-        info!("Update Project (description) :: {}", desc);
-        if desc.is_empty() {
-            project.description = None;
-        } else {
-            project.description = Some(desc.clone());
+    if let Some(parent) = project_req.parent {
+        let parent = parent as i32;
+        if parent != project.parent && session.project_scope().is_some() {
+            // Scoped agent tokens can only move the project within their scope
+            let target = models::Projects::fetch_by_primary_key(&connection, parent)
+                .await
+                .map_err(|_| KonarrServerError::ProjectNotFoundError(parent))?;
+            session.authorize_project(&connection, &target).await?;
         }
-    }
-    if let Some(parent) = &project_req.parent {
-        project.parent = *parent as i32;
+        project.parent = parent;
         // TODO: Update the name of the project?
     }
 
@@ -230,6 +764,328 @@ pub async fn patch_project(
     Ok(Json(project.into()))
 }
 
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct ProjectTransferReq {
+    /// New parent (Server, Group or Cluster)
+    pub(crate) parent: u32,
+    /// Rename the project to the prefix of the new parent
+    #[serde(default)]
+    pub(crate) rename: bool,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct ProjectTransferResp {
+    id: i32,
+    from_parent: i32,
+    to_parent: i32,
+    old_name: String,
+    new_name: String,
+    user: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Transfer a Project to another parent (Server, Group or Cluster)
+///
+/// The alerts roll-up of the previous and the new parent are recalculated.
+#[post("/<id>/transfer", data = "<transfer_req>", format = "json")]
+pub async fn transfer_project(
+    state: &State<AppState>,
+    session: Session,
+    id: i32,
+    transfer_req: Json<ProjectTransferReq>,
+) -> ApiResult<ProjectResp> {
+    if session.agent.is_some() || session.user.role == UserRole::Agent {
+        return Err(KonarrServerError::Unauthorized);
+    }
+    let connection = std::sync::Arc::clone(&state.connection);
+
+    let mut project = match models::Projects::fetch_by_primary_key(&connection, id).await {
+        Ok(project) if project.status != models::ProjectStatus::Archived => project,
+        _ => return Err(KonarrServerError::ProjectNotFoundError(id)),
+    };
+    let parent_id = transfer_req.parent as i32;
+    let target = models::Projects::fetch_by_primary_key(&connection, parent_id)
+        .await
+        .map_err(|_| KonarrServerError::ProjectNotFoundError(parent_id))?;
+
+    let transfer = project
+        .transfer(
+            &connection,
+            &target,
+            transfer_req.rename,
+            session.user.username.clone(),
+        )
+        .await?;
+    info!(
+        "Transferred Project :: {} ({} -> {}) by {}",
+        project.name, transfer.from_parent, transfer.to_parent, session.user.username
+    );
+
+    // Recalculate the alerts of the previous and the new parent
+    for parent in [transfer.from_parent, transfer.to_parent] {
+        if parent <= 0 {
+            continue;
+        }
+        if let Ok(parent) = models::Projects::fetch_by_primary_key(&connection, parent).await {
+            parent.calculate_group_alerts(&connection).await?;
+        }
+    }
+
+    // Run the statistics task in the background
+    let stats_connection = std::sync::Arc::clone(&connection);
+    tokio::spawn(async move {
+        konarr::tasks::statistics(&stats_connection)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to run alert calculator: {:?}", e);
+            })
+            .ok();
+    });
+
+    project.fetch_snapshots(&connection).await?;
+    Ok(Json(project.into()))
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct ProjectMergeReq {
+    /// Project merged into the project (same type)
+    pub(crate) source_project_id: u32,
+    /// Keep the name of the source as an alias (agents using the old name)
+    #[serde(default = "default_true")]
+    pub(crate) alias: bool,
+    /// Remove the source project (archived by default)
+    #[serde(default)]
+    pub(crate) delete_source: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Merge another Project into the Project (duplicates created by agent naming changes)
+///
+/// The history of the source (snapshots, alert events, settings, ...) is moved to the
+/// project and the source is archived (or removed).
+#[post("/<id>/merge", data = "<merge_req>", format = "json")]
+pub async fn merge_project(
+    state: &State<AppState>,
+    session: AdminSession,
+    id: i32,
+    merge_req: Json<ProjectMergeReq>,
+) -> ApiResult<ProjectResp> {
+    let connection = std::sync::Arc::clone(&state.connection);
+
+    let mut project = match models::Projects::fetch_by_primary_key(&connection, id).await {
+        Ok(project) if project.status != models::ProjectStatus::Archived => project,
+        _ => return Err(KonarrServerError::ProjectNotFoundError(id)),
+    };
+    let source_id = merge_req.source_project_id as i32;
+    let mut source = models::Projects::fetch_by_primary_key(&connection, source_id)
+        .await
+        .map_err(|_| KonarrServerError::ProjectNotFoundError(source_id))?;
+    let source_parent = source.parent;
+
+    let summary = source
+        .merge_into(
+            &connection,
+            &project,
+            &models::ProjectMerge {
+                alias: merge_req.alias,
+                delete_source: merge_req.delete_source,
+            },
+        )
+        .await?;
+    info!(
+        "Merged Project :: {} into {} by {}",
+        source.name, project.name, session.user.username
+    );
+    models::AuditLog::record(
+        &connection,
+        "project.merge",
+        session.user.username.clone(),
+        "project",
+        id,
+        Some(
+            serde_json::json!({
+                "source": source_id,
+                "name": source.name,
+                "snapshots": summary.snapshots,
+                "children": summary.children,
+                "alias": summary.alias,
+                "deleted": merge_req.delete_source,
+            })
+            .to_string(),
+        ),
+    )
+    .await?;
+
+    // Recalculate the alerts of the parents
+    for parent in [source_parent, project.parent] {
+        if parent <= 0 {
+            continue;
+        }
+        if let Ok(parent) = models::Projects::fetch_by_primary_key(&connection, parent).await {
+            parent.calculate_group_alerts(&connection).await?;
+        }
+    }
+    state.cache.clear().await;
+
+    // Run the statistics task in the background
+    let stats_connection = std::sync::Arc::clone(&connection);
+    tokio::spawn(async move {
+        konarr::tasks::statistics(&stats_connection)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to run alert calculator: {:?}", e);
+            })
+            .ok();
+    });
+
+    project.fetch_snapshots(&connection).await?;
+    Ok(Json(project.into()))
+}
+
+#[derive(serde::Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ProjectSnapshotLinkReq {
+    snapshot: u32,
+}
+
+/// Link an existing Snapshot to a Project (projects tracking the same image)
+///
+/// The snapshot has to be completed, it is shared with the other projects (not copied).
+#[post("/<id>/snapshots", data = "<link_req>", format = "json")]
+pub async fn link_project_snapshot(
+    state: &State<AppState>,
+    session: Session,
+    id: i32,
+    link_req: Json<ProjectSnapshotLinkReq>,
+) -> ApiResult<ProjectResp> {
+    let connection = std::sync::Arc::clone(&state.connection);
+
+    let mut project = match models::Projects::fetch_by_primary_key(&connection, id).await {
+        Ok(project) if project.status != models::ProjectStatus::Archived => project,
+        _ => return Err(KonarrServerError::ProjectNotFoundError(id)),
+    };
+    session.authorize_project(&connection, &project).await?;
+    let snapshot_id = link_req.snapshot as i32;
+    let snapshot = match models::Snapshot::fetch_by_primary_key(&connection, snapshot_id).await {
+        Ok(snapshot) => snapshot,
+        Err(_) => return Err(KonarrServerError::SnapshotNotFoundError(snapshot_id)),
+    };
+    session
+        .authorize_snapshot(&connection, &snapshot, SnapshotAccess::Read)
+        .await?;
+    if snapshot.state != models::SnapshotState::Completed {
+        return Err(konarr::KonarrError::InvalidData(format!(
+            "Snapshot {} is not completed",
+            snapshot_id
+        ))
+        .into());
+    }
+
+    match project.fetch_latest_snapshot(&connection).await? {
+        Some(latest) if latest.id == snapshot.id => {
+            debug!(
+                "Snapshot({}) is already linked to Project({})",
+                snapshot_id, id
+            );
+        }
+        _ => {
+            info!("Linking Snapshot({}) to Project({})", snapshot_id, id);
+            project.add_snapshot(&connection, snapshot).await?;
+        }
+    }
+
+    // Run the statistics task in the background
+    let stats_connection = std::sync::Arc::clone(&connection);
+    tokio::spawn(async move {
+        konarr::tasks::statistics(&stats_connection)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to run alert calculator: {:?}", e);
+            })
+            .ok();
+    });
+
+    project.fetch_snapshots(&connection).await?;
+    Ok(Json(project.into()))
+}
+
+/// Get the transfers history of a Project (newest first)
+#[get("/<id>/transfers")]
+pub(crate) async fn get_project_transfers(
+    state: &State<AppState>,
+    _session: Session,
+    id: i32,
+) -> ApiResult<Vec<ProjectTransferResp>> {
+    let project = models::Projects::fetch_by_primary_key(&state.connection, id)
+        .await
+        .map_err(|_| KonarrServerError::ProjectNotFoundError(id))?;
+
+    Ok(Json(
+        project
+            .fetch_transfers(&state.connection)
+            .await?
+            .into_iter()
+            .map(|transfer| transfer.into())
+            .collect(),
+    ))
+}
+
+/// Get the settings of a project (`name -> value`)
+#[get("/<id>/settings")]
+pub(crate) async fn get_project_settings(
+    state: &State<AppState>,
+    _session: Session,
+    id: i32,
+) -> ApiResult<HashMap<String, String>> {
+    let project = models::Projects::fetch_by_primary_key(&state.connection, id)
+        .await
+        .map_err(|_| KonarrServerError::ProjectNotFoundError(id))?;
+
+    Ok(Json(project_settings(state, project.id.into()).await?))
+}
+
+/// Update the settings of a project (`name -> value`)
+#[patch("/<id>/settings", data = "<settings>", format = "json")]
+pub(crate) async fn update_project_settings(
+    state: &State<AppState>,
+    session: AdminSession,
+    id: i32,
+    settings: Json<HashMap<String, String>>,
+) -> ApiResult<HashMap<String, String>> {
+    let project = models::Projects::fetch_by_primary_key(&state.connection, id)
+        .await
+        .map_err(|_| KonarrServerError::ProjectNotFoundError(id))?;
+
+    for (name, value) in settings.iter() {
+        info!(
+            "Updating Project Setting :: {} {} = {} by {}",
+            project.name, name, value, session.user.username
+        );
+        ProjectSettings::set(&state.connection, project.id.into(), name.as_str(), value).await?;
+    }
+
+    Ok(Json(project_settings(state, project.id.into()).await?))
+}
+
+async fn project_settings(
+    state: &State<AppState>,
+    project_id: i32,
+) -> Result<HashMap<String, String>, KonarrServerError> {
+    Ok(
+        ProjectSettings::fetch_settings(&state.connection, project_id)
+            .await?
+            .into_iter()
+            .map(|setting| (setting.name.to_string(), setting.value))
+            .collect(),
+    )
+}
+
 #[patch("/<id>/metadata")]
 #[allow(unused)]
 pub(crate) async fn update_project_metadata(
@@ -265,6 +1121,15 @@ pub async fn delete_project(
         project.name, session.user.username
     );
     project.archive(&connection).await?;
+    models::AuditLog::record(
+        &connection,
+        "project.archive",
+        session.user.username.clone(),
+        "project",
+        id,
+        None,
+    )
+    .await?;
     // Run the statistics task in the background
     tokio::spawn(async move {
         konarr::tasks::statistics(&connection)
@@ -278,6 +1143,46 @@ pub async fn delete_project(
     Ok(Json(project.into()))
 }
 
+/// Restore an archived Project
+#[post("/<id>/restore")]
+pub(crate) async fn restore_project(
+    state: &State<AppState>,
+    session: AdminSession,
+    id: i32,
+) -> ApiResult<ProjectResp> {
+    let connection = std::sync::Arc::clone(&state.connection);
+
+    let mut project = models::Projects::fetch_by_primary_key(&connection, id).await?;
+    info!(
+        "Restoring Project :: {} by {}",
+        project.name, session.user.username
+    );
+    project.restore(&connection).await?;
+    models::AuditLog::record(
+        &connection,
+        "project.restore",
+        session.user.username.clone(),
+        "project",
+        id,
+        None,
+    )
+    .await?;
+    project.fetch_children(&connection).await?;
+    project.fetch_snapshots(&connection).await?;
+
+    // Run the statistics task in the background
+    tokio::spawn(async move {
+        konarr::tasks::statistics(&connection)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to run the statistics task: {:?}", e);
+            })
+            .ok();
+    });
+
+    Ok(Json(project.into()))
+}
+
 /// Model -> Response
 impl From<models::Projects> for ProjectResp {
     fn from(project: models::Projects) -> Self {
@@ -301,14 +1206,37 @@ impl From<models::Projects> for ProjectResp {
             None => None,
         };
 
+        // Drift is checked live, freshness is flagged by the stale scans task
+        let stale = project.is_scan_stale()
+            || snapshot
+                .as_ref()
+                .and_then(|snap| snap.find_metadata("scan.stale"))
+                .is_some_and(|stale| stale.as_bool());
+        let last_scanned_at = project.last_scanned_at();
+        let health = snapshot
+            .as_ref()
+            .map(|snap| snap.health())
+            .unwrap_or_default();
+        let agent_outdated = snapshot
+            .as_ref()
+            .and_then(|snap| snap.find_metadata("scan.agent.outdated"))
+            .is_some_and(|outdated| outdated.as_bool());
+
+        let container: Option<ContainerResp> = match (&project.project_type, &snapshot) {
+            (ProjectType::Container, Some(snap)) => ContainerResp::from_snapshot(snap),
+            _ => None,
+        };
+
         ProjectResp {
             id: project.id.into(),
             name: project.name.clone(),
             title: project.title.unwrap_or(project.name),
             status,
+            container,
             project_type: project.project_type.to_string(),
             description: project.description.clone(),
             created_at: project.created_at,
+            archived_at: project.archived_at,
             snapshot: snapshot.map(|snap| snap.into()),
             snapshots: project.snapshots.len() as u32,
             security: Some(security),
@@ -316,25 +1244,84 @@ impl From<models::Projects> for ProjectResp {
             children: project
                 .children
                 .iter()
-                .map(|proj| proj.clone().into())
+                .map(|proj| ProjectChildResp::Full(proj.clone().into()))
                 .collect(),
+            edited_by_user: project.edited_by_user,
+            stale,
+            last_scanned_at,
+            agent_outdated,
+            health: health.to_string(),
             ..Default::default()
         }
     }
 }
 
+impl From<models::ProjectTransfers> for ProjectTransferResp {
+    fn from(transfer: models::ProjectTransfers) -> Self {
+        ProjectTransferResp {
+            id: transfer.id.into(),
+            from_parent: transfer.from_parent,
+            to_parent: transfer.to_parent,
+            old_name: transfer.old_name,
+            new_name: transfer.new_name,
+            user: transfer.user,
+            created_at: transfer.created_at,
+        }
+    }
+}
+
+impl ContainerResp {
+    /// Runtime information from the snapshot metadata (if the agent reported any)
+    fn from_snapshot(snapshot: &models::Snapshot) -> Option<Self> {
+        let status = snapshot
+            .find_metadata("container.status")
+            .map(|m| m.as_string());
+        let health = snapshot
+            .find_metadata("container.health")
+            .map(|m| m.as_string());
+        if status.is_none() && health.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            status,
+            health,
+            restart_count: snapshot
+                .find_metadata("container.restart_count")
+                .map(|m| m.as_u32())
+                .unwrap_or_default(),
+            started_at: snapshot
+                .find_metadata("container.started_at")
+                .map(|m| m.as_string()),
+            uptime: snapshot
+                .find_metadata("container.uptime")
+                .and_then(|m| m.as_string().parse().ok()),
+            ports: snapshot
+                .find_metadata("container.ports")
+                .map(|m| ContainerPort::parse_list(&m.as_string()))
+                .unwrap_or_default()
+                .into_iter()
+                .map(ContainerPortResp::from)
+                .collect(),
+            networks: snapshot
+                .find_metadata("container.networks")
+                .map(|m| parse_networks(&m.as_string()))
+                .unwrap_or_default(),
+        })
+    }
+}
+
 /// Request -> Model
 impl From<ProjectReq> for models::Projects {
     fn from(project: ProjectReq) -> Self {
+        // The name is normalized, the original name is kept as the title
         let title = project
-            .name
-            .split('/')
-            .last()
-            .unwrap_or(project.name.as_str())
-            .to_string();
+            .title
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| project_name_title(&project.name));
 
         models::Projects {
-            name: project.name.clone(),
+            name: normalize_project_name(&project.name),
             title: Some(title),
             project_type: ProjectType::from(project.r#type),
             description: project.description,
@@ -353,6 +1340,7 @@ mod tests {
     fn test_project_req_to_project() {
         let req = ProjectReq {
             name: "server/test".to_string(),
+            title: None,
             r#type: "server".to_string(),
             description: Some("test".to_string()),
             parent: Some(1),
@@ -362,5 +1350,115 @@ mod tests {
         assert_eq!(project.name.as_str(), "server/test");
         assert_eq!(project.title, Some("test".to_string()));
         assert_eq!(project.project_type, models::ProjectType::Server);
+
+        let req = ProjectReq {
+            name: "server/test".to_string(),
+            title: Some("Test App".to_string()),
+            r#type: "container".to_string(),
+            description: None,
+            parent: None,
+        };
+        let project: models::Projects = req.into();
+        assert_eq!(project.title, Some("Test App".to_string()));
+    }
+
+    #[test]
+    fn test_project_req_normalized_name() {
+        // Compose project / service from the agent
+        let req = ProjectReq {
+            name: "MyHost/My Stack/Web Server".to_string(),
+            title: None,
+            r#type: "container".to_string(),
+            description: None,
+            parent: None,
+        };
+        let project: models::Projects = req.into();
+        assert_eq!(project.name.as_str(), "myhost/my-stack/web-server");
+        assert_eq!(project.title, Some("Web Server".to_string()));
+
+        let req = ProjectReq {
+            name: "  MyHost ".to_string(),
+            title: None,
+            r#type: "server".to_string(),
+            description: None,
+            parent: None,
+        };
+        let project: models::Projects = req.into();
+        assert_eq!(project.name.as_str(), "myhost");
+        assert_eq!(project.title, Some("MyHost".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_metadata_filters() -> Result<(), konarr::KonarrError> {
+        use crate::guards::{maintenance::Maintenance, AgentTokenCache, SessionCache};
+        use rocket::{
+            http::{Header, Status},
+            local::asynchronous::Client,
+        };
+        use std::sync::{Arc, RwLock};
+
+        let connection = crate::api::database_test().await?;
+
+        for (name, image) in [("web", "ghcr.io/42bytelabs/konarr"), ("proxy", "nginx")] {
+            let mut project = models::Projects::new(name, ProjectType::Container);
+            project.save(&connection).await?;
+            let mut snapshot = models::Snapshot::create(&connection).await?;
+            snapshot
+                .set_metadata(
+                    &connection,
+                    models::SnapshotMetadataKey::ContainerImage,
+                    image,
+                )
+                .await?;
+            project.add_snapshot(&connection, snapshot).await?;
+        }
+
+        let mut session =
+            models::Sessions::new(models::SessionType::User, models::SessionState::Active);
+        session.save(&connection).await?;
+        let mut user = models::Users::new("user", "password", models::UserRole::User, session.id);
+        user.save(&connection).await?;
+        let (_, session) = models::Users::login(&connection, "user", "password").await?;
+        let auth = Header::new("Authorization", format!("Bearer {}", session.token));
+
+        let state = AppState {
+            connection: Arc::new(tokio::sync::Mutex::new(connection)),
+            sessions: Arc::new(RwLock::new(SessionCache::default())),
+            agent_tokens: Arc::new(RwLock::new(AgentTokenCache::new(String::new()))),
+            config: konarr::Config::default(),
+            init: true,
+            maintenance: Maintenance::default(),
+            cache: Default::default(),
+            checks: Default::default(),
+        };
+        let rocket = rocket::build()
+            .manage(state)
+            .mount("/api/projects", routes());
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+
+        let response = client
+            .get("/api/projects?metadata.registry=GHCR.IO")
+            .header(auth.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        let names: Vec<&str> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|project| project["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["web"]);
+        assert_eq!(body["count"], 1);
+
+        // Arbitrary metadata keys are refused
+        let response = client
+            .get("/api/projects?metadata.container.image=nginx")
+            .header(auth.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+        Ok(())
     }
 }
@@ -0,0 +1,232 @@
+//! # Public Status Page
+//!
+//! Read-only aggregates for a public status page (mounted under `/api/public`). The
+//! endpoints do not require authentication and are only available when the
+//! `public.dashboard.enabled` setting is enabled. Only counts and the names of the
+//! projects which opted-in (`public.listed` project setting) are returned, never any
+//! component, version, advisory or metadata.
+
+use konarr::models::{
+    self,
+    settings::{find_setting, keys::Setting, ServerSettings, SettingNamespace},
+    ProjectSetting, ProjectSettings,
+};
+use rocket::{serde::json::Json, State};
+use rocket_governor::RocketGovernor;
+
+use super::ApiResult;
+use crate::{error::KonarrServerError, AppState};
+
+/// Severities of the public badge (most severe first)
+const PUBLIC_SEVERITIES: [&str; 8] = [
+    "critical",
+    "high",
+    "medium",
+    "low",
+    "informational",
+    "malware",
+    "unmaintained",
+    "unknown",
+];
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![get_summary]
+}
+
+/// Public summary of the server
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct PublicSummaryResp {
+    /// Number of projects (not archived)
+    projects: i64,
+    /// Totals of the open alerts per severity
+    alerts: PublicAlertsResp,
+    /// Last time the alerts totals were calculated
+    updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Projects which opted-in to be listed
+    listed: Vec<PublicProjectResp>,
+}
+
+/// Totals of the open alerts per severity
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct PublicAlertsResp {
+    total: u32,
+    critical: u32,
+    high: u32,
+    medium: u32,
+    low: u32,
+    informational: u32,
+    unmaintained: u32,
+    malware: u32,
+    unknown: u32,
+}
+
+/// Listed project (name and the most severe open alert only)
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct PublicProjectResp {
+    name: String,
+    /// Most severe open alert (`none` without alerts)
+    severity: String,
+}
+
+/// Public summary (404 when the public status page is disabled)
+#[get("/summary")]
+pub(crate) async fn get_summary(
+    state: &State<AppState>,
+    _limiter: RocketGovernor<'_, crate::guards::limit::RateLimit>,
+) -> ApiResult<PublicSummaryResp> {
+    if !ServerSettings::get_bool(&state.connection, Setting::PublicDashboardEnabled).await? {
+        return Err(KonarrServerError::PublicDashboardDisabled);
+    }
+
+    let settings =
+        ServerSettings::get_namespace(&state.connection, SettingNamespace::SecurityAlerts).await?;
+    let updated_at = settings.iter().map(|setting| setting.updated_at).max();
+
+    let mut listed = Vec::new();
+    for id in
+        ProjectSettings::fetch_enabled(&state.connection, ProjectSetting::PublicListed).await?
+    {
+        let mut project = match models::Projects::fetch_by_primary_key(&state.connection, id).await
+        {
+            Ok(project) if project.status == models::ProjectStatus::Active => project,
+            _ => continue,
+        };
+        let snapshot = project
+            .refresh_latest_snapshot(&state.connection)
+            .await?
+            .cloned();
+        listed.push(PublicProjectResp {
+            name: project.title.clone().unwrap_or(project.name.clone()),
+            severity: public_severity(snapshot.as_ref()).to_string(),
+        });
+    }
+
+    Ok(Json(PublicSummaryResp {
+        projects: models::Projects::count_active(&state.connection).await?,
+        alerts: PublicAlertsResp::from(&settings),
+        updated_at,
+        listed,
+    }))
+}
+
+/// Most severe open alert of a snapshot (`none` without alerts or snapshot)
+fn public_severity(snapshot: Option<&models::Snapshot>) -> &'static str {
+    snapshot
+        .and_then(|snapshot| {
+            PUBLIC_SEVERITIES.into_iter().find(|severity| {
+                snapshot.find_metadata_usize(&format!("security.alerts.{}", severity)) > 0
+            })
+        })
+        .unwrap_or("none")
+}
+
+impl From<&Vec<ServerSettings>> for PublicAlertsResp {
+    fn from(settings: &Vec<ServerSettings>) -> Self {
+        let count = |name: Setting| {
+            find_setting(settings, name)
+                .and_then(|setting| setting.value.parse().ok())
+                .unwrap_or(0)
+        };
+        Self {
+            total: count(Setting::SecurityAlertsTotal),
+            critical: count(Setting::SecurityAlertsCritical),
+            high: count(Setting::SecurityAlertsHigh),
+            medium: count(Setting::SecurityAlertsMedium),
+            low: count(Setting::SecurityAlertsLow),
+            informational: count(Setting::SecurityAlertsInformational),
+            unmaintained: count(Setting::SecurityAlertsUnmaintained),
+            malware: count(Setting::SecurityAlertsMalware),
+            unknown: count(Setting::SecurityAlertsUnknown),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::serde::json::{self, Value};
+
+    /// Every key of the serialized summary (nested objects and lists included)
+    fn keys(value: &Value, prefix: &str, keys: &mut Vec<String>) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    let key = format!("{}{}", prefix, key);
+                    keys.push(key.clone());
+                    self::keys(value, &format!("{}.", key), keys);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self::keys(item, prefix, keys);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_public_summary_fields() {
+        let summary = PublicSummaryResp {
+            projects: 3,
+            alerts: PublicAlertsResp {
+                total: 2,
+                critical: 1,
+                high: 1,
+                ..Default::default()
+            },
+            updated_at: Some(chrono::Utc::now()),
+            listed: vec![PublicProjectResp {
+                name: "web".to_string(),
+                severity: "critical".to_string(),
+            }],
+        };
+
+        let mut found = Vec::new();
+        keys(&json::to_value(&summary).unwrap(), "", &mut found);
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                "alerts",
+                "alerts.critical",
+                "alerts.high",
+                "alerts.informational",
+                "alerts.low",
+                "alerts.malware",
+                "alerts.medium",
+                "alerts.total",
+                "alerts.unknown",
+                "alerts.unmaintained",
+                "listed",
+                "listed.name",
+                "listed.severity",
+                "projects",
+                "updatedAt",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_public_severity() -> Result<(), konarr::KonarrError> {
+        let connection = crate::api::database_test().await?;
+
+        assert_eq!(public_severity(None), "none");
+        let mut snapshot = models::Snapshot::create(&connection).await?;
+        snapshot.fetch_metadata(&connection).await?;
+        assert_eq!(public_severity(Some(&snapshot)), "none");
+
+        snapshot
+            .set_metadata(&connection, "security.alerts.low", "2")
+            .await?;
+        snapshot
+            .set_metadata(&connection, "security.alerts.high", "1")
+            .await?;
+        snapshot.fetch_metadata(&connection).await?;
+        assert_eq!(public_severity(Some(&snapshot)), "high");
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+//! # Search API
+
+use konarr::models::search::{SearchCategory, SearchResults};
+use log::info;
+use rocket::{serde::json::Json, State};
+
+use super::ApiResult;
+use crate::{guards::Session, AppState};
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![search]
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct SearchResp {
+    query: String,
+    results: Vec<SearchCategoryResp>,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct SearchCategoryResp {
+    r#type: String,
+    total: i64,
+    data: Vec<SearchResultResp>,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct SearchResultResp {
+    r#type: String,
+    id: i32,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+/// Search across projects, components and advisories
+///
+/// Use `type` to only search a single category (for "show more" links)
+#[get("/?<q>&<type>&<limit>")]
+pub(crate) async fn search(
+    state: &State<AppState>,
+    _session: Session,
+    q: String,
+    r#type: Option<String>,
+    limit: Option<u32>,
+) -> ApiResult<SearchResp> {
+    let limit = limit.unwrap_or(5).clamp(1, 100);
+    info!("Searching for `{}` (limit: {})", q, limit);
+
+    let results = if let Some(category) = r#type {
+        vec![
+            SearchCategory::from(category)
+                .search(&state.connection, &q, limit)
+                .await?,
+        ]
+    } else {
+        SearchCategory::search_all(&state.connection, &q, limit).await?
+    };
+
+    Ok(Json(SearchResp {
+        query: q,
+        results: results.into_iter().map(|r| r.into()).collect(),
+    }))
+}
+
+impl From<SearchResults> for SearchCategoryResp {
+    fn from(value: SearchResults) -> Self {
+        Self {
+            r#type: value.category.to_string(),
+            total: value.total,
+            data: value
+                .results
+                .into_iter()
+                .map(|r| SearchResultResp {
+                    r#type: r.category.to_string(),
+                    id: r.id,
+                    name: r.name,
+                    description: r.description,
+                })
+                .collect(),
+        }
+    }
+}
@@ -2,14 +2,22 @@
 
 use geekorm::{prelude::Pagination, GeekConnector, QueryBuilderTrait, QueryOrder};
 use konarr::models::{
-    security::{Alerts, SecuritySeverity, SecurityState},
-    Snapshot,
+    security::{
+        AlertBulkAction, AlertBulkFilter, AlertComponentSummary, AlertKind, Alerts,
+        SecuritySeverity, SecurityState,
+    },
+    AuditLog, Snapshot,
 };
+use konarr::tasks::ScanProvenance;
 use log::info;
 use rocket::{serde::json::Json, State};
 
 use super::{dependencies::DependencyResp, ApiResponse, ApiResult};
-use crate::{guards::Session, AppState};
+use crate::{
+    error::KonarrServerError,
+    guards::{Session, SnapshotAccess},
+    AppState,
+};
 
 /// Security Summary
 #[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
@@ -24,10 +32,75 @@ pub struct SecuritySummary {
     pub unmaintained: u32,
     pub malware: u32,
     pub unknown: u32,
+    /// Open image policy findings (not included in the total)
+    pub policy: u32,
+    /// Open end-of-life findings (not included in the total)
+    pub eol: u32,
+    /// Alerts in direct dependencies (only if the SBOM has dependency graph data)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direct: Option<SecurityExposure>,
+    /// Alerts in transitive dependencies (only if the SBOM has dependency graph data)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transitive: Option<SecurityExposure>,
+    /// Alerts resolved by the snapshots of the project (single project only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<SecurityResolved>,
+    /// Build of the advisories (Grype) database the snapshot was last scanned with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grype_build: Option<String>,
+    /// Version of the matcher the snapshot was last scanned with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matcher_version: Option<i32>,
+}
+
+/// Alerts resolved (fixed) by the snapshots of a project
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct SecurityResolved {
+    pub total: u32,
+    /// Resolved in the last 30 days
+    pub recent: u32,
+}
+
+/// Alert counts for the direct or transitive dependencies
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub struct SecurityExposure {
+    pub total: u32,
+    pub critical: u32,
+    pub high: u32,
+    pub medium: u32,
+    pub low: u32,
+    pub informational: u32,
+    pub unmaintained: u32,
+    pub malware: u32,
+    pub unknown: u32,
 }
 
 pub fn routes() -> Vec<rocket::Route> {
-    routes![get_alerts, get_alert]
+    routes![
+        get_alerts,
+        get_alert,
+        update_alert,
+        bulk_alerts,
+        get_top_components
+    ]
+}
+
+/// Component with the most alerts across the latest project snapshots
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct TopComponentResp {
+    id: i32,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
+    manager: String,
+    r#type: String,
+    purl: String,
+    severity: String,
+    alerts: i64,
+    projects: i64,
 }
 
 #[derive(Default, serde::Serialize, serde::Deserialize)]
@@ -35,7 +108,12 @@ pub fn routes() -> Vec<rocket::Route> {
 pub(crate) struct AlertResp {
     id: i32,
     name: String,
+    /// Kind of alert (`vulnerability`, `policy` or `eol`)
+    kind: String,
+    /// In a direct dependency of the main component (`null` without dependency graph data)
+    direct: Option<bool>,
     severity: String,
+    state: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -43,9 +121,46 @@ pub(crate) struct AlertResp {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     dependency: Option<DependencyResp>,
+    /// Component (and version) that triggered the alert
+    #[serde(skip_serializing_if = "Option::is_none")]
+    component: Option<AlertComponentResp>,
+    /// The vulnerable component did not match a dependency in the snapshot
+    unmatched: bool,
+    /// VEX statement the alert was suppressed by (`NotAffected` state)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vex: Option<AlertVexResp>,
+    /// Build of the advisories (Grype) database of the scan which found the alert
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grype_build: Option<String>,
+    /// Version of the matcher of the scan which found the alert
+    #[serde(skip_serializing_if = "Option::is_none")]
+    matcher_version: Option<i32>,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct AlertVexResp {
+    /// VEX document ID
+    document: String,
+    /// Format of the document (`openvex` or `cyclonedx`)
+    format: String,
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    justification: Option<String>,
 }
 
-#[get("/?<page>&<limit>&<search>&<state>&<severity>")]
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct AlertComponentResp {
+    purl: String,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    r#type: String,
+}
+
+#[get("/?<page>&<limit>&<search>&<state>&<severity>&<kind>&<direct>")]
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn get_alerts(
     app_state: &State<AppState>,
     _session: Session,
@@ -54,84 +169,331 @@ pub(crate) async fn get_alerts(
     state: Option<String>,
     search: Option<String>,
     severity: Option<String>,
-) -> ApiResult<ApiResponse<Vec<AlertResp>>> {
+    kind: Option<String>,
+    direct: Option<bool>,
+) -> ApiResult<ApiResponse<AlertResp>> {
     let page = Pagination::from((page, limit));
 
     let total = Alerts::count_vulnerable(&app_state.connection).await?;
-    let pages = (total as f32 / page.limit() as f32).ceil() as u32;
 
     let state = SecurityState::from(state);
 
-    let alerts = if let Some(search) = search {
+    let (alerts, count) = if let Some(search) = search {
         info!("Searching for alerts: {}", search);
-        Alerts::search(&app_state.connection, search).await?
+        let mut alerts = Alerts::search(&app_state.connection, search).await?;
+        Alerts::fetch_details(&app_state.connection, &mut alerts).await?;
+        let count = alerts.len() as u64;
+        (alerts, count)
     } else if let Some(severity) = severity {
         let severity = SecuritySeverity::from(severity);
         info!("Filtering alerts by severity: {:?}", severity);
-        Alerts::filter_severity(&app_state.connection, severity, &page).await?
+        (
+            Alerts::filter_severity(&app_state.connection, severity.clone(), &page).await?,
+            Alerts::count_severity(&app_state.connection, severity).await?,
+        )
+    } else if kind.is_some() || direct.is_some() {
+        let kind = kind.as_deref().map(parse_alert_kind).transpose()?;
+        info!(
+            "Filtering alerts by kind: {:?} / direct: {:?}",
+            kind, direct
+        );
+        (
+            Alerts::filter_kind(&app_state.connection, kind, direct, state.clone(), &page).await?,
+            Alerts::count_kind(&app_state.connection, kind, direct, state).await?,
+        )
     } else {
         info!("Getting alerts");
-        Alerts::query(
+        let mut alerts = Alerts::query(
             &app_state.connection,
             Alerts::query_select()
-                .where_eq("state", state)
+                .where_eq("state", state.clone())
                 .order_by("id", QueryOrder::Asc)
                 .page(&page)
                 .build()?,
         )
-        .await?
+        .await?;
+        let count = Alerts::row_count(
+            &app_state.connection,
+            Alerts::query_count().where_eq("state", state).build()?,
+        )
+        .await? as u64;
+        // Component details of the page
+        Alerts::fetch_details(&app_state.connection, &mut alerts).await?;
+        (alerts, count)
     };
 
     Ok(Json(ApiResponse::new(
         alerts.into_iter().map(|a| a.into()).collect(),
         total,
-        pages,
+        count,
+        page.limit() as u64,
     )))
 }
 
 #[get("/<id>")]
 pub(crate) async fn get_alert(
     state: &State<AppState>,
-    _session: Session,
+    session: Session,
     id: i32,
 ) -> ApiResult<AlertResp> {
     let mut alert = Alerts::fetch_by_primary_key(&state.connection, id).await?;
+    // Scoped agent tokens can only read the alerts of the snapshots in their scope
+    let snapshot = Snapshot::fetch_by_primary_key(&state.connection, alert.snapshot_id.key)
+        .await
+        .map_err(|_| KonarrServerError::SnapshotNotFoundError(alert.snapshot_id.key))?;
+    session
+        .authorize_snapshot(&state.connection, &snapshot, SnapshotAccess::Read)
+        .await?;
 
-    alert.fetch_advisory_id(&state.connection).await?;
+    // Fetch the snapshot, advisory and dependency (with component / version)
+    alert.fetch(&state.connection).await?;
     alert.fetch_metadata(&state.connection).await?;
 
-    alert.fetch_snapshot_id(&state.connection).await?;
+    info!(
+        "Fetched alert: {} (dep: {:?})",
+        alert.name, alert.dependency_id
+    );
+
+    Ok(Json(alert.into()))
+}
 
-    // Fetch the dependency
-    alert.fetch_dependency_id(&state.connection).await?;
-    alert.dependency_id.data.fetch(&state.connection).await?;
+#[derive(Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct AlertStateReq {
+    /// New state of the alert (`acknowledged`, `false_positive` or `vulnerable` to reopen it)
+    state: String,
+}
 
+#[patch("/<id>", data = "<data>", format = "json")]
+pub(crate) async fn update_alert(
+    state: &State<AppState>,
+    session: Session,
+    id: i32,
+    data: Json<AlertStateReq>,
+) -> ApiResult<AlertResp> {
+    if session.agent.is_some() {
+        return Err(KonarrServerError::Unauthorized);
+    }
+    let mut alert = Alerts::fetch_by_primary_key(&state.connection, id).await?;
+
+    match SecurityState::from(data.state.clone()) {
+        SecurityState::Acknowledged => {
+            alert
+                .acknowledge(&state.connection, session.user.username.clone())
+                .await?
+        }
+        SecurityState::FalsePositive => {
+            alert
+                .false_positive(&state.connection, session.user.username.clone())
+                .await?
+        }
+        SecurityState::Vulnerable => {
+            alert
+                .reopen(&state.connection, session.user.username.clone())
+                .await?
+        }
+        _ => {
+            return Err(konarr::KonarrError::InvalidData(format!(
+                "Alert state `{}` can not be set",
+                data.state
+            ))
+            .into())
+        }
+    }
     info!(
-        "Fetched alert: {} (dep: {})",
-        alert.name, alert.dependency_id
+        "Alert({}) {:?} by User({})",
+        alert.id, alert.state, session.user.id
     );
 
+    alert.fetch(&state.connection).await?;
+    alert.fetch_metadata(&state.connection).await?;
     Ok(Json(alert.into()))
 }
 
+#[derive(Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct AlertBulkReq {
+    /// Action to apply (`acknowledge`, `false-positive` or `reopen`)
+    action: String,
+    /// Reason of the change (recorded in the audit log)
+    reason: String,
+    /// Advisory ID or glob (`CVE-2024-1234`, `GHSA-*`)
+    advisory: Option<String>,
+    severity: Option<String>,
+    /// Project and its children
+    project: Option<i32>,
+    /// Package URL glob (`pkg:deb/debian/perl*`)
+    purl: Option<String>,
+    /// Only return the alerts which would change
+    #[serde(default, alias = "dry_run")]
+    dry_run: bool,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct AlertBulkResp {
+    action: String,
+    dry_run: bool,
+    /// Alerts matching the filter
+    matched: usize,
+    /// Alerts changed (or which would change for a dry run)
+    count: usize,
+    alerts: Vec<AlertResp>,
+}
+
+/// Acknowledge, mark as false positive or reopen the alerts of the latest snapshots matching
+/// a filter
+#[post("/alerts/bulk", data = "<data>", format = "json")]
+pub(crate) async fn bulk_alerts(
+    state: &State<AppState>,
+    session: Session,
+    data: Json<AlertBulkReq>,
+) -> ApiResult<AlertBulkResp> {
+    if session.agent.is_some() {
+        return Err(KonarrServerError::Unauthorized);
+    }
+    let data = data.into_inner();
+    let action = AlertBulkAction::parse(&data.action).ok_or_else(|| {
+        konarr::KonarrError::InvalidData(format!("Unknown bulk action `{}`", data.action))
+    })?;
+    let reason = data.reason.trim();
+    if reason.is_empty() {
+        return Err(konarr::KonarrError::InvalidData(
+            "A reason is required for bulk changes".to_string(),
+        )
+        .into());
+    }
+    let filter = AlertBulkFilter {
+        advisory: data.advisory,
+        severity: data.severity.map(SecuritySeverity::from),
+        project_id: data.project,
+        purl: data.purl,
+    };
+
+    let result = filter
+        .apply(
+            &state.connection,
+            action,
+            &session.user.username,
+            data.dry_run,
+        )
+        .await?;
+    info!(
+        "Bulk {} :: {} / {} alerts by User({}) (dry run: {})",
+        action,
+        result.changed.len(),
+        result.matched,
+        session.user.id,
+        data.dry_run
+    );
+
+    if !data.dry_run {
+        AuditLog::record(
+            &state.connection,
+            "alerts.bulk",
+            session.user.username.clone(),
+            "alert",
+            0,
+            Some(
+                serde_json::json!({
+                    "action": action.to_string(),
+                    "reason": reason,
+                    "filter": filter.details(),
+                    "count": result.changed.len(),
+                })
+                .to_string(),
+            ),
+        )
+        .await?;
+        state.cache.clear().await;
+    }
+
+    let mut alerts = Vec::with_capacity(result.changed.len());
+    for mut alert in result.changed {
+        alert.fetch(&state.connection).await?;
+        alerts.push(alert.into());
+    }
+    Ok(Json(AlertBulkResp {
+        action: action.to_string(),
+        dry_run: data.dry_run,
+        matched: result.matched,
+        count: alerts.len(),
+        alerts,
+    }))
+}
+
+#[get("/top-components?<limit>")]
+pub(crate) async fn get_top_components(
+    state: &State<AppState>,
+    _session: Session,
+    limit: Option<u32>,
+) -> ApiResult<Vec<TopComponentResp>> {
+    let limit = limit.unwrap_or(20).clamp(1, 100);
+    info!("Getting top {} vulnerable components", limit);
+
+    let components = Alerts::top_components(&state.connection, limit).await?;
+
+    Ok(Json(components.into_iter().map(|c| c.into()).collect()))
+}
+
+impl From<AlertComponentSummary> for TopComponentResp {
+    fn from(value: AlertComponentSummary) -> Self {
+        Self {
+            id: value.component.id.into(),
+            purl: value.component.purl(),
+            name: value.component.name,
+            namespace: value.component.namespace,
+            manager: value.component.manager.to_string(),
+            r#type: value.component.component_type.to_string(),
+            severity: value.severity.to_string(),
+            alerts: value.alerts,
+            projects: value.projects,
+        }
+    }
+}
+
 impl From<Alerts> for AlertResp {
     fn from(value: Alerts) -> Self {
         let severity = value.advisory_id.data.severity.to_string();
 
-        let dependency: DependencyResp = value.dependency_id.clone().data.into();
+        let component = value.dependency.as_ref().map(|dep| AlertComponentResp {
+            purl: dep.purl(),
+            name: dep.name(),
+            version: dep.version(),
+            r#type: dep.component_type().to_string(),
+        });
 
         Self {
             id: value.id.into(),
             name: value.name.clone(),
+            kind: value.kind().to_string(),
+            direct: value.direct(),
             severity,
+            state: value.state.to_string(),
             description: value.description(),
             url: value.url(),
-            dependency: Some(dependency),
-            ..Default::default()
+            unmatched: value.is_unmatched(),
+            component,
+            dependency: value.dependency.clone().map(|dep| dep.into()),
+            vex: value.vex.map(|vex| AlertVexResp {
+                document: vex.document,
+                format: vex.format,
+                status: vex.status.to_string(),
+                justification: vex.justification,
+            }),
+            grype_build: value.grype_build,
+            matcher_version: value.matcher_version,
         }
     }
 }
 
+/// Parse the `kind` query parameter of the alert listings
+pub(crate) fn parse_alert_kind(kind: &str) -> Result<AlertKind, KonarrServerError> {
+    AlertKind::parse(kind).ok_or_else(|| {
+        konarr::KonarrError::InvalidData(format!("Unknown alert kind `{}`", kind)).into()
+    })
+}
+
 impl From<&Snapshot> for SecuritySummary {
     fn from(snapshot: &Snapshot) -> Self {
         let total = snapshot.find_metadata_usize("security.alerts.total") as u32;
@@ -143,6 +505,11 @@ impl From<&Snapshot> for SecuritySummary {
         let unmaintained = snapshot.find_metadata_usize("security.alerts.unmaintained") as u32;
         let malware = snapshot.find_metadata_usize("security.alerts.malware") as u32;
         let unknown = snapshot.find_metadata_usize("security.alerts.unknown") as u32;
+        let policy = snapshot.find_metadata_usize("security.policy.total") as u32;
+        let eol = snapshot.find_metadata_usize("security.eol.total") as u32;
+        let direct = SecurityExposure::from_snapshot(snapshot, "direct");
+        let transitive = SecurityExposure::from_snapshot(snapshot, "transitive");
+        let provenance = ScanProvenance::from(snapshot);
 
         Self {
             total,
@@ -154,6 +521,142 @@ impl From<&Snapshot> for SecuritySummary {
             unmaintained,
             malware,
             unknown,
+            policy,
+            eol,
+            direct,
+            transitive,
+            resolved: None,
+            grype_build: provenance.grype_build,
+            matcher_version: provenance.matcher_version,
+        }
+    }
+}
+
+impl SecurityExposure {
+    /// Alert counts of the direct or transitive dependencies (`security.alerts.*.<exposure>`)
+    fn from_snapshot(snapshot: &Snapshot, exposure: &str) -> Option<Self> {
+        snapshot.find_metadata(&format!("security.alerts.total.{}", exposure))?;
+        let count = |severity: &str| {
+            snapshot.find_metadata_usize(&format!("security.alerts.{}.{}", severity, exposure))
+                as u32
+        };
+        Some(Self {
+            total: count("total"),
+            critical: count("critical"),
+            high: count("high"),
+            medium: count("medium"),
+            low: count("low"),
+            informational: count("informational"),
+            unmaintained: count("unmaintained"),
+            malware: count("malware"),
+            unknown: count("unknown"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guards::{maintenance::Maintenance, AgentTokenCache, SessionCache};
+    use konarr::models::{self, security::Advisories};
+    use rocket::{
+        http::{Header, Status},
+        local::asynchronous::Client,
+    };
+    use std::sync::{Arc, RwLock};
+
+    #[tokio::test]
+    async fn test_bulk_alerts() -> Result<(), konarr::KonarrError> {
+        let connection = crate::api::database_test().await?;
+
+        let mut advisory = Advisories::new(
+            "CVE-2024-0001",
+            models::security::AdvisorySource::Unknown,
+            SecuritySeverity::High,
+        );
+        advisory.save(&connection).await?;
+        for name in ["web", "db"] {
+            let mut project = models::Projects::new(name, models::ProjectType::Container);
+            project.save(&connection).await?;
+            let snapshot = Snapshot::create(&connection).await?;
+            let mut alert = Alerts::new("CVE-2024-0001", snapshot.id, advisory.id);
+            alert.save(&connection).await?;
+            project.add_snapshot(&connection, snapshot).await?;
         }
+
+        let mut session =
+            models::Sessions::new(models::SessionType::User, models::SessionState::Active);
+        session.save(&connection).await?;
+        let mut user = models::Users::new("user", "password", models::UserRole::User, session.id);
+        user.save(&connection).await?;
+        let (_, session) = models::Users::login(&connection, "user", "password").await?;
+        let auth = Header::new("Authorization", format!("Bearer {}", session.token));
+
+        let state = AppState {
+            connection: Arc::new(tokio::sync::Mutex::new(connection)),
+            sessions: Arc::new(RwLock::new(SessionCache::default())),
+            agent_tokens: Arc::new(RwLock::new(AgentTokenCache::new(String::new()))),
+            config: konarr::Config::default(),
+            init: true,
+            maintenance: Maintenance::default(),
+            cache: Default::default(),
+            checks: Default::default(),
+        };
+        let rocket = rocket::build()
+            .manage(state)
+            .mount("/api/security", routes());
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+
+        // The count matches the filters, the total does not
+        for (severity, count) in [("high", 2), ("low", 0)] {
+            let response = client
+                .get(format!("/api/security?severity={}", severity))
+                .header(auth.clone())
+                .dispatch()
+                .await;
+            let body: serde_json::Value = response.into_json().await.unwrap();
+            assert_eq!(body["total"], 2);
+            assert_eq!(body["count"], count, "{}", severity);
+        }
+
+        // A reason and a filter are required
+        for body in [
+            r#"{"action": "acknowledge", "reason": "", "advisory": "CVE-2024-0001"}"#,
+            r#"{"action": "acknowledge", "reason": "test-only"}"#,
+        ] {
+            let response = client
+                .post("/api/security/alerts/bulk")
+                .header(auth.clone())
+                .header(rocket::http::ContentType::JSON)
+                .body(body)
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::BadRequest);
+        }
+
+        let response = client
+            .post("/api/security/alerts/bulk")
+            .header(auth.clone())
+            .header(rocket::http::ContentType::JSON)
+            .body(r#"{"action": "false-positive", "reason": "test-only", "advisory": "CVE-2024-0001", "dry_run": true}"#)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["count"], 2);
+        assert_eq!(body["alerts"][0]["state"], "Vulnerable");
+
+        let response = client
+            .post("/api/security/alerts/bulk")
+            .header(auth.clone())
+            .header(rocket::http::ContentType::JSON)
+            .body(r#"{"action": "false-positive", "reason": "test-only", "advisory": "CVE-2024-0001"}"#)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["count"], 2);
+        assert_eq!(body["alerts"][0]["state"], "FalsePositive");
+        Ok(())
     }
 }
@@ -1,32 +1,52 @@
 use geekorm::prelude::*;
 use konarr::{
-    bom::{BomParser, Parsers},
+    bom::{vex::VexDocument, BomParser, Parsers},
     models::{
         self,
-        security::{Advisories, Alerts, SecuritySeverity},
-        SnapshotMetadataKey,
+        security::{Alerts, SecuritySeverity, VexStatements},
+        SbomUploadResult, SnapshotMetadataKey, SnapshotState,
     },
 };
-use log::{debug, info};
-use rocket::{data::ToByteUnit, serde::json::Json, State};
-use std::{collections::HashMap, str::FromStr};
+use log::{debug, info, warn};
+use rocket::{
+    data::{Limits, ToByteUnit},
+    form::Form,
+    fs::TempFile,
+    serde::json::Json,
+    State,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+use tokio::io::AsyncReadExt;
 
 use super::{
-    dependencies::DependencyResp,
-    security::{AlertResp, SecuritySummary},
+    dependencies::{parse_component_type, DependencyResp},
+    security::{parse_alert_kind, AlertResp, SecuritySummary},
     ApiResponse, ApiResult,
 };
-use crate::{error::KonarrServerError, guards::Session, AppState};
+use crate::{
+    error::KonarrServerError,
+    guards::{Session, SnapshotAccess},
+    AppState,
+};
 
 pub fn routes() -> Vec<rocket::Route> {
     routes![
         get_snapshot,
+        get_snapshot_by_digest,
         get_snapshots,
         get_snapshot_dependencies,
         get_snapshot_alerts,
         create_snapshot,
         upload_bom,
+        upload_bom_form,
+        get_snapshot_uploads,
+        upload_vex,
         patch_snapshot_metadata,
+        delete_snapshot_metadata,
+        export_snapshot,
     ]
 }
 
@@ -34,33 +54,146 @@ pub fn routes() -> Vec<rocket::Route> {
 #[serde(rename_all = "camelCase", crate = "rocket::serde")]
 pub(crate) struct SnapshotResp {
     id: i32,
+    state: String,
     created_at: chrono::DateTime<chrono::Utc>,
     dependencies: i32,
     security: SecuritySummary,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scan: Option<SnapshotScanResp>,
+    /// Components which failed to index (the snapshot is partially complete)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    errors: Option<SnapshotErrorsResp>,
+    /// If the original SBOM is stored (removed by the SBOM retention)
+    sbom_available: bool,
+    /// Dependency changes since the previous snapshot of the project
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diff: Option<models::SnapshotDiff>,
     metadata: HashMap<String, String>,
+    /// Metadata values which were skipped by an update (key and reason)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    invalid_metadata: BTreeMap<String, String>,
+}
+
+/// Components of the SBOM which failed to index
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct SnapshotErrorsResp {
+    count: usize,
+    sample: Vec<models::BomComponentError>,
+}
+
+/// How the SBOM tool was run to generate the snapshot
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct SnapshotScanResp {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    agent_host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    agent_version: Option<String>,
 }
 
 /// Get a snapshot by ID
 #[get("/<id>")]
 pub(crate) async fn get_snapshot(
     state: &State<AppState>,
-    _session: Session,
+    session: Session,
     id: u32,
 ) -> ApiResult<SnapshotResp> {
     info!("Fetching snapshot: {}", id);
-    let mut snapshot =
-        match models::Snapshot::fetch_by_primary_key(&state.connection, id as i32).await {
-            Ok(snapshot) => snapshot,
-            Err(e) => {
-                log::error!("Failed to fetch snapshot: {:?}", e);
-                return Err(KonarrServerError::SnapshotNotFoundError(id as i32).into());
-            }
-        };
+    let mut snapshot = fetch_snapshot(state, &session, id, SnapshotAccess::Read).await?;
+    snapshot.fetch_metadata(&state.connection).await?;
+
+    Ok(Json(snapshot.into()))
+}
+
+/// Get the latest completed snapshot scanned from a container image digest
+///
+/// Agents use this to reuse a snapshot of another project tracking the same image.
+#[get("/digest/<sha>", rank = 1)]
+pub(crate) async fn get_snapshot_by_digest(
+    state: &State<AppState>,
+    session: Session,
+    sha: &str,
+) -> ApiResult<SnapshotResp> {
+    info!("Fetching snapshot by digest: {}", sha);
+    let mut snapshot = models::Snapshot::find_by_container_sha(&state.connection, sha)
+        .await?
+        .ok_or(KonarrServerError::GeekOrmError(geekorm::Error::NoRowsFound))?;
+    session
+        .authorize_snapshot(&state.connection, &snapshot, SnapshotAccess::Read)
+        .await?;
     snapshot.fetch_metadata(&state.connection).await?;
 
     Ok(Json(snapshot.into()))
 }
 
+/// Export the dependencies of a snapshot (`format=github` for the GitHub dependency
+/// submission API, the `sha` / `ref` default to placeholders to be replaced by the caller)
+///
+/// `format=cyclonedx` returns the original SBOM, or an SBOM regenerated from the
+/// dependencies if it is no longer stored.
+#[get("/<id>/export?<format>&<sha>&<ref>")]
+pub(crate) async fn export_snapshot(
+    state: &State<AppState>,
+    session: Session,
+    id: u32,
+    format: Option<String>,
+    sha: Option<String>,
+    r#ref: Option<String>,
+) -> ApiResult<serde_json::Value> {
+    let format = format.unwrap_or_else(|| "github".to_string());
+    if format != "github" && format != "cyclonedx" {
+        return Err(
+            konarr::KonarrError::InvalidData(format!("Unknown export format: {}", format)).into(),
+        );
+    }
+
+    let mut snapshot = fetch_snapshot(state, &session, id, SnapshotAccess::Read).await?;
+    snapshot.fetch_metadata(&state.connection).await?;
+
+    if format == "cyclonedx" {
+        if let Some(path) = snapshot.metadata.get(&SnapshotMetadataKey::BomPath) {
+            let path = state.config.sboms_path()?.join(path.as_string());
+            match tokio::fs::read(&path).await {
+                Ok(data) => {
+                    return Ok(Json(
+                        serde_json::from_slice(&data).map_err(konarr::KonarrError::from)?,
+                    ))
+                }
+                Err(e) => warn!("Failed to read SBOM `{}`: {}", path.display(), e),
+            }
+        }
+        info!(
+            "Exporting Snapshot({}) :: regenerating the SBOM from the dependencies",
+            snapshot.id
+        );
+        return Ok(Json(snapshot.export_cyclonedx(&state.connection).await?));
+    }
+
+    let mut export = snapshot.export_github(&state.connection).await?;
+    info!(
+        "Exporting Snapshot({}) :: {} manifests ({} dependencies skipped)",
+        snapshot.id,
+        export.manifests.len(),
+        export.skipped()
+    );
+    export.sha = sha.unwrap_or_default();
+    export.git_ref = r#ref.unwrap_or_else(|| "refs/heads/main".to_string());
+    Ok(Json(
+        serde_json::to_value(export).map_err(konarr::KonarrError::from)?,
+    ))
+}
+
 #[derive(serde::Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct SnapshotCreateReq {
@@ -70,7 +203,7 @@ pub struct SnapshotCreateReq {
 #[post("/", data = "<snapshot>")]
 pub(crate) async fn create_snapshot(
     state: &State<AppState>,
-    _session: Session,
+    session: Session,
     snapshot: Json<SnapshotCreateReq>,
 ) -> ApiResult<SnapshotResp> {
     info!("Creating snapshot for Project: {}", snapshot.project_id);
@@ -92,6 +225,9 @@ pub(crate) async fn create_snapshot(
             }
         };
     debug!("Project: {:?}", project);
+    session
+        .authorize_project(&state.connection, &project)
+        .await?;
 
     let snapshot = match models::Snapshot::create(&state.connection).await {
         Ok(snapshot) => snapshot,
@@ -110,118 +246,555 @@ pub(crate) async fn create_snapshot(
 #[patch("/<id>/metadata", data = "<metadata>")]
 pub(crate) async fn patch_snapshot_metadata(
     state: &State<AppState>,
-    _session: Session,
+    session: Session,
     id: u32,
-    metadata: Json<HashMap<String, String>>,
+    metadata: Json<HashMap<String, Option<String>>>,
 ) -> ApiResult<SnapshotResp> {
     info!("Updating metadata for snapshot: {}", id);
-    let mut snapshot =
-        match models::Snapshot::fetch_by_primary_key(&state.connection, id as i32).await {
-            Ok(snapshot) => snapshot,
-            Err(e) => {
-                log::error!("Failed to fetch snapshot: {:?}", e);
-                return Err(KonarrServerError::SnapshotNotFoundError(id as i32).into());
-            }
-        };
+    let mut snapshot = fetch_snapshot(state, &session, id, SnapshotAccess::Write).await?;
     snapshot.fetch_metadata(&state.connection).await?;
+    let mut invalid = BTreeMap::new();
 
     for (key, value) in metadata.iter() {
+        let metadata_key = parse_metadata_key(key)?;
+        // Explicit nulls remove the key (empty strings are ignored)
+        let Some(value) = value else {
+            delete_metadata_key(state, &mut snapshot, &metadata_key).await?;
+            continue;
+        };
         if value.is_empty() {
             continue;
         }
-        let metadata_key = match SnapshotMetadataKey::from_str(key) {
-            Ok(key) => key,
+
+        // Invalid values are skipped (and reported) without failing the other keys
+        let value = match metadata_key.normalize(value) {
+            Ok(value) => value,
             Err(e) => {
-                log::error!("Invalid metadata key: {}", e);
-                return Err(konarr::KonarrError::InvalidData(format!(
-                    "Invalid metadata key: {}",
-                    e
-                ))
-                .into());
+                log::warn!("Skipping metadata `{}`: {}", metadata_key, e);
+                invalid.insert(key.clone(), e.to_string());
+                continue;
             }
         };
 
         log::info!("Setting metadata: {} = {}", metadata_key, value);
 
         snapshot
-            .set_metadata(&state.connection, metadata_key, value)
+            .set_metadata(&state.connection, metadata_key, &value)
             .await?;
     }
 
+    let mut resp = SnapshotResp::from(snapshot);
+    resp.invalid_metadata = invalid;
+    Ok(Json(resp))
+}
+
+/// Remove a metadata key from a snapshot
+#[delete("/<id>/metadata/<key>")]
+pub(crate) async fn delete_snapshot_metadata(
+    state: &State<AppState>,
+    session: Session,
+    id: u32,
+    key: &str,
+) -> ApiResult<SnapshotResp> {
+    info!("Removing metadata `{}` from snapshot: {}", key, id);
+    let metadata_key = parse_metadata_key(key)?;
+    let mut snapshot = fetch_snapshot(state, &session, id, SnapshotAccess::Write).await?;
+    snapshot.fetch_metadata(&state.connection).await?;
+
+    delete_metadata_key(state, &mut snapshot, &metadata_key).await?;
+
     Ok(Json(snapshot.into()))
 }
 
-#[post("/<id>/bom", data = "<data>")]
+fn parse_metadata_key(key: &str) -> Result<SnapshotMetadataKey, KonarrServerError> {
+    SnapshotMetadataKey::from_str(key).map_err(|e| {
+        log::error!("Invalid metadata key: {}", e);
+        konarr::KonarrError::InvalidData(format!("Invalid metadata key: {}", e)).into()
+    })
+}
+
+/// Remove a metadata key (the keys computed by the server can't be removed)
+async fn delete_metadata_key(
+    state: &State<AppState>,
+    snapshot: &mut models::Snapshot,
+    key: &SnapshotMetadataKey,
+) -> Result<(), KonarrServerError> {
+    if key.is_computed() {
+        return Err(konarr::KonarrError::InvalidData(format!(
+            "Metadata `{}` is computed by the server and can't be removed",
+            key
+        ))
+        .into());
+    }
+    if snapshot.delete_metadata(&state.connection, key).await? {
+        log::info!("Removed metadata: {}", key);
+    }
+    Ok(())
+}
+
+#[post("/<id>/bom", data = "<data>", rank = 2)]
 pub(crate) async fn upload_bom(
     state: &State<AppState>,
-    _session: Session,
+    session: Session,
+    limits: &Limits,
     id: u32,
     data: rocket::data::Data<'_>,
 ) -> ApiResult<SnapshotResp> {
     info!("Uploading SBOM for snapshot: {}", id);
-    let mut snapshot = models::Snapshot::fetch_by_primary_key(&state.connection, id as i32).await?;
+    let mut snapshot = start_upload(state, &session, id).await?;
+
+    let limit = limits.get("sbom").unwrap_or(50.mebibytes());
+    let data = match data.open(limit).into_bytes().await {
+        Ok(data) if data.is_complete() => Ok(data.into_inner()),
+        Ok(_) => Err(KonarrServerError::PayloadTooLarge(
+            state.config.server.limits.sbom.clone(),
+        )),
+        Err(e) => {
+            log::error!("Failed to read SBOM for snapshot({}): {}", snapshot.id, e);
+            Err(konarr::KonarrError::ParseSBOM(format!("Failed to read data: {}", e)).into())
+        }
+    };
 
-    let data = data
-        .open(10.megabytes())
-        .into_bytes()
+    store_upload(state, &session, &mut snapshot, data, None).await
+}
+
+/// Multipart SBOM upload (`curl -F sbom=@sbom.json`)
+#[derive(FromForm)]
+pub(crate) struct SbomUploadForm<'r> {
+    sbom: TempFile<'r>,
+}
+
+#[post("/<id>/bom", data = "<form>", format = "multipart/form-data", rank = 1)]
+pub(crate) async fn upload_bom_form(
+    state: &State<AppState>,
+    session: Session,
+    id: u32,
+    form: Form<SbomUploadForm<'_>>,
+) -> ApiResult<SnapshotResp> {
+    info!("Uploading SBOM file for snapshot: {}", id);
+    let mut snapshot = start_upload(state, &session, id).await?;
+
+    // Only the file name is kept (browsers / clients might send a path)
+    let filename = form
+        .sbom
+        .raw_name()
+        .map(|name| name.dangerous_unsafe_unsanitized_raw().as_str())
+        .and_then(|name| name.rsplit(['/', '\\']).next())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string());
+
+    let mut data = Vec::with_capacity(form.sbom.len() as usize);
+    let data = match form.sbom.open().await {
+        Ok(mut file) => match file.read_to_end(&mut data).await {
+            Ok(_) => Ok(data),
+            Err(e) => {
+                Err(konarr::KonarrError::ParseSBOM(format!("Failed to read file: {}", e)).into())
+            }
+        },
+        Err(e) => Err(konarr::KonarrError::ParseSBOM(format!("Failed to read file: {}", e)).into()),
+    };
+
+    store_upload(state, &session, &mut snapshot, data, filename).await
+}
+
+/// Fetch a snapshot the session has access to (404 if it does not)
+async fn fetch_snapshot(
+    state: &State<AppState>,
+    session: &Session,
+    id: u32,
+    access: SnapshotAccess,
+) -> Result<models::Snapshot, KonarrServerError> {
+    let snapshot = match models::Snapshot::fetch_by_primary_key(&state.connection, id as i32).await
+    {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            log::error!("Failed to fetch snapshot: {:?}", e);
+            return Err(KonarrServerError::SnapshotNotFoundError(id as i32));
+        }
+    };
+    session
+        .authorize_snapshot(&state.connection, &snapshot, access)
+        .await?;
+    Ok(snapshot)
+}
+
+/// Fetch the snapshot and mark it as processing
+///
+/// Snapshots which are being processed or completed are a conflict (409 with the current
+/// state), a new snapshot has to be created to upload another SBOM.
+async fn start_upload(
+    state: &State<AppState>,
+    session: &Session,
+    id: u32,
+) -> Result<models::Snapshot, KonarrServerError> {
+    let mut snapshot = fetch_snapshot(state, session, id, SnapshotAccess::Write).await?;
+
+    if !snapshot.start_processing(&state.connection).await? {
+        let current = models::Snapshot::fetch_by_primary_key(&state.connection, snapshot.id)
+            .await
+            .map(|current| current.state)
+            .unwrap_or(snapshot.state);
+        log::warn!(
+            "Snapshot({}) can not be uploaded to: {}",
+            snapshot.id,
+            current
+        );
+        return Err(KonarrServerError::SnapshotStateError {
+            id: snapshot.id.into(),
+            state: current.to_string(),
+        });
+    }
+    Ok(snapshot)
+}
+
+/// Process the uploaded SBOM data and record the upload in the history
+///
+/// Any error marks the snapshot as failed (so the upload can be retried) before the
+/// failed upload is recorded.
+async fn store_upload(
+    state: &State<AppState>,
+    session: &Session,
+    snapshot: &mut models::Snapshot,
+    data: Result<Vec<u8>, KonarrServerError>,
+    filename: Option<String>,
+) -> ApiResult<SnapshotResp> {
+    let snapshot_id: i32 = snapshot.id.into();
+    let upload =
+        |sha: String, size: Option<usize>, result, error: Option<String>| models::SbomUploads {
+            snapshot_id: snapshot_id.into(),
+            sha,
+            size_bytes: size.map(|s| s as i64),
+            uploaded_by: Some(session.actor()),
+            filename: filename.clone(),
+            uploaded_at: chrono::Utc::now(),
+            parse_result: result,
+            error,
+            ..Default::default()
+        };
+
+    let data = match data {
+        Ok(data) => data,
+        Err(e) => {
+            snapshot
+                .set_state(&state.connection, SnapshotState::Failed)
+                .await?;
+            upload(
+                String::new(),
+                None,
+                SbomUploadResult::Failed,
+                Some(e.to_string()),
+            )
+            .save(&state.connection)
+            .await?;
+            return Err(e);
+        }
+    };
+    let sha = konarr::bom::sha256(&data);
+
+    let result = match process_upload(state, session, snapshot, &data).await {
+        Ok(_) => upload(
+            sha.clone(),
+            Some(data.len()),
+            SbomUploadResult::Success,
+            None,
+        )
+        .save(&state.connection)
         .await
-        .map_err(|_| konarr::KonarrError::ParseSBOM("Failed to read data".to_string()))?;
+        .map_err(KonarrServerError::from),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(_) => {
+            snapshot
+                .set_state(&state.connection, SnapshotState::Completed)
+                .await?;
+        }
+        Err(e) => {
+            log::error!(
+                "Failed to process SBOM for snapshot({}): {}",
+                snapshot.id,
+                e
+            );
+            snapshot
+                .set_state(&state.connection, SnapshotState::Failed)
+                .await?;
+            upload(
+                sha,
+                Some(data.len()),
+                SbomUploadResult::Failed,
+                Some(e.to_string()),
+            )
+            .save(&state.connection)
+            .await?;
+            return Err(e);
+        }
+    }
+
+    let connection = std::sync::Arc::clone(&state.connection);
+    let config = state.config.clone();
+    let completed = snapshot.clone();
 
+    tokio::spawn(async move {
+        konarr::tasks::notifications::notify_snapshot_diff(&connection, &completed)
+            .await
+            .map_err(|e| {
+                log::warn!("Failed to send the snapshot changes notification: {:?}", e);
+            })
+            .ok();
+        konarr::tasks::advisories::scan(&config, &connection)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to scan projects: {:?}", e);
+            })
+            .ok();
+    });
+
+    Ok(Json(snapshot.clone().into()))
+}
+
+/// Process the uploaded SBOM (agent version, SBOM and image policy findings)
+async fn process_upload(
+    state: &State<AppState>,
+    session: &Session,
+    snapshot: &mut models::Snapshot,
+    data: &[u8],
+) -> Result<(), KonarrServerError> {
+    // Agents which did not report their version in the metadata (`User-Agent`)
+    if let Some(version) = &session.agent_version {
+        snapshot.fetch_metadata(&state.connection).await?;
+        if !snapshot
+            .metadata
+            .contains_key(&SnapshotMetadataKey::ScanAgentVersion)
+        {
+            snapshot
+                .set_metadata(
+                    &state.connection,
+                    SnapshotMetadataKey::ScanAgentVersion,
+                    &SnapshotMetadataKey::ScanAgentVersion.normalize(version)?,
+                )
+                .await?;
+        }
+    }
+
+    process_bom(state, snapshot, data).await?;
+
+    // Image policy findings (updates the snapshot summary)
+    let policy = models::security::PolicyConfig::load(&state.connection).await?;
+    if policy.is_enabled() {
+        policy.apply(&state.connection, snapshot).await?;
+        snapshot.calculate_alerts_summary(&state.connection).await?;
+    }
+    Ok(())
+}
+
+/// Parse, index, and store the uploaded SBOM for the snapshot
+async fn process_bom(
+    state: &State<AppState>,
+    snapshot: &mut models::Snapshot,
+    data: &[u8],
+) -> Result<(), KonarrServerError> {
     info!("Read SBOM data: {} bytes", data.len());
-    let bom = Parsers::parse(&data)
+    // Parsing large SBOMs is CPU heavy, keep it off the async runtime
+    let raw = data.to_vec();
+    let bom = tokio::task::spawn_blocking(move || Parsers::parse(&raw))
+        .await
+        .map_err(|e| KonarrServerError::BillOfMaterialsParseError(e.to_string()))?
         .map_err(|e| KonarrServerError::BillOfMaterialsParseError(e.to_string()))?;
-    debug!("Parsed SBOM: {:?}", bom);
+    debug!("Parsed SBOM: {} components", bom.components.len());
 
     info!("Adding SBOM to snapshot: {}", snapshot.id);
-    snapshot.add_bom(&state.connection, &bom).await?;
+    let ingest = snapshot.add_bom(&state.connection, &bom).await?;
+    info!(
+        "Indexed {} components in {}ms ({} batches)",
+        ingest.components, ingest.duration_ms, ingest.batches
+    );
 
     let id = uuid::Uuid::new_v4();
     let file_name = format!("{}.{}.json", id, bom.sbom_type.to_file_name());
     let sbom_path = state.config.sboms_path()?.join(&file_name);
 
     info!("Writing SBOM to file: {}", sbom_path.display());
-    tokio::fs::write(&sbom_path, &*data)
+    tokio::fs::write(&sbom_path, data)
         .await
         .map_err(|e| KonarrServerError::BillOfMaterialsParseError(e.to_string()))?;
 
     snapshot
         .set_metadata(&state.connection, SnapshotMetadataKey::BomPath, &file_name)
         .await?;
+    Ok(())
+}
 
-    let connection = std::sync::Arc::clone(&state.connection);
-    let config = state.config.clone();
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct SbomUploadResp {
+    id: i32,
+    sha: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_bytes: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uploaded_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filename: Option<String>,
+    uploaded_at: chrono::DateTime<chrono::Utc>,
+    result: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
 
-    tokio::spawn(async move {
-        konarr::tasks::advisories::scan(&config, &connection)
-            .await
-            .map_err(|e| {
-                log::error!("Failed to scan projects: {:?}", e);
-            })
-            .ok();
-    });
+impl From<models::SbomUploads> for SbomUploadResp {
+    fn from(upload: models::SbomUploads) -> Self {
+        Self {
+            id: upload.id.into(),
+            sha: upload.sha,
+            size_bytes: upload.size_bytes,
+            uploaded_by: upload.uploaded_by,
+            filename: upload.filename,
+            uploaded_at: upload.uploaded_at,
+            result: upload.parse_result.to_string(),
+            error: upload.error,
+        }
+    }
+}
 
-    Ok(Json(snapshot.into()))
+/// SBOM upload history for a snapshot (newest first)
+#[get("/<id>/uploads")]
+pub(crate) async fn get_snapshot_uploads(
+    state: &State<AppState>,
+    session: Session,
+    id: u32,
+) -> ApiResult<ApiResponse<SbomUploadResp>> {
+    let snapshot = fetch_snapshot(state, &session, id, SnapshotAccess::Read).await?;
+    let uploads = models::SbomUploads::fetch_by_snapshot(&state.connection, snapshot.id).await?;
+    let total = uploads.len() as u64;
+
+    Ok(Json(ApiResponse::new(
+        uploads.into_iter().map(|u| u.into()).collect(),
+        total,
+        total,
+        total,
+    )))
+}
+
+/// Result of a VEX document upload
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", crate = "rocket::serde")]
+pub(crate) struct VexUploadResp {
+    /// VEX document ID
+    document: String,
+    /// Format of the document (`openvex` or `cyclonedx`)
+    format: String,
+    /// Number of statements stored
+    statements: usize,
+    /// Number of alerts of the snapshot suppressed by the statements
+    suppressed: u64,
+}
+
+/// Upload an OpenVEX or CycloneDX VEX document for a snapshot
+#[post("/<id>/vex", data = "<data>")]
+pub(crate) async fn upload_vex(
+    state: &State<AppState>,
+    session: Session,
+    limits: &Limits,
+    id: u32,
+    data: rocket::data::Data<'_>,
+) -> ApiResult<VexUploadResp> {
+    info!("Uploading VEX document for snapshot: {}", id);
+    let mut snapshot = fetch_snapshot(state, &session, id, SnapshotAccess::Write).await?;
+
+    let limit = limits.get("sbom").unwrap_or(50.mebibytes());
+    let data = match data.open(limit).into_bytes().await {
+        Ok(data) if data.is_complete() => data.into_inner(),
+        Ok(_) => {
+            return Err(KonarrServerError::PayloadTooLarge(
+                state.config.server.limits.sbom.clone(),
+            ))
+        }
+        Err(e) => {
+            return Err(konarr::KonarrError::InvalidData(format!(
+                "Failed to read the VEX document: {}",
+                e
+            ))
+            .into())
+        }
+    };
+    let document = VexDocument::parse(&data)?;
+
+    let statements = VexStatements::store(&state.connection, &snapshot, &document).await?;
+    let suppressed = VexStatements::apply(&state.connection, &snapshot).await?;
+    snapshot.fetch_metadata(&state.connection).await?;
+    snapshot.calculate_alerts_summary(&state.connection).await?;
+    info!(
+        "Snapshot({}) :: VEX document `{}` ({} statements, {} alerts suppressed)",
+        snapshot.id,
+        document.id,
+        statements.len(),
+        suppressed
+    );
+
+    Ok(Json(VexUploadResp {
+        document: document.id,
+        format: document.format.to_string(),
+        statements: statements.len(),
+        suppressed,
+    }))
 }
 
-#[get("/<id>/dependencies?<search>&<page>&<limit>")]
+#[get("/<id>/dependencies?<search>&<manager>&<ecosystem>&<component_type>&<page>&<limit>")]
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn get_snapshot_dependencies(
     state: &State<AppState>,
-    _session: Session,
+    session: Session,
     id: u32,
     search: Option<String>,
+    manager: Option<String>,
+    ecosystem: Option<String>,
+    component_type: Option<String>,
     page: Option<u32>,
     limit: Option<u32>,
-) -> ApiResult<ApiResponse<Vec<DependencyResp>>> {
+) -> ApiResult<ApiResponse<DependencyResp>> {
     let page = page.unwrap_or(0) as usize;
     let limit = limit.unwrap_or(10) as usize;
+    let component_type = component_type
+        .as_deref()
+        .map(parse_component_type)
+        .transpose()?;
 
-    let mut snapshot = models::Snapshot::fetch_by_primary_key(&state.connection, id as i32).await?;
+    let mut snapshot = fetch_snapshot(state, &session, id, SnapshotAccess::Read).await?;
     snapshot.fetch_metadata(&state.connection).await?;
 
     let total = snapshot.find_metadata_usize("bom.dependencies.count");
+    let mut count = total;
+
+    let manager = manager.map(models::ComponentManager::from);
 
     let mut deps = if let Some(search) = search {
-        models::Dependencies::search(&state.connection, snapshot.id, search).await?
+        let deps =
+            models::Dependencies::search(&state.connection, snapshot.id, search, manager.as_ref())
+                .await?;
+        count = deps.len();
+        deps
+    } else if let Some(manager) = manager {
+        count = snapshot
+            .count_dependencies_by_manager(&state.connection, &manager)
+            .await?;
+        snapshot
+            .fetch_dependencies_by_manager(&state.connection, &manager, page, limit)
+            .await?
+    } else if let Some(ecosystem) = ecosystem {
+        let ecosystem = models::ComponentEcosystem::from(ecosystem);
+        count = snapshot
+            .count_dependencies_by_ecosystem(&state.connection, &ecosystem)
+            .await?;
+        snapshot
+            .fetch_dependencies_by_ecosystem(&state.connection, &ecosystem, page, limit)
+            .await?
+    } else if let Some(component_type) = component_type {
+        count = snapshot
+            .count_dependencies_by_component_type(&state.connection, &component_type)
+            .await?;
+        snapshot
+            .fetch_dependencies_by_component_type(&state.connection, &component_type, page, limit)
+            .await?
     } else {
         snapshot
             .fetch_dependencies(&state.connection, page, limit)
@@ -234,50 +807,71 @@ pub(crate) async fn get_snapshot_dependencies(
 
     Ok(Json(ApiResponse::new(
         deps.into_iter().map(|d| d.into()).collect(),
-        total as u32,
-        (total / limit) as u32,
+        total as u64,
+        count as u64,
+        limit as u64,
     )))
 }
 
-#[get("/<id>/alerts?<search>&<severity>&<page>&<limit>")]
+#[get("/<id>/alerts?<search>&<severity>&<kind>&<direct>&<component_type>&<page>&<limit>")]
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn get_snapshot_alerts(
     state: &State<AppState>,
-    _session: Session,
+    session: Session,
     id: u32,
     search: Option<String>,
     severity: Option<String>,
+    kind: Option<String>,
+    direct: Option<bool>,
+    component_type: Option<String>,
     page: Option<u32>,
     limit: Option<u32>,
-) -> ApiResult<ApiResponse<Vec<AlertResp>>> {
-    let snapshot = models::Snapshot::fetch_by_primary_key(&state.connection, id as i32).await?;
+) -> ApiResult<ApiResponse<AlertResp>> {
+    let snapshot = fetch_snapshot(state, &session, id, SnapshotAccess::Read).await?;
     let total = snapshot.fetch_alerts_count(&state.connection).await?;
 
     let page = Pagination::from((page, limit));
-    let pages = (total as f32 / page.limit() as f32).ceil() as u32;
-
-    let alerts: Vec<Alerts> = if let Some(_search) = search {
-        vec![] // TODO: Implement search
-    } else if let Some(severity) = severity {
-        let severity = SecuritySeverity::from(severity);
-
-        info!("Filtering alerts by severity: {:?}", severity);
-        let mut alerts = Alerts::query(
-            &state.connection,
-            Alerts::query_select()
-                .join(Advisories::table())
-                .where_eq("snapshot_id", snapshot.id)
-                .and()
-                .where_eq("Advisories.severity", severity)
-                .page(&page)
-                .build()?,
+    let kind = kind.as_deref().map(parse_alert_kind).transpose()?;
+    let component_type = component_type
+        .as_deref()
+        .map(parse_component_type)
+        .transpose()?;
+
+    let (alerts, count): (Vec<Alerts>, u64) = if let Some(_search) = search {
+        (vec![], 0) // TODO: Implement search
+    } else if severity.is_some() || kind.is_some() || direct.is_some() || component_type.is_some() {
+        let severity = severity.map(SecuritySeverity::from);
+
+        info!(
+            "Filtering alerts by severity: {:?} / kind: {:?} / direct: {:?} / component type: {:?}",
+            severity, kind, direct, component_type
+        );
+        (
+            Alerts::fetch_snapshot_page(
+                &state.connection,
+                snapshot.id.into(),
+                severity.clone(),
+                kind,
+                direct,
+                component_type.clone(),
+                &page,
+            )
+            .await?,
+            Alerts::count_snapshot_page(
+                &state.connection,
+                snapshot.id.into(),
+                severity,
+                kind,
+                direct,
+                component_type,
+            )
+            .await?,
         )
-        .await?;
-        for alert in alerts.iter_mut() {
-            alert.fetch(&state.connection).await?;
-        }
-        alerts
     } else {
-        snapshot.fetch_alerts_page(&state.connection, &page).await?
+        (
+            snapshot.fetch_alerts_page(&state.connection, &page).await?,
+            total as u64,
+        )
     };
     info!(
         "Found `{}` alerts in snapshot `{}`",
@@ -287,27 +881,28 @@ pub(crate) async fn get_snapshot_alerts(
 
     Ok(Json(ApiResponse::new(
         alerts.into_iter().map(|a| a.into()).collect(),
-        total as u32,
-        pages,
+        total as u64,
+        count,
+        page.limit() as u64,
     )))
 }
 
 #[get("/?<page>&<limit>")]
 pub async fn get_snapshots(
     state: &State<AppState>,
-    _session: Session,
+    session: Session,
     page: Option<u32>,
     limit: Option<u32>,
 ) -> ApiResult<Vec<SnapshotResp>> {
     let page = page.unwrap_or(0) as usize;
     let limit = limit.unwrap_or(25) as usize;
 
-    let mut snapshots = models::Snapshot::query(
+    // Scoped agent tokens only list the snapshots of their projects
+    let mut snapshots = models::Snapshot::fetch_page(
         &state.connection,
-        models::Snapshot::query_select()
-            .limit(limit)
-            .offset(page * limit)
-            .build()?,
+        session.project_scope(),
+        limit,
+        page * limit,
     )
     .await?;
 
@@ -315,25 +910,7 @@ pub async fn get_snapshots(
 
     for snapshot in snapshots.iter_mut() {
         snapshot.fetch_metadata(&state.connection).await?;
-
-        let mut count = 0;
-
-        let mut metadata = HashMap::new();
-        for (name, meta) in snapshot.metadata.iter() {
-            if *name == SnapshotMetadataKey::DependenciesTotal {
-                count = meta.as_string().parse().unwrap_or(0);
-                continue;
-            }
-            metadata.insert(name.to_string(), meta.as_string());
-        }
-
-        resp.push(SnapshotResp {
-            id: snapshot.id.into(),
-            created_at: snapshot.created_at,
-            dependencies: count,
-            security: SecuritySummary::default(),
-            metadata,
-        });
+        resp.push(snapshot.clone().into());
     }
 
     Ok(Json(resp))
@@ -343,21 +920,630 @@ impl From<models::Snapshot> for SnapshotResp {
     fn from(snapshot: models::Snapshot) -> Self {
         let mut count = 0;
         let mut metadata = HashMap::new();
+        let mut scan: Option<SnapshotScanResp> = None;
+        let mut errors = SnapshotErrorsResp::default();
 
         for (name, meta) in snapshot.metadata.iter() {
             if *name == SnapshotMetadataKey::DependenciesTotal {
                 count = meta.as_string().parse().unwrap_or(0);
                 continue;
+            } else if *name == SnapshotMetadataKey::BomErrorsCount {
+                errors.count = meta.as_string().parse().unwrap_or(0);
+                continue;
+            } else if *name == SnapshotMetadataKey::BomErrors {
+                errors.sample = serde_json::from_str(&meta.as_string()).unwrap_or_default();
+                continue;
+            } else if name.is_scan() {
+                let scan = scan.get_or_insert_with(SnapshotScanResp::default);
+                let value = meta.as_string();
+                match name {
+                    SnapshotMetadataKey::ScanTool => scan.tool = Some(value),
+                    SnapshotMetadataKey::ScanToolVersion => scan.tool_version = Some(value),
+                    SnapshotMetadataKey::ScanTarget => scan.target = Some(value),
+                    SnapshotMetadataKey::ScanScope => scan.scope = Some(value),
+                    SnapshotMetadataKey::ScanDuration => scan.duration_ms = value.parse().ok(),
+                    SnapshotMetadataKey::ScanAgentHost => scan.agent_host = Some(value),
+                    SnapshotMetadataKey::ScanAgentVersion => scan.agent_version = Some(value),
+                    _ => {}
+                }
+                continue;
+            } else if name.is_diff() {
+                continue;
             }
             metadata.insert(name.to_string(), meta.as_string());
         }
 
         SnapshotResp {
             id: snapshot.id.into(),
+            state: snapshot.state.to_string(),
             created_at: snapshot.created_at,
             dependencies: count,
             security: SecuritySummary::default(),
+            scan,
+            errors: (errors.count != 0).then_some(errors),
+            sbom_available: snapshot
+                .metadata
+                .contains_key(&SnapshotMetadataKey::BomPath),
+            diff: snapshot.diff(),
             metadata,
+            invalid_metadata: BTreeMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guards::{maintenance::Maintenance, AgentTokenCache, SessionCache};
+    use konarr::models::{AgentTokens, ProjectType, Projects};
+    use rocket::{
+        http::{ContentType, Header, Status},
+        local::asynchronous::Client,
+    };
+    use std::sync::{Arc, RwLock};
+    use tokio::sync::Mutex;
+
+    async fn client(connection: libsql::Connection) -> Client {
+        let state = AppState {
+            connection: Arc::new(Mutex::new(connection)),
+            sessions: Arc::new(RwLock::new(SessionCache::default())),
+            agent_tokens: Arc::new(RwLock::new(AgentTokenCache::new(String::new()))),
+            config: konarr::Config::default(),
+            init: true,
+            maintenance: Maintenance::default(),
+            cache: Default::default(),
+            checks: Default::default(),
+        };
+        let rocket = rocket::build()
+            .manage(state)
+            .mount("/api/snapshots", routes())
+            .mount("/api/projects", super::super::projects::routes())
+            .mount("/api/security", super::super::security::routes());
+        Client::tracked(rocket).await.expect("valid rocket")
+    }
+
+    #[tokio::test]
+    async fn test_seeded_snapshot() -> Result<(), konarr::KonarrError> {
+        let connection = libsql::Builder::new_local(":memory:")
+            .build()
+            .await?
+            .connect()?;
+        konarr::models::seed::seed(&connection, konarr::models::seed::SeedProfile::Demo).await?;
+        let (_, session) = models::Users::login(
+            &connection,
+            "demo-user",
+            konarr::models::seed::SEED_PASSWORD,
+        )
+        .await?;
+        let client = client(connection).await;
+
+        // First snapshot of the demo profile (`demo-server-01/nginx`)
+        let response = client
+            .get("/api/snapshots/1")
+            .header(Header::new(
+                "Authorization",
+                format!("Bearer {}", session.token),
+            ))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["dependencies"], 10);
+        assert_eq!(body["metadata"]["security.alerts.critical"], "1");
+        assert!(body.get("errors").is_none());
+        // The seeded SBOMs are not stored, the export is regenerated
+        assert_eq!(body["sbomAvailable"], false);
+        let response = client
+            .get("/api/snapshots/1/export?format=cyclonedx")
+            .header(Header::new(
+                "Authorization",
+                format!("Bearer {}", session.token),
+            ))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["bomFormat"], "CycloneDX");
+        assert_eq!(body["components"].as_array().unwrap().len(), 10);
+        assert_eq!(
+            body["metadata"]["properties"][0]["name"],
+            "konarr:regenerated"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_component_type_filter() -> Result<(), konarr::KonarrError> {
+        let connection = libsql::Builder::new_local(":memory:")
+            .build()
+            .await?
+            .connect()?;
+        konarr::models::seed::seed(&connection, konarr::models::seed::SeedProfile::Demo).await?;
+        let (_, session) = models::Users::login(
+            &connection,
+            "demo-user",
+            konarr::models::seed::SEED_PASSWORD,
+        )
+        .await?;
+        let client = client(connection).await;
+        let get = |path: &str| {
+            client
+                .get(path.to_string())
+                .header(Header::new(
+                    "Authorization",
+                    format!("Bearer {}", session.token),
+                ))
+                .dispatch()
+        };
+
+        let response = get("/api/snapshots/1/dependencies?component_type=library&limit=100").await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        let dependencies = body["data"].as_array().unwrap();
+        assert!(!dependencies.is_empty());
+        assert!(dependencies.iter().all(|d| d["type"] == "Library"));
+        assert_eq!(body["count"], dependencies.len());
+
+        // Search and package manager filters are combined
+        let name = dependencies[0]["name"].as_str().unwrap();
+        let manager = dependencies[0]["manager"].as_str().unwrap();
+        let other = if manager == "nuget" { "gem" } else { "nuget" };
+        for (manager, found) in [(manager, true), (other, false)] {
+            let response = get(&format!(
+                "/api/snapshots/1/dependencies?search={}&manager={}",
+                name, manager
+            ))
+            .await;
+            let body: serde_json::Value = response.into_json().await.unwrap();
+            let dependencies = body["data"].as_array().unwrap();
+            assert_eq!(!dependencies.is_empty(), found);
+            assert!(dependencies.iter().all(|d| d["manager"] == manager));
+        }
+
+        let response = get("/api/snapshots/1/alerts?component_type=lib&limit=100").await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        let alerts = body["data"].as_array().unwrap();
+        assert!(alerts.iter().all(|a| a["component"]["type"] == "Library"));
+        assert_eq!(body["count"], alerts.len());
+
+        let response = get("/api/snapshots/1/alerts?severity=unknown").await;
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["count"], 0);
+        assert!(body["total"].as_u64().unwrap() > 0);
+
+        // Unknown component types are rejected
+        for path in [
+            "/api/snapshots/1/dependencies?component_type=spaceship",
+            "/api/snapshots/1/alerts?component_type=spaceship",
+        ] {
+            assert_eq!(get(path).await.status(), Status::UnprocessableEntity);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_agent_token_scope() -> Result<(), konarr::KonarrError> {
+        let connection = crate::api::database_test().await?;
+
+        let mut group = Projects::new("ci", ProjectType::Group);
+        group.save(&connection).await?;
+        let mut web = Projects::new("ci/web", ProjectType::Container);
+        web.parent = group.id.into();
+        web.save(&connection).await?;
+        let mut other = Projects::new("homelab/db", ProjectType::Container);
+        other.save(&connection).await?;
+        let mut snapshot = models::Snapshot::create(&connection).await?;
+        other.add_snapshot(&connection, snapshot.clone()).await?;
+        snapshot.fetch_metadata(&connection).await?;
+        // Snapshot of the same image tracked by both projects
+        let shared = models::Snapshot::create(&connection).await?;
+        other.add_snapshot(&connection, shared.clone()).await?;
+        web.add_snapshot(&connection, shared.clone()).await?;
+
+        let (_, token) =
+            AgentTokens::create(&connection, "ci", None, Some(group.id.into())).await?;
+        // Users are not restricted to a project
+        let mut user_session =
+            models::Sessions::new(models::SessionType::User, models::SessionState::Active);
+        user_session.save(&connection).await?;
+        let mut user =
+            models::Users::new("user", "password", models::UserRole::User, user_session.id);
+        user.save(&connection).await?;
+        let (_, user_session) = models::Users::login(&connection, "user", "password").await?;
+
+        let mut advisory = models::security::Advisories::new(
+            "CVE-2024-0001",
+            models::security::AdvisorySource::Unknown,
+            models::security::SecuritySeverity::High,
+        );
+        advisory.save(&connection).await?;
+        let mut alerts = Vec::new();
+        for snapshot in [&snapshot, &shared] {
+            let mut alert =
+                models::security::Alerts::new("CVE-2024-0001", snapshot.id, advisory.id);
+            alert.save(&connection).await?;
+            alerts.push(alert);
+        }
+
+        let client = client(connection).await;
+
+        let create = |project: i32| {
+            client
+                .post("/api/snapshots")
+                .header(ContentType::JSON)
+                .header(Header::new("Authorization", token.clone()))
+                .body(format!(r#"{{"project_id":{}}}"#, project))
+        };
+
+        // Child of the scope
+        assert_eq!(create(web.id.into()).dispatch().await.status(), Status::Ok);
+        // Outside of the scope
+        let response = create(other.id.into()).dispatch().await;
+        assert_eq!(response.status(), Status::Forbidden);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert!(body["details"].as_str().unwrap().contains("scoped"));
+
+        // Projects outside of the scope can not be moved into it (or edited)
+        let patch = |project: i32, body: String| {
+            client
+                .patch(format!("/api/projects/{}", project))
+                .header(ContentType::JSON)
+                .header(Header::new("Authorization", token.clone()))
+                .body(body)
+                .dispatch()
+        };
+        let response = patch(other.id.into(), format!(r#"{{"parent":{}}}"#, group.id)).await;
+        assert_eq!(response.status(), Status::Forbidden);
+        // Projects in the scope can not be moved out of it
+        let response = patch(web.id.into(), format!(r#"{{"parent":{}}}"#, other.id)).await;
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = patch(web.id.into(), r#"{"parent":0}"#.to_string()).await;
+        assert_eq!(response.status(), Status::NotFound);
+        let response = patch(web.id.into(), format!(r#"{{"parent":{}}}"#, group.id)).await;
+        assert_eq!(response.status(), Status::Ok);
+        let state = client.rocket().state::<AppState>().unwrap();
+        let moved = Projects::fetch_by_primary_key(&state.connection, other.id).await?;
+        assert_eq!(moved.parent, 0);
+
+        // Projects (and their snapshots / alerts) outside of the scope are not found
+        let read = |path: String| {
+            client
+                .get(path)
+                .header(Header::new("Authorization", token.clone()))
+                .dispatch()
+        };
+        for (path, status) in [
+            (format!("/api/projects/{}", web.id), Status::Ok),
+            (format!("/api/projects/{}", other.id), Status::NotFound),
+            (
+                format!("/api/projects/{}/alerts/{}/timeline", web.id, advisory.id),
+                Status::Ok,
+            ),
+            (
+                format!("/api/projects/{}/alerts/{}/timeline", other.id, advisory.id),
+                Status::NotFound,
+            ),
+            (format!("/api/security/{}", alerts[1].id), Status::Ok),
+            (format!("/api/security/{}", alerts[0].id), Status::NotFound),
+        ] {
+            assert_eq!(read(path.clone()).await.status(), status, "{}", path);
+        }
+        // Only the projects in the scope are listed
+        let response = read("/api/projects?type=all&limit=100".to_string()).await;
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        let names: Vec<&str> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert!(names.contains(&"ci/web"));
+        assert!(!names.contains(&"homelab/db"));
+
+        // Snapshot of a project outside of the scope (does not leak that it exists)
+        let response = client
+            .patch(format!("/api/snapshots/{}/metadata", snapshot.id))
+            .header(ContentType::JSON)
+            .header(Header::new("Authorization", token.clone()))
+            .body(r#"{"bom.tool":"syft@1.0.0"}"#)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+        let response = client
+            .post(format!("/api/snapshots/{}/bom", snapshot.id))
+            .header(ContentType::JSON)
+            .header(Header::new("Authorization", token.clone()))
+            .body("{}")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert!(body["details"].as_str().unwrap().contains("not found"));
+
+        let get = |path: String| {
+            client
+                .get(path)
+                .header(Header::new("Authorization", token.clone()))
+                .dispatch()
+        };
+        for path in ["", "/dependencies", "/alerts", "/uploads", "/export"] {
+            let response = get(format!("/api/snapshots/{}{}", snapshot.id, path)).await;
+            assert_eq!(response.status(), Status::NotFound, "{}", path);
+        }
+        // Same as a snapshot that does not exist
+        assert_eq!(
+            get("/api/snapshots/999".to_string()).await.status(),
+            Status::NotFound
+        );
+
+        // Shared with a project in the scope: readable but not writable
+        let response = get(format!("/api/snapshots/{}/dependencies", shared.id)).await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client
+            .patch(format!("/api/snapshots/{}/metadata", shared.id))
+            .header(ContentType::JSON)
+            .header(Header::new("Authorization", token.clone()))
+            .body(r#"{"bom.tool":"syft@1.0.0"}"#)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        // Only the snapshots in the scope are listed
+        let response = get("/api/snapshots".to_string()).await;
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        let ids: Vec<i64> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["id"].as_i64().unwrap())
+            .collect();
+        assert!(ids.contains(&(i32::from(shared.id) as i64)));
+        assert!(!ids.contains(&(i32::from(snapshot.id) as i64)));
+        // Filtered before paginating: the first page is full of scoped snapshots
+        let response = get("/api/snapshots?limit=1".to_string()).await;
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body[0]["id"].as_i64(), Some(i32::from(shared.id) as i64));
+
+        // Users see every snapshot
+        let response = client
+            .get("/api/snapshots")
+            .header(Header::new(
+                "Authorization",
+                format!("Bearer {}", user_session.token),
+            ))
+            .dispatch()
+            .await;
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        let user_ids: Vec<i64> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["id"].as_i64().unwrap())
+            .collect();
+        assert!(user_ids.contains(&(i32::from(snapshot.id) as i64)));
+        assert!(user_ids.contains(&(i32::from(shared.id) as i64)));
+        assert!(user_ids.len() > ids.len());
+
+        for path in ["", "/dependencies", "/alerts"] {
+            let response = client
+                .get(format!("/api/snapshots/{}{}", snapshot.id, path))
+                .header(Header::new(
+                    "Authorization",
+                    format!("Bearer {}", user_session.token),
+                ))
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok, "{}", path);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upload_state() -> Result<(), konarr::KonarrError> {
+        let connection = crate::api::database_test().await?;
+
+        let mut project = Projects::new("ci/web", ProjectType::Container);
+        project.save(&connection).await?;
+        let snapshot = models::Snapshot::create(&connection).await?;
+        project.add_snapshot(&connection, snapshot.clone()).await?;
+        let mut completed = models::Snapshot::create(&connection).await?;
+        project.add_snapshot(&connection, completed.clone()).await?;
+        completed
+            .set_state(&connection, SnapshotState::Completed)
+            .await?;
+
+        let (_, token) = AgentTokens::create(&connection, "ci", None, None).await?;
+        let client = client(connection).await;
+        let upload = |id: i32, body: &'static str| {
+            client
+                .post(format!("/api/snapshots/{}/bom", id))
+                .header(ContentType::JSON)
+                .header(Header::new("Authorization", token.clone()))
+                .body(body)
+                .dispatch()
+        };
+
+        // Completed snapshots conflict with their current state
+        let response = upload(completed.id.into(), "{}").await;
+        assert_eq!(response.status(), Status::Conflict);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["state"], "Completed");
+
+        // Failed uploads release the snapshot and can be retried
+        let state = client.rocket().state::<AppState>().unwrap();
+        for _ in 0..2 {
+            let response = upload(snapshot.id.into(), "{}").await;
+            assert_eq!(response.status(), Status::BadRequest);
+            let failed =
+                models::Snapshot::fetch_by_primary_key(&state.connection, snapshot.id).await?;
+            assert_eq!(failed.state, SnapshotState::Failed);
+        }
+        let uploads =
+            models::SbomUploads::fetch_by_snapshot_id(&state.connection, snapshot.id).await?;
+        assert_eq!(uploads.len(), 2);
+        assert!(uploads
+            .iter()
+            .all(|upload| upload.parse_result == SbomUploadResult::Failed));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_metadata() -> Result<(), konarr::KonarrError> {
+        let connection = crate::api::database_test().await?;
+
+        let mut snapshot = models::Snapshot::create(&connection).await?;
+        for (key, value) in [
+            (SnapshotMetadataKey::ContainerLicenses, "bogus"),
+            (SnapshotMetadataKey::ContainerUrl, "https://example.com"),
+            (SnapshotMetadataKey::SecurityAlertCritical, "1"),
+        ] {
+            snapshot.set_metadata(&connection, key, value).await?;
         }
+
+        let mut session =
+            models::Sessions::new(models::SessionType::User, models::SessionState::Active);
+        session.save(&connection).await?;
+        let mut user = models::Users::new("user", "password", models::UserRole::User, session.id);
+        user.save(&connection).await?;
+        let (_, session) = models::Users::login(&connection, "user", "password").await?;
+        let auth = Header::new("Authorization", format!("Bearer {}", session.token));
+
+        let client = client(connection).await;
+        let metadata = || async {
+            let response = client
+                .get(format!("/api/snapshots/{}", snapshot.id))
+                .header(auth.clone())
+                .dispatch()
+                .await;
+            let body: serde_json::Value = response.into_json().await.unwrap();
+            body["metadata"].as_object().unwrap().clone()
+        };
+
+        // Explicit nulls remove the key, empty strings are ignored
+        let response = client
+            .patch(format!("/api/snapshots/{}/metadata", snapshot.id))
+            .header(ContentType::JSON)
+            .header(auth.clone())
+            .body(r#"{"container.licenses": null, "container.url": ""}"#)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let metadata_after = metadata().await;
+        assert!(!metadata_after.contains_key("container.licenses"));
+        assert!(metadata_after.contains_key("container.url"));
+
+        // Invalid values are skipped and reported, the other keys are still updated
+        let response = client
+            .patch(format!("/api/snapshots/{}/metadata", snapshot.id))
+            .header(ContentType::JSON)
+            .header(auth.clone())
+            .body(r#"{"container.restart_count": "many", "container.status": "Running"}"#)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        let invalid = body["invalidMetadata"].as_object().unwrap();
+        assert_eq!(invalid.len(), 1);
+        assert!(invalid["container.restart_count"]
+            .as_str()
+            .unwrap()
+            .contains("many"));
+        let metadata_after = metadata().await;
+        assert!(!metadata_after.contains_key("container.restart_count"));
+        assert_eq!(metadata_after["container.status"], "running");
+
+        let response = client
+            .delete(format!(
+                "/api/snapshots/{}/metadata/container.url",
+                snapshot.id
+            ))
+            .header(auth.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        assert!(!metadata().await.contains_key("container.url"));
+
+        // Computed by the server
+        let response = client
+            .delete(format!(
+                "/api/snapshots/{}/metadata/security.alerts.critical",
+                snapshot.id
+            ))
+            .header(auth.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upload_vex() -> Result<(), konarr::KonarrError> {
+        let connection = crate::api::database_test().await?;
+
+        let mut project = Projects::new("homelab/nginx", ProjectType::Container);
+        project.save(&connection).await?;
+        let snapshot = models::Snapshot::create(&connection).await?;
+        project.add_snapshot(&connection, snapshot.clone()).await?;
+
+        let mut dependency = models::Dependencies::from_purl(
+            &connection,
+            "pkg:deb/debian/openssl@3.0.11-1".to_string(),
+        )
+        .await?;
+        dependency.snapshot_id = snapshot.id.into();
+        dependency.save(&connection).await?;
+        let mut advisory = models::Advisories::new(
+            "CVE-2023-5678",
+            models::security::AdvisorySource::Unknown,
+            SecuritySeverity::High,
+        );
+        advisory.save(&connection).await?;
+        let mut alert = Alerts {
+            dependency_id: Some(dependency.id.into()),
+            ..Alerts::new("CVE-2023-5678", snapshot.id, advisory.id)
+        };
+        alert.save(&connection).await?;
+
+        let (_, token) = AgentTokens::create(&connection, "ci", None, None).await?;
+        let client = client(connection).await;
+
+        let response = client
+            .post(format!("/api/snapshots/{}/vex", snapshot.id))
+            .header(ContentType::JSON)
+            .header(Header::new("Authorization", token.clone()))
+            .body(include_str!("../../../src/bom/testdata/vex.cdx.json"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["format"], "cyclonedx");
+        assert_eq!(body["statements"], 3);
+        assert_eq!(body["suppressed"], 1);
+
+        let response = client
+            .get(format!("/api/snapshots/{}/alerts", snapshot.id))
+            .header(Header::new("Authorization", token.clone()))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        let alert = &body["data"][0];
+        assert_eq!(alert["state"], "NotAffected");
+        assert_eq!(
+            alert["vex"]["document"],
+            "urn:uuid:3e671687-395b-41f5-a30f-a58921a69b79"
+        );
+        assert_eq!(alert["vex"]["justification"], "code_not_reachable");
+
+        let response = client
+            .post(format!("/api/snapshots/{}/vex", snapshot.id))
+            .header(ContentType::JSON)
+            .header(Header::new("Authorization", token))
+            .body(r#"{"components": []}"#)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+        Ok(())
     }
 }
@@ -0,0 +1,123 @@
+//! # Response Cache
+//!
+//! In-process cache of the summary payloads polled by the dashboard (server info,
+//! security summary and statistics). Entries expire after the TTL of their key and are
+//! stale once the statistics / alerts tasks finished (see
+//! [konarr::tasks::summaries_generation]).
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use rocket::serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::RwLock;
+
+use crate::error::KonarrServerError;
+
+/// Cached payloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheKey {
+    /// Projects and dependencies summaries of the server info
+    ServerInfo,
+    /// Security alerts summary
+    SecuritySummary,
+    /// Statistics settings
+    Statistics,
+}
+
+impl CacheKey {
+    /// Time the payload is cached for
+    pub fn ttl(&self) -> Duration {
+        match self {
+            CacheKey::ServerInfo => Duration::from_secs(30),
+            CacheKey::SecuritySummary => Duration::from_secs(30),
+            CacheKey::Statistics => Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    cached_at: Instant,
+    /// Summaries generation the payload was loaded in
+    generation: u64,
+    value: serde_json::Value,
+}
+
+/// Cache of the summary payloads
+#[derive(Debug)]
+pub struct ResponseCache {
+    entries: RwLock<HashMap<CacheKey, CacheEntry>>,
+    /// Current summaries generation (the cached payloads of older generations are stale)
+    generation: fn() -> u64,
+    /// Number of payloads loaded from the database
+    loads: AtomicU64,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::with_generation(konarr::tasks::summaries_generation)
+    }
+}
+
+impl ResponseCache {
+    /// Cache invalidated when the generation changes
+    pub fn with_generation(generation: fn() -> u64) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            generation,
+            loads: AtomicU64::new(0),
+        }
+    }
+
+    /// Get a cached payload or load it (`fresh` always loads it)
+    pub async fn get_or_load<T, F, Fut>(
+        &self,
+        key: CacheKey,
+        fresh: bool,
+        load: F,
+    ) -> Result<T, KonarrServerError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, KonarrServerError>>,
+    {
+        let generation = (self.generation)();
+        if !fresh {
+            let entries = self.entries.read().await;
+            if let Some(entry) = entries.get(&key) {
+                if entry.generation == generation && entry.cached_at.elapsed() < key.ttl() {
+                    if let Ok(value) = serde_json::from_value(entry.value.clone()) {
+                        return Ok(value);
+                    }
+                }
+            }
+        }
+
+        log::debug!("Loading the {:?} payload", key);
+        self.loads.fetch_add(1, Ordering::Relaxed);
+        let value = load().await?;
+        self.entries.write().await.insert(
+            key,
+            CacheEntry {
+                cached_at: Instant::now(),
+                generation,
+                value: serde_json::to_value(&value).map_err(konarr::KonarrError::from)?,
+            },
+        );
+        Ok(value)
+    }
+
+    /// Remove every cached payload
+    pub async fn clear(&self) {
+        self.entries.write().await.clear();
+    }
+
+    /// Number of payloads loaded (not served from the cache)
+    #[cfg(test)]
+    pub fn loads(&self) -> u64 {
+        self.loads.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,353 @@
+//! Startup self-checks of the server configuration
+use konarr::{
+    utils::config::{ServerConfig, SessionsConfig},
+    Config,
+};
+use log::{error, info, warn};
+use std::path::Path;
+
+/// Status of a startup check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase", crate = "rocket::serde")]
+pub enum CheckStatus {
+    Pass,
+    /// Works but is likely not what was intended
+    Warn,
+    /// The server should not start
+    Fail,
+}
+
+/// Result of a single startup check
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+impl CheckResult {
+    fn new(name: &str, status: CheckStatus, message: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            message: message.into(),
+        }
+    }
+    fn pass(name: &str, message: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Pass, message)
+    }
+    fn warn(name: &str, message: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Warn, message)
+    }
+    fn fail(name: &str, message: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Fail, message)
+    }
+}
+
+/// Run all the startup checks against the configuration
+///
+/// A missing server secret is generated and saved to the configuration file.
+pub fn run(config: &mut Config) -> Vec<CheckResult> {
+    let mut results = vec![check_secret(config)];
+
+    let data = config.data_path().map(|p| p.clone());
+    match data {
+        Ok(data) => {
+            results.push(check_writable("data", &data));
+            results.push(check_grypedb(&data.join("grypedb")));
+        }
+        Err(e) => {
+            results.push(CheckResult::fail(
+                "data",
+                format!("Failed to create the data path: {}", e),
+            ));
+        }
+    }
+    results.push(check_database(config.database.path.as_deref()));
+    results.push(check_frontend(&config.server.frontend));
+    results.push(check_cors(config));
+    results.push(check_sessions(config.sessions()));
+    results
+}
+
+/// If any of the checks failed
+pub fn failed(results: &[CheckResult]) -> bool {
+    results.iter().any(|r| r.status == CheckStatus::Fail)
+}
+
+/// Print the check results as a table
+pub fn log(results: &[CheckResult]) {
+    info!("Startup checks:");
+    for result in results {
+        match result.status {
+            CheckStatus::Pass => info!(" PASS  {:<10} {}", result.name, result.message),
+            CheckStatus::Warn => warn!(" WARN  {:<10} {}", result.name, result.message),
+            CheckStatus::Fail => error!(" FAIL  {:<10} {}", result.name, result.message),
+        }
+    }
+}
+
+/// The server secret signs the session cookies, a random one per run logs everyone out
+fn check_secret(config: &mut Config) -> CheckResult {
+    if !config.server.secret.is_empty() {
+        return CheckResult::pass("secret", "Server secret is set");
+    }
+    config.server.secret = ServerConfig::generate_secret();
+
+    if config.is_env_only() {
+        CheckResult::warn(
+            "secret",
+            "Generated a secret for this run, set `KONARR_SERVER_SECRET` to keep sessions across restarts",
+        )
+    } else {
+        match config.autosave() {
+            Ok(_) => CheckResult::pass("secret", "Generated and saved a new server secret"),
+            Err(e) => CheckResult::fail(
+                "secret",
+                format!("Failed to save the generated server secret: {}", e),
+            ),
+        }
+    }
+}
+
+/// Check the directory exists and a file can be created in it
+fn check_writable(name: &str, path: &Path) -> CheckResult {
+    if !path.is_dir() {
+        return CheckResult::fail(name, format!("{} is not a directory", path.display()));
+    }
+    let probe = path.join(format!(".konarr-check-{}", std::process::id()));
+    match std::fs::write(&probe, b"") {
+        Ok(_) => {
+            std::fs::remove_file(&probe).ok();
+            CheckResult::pass(name, format!("{} is writable", path.display()))
+        }
+        Err(e) => CheckResult::fail(name, format!("{} is not writable: {}", path.display(), e)),
+    }
+}
+
+/// Local databases need a writable directory (SQLite creates journal files next to it)
+fn check_database(path: Option<&str>) -> CheckResult {
+    match path {
+        None | Some(":memory:") => CheckResult::warn(
+            "database",
+            "Using an in-memory database, data is lost on restart",
+        ),
+        Some(path) if path.starts_with("libsql:") => {
+            CheckResult::pass("database", format!("Remote database {}", path))
+        }
+        Some(path) if path.starts_with("/") || path.starts_with("./") || path.starts_with("\\") => {
+            let path = Path::new(path);
+            if path.is_dir() {
+                return CheckResult::fail("database", format!("{} is a directory", path.display()));
+            }
+            if let Some(parent) = path.parent() {
+                if let Err(e) = std::fs::create_dir_all(parent) {
+                    return CheckResult::fail(
+                        "database",
+                        format!("Failed to create {}: {}", parent.display(), e),
+                    );
+                }
+                let result = check_writable("database", parent);
+                if result.status == CheckStatus::Fail {
+                    return result;
+                }
+            }
+            match std::fs::metadata(path) {
+                Ok(meta) if meta.permissions().readonly() => {
+                    CheckResult::fail("database", format!("{} is read-only", path.display()))
+                }
+                _ => CheckResult::pass("database", format!("{} is writable", path.display())),
+            }
+        }
+        Some(path) => CheckResult::fail(
+            "database",
+            format!(
+                "Invalid database path `{}` (use an absolute path, `./` or `libsql://`)",
+                path
+            ),
+        ),
+    }
+}
+
+/// The GrypeDB path is a directory the advisories are downloaded into
+fn check_grypedb(path: &Path) -> CheckResult {
+    if path.is_file() {
+        CheckResult::fail(
+            "grypedb",
+            format!("{} is a file, expected a directory", path.display()),
+        )
+    } else {
+        CheckResult::pass("grypedb", format!("{}", path.display()))
+    }
+}
+
+/// Without a built frontend the server runs in API-only mode
+fn check_frontend(path: &Path) -> CheckResult {
+    if path.is_file() {
+        CheckResult::fail(
+            "frontend",
+            format!("{} is a file, expected a directory", path.display()),
+        )
+    } else if path.join("index.html").is_file() {
+        CheckResult::pass("frontend", format!("Serving {}", path.display()))
+    } else {
+        CheckResult::warn(
+            "frontend",
+            format!(
+                "No frontend found in {}, running in API-only mode",
+                path.display()
+            ),
+        )
+    }
+}
+
+fn check_cors(config: &Config) -> CheckResult {
+    match config.frontend_url() {
+        Err(e) => CheckResult::fail("cors", format!("Invalid server domain: {}", e)),
+        Ok(_) if !config.server.cors => {
+            CheckResult::warn("cors", "CORS is disabled, allowing all origins")
+        }
+        Ok(Some(url)) => CheckResult::pass("cors", format!("Allowing origin {}", url)),
+        Ok(None) => CheckResult::warn(
+            "cors",
+            "CORS is enabled but `server.domain` is not set, allowing all origins",
+        ),
+    }
+}
+
+fn check_sessions(sessions: &SessionsConfig) -> CheckResult {
+    let invalid: Vec<&str> = [
+        ("admins", sessions.admins.expires),
+        ("users", sessions.users.expires),
+        ("agents", sessions.agents.expires),
+    ]
+    .into_iter()
+    .filter(|(_, expires)| *expires <= 0)
+    .map(|(role, _)| role)
+    .collect();
+
+    if invalid.is_empty() {
+        CheckResult::pass("sessions", "Session expiry is valid")
+    } else {
+        CheckResult::fail(
+            "sessions",
+            format!(
+                "Session expiry must be greater than 0 hours ({})",
+                invalid.join(", ")
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tempdir(name: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("konarr-checks-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_secret() {
+        let mut config = Config::default();
+        config.server.secret = "secret".to_string();
+        assert_eq!(check_secret(&mut config).status, CheckStatus::Pass);
+
+        let mut config = Config::load_env().unwrap();
+        config.server.secret = String::new();
+        let result = check_secret(&mut config);
+        assert_eq!(result.status, CheckStatus::Warn);
+        assert!(!config.server.secret.is_empty());
+
+        // Persisted to the config file
+        let path = tempdir("secret").join("konarr.yml");
+        let config = Config::default();
+        config.save(&path).unwrap();
+        let mut config = Config::load(&path).unwrap();
+        assert!(config.server.secret.is_empty());
+        assert_eq!(check_secret(&mut config).status, CheckStatus::Pass);
+        let saved = Config::load(&path).unwrap();
+        assert_eq!(saved.server.secret, config.server.secret);
+    }
+
+    #[test]
+    fn test_writable() {
+        let path = tempdir("writable");
+        assert_eq!(check_writable("data", &path).status, CheckStatus::Pass);
+        assert_eq!(
+            check_writable("data", &path.join("missing")).status,
+            CheckStatus::Fail
+        );
+    }
+
+    #[test]
+    fn test_database() {
+        assert_eq!(check_database(None).status, CheckStatus::Warn);
+        assert_eq!(check_database(Some(":memory:")).status, CheckStatus::Warn);
+        assert_eq!(
+            check_database(Some("libsql://konarr.example.com")).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(check_database(Some("konarr.db")).status, CheckStatus::Fail);
+
+        let path = tempdir("database");
+        let db = path.join("konarr.db");
+        assert_eq!(check_database(db.to_str()).status, CheckStatus::Pass);
+        assert_eq!(check_database(path.to_str()).status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_grypedb() {
+        let path = tempdir("grypedb");
+        assert_eq!(check_grypedb(&path).status, CheckStatus::Pass);
+
+        let file = path.join("grypedb");
+        std::fs::write(&file, b"").unwrap();
+        assert_eq!(check_grypedb(&file).status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_frontend() {
+        let path = tempdir("frontend");
+        assert_eq!(check_frontend(&path).status, CheckStatus::Warn);
+
+        std::fs::write(path.join("index.html"), b"").unwrap();
+        assert_eq!(check_frontend(&path).status, CheckStatus::Pass);
+        assert_eq!(
+            check_frontend(&path.join("index.html")).status,
+            CheckStatus::Fail
+        );
+    }
+
+    #[test]
+    fn test_cors() {
+        let mut config = Config::default();
+        assert_eq!(check_cors(&config).status, CheckStatus::Warn);
+
+        config.server.cors = true;
+        assert_eq!(check_cors(&config).status, CheckStatus::Warn);
+
+        config.server.domain = Some("konarr.example.com".to_string());
+        assert_eq!(check_cors(&config).status, CheckStatus::Pass);
+
+        config.server.domain = Some("not a domain".to_string());
+        assert_eq!(check_cors(&config).status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_sessions() {
+        let mut sessions = SessionsConfig::default();
+        assert_eq!(check_sessions(&sessions).status, CheckStatus::Pass);
+
+        sessions.users.expires = 0;
+        sessions.agents.expires = -1;
+        let result = check_sessions(&sessions);
+        assert_eq!(result.status, CheckStatus::Fail);
+        assert!(result.message.contains("users, agents"));
+        assert!(failed(&[result]));
+    }
+}
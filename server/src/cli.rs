@@ -20,6 +20,14 @@ pub struct Arguments {
     /// Path to the configuration file
     #[clap(short, long, default_value = "config/konarr.yml")]
     pub config: PathBuf,
+
+    /// Only load the configuration from the environment (no configuration file)
+    #[clap(long, env = "KONARR_NO_CONFIG", default_value_t = false)]
+    pub no_config: bool,
+
+    /// Start the server even if the startup checks fail
+    #[clap(long, env = "KONARR_SKIP_CHECKS", default_value_t = false)]
+    pub skip_checks: bool,
 }
 
 pub fn init() -> Arguments {
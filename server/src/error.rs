@@ -19,17 +19,52 @@ pub enum KonarrServerError {
     /// Project Not Found Error
     #[error("Project {0} not found")]
     ProjectNotFoundError(i32),
+    /// Project with the same name already exists
+    #[error("Project `{name}` already exists ({id})")]
+    ProjectExistsError {
+        /// Name of the project
+        name: String,
+        /// ID of the existing project
+        id: i32,
+    },
     /// Snapshot Not Found Error
     #[error("Snapshot {0} not found")]
     SnapshotNotFoundError(i32),
+    /// Snapshot can not be uploaded to in its current state (processing or completed)
+    #[error("Snapshot {id} can not be uploaded to while it is `{state}`")]
+    SnapshotStateError {
+        /// ID of the snapshot
+        id: i32,
+        /// Current state of the snapshot
+        state: String,
+    },
+
+    /// Public status page is disabled (`public.dashboard.enabled`)
+    #[error("Not Found")]
+    PublicDashboardDisabled,
+
+    /// Request body is larger than the configured limit
+    #[error("Request body is larger than the limit ({0})")]
+    PayloadTooLarge(String),
+
+    /// Query parameter with an unknown value
+    #[error("Invalid query parameter: {0}")]
+    InvalidQuery(String),
 
     /// Bill of Materials Parsing Error
     #[error("Failed to parse bill of materials: {0}")]
     BillOfMaterialsParseError(String),
 
+    /// Server is in maintenance mode (`maintenance.enabled`)
+    #[error("Server is in maintenance mode (read-only), try again later")]
+    Maintenance,
+
     /// Unauthorized Error
     #[error("Unauthorized")]
     Unauthorized,
+    /// Forbidden (the agent token is not scoped to the project)
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
     /// Readonly property/field cannot be modified
     #[error("Readonly property/field cannot be modified: {0}")]
     UnauthorizedReadonly(String),
@@ -48,3 +83,41 @@ pub enum KonarrServerError {
     #[error("GeekOrm Error: {0}")]
     GeekOrmError(#[from] geekorm::Error),
 }
+
+impl KonarrServerError {
+    /// HTTP status code of the error
+    pub fn status_code(&self) -> u16 {
+        match self {
+            KonarrServerError::DependencyNotFoundError(_)
+            | KonarrServerError::ProjectNotFoundError(_)
+            | KonarrServerError::SnapshotNotFoundError(_)
+            | KonarrServerError::PublicDashboardDisabled
+            | KonarrServerError::GeekOrmError(geekorm::Error::NoRowsFound) => 404,
+            KonarrServerError::ProjectExistsError { .. }
+            | KonarrServerError::SnapshotStateError { .. } => 409,
+            KonarrServerError::PayloadTooLarge(_) => 413,
+            KonarrServerError::BillOfMaterialsParseError(_) => 400,
+            KonarrServerError::InvalidQuery(_) => 422,
+            KonarrServerError::Unauthorized => 401,
+            KonarrServerError::Forbidden(_) => 403,
+            KonarrServerError::Maintenance => 503,
+            error if error.is_database_busy() => 503,
+            KonarrServerError::KonarrError(error) => error.status_code(),
+            _ => 500,
+        }
+    }
+
+    /// Check if the error is a SQLite busy / locked error (the request can be retried)
+    pub fn is_database_busy(&self) -> bool {
+        match self {
+            KonarrServerError::KonarrError(error) => error.is_database_busy(),
+            KonarrServerError::DatabaseError(error) => {
+                konarr::models::is_database_busy(error.to_string())
+            }
+            KonarrServerError::GeekOrmError(error) => {
+                konarr::models::is_database_busy(error.to_string())
+            }
+            _ => false,
+        }
+    }
+}
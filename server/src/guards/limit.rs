@@ -1,8 +1,11 @@
-//! Rate limiting guard.
-use rocket::serde::json::Json;
+//! Rate limiting guard and request body size limits.
+use rocket::{serde::json::Json, Request};
 use rocket_governor::{Method, Quota, RocketGovernable};
 
-use crate::api::{ApiError, ApiErrorResponse};
+use crate::{
+    api::{ApiError, ApiErrorResponse},
+    AppState,
+};
 
 pub struct RateLimit;
 
@@ -15,6 +18,36 @@ pub async fn rate_limit() -> ApiErrorResponse {
                 message: "Rate limit exceeded".to_string(),
                 details: None,
                 status: 429,
+                id: None,
+                state: None,
+            }),
+        ),
+    }
+}
+
+/// Request body is larger than the configured limit
+#[rocket::catch(413)]
+pub async fn payload_too_large(request: &Request<'_>) -> ApiErrorResponse {
+    let limits = request
+        .rocket()
+        .state::<AppState>()
+        .map(|state| state.config.server.limits.clone())
+        .unwrap_or_default();
+    let limit = if request.uri().path().ends_with("/bom") {
+        limits.sbom
+    } else {
+        limits.json
+    };
+
+    ApiErrorResponse::PayloadTooLarge {
+        inner: (
+            rocket::http::Status::PayloadTooLarge,
+            Json(ApiError {
+                message: "Payload Too Large".to_string(),
+                details: Some(format!("Request body is larger than the limit ({})", limit)),
+                status: 413,
+                id: None,
+                state: None,
             }),
         ),
     }
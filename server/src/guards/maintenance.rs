@@ -0,0 +1,173 @@
+//! Maintenance mode (read-only server)
+//!
+//! While `maintenance.enabled` is set, the mutating requests (POST, PUT, PATCH and
+//! DELETE) are rerouted to the maintenance routes which respond with a
+//! `503 Service Unavailable`. Logging in and toggling the maintenance mode are still
+//! allowed so an admin can turn it off.
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use log::debug;
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{uri::Origin, Method},
+    Data, Request,
+};
+
+use crate::error::KonarrServerError;
+
+/// Paths of the mutating routes still allowed in maintenance mode
+pub const MAINTENANCE_ALLOWED: [&str; 2] = ["/api/auth/login", "/api/admin/maintenance"];
+/// Path the blocked requests are rerouted to (see [routes])
+const MAINTENANCE_PATH: &str = "/api/maintenance";
+
+/// Maintenance mode flag, shared by the fairing and the server state
+#[derive(Debug, Clone, Default)]
+pub struct Maintenance(Arc<AtomicBool>);
+
+impl Maintenance {
+    pub fn new(enabled: bool) -> Self {
+        Self(Arc::new(AtomicBool::new(enabled)))
+    }
+
+    /// If the server is in maintenance mode
+    pub fn enabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable the maintenance mode
+    pub fn set(&self, enabled: bool) {
+        self.0.store(enabled, Ordering::Relaxed);
+    }
+
+    /// If the request is blocked by the maintenance mode
+    pub fn blocks(&self, method: Method, path: &str) -> bool {
+        self.enabled()
+            && matches!(
+                method,
+                Method::Post | Method::Put | Method::Patch | Method::Delete
+            )
+            && !MAINTENANCE_ALLOWED.contains(&path.trim_end_matches('/'))
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for Maintenance {
+    fn info(&self) -> Info {
+        Info {
+            name: "Maintenance Mode",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        if self.blocks(request.method(), request.uri().path().as_str()) {
+            debug!(
+                "Maintenance mode :: blocking {} {}",
+                request.method(),
+                request.uri()
+            );
+            request.set_uri(Origin::path_only(MAINTENANCE_PATH));
+        }
+    }
+}
+
+/// Routes the blocked requests are rerouted to (mounted on `/api`)
+pub fn routes() -> Vec<rocket::Route> {
+    routes![blocked_post, blocked_put, blocked_patch, blocked_delete]
+}
+
+#[post("/maintenance")]
+async fn blocked_post() -> Result<(), KonarrServerError> {
+    Err(KonarrServerError::Maintenance)
+}
+
+#[put("/maintenance")]
+async fn blocked_put() -> Result<(), KonarrServerError> {
+    Err(KonarrServerError::Maintenance)
+}
+
+#[patch("/maintenance")]
+async fn blocked_patch() -> Result<(), KonarrServerError> {
+    Err(KonarrServerError::Maintenance)
+}
+
+#[delete("/maintenance")]
+async fn blocked_delete() -> Result<(), KonarrServerError> {
+    Err(KonarrServerError::Maintenance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        guards::{AgentTokenCache, SessionCache},
+        AppState,
+    };
+    use rocket::{
+        http::{ContentType, Status},
+        local::asynchronous::Client,
+    };
+    use std::sync::RwLock;
+    use tokio::sync::Mutex;
+
+    #[test]
+    fn test_maintenance_blocks() {
+        let maintenance = Maintenance::default();
+        assert!(!maintenance.blocks(Method::Post, "/api/projects"));
+
+        maintenance.set(true);
+        assert!(maintenance.blocks(Method::Post, "/api/projects"));
+        assert!(maintenance.blocks(Method::Delete, "/api/projects/1"));
+        assert!(!maintenance.blocks(Method::Get, "/api/projects"));
+        assert!(!maintenance.blocks(Method::Post, "/api/auth/login"));
+        assert!(!maintenance.blocks(Method::Post, "/api/admin/maintenance/"));
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_mode() -> Result<(), konarr::KonarrError> {
+        let connection = crate::api::database_test().await?;
+
+        let maintenance = Maintenance::new(true);
+        let state = AppState {
+            connection: Arc::new(Mutex::new(connection)),
+            sessions: Arc::new(RwLock::new(SessionCache::default())),
+            agent_tokens: Arc::new(RwLock::new(AgentTokenCache::new(String::new()))),
+            config: konarr::Config::default(),
+            init: true,
+            maintenance: maintenance.clone(),
+            cache: Default::default(),
+            checks: Default::default(),
+        };
+        let rocket = rocket::build()
+            .manage(state)
+            .attach(maintenance.clone())
+            .mount("/api", routes())
+            .mount("/api/projects", crate::api::projects::routes());
+        let client = Client::tracked(rocket).await.expect("valid rocket");
+
+        let create = || {
+            client
+                .post("/api/projects")
+                .header(ContentType::JSON)
+                .body(r#"{"name":"web","type":"Container"}"#)
+        };
+
+        // Mutating routes are blocked, reads are not
+        let response = create().dispatch().await;
+        assert_eq!(response.status(), Status::ServiceUnavailable);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["status"], 503);
+        assert_ne!(
+            client.get("/api/projects").dispatch().await.status(),
+            Status::ServiceUnavailable
+        );
+
+        // Back to normal (unauthenticated)
+        maintenance.set(false);
+        assert_eq!(create().dispatch().await.status(), Status::Unauthorized);
+        Ok(())
+    }
+}
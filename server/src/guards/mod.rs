@@ -1,26 +1,270 @@
 //! # Guards
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use konarr::models::{
-    settings::{keys::Setting, ServerSettings},
-    Sessions, UserRole, Users,
+use geekorm::GeekConnection;
+use konarr::{
+    models::{
+        auth::tokens::AGENT_TOKEN_PREFIX,
+        settings::{keys::Setting, ServerSettings},
+        AgentCertificates, AgentTokens, Projects, Sessions, Snapshot, UserRole, Users,
+    },
+    utils::{config::SessionsConfig, version::user_agent_version},
 };
 use rocket::{
+    mtls::Certificate,
     outcome::try_outcome,
     request::{FromRequest, Outcome, Request},
+    serde::json::Json,
     State,
 };
 use tokio::sync::Mutex;
 
 pub mod limit;
+pub mod maintenance;
 
-use crate::{error::KonarrServerError, AppState};
+use crate::{
+    api::{ApiError, ApiErrorResponse},
+    error::KonarrServerError,
+    AppState,
+};
 
 #[derive(Debug, Clone)]
 pub struct Session {
     pub user: Users,
     #[allow(unused)]
     pub session: Sessions,
+    /// Agent token used (if the session is for an agent)
+    pub agent: Option<AgentIdentity>,
+    /// Konarr version reported by the agent (`User-Agent`)
+    pub agent_version: Option<String>,
+    /// How the request was authenticated
+    pub auth: AuthMethod,
+}
+
+/// How a request was authenticated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuthMethod {
+    /// Session cookie (web UI)
+    #[default]
+    Cookie,
+    /// Session token with the `Bearer` scheme (CLI / personal token)
+    Bearer,
+    /// Agent key or scoped agent token
+    Agent,
+}
+
+impl std::fmt::Display for AuthMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthMethod::Cookie => write!(f, "cookie"),
+            AuthMethod::Bearer => write!(f, "bearer"),
+            AuthMethod::Agent => write!(f, "agent"),
+        }
+    }
+}
+
+/// Agent identity from the token used to authenticate
+#[derive(Debug, Clone, PartialEq)]
+pub enum AgentIdentity {
+    /// Legacy shared agent key (`agent.key` setting)
+    Legacy,
+    /// Scoped agent token
+    Token {
+        /// Name of the token
+        name: String,
+        /// Project whose subtree the token is restricted to
+        project_scope: Option<i32>,
+        /// When the token expires
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    },
+}
+
+/// Reason a request was not authorized (returned by the 401 catcher)
+#[derive(Debug, Default)]
+pub struct AuthFailure(pub Option<&'static str>);
+
+/// Cached agent tokens
+#[derive(Debug, Default)]
+pub struct AgentTokenCache {
+    /// Legacy shared agent key
+    pub legacy: String,
+    /// Scoped agent tokens (by token hash)
+    pub tokens: HashMap<String, AgentTokens>,
+}
+
+impl AgentTokenCache {
+    /// Create the cache with the legacy agent key
+    pub fn new(legacy: String) -> Self {
+        Self {
+            legacy,
+            tokens: HashMap::new(),
+        }
+    }
+
+    /// Invalidate a cached scoped token (on revoke)
+    pub fn invalidate(&mut self, token_hash: &str) {
+        self.tokens.remove(token_hash);
+    }
+}
+
+/// Number of cached sessions before the invalid sessions are pruned
+pub const SESSIONS_CACHE_LIMIT: usize = 1024;
+
+/// Cached user sessions (by session token)
+#[derive(Debug, Default)]
+pub struct SessionCache {
+    sessions: HashMap<String, Session>,
+}
+
+impl SessionCache {
+    /// Get a cached session by token
+    pub fn get(&self, token: &str) -> Option<&Session> {
+        self.sessions.get(token)
+    }
+
+    /// Cache a session
+    ///
+    /// The token of a browser session is regenerated on login so the previous tokens
+    /// of the same session are removed. The invalid sessions are pruned once the cache
+    /// is over [SESSIONS_CACHE_LIMIT].
+    pub fn insert(&mut self, session: Session, config: &SessionsConfig) {
+        self.sessions
+            .retain(|_, s| s.session.id != session.session.id);
+        self.sessions.insert(session.session.token.clone(), session);
+
+        if self.sessions.len() > SESSIONS_CACHE_LIMIT {
+            self.prune(config);
+        }
+    }
+
+    /// Remove a cached session by token
+    pub fn remove(&mut self, token: &str) {
+        self.sessions.remove(token);
+    }
+
+    /// Remove the sessions which are no longer valid (returns the number removed)
+    pub fn prune(&mut self, config: &SessionsConfig) -> usize {
+        let before = self.sessions.len();
+        self.sessions.retain(|_, s| s.user.validate_session(config));
+        before - self.sessions.len()
+    }
+}
+
+impl Session {
+    /// Name of who performed the action (username or agent token)
+    pub fn actor(&self) -> String {
+        match &self.agent {
+            Some(AgentIdentity::Token { name, .. }) => format!("{}:{}", self.user.username, name),
+            _ => self.user.username.clone(),
+        }
+    }
+
+    /// Project scope of the agent token (if the session is for a scoped agent token)
+    pub fn project_scope(&self) -> Option<i32> {
+        match &self.agent {
+            Some(AgentIdentity::Token { project_scope, .. }) => *project_scope,
+            _ => None,
+        }
+    }
+
+    /// When the session (or agent token) expires
+    ///
+    /// User sessions are extended on each access, the legacy agent key never expires.
+    pub fn expires_at(&self, config: &SessionsConfig) -> Option<chrono::DateTime<chrono::Utc>> {
+        match &self.agent {
+            Some(AgentIdentity::Token { expires_at, .. }) => *expires_at,
+            Some(AgentIdentity::Legacy) => None,
+            None => Some(
+                self.session.last_accessed
+                    + chrono::TimeDelta::hours(self.user.get_config(config).expires.into()),
+            ),
+        }
+    }
+
+    /// Check the session can write to the project
+    ///
+    /// Agent tokens with a project scope can only access the projects in the scope
+    /// subtree (the ancestry is evaluated on every request).
+    pub async fn authorize_project<'a, T>(
+        &self,
+        connection: &'a T,
+        project: &Projects,
+    ) -> Result<(), KonarrServerError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let Some(AgentIdentity::Token {
+            name,
+            project_scope: Some(scope),
+            ..
+        }) = &self.agent
+        else {
+            return Ok(());
+        };
+        if project.is_within(connection, *scope).await? {
+            return Ok(());
+        }
+        log::warn!(
+            "Agent token `{}` tried to access Project({}) outside of its scope",
+            name,
+            project.id
+        );
+        Err(KonarrServerError::Forbidden(format!(
+            "Agent token `{}` is scoped to Project({}) and can not access Project({})",
+            name, scope, project.id
+        )))
+    }
+
+    /// Check the session can access the snapshot
+    ///
+    /// Every snapshot route goes through this check. Snapshots are shared by the
+    /// projects tracking the same image: reading needs access to one of its projects,
+    /// writing needs access to all of them. Users and the legacy agent key can
+    /// access every project. A denied request is a
+    /// [KonarrServerError::SnapshotNotFoundError] so it does not reveal that the
+    /// snapshot exists.
+    pub async fn authorize_snapshot<'a, T>(
+        &self,
+        connection: &'a T,
+        snapshot: &Snapshot,
+        access: SnapshotAccess,
+    ) -> Result<(), KonarrServerError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        if self.project_scope().is_none() {
+            return Ok(());
+        }
+        let projects = snapshot.fetch_projects(connection).await?;
+
+        let mut allowed = Vec::with_capacity(projects.len());
+        for project in projects.iter() {
+            allowed.push(self.authorize_project(connection, project).await.is_ok());
+        }
+        let authorized = match access {
+            SnapshotAccess::Read => allowed.iter().any(|a| *a),
+            SnapshotAccess::Write => !allowed.is_empty() && allowed.iter().all(|a| *a),
+        };
+        if authorized {
+            return Ok(());
+        }
+        log::warn!(
+            "{} tried to {:?} Snapshot({}) outside of its scope",
+            self.actor(),
+            access,
+            snapshot.id
+        );
+        Err(KonarrServerError::SnapshotNotFoundError(snapshot.id.into()))
+    }
+}
+
+/// Access to a snapshot (see [Session::authorize_snapshot])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotAccess {
+    /// Read the snapshot, its dependencies and alerts
+    Read,
+    /// Upload an SBOM / VEX document or update the metadata
+    Write,
 }
 
 #[allow(unused)]
@@ -40,9 +284,53 @@ impl<'r> FromRequest<'r> for Session {
 
         let connection = Arc::clone(&appstate.connection);
 
+        // User Auth (Bearer session token, used by the CLI)
+        if let Some(token) = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|h| h.strip_prefix("Bearer "))
+        {
+            return match find_session(appstate, connection, token).await {
+                Ok(mut session) => {
+                    session.auth = AuthMethod::Bearer;
+                    log::info!("User performing action: {}", session.user.id);
+                    Outcome::Success(session)
+                }
+                Err(e) => {
+                    log::warn!("Failed to get session: {}", e);
+                    Outcome::Error((rocket::http::Status::Unauthorized, ()))
+                }
+            };
+        }
+
         // Agent
         if let Some(token) = req.headers().get_one("Authorization") {
-            if agent_validation(appstate, connection, &token).await {
+            let agent_version = req
+                .headers()
+                .get_one("User-Agent")
+                .and_then(user_agent_version);
+            if let Some(agent) = agent_validation(
+                appstate,
+                Arc::clone(&connection),
+                token,
+                agent_version.clone(),
+            )
+            .await
+            {
+                // Agents need a registered client certificate in mTLS mode
+                let mtls = appstate
+                    .config
+                    .server
+                    .tls
+                    .as_ref()
+                    .is_some_and(|tls| tls.agent_mtls());
+                if mtls {
+                    if let Err(reason) = agent_certificate(req, connection).await {
+                        log::error!("Agent mTLS failed: {}", reason);
+                        req.local_cache(|| AuthFailure(Some(reason)));
+                        return Outcome::Error((rocket::http::Status::Unauthorized, ()));
+                    }
+                }
                 // This is a Agent User, no need to check the session
                 // Return a dummy session
                 return Outcome::Success(Session {
@@ -53,6 +341,9 @@ impl<'r> FromRequest<'r> for Session {
                         ..Default::default()
                     },
                     session: Sessions::default(),
+                    agent: Some(agent),
+                    agent_version,
+                    auth: AuthMethod::Agent,
                 });
             } else {
                 return Outcome::Error((rocket::http::Status::Unauthorized, ()));
@@ -93,7 +384,7 @@ async fn find_session(
 
     // Check the cached session (this is a quick check)
     if let Ok(sessions) = appstate.sessions.read() {
-        if let Some(sess) = sessions.iter().find(|s| s.session.token == token) {
+        if let Some(sess) = sessions.get(token) {
             log::debug!("Found session in cache - User({})", sess.user.id);
             if sess.user.validate_session(config) {
                 return Ok(sess.clone());
@@ -110,9 +401,22 @@ async fn find_session(
         }
     };
 
-    let mut user = match Users::fetch_by_sessions(&connection, session.id).await {
-        Ok(user) => user.first().unwrap().clone(),
-        Err(_) => return Err(KonarrServerError::InternalServerError),
+    let mut user = match session.user_id {
+        // Application session (CLI)
+        Some(user_id) => match Users::fetch_by_primary_key(&connection, user_id).await {
+            Ok(user) => user,
+            Err(_) => {
+                log::error!(
+                    "Application session user does not exist - User({})",
+                    user_id
+                );
+                return Err(KonarrServerError::Unauthorized);
+            }
+        },
+        None => match Users::fetch_by_sessions(&connection, session.id).await {
+            Ok(user) => user.first().unwrap().clone(),
+            Err(_) => return Err(KonarrServerError::InternalServerError),
+        },
     };
     user.sessions.data = session.clone();
 
@@ -135,48 +439,182 @@ async fn find_session(
             user.id,
             session.id
         );
-        sessions.push(Session {
-            user: user.clone(),
-            session: user.sessions.data.clone(),
-        });
+        sessions.insert(
+            Session {
+                user: user.clone(),
+                session: user.sessions.data.clone(),
+                agent: None,
+                agent_version: None,
+                auth: AuthMethod::Cookie,
+            },
+            config,
+        );
     }
 
-    Ok(Session { user, session })
+    Ok(Session {
+        user,
+        session,
+        agent: None,
+        agent_version: None,
+        auth: AuthMethod::Cookie,
+    })
 }
 
 /// Validate the agent token
 ///
-/// - Checks the cached token
-/// - Checks the database for the token
+/// - Scoped agent tokens (`konarr-agent-` prefix) are checked in the cache and then the database
+/// - The legacy agent key is checked in the cache and then the database (deprecated)
 async fn agent_validation(
     appstate: &AppState,
     connection: Arc<Mutex<libsql::Connection>>,
     token: &str,
-) -> bool {
-    // Check the cached agent token
-    if let Ok(key) = &appstate.agent_token.read() {
-        if token == **key {
-            log::info!("Agent performing action");
-            return true;
-        }
-        log::debug!("Cached Agent Key Mismatch, checking database");
+    agent_version: Option<String>,
+) -> Option<AgentIdentity> {
+    if token.starts_with(AGENT_TOKEN_PREFIX) {
+        return scoped_agent_validation(appstate, connection, token, agent_version).await;
     }
-    // Check the database for the agent key (expensive check)
-    match ServerSettings::fetch_by_name(&connection, Setting::AgentKey).await {
-        Ok(key) => {
-            if token == key.value {
-                log::info!("Agent performing action");
-                return true;
-            }
-            if let Ok(mut atoken) = appstate.agent_token.write() {
+
+    // Check the cached agent key
+    let mut valid = match appstate.agent_tokens.read() {
+        Ok(cache) => token == cache.legacy,
+        Err(_) => false,
+    };
+    if !valid {
+        log::debug!("Cached Agent Key Mismatch, checking database");
+        // Check the database for the agent key (expensive check)
+        if let Ok(key) = ServerSettings::get(&connection, Setting::AgentKey).await {
+            valid = token == key.value;
+            if let Ok(mut cache) = appstate.agent_tokens.write() {
                 log::debug!("Updating cached agent key");
-                *atoken = key.value.clone();
+                cache.legacy = key.value;
             }
         }
-        _ => {}
+    }
+    if !valid {
+        log::error!("Invalid Agent Key");
+        return None;
+    }
+
+    if !ServerSettings::get_bool(&connection, Setting::AgentKeyLegacy)
+        .await
+        .unwrap_or(true)
+    {
+        log::error!("Legacy agent key is disabled, use a scoped agent token");
+        return None;
+    }
+    log::warn!("The shared agent key is deprecated, please use a scoped agent token");
+    log::info!("Agent performing action (legacy agent key)");
+    Some(AgentIdentity::Legacy)
+}
+
+/// Validate a scoped agent token and record that it was used (with the agent version)
+async fn scoped_agent_validation(
+    appstate: &AppState,
+    connection: Arc<Mutex<libsql::Connection>>,
+    token: &str,
+    agent_version: Option<String>,
+) -> Option<AgentIdentity> {
+    let hash = AgentTokens::hash(token);
+
+    let cached = appstate
+        .agent_tokens
+        .read()
+        .ok()
+        .and_then(|cache| cache.tokens.get(&hash).cloned());
+
+    let mut agent_token = match cached {
+        Some(agent_token) => agent_token,
+        None => match AgentTokens::fetch_by_token(&connection, token).await {
+            Ok(agent_token) => agent_token,
+            Err(_) => {
+                log::error!("Invalid Agent Token");
+                return None;
+            }
+        },
     };
-    log::error!("Invalid Agent Key");
-    return false;
+
+    if !agent_token.is_valid() {
+        log::error!("Agent token `{}` is revoked or expired", agent_token.name);
+        if let Ok(mut cache) = appstate.agent_tokens.write() {
+            cache.invalidate(&hash);
+        }
+        return None;
+    }
+
+    // Only record the usage once a minute (or when the agent was upgraded)
+    let stale = agent_token
+        .last_used_at
+        .map(|t| chrono::Utc::now() - t > chrono::TimeDelta::minutes(1))
+        .unwrap_or(true);
+    let upgraded = agent_version.is_some() && agent_token.agent_version != agent_version;
+    if stale || upgraded {
+        if let Err(e) = agent_token.touch(&connection, agent_version).await {
+            log::warn!("Failed to update agent token usage: {}", e);
+        }
+    }
+    if let Ok(mut cache) = appstate.agent_tokens.write() {
+        cache.tokens.insert(hash, agent_token.clone());
+    }
+
+    log::info!("Agent performing action - Token({})", agent_token.name);
+    Some(AgentIdentity::Token {
+        name: agent_token.name,
+        project_scope: agent_token.project_scope,
+        expires_at: agent_token.expires_at,
+    })
+}
+
+/// Validate the client certificate of an agent request (mTLS)
+///
+/// The certificate chain is validated by Rocket against the client CA, the certificate
+/// also needs to be registered in the agent certificates allowlist.
+async fn agent_certificate(
+    req: &Request<'_>,
+    connection: Arc<Mutex<libsql::Connection>>,
+) -> Result<String, &'static str> {
+    let certificate = match req.guard::<Certificate<'_>>().await {
+        Outcome::Success(certificate) => certificate,
+        Outcome::Forward(_) => return Err("Client certificate is required for agent requests"),
+        Outcome::Error(_) => return Err("Invalid client certificate"),
+    };
+    let fingerprint = AgentCertificates::fingerprint(certificate.as_bytes());
+
+    let mut agent_certificate =
+        match AgentCertificates::fetch_by_fingerprint(&connection, fingerprint).await {
+            Ok(agent_certificate) => agent_certificate,
+            Err(_) => return Err("Client certificate is not registered"),
+        };
+
+    // Only record the usage once a minute
+    let stale = agent_certificate
+        .last_used_at
+        .map(|t| chrono::Utc::now() - t > chrono::TimeDelta::minutes(1))
+        .unwrap_or(true);
+    if stale {
+        if let Err(e) = agent_certificate.touch(&connection).await {
+            log::warn!("Failed to update agent certificate usage: {}", e);
+        }
+    }
+    log::info!("Agent certificate - {}", agent_certificate.name);
+    Ok(agent_certificate.name)
+}
+
+/// Unauthorized requests (with the reason if the guard provided one)
+#[rocket::catch(401)]
+pub async fn unauthorized(request: &Request<'_>) -> ApiErrorResponse {
+    let reason = request.local_cache(AuthFailure::default);
+    ApiErrorResponse::Unauthorized {
+        inner: (
+            rocket::http::Status::Unauthorized,
+            Json(ApiError {
+                message: reason.0.unwrap_or("Unauthorized").to_string(),
+                details: None,
+                status: 401,
+                id: None,
+                state: None,
+            }),
+        ),
+    }
 }
 
 #[rocket::async_trait]
@@ -10,12 +10,19 @@ use konarr::{
     Config, KonarrError,
 };
 use log::{debug, error, info, warn};
-use rocket::{fs::FileServer, Rocket};
+use rocket::{
+    config::{MutualTls, TlsConfig},
+    data::{Limits, ToByteUnit},
+    fs::FileServer,
+    Rocket,
+};
 use rocket_cors::{Cors, CorsOptions};
 use std::sync::{Arc, RwLock};
 use tokio::sync::Mutex;
 
 mod api;
+mod cache;
+mod checks;
 mod cli;
 mod error;
 mod guards;
@@ -26,20 +33,31 @@ pub struct AppState {
     /// Database Connection
     connection: Arc<Mutex<libsql::Connection>>,
     /// Active sessions for the server
-    sessions: Arc<RwLock<Vec<guards::Session>>>,
-    /// Token used by the agent to authenticate
-    agent_token: Arc<RwLock<String>>,
+    sessions: Arc<RwLock<guards::SessionCache>>,
+    /// Tokens used by the agents to authenticate (legacy key and scoped tokens)
+    agent_tokens: Arc<RwLock<guards::AgentTokenCache>>,
     /// Configuration
     config: Config,
     /// If the server has been initialized
     init: bool,
+    /// Maintenance mode (read-only)
+    maintenance: guards::maintenance::Maintenance,
+    /// Cache of the summary payloads
+    cache: Arc<cache::ResponseCache>,
+    /// Results of the startup checks
+    checks: Arc<Vec<checks::CheckResult>>,
 }
 
 #[rocket::main]
 async fn main() -> Result<()> {
     let arguments = cli::init();
 
-    let mut config = match Config::load(&arguments.config) {
+    let config = if arguments.no_config {
+        Config::load_env()
+    } else {
+        Config::load(&arguments.config)
+    };
+    let mut config = match config {
         Ok(config) => config,
         Err(e) => {
             warn!("Error loading configuration: {}", e);
@@ -50,6 +68,18 @@ async fn main() -> Result<()> {
         }
     };
 
+    // Startup checks
+    let checks = checks::run(&mut config);
+    checks::log(&checks);
+    if checks::failed(&checks) {
+        if arguments.skip_checks {
+            warn!("Startup checks failed, continuing as `--skip-checks` is set");
+        } else {
+            error!("Startup checks failed, fix the configuration or use `--skip-checks`");
+            return Err(anyhow::anyhow!("Startup checks failed"));
+        }
+    }
+
     // Database
     create(&mut config).await?;
 
@@ -59,7 +89,7 @@ async fn main() -> Result<()> {
     konarr::tasks::init(task_config, database).await?;
 
     // Server
-    server(config).await?;
+    server(config, checks).await?;
 
     Ok(())
 }
@@ -73,10 +103,41 @@ async fn main() -> Result<()> {
 async fn create(config: &mut Config) -> Result<()> {
     let connection = config.database.connection().await?;
 
-    database_create(&connection).await?;
+    // Secret settings encryption
+    konarr::models::settings::secrets::init(config.server.encryption_key.as_deref())?;
+
+    match database_create(&connection).await {
+        Ok(()) => {}
+        Err(e @ KonarrError::MigrationError(_)) => {
+            error!("{}", e);
+            error!("The database needs to be migrated, the server will not start");
+            error!("Run `konarr-cli database migrate --dry-run` to see the migration plan");
+            return Err(e.into());
+        }
+        Err(e) => {
+            error!("Failed to create the database: {}", e);
+            return Err(e.into());
+        }
+    }
+    match ServerSettings::verify_secrets(&connection).await {
+        Ok(plaintext) if plaintext > 0 && config.server.encryption_key.is_some() => {
+            warn!(
+                "{} secret setting(s) are stored in plain text, run `konarr-cli database encrypt-secrets`",
+                plaintext
+            );
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!("{}", e);
+            error!(
+                "Set `KONARR_SERVER_ENCRYPTION_KEY` to the key the settings were encrypted with"
+            );
+            return Err(e.into());
+        }
+    }
 
     // Store the server setting into the config file
-    if let Ok(token) = ServerSettings::fetch_by_name(&connection, Setting::AgentKey).await {
+    if let Ok(token) = ServerSettings::get(&connection, Setting::AgentKey).await {
         if config.agent.token != Some(token.value.clone()) {
             log::info!("Updating Agent Token");
             config.agent.token = Some(token.value);
@@ -125,12 +186,12 @@ fn cors(config: &Config) -> Result<Cors, KonarrError> {
                 ..Default::default()
             }
             .to_cors()
-            .map_err(|_| KonarrError::UnknownError("Failed to build CORS".to_string()))?
+            .map_err(|_| KonarrError::ConfigParseError("Failed to build CORS".to_string()))?
         } else {
             info!("CORS enabled");
             CorsOptions::default()
                 .to_cors()
-                .map_err(|_| KonarrError::UnknownError("Failed to build CORS".to_string()))?
+                .map_err(|_| KonarrError::ConfigParseError("Failed to build CORS".to_string()))?
         };
 
         Ok(cors)
@@ -142,62 +203,139 @@ fn cors(config: &Config) -> Result<Cors, KonarrError> {
             ..Default::default()
         }
         .to_cors()
-        .map_err(|_| KonarrError::UnknownError("Failed to build CORS".to_string()))?)
+        .map_err(|_| KonarrError::ConfigParseError("Failed to build CORS".to_string()))?)
     }
 }
 
-fn rocket(config: &Config) -> Rocket<rocket::Build> {
-    let rocket_config = rocket::Config::figment()
+fn rocket(config: &Config) -> Result<Rocket<rocket::Build>, KonarrError> {
+    let sbom = config.server.limits.sbom_bytes()?.bytes();
+    let limits = Limits::default()
+        .limit("json", config.server.limits.json_bytes()?.bytes())
+        .limit("sbom", sbom)
+        // Multipart SBOM uploads
+        .limit("data-form", sbom)
+        .limit("file", sbom);
+
+    let mut rocket_config = rocket::Config::figment()
         // Always overwrite the secret key
-        .merge(("secret_key", config.server.secret.clone()));
+        .merge(("secret_key", config.server.secret.clone()))
+        .merge(("limits", limits));
 
-    rocket::custom(rocket_config)
+    // The Konarr bind settings take precedence over Rocket's (`ROCKET_ADDRESS` /
+    // `ROCKET_PORT` and `Rocket.toml`), which are used when they are not set
+    for issue in config.validate_bind() {
+        warn!("Config `{}` :: {}", issue.key, issue.message);
+    }
+    if let Some(address) = config.server.bind_address()? {
+        info!("Binding to address: {}", address);
+        rocket_config = rocket_config.merge(("address", address));
+    }
+    if let Some(port) = config.server.bind_port()? {
+        info!("Binding to port: {}", port);
+        rocket_config = rocket_config.merge(("port", port));
+    }
+
+    if let Some(tls) = &config.server.tls {
+        info!("Enabling TLS");
+        let mut tls_config = TlsConfig::from_paths(&tls.certs, &tls.key);
+        if let Some(client_ca) = &tls.client_ca {
+            // Client certificates are optional, browsers use session cookies
+            info!("Enabling client certificates (mTLS)");
+            tls_config = tls_config.with_mutual(MutualTls::from_path(client_ca).mandatory(false));
+        }
+        if tls.agent_mtls() {
+            info!("Agents require a registered client certificate");
+        } else if tls.agent_mtls {
+            warn!("Agent mTLS is enabled but no client CA is set, ignoring");
+        }
+        rocket_config = rocket_config.merge(("tls", tls_config));
+    }
+
+    Ok(rocket::custom(rocket_config))
 }
 
-async fn server(config: Config) -> Result<()> {
+async fn server(config: Config, checks: Vec<checks::CheckResult>) -> Result<()> {
     let frontend = config.frontend_path()?;
     debug!("Frontend Path: {:?}", frontend);
     let cors = cors(&config)?;
 
     let database = config.database().await?;
     let connection = database.connect()?;
+    konarr::models::connection_init(&connection).await?;
     debug!("Database Initialized");
 
     // Check if we have init Konarr
     let init: bool = ServerSettings::get_bool(&connection, Setting::Initialized).await?;
-    let agent_token: String = ServerSettings::fetch_by_name(&connection, Setting::AgentKey)
+    let agent_token: String = ServerSettings::get(&connection, Setting::AgentKey)
         .await?
         .value;
+    let maintenance = guards::maintenance::Maintenance::new(
+        ServerSettings::get_bool(&connection, Setting::MaintenanceEnabled).await?,
+    );
+    if maintenance.enabled() {
+        warn!("Maintenance mode is enabled, the server is read-only");
+    }
 
     if !frontend.exists() {
         info!("No Frontend found, creating directory and running in API-only mode");
         std::fs::create_dir_all(&frontend)?;
     }
 
+    let sessions = Arc::new(RwLock::new(guards::SessionCache::default()));
+    // Prune the invalid cached sessions (same schedule as the cleanup task)
+    let prune_sessions = Arc::clone(&sessions);
+    let prune_config = config.sessions().clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            if let Ok(mut sessions) = prune_sessions.write() {
+                let pruned = sessions.prune(&prune_config);
+                debug!("Pruned {} cached sessions", pruned);
+            }
+        }
+    });
+
     let state = AppState {
         connection: Arc::new(Mutex::new(connection)),
-        sessions: Arc::new(RwLock::new(Vec::new())),
-        agent_token: Arc::new(RwLock::new(agent_token)),
+        sessions,
+        agent_tokens: Arc::new(RwLock::new(guards::AgentTokenCache::new(agent_token))),
         config: config.clone(),
         init,
+        maintenance: maintenance.clone(),
+        cache: Arc::new(cache::ResponseCache::default()),
+        checks: Arc::new(checks),
     };
 
     info!("Building Rocket");
-    let rocket = rocket(&config)
+    let rocket = rocket(&config)?
         .manage(state)
         .attach(cors)
+        .attach(maintenance)
         // Limit
-        .register("/", catchers!(guards::limit::rate_limit))
+        .register(
+            "/",
+            catchers![
+                guards::unauthorized,
+                guards::limit::rate_limit,
+                guards::limit::payload_too_large
+            ],
+        )
         // Mount Client files
         .mount("/", routes::routes())
         .mount("/", FileServer::from(frontend))
         // Mount API
         .mount("/api", routes![api::base::base])
+        .mount("/api", guards::maintenance::routes())
         .mount("/api/auth", api::auth::routes())
         .mount("/api/projects", api::projects::routes())
+        .mount("/api/projects", api::badges::routes())
+        .mount("/api/projects", api::feeds::routes())
         .mount("/api/snapshots", api::snapshots::routes())
         .mount("/api/dependencies", api::dependencies::routes())
         .mount("/api/security", api::security::routes())
+        .mount("/api/search", api::search::routes())
+        .mount("/api/public", api::public::routes())
         .mount("/api/admin", api::admin::routes())
         .mount("/api", api::websock::routes());
 
@@ -209,3 +347,38 @@ async fn server(config: Config) -> Result<()> {
     info!("Stopping Rocket");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rocket_bind() -> Result<(), KonarrError> {
+        let mut config = Config::default();
+        config.server.bind_address = Some("::".to_string());
+        config.server.port = Some(9443);
+        config.server.domain = Some("konarr.example.com".to_string());
+        config.server.scheme = Some("https".to_string());
+
+        let server = rocket(&config)?;
+        let figment = server.figment();
+        assert_eq!(
+            figment
+                .extract_inner::<std::net::IpAddr>("address")
+                .unwrap(),
+            "::".parse::<std::net::IpAddr>().unwrap()
+        );
+        assert_eq!(figment.extract_inner::<u16>("port").unwrap(), 9443);
+
+        // Behind a reverse proxy the external URL keeps the external port
+        config.server.port = None;
+        config.server.bind_port = Some(8080);
+        let server = rocket(&config)?;
+        assert_eq!(server.figment().extract_inner::<u16>("port").unwrap(), 8080);
+        assert_eq!(
+            config.frontend_url()?.unwrap().as_str(),
+            "https://konarr.example.com/"
+        );
+        Ok(())
+    }
+}
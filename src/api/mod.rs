@@ -0,0 +1,82 @@
+//! # Konarr API types
+//!
+//! Types shared by the Konarr server and client for the REST API.
+
+/// Pagination Response
+///
+/// Shared response envelope for paginated lists (used by both the server and the client).
+///
+/// ```json
+/// { "data": [], "total": 42, "count": 3, "pages": 1 }
+/// ```
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Pagination<T> {
+    /// Data Response
+    pub data: Vec<T>,
+    /// Total amount (ignores any filters / search)
+    pub total: u64,
+    /// Count of the results matching the current filters / search
+    #[serde(default)]
+    pub count: u64,
+    /// Page count (based on the count)
+    pub pages: u64,
+}
+
+impl<T> Pagination<T> {
+    /// Create a new Pagination Response
+    ///
+    /// The number of pages is calculated from the `count` and `limit`.
+    pub fn new(data: Vec<T>, total: impl Into<u64>, count: impl Into<u64>, limit: u64) -> Self {
+        let count = count.into();
+        Self {
+            data,
+            total: total.into(),
+            count,
+            pages: count.div_ceil(limit.max(1)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shared fixture for the wire format (also used by the server tests)
+    const PAGINATION: &str = include_str!("testdata/pagination.json");
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Item {
+        id: u32,
+    }
+
+    #[test]
+    fn test_pagination_new() {
+        let page = Pagination::new(vec![Item { id: 1 }], 42u64, 21u64, 10);
+        assert_eq!(page.total, 42);
+        assert_eq!(page.count, 21);
+        assert_eq!(page.pages, 3);
+
+        let empty: Pagination<Item> = Pagination::new(vec![], 0u64, 0u64, 0);
+        assert_eq!(empty.pages, 0);
+    }
+
+    #[test]
+    fn test_pagination_wire_format() {
+        let page: Pagination<Item> = serde_json::from_str(PAGINATION).unwrap();
+        assert_eq!(page.data, vec![Item { id: 1 }, Item { id: 2 }]);
+        assert_eq!(page.total, 42);
+        assert_eq!(page.count, 12);
+        assert_eq!(page.pages, 6);
+
+        let value = serde_json::to_value(&page).unwrap();
+        let fixture: serde_json::Value = serde_json::from_str(PAGINATION).unwrap();
+        assert_eq!(value, fixture);
+    }
+
+    #[test]
+    fn test_pagination_missing_count() {
+        let page: Pagination<Item> =
+            serde_json::from_str(r#"{"data":[],"total":1,"pages":1}"#).unwrap();
+        assert_eq!(page.count, 0);
+    }
+}
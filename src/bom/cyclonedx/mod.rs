@@ -3,7 +3,11 @@
 pub mod spec_v1_5;
 pub mod spec_v1_6;
 
-use super::{BillOfMaterials, BomParser};
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{sbom::BOM_PROPERTY_CONFIDENCE, BillOfMaterials, BomParser};
 use spec_v1_5::Bom as Bom_v1_5;
 use spec_v1_6::Bom as Bom_v1_6;
 
@@ -23,3 +27,285 @@ impl BomParser for CycloneDx {
         }
     }
 }
+
+/// Component property (name / value pair added by the SBOM tool)
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Property {
+    pub(crate) name: String,
+    pub(crate) value: Option<String>,
+}
+
+/// Component evidence
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Evidence {
+    /// Identity evidence (an object in v1.5 and a list in v1.6)
+    pub(crate) identity: Option<serde_json::Value>,
+    pub(crate) occurrences: Option<Vec<Occurrence>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Occurrence {
+    pub(crate) location: String,
+}
+
+/// Flatten the properties and evidence of a component into a properties map
+///
+/// Evidence is stored with the `cdx:evidence:` prefix (highest identity confidence
+/// and the occurrence locations).
+pub(crate) fn component_properties(
+    properties: Option<&Vec<Property>>,
+    evidence: Option<&Evidence>,
+) -> BTreeMap<String, String> {
+    let mut map: BTreeMap<String, String> = properties
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.value.clone().map(|value| (p.name.clone(), value)))
+        .collect();
+
+    if let Some(evidence) = evidence {
+        let confidence = match &evidence.identity {
+            Some(serde_json::Value::Array(identities)) => identities
+                .iter()
+                .filter_map(|i| i.get("confidence").and_then(|c| c.as_f64()))
+                .reduce(f64::max),
+            Some(identity) => identity.get("confidence").and_then(|c| c.as_f64()),
+            None => None,
+        };
+        if let Some(confidence) = confidence {
+            map.insert(BOM_PROPERTY_CONFIDENCE.to_string(), confidence.to_string());
+        }
+        for (index, occurrence) in evidence.occurrences.iter().flatten().enumerate() {
+            map.insert(
+                format!("cdx:evidence:occurrence:{}:location", index),
+                occurrence.location.clone(),
+            );
+        }
+    }
+    map
+}
+
+/// Property prefix of the image labels added by Syft to the main component
+pub const BOM_PROPERTY_IMAGE_LABELS: &str = "syft:image:labels:";
+
+/// Image labels from the properties of the main (container) component
+///
+/// Syft adds the labels with the [BOM_PROPERTY_IMAGE_LABELS] prefix, properties named
+/// after an OCI annotation (`org.opencontainers.image.*`) are also used.
+pub(crate) fn container_labels(properties: Option<&Vec<Property>>) -> BTreeMap<String, String> {
+    properties
+        .into_iter()
+        .flatten()
+        .filter_map(|p| {
+            let name = p.name.strip_prefix(BOM_PROPERTY_IMAGE_LABELS).or_else(|| {
+                p.name
+                    .starts_with("org.opencontainers.")
+                    .then_some(p.name.as_str())
+            })?;
+            p.value.clone().map(|value| (name.to_string(), value))
+        })
+        .collect()
+}
+
+/// Property with the path of the extra scan a component was merged from
+pub const BOM_PROPERTY_EXTRA_SCAN: &str = "konarr:scan:path";
+
+/// Merge the components of a CycloneDX SBOM (extra scan of a path) into another SBOM
+///
+/// Components already in the SBOM (same Package URL) are skipped, the merged components
+/// are tagged with the scanned path ([BOM_PROPERTY_EXTRA_SCAN]) and keep their
+/// dependencies between each other. Returns the number of components merged.
+pub fn merge(
+    sbom: &mut serde_json::Value,
+    other: &serde_json::Value,
+    path: &str,
+) -> Result<usize, crate::KonarrError> {
+    let invalid =
+        |name: &str| crate::KonarrError::ParseSBOM(format!("{} is not a CycloneDX SBOM", name));
+    for (name, value) in [("SBOM", &*sbom), ("Extra SBOM", other)] {
+        if value.get("bomFormat").and_then(|f| f.as_str()) != Some("CycloneDX") {
+            return Err(invalid(name));
+        }
+    }
+
+    let component_key = |component: &serde_json::Value| {
+        component
+            .get("purl")
+            .or_else(|| component.get("bom-ref"))
+            .and_then(|key| key.as_str())
+            .map(|key| key.to_string())
+    };
+    let components = sbom
+        .as_object_mut()
+        .ok_or_else(|| invalid("SBOM"))?
+        .entry("components")
+        .or_insert_with(|| serde_json::Value::Array(vec![]))
+        .as_array_mut()
+        .ok_or_else(|| invalid("SBOM"))?;
+    let mut known: std::collections::HashSet<String> =
+        components.iter().filter_map(component_key).collect();
+
+    let mut added = 0;
+    let mut merged = std::collections::HashSet::new();
+    for component in other
+        .get("components")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+    {
+        let Some(key) = component_key(component) else {
+            continue;
+        };
+        if !known.insert(key) {
+            continue;
+        }
+        let mut component = component.clone();
+        if let Some(object) = component.as_object_mut() {
+            let properties = object
+                .entry("properties")
+                .or_insert_with(|| serde_json::Value::Array(vec![]));
+            if let Some(properties) = properties.as_array_mut() {
+                properties.push(serde_json::json!({
+                    "name": BOM_PROPERTY_EXTRA_SCAN,
+                    "value": path,
+                }));
+            }
+        }
+        if let Some(bom_ref) = component.get("bom-ref").and_then(|r| r.as_str()) {
+            merged.insert(bom_ref.to_string());
+        }
+        components.push(component);
+        added += 1;
+    }
+
+    // Dependencies between the merged components
+    let dependencies: Vec<serde_json::Value> = other
+        .get("dependencies")
+        .and_then(|d| d.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|dependency| {
+            dependency
+                .get("ref")
+                .and_then(|r| r.as_str())
+                .is_some_and(|r| merged.contains(r))
+        })
+        .map(|dependency| {
+            let mut dependency = dependency.clone();
+            if let Some(depends_on) = dependency
+                .get_mut("dependsOn")
+                .and_then(|d| d.as_array_mut())
+            {
+                depends_on.retain(|r| r.as_str().is_some_and(|r| merged.contains(r)));
+            }
+            dependency
+        })
+        .collect();
+    if !dependencies.is_empty() {
+        if let Some(existing) = sbom.as_object_mut().and_then(|object| {
+            object
+                .entry("dependencies")
+                .or_insert_with(|| serde_json::Value::Array(vec![]))
+                .as_array_mut()
+        }) {
+            existing.extend(dependencies);
+        }
+    }
+
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_labels() {
+        let bom =
+            CycloneDx::parse(include_bytes!("../testdata/syft-nginx-labels.cdx.json")).unwrap();
+        assert_eq!(bom.version, "1.5");
+        assert_eq!(
+            bom.container.image.as_deref(),
+            Some("ghcr.io/42bytelabs/nginx-demo")
+        );
+        assert_eq!(bom.container.labels.len(), 7);
+        assert_eq!(
+            bom.container.labels.get("org.opencontainers.image.url"),
+            Some(&"https://github.com/42ByteLabs/nginx-demo".to_string())
+        );
+        assert_eq!(
+            bom.container.labels.get("maintainer"),
+            Some(&"NGINX Docker Maintainers <docker-maint@nginx.com>".to_string())
+        );
+
+        // Component properties are not labels
+        let bom = CycloneDx::parse(include_bytes!("../testdata/syft-alpine.cdx.json")).unwrap();
+        assert!(bom.container.labels.is_empty());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut sbom: serde_json::Value =
+            serde_json::from_slice(include_bytes!("../testdata/syft-alpine.cdx.json")).unwrap();
+        let extra: serde_json::Value =
+            serde_json::from_slice(include_bytes!("../testdata/syft-venv-dir.cdx.json")).unwrap();
+
+        // `zlib` is already in the image SBOM
+        assert_eq!(merge(&mut sbom, &extra, "/opt/venv").unwrap(), 2);
+        // Merging again does not duplicate the components
+        assert_eq!(merge(&mut sbom, &extra, "/opt/venv").unwrap(), 0);
+
+        let data = serde_json::to_vec(&sbom).unwrap();
+        let bom = CycloneDx::parse(&data).unwrap();
+        assert_eq!(bom.components.len(), 5);
+        let requests = bom
+            .components
+            .iter()
+            .find(|c| c.purl.starts_with("pkg:pypi/requests@"))
+            .unwrap();
+        assert_eq!(
+            requests.properties.get(BOM_PROPERTY_EXTRA_SCAN),
+            Some(&"/opt/venv".to_string())
+        );
+        let zlib = bom
+            .components
+            .iter()
+            .find(|c| c.purl.starts_with("pkg:apk/alpine/zlib@"))
+            .unwrap();
+        assert!(!zlib.properties.contains_key(BOM_PROPERTY_EXTRA_SCAN));
+
+        // Dependencies between the merged components are kept
+        let dependencies = sbom["dependencies"].as_array().unwrap();
+        assert!(dependencies.iter().any(|d| d["ref"]
+            .as_str()
+            .unwrap()
+            .starts_with("pkg:pypi/requests@")
+            && d["dependsOn"].as_array().unwrap().len() == 1));
+
+        assert!(merge(
+            &mut sbom,
+            &serde_json::json!({"spdxVersion": "SPDX-2.3"}),
+            "/"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_component_origin() {
+        let bom =
+            CycloneDx::parse(include_bytes!("../testdata/syft-debian-origin.cdx.json")).unwrap();
+        let origins: Vec<(&str, Option<&str>)> = bom
+            .components
+            .iter()
+            .map(|c| (c.purl.split('@').next().unwrap(), c.origin.as_deref()))
+            .collect();
+        assert_eq!(
+            origins,
+            vec![
+                ("pkg:deb/debian/libssl3", Some("openssl")),
+                ("pkg:deb/debian/libc6", Some("glibc")),
+                ("pkg:deb/debian/openssl", None),
+                ("pkg:apk/alpine/libcrypto3", Some("openssl")),
+            ]
+        );
+    }
+}
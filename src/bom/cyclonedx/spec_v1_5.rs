@@ -2,7 +2,9 @@
 
 use log::warn;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
+use super::{component_properties, container_labels, Evidence, Property};
 use crate::bom::{
     sbom::{BomComponent, BomComponentType, BomTool, BomType, Container},
     BillOfMaterials, BomParser,
@@ -22,6 +24,8 @@ pub struct Bom {
     pub(crate) metadata: Option<Metadata>,
 
     pub(crate) components: Option<Vec<Component>>,
+
+    pub(crate) dependencies: Option<Vec<Dependency>>,
 }
 
 impl BomParser for Bom {
@@ -52,9 +56,25 @@ impl From<Bom> for BillOfMaterials {
     fn from(value: Bom) -> Self {
         let mut sbom = BillOfMaterials::new(BomType::CycloneDX_1_5, value.spec_version);
 
+        // Components the main component depends on (only if the SBOM has graph data)
+        let direct: Option<Vec<String>> = value
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.component.as_ref())
+            .and_then(|comp| comp.bom_ref.as_ref())
+            .zip(value.dependencies.as_ref())
+            .map(|(root, dependencies)| {
+                dependencies
+                    .iter()
+                    .find(|dep| &dep.reference == root)
+                    .and_then(|dep| dep.depends_on.clone())
+                    .unwrap_or_default()
+            });
+
         if let Some(metadata) = value.metadata {
             if let Some(comp) = metadata.component {
                 sbom.container = Container {
+                    labels: container_labels(comp.properties.as_ref()),
                     image: comp.name,
                     version: comp.version,
                     ..Container::default()
@@ -74,6 +94,7 @@ impl From<Bom> for BillOfMaterials {
         }
 
         if let Some(components) = value.components {
+            let mut keys = HashSet::with_capacity(components.len());
             for comp in components.iter() {
                 let purl: String = if let Some(purl) = comp.purl.as_ref() {
                     purl.to_string()
@@ -87,14 +108,24 @@ impl From<Bom> for BillOfMaterials {
 
                 let mut bom_comp = BomComponent::from_purl(purl);
 
-                // check if the bom_comp is already in the list
-                if sbom.components.contains(&bom_comp) {
+                // Same package and version (see `BomComponent::key`)
+                if !keys.insert(bom_comp.key()) {
+                    sbom.duplicates += 1;
                     continue;
                 }
 
                 if let Some(typ) = comp.comp_type.as_ref() {
                     bom_comp.comp_type = BomComponentType::from(typ.to_string());
                 }
+                bom_comp.scope = comp.scope.clone();
+                bom_comp.properties =
+                    component_properties(comp.properties.as_ref(), comp.evidence.as_ref());
+                bom_comp.origin = bom_comp.find_origin();
+                bom_comp.direct = direct.as_ref().map(|direct| {
+                    comp.bom_ref
+                        .as_ref()
+                        .is_some_and(|bom_ref| direct.contains(bom_ref))
+                });
 
                 sbom.components.push(bom_comp);
             }
@@ -121,6 +152,8 @@ pub(crate) struct Tools {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct Component {
+    #[serde(rename = "bom-ref")]
+    pub(crate) bom_ref: Option<String>,
     /// TODO: This can only be a set of known values
     #[serde(rename = "type")]
     pub(crate) comp_type: Option<String>,
@@ -131,6 +164,22 @@ pub(crate) struct Component {
     pub(crate) purl: Option<String>,
 
     pub(crate) author: Option<String>,
+
+    /// Scope of the component (required, optional or excluded)
+    pub(crate) scope: Option<String>,
+
+    /// Properties added by the SBOM tool
+    pub(crate) properties: Option<Vec<Property>>,
+    /// Evidence of the component (identity and occurrences)
+    pub(crate) evidence: Option<Evidence>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Dependency {
+    #[serde(rename = "ref")]
+    pub(crate) reference: String,
+    #[serde(rename = "dependsOn")]
+    pub(crate) depends_on: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -139,3 +188,64 @@ pub(crate) struct ToolService {
     pub(crate) name: Option<String>,
     pub(crate) version: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_and_graph() {
+        let data = r#"{
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "metadata": {
+                "component": { "bom-ref": "root", "type": "container", "name": "app" }
+            },
+            "components": [
+                { "bom-ref": "express", "type": "library", "name": "express", "purl": "pkg:npm/express@4.19.2", "scope": "required" },
+                { "bom-ref": "qs", "type": "library", "name": "qs", "purl": "pkg:npm/qs@6.11.0", "scope": "optional" },
+                { "type": "library", "name": "zlib", "purl": "pkg:deb/debian/zlib1g@1.2.13" }
+            ],
+            "dependencies": [
+                { "ref": "root", "dependsOn": ["express"] },
+                { "ref": "express", "dependsOn": ["qs"] }
+            ]
+        }"#;
+        let sbom = Bom::parse(data.as_bytes()).unwrap();
+        assert_eq!(sbom.components.len(), 3);
+
+        let express = &sbom.components[0];
+        assert_eq!(express.scope.as_deref(), Some("required"));
+        assert_eq!(express.direct, Some(true));
+        let qs = &sbom.components[1];
+        assert_eq!(qs.scope.as_deref(), Some("optional"));
+        assert_eq!(qs.direct, Some(false));
+        let zlib = &sbom.components[2];
+        assert_eq!(zlib.scope, None);
+        assert_eq!(zlib.direct, Some(false));
+
+        // Without graph data the components are neither direct nor transitive
+        let data = data.replace("\"dependencies\"", "\"unused\"");
+        let sbom = Bom::parse(data.as_bytes()).unwrap();
+        assert!(sbom.components.iter().all(|c| c.direct.is_none()));
+    }
+
+    #[test]
+    fn test_identity_evidence() {
+        let data = r#"{
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "components": [
+                {
+                    "type": "library", "name": "openssl", "purl": "pkg:generic/openssl@3.0.1",
+                    "properties": [{ "name": "aquasecurity:trivy:FilePath", "value": "/usr/lib/libssl.so.3" }],
+                    "evidence": { "identity": { "field": "purl", "confidence": 1 } }
+                }
+            ]
+        }"#;
+        let sbom = Bom::parse(data.as_bytes()).unwrap();
+        let evidence = sbom.components[0].evidence().unwrap();
+        assert_eq!(evidence.confidence, Some(1.0));
+        assert_eq!(evidence.paths, vec!["/usr/lib/libssl.so.3"]);
+    }
+}
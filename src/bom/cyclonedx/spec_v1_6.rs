@@ -2,7 +2,9 @@
 
 use log::warn;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
+use super::{component_properties, container_labels, Evidence, Property};
 use crate::bom::{
     sbom::{BomComponent, BomComponentType, BomTool, BomType, BomVulnerability, Container},
     BillOfMaterials, BomParser,
@@ -23,6 +25,8 @@ pub struct Bom {
 
     pub(crate) components: Option<Vec<Component>>,
 
+    pub(crate) dependencies: Option<Vec<Dependency>>,
+
     pub(crate) vulnerabilities: Option<Vec<Vulnerability>>,
 }
 
@@ -54,9 +58,25 @@ impl From<Bom> for BillOfMaterials {
     fn from(value: Bom) -> Self {
         let mut sbom = BillOfMaterials::new(BomType::CycloneDX_1_6, value.spec_version);
 
+        // Components the main component depends on (only if the SBOM has graph data)
+        let direct: Option<Vec<String>> = value
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.component.as_ref())
+            .and_then(|comp| comp.bom_ref.as_ref())
+            .zip(value.dependencies.as_ref())
+            .map(|(root, dependencies)| {
+                dependencies
+                    .iter()
+                    .find(|dep| &dep.reference == root)
+                    .and_then(|dep| dep.depends_on.clone())
+                    .unwrap_or_default()
+            });
+
         if let Some(metadata) = value.metadata {
             if let Some(comp) = metadata.component {
                 sbom.container = Container {
+                    labels: container_labels(comp.properties.as_ref()),
                     image: comp.name,
                     version: comp.version,
                     ..Container::default()
@@ -76,6 +96,7 @@ impl From<Bom> for BillOfMaterials {
         }
 
         if let Some(components) = value.components {
+            let mut keys = HashSet::with_capacity(components.len());
             for comp in components.iter() {
                 let purl: String = if let Some(purl) = comp.purl.as_ref() {
                     purl.to_string()
@@ -89,14 +110,24 @@ impl From<Bom> for BillOfMaterials {
 
                 let mut bom_comp = BomComponent::from_purl(purl);
 
-                // check if the bom_comp is already in the list
-                if sbom.components.contains(&bom_comp) {
+                // Same package and version (see `BomComponent::key`)
+                if !keys.insert(bom_comp.key()) {
+                    sbom.duplicates += 1;
                     continue;
                 }
 
                 if let Some(typ) = comp.comp_type.as_ref() {
                     bom_comp.comp_type = BomComponentType::from(typ.to_string());
                 }
+                bom_comp.scope = comp.scope.clone();
+                bom_comp.properties =
+                    component_properties(comp.properties.as_ref(), comp.evidence.as_ref());
+                bom_comp.origin = bom_comp.find_origin();
+                bom_comp.direct = direct.as_ref().map(|direct| {
+                    comp.bom_ref
+                        .as_ref()
+                        .is_some_and(|bom_ref| direct.contains(bom_ref))
+                });
 
                 sbom.components.push(bom_comp);
             }
@@ -158,6 +189,8 @@ pub(crate) struct Tools {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct Component {
+    #[serde(rename = "bom-ref")]
+    pub(crate) bom_ref: Option<String>,
     /// TODO: This can only be a set of known values
     #[serde(rename = "type")]
     pub(crate) comp_type: Option<String>,
@@ -168,6 +201,22 @@ pub(crate) struct Component {
     pub(crate) purl: Option<String>,
 
     pub(crate) author: Option<String>,
+
+    /// Scope of the component (required, optional or excluded)
+    pub(crate) scope: Option<String>,
+
+    /// Properties added by the SBOM tool
+    pub(crate) properties: Option<Vec<Property>>,
+    /// Evidence of the component (identity and occurrences)
+    pub(crate) evidence: Option<Evidence>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Dependency {
+    #[serde(rename = "ref")]
+    pub(crate) reference: String,
+    #[serde(rename = "dependsOn")]
+    pub(crate) depends_on: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -211,3 +260,171 @@ pub(crate) struct VulnerabilityCompRef {
     #[serde(rename = "ref")]
     pub(crate) reference: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_and_graph() {
+        let data = r#"{
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.6",
+            "metadata": {
+                "component": { "bom-ref": "root", "type": "container", "name": "app" }
+            },
+            "components": [
+                { "bom-ref": "express", "type": "library", "name": "express", "purl": "pkg:npm/express@4.19.2", "scope": "required" },
+                { "bom-ref": "qs", "type": "library", "name": "qs", "purl": "pkg:npm/qs@6.11.0", "scope": "optional" },
+                { "type": "library", "name": "zlib", "purl": "pkg:deb/debian/zlib1g@1.2.13" }
+            ],
+            "dependencies": [
+                { "ref": "root", "dependsOn": ["express"] },
+                { "ref": "express", "dependsOn": ["qs"] }
+            ]
+        }"#;
+        let sbom = Bom::parse(data.as_bytes()).unwrap();
+        assert_eq!(sbom.components.len(), 3);
+
+        let express = &sbom.components[0];
+        assert_eq!(express.scope.as_deref(), Some("required"));
+        assert_eq!(express.direct, Some(true));
+        let qs = &sbom.components[1];
+        assert_eq!(qs.scope.as_deref(), Some("optional"));
+        assert_eq!(qs.direct, Some(false));
+        let zlib = &sbom.components[2];
+        assert_eq!(zlib.scope, None);
+        assert_eq!(zlib.direct, Some(false));
+
+        // Without graph data the components are neither direct nor transitive
+        let data = data.replace("\"dependencies\"", "\"unused\"");
+        let sbom = Bom::parse(data.as_bytes()).unwrap();
+        assert!(sbom.components.iter().all(|c| c.direct.is_none()));
+    }
+
+    #[test]
+    fn test_component_dedup() {
+        let data = r#"{
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.6",
+            "components": [
+                { "type": "library", "name": "openssl", "purl": "pkg:deb/debian/openssl@3.0.1?arch=amd64&distro=debian-12" },
+                { "type": "library", "name": "openssl", "purl": "pkg:deb/debian/openssl@3.0.2?arch=amd64&distro=debian-12" },
+                { "type": "library", "name": "openssl", "purl": "pkg:DEB/debian/openssl@3.0.1?distro=debian-12.1&arch=amd64" },
+                { "type": "library", "name": "openssl", "purl": "pkg:deb/debian/openssl@3.0.1?arch=arm64" },
+                { "type": "library", "name": "express", "purl": "pkg:npm/express@4.19.2" },
+                { "type": "library", "name": "express", "purl": "pkg:npm/express@4.19.2#lib" }
+            ]
+        }"#;
+        let sbom = Bom::parse(data.as_bytes()).unwrap();
+
+        // Both versions (and architectures) are kept, the duplicates are dropped
+        let keys: Vec<String> = sbom.components.iter().map(|c| c.key()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "pkg:deb/debian/openssl@3.0.1?arch=amd64",
+                "pkg:deb/debian/openssl@3.0.2?arch=amd64",
+                "pkg:deb/debian/openssl@3.0.1?arch=arm64",
+                "pkg:npm/express@4.19.2",
+            ]
+        );
+        assert_eq!(sbom.duplicates, 2);
+    }
+
+    #[test]
+    fn test_component_dedup_performance() {
+        let components: Vec<serde_json::Value> = (0..10_000)
+            .map(|i| {
+                serde_json::json!({
+                    "type": "library",
+                    "name": format!("package-{}", i % 5_000),
+                    "purl": format!("pkg:npm/package-{}@1.0.{}", i % 5_000, i / 5_000),
+                })
+            })
+            .chain((0..1_000).map(|i| {
+                serde_json::json!({
+                    "type": "library",
+                    "name": format!("package-{}", i),
+                    "purl": format!("pkg:npm/package-{}@1.0.0", i),
+                })
+            }))
+            .collect();
+        let data = serde_json::to_vec(&serde_json::json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.6",
+            "components": components,
+        }))
+        .unwrap();
+
+        let started = std::time::Instant::now();
+        let sbom = Bom::parse(&data).unwrap();
+        assert_eq!(sbom.components.len(), 10_000);
+        assert_eq!(sbom.duplicates, 1_000);
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_syft_evidence() {
+        let sbom = Bom::parse(include_bytes!("../testdata/syft-alpine.cdx.json")).unwrap();
+        assert_eq!(sbom.components.len(), 3);
+
+        let busybox = sbom.components[0].evidence().unwrap();
+        assert_eq!(busybox.found_by.as_deref(), Some("apk-db-cataloger"));
+        assert_eq!(busybox.paths, vec!["/lib/apk/db/installed"]);
+        assert_eq!(busybox.confidence, None);
+
+        let cli = sbom.components[1].evidence().unwrap();
+        assert_eq!(cli.found_by.as_deref(), Some("go-module-binary-cataloger"));
+        assert_eq!(
+            cli.paths,
+            vec![
+                "/usr/local/bin/docker",
+                "/usr/libexec/docker/cli-plugins/docker-compose"
+            ]
+        );
+        assert_eq!(
+            sbom.components[1]
+                .properties
+                .get("syft:metadata:goCompiledVersion")
+                .map(|v| v.as_str()),
+            Some("go1.22.7")
+        );
+        // Component without properties
+        assert!(sbom.components[2].evidence().is_none());
+
+        // Paths are dropped to fit in the budget
+        let full = cli.to_json(1024).unwrap();
+        assert!(full.contains("docker-compose"));
+        let truncated = cli.to_json(100).unwrap();
+        assert!(truncated.len() <= 100);
+        assert!(truncated.contains("/usr/local/bin/docker"));
+        assert!(!truncated.contains("docker-compose"));
+        assert!(cli.to_json(10).is_none());
+    }
+
+    #[test]
+    fn test_identity_evidence() {
+        let data = r#"{
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.6",
+            "components": [
+                {
+                    "type": "library", "name": "openssl", "purl": "pkg:generic/openssl@3.0.1",
+                    "evidence": {
+                        "identity": [
+                            { "field": "purl", "confidence": 0.4 },
+                            { "field": "name", "confidence": 0.8 }
+                        ],
+                        "occurrences": [{ "location": "/usr/lib/libssl.so.3" }]
+                    }
+                }
+            ]
+        }"#;
+        let sbom = Bom::parse(data.as_bytes()).unwrap();
+        let evidence = sbom.components[0].evidence().unwrap();
+        assert_eq!(evidence.found_by, None);
+        assert_eq!(evidence.confidence, Some(0.8));
+        assert_eq!(evidence.paths, vec!["/usr/lib/libssl.so.3"]);
+    }
+}
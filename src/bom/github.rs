@@ -0,0 +1,340 @@
+//! # GitHub Dependency Submission
+//!
+//! Snapshot of the dependencies in the format of the GitHub dependency submission API
+//! (`POST /repos/{owner}/{repo}/dependency-graph/snapshots`).
+//!
+//! https://docs.github.com/en/rest/dependency-graph/dependency-submission
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Package URL types supported by the GitHub dependency graph
+///
+/// Other packages (OS packages, generic, ...) are skipped.
+pub const GITHUB_PURL_TYPES: [&str; 8] = [
+    "cargo", "composer", "gem", "golang", "maven", "npm", "nuget", "pypi",
+];
+
+/// Metadata key with the number of skipped dependencies
+pub const GITHUB_SKIPPED_METADATA: &str = "konarr.skipped";
+
+/// GitHub dependency snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitHubSnapshot {
+    /// Version of the snapshot format (always `0`)
+    pub version: u32,
+    /// Commit SHA the snapshot is for
+    pub sha: String,
+    /// Git reference (`refs/heads/main`)
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    /// Job which created the snapshot
+    pub job: GitHubJob,
+    /// Detector which created the snapshot
+    pub detector: GitHubDetector,
+    /// Time the dependencies were scanned
+    pub scanned: chrono::DateTime<chrono::Utc>,
+    /// Metadata of the snapshot (scalar values only)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+    /// Manifests (by name)
+    pub manifests: BTreeMap<String, GitHubManifest>,
+}
+
+/// Job which created the snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitHubJob {
+    /// Correlator (snapshots with the same correlator replace each other)
+    pub correlator: String,
+    /// Job ID
+    pub id: String,
+}
+
+/// Detector (tool) which created the snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitHubDetector {
+    /// Name of the detector
+    pub name: String,
+    /// Version of the detector
+    pub version: String,
+    /// URL of the detector
+    pub url: String,
+}
+
+/// Manifest (a container image)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitHubManifest {
+    /// Name of the manifest
+    pub name: String,
+    /// Location of the manifest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<GitHubManifestFile>,
+    /// Resolved dependencies (by package name)
+    pub resolved: BTreeMap<String, GitHubDependency>,
+}
+
+/// Location of the manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitHubManifestFile {
+    /// Path of the manifest in the repository
+    pub source_location: String,
+}
+
+/// Resolved dependency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitHubDependency {
+    /// Package URL of the dependency
+    pub package_url: String,
+    /// `direct` or `indirect`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relationship: Option<String>,
+    /// `runtime` or `development`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Dependencies of the dependency
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+impl GitHubSnapshot {
+    /// New snapshot (the `sha` / `ref` are set by the caller)
+    pub fn new(correlator: impl Into<String>, job: impl Into<String>) -> Self {
+        Self {
+            version: 0,
+            sha: String::new(),
+            git_ref: String::new(),
+            job: GitHubJob {
+                correlator: correlator.into(),
+                id: job.into(),
+            },
+            detector: GitHubDetector {
+                name: "konarr".to_string(),
+                version: crate::KONARR_VERSION.to_string(),
+                url: "https://github.com/42ByteLabs/konarr".to_string(),
+            },
+            scanned: chrono::Utc::now(),
+            metadata: BTreeMap::new(),
+            manifests: BTreeMap::new(),
+        }
+    }
+
+    /// Add a manifest (returns the manifest to add dependencies to)
+    pub fn manifest(&mut self, name: impl Into<String>) -> &mut GitHubManifest {
+        let name = name.into();
+        self.manifests
+            .entry(name.clone())
+            .or_insert_with(|| GitHubManifest {
+                name,
+                file: None,
+                resolved: BTreeMap::new(),
+            })
+    }
+
+    /// Number of skipped dependencies (not supported by GitHub)
+    pub fn skipped(&self) -> u64 {
+        self.metadata
+            .get(GITHUB_SKIPPED_METADATA)
+            .and_then(|value| value.as_u64())
+            .unwrap_or_default()
+    }
+
+    /// Record skipped dependencies
+    pub fn add_skipped(&mut self, count: u64) {
+        let skipped = self.skipped() + count;
+        self.metadata
+            .insert(GITHUB_SKIPPED_METADATA.to_string(), skipped.into());
+    }
+}
+
+impl GitHubManifest {
+    /// Add a dependency, returns `false` if GitHub does not support the package
+    /// (type not in [GITHUB_PURL_TYPES] or without a version)
+    pub fn add_dependency(
+        &mut self,
+        purl_type: &str,
+        namespace: Option<&str>,
+        name: &str,
+        version: Option<&str>,
+        direct: Option<bool>,
+        scope: Option<&str>,
+    ) -> bool {
+        let purl_type = purl_type.to_lowercase();
+        let Some(version) = version.filter(|version| !version.is_empty()) else {
+            return false;
+        };
+        if !GITHUB_PURL_TYPES.contains(&purl_type.as_str()) || name.is_empty() {
+            return false;
+        }
+
+        let package = match namespace.filter(|namespace| !namespace.is_empty()) {
+            Some(namespace) => format!("{}/{}", namespace, name),
+            None => name.to_string(),
+        };
+        let package_url = format!(
+            "pkg:{}/{}@{}",
+            purl_type,
+            package
+                .split('/')
+                .map(purl_encode)
+                .collect::<Vec<String>>()
+                .join("/"),
+            purl_encode(version)
+        );
+
+        self.resolved.insert(
+            format!("{}:{}@{}", purl_type, package, version),
+            GitHubDependency {
+                package_url,
+                relationship: direct
+                    .map(|direct| if direct { "direct" } else { "indirect" }.to_string()),
+                scope: scope.map(github_scope),
+                dependencies: Vec::new(),
+            },
+        );
+        true
+    }
+}
+
+#[cfg(feature = "client")]
+impl GitHubSnapshot {
+    /// Submit the snapshot to the GitHub dependency graph of a repository (`owner/name`)
+    ///
+    /// Returns the ID of the submitted snapshot.
+    pub async fn submit(&self, repository: &str, token: &str) -> Result<u64, crate::KonarrError> {
+        if self.sha.len() != 40 || repository.split('/').count() != 2 {
+            return Err(crate::KonarrError::InvalidData(format!(
+                "A commit SHA and `owner/name` repository are required (sha: `{}`, repo: `{}`)",
+                self.sha, repository
+            )));
+        }
+        let url = format!(
+            "https://api.github.com/repos/{}/dependency-graph/snapshots",
+            repository
+        );
+        log::info!("Submitting the dependency snapshot to {}", url);
+
+        let response = crate::utils::config::client_builder()
+            .user_agent(format!("Konarr/{}", crate::KONARR_VERSION))
+            .build()?
+            .post(url)
+            .bearer_auth(token)
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .json(self)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(crate::KonarrError::KonarrClient(format!(
+                "GitHub dependency submission failed ({}): {}",
+                status,
+                body.get("message")
+                    .and_then(|message| message.as_str())
+                    .unwrap_or_default()
+            )));
+        }
+        Ok(body
+            .get("id")
+            .and_then(|id| id.as_u64())
+            .unwrap_or_default())
+    }
+}
+
+/// Map a SBOM scope to the GitHub scope (`excluded` / development scopes are not runtime)
+fn github_scope(scope: &str) -> String {
+    match scope.to_lowercase().as_str() {
+        "excluded" | "dev" | "development" | "test" => "development",
+        _ => "runtime",
+    }
+    .to_string()
+}
+
+/// Percent-encode the reserved characters of a Package URL segment
+fn purl_encode(segment: &str) -> String {
+    segment
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' | '~' | '+' | ':' => c.to_string(),
+            _ => c
+                .to_string()
+                .bytes()
+                .map(|b| format!("%{:02X}", b))
+                .collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_snapshot() {
+        let mut snapshot = GitHubSnapshot::new("konarr/web", "42");
+        snapshot.sha = "ce587453ced02b1526dfb4cb910479d431683101".to_string();
+        snapshot.git_ref = "refs/heads/main".to_string();
+
+        let manifest = snapshot.manifest("ghcr.io/42bytelabs/web:1.0");
+        assert!(manifest.add_dependency(
+            "npm",
+            Some("@angular"),
+            "core",
+            Some("17.0.1"),
+            Some(true),
+            Some("required"),
+        ));
+        assert!(manifest.add_dependency(
+            "pypi",
+            None,
+            "requests",
+            Some("2.31.0"),
+            Some(false),
+            None
+        ));
+        // OS packages, generic and packages without versions are not supported
+        assert!(!manifest.add_dependency(
+            "deb",
+            Some("debian"),
+            "openssl",
+            Some("3.0.1"),
+            None,
+            None
+        ));
+        assert!(!manifest.add_dependency("generic", None, "busybox", Some("1.36"), None, None));
+        assert!(!manifest.add_dependency("npm", None, "left-pad", None, None, None));
+        snapshot.add_skipped(3);
+        assert_eq!(snapshot.skipped(), 3);
+
+        let value = serde_json::to_value(&snapshot).unwrap();
+        // Required properties of the dependency submission schema
+        for key in ["version", "sha", "ref", "job", "detector", "scanned"] {
+            assert!(value.get(key).is_some(), "missing `{}`", key);
+        }
+        assert_eq!(value["version"], 0);
+        assert_eq!(value["job"]["correlator"], "konarr/web");
+        assert_eq!(value["job"]["id"], "42");
+        assert_eq!(value["detector"]["name"], "konarr");
+        assert!(value["detector"]["version"].is_string());
+        assert!(value["detector"]["url"].is_string());
+        assert!(chrono::DateTime::parse_from_rfc3339(value["scanned"].as_str().unwrap()).is_ok());
+        assert_eq!(value["metadata"][GITHUB_SKIPPED_METADATA], 3);
+
+        let manifest = &value["manifests"]["ghcr.io/42bytelabs/web:1.0"];
+        assert_eq!(manifest["name"], "ghcr.io/42bytelabs/web:1.0");
+        assert!(manifest.get("file").is_none());
+        let resolved = manifest["resolved"].as_object().unwrap();
+        assert_eq!(resolved.len(), 2);
+
+        let angular = &resolved["npm:@angular/core@17.0.1"];
+        assert_eq!(angular["package_url"], "pkg:npm/%40angular/core@17.0.1");
+        assert_eq!(angular["relationship"], "direct");
+        assert_eq!(angular["scope"], "runtime");
+        assert_eq!(angular["dependencies"], serde_json::json!([]));
+
+        let requests = &resolved["pypi:requests@2.31.0"];
+        assert_eq!(requests["package_url"], "pkg:pypi/requests@2.31.0");
+        assert_eq!(requests["relationship"], "indirect");
+        assert!(requests.get("scope").is_none());
+    }
+}
@@ -1,11 +1,15 @@
 //! # Konarr SBOM Module
 
 pub mod cyclonedx;
+pub mod github;
+pub mod processors;
 pub mod sbom;
+pub mod vex;
 
 use sha2::Digest;
 use std::path::PathBuf;
 
+pub use processors::BomProcessors;
 pub use sbom::BillOfMaterials;
 
 use crate::KonarrError;
@@ -21,6 +25,17 @@ pub trait BomParser {
     }
 }
 
+/// SBOM Processor Trait
+///
+/// Processors inspect and modify a parsed SBOM before it is indexed (dependencies
+/// and alerts). They are applied in order by [BomProcessors].
+pub trait BomProcessor: Send + Sync {
+    /// Name of the processor (used for logging)
+    fn name(&self) -> &str;
+    /// Process the Bill of Materials
+    fn process(&self, bom: &mut BillOfMaterials) -> Result<(), crate::KonarrError>;
+}
+
 /// Parsers
 #[allow(non_camel_case_types)]
 pub enum Parsers {
@@ -30,15 +45,18 @@ pub enum Parsers {
     CycloneDX_v1_6,
 }
 
+/// SHA256 (hex) of the SBOM data
+pub fn sha256(data: &[u8]) -> String {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(data);
+    format!("{:x}", hasher.finalize())
+}
+
 impl BomParser for Parsers {
     fn parse(data: &[u8]) -> Result<BillOfMaterials, crate::KonarrError> {
-        let mut hasher = sha2::Sha256::new();
-        hasher.update(data);
-        let sha = hasher.finalize();
-
         // CycloneDX
         if let Ok(mut sbom) = cyclonedx::CycloneDx::parse(data) {
-            sbom.sha = format!("{:x}", sha);
+            sbom.sha = sha256(data);
             Ok(sbom)
         } else {
             Err(KonarrError::ParseSBOM("Failed to parse SBOM".to_string()))
@@ -0,0 +1,298 @@
+//! # SBOM Processors
+//!
+//! Registry of [BomProcessor]s applied to a parsed SBOM before it is added to a Snapshot
+//! and the built-in processors (configured using the `bom.*` server settings).
+use std::str::FromStr;
+
+use super::{BillOfMaterials, BomProcessor};
+use crate::KonarrError;
+
+/// Property set on components in an internal namespace
+pub const BOM_PROPERTY_INTERNAL: &str = "konarr:internal";
+
+/// Ordered list of SBOM processors
+pub struct BomProcessors {
+    processors: Vec<Box<dyn BomProcessor>>,
+    /// Log and skip processors which fail (instead of returning the error)
+    pub skip_failures: bool,
+}
+
+impl Default for BomProcessors {
+    fn default() -> Self {
+        Self {
+            processors: Vec::new(),
+            skip_failures: true,
+        }
+    }
+}
+
+impl std::fmt::Debug for BomProcessors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BomProcessors")
+            .field(
+                "processors",
+                &self.processors.iter().map(|p| p.name()).collect::<Vec<_>>(),
+            )
+            .field("skip_failures", &self.skip_failures)
+            .finish()
+    }
+}
+
+impl BomProcessors {
+    /// Register a processor (processors are applied in the order they are registered)
+    pub fn register(&mut self, processor: impl BomProcessor + 'static) -> &mut Self {
+        self.processors.push(Box::new(processor));
+        self
+    }
+
+    /// Number of registered processors
+    pub fn len(&self) -> usize {
+        self.processors.len()
+    }
+
+    /// Check if no processors are registered
+    pub fn is_empty(&self) -> bool {
+        self.processors.is_empty()
+    }
+
+    /// Apply all the processors to the Bill of Materials
+    pub fn apply(&self, bom: &mut BillOfMaterials) -> Result<(), KonarrError> {
+        for processor in self.processors.iter() {
+            log::debug!("Applying SBOM processor: {}", processor.name());
+            if let Err(err) = processor.process(bom) {
+                if !self.skip_failures {
+                    return Err(err);
+                }
+                log::warn!(
+                    "SBOM processor `{}` failed, skipping: {}",
+                    processor.name(),
+                    err
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Create the built-in processors from the server settings
+    #[cfg(feature = "models")]
+    pub async fn from_settings<'a, T>(connection: &'a T) -> Result<Self, KonarrError>
+    where
+        T: geekorm::GeekConnection<Connection = T> + 'a,
+    {
+        use crate::models::{settings::keys::Setting, ServerSettings};
+
+        let setting = |name: Setting| async move {
+            ServerSettings::fetch_by_name(connection, name)
+                .await
+                .map(|s| s.value)
+                .unwrap_or_default()
+        };
+
+        let mut processors = Self {
+            skip_failures: ServerSettings::get_bool(connection, Setting::BomProcessorsSkipFailures)
+                .await
+                .unwrap_or(true),
+            ..Default::default()
+        };
+
+        let exclude = ExcludeProcessor::parse(&setting(Setting::BomExclude).await);
+        if !exclude.patterns.is_empty() {
+            processors.register(exclude);
+        }
+        let internal =
+            InternalNamespaceProcessor::parse(&setting(Setting::BomInternalNamespaces).await);
+        if !internal.namespaces.is_empty() {
+            processors.register(internal);
+        }
+        Ok(processors)
+    }
+}
+
+/// Remove components which Package URL matches one of the globs (`bom.exclude`)
+///
+/// Globs support `*` as a wildcard (`pkg:npm/@internal/*`).
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeProcessor {
+    /// Package URL globs
+    pub patterns: Vec<String>,
+}
+
+impl ExcludeProcessor {
+    /// Parse a comma separated list of globs
+    pub fn parse(value: &str) -> Self {
+        Self {
+            patterns: split_list(value),
+        }
+    }
+}
+
+impl BomProcessor for ExcludeProcessor {
+    fn name(&self) -> &str {
+        "exclude"
+    }
+
+    fn process(&self, bom: &mut BillOfMaterials) -> Result<(), KonarrError> {
+        let before = bom.components.len();
+        bom.components
+            .retain(|comp| !self.patterns.iter().any(|p| glob_match(p, &comp.purl)));
+
+        let removed = before - bom.components.len();
+        if removed != 0 {
+            log::info!("Excluded `{}` components from the SBOM", removed);
+        }
+        Ok(())
+    }
+}
+
+/// Annotate components in internal namespaces (`bom.internal.namespaces`)
+///
+/// The namespace is matched against the Package URL namespace (`pkg:npm/@acme/app`
+/// is in the `@acme` namespace).
+#[derive(Debug, Clone, Default)]
+pub struct InternalNamespaceProcessor {
+    /// Internal namespaces
+    pub namespaces: Vec<String>,
+}
+
+impl InternalNamespaceProcessor {
+    /// Parse a comma separated list of namespaces
+    pub fn parse(value: &str) -> Self {
+        Self {
+            namespaces: split_list(value),
+        }
+    }
+}
+
+impl BomProcessor for InternalNamespaceProcessor {
+    fn name(&self) -> &str {
+        "internal-namespaces"
+    }
+
+    fn process(&self, bom: &mut BillOfMaterials) -> Result<(), KonarrError> {
+        for comp in bom.components.iter_mut() {
+            let Ok(purl) = purl::GenericPurl::<String>::from_str(&comp.purl) else {
+                continue;
+            };
+            let Some(namespace) = purl.namespace() else {
+                continue;
+            };
+            if self
+                .namespaces
+                .iter()
+                .any(|ns| namespace == ns || namespace.starts_with(&format!("{}/", ns)))
+            {
+                comp.properties
+                    .insert(BOM_PROPERTY_INTERNAL.to_string(), "true".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+/// Match text against a glob (only `*` is supported)
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let mut rest = text;
+    for (index, part) in parts.iter().enumerate() {
+        if index == 0 {
+            match rest.strip_prefix(part) {
+                Some(r) => rest = r,
+                None => return false,
+            }
+        } else if index == parts.len() - 1 {
+            return rest.ends_with(part);
+        } else if let Some(pos) = rest.find(part) {
+            rest = &rest[pos + part.len()..];
+        } else {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bom::sbom::{BomComponent, BomType};
+
+    struct FailingProcessor;
+
+    impl BomProcessor for FailingProcessor {
+        fn name(&self) -> &str {
+            "failing"
+        }
+        fn process(&self, _bom: &mut BillOfMaterials) -> Result<(), KonarrError> {
+            Err(KonarrError::unknown("failed"))
+        }
+    }
+
+    fn test_bom() -> BillOfMaterials {
+        let mut bom = BillOfMaterials::new(BomType::CycloneDX_1_6, "1.6".to_string());
+        for purl in [
+            "pkg:npm/%40acme/app@1.0.0",
+            "pkg:npm/react@18.0.0",
+            "pkg:golang/github.com/acme/tool@v1.0.0",
+            "pkg:deb/debian/openssl@3.0.1",
+        ] {
+            bom.components
+                .push(BomComponent::from_purl(purl.to_string()));
+        }
+        bom
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("pkg:npm/*", "pkg:npm/react@18.0.0"));
+        assert!(glob_match("*openssl*", "pkg:deb/debian/openssl@3.0.1"));
+        assert!(glob_match(
+            "pkg:deb/debian/openssl@3.0.1",
+            "pkg:deb/debian/openssl@3.0.1"
+        ));
+        assert!(!glob_match("pkg:npm/*", "pkg:deb/debian/openssl@3.0.1"));
+        assert!(!glob_match("pkg:*/react", "pkg:npm/react@18.0.0"));
+    }
+
+    #[test]
+    fn test_processors() {
+        let mut processors = BomProcessors::default();
+        processors
+            .register(ExcludeProcessor::parse("pkg:deb/*, pkg:npm/react*"))
+            .register(InternalNamespaceProcessor::parse("@acme,github.com/acme"));
+
+        let mut bom = test_bom();
+        processors.apply(&mut bom).unwrap();
+
+        assert_eq!(bom.components.len(), 2);
+        assert!(bom
+            .components
+            .iter()
+            .all(|c| c.properties.get(BOM_PROPERTY_INTERNAL) == Some(&"true".to_string())));
+    }
+
+    #[test]
+    fn test_processors_failures() {
+        let mut processors = BomProcessors::default();
+        processors
+            .register(FailingProcessor)
+            .register(ExcludeProcessor::parse("pkg:deb/*"));
+
+        let mut bom = test_bom();
+        processors.apply(&mut bom).unwrap();
+        assert_eq!(bom.components.len(), 3);
+
+        processors.skip_failures = false;
+        assert!(processors.apply(&mut test_bom()).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 //! # Bill of Materials (BOM) module
 
 use serde::{Deserialize, Serialize};
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
 
 /// Bill of Materials
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +22,9 @@ pub struct BillOfMaterials {
     pub components: Vec<BomComponent>,
     /// List of vulnerabilities
     pub vulnerabilities: Vec<BomVulnerability>,
+    /// Number of duplicate components dropped while parsing (see [BomComponent::key])
+    #[serde(default)]
+    pub duplicates: usize,
 }
 
 impl BillOfMaterials {
@@ -36,6 +39,7 @@ impl BillOfMaterials {
             container: Container::default(),
             components: Vec::new(),
             vulnerabilities: Vec::new(),
+            duplicates: 0,
         }
     }
 }
@@ -95,6 +99,23 @@ pub struct BomComponent {
     pub comp_type: BomComponentType,
     /// Signature of the component
     pub signature: Option<String>,
+    /// Scope of the component (`required`, `optional` or `excluded`) if the SBOM provided one
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// If the main component depends on the component directly
+    ///
+    /// Only set when the SBOM contains dependency graph data.
+    #[serde(default)]
+    pub direct: Option<bool>,
+    /// Properties / annotations added while processing the SBOM
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+    /// Source / origin package the component was built from (deb source, apk origin)
+    ///
+    /// Only set if it differs from the name of the component, distro advisories are
+    /// published against the source package (`openssl` for `libssl3`).
+    #[serde(default)]
+    pub origin: Option<String>,
 }
 
 impl BomComponent {
@@ -106,6 +127,151 @@ impl BomComponent {
             ..Default::default()
         }
     }
+
+    /// Key the components are deduplicated by
+    ///
+    /// The Package URL normalized to its type, namespace, name, version and the
+    /// qualifiers in [BOM_COMPONENT_KEY_QUALIFIERS] (other qualifiers and the subpath
+    /// differ between tools for the same package). Different versions of a package are
+    /// different components.
+    pub fn key(&self) -> String {
+        let Ok(purl) = purl::GenericPurl::<String>::from_str(&self.purl) else {
+            return self
+                .purl
+                .split(['?', '#'])
+                .next()
+                .unwrap_or_default()
+                .to_string();
+        };
+        let mut key = format!("pkg:{}/", purl.package_type().to_lowercase());
+        if let Some(namespace) = purl.namespace() {
+            key.push_str(namespace);
+            key.push('/');
+        }
+        key.push_str(purl.name());
+        if let Some(version) = purl.version() {
+            key.push('@');
+            key.push_str(version);
+        }
+        let qualifiers: Vec<String> = BOM_COMPONENT_KEY_QUALIFIERS
+            .iter()
+            .filter_map(|name| {
+                purl.qualifiers()
+                    .get(*name)
+                    .map(|value| format!("{}={}", name, value))
+            })
+            .collect();
+        if !qualifiers.is_empty() {
+            key.push('?');
+            key.push_str(&qualifiers.join("&"));
+        }
+        key
+    }
+
+    /// Evidence of how the component was found (see [BomEvidence])
+    pub fn evidence(&self) -> Option<BomEvidence> {
+        BomEvidence::from_properties(&self.properties)
+    }
+
+    /// Find the source / origin package of the component
+    ///
+    /// Uses the properties in [BOM_PROPERTY_ORIGIN] then the `upstream` qualifier of the
+    /// Package URL (Syft), the version is removed (`gcc-12@12.2.0`, `glibc (2.36-9)`).
+    pub fn find_origin(&self) -> Option<String> {
+        let purl = purl::GenericPurl::<String>::from_str(&self.purl).ok();
+        let name = purl.as_ref().map(|p| p.name().to_string());
+
+        BOM_PROPERTY_ORIGIN
+            .iter()
+            .find_map(|property| self.properties.get(*property).cloned())
+            .or_else(|| {
+                purl.as_ref()
+                    .and_then(|p| p.qualifiers().get("upstream").map(|u| u.to_string()))
+            })
+            .and_then(|origin| {
+                origin
+                    .split(['@', ' ', '('])
+                    .next()
+                    .map(|o| o.trim().to_string())
+            })
+            .filter(|origin| !origin.is_empty() && Some(origin) != name.as_ref())
+    }
+}
+
+/// Properties with the source / origin package of a component (see [BomComponent::find_origin])
+pub const BOM_PROPERTY_ORIGIN: [&str; 4] = [
+    "syft:metadata:originPackage",
+    "syft:metadata:source",
+    "syft:metadata:upstream",
+    "aquasecurity:trivy:SrcName",
+];
+
+/// Package URL qualifiers which make a component distinct (see [BomComponent::key])
+pub const BOM_COMPONENT_KEY_QUALIFIERS: [&str; 1] = ["arch"];
+
+/// Property with the cataloger which found the component (Syft)
+pub const BOM_PROPERTY_FOUND_BY: &str = "syft:package:foundBy";
+/// Property with the file the component was found in (Trivy)
+pub const BOM_PROPERTY_FILE_PATH: &str = "aquasecurity:trivy:FilePath";
+/// Property with the confidence of the component identity (CycloneDX evidence)
+pub const BOM_PROPERTY_CONFIDENCE: &str = "cdx:evidence:identity:confidence";
+
+/// Evidence of how a component was found
+///
+/// Selected from the properties of the component (Syft / Trivy properties and the
+/// CycloneDX `evidence`) to help triaging false positives.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BomEvidence {
+    /// Cataloger / analyser which found the component
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub found_by: Option<String>,
+    /// Files the component was found in
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Confidence of the component identity (`0.0` to `1.0`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+}
+
+impl BomEvidence {
+    /// Select the evidence from the properties (`None` if there is no evidence)
+    pub fn from_properties(properties: &BTreeMap<String, String>) -> Option<Self> {
+        let mut evidence = Self {
+            found_by: properties.get(BOM_PROPERTY_FOUND_BY).cloned(),
+            confidence: properties
+                .get(BOM_PROPERTY_CONFIDENCE)
+                .and_then(|c| c.parse().ok()),
+            ..Default::default()
+        };
+        for (name, value) in properties.iter() {
+            let is_path = name == BOM_PROPERTY_FILE_PATH
+                || (name.starts_with("syft:location:") && name.ends_with(":path"))
+                || (name.starts_with("cdx:evidence:occurrence:") && name.ends_with(":location"));
+            if is_path && !evidence.paths.contains(value) {
+                evidence.paths.push(value.clone());
+            }
+        }
+
+        if evidence == Self::default() {
+            None
+        } else {
+            Some(evidence)
+        }
+    }
+
+    /// Serialise the evidence as compact JSON within the byte budget
+    ///
+    /// Paths are dropped (last first) until the evidence fits, `None` if it can't fit.
+    pub fn to_json(&self, budget: usize) -> Option<String> {
+        let mut evidence = self.clone();
+        loop {
+            let json = serde_json::to_string(&evidence).ok()?;
+            if json.len() <= budget {
+                return Some(json);
+            }
+            evidence.paths.pop()?;
+        }
+    }
 }
 
 /// Bill of Materials Vulnerability
@@ -179,6 +345,9 @@ pub struct Container {
     pub image_digest: Option<String>,
     /// Container Tag
     pub image_tag: Option<String>,
+    /// Image labels (`org.opencontainers.image.*`, ...)
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
 }
 
 impl From<BomType> for String {
@@ -0,0 +1,384 @@
+//! # Vulnerability Exploitability eXchange (VEX)
+//!
+//! Statements of OpenVEX and CycloneDX VEX documents. Only the fields needed to
+//! suppress alerts are parsed (advisory, products, status and justification), the
+//! rest of the document is ignored.
+//!
+//! - https://github.com/openvex/spec
+//! - https://cyclonedx.org/capabilities/vex/
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::KonarrError;
+
+/// Format of a VEX document
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VexFormat {
+    /// OpenVEX
+    #[default]
+    OpenVex,
+    /// CycloneDX (`vulnerabilities` with an `analysis`)
+    CycloneDx,
+}
+
+impl std::fmt::Display for VexFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VexFormat::OpenVex => write!(f, "openvex"),
+            VexFormat::CycloneDx => write!(f, "cyclonedx"),
+        }
+    }
+}
+
+/// Status of an advisory for the products of a statement
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BomVexStatus {
+    /// The products are affected
+    Affected,
+    /// The products are not affected (the alerts are suppressed)
+    NotAffected,
+    /// The products are fixed
+    Fixed,
+    /// Not known yet
+    #[default]
+    UnderInvestigation,
+}
+
+impl BomVexStatus {
+    /// Parse an OpenVEX status or a CycloneDX analysis state
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace('-', "_").as_str() {
+            "affected" | "exploitable" => Some(BomVexStatus::Affected),
+            "not_affected" | "false_positive" => Some(BomVexStatus::NotAffected),
+            "fixed" | "resolved" | "resolved_with_pedigree" => Some(BomVexStatus::Fixed),
+            "under_investigation" | "in_triage" => Some(BomVexStatus::UnderInvestigation),
+            _ => None,
+        }
+    }
+}
+
+/// VEX Statement
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VexStatement {
+    /// Advisory (CVE, GHSA, etc.)
+    pub advisory: String,
+    /// Products the statement is for (Package URLs or references, empty for every product)
+    pub products: Vec<String>,
+    /// Status
+    pub status: BomVexStatus,
+    /// Justification (why the products are not affected)
+    pub justification: Option<String>,
+}
+
+/// VEX Document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VexDocument {
+    /// Document ID (`@id`, `serialNumber` or the SHA256 of the document)
+    pub id: String,
+    /// Format of the document
+    pub format: VexFormat,
+    /// Statements of the document
+    pub statements: Vec<VexStatement>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenVexDocument {
+    #[serde(rename = "@id")]
+    id: Option<String>,
+    #[serde(default)]
+    statements: Vec<OpenVexStatement>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenVexStatement {
+    vulnerability: OpenVexVulnerability,
+    #[serde(default)]
+    products: Vec<OpenVexProduct>,
+    status: String,
+    justification: Option<String>,
+    impact_statement: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OpenVexVulnerability {
+    Name(String),
+    Object {
+        name: Option<String>,
+        #[serde(rename = "@id")]
+        id: Option<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OpenVexProduct {
+    Id(String),
+    Object {
+        #[serde(rename = "@id")]
+        id: Option<String>,
+        identifiers: Option<OpenVexIdentifiers>,
+        #[serde(default)]
+        subcomponents: Vec<OpenVexProduct>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenVexIdentifiers {
+    purl: Option<String>,
+}
+
+impl OpenVexProduct {
+    /// Package URL or ID of the product
+    fn id(&self) -> Option<String> {
+        match self {
+            OpenVexProduct::Id(id) => Some(id.clone()),
+            OpenVexProduct::Object {
+                id, identifiers, ..
+            } => identifiers
+                .as_ref()
+                .and_then(|i| i.purl.clone())
+                .or_else(|| id.clone()),
+        }
+    }
+
+    /// Products the statement applies to (the subcomponents if any)
+    fn products(&self) -> Vec<String> {
+        match self {
+            OpenVexProduct::Object { subcomponents, .. } if !subcomponents.is_empty() => {
+                subcomponents.iter().filter_map(|s| s.id()).collect()
+            }
+            _ => self.id().into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxVex {
+    bom_format: String,
+    serial_number: Option<String>,
+    #[serde(default)]
+    components: Vec<CycloneDxVexComponent>,
+    #[serde(default)]
+    vulnerabilities: Vec<CycloneDxVexVulnerability>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CycloneDxVexComponent {
+    #[serde(rename = "bom-ref")]
+    bom_ref: Option<String>,
+    purl: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CycloneDxVexVulnerability {
+    id: String,
+    analysis: Option<CycloneDxVexAnalysis>,
+    #[serde(default)]
+    affects: Vec<CycloneDxVexAffects>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CycloneDxVexAnalysis {
+    state: Option<String>,
+    justification: Option<String>,
+    detail: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CycloneDxVexAffects {
+    #[serde(rename = "ref")]
+    reference: String,
+}
+
+impl VexDocument {
+    /// Parse an OpenVEX or CycloneDX VEX document (JSON)
+    ///
+    /// Statements with an unknown status (or without an analysis) are skipped.
+    pub fn parse(data: &[u8]) -> Result<Self, KonarrError> {
+        let value: serde_json::Value = serde_json::from_slice(data)?;
+
+        if value.get("bomFormat").is_some() {
+            let doc: CycloneDxVex = serde_json::from_value(value)?;
+            if doc.bom_format != "CycloneDX" {
+                return Err(KonarrError::InvalidData(format!(
+                    "Unsupported VEX document format `{}`",
+                    doc.bom_format
+                )));
+            }
+            Ok(Self::from_cyclonedx(doc, data))
+        } else if value.get("statements").is_some() {
+            let doc: OpenVexDocument = serde_json::from_value(value)?;
+            Ok(Self::from_openvex(doc, data))
+        } else {
+            Err(KonarrError::InvalidData(
+                "Unknown VEX document (expected OpenVEX or CycloneDX)".to_string(),
+            ))
+        }
+    }
+
+    fn from_openvex(doc: OpenVexDocument, data: &[u8]) -> Self {
+        let statements = doc
+            .statements
+            .into_iter()
+            .filter_map(|statement| {
+                let advisory = match statement.vulnerability {
+                    OpenVexVulnerability::Name(name) => Some(name),
+                    OpenVexVulnerability::Object { name, id } => name.or(id),
+                }?;
+                let Some(status) = BomVexStatus::parse(&statement.status) else {
+                    log::warn!(
+                        "Unknown VEX status `{}` for `{}`",
+                        statement.status,
+                        advisory
+                    );
+                    return None;
+                };
+                Some(VexStatement {
+                    advisory,
+                    products: statement
+                        .products
+                        .iter()
+                        .flat_map(|p| p.products())
+                        .collect(),
+                    status,
+                    justification: statement.justification.or(statement.impact_statement),
+                })
+            })
+            .collect();
+
+        Self {
+            id: doc
+                .id
+                .unwrap_or_else(|| format!("sha256:{}", super::sha256(data))),
+            format: VexFormat::OpenVex,
+            statements,
+        }
+    }
+
+    fn from_cyclonedx(doc: CycloneDxVex, data: &[u8]) -> Self {
+        // Affects reference the `bom-ref` of the components (or a BOM-Link)
+        let refs: HashMap<String, String> = doc
+            .components
+            .into_iter()
+            .filter_map(|c| Some((c.bom_ref?, c.purl?)))
+            .collect();
+
+        let statements = doc
+            .vulnerabilities
+            .into_iter()
+            .filter_map(|vuln| {
+                let analysis = vuln.analysis?;
+                let status = analysis.state.as_deref().and_then(BomVexStatus::parse)?;
+                Some(VexStatement {
+                    products: vuln
+                        .affects
+                        .iter()
+                        .map(|affects| match refs.get(&affects.reference) {
+                            Some(purl) => purl.clone(),
+                            None => match affects.reference.split_once('#') {
+                                Some((_, reference)) => refs
+                                    .get(reference)
+                                    .cloned()
+                                    .unwrap_or(reference.to_string()),
+                                None => affects.reference.clone(),
+                            },
+                        })
+                        .collect(),
+                    advisory: vuln.id,
+                    status,
+                    justification: analysis.justification.or(analysis.detail),
+                })
+            })
+            .collect();
+
+        Self {
+            id: doc
+                .serial_number
+                .unwrap_or_else(|| format!("sha256:{}", super::sha256(data))),
+            format: VexFormat::CycloneDx,
+            statements,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openvex() {
+        let doc = VexDocument::parse(include_bytes!("testdata/openvex.json")).unwrap();
+        assert_eq!(doc.format, VexFormat::OpenVex);
+        assert_eq!(doc.id, "https://openvex.dev/docs/konarr/vex-nginx-0001");
+        // The statement with an unknown status is skipped
+        assert_eq!(doc.statements.len(), 3);
+
+        let statement = &doc.statements[0];
+        assert_eq!(statement.advisory, "CVE-2023-44487");
+        assert_eq!(statement.status, BomVexStatus::NotAffected);
+        assert_eq!(
+            statement.justification.as_deref(),
+            Some("vulnerable_code_not_in_execute_path")
+        );
+        // The subcomponents of the image
+        assert_eq!(
+            statement.products,
+            vec!["pkg:deb/debian/libnghttp2-14@1.52.0-1?arch=amd64&distro=debian-12"]
+        );
+        assert_eq!(doc.statements[1].status, BomVexStatus::Affected);
+        // Products which are not tracked are kept as-is
+        assert_eq!(doc.statements[2].advisory, "CVE-2024-0001");
+        assert_eq!(doc.statements[2].products, vec!["pkg:npm/untracked@1.0.0"]);
+        assert_eq!(doc.statements[2].status, BomVexStatus::Fixed);
+    }
+
+    #[test]
+    fn test_cyclonedx_vex() {
+        let doc = VexDocument::parse(include_bytes!("testdata/vex.cdx.json")).unwrap();
+        assert_eq!(doc.format, VexFormat::CycloneDx);
+        assert_eq!(doc.id, "urn:uuid:3e671687-395b-41f5-a30f-a58921a69b79");
+        // Vulnerabilities without an analysis are skipped
+        assert_eq!(doc.statements.len(), 3);
+
+        let statement = &doc.statements[0];
+        assert_eq!(statement.advisory, "CVE-2023-5678");
+        assert_eq!(statement.status, BomVexStatus::NotAffected);
+        assert_eq!(
+            statement.justification.as_deref(),
+            Some("code_not_reachable")
+        );
+        // `bom-ref` of a component
+        assert_eq!(
+            statement.products,
+            vec!["pkg:deb/debian/openssl@3.0.11-1?arch=amd64"]
+        );
+        // BOM-Link
+        assert_eq!(
+            doc.statements[1].products,
+            vec!["pkg:deb/debian/libnghttp2-14@1.52.0-1"]
+        );
+        assert_eq!(doc.statements[1].status, BomVexStatus::Affected);
+        assert_eq!(doc.statements[2].status, BomVexStatus::Fixed);
+
+        assert!(VexDocument::parse(br#"{"components": []}"#).is_err());
+        assert!(VexDocument::parse(b"not json").is_err());
+    }
+
+    #[test]
+    fn test_vex_status() {
+        assert_eq!(
+            BomVexStatus::parse("not_affected"),
+            Some(BomVexStatus::NotAffected)
+        );
+        assert_eq!(
+            BomVexStatus::parse("in_triage"),
+            Some(BomVexStatus::UnderInvestigation)
+        );
+        assert_eq!(BomVexStatus::parse("resolved"), Some(BomVexStatus::Fixed));
+        assert_eq!(BomVexStatus::parse("unknown"), None);
+    }
+}
@@ -0,0 +1,286 @@
+//! # Dependencies (components)
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::{ApiResponse, KonarrClient};
+use crate::KonarrError;
+
+/// Dependency of a snapshot
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KonarrDependency {
+    /// Component ID
+    pub id: u32,
+    /// Component type
+    #[serde(rename = "type")]
+    pub component_type: String,
+    /// Package manager
+    pub manager: String,
+    /// Name of the component
+    pub name: String,
+    /// Full coordinate of the package (`group:artifact`, `@scope/name`, `namespace/name`)
+    pub display_name: String,
+    /// Package URL of the component
+    pub purl: Option<String>,
+    /// Version
+    pub version: Option<String>,
+    /// Direct dependency of the snapshot (if the SBOM has dependency graph data)
+    pub direct: Option<bool>,
+}
+
+/// Versions of a component used across the projects (latest snapshots)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KonarrComponentVersions {
+    /// Component ID
+    pub id: u32,
+    /// Display name of the component
+    pub display_name: String,
+    /// Package URL of the component
+    pub purl: String,
+    /// Versions (newest first)
+    pub versions: Vec<KonarrComponentVersion>,
+    /// Recommended version to pin to
+    pub recommended: Option<String>,
+}
+
+/// Version of a component and its alerts
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KonarrComponentVersion {
+    /// Component Version ID
+    pub id: u32,
+    /// Version
+    pub version: String,
+    /// Number of projects using the version
+    pub projects: i64,
+    /// Number of open alerts (advisories)
+    pub alerts: i64,
+    /// Highest severity of the alerts
+    pub severity: Option<String>,
+    /// Number of alerts by severity
+    #[serde(default)]
+    pub severities: BTreeMap<String, i64>,
+}
+
+impl KonarrComponentVersions {
+    /// Fetch the versions of a component
+    pub async fn fetch(client: &KonarrClient, component_id: u32) -> Result<Self, KonarrError> {
+        debug!("Fetching Versions for Component: {}", component_id);
+        match client
+            .get(&format!("/dependencies/{}/versions", component_id))
+            .await?
+            .json::<ApiResponse<Self>>()
+            .await?
+        {
+            ApiResponse::Ok(versions) => Ok(versions),
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Custom tags of the components
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KonarrComponentTag {
+    /// Tag
+    pub tag: String,
+    /// Component to tag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component: Option<u32>,
+    /// PURL pattern of the components to tag (`*` wildcards)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purl: Option<String>,
+    /// Number of newly tagged (or untagged) components
+    #[serde(default, skip_serializing)]
+    pub count: usize,
+}
+
+impl KonarrComponentTag {
+    /// Tags of a component
+    pub async fn list(
+        client: &KonarrClient,
+        component_id: u32,
+    ) -> Result<Vec<String>, KonarrError> {
+        debug!("Listing Tags for Component: {}", component_id);
+        match client
+            .get(&format!("/dependencies/{}/tags", component_id))
+            .await?
+            .json::<ApiResponse<Vec<String>>>()
+            .await?
+        {
+            ApiResponse::Ok(tags) => Ok(tags),
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+
+    /// Assign a tag to a component (admin)
+    pub async fn assign(
+        client: &KonarrClient,
+        component_id: u32,
+        tag: impl Into<String>,
+    ) -> Result<Self, KonarrError> {
+        Self {
+            tag: tag.into(),
+            component: Some(component_id),
+            ..Default::default()
+        }
+        .send(client)
+        .await
+    }
+
+    /// Assign a tag to every component matching a PURL pattern (admin)
+    pub async fn assign_by_purl(
+        client: &KonarrClient,
+        pattern: impl Into<String>,
+        tag: impl Into<String>,
+    ) -> Result<Self, KonarrError> {
+        Self {
+            tag: tag.into(),
+            purl: Some(pattern.into()),
+            ..Default::default()
+        }
+        .send(client)
+        .await
+    }
+
+    /// Remove a tag from a component (admin)
+    pub async fn remove(
+        client: &KonarrClient,
+        component_id: u32,
+        tag: &str,
+    ) -> Result<Self, KonarrError> {
+        debug!("Removing Tag `{}` from Component: {}", tag, component_id);
+        let path = format!("/admin/dependencies/{}/tags/{}", component_id, tag);
+        match client
+            .delete(&path)
+            .await?
+            .json::<ApiResponse<Self>>()
+            .await?
+        {
+            ApiResponse::Ok(tag) => Ok(tag),
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+
+    async fn send(&self, client: &KonarrClient) -> Result<Self, KonarrError> {
+        debug!("Assigning Tag: {}", self.tag);
+        match client
+            .post("/admin/dependencies/tags", self)
+            .await?
+            .json::<ApiResponse<Self>>()
+            .await?
+        {
+            ApiResponse::Ok(tag) => Ok(tag),
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Component Annotation
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KonarrAnnotation {
+    /// Annotation ID
+    #[serde(skip_serializing)]
+    pub id: u32,
+    /// Component ID
+    #[serde(skip_serializing)]
+    pub component_id: u32,
+    /// Project the annotation applies to (all projects if not set)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<u32>,
+    /// Author (username)
+    #[serde(skip_serializing)]
+    pub author: String,
+    /// Text of the annotation (markdown)
+    pub text: String,
+    /// Created At
+    #[serde(skip_serializing)]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Updated At
+    #[serde(skip_serializing)]
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl KonarrAnnotation {
+    /// Create a new annotation for a component (not sent to the server)
+    pub fn new(component_id: u32, text: impl Into<String>) -> Self {
+        Self {
+            component_id,
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// List the annotations of a component (global and the annotations of the project)
+    pub async fn list(
+        client: &KonarrClient,
+        component_id: u32,
+        project: Option<u32>,
+    ) -> Result<Vec<Self>, KonarrError> {
+        debug!("Listing Annotations for Component: {}", component_id);
+        let path = match project {
+            Some(project) => format!(
+                "/dependencies/{}/annotations?project={}",
+                component_id, project
+            ),
+            None => format!("/dependencies/{}/annotations", component_id),
+        };
+        match client
+            .get(&path)
+            .await?
+            .json::<ApiResponse<Vec<Self>>>()
+            .await?
+        {
+            ApiResponse::Ok(annotations) => Ok(annotations),
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+
+    /// Create the annotation
+    pub async fn create(&mut self, client: &KonarrClient) -> Result<Self, KonarrError> {
+        debug!("Annotating Component: {}", self.component_id);
+        let path = format!("/dependencies/{}/annotations", self.component_id);
+        let response = client.post(&path, &self).await?;
+        self.handle(response).await
+    }
+
+    /// Update the text of the annotation (author or admin)
+    pub async fn update(
+        &mut self,
+        client: &KonarrClient,
+        text: impl Into<String>,
+    ) -> Result<Self, KonarrError> {
+        debug!("Updating Annotation: {}", self.id);
+        self.text = text.into();
+        let path = format!(
+            "/dependencies/{}/annotations/{}",
+            self.component_id, self.id
+        );
+        let response = client.patch(&path, &self).await?;
+        self.handle(response).await
+    }
+
+    /// Delete the annotation (author or admin)
+    pub async fn delete(&mut self, client: &KonarrClient) -> Result<Self, KonarrError> {
+        debug!("Deleting Annotation: {}", self.id);
+        let path = format!(
+            "/dependencies/{}/annotations/{}",
+            self.component_id, self.id
+        );
+        let response = client.delete(&path).await?;
+        self.handle(response).await
+    }
+
+    async fn handle(&mut self, response: reqwest::Response) -> Result<Self, KonarrError> {
+        match response.json::<ApiResponse<Self>>().await? {
+            ApiResponse::Ok(annotation) => {
+                *self = annotation;
+                Ok(self.clone())
+            }
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+}
@@ -20,28 +20,23 @@ use log::{debug, info};
 use server::User;
 use url::Url;
 
+pub mod dependencies;
 pub mod projects;
+pub mod search;
 pub mod security;
 pub mod server;
 pub mod snapshot;
 
 pub use server::ServerInfo;
 
+pub use crate::api::Pagination;
+
 use crate::{KonarrError, KONARR_VERSION};
 
-/// Pagination Response
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-pub struct Pagination<T>
-where
-    T: serde::Serialize + Send,
-{
-    /// Data Response
-    pub data: Vec<T>,
-    /// Pages
-    pub pages: u64,
-    /// Total
-    pub total: u64,
-}
+/// Number of attempts of the retried requests (see [with_retries])
+pub const CLIENT_RETRY_ATTEMPTS: u32 = 3;
+/// Delay before the first retry (doubled on every attempt)
+pub const CLIENT_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
 
 /// API Error
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -52,6 +47,12 @@ pub struct ApiError {
     pub details: Option<String>,
     /// Error Status Code
     pub status: u16,
+    /// ID of the conflicting resource (if any)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u32>,
+    /// Current state of the resource (if the request conflicts with it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
 }
 
 /// API Response
@@ -85,8 +86,9 @@ impl KonarrClient {
     /// New Konarr Client
     pub fn new(url: impl Into<Url>) -> Self {
         let url = url.into();
-        let client = reqwest::Client::builder()
+        let client = crate::utils::config::client_builder()
             .cookie_store(true)
+            .user_agent(format!("Konarr/{}", KONARR_VERSION))
             .build()
             .unwrap();
 
@@ -149,6 +151,12 @@ impl KonarrClient {
         Ok(self.server().await?.user)
     }
 
+    /// Get the authenticated principal and its effective permissions
+    pub async fn whoami(&self) -> Result<server::WhoAmI, crate::KonarrError> {
+        debug!("Getting the authenticated principal");
+        server::WhoAmI::fetch(self).await
+    }
+
     /// Login to Konarr Server
     pub async fn login(&mut self) -> Result<(), KonarrError> {
         if let Some((username, password)) = &self.credentials {
@@ -167,10 +175,13 @@ impl KonarrClient {
                 info!("Login Successful");
                 Ok(())
             } else {
-                Err(KonarrError::UnknownError("Login Failed".to_string()))
+                Err(KonarrError::AuthenticationError(format!(
+                    "Login Failed ({})",
+                    response.status()
+                )))
             }
         } else {
-            Err(KonarrError::UnknownError(
+            Err(KonarrError::AuthenticationError(
                 "No Credentials Provided".to_string(),
             ))
         }
@@ -194,6 +205,52 @@ impl KonarrClient {
         Ok(())
     }
 
+    /// Login to Konarr Server and return the session token
+    ///
+    /// The token can be stored and used with the `Bearer` authorization scheme.
+    pub async fn login_session(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<String, KonarrError> {
+        let response = self
+            .client
+            .post(self.base("/auth/login")?)
+            .json(&serde_json::json!({
+                "username": username,
+                "password": password,
+                "token": true,
+            }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(KonarrError::AuthenticationError(format!(
+                "Login Failed ({})",
+                response.status()
+            )));
+        }
+
+        let login: serde_json::Value = response.json().await?;
+        login["token"]
+            .as_str()
+            .map(|t| t.to_string())
+            .ok_or(KonarrError::AuthenticationError(
+                "Server did not return a session token".to_string(),
+            ))
+    }
+
+    /// Logout of the Konarr Server (ends the current session)
+    pub async fn logout(&self) -> Result<(), KonarrError> {
+        let response = self.post("/auth/logout", serde_json::json!({})).await?;
+        if !response.status().is_success() {
+            return Err(KonarrError::AuthenticationError(format!(
+                "Logout Failed ({})",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+
     /// Client GET Request
     pub async fn get(&self, path: &str) -> Result<reqwest::Response, reqwest::Error> {
         self.client
@@ -236,12 +293,63 @@ impl KonarrClient {
     }
 }
 
+/// Run a request, retrying it when the error is retryable ([KonarrError::is_retryable])
+///
+/// Waits the `Retry-After` of rate limited requests, otherwise backs off exponentially
+/// from [CLIENT_RETRY_DELAY].
+pub async fn with_retries<T, F, Fut>(mut request: F) -> Result<T, KonarrError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, KonarrError>>,
+{
+    let mut attempt = 0;
+    loop {
+        match request().await {
+            Err(err) if err.is_retryable() && attempt + 1 < CLIENT_RETRY_ATTEMPTS => {
+                let delay = retry_delay(&err, attempt);
+                log::warn!(
+                    "Request failed ({}), retrying in {}s ({}/{})",
+                    err,
+                    delay.as_secs(),
+                    attempt + 1,
+                    CLIENT_RETRY_ATTEMPTS - 1
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Delay before retrying a failed request
+fn retry_delay(err: &KonarrError, attempt: u32) -> std::time::Duration {
+    match err {
+        KonarrError::RateLimited {
+            retry_after: Some(seconds),
+        } => std::time::Duration::from_secs(*seconds),
+        _ => CLIENT_RETRY_DELAY * 2u32.pow(attempt),
+    }
+}
+
 /// Konarr Client Builder
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct KonarrClientBuilder {
     url: Option<Url>,
     token: Option<String>,
     credentials: Option<(String, String)>,
+    identity: Option<reqwest::Identity>,
+}
+
+impl std::fmt::Debug for KonarrClientBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KonarrClientBuilder")
+            .field("url", &self.url)
+            .field("token", &self.token)
+            .field("credentials", &self.credentials)
+            .field("identity", &self.identity.is_some())
+            .finish()
+    }
 }
 
 impl KonarrClientBuilder {
@@ -268,14 +376,24 @@ impl KonarrClientBuilder {
         self
     }
 
+    /// Set the client certificate and (PKCS#8) private key used for mutual TLS
+    pub fn client_identity(mut self, cert_pem: &[u8], key_pem: &[u8]) -> Result<Self, KonarrError> {
+        self.identity = Some(reqwest::Identity::from_pkcs8_pem(cert_pem, key_pem)?);
+        Ok(self)
+    }
+
     /// Build the Konarr Client
     pub fn build(self) -> Result<KonarrClient, KonarrError> {
         if let Some(url) = self.url {
-            let client = reqwest::Client::builder()
+            let mut builder = crate::utils::config::client_builder()
                 .cookie_store(true)
-                .timeout(std::time::Duration::from_secs(30))
-                .build()
-                .unwrap();
+                .user_agent(format!("Konarr/{}", KONARR_VERSION))
+                .timeout(std::time::Duration::from_secs(30));
+            if let Some(identity) = self.identity {
+                debug!("Using client certificate (mTLS)");
+                builder = builder.identity(identity);
+            }
+            let client = builder.build()?;
 
             Ok(KonarrClient {
                 version: KONARR_VERSION.to_string(),
@@ -285,7 +403,41 @@ impl KonarrClientBuilder {
                 credentials: self.credentials,
             })
         } else {
-            Err(KonarrError::UnknownError("Base URL not set".to_string()))
+            Err(KonarrError::ConfigParseError(
+                "Base URL not set".to_string(),
+            ))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        let err = KonarrError::HttpError {
+            status: 503,
+            message: "unavailable".into(),
+        };
+        assert_eq!(retry_delay(&err, 0), CLIENT_RETRY_DELAY);
+        assert_eq!(retry_delay(&err, 2), CLIENT_RETRY_DELAY * 4);
+
+        let err = KonarrError::RateLimited {
+            retry_after: Some(30),
+        };
+        assert_eq!(retry_delay(&err, 1), std::time::Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_with_retries_not_retryable() {
+        let mut calls = 0;
+        let result: Result<(), KonarrError> = with_retries(|| {
+            calls += 1;
+            async { Err(KonarrError::InvalidData("bad".into())) }
+        })
+        .await;
+        assert!(matches!(result, Err(KonarrError::InvalidData(_))));
+        assert_eq!(calls, 1);
+    }
+}
@@ -1,5 +1,7 @@
 //! # Konarr Project - Agent
-use super::KonarrProject;
+use std::collections::HashMap;
+
+use super::{KonarrProject, KonarrProjectUpdate};
 use crate::{client::snapshot::KonarrSnapshot, KonarrClient, KonarrError};
 use log::{debug, info};
 
@@ -15,7 +17,53 @@ pub struct KonarrProjectSnapshotData {
     pub tool: Option<String>,
 }
 
+/// Container label for the project title
+pub const LABEL_TITLE: &str = "org.opencontainers.image.title";
+/// Container label for the project description
+pub const LABEL_DESCRIPTION: &str = "org.opencontainers.image.description";
+
 impl KonarrProject {
+    /// Compare the container labels with the project title / description
+    ///
+    /// Returns the update to send if the labels changed. Projects edited by a user
+    /// are never updated from the labels.
+    pub fn label_changes(&self, labels: &HashMap<String, String>) -> Option<KonarrProjectUpdate> {
+        if self.edited_by_user {
+            return None;
+        }
+        let update = KonarrProjectUpdate {
+            title: labels
+                .get(LABEL_TITLE)
+                .filter(|title| !title.is_empty() && **title != self.title)
+                .cloned(),
+            description: labels
+                .get(LABEL_DESCRIPTION)
+                .filter(|desc| self.description.as_ref() != Some(*desc))
+                .cloned(),
+        };
+        if update == KonarrProjectUpdate::default() {
+            None
+        } else {
+            Some(update)
+        }
+    }
+
+    /// Update the project title / description from the container labels (if they changed)
+    pub async fn sync_labels(
+        &mut self,
+        client: &KonarrClient,
+        labels: &HashMap<String, String>,
+    ) -> Result<bool, KonarrError> {
+        match self.label_changes(labels) {
+            Some(update) => {
+                info!("Updating Project details from labels: {}", self.name);
+                self.update(client, &update).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
     /// Get or create a new snapshot for a project
     ///
     /// This will create a new snapshot ifthe following conditions are met:
@@ -61,9 +109,13 @@ impl KonarrProject {
                 return Ok(self.snapshot.clone().unwrap());
             }
 
-            // Check container SHA
+            // Check container SHA (the scanned image, the running image is updated every run)
             if let Some(container_sha) = &data.container_sha {
-                if let Some(sha) = snap.metadata.get("container.sha") {
+                if let Some(sha) = snap
+                    .metadata
+                    .get("scan.container.sha")
+                    .or_else(|| snap.metadata.get("container.sha"))
+                {
                     debug!("Container Snapshot SHA: {} == {}", &container_sha, sha);
                     if sha != container_sha {
                         info!("Snapshot SHA for Container is different: {}", self.name);
@@ -91,3 +143,31 @@ impl KonarrProject {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_changes() {
+        let mut project = KonarrProject::new("server/app", "container");
+        project.title = "app".to_string();
+        project.description = Some("Old".to_string());
+
+        let mut labels = HashMap::from([(LABEL_DESCRIPTION.to_string(), "Old".to_string())]);
+        assert_eq!(project.label_changes(&labels), None);
+
+        labels.insert(LABEL_DESCRIPTION.to_string(), "New".to_string());
+        labels.insert(LABEL_TITLE.to_string(), "App".to_string());
+        assert_eq!(
+            project.label_changes(&labels),
+            Some(KonarrProjectUpdate {
+                title: Some("App".to_string()),
+                description: Some("New".to_string()),
+            })
+        );
+
+        project.edited_by_user = true;
+        assert_eq!(project.label_changes(&labels), None);
+    }
+}
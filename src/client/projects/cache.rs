@@ -0,0 +1,159 @@
+//! # Konarr Project - Agent Cache
+//!
+//! Local cache of the SBOMs uploaded by the agent, keyed by the project and the container
+//! image digest. If the image digest has not changed (and the snapshot still exists on the
+//! server) the agent skips running the SBOM tool and only updates the runtime metadata.
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
+
+use crate::KonarrError;
+
+/// Name of the agent cache file (in the data path)
+pub const AGENT_CACHE_FILE: &str = "agent-cache.json";
+
+/// Agent cache entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentCacheEntry {
+    /// SHA256 of the uploaded SBOM
+    pub sbom_sha: String,
+    /// Snapshot the SBOM was uploaded to
+    pub snapshot: u32,
+    /// When the SBOM was uploaded
+    pub uploaded_at: DateTime<Utc>,
+}
+
+/// Agent cache (project and image digest -> uploaded SBOM)
+#[derive(Debug, Clone, Default)]
+pub struct AgentCache {
+    path: PathBuf,
+    expires: Duration,
+    entries: BTreeMap<String, AgentCacheEntry>,
+}
+
+impl AgentCache {
+    /// Load the cache from a file, expired entries are dropped
+    ///
+    /// A missing or invalid cache file results in an empty cache.
+    pub fn load(path: impl Into<PathBuf>, expires: Duration) -> Self {
+        let path = path.into();
+        let entries: BTreeMap<String, AgentCacheEntry> = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                warn!("Invalid agent cache `{}`, ignoring: {}", path.display(), e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        let mut cache = Self {
+            path,
+            expires,
+            entries,
+        };
+        cache.prune();
+        debug!("Loaded {} agent cache entries", cache.entries.len());
+        cache
+    }
+
+    /// Write the cache to its file
+    pub fn save(&self) -> Result<(), KonarrError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&self.entries)?)?;
+        Ok(())
+    }
+
+    /// Get the cache entry of a project for an image digest (if it has not expired)
+    ///
+    /// Projects running the same image each have their own snapshot.
+    pub fn get(&self, project: u32, digest: &str) -> Option<&AgentCacheEntry> {
+        self.entries
+            .get(&Self::key(project, digest))
+            .filter(|entry| !self.is_expired(entry))
+    }
+
+    /// Store the SBOM uploaded by a project for an image digest
+    pub fn insert(
+        &mut self,
+        project: u32,
+        digest: &str,
+        sbom_sha: impl Into<String>,
+        snapshot: u32,
+        uploaded_at: DateTime<Utc>,
+    ) {
+        self.entries.insert(
+            Self::key(project, digest),
+            AgentCacheEntry {
+                sbom_sha: sbom_sha.into(),
+                snapshot,
+                uploaded_at,
+            },
+        );
+    }
+
+    /// Remove the entry of a project for an image digest
+    pub fn remove(&mut self, project: u32, digest: &str) -> Option<AgentCacheEntry> {
+        self.entries.remove(&Self::key(project, digest))
+    }
+
+    /// Number of entries in the cache
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// If the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn key(project: u32, digest: &str) -> String {
+        format!("{}/{}", project, digest)
+    }
+
+    fn is_expired(&self, entry: &AgentCacheEntry) -> bool {
+        Utc::now().signed_duration_since(entry.uploaded_at) >= self.expires
+    }
+
+    fn prune(&mut self) {
+        let expires = self.expires;
+        self.entries
+            .retain(|_, entry| Utc::now().signed_duration_since(entry.uploaded_at) < expires);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_cache() {
+        let path = std::env::temp_dir()
+            .join("konarr-test-agent-cache")
+            .join(AGENT_CACHE_FILE);
+        std::fs::remove_file(&path).ok();
+
+        let mut cache = AgentCache::load(&path, Duration::days(7));
+        assert!(cache.is_empty());
+
+        cache.insert(1, "sha256:aaa", "0123", 1, Utc::now());
+        cache.insert(1, "sha256:bbb", "4567", 2, Utc::now() - Duration::days(8));
+        assert_eq!(cache.get(1, "sha256:aaa").map(|e| e.snapshot), Some(1));
+        assert_eq!(cache.get(1, "sha256:bbb"), None);
+        // Another project running the same image
+        assert_eq!(cache.get(2, "sha256:aaa"), None);
+        cache.save().unwrap();
+
+        let mut cache = AgentCache::load(&path, Duration::days(7));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(
+            cache.get(1, "sha256:aaa").map(|e| e.sbom_sha.as_str()),
+            Some("0123")
+        );
+
+        cache.remove(1, "sha256:aaa");
+        assert!(cache.get(1, "sha256:aaa").is_none());
+
+        std::fs::remove_file(&path).ok();
+    }
+}
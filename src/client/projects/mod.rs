@@ -2,7 +2,10 @@
 use log::debug;
 use serde::{Deserialize, Serialize};
 
-use crate::KonarrError;
+use crate::{
+    utils::names::{normalize_project_name, project_name_title},
+    KonarrError,
+};
 
 use super::{
     security::SecuritySummary, snapshot::KonarrSnapshot, ApiResponse, KonarrClient, Pagination,
@@ -10,6 +13,10 @@ use super::{
 
 #[cfg(feature = "agent")]
 pub mod agent;
+#[cfg(feature = "agent")]
+pub mod cache;
+#[cfg(all(feature = "agent", feature = "docker"))]
+pub mod swarm;
 
 /// List of Konarr Projects
 pub struct KonarrProjects;
@@ -61,6 +68,36 @@ impl KonarrProjects {
         }
     }
 
+    /// List the Projects whose latest snapshot matches the metadata filters
+    ///
+    /// The filters are `(name, value)` pairs, the server only accepts the `registry`,
+    /// `repository`, `tag`, `os`, `arch` and `engine` names.
+    pub async fn filter_metadata(
+        client: &KonarrClient,
+        filters: &[(&str, &str)],
+        page: u32,
+        limit: u32,
+    ) -> Result<Pagination<KonarrProject>, KonarrError> {
+        debug!("Filtering Projects by metadata: {:?}", filters);
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        for (name, value) in filters {
+            query.append_pair(&format!("metadata.{}", name), value);
+        }
+        let query = query
+            .append_pair("page", &page.to_string())
+            .append_pair("limit", &limit.to_string())
+            .finish();
+        match client
+            .get(&format!("/projects?{}", query))
+            .await?
+            .json::<ApiResponse<Pagination<KonarrProject>>>()
+            .await?
+        {
+            ApiResponse::Ok(pagination) => Ok(pagination),
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+
     /// Get Project by ID
     pub async fn by_id(
         client: &KonarrClient,
@@ -80,10 +117,11 @@ impl KonarrProjects {
         name: &str,
     ) -> Result<Option<KonarrProject>, KonarrError> {
         debug!("Getting Project by Name: {}", name);
+        let normalized = normalize_project_name(name);
         let search = Self::search(client, name).await?;
 
         for result in search.data {
-            if result.title == name || result.name == name {
+            if result.title == name || normalize_project_name(&result.name) == normalized {
                 return Ok(Some(result));
             }
         }
@@ -91,6 +129,18 @@ impl KonarrProjects {
     }
 }
 
+/// Project Update Request
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KonarrProjectUpdate {
+    /// Project title
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Project Description (empty to remove)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
 /// Project Request
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -100,8 +150,8 @@ pub struct KonarrProject {
     pub id: u32,
     /// Project Name
     pub name: String,
-    /// Project title
-    #[serde(skip_serializing)]
+    /// Project title (defaults to the last segment of the name)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub title: String,
     /// Project Description
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -117,7 +167,7 @@ pub struct KonarrProject {
     #[serde(skip_serializing)]
     pub snapshot: Option<KonarrSnapshot>,
     /// Number of Snapshots
-    #[serde(skip_serializing)]
+    #[serde(default, skip_serializing)]
     pub snapshots: u32,
 
     /// Security
@@ -129,29 +179,159 @@ pub struct KonarrProject {
     pub parent: Option<u32>,
 
     /// Project Children
+    ///
+    /// Summary children (the default of the project list) only have the ID, name, type and
+    /// health, the other fields use their defaults.
     #[serde(skip_serializing)]
     pub children: Option<Vec<KonarrProject>>,
 
+    /// The title or description was edited by a user
+    #[serde(default, skip_serializing)]
+    pub edited_by_user: bool,
+
+    /// Minimum number of hours between two scans (not set uses the agent's scan cycle)
+    #[serde(default, skip_serializing)]
+    pub scan_interval_hours: Option<u32>,
+
     /// Created At
-    #[serde(skip_serializing)]
+    #[serde(default, skip_serializing)]
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl KonarrProject {
     /// Create a new Project
+    ///
+    /// The name is normalized and the original name is kept as the title
+    /// (the last segment of the name).
     pub fn new(name: impl Into<String>, r#type: impl Into<String>) -> Self {
+        let name = name.into();
         Self {
-            name: name.into(),
+            name: normalize_project_name(&name),
+            title: project_name_title(&name),
             project_type: r#type.into(),
             ..Default::default()
         }
     }
 
+    /// Check if the project is due to be scanned
+    ///
+    /// Projects without a scan interval are always due, otherwise the latest snapshot
+    /// has to be older than the interval. An image digest which differs from the one of
+    /// the latest snapshot (`container.sha`) is always due.
+    pub fn scan_due(&self, now: chrono::DateTime<chrono::Utc>, digest: Option<&str>) -> bool {
+        let Some(snapshot) = &self.snapshot else {
+            return true;
+        };
+        if let Some(digest) = digest.filter(|digest| !digest.is_empty()) {
+            if snapshot.metadata.get("container.sha").map(String::as_str) != Some(digest) {
+                return true;
+            }
+        }
+        match self.scan_interval_hours {
+            Some(hours) if hours > 0 => {
+                now.signed_duration_since(snapshot.created_at)
+                    >= chrono::Duration::hours(hours as i64)
+            }
+            _ => true,
+        }
+    }
+
     /// Create new Project
+    ///
+    /// Fails with a conflict if a project with the same name already exists.
     pub async fn create(&mut self, client: &KonarrClient) -> Result<Self, KonarrError> {
         debug!("Creating Project: {}", self.name);
+        self.create_request(client, "/projects").await
+    }
+
+    /// Create new Project or get the existing project with the same name
+    pub async fn find_or_create(&mut self, client: &KonarrClient) -> Result<Self, KonarrError> {
+        debug!("Finding or Creating Project: {}", self.name);
+        self.create_request(client, "/projects?find_or_create=true")
+            .await
+    }
+
+    async fn create_request(
+        &mut self,
+        client: &KonarrClient,
+        path: &str,
+    ) -> Result<Self, KonarrError> {
+        match client
+            .post(path, &self)
+            .await?
+            .json::<ApiResponse<Self>>()
+            .await?
+        {
+            ApiResponse::Ok(project) => {
+                *self = project;
+                Ok(self.clone())
+            }
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+
+    /// Update the Project (title / description)
+    pub async fn update(
+        &mut self,
+        client: &KonarrClient,
+        update: &KonarrProjectUpdate,
+    ) -> Result<Self, KonarrError> {
+        debug!("Updating Project: {}", self.id);
+        match client
+            .patch(&format!("/projects/{}", self.id), update)
+            .await?
+            .json::<ApiResponse<Self>>()
+            .await?
+        {
+            ApiResponse::Ok(project) => {
+                *self = project;
+                Ok(self.clone())
+            }
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+
+    /// Transfer the Project to another parent (Server, Group or Cluster)
+    ///
+    /// When `rename` is set the project is renamed to the prefix of the new parent.
+    pub async fn transfer(
+        &mut self,
+        client: &KonarrClient,
+        parent: u32,
+        rename: bool,
+    ) -> Result<Self, KonarrError> {
+        debug!("Transferring Project: {} -> {}", self.id, parent);
         match client
-            .post("/projects", &self)
+            .post(
+                &format!("/projects/{}/transfer", self.id),
+                &serde_json::json!({ "parent": parent, "rename": rename }),
+            )
+            .await?
+            .json::<ApiResponse<Self>>()
+            .await?
+        {
+            ApiResponse::Ok(project) => {
+                *self = project;
+                Ok(self.clone())
+            }
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+
+    /// Link an existing (completed) snapshot to the Project
+    ///
+    /// Used when another project already has a snapshot of the same container image.
+    pub async fn link_snapshot(
+        &mut self,
+        client: &KonarrClient,
+        snapshot: u32,
+    ) -> Result<Self, KonarrError> {
+        debug!("Linking Snapshot({}) to Project({})", snapshot, self.id);
+        match client
+            .post(
+                &format!("/projects/{}/snapshots", self.id),
+                &serde_json::json!({ "snapshot": snapshot }),
+            )
             .await?
             .json::<ApiResponse<Self>>()
             .await?
@@ -194,3 +374,100 @@ impl KonarrProject {
             .await?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_due() {
+        let now = chrono::Utc::now();
+        let mut project = KonarrProject::new("homelab/web", "container");
+        // No snapshot yet
+        assert!(project.scan_due(now, Some("sha256:abc")));
+
+        project.snapshot = Some(KonarrSnapshot {
+            id: 1,
+            state: None,
+            dependencies: 0,
+            security: None,
+            scan: None,
+            errors: None,
+            metadata: std::collections::HashMap::from([(
+                "container.sha".to_string(),
+                "sha256:abc".to_string(),
+            )]),
+            invalid_metadata: Default::default(),
+            created_at: now - chrono::Duration::hours(2),
+            new: false,
+        });
+        // No interval (the agent's global cycle)
+        assert!(project.scan_due(now, Some("sha256:abc")));
+        project.scan_interval_hours = Some(0);
+        assert!(project.scan_due(now, Some("sha256:abc")));
+
+        project.scan_interval_hours = Some(24);
+        assert!(!project.scan_due(now, Some("sha256:abc")));
+        assert!(!project.scan_due(now, None));
+        assert!(!project.scan_due(now, Some("")));
+        // New image digest (container recreated)
+        assert!(project.scan_due(now, Some("sha256:def")));
+        // Interval has passed
+        assert!(project.scan_due(now + chrono::Duration::hours(22), Some("sha256:abc")));
+
+        // Snapshot without a digest
+        project.snapshot.as_mut().unwrap().metadata.clear();
+        assert!(project.scan_due(now, Some("sha256:abc")));
+        assert!(!project.scan_due(now, None));
+    }
+
+    #[test]
+    fn test_project_summary_children() {
+        // Project list with the default `children=summary`
+        let json = serde_json::json!({
+            "data": [{
+                "id": 1,
+                "name": "homelab",
+                "title": "Homelab",
+                "type": "Group",
+                "snapshots": 0,
+                "health": "amber",
+                "createdAt": "2024-10-01T12:00:00Z",
+                "children": [
+                    {"id": 2, "name": "homelab/web", "type": "Container", "health": "green"},
+                    {"id": 3, "name": "homelab/db", "type": "Container", "health": "amber"}
+                ],
+                "editedByUser": false,
+                "stale": false,
+                "agentOutdated": false
+            }],
+            "total": 3,
+            "count": 1,
+            "pages": 1
+        });
+
+        let projects: Pagination<KonarrProject> = serde_json::from_value(json).unwrap();
+        let project = &projects.data[0];
+        assert_eq!(project.title, "Homelab");
+        assert_eq!(project.snapshots, 0);
+
+        let children = project.children.as_ref().unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].id, 2);
+        assert_eq!(children[0].name, "homelab/web");
+        assert_eq!(children[0].project_type, "Container");
+        assert!(children[0].title.is_empty());
+        assert_eq!(children[1].snapshots, 0);
+        assert_eq!(
+            children[1].created_at,
+            chrono::DateTime::<chrono::Utc>::default()
+        );
+
+        // Re-serialized as a project request (create / update)
+        let child = serde_json::to_value(&children[0]).unwrap();
+        assert_eq!(
+            child,
+            serde_json::json!({"name": "homelab/web", "type": "Container"})
+        );
+    }
+}
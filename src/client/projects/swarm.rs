@@ -0,0 +1,279 @@
+//! # Konarr Project - Docker Swarm
+//!
+//! The agent running on a Swarm manager node creates a project per service
+//! (`<cluster>/<stack>/<service>` under a Cluster project) and scans the image of the
+//! service once, instead of every node scanning the replicas it runs.
+use std::collections::HashMap;
+
+use bollard::models::{LocalNodeState, Service, SystemInfo};
+
+/// Service label of the stack the service was deployed with
+pub const LABEL_STACK: &str = "com.docker.stack.namespace";
+/// Container label of the service a container (task) belongs to
+pub const LABEL_SERVICE_ID: &str = "com.docker.swarm.service.id";
+
+/// Role of the Docker node in a Swarm
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SwarmRole {
+    /// Swarm is not active on the node
+    #[default]
+    Inactive,
+    /// Worker node (the services are scanned by a manager)
+    Worker,
+    /// Manager node (can list the services)
+    Manager,
+}
+
+impl SwarmRole {
+    /// Role of the node from the Docker system info
+    pub fn from_info(info: &SystemInfo) -> Self {
+        match info.swarm.as_ref() {
+            Some(swarm) if swarm.local_node_state == Some(LocalNodeState::ACTIVE) => {
+                if swarm.control_available.unwrap_or_default() {
+                    SwarmRole::Manager
+                } else {
+                    SwarmRole::Worker
+                }
+            }
+            _ => SwarmRole::Inactive,
+        }
+    }
+
+    /// If Swarm is active on the node
+    pub fn is_active(&self) -> bool {
+        *self != SwarmRole::Inactive
+    }
+}
+
+/// Docker Swarm Service
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SwarmService {
+    /// Service ID
+    pub id: String,
+    /// Project name (`<cluster>/<stack>/<service>`)
+    pub name: String,
+    /// Stack the service was deployed with
+    pub stack: Option<String>,
+    /// Image (without the digest)
+    pub image: String,
+    /// Image digest pinned by the service (`sha256:...`)
+    pub digest: Option<String>,
+    /// Scheduling mode (replicated, global, ...)
+    pub mode: String,
+    /// Number of desired replicas
+    pub replicas: Option<u64>,
+    /// Number of running replicas
+    pub running: Option<u64>,
+    /// Placement constraints
+    pub placement: Vec<String>,
+    /// Labels of the service
+    pub labels: HashMap<String, String>,
+}
+
+impl SwarmService {
+    /// Service of the cluster (`None` if the service has no name or image)
+    pub fn from_service(cluster: &str, service: &Service) -> Option<Self> {
+        let spec = service.spec.as_ref()?;
+        let task = spec.task_template.as_ref();
+        let reference = task
+            .and_then(|task| task.container_spec.as_ref())
+            .and_then(|container| container.image.clone())
+            .filter(|image| !image.is_empty())?;
+        let labels = spec.labels.clone().unwrap_or_default();
+
+        // Services of a stack are named `<stack>_<service>`
+        let stack = labels.get(LABEL_STACK).cloned();
+        let full_name = spec.name.clone()?;
+        let name = match &stack {
+            Some(stack) => {
+                let service = full_name
+                    .strip_prefix(&format!("{}_", stack))
+                    .unwrap_or(&full_name);
+                format!("{}/{}/{}", cluster, stack, service)
+            }
+            None => format!("{}/{}", cluster, full_name),
+        };
+
+        let (image, digest) = match reference.split_once('@') {
+            Some((image, digest)) => (image.to_string(), Some(digest.to_string())),
+            None => (reference, None),
+        };
+
+        let mode = spec.mode.as_ref();
+        let (mode, replicas) = match mode {
+            Some(mode) if mode.global.is_some() => ("global", None),
+            Some(mode) if mode.replicated_job.is_some() => ("replicated-job", None),
+            Some(mode) if mode.global_job.is_some() => ("global-job", None),
+            Some(mode) => (
+                "replicated",
+                mode.replicated
+                    .as_ref()
+                    .and_then(|replicated| replicated.replicas)
+                    .map(|replicas| replicas.max(0) as u64),
+            ),
+            None => ("replicated", None),
+        };
+        let status = service.service_status.as_ref();
+
+        Some(Self {
+            id: service.id.clone().unwrap_or_default(),
+            name,
+            stack,
+            image,
+            digest,
+            mode: mode.to_string(),
+            replicas: status.and_then(|status| status.desired_tasks).or(replicas),
+            running: status.and_then(|status| status.running_tasks),
+            placement: task
+                .and_then(|task| task.placement.as_ref())
+                .and_then(|placement| placement.constraints.clone())
+                .unwrap_or_default(),
+            labels,
+        })
+    }
+
+    /// Image reference to scan (pinned to the digest if known)
+    pub fn reference(&self) -> String {
+        match &self.digest {
+            Some(digest) => format!("{}@{}", self.image, digest),
+            None => self.image.clone(),
+        }
+    }
+
+    /// Snapshot metadata of the service (replicas and placement)
+    pub fn metadata(&self) -> HashMap<&'static str, String> {
+        let mut metadata = HashMap::from([
+            ("swarm.service.id", self.id.clone()),
+            ("swarm.mode", self.mode.clone()),
+            ("swarm.placement", self.placement.join(",")),
+        ]);
+        if let Some(stack) = &self.stack {
+            metadata.insert("swarm.stack", stack.clone());
+        }
+        if let Some(replicas) = self.replicas {
+            metadata.insert("swarm.replicas", replicas.to_string());
+        }
+        if let Some(running) = self.running {
+            metadata.insert("swarm.replicas.running", running.to_string());
+        }
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bollard::models::{
+        ServiceServiceStatus, ServiceSpec, ServiceSpecMode, ServiceSpecModeReplicated, SwarmInfo,
+        TaskSpec, TaskSpecContainerSpec, TaskSpecPlacement,
+    };
+
+    fn service(name: &str, image: &str, stack: Option<&str>, mode: ServiceSpecMode) -> Service {
+        Service {
+            id: Some(format!("{}-id", name)),
+            spec: Some(ServiceSpec {
+                name: Some(name.to_string()),
+                labels: stack
+                    .map(|stack| HashMap::from([(LABEL_STACK.to_string(), stack.to_string())])),
+                task_template: Some(TaskSpec {
+                    container_spec: Some(TaskSpecContainerSpec {
+                        image: Some(image.to_string()),
+                        ..Default::default()
+                    }),
+                    placement: Some(TaskSpecPlacement {
+                        constraints: Some(vec!["node.role==worker".to_string()]),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                mode: Some(mode),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_swarm_role() {
+        let info = |state: LocalNodeState, manager: bool| SystemInfo {
+            swarm: Some(SwarmInfo {
+                local_node_state: Some(state),
+                control_available: Some(manager),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            SwarmRole::from_info(&SystemInfo::default()),
+            SwarmRole::Inactive
+        );
+        assert_eq!(
+            SwarmRole::from_info(&info(LocalNodeState::INACTIVE, false)),
+            SwarmRole::Inactive
+        );
+        assert_eq!(
+            SwarmRole::from_info(&info(LocalNodeState::ACTIVE, false)),
+            SwarmRole::Worker
+        );
+        assert_eq!(
+            SwarmRole::from_info(&info(LocalNodeState::ACTIVE, true)),
+            SwarmRole::Manager
+        );
+        assert!(!SwarmRole::Inactive.is_active());
+    }
+
+    #[test]
+    fn test_swarm_service() {
+        let mut web = service(
+            "web_nginx",
+            "nginx:1.27@sha256:abcdef",
+            Some("web"),
+            ServiceSpecMode {
+                replicated: Some(ServiceSpecModeReplicated { replicas: Some(3) }),
+                ..Default::default()
+            },
+        );
+        let swarm = SwarmService::from_service("homelab", &web).unwrap();
+        assert_eq!(swarm.name, "homelab/web/nginx");
+        assert_eq!(swarm.image, "nginx:1.27");
+        assert_eq!(swarm.digest.as_deref(), Some("sha256:abcdef"));
+        assert_eq!(swarm.reference(), "nginx:1.27@sha256:abcdef");
+        assert_eq!(swarm.replicas, Some(3));
+        assert_eq!(swarm.running, None);
+
+        // The status (`status=true`) has the running and desired tasks
+        web.service_status = Some(ServiceServiceStatus {
+            running_tasks: Some(2),
+            desired_tasks: Some(3),
+            ..Default::default()
+        });
+        let metadata = SwarmService::from_service("homelab", &web)
+            .unwrap()
+            .metadata();
+        assert_eq!(metadata["swarm.stack"], "web");
+        assert_eq!(metadata["swarm.mode"], "replicated");
+        assert_eq!(metadata["swarm.replicas"], "3");
+        assert_eq!(metadata["swarm.replicas.running"], "2");
+        assert_eq!(metadata["swarm.placement"], "node.role==worker");
+
+        let agent = service(
+            "node-exporter",
+            "prom/node-exporter:latest",
+            None,
+            ServiceSpecMode {
+                global: Some(HashMap::new()),
+                ..Default::default()
+            },
+        );
+        let swarm = SwarmService::from_service("homelab", &agent).unwrap();
+        assert_eq!(swarm.name, "homelab/node-exporter");
+        assert_eq!(swarm.mode, "global");
+        assert_eq!(swarm.digest, None);
+        assert!(!swarm.metadata().contains_key("swarm.stack"));
+
+        // Services without an image are skipped
+        let mut broken = agent.clone();
+        broken.spec.as_mut().unwrap().task_template = None;
+        assert!(SwarmService::from_service("homelab", &broken).is_none());
+    }
+}
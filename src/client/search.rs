@@ -0,0 +1,63 @@
+//! # Instance-wide Search
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use super::{ApiResponse, KonarrClient};
+use crate::KonarrError;
+
+/// Search Response
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct KonarrSearch {
+    /// Search term
+    pub query: String,
+    /// Results grouped by category
+    pub results: Vec<KonarrSearchCategory>,
+}
+
+/// Search results for a single category
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct KonarrSearchCategory {
+    /// Category (projects, components, advisories)
+    #[serde(rename = "type")]
+    pub category: String,
+    /// Total number of matches in the category
+    pub total: u64,
+    /// Limited list of matches
+    pub data: Vec<KonarrSearchResult>,
+}
+
+/// Single Search Result
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct KonarrSearchResult {
+    /// Category (projects, components, advisories)
+    #[serde(rename = "type")]
+    pub category: String,
+    /// ID of the Project, Component or Advisory
+    pub id: u32,
+    /// Name
+    pub name: String,
+    /// Additional information (project title, purl, severity)
+    pub description: Option<String>,
+}
+
+impl KonarrSearch {
+    /// Search across projects, components and advisories
+    pub async fn search(
+        client: &KonarrClient,
+        term: impl Into<String>,
+    ) -> Result<Self, KonarrError> {
+        let term = term.into();
+        debug!("Searching for: {}", term);
+        let query: String = url::form_urlencoded::byte_serialize(term.as_bytes()).collect();
+
+        match client
+            .get(&format!("/search?q={}", query))
+            .await?
+            .json::<ApiResponse<Self>>()
+            .await?
+        {
+            ApiResponse::Ok(search) => Ok(search),
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+}
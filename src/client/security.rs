@@ -1,6 +1,9 @@
 //! # Agent Security
+use log::debug;
 use serde::{Deserialize, Serialize};
 
+use super::{ApiResponse, KonarrClient};
+
 /// Security Summary
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -24,3 +27,95 @@ pub struct SecuritySummary {
     /// Unknown
     pub unknown: u32,
 }
+
+/// Security alert of a snapshot
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KonarrAlert {
+    /// Alert ID
+    pub id: u32,
+    /// Name of the advisory
+    pub name: String,
+    /// Kind of alert (`vulnerability`, `policy` or `eol`)
+    pub kind: String,
+    /// Severity
+    pub severity: String,
+    /// State
+    pub state: String,
+    /// Component (and version) that triggered the alert
+    pub component: Option<KonarrAlertComponent>,
+}
+
+/// Component that triggered an alert
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct KonarrAlertComponent {
+    /// Package URL of the component
+    pub purl: String,
+    /// Name of the component
+    pub name: String,
+    /// Version
+    pub version: Option<String>,
+    /// Component type
+    #[serde(rename = "type")]
+    pub component_type: String,
+}
+
+/// Bulk change of the alerts matching a filter
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KonarrAlertsBulk {
+    /// Action (`acknowledge`, `false-positive` or `reopen`)
+    pub action: String,
+    /// Reason of the change
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reason: String,
+    /// Advisory ID or glob
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub advisory: Option<String>,
+    /// Severity of the advisory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<String>,
+    /// Project ID (and its children)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<u32>,
+    /// Package URL glob
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purl: Option<String>,
+    /// Only report the alerts which would change
+    pub dry_run: bool,
+}
+
+/// Result of a bulk change
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KonarrAlertsBulkResult {
+    /// Action applied
+    pub action: String,
+    /// Dry run (nothing was changed)
+    pub dry_run: bool,
+    /// Alerts matching the filter
+    pub matched: u32,
+    /// Alerts changed (or which would change)
+    pub count: u32,
+    /// Changed alerts
+    pub alerts: Vec<KonarrAlert>,
+}
+
+impl KonarrAlertsBulk {
+    /// Apply the bulk change on the server
+    pub async fn apply(
+        &self,
+        client: &KonarrClient,
+    ) -> Result<KonarrAlertsBulkResult, crate::KonarrError> {
+        debug!("Bulk {} of alerts :: {:?}", self.action, self);
+        match client
+            .post("/security/alerts/bulk", self)
+            .await?
+            .json::<ApiResponse<KonarrAlertsBulkResult>>()
+            .await?
+        {
+            ApiResponse::Ok(result) => Ok(result),
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+}
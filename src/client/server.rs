@@ -11,6 +11,9 @@ pub struct ServerInfo {
     pub commit: String,
     /// Server Configuration
     pub config: Option<ServerConfig>,
+    /// Server is in maintenance mode (read-only, uploads are rejected)
+    #[serde(default)]
+    pub maintenance: bool,
     /// Server User
     pub user: Option<User>,
     /// Summary of Konarr Projects
@@ -21,6 +24,9 @@ pub struct ServerInfo {
     pub security: Option<SecuritySummary>,
     /// Agent Settings
     pub agent: Option<AgentSettings>,
+    /// Build information (if visible to the user)
+    #[serde(default)]
+    pub build: Option<BuildInfo>,
 }
 
 impl ServerInfo {
@@ -30,6 +36,21 @@ impl ServerInfo {
     }
 }
 
+/// Server Build Information
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    /// Commit Hash of the build
+    pub commit: String,
+    /// Date of the build
+    pub date: String,
+    /// Rust compiler version
+    pub rustc: String,
+    /// Enabled cargo features
+    #[serde(default)]
+    pub features: Vec<String>,
+}
+
 /// Server Configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -52,6 +73,101 @@ pub struct User {
     pub avatar: Option<String>,
 }
 
+/// Authenticated principal and its effective permissions (`/auth/whoami`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WhoAmI {
+    /// How the request was authenticated (`cookie`, `bearer` or `agent`)
+    pub auth: String,
+    /// User (agents use the `konarr-agent` user)
+    pub user: WhoAmIUser,
+    /// Agent key / token (agent requests only)
+    pub agent: Option<WhoAmIAgent>,
+    /// When the session or agent token expires
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Effective capabilities
+    pub capabilities: WhoAmICapabilities,
+}
+
+/// Authenticated User
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WhoAmIUser {
+    /// User ID
+    pub id: i32,
+    /// Username
+    pub username: String,
+    /// User Role
+    pub role: String,
+}
+
+/// Authenticated Agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WhoAmIAgent {
+    /// Name of the scoped agent token (not set for the legacy agent key)
+    pub token: Option<String>,
+    /// The deprecated shared agent key was used
+    pub legacy: bool,
+    /// Konarr version reported by the agent
+    pub version: Option<String>,
+}
+
+/// Effective capabilities of the authenticated principal
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WhoAmICapabilities {
+    /// Can create snapshots and upload SBOMs
+    pub can_upload: bool,
+    /// Can use the admin API
+    pub can_admin: bool,
+    /// Project whose subtree the agent token is restricted to
+    pub project_scope: Option<i32>,
+}
+
+impl WhoAmI {
+    /// Get the authenticated principal
+    pub async fn fetch(client: &super::KonarrClient) -> Result<Self, crate::KonarrError> {
+        match client
+            .get("/auth/whoami")
+            .await?
+            .json::<super::ApiResponse<Self>>()
+            .await?
+        {
+            super::ApiResponse::Ok(whoami) => Ok(whoami),
+            super::ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+}
+
+impl std::fmt::Display for WhoAmI {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.agent {
+            Some(WhoAmIAgent {
+                token: Some(token), ..
+            }) => write!(f, "agent token `{}`", token)?,
+            Some(_) => write!(f, "legacy agent key")?,
+            None => write!(
+                f,
+                "{} ({}, {})",
+                self.user.username, self.user.role, self.auth
+            )?,
+        }
+        write!(
+            f,
+            " - upload: {}, admin: {}",
+            self.capabilities.can_upload, self.capabilities.can_admin
+        )?;
+        if let Some(scope) = self.capabilities.project_scope {
+            write!(f, ", project scope: {}", scope)?;
+        }
+        if let Some(expires_at) = self.expires_at {
+            write!(f, ", expires: {}", expires_at.to_rfc3339())?;
+        }
+        Ok(())
+    }
+}
+
 /// Konarr Project Summary
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -95,6 +211,9 @@ pub struct DependencySummary {
     pub operating_environments: u32,
     /// Middleware
     pub middleware: u32,
+    /// Dependencies by custom tag
+    #[serde(default)]
+    pub tags: std::collections::BTreeMap<String, u32>,
 }
 
 /// Security Summary
@@ -119,10 +238,20 @@ pub struct SecuritySummary {
     pub unmaintained: u32,
     /// Unknown Security Issues
     pub unknown: u32,
+    /// Alerts resolved by a new snapshot (all time)
+    #[serde(default)]
+    pub resolved: u32,
+    /// Alerts resolved in the last 30 days
+    #[serde(default)]
+    pub resolved_recent: u32,
 }
 
 /// Agent Configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// The settings managed by the server ([AGENT_SERVER_SETTINGS]) replace the local
+/// configuration (`konarr.yml` / environment) of the agent, the other agent settings
+/// (token, Docker socket, project, ...) are local only.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AgentSettings {
     /// Agent tool name
@@ -131,4 +260,297 @@ pub struct AgentSettings {
     pub auto_install: bool,
     /// Agent Auto-Update Tools
     pub auto_update: bool,
+    /// Containers which are not scanned (older servers do not send it)
+    #[serde(default)]
+    pub exclude: Option<Vec<String>>,
+    /// Minutes between two runs in monitoring mode
+    #[serde(default)]
+    pub interval: Option<u32>,
+    /// Days before a cached SBOM is rescanned
+    #[serde(default)]
+    pub cache_expires: Option<u32>,
+}
+
+/// Agent configuration keys managed by the server
+pub const AGENT_SERVER_SETTINGS: [&str; 6] = [
+    "agent.tool",
+    "agent.tool_auto_install",
+    "agent.tool_auto_update",
+    "agent.exclude",
+    "agent.interval",
+    "agent.cache_expires",
+];
+
+/// Local agent setting replaced by the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentSettingOverride {
+    /// Configuration key (`agent.tool`)
+    pub key: &'static str,
+    /// Local value
+    pub local: String,
+    /// Server value
+    pub server: String,
+}
+
+impl AgentSettings {
+    /// Apply the server-authoritative settings to the agent configuration
+    ///
+    /// Returns the configuration keys set by the server and the local values which were
+    /// replaced (the values which differ).
+    pub fn apply(
+        &self,
+        config: &mut crate::utils::config::AgentConfig,
+    ) -> (Vec<&'static str>, Vec<AgentSettingOverride>) {
+        let mut keys = Vec::new();
+        let mut overrides = Vec::new();
+        let mut set = |key: &'static str, local: String, server: String| {
+            keys.push(key);
+            if local != server {
+                overrides.push(AgentSettingOverride { key, local, server });
+            }
+        };
+
+        let tool = self.tool.to_lowercase();
+        set(
+            "agent.tool",
+            config.tool.clone().unwrap_or_default(),
+            tool.clone(),
+        );
+        config.tool = Some(tool);
+        set(
+            "agent.tool_auto_install",
+            config.tool_auto_install.to_string(),
+            self.auto_install.to_string(),
+        );
+        config.tool_auto_install = self.auto_install;
+        set(
+            "agent.tool_auto_update",
+            config.tool_auto_update.to_string(),
+            self.auto_update.to_string(),
+        );
+        config.tool_auto_update = self.auto_update;
+
+        if let Some(exclude) = &self.exclude {
+            set("agent.exclude", config.exclude.join(","), exclude.join(","));
+            config.exclude = exclude.clone();
+        }
+        if let Some(interval) = self.interval {
+            set(
+                "agent.interval",
+                config.interval().to_string(),
+                interval.to_string(),
+            );
+            config.interval = Some(interval);
+        }
+        if let Some(expires) = self.cache_expires {
+            set(
+                "agent.cache_expires",
+                config.cache_expires().num_days().to_string(),
+                expires.to_string(),
+            );
+            config.cache_expires = Some(expires);
+        }
+        (keys, overrides)
+    }
+}
+
+/// Server Status (admin only)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatus {
+    /// Storage diagnostics
+    pub storage: StorageStatus,
+}
+
+/// Storage diagnostics
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageStatus {
+    /// Size of the database file (bytes)
+    pub database_size: u64,
+    /// Free disk space on the data path (bytes)
+    pub disk_free: u64,
+    /// Size of the Grype database (bytes)
+    pub grypedb_size: u64,
+    /// Build of the Grype database
+    pub grypedb_built: Option<String>,
+    /// Number of stored SBOMs
+    pub sboms_count: u64,
+    /// Total size of the stored SBOMs (bytes)
+    pub sboms_size: u64,
+    /// Snapshots with the largest SBOMs
+    #[serde(default)]
+    pub largest_snapshots: Vec<SnapshotSize>,
+    /// When the storage diagnostics last changed
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Size of a Snapshot SBOM
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotSize {
+    /// Snapshot ID
+    pub snapshot_id: u32,
+    /// Size of the SBOM (bytes)
+    pub size_bytes: u64,
+}
+
+impl ServerStatus {
+    /// Get the server status (requires an admin session)
+    pub async fn fetch(client: &super::KonarrClient) -> Result<Self, crate::KonarrError> {
+        match client
+            .get("/admin/status")
+            .await?
+            .json::<super::ApiResponse<Self>>()
+            .await?
+        {
+            super::ApiResponse::Ok(status) => Ok(status),
+            super::ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Maintenance mode of the server (admin only)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceStatus {
+    /// If the server is in maintenance mode (read-only)
+    pub enabled: bool,
+}
+
+impl MaintenanceStatus {
+    /// Get the maintenance mode (requires an admin session)
+    pub async fn fetch(client: &super::KonarrClient) -> Result<Self, crate::KonarrError> {
+        match client
+            .get("/admin/maintenance")
+            .await?
+            .json::<super::ApiResponse<Self>>()
+            .await?
+        {
+            super::ApiResponse::Ok(status) => Ok(status),
+            super::ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+
+    /// Enable or disable the maintenance mode (requires an admin session)
+    pub async fn set(
+        client: &super::KonarrClient,
+        enabled: bool,
+    ) -> Result<Self, crate::KonarrError> {
+        match client
+            .post("/admin/maintenance", Self { enabled })
+            .await?
+            .json::<super::ApiResponse<Self>>()
+            .await?
+        {
+            super::ApiResponse::Ok(status) => Ok(status),
+            super::ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_info_maintenance() {
+        let info: ServerInfo = serde_json::from_str(
+            r#"{"version":"0.4.0","commit":"abc","config":{"initialised":true,"registration":false}}"#,
+        )
+        .unwrap();
+        // Older servers do not report the maintenance mode
+        assert!(!info.maintenance);
+
+        let info: ServerInfo = serde_json::from_str(
+            r#"{"version":"0.4.0","commit":"abc","maintenance":true,"config":{"initialised":true,"registration":false}}"#,
+        )
+        .unwrap();
+        assert!(info.maintenance);
+    }
+    #[test]
+    fn test_whoami_display() {
+        let whoami: WhoAmI = serde_json::from_str(
+            r#"{"auth":"agent","user":{"id":0,"username":"konarr-agent","role":"Agent"},"agent":{"token":"ci","legacy":false},"capabilities":{"canUpload":true,"canAdmin":false,"projectScope":4}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            whoami.to_string(),
+            "agent token `ci` - upload: true, admin: false, project scope: 4"
+        );
+    }
+
+    #[test]
+    fn test_agent_settings_apply() {
+        use crate::utils::config::AgentConfig;
+
+        let local = AgentConfig {
+            project_id: Some(4),
+            host: Some("node-1".to_string()),
+            token: Some("local-token".to_string()),
+            docker_socket: Some("/run/docker.sock".to_string()),
+            tool: Some("trivy".to_string()),
+            tool_auto_install: false,
+            tool_auto_update: true,
+            no_cache: true,
+            cache_expires: Some(3),
+            exclude: vec!["local-*".to_string()],
+            interval: Some(10),
+            ..Default::default()
+        };
+
+        // Older servers only send the tool settings
+        let settings: AgentSettings =
+            serde_json::from_str(r#"{"tool":"Syft","autoInstall":true,"autoUpdate":true}"#)
+                .unwrap();
+        let mut config = local.clone();
+        let (keys, overrides) = settings.apply(&mut config);
+        assert_eq!(keys, AGENT_SERVER_SETTINGS[..3].to_vec());
+        assert_eq!(
+            overrides
+                .iter()
+                .map(|o| (o.key, o.local.as_str(), o.server.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("agent.tool", "trivy", "syft"),
+                ("agent.tool_auto_install", "false", "true"),
+            ]
+        );
+        assert_eq!(config.tool.as_deref(), Some("syft"));
+        assert_eq!(config.exclude, local.exclude);
+        assert_eq!(config.interval, Some(10));
+
+        let settings: AgentSettings = serde_json::from_str(
+            r#"{"tool":"Syft","autoInstall":false,"autoUpdate":false,"exclude":["registry:*"],"interval":5,"cacheExpires":7}"#,
+        )
+        .unwrap();
+        let mut config = local.clone();
+        let (keys, overrides) = settings.apply(&mut config);
+        assert_eq!(keys, AGENT_SERVER_SETTINGS.to_vec());
+        assert_eq!(overrides.len(), 5);
+        assert_eq!(config.exclude, vec!["registry:*".to_string()]);
+        assert!(config.is_excluded("registry", "registry:2"));
+        assert!(!config.is_excluded("local-web", "nginx:latest"));
+        assert_eq!(config.interval(), 5);
+        assert_eq!(config.cache_expires().num_days(), 7);
+        assert!(!config.tool_auto_update);
+
+        // The local only settings are never replaced
+        assert_eq!(config.project_id, local.project_id);
+        assert_eq!(config.host, local.host);
+        assert_eq!(config.token, local.token);
+        assert_eq!(config.docker_socket, local.docker_socket);
+        assert_eq!(config.no_cache, local.no_cache);
+
+        // Round-trip of the effective configuration (sources of the values)
+        let mut effective = crate::Config::default();
+        effective.agent = config;
+        let sources = effective.agent_sources(&keys);
+        let source = |key: &str| sources.iter().find(|v| v.key == key).unwrap().source;
+        assert_eq!(source("agent.tool"), crate::ConfigSource::Server);
+        assert_eq!(source("agent.exclude"), crate::ConfigSource::Server);
+        assert_ne!(source("agent.token"), crate::ConfigSource::Server);
+        assert_ne!(source("agent.docker-socket"), crate::ConfigSource::Server);
+        assert!(sources.iter().all(|v| v.key.starts_with("agent.")));
+    }
 }
@@ -1,10 +1,17 @@
 //! Snapshot Request
-use log::debug;
+use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use super::security::SecuritySummary;
-use super::{ApiResponse, KonarrClient};
+use super::dependencies::KonarrDependency;
+use super::security::{KonarrAlert, SecuritySummary};
+use super::{ApiResponse, KonarrClient, Pagination};
+use crate::bom::github::GitHubSnapshot;
+
+/// Number of times to poll a snapshot that is being processed
+const SNAPSHOT_PROCESSING_RETRIES: u32 = 30;
+/// Interval between polling a snapshot that is being processed
+const SNAPSHOT_PROCESSING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
 
 /// Snapshot Request
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -12,13 +19,25 @@ use super::{ApiResponse, KonarrClient};
 pub struct KonarrSnapshot {
     /// Snapshot ID
     pub id: u32,
+    /// Snapshot State (Created, Processing, Completed, Failed)
+    #[serde(default)]
+    pub state: Option<String>,
     /// Dependencies Count
     pub dependencies: u32,
     /// Security Summary
     #[serde(default)]
     pub security: Option<SecuritySummary>,
+    /// Scan information (how the SBOM tool was run)
+    #[serde(default)]
+    pub scan: Option<KonarrSnapshotScan>,
+    /// Components which failed to index (the snapshot is partially complete)
+    #[serde(default)]
+    pub errors: Option<KonarrSnapshotErrors>,
     /// Snapshot Metadata
     pub metadata: HashMap<String, String>,
+    /// Metadata values the server skipped on the last update (key and reason)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub invalid_metadata: HashMap<String, String>,
     /// Created At
     pub created_at: chrono::DateTime<chrono::Utc>,
 
@@ -27,6 +46,47 @@ pub struct KonarrSnapshot {
     pub new: bool,
 }
 
+/// Components of the SBOM which failed to index
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KonarrSnapshotErrors {
+    /// Number of components which failed
+    pub count: usize,
+    /// Sample of the errors
+    #[serde(default)]
+    pub sample: Vec<KonarrSnapshotError>,
+}
+
+/// Component of the SBOM which failed to index
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KonarrSnapshotError {
+    /// Package URL of the component
+    pub purl: String,
+    /// Reason the component failed
+    pub reason: String,
+}
+
+/// Snapshot Scan Information
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KonarrSnapshotScan {
+    /// Tool used to generate the SBOM
+    pub tool: Option<String>,
+    /// Tool version
+    pub tool_version: Option<String>,
+    /// Scan target
+    pub target: Option<String>,
+    /// Scope the image was scanned with (`all-layers`, `squashed`)
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// Duration of the scan in milliseconds
+    pub duration_ms: Option<u64>,
+    /// Host the agent was running on
+    pub agent_host: Option<String>,
+    /// Version of the agent
+    pub agent_version: Option<String>,
+}
+
 impl KonarrSnapshot {
     /// Create a new snapshot
     pub async fn create(
@@ -70,6 +130,100 @@ impl KonarrSnapshot {
         }
     }
 
+    /// Get the latest completed snapshot scanned from a container image digest
+    pub async fn by_digest(
+        client: &KonarrClient,
+        digest: &str,
+    ) -> Result<Self, crate::KonarrError> {
+        debug!("Getting snapshot by digest: `{}`", digest);
+        match client
+            .get(format!("/snapshots/digest/{}", digest).as_str())
+            .await?
+            .json::<ApiResponse<Self>>()
+            .await?
+        {
+            ApiResponse::Ok(snapshot) => Ok(snapshot),
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+
+    /// List the dependencies of a snapshot (optionally of a component type)
+    pub async fn dependencies(
+        client: &KonarrClient,
+        id: u32,
+        component_type: Option<&str>,
+        page: u32,
+        limit: u32,
+    ) -> Result<Pagination<KonarrDependency>, crate::KonarrError> {
+        debug!("Listing dependencies of snapshot `{}`", id);
+        let query = Self::list_query(component_type, page, limit);
+        match client
+            .get(&format!("/snapshots/{}/dependencies?{}", id, query))
+            .await?
+            .json::<ApiResponse<Pagination<KonarrDependency>>>()
+            .await?
+        {
+            ApiResponse::Ok(dependencies) => Ok(dependencies),
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+
+    /// List the security alerts of a snapshot (optionally of a component type)
+    pub async fn alerts(
+        client: &KonarrClient,
+        id: u32,
+        component_type: Option<&str>,
+        page: u32,
+        limit: u32,
+    ) -> Result<Pagination<KonarrAlert>, crate::KonarrError> {
+        debug!("Listing alerts of snapshot `{}`", id);
+        let query = Self::list_query(component_type, page, limit);
+        match client
+            .get(&format!("/snapshots/{}/alerts?{}", id, query))
+            .await?
+            .json::<ApiResponse<Pagination<KonarrAlert>>>()
+            .await?
+        {
+            ApiResponse::Ok(alerts) => Ok(alerts),
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+
+    fn list_query(component_type: Option<&str>, page: u32, limit: u32) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        if let Some(component_type) = component_type {
+            query.append_pair("component_type", component_type);
+        }
+        query
+            .append_pair("page", &page.to_string())
+            .append_pair("limit", &limit.to_string())
+            .finish()
+    }
+
+    /// Export the dependencies in the GitHub dependency submission format
+    pub async fn export_github(
+        client: &KonarrClient,
+        id: u32,
+        sha: &str,
+        git_ref: &str,
+    ) -> Result<GitHubSnapshot, crate::KonarrError> {
+        debug!("Exporting snapshot `{}` for GitHub", id);
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("format", "github")
+            .append_pair("sha", sha)
+            .append_pair("ref", git_ref)
+            .finish();
+        match client
+            .get(&format!("/snapshots/{}/export?{}", id, query))
+            .await?
+            .json::<ApiResponse<GitHubSnapshot>>()
+            .await?
+        {
+            ApiResponse::Ok(export) => Ok(export),
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+
     /// Update Metadata to a snapshot (only update on changes)
     pub async fn update_metadata(
         &self,
@@ -91,12 +245,37 @@ impl KonarrSnapshot {
         }
 
         debug!("Updating Metadata for Snapshot({:?})", self.id);
-        client
+        let response = client
             .patch(format!("/snapshots/{}/metadata", self.id).as_str(), changes)
             .await?;
+        // Invalid values are skipped by the server (the other keys are still updated)
+        if let Ok(ApiResponse::Ok(snapshot)) = response.json::<ApiResponse<Self>>().await {
+            for (key, reason) in snapshot.invalid_metadata {
+                warn!("Metadata `{}` was skipped by the server: {}", key, reason);
+            }
+        }
         Ok(())
     }
 
+    /// Remove a Metadata key from the snapshot (computed keys are refused)
+    pub async fn remove_metadata(
+        &mut self,
+        client: &KonarrClient,
+        key: &str,
+    ) -> Result<(), crate::KonarrError> {
+        debug!("Removing Metadata `{}` from Snapshot({:?})", key, self.id);
+        let response = client
+            .delete(format!("/snapshots/{}/metadata/{}", self.id, key).as_str())
+            .await?;
+        match response.json::<ApiResponse<Self>>().await? {
+            ApiResponse::Ok(snapshot) => {
+                *self = snapshot;
+                Ok(())
+            }
+            ApiResponse::Error(err) => Err(err.into()),
+        }
+    }
+
     /// Upload BOM to the the snapshot
     pub async fn upload_bom<T>(
         &self,
@@ -114,8 +293,60 @@ impl KonarrSnapshot {
             .json::<ApiResponse<Self>>()
             .await?
         {
-            ApiResponse::Ok(snapshot) => Ok(snapshot),
+            ApiResponse::Ok(snapshot) => {
+                if let Some(errors) = &snapshot.errors {
+                    warn!(
+                        "Snapshot({}) is partially complete, {} components failed to index",
+                        snapshot.id, errors.count
+                    );
+                    for error in errors.sample.iter() {
+                        warn!(" > {} :: {}", error.purl, error.reason);
+                    }
+                }
+                Ok(snapshot)
+            }
+            // Servers which do not report the state only conflict while processing
+            ApiResponse::Error(err)
+                if err.status == 409
+                    && matches!(err.state.as_deref(), None | Some("Processing")) =>
+            {
+                warn!(
+                    "Snapshot({}) is already being processed by the server, waiting for it to finish",
+                    self.id
+                );
+                self.wait_for_processing(client).await
+            }
+            ApiResponse::Error(err) if err.status == 409 => {
+                Err(crate::KonarrError::KonarrClient(format!(
+                    "Snapshot({}) is `{}`, create a new snapshot to upload the SBOM",
+                    self.id,
+                    err.state.unwrap_or_default()
+                )))
+            }
             ApiResponse::Error(err) => Err(err.into()),
         }
     }
+
+    /// Poll the server until the snapshot is no longer being processed
+    pub async fn wait_for_processing(
+        &self,
+        client: &KonarrClient,
+    ) -> Result<Self, crate::KonarrError> {
+        for _ in 0..SNAPSHOT_PROCESSING_RETRIES {
+            tokio::time::sleep(SNAPSHOT_PROCESSING_INTERVAL).await;
+
+            let snapshot = Self::by_id(client, self.id).await?;
+            if snapshot.state.as_deref() != Some("Processing") {
+                debug!(
+                    "Snapshot({}) finished processing: {:?}",
+                    self.id, snapshot.state
+                );
+                return Ok(snapshot);
+            }
+        }
+        Err(crate::KonarrError::KonarrClient(format!(
+            "Snapshot({}) is still being processed by the server",
+            self.id
+        )))
+    }
 }
@@ -1,4 +1,7 @@
 //! # Konarr Error
+//!
+//! Every error maps to a HTTP status code ([KonarrError::status_code]) used by the server
+//! API, and can be retried or not ([KonarrError::is_retryable]) by the clients.
 use thiserror::Error;
 
 /// Konarr Error
@@ -26,6 +29,12 @@ pub enum KonarrError {
     /// Parsing Bill of Materials Error
     #[error("Failed to parse SBOM: {0}")]
     ParseSBOM(String),
+    /// Bill of Materials is parsed but not valid / supported
+    #[error("Invalid SBOM: {reason}")]
+    InvalidSbom {
+        /// Reason the SBOM is invalid
+        reason: String,
+    },
     /// Parsing PURL
     #[error("PURL parsing error")]
     PurlError(#[from] purl::ParseError),
@@ -36,6 +45,41 @@ pub enum KonarrError {
     /// Invalid Data
     #[error("Invalid Data: {0}")]
     InvalidData(String),
+    /// Checksum of downloaded / stored data does not match
+    #[error("Checksum mismatch (expected `{expected}`, got `{actual}`)")]
+    ChecksumMismatch {
+        /// Expected checksum
+        expected: String,
+        /// Checksum of the data
+        actual: String,
+    },
+
+    /// Resource Not Found
+    #[error("Not Found: {0}")]
+    NotFound(String),
+    /// Project Not Found (by name or ID)
+    #[error("Project `{0}` not found")]
+    ProjectNotFound(String),
+    /// Resource is not in the state required by the action
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    /// Feature is disabled by the server settings
+    #[error("Disabled: {0}")]
+    Disabled(String),
+    /// Too many requests, retry after the number of seconds (if known)
+    #[error("Rate limited")]
+    RateLimited {
+        /// Seconds to wait before retrying
+        retry_after: Option<u64>,
+    },
+    /// HTTP request to an external service failed
+    #[error("HTTP request failed ({status}): {message}")]
+    HttpError {
+        /// HTTP status code of the response
+        status: u16,
+        /// Request / response details
+        message: String,
+    },
 
     /// Authentication Error
     #[error("Authentication Error: {0}")]
@@ -54,6 +98,16 @@ pub enum KonarrError {
     #[error("{0}")]
     GeekOrm(#[from] geekorm::Error),
 
+    /// Database Migration Error
+    #[cfg(feature = "models")]
+    #[error("Database Migration Error: {0}")]
+    MigrationError(String),
+
+    /// Settings Encryption Error (missing or wrong encryption key)
+    #[cfg(feature = "models")]
+    #[error("Settings Encryption Error: {0}")]
+    EncryptionError(String),
+
     /// Libsql Error
     #[cfg(feature = "models")]
     #[error("{0}")]
@@ -63,6 +117,10 @@ pub enum KonarrError {
     #[cfg(feature = "tools")]
     #[error("Tool Error: {0}")]
     ToolError(String),
+    /// Tool is not installed / found
+    #[cfg(feature = "tools")]
+    #[error("Tool not available: {0}")]
+    ToolNotAvailable(String),
 
     /// From Utf8 Error
     #[error("{0}")]
@@ -83,17 +141,169 @@ pub enum KonarrError {
     BollardError(#[from] bollard::errors::Error),
 
     /// Unknown Error
+    ///
+    /// Last resort, use [KonarrError::unknown] so the remaining cases are logged.
     #[error("Unknown Error: {0}")]
     UnknownError(String),
 }
 
+impl KonarrError {
+    /// Unknown Error (logged at debug level with the caller location so the remaining
+    /// cases can be replaced with a specific error)
+    #[track_caller]
+    pub fn unknown(message: impl Into<String>) -> Self {
+        let message = message.into();
+        log::debug!(
+            "UnknownError constructed at {}: {}",
+            std::panic::Location::caller(),
+            message
+        );
+        KonarrError::UnknownError(message)
+    }
+
+    /// Error from the HTTP status of a response
+    pub fn from_status(status: u16, message: impl Into<String>) -> Self {
+        let message = message.into();
+        match status {
+            401 | 403 => KonarrError::AuthenticationError(message),
+            404 => KonarrError::NotFound(message),
+            409 => KonarrError::Conflict(message),
+            429 => KonarrError::RateLimited { retry_after: None },
+            _ => KonarrError::HttpError { status, message },
+        }
+    }
+
+    /// HTTP status code of the error (used by the server API responses)
+    pub fn status_code(&self) -> u16 {
+        match self {
+            KonarrError::ParseSBOM(_)
+            | KonarrError::InvalidSbom { .. }
+            | KonarrError::PurlError(_)
+            | KonarrError::VersionError(_)
+            | KonarrError::InvalidData(_) => 400,
+            KonarrError::AuthenticationError(_) | KonarrError::Unauthorized => 401,
+            KonarrError::NotFound(_) | KonarrError::ProjectNotFound(_) => 404,
+            #[cfg(feature = "models")]
+            KonarrError::GeekOrm(geekorm::Error::NoRowsFound) => 404,
+            KonarrError::Conflict(_) => 409,
+            KonarrError::RateLimited { .. } => 429,
+            KonarrError::ChecksumMismatch { .. } | KonarrError::HttpError { .. } => 502,
+            #[cfg(feature = "client")]
+            KonarrError::KonarrClient(_) | KonarrError::ReqwestError(_) => 502,
+            KonarrError::Disabled(_) => 503,
+            #[cfg(feature = "models")]
+            error if error.is_database_busy() => 503,
+            #[cfg(feature = "tools")]
+            KonarrError::ToolNotAvailable(_) => 503,
+            _ => 500,
+        }
+    }
+
+    /// Check if the error is a SQLite busy / locked error (another connection is writing)
+    #[cfg(feature = "models")]
+    pub fn is_database_busy(&self) -> bool {
+        match self {
+            KonarrError::GeekOrm(e) => crate::models::is_database_busy(e.to_string()),
+            KonarrError::Libsql(e) => crate::models::is_database_busy(e.to_string()),
+            _ => false,
+        }
+    }
+
+    /// Check if the action can be retried (transient network / server errors)
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            #[cfg(feature = "models")]
+            error if error.is_database_busy() => true,
+            KonarrError::RateLimited { .. } | KonarrError::ChecksumMismatch { .. } => true,
+            KonarrError::HttpError { status, .. } => *status >= 500,
+            KonarrError::IOError(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::ConnectionRefused
+            ),
+            #[cfg(feature = "client")]
+            KonarrError::ReqwestError(e) => {
+                e.is_timeout()
+                    || e.is_connect()
+                    || e.status()
+                        .is_some_and(|status| status.is_server_error() || status.as_u16() == 429)
+            }
+            _ => false,
+        }
+    }
+}
+
 #[cfg(feature = "client")]
 impl From<crate::client::ApiError> for KonarrError {
     fn from(error: crate::client::ApiError) -> Self {
-        if let Some(details) = error.details {
-            KonarrError::KonarrClient(format!("{} - {}", error.message, details))
+        let message = if let Some(details) = error.details {
+            format!("{} - {}", error.message, details)
         } else {
-            KonarrError::KonarrClient(error.message)
+            error.message
+        };
+        match error.status {
+            401 => KonarrError::AuthenticationError(message),
+            404 => KonarrError::NotFound(message),
+            409 => KonarrError::Conflict(message),
+            429 => KonarrError::RateLimited { retry_after: None },
+            status if status >= 500 => KonarrError::HttpError { status, message },
+            _ => KonarrError::KonarrClient(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_code() {
+        assert_eq!(KonarrError::InvalidData("x".into()).status_code(), 400);
+        assert_eq!(
+            KonarrError::InvalidSbom {
+                reason: "no components".into()
+            }
+            .status_code(),
+            400
+        );
+        assert_eq!(KonarrError::Unauthorized.status_code(), 401);
+        assert_eq!(
+            KonarrError::ProjectNotFound("web".into()).status_code(),
+            404
+        );
+        assert_eq!(KonarrError::Conflict("x".into()).status_code(), 409);
+        assert_eq!(
+            KonarrError::RateLimited { retry_after: None }.status_code(),
+            429
+        );
+        assert_eq!(KonarrError::unknown("x").status_code(), 500);
+
+        assert_eq!(KonarrError::from_status(404, "missing").status_code(), 404);
+        assert!(matches!(
+            KonarrError::from_status(503, "down"),
+            KonarrError::HttpError { status: 503, .. }
+        ));
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(KonarrError::RateLimited {
+            retry_after: Some(5)
         }
+        .is_retryable());
+        assert!(KonarrError::from_status(502, "bad gateway").is_retryable());
+        assert!(!KonarrError::from_status(400, "bad request").is_retryable());
+        assert!(
+            KonarrError::IOError(std::io::Error::from(std::io::ErrorKind::TimedOut)).is_retryable()
+        );
+        assert!(
+            !KonarrError::IOError(std::io::Error::from(std::io::ErrorKind::NotFound))
+                .is_retryable()
+        );
+        assert!(!KonarrError::InvalidData("x".into()).is_retryable());
+        assert!(!KonarrError::AuthenticationError("x".into()).is_retryable());
     }
 }
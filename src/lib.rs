@@ -8,6 +8,8 @@
 #![deny(unsafe_code)]
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "api")]
+pub mod api;
 pub mod bom;
 #[cfg(feature = "client")]
 pub mod client;
@@ -22,7 +24,7 @@ pub mod utils;
 pub mod models;
 
 pub use error::KonarrError;
-pub use utils::config::Config;
+pub use utils::config::{Config, ConfigIssue, ConfigIssueLevel, ConfigSource, ConfigValue};
 
 #[cfg(feature = "client")]
 pub use client::KonarrClient;
@@ -37,3 +39,22 @@ pub const KONARR_BANNER: &str = r#" _   __
 
 /// Konarr Version
 pub const KONARR_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Cargo features the Konarr library was built with
+pub fn build_features() -> Vec<&'static str> {
+    [
+        ("api", cfg!(feature = "api")),
+        ("models", cfg!(feature = "models")),
+        ("tasks", cfg!(feature = "tasks")),
+        ("client", cfg!(feature = "client")),
+        ("agent", cfg!(feature = "agent")),
+        ("tools", cfg!(feature = "tools")),
+        ("tools-grypedb", cfg!(feature = "tools-grypedb")),
+        ("websocket", cfg!(feature = "websocket")),
+        ("docker", cfg!(feature = "docker")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(feature, _)| feature)
+    .collect()
+}
@@ -0,0 +1,123 @@
+//! # Audit Log
+//!
+//! Record of the administrative actions (archiving, restoring, ...) done by users,
+//! the latest entries are listed in the admin API.
+
+use chrono::{DateTime, Utc};
+use geekorm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Audit Log Model
+#[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AuditLog {
+    /// Primary Key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+
+    /// Action (`project.archive`, `project.restore`, ...)
+    pub action: String,
+    /// User who did the action
+    pub actor: String,
+    /// Kind of resource the action was done on (`project`, ...)
+    pub resource: String,
+    /// ID of the resource
+    pub resource_id: i32,
+    /// Details of the action (free form)
+    pub details: Option<String>,
+
+    /// Time of the action
+    #[geekorm(new = "Utc::now()")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditLog {
+    /// Initialise the Audit Log table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::create_table(connection).await?;
+        Ok(())
+    }
+
+    /// Record an action
+    pub async fn record<'a, T>(
+        connection: &'a T,
+        action: impl Into<String>,
+        actor: impl Into<String>,
+        resource: impl Into<String>,
+        resource_id: i32,
+        details: Option<String>,
+    ) -> Result<Self, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut entry = Self::new(action.into(), actor.into(), resource.into(), resource_id);
+        entry.details = details;
+        entry.save(connection).await?;
+        Ok(entry)
+    }
+
+    /// Fetch the latest entries (most recent first), optionally of a single resource
+    pub async fn fetch_latest<'a, T>(
+        connection: &'a T,
+        resource: Option<(&str, i32)>,
+        limit: usize,
+    ) -> Result<Vec<Self>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut query = Self::query_select();
+        if let Some((resource, id)) = resource {
+            query = query
+                .where_eq("resource", resource)
+                .and()
+                .where_eq("resource_id", id);
+        }
+        Ok(Self::query(
+            connection,
+            query
+                .order_by("id", QueryOrder::Desc)
+                .limit(limit)
+                .build()?,
+        )
+        .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_audit_log() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        AuditLog::record(&connection, "project.archive", "admin", "project", 1, None).await?;
+        AuditLog::record(
+            &connection,
+            "project.restore",
+            "admin",
+            "project",
+            1,
+            Some("status=active".to_string()),
+        )
+        .await?;
+        AuditLog::record(&connection, "project.archive", "admin", "project", 2, None).await?;
+
+        let latest = AuditLog::fetch_latest(&connection, None, 10).await?;
+        assert_eq!(latest.len(), 3);
+        assert_eq!(latest[0].resource_id, 2);
+
+        let project = AuditLog::fetch_latest(&connection, Some(("project", 1)), 10).await?;
+        assert_eq!(
+            project
+                .iter()
+                .map(|e| e.action.as_str())
+                .collect::<Vec<_>>(),
+            vec!["project.restore", "project.archive"]
+        );
+        assert_eq!(project[0].details.as_deref(), Some("status=active"));
+        Ok(())
+    }
+}
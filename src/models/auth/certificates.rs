@@ -0,0 +1,143 @@
+//! # Agent Certificates
+//!
+//! Allowlist of the client certificates agents can use for mutual TLS.
+//! Certificates are identified by the SHA256 fingerprint of the DER encoded certificate.
+
+use chrono::{DateTime, Utc};
+use geekorm::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+
+/// Agent Certificates Model
+#[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AgentCertificates {
+    /// Primary Key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+
+    /// Name of the certificate (machine / host)
+    pub name: String,
+
+    /// SHA256 fingerprint of the certificate (lowercase hex, no separators)
+    #[geekorm(unique)]
+    pub fingerprint: String,
+
+    /// User who registered the certificate
+    pub created_by: String,
+
+    /// Time the certificate was registered
+    #[geekorm(new = "Utc::now()")]
+    pub created_at: DateTime<Utc>,
+    /// Last time the certificate was used
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl AgentCertificates {
+    /// Initialise the Agent Certificates table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::create_table(connection).await?;
+        Ok(())
+    }
+
+    /// SHA256 fingerprint of a DER encoded certificate
+    pub fn fingerprint(der: &[u8]) -> String {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(der);
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Normalise a fingerprint (`AB:CD:..` or `abcd..`) to lowercase hex
+    ///
+    /// Returns `None` if the value is not a SHA256 fingerprint.
+    pub fn normalize_fingerprint(fingerprint: &str) -> Option<String> {
+        let normalized: String = fingerprint
+            .trim()
+            .chars()
+            .filter(|c| *c != ':')
+            .collect::<String>()
+            .to_lowercase();
+        if normalized.len() == 64 && normalized.chars().all(|c| c.is_ascii_hexdigit()) {
+            Some(normalized)
+        } else {
+            None
+        }
+    }
+
+    /// Register a certificate fingerprint
+    pub async fn register<'a, T>(
+        connection: &'a T,
+        name: impl Into<String>,
+        fingerprint: &str,
+        created_by: impl Into<String>,
+    ) -> Result<Self, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let name = name.into();
+        if name.is_empty() {
+            return Err(crate::KonarrError::InvalidData(
+                "Agent certificate name can not be empty".to_string(),
+            ));
+        }
+        let fingerprint = Self::normalize_fingerprint(fingerprint).ok_or_else(|| {
+            crate::KonarrError::InvalidData(format!(
+                "Invalid SHA256 certificate fingerprint `{}`",
+                fingerprint
+            ))
+        })?;
+
+        let mut certificate = Self::new(name, fingerprint, created_by.into());
+        certificate.save(connection).await?;
+        Ok(certificate)
+    }
+
+    /// Record that the certificate was used
+    pub async fn touch<'a, T>(&mut self, connection: &'a T) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        self.last_used_at = Some(Utc::now());
+        self.update(connection).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_agent_certificates() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let fingerprint = AgentCertificates::fingerprint(b"certificate");
+        let colons = fingerprint
+            .as_bytes()
+            .chunks(2)
+            .map(|c| String::from_utf8_lossy(c).to_uppercase())
+            .collect::<Vec<_>>()
+            .join(":");
+        assert_eq!(
+            AgentCertificates::normalize_fingerprint(&colons),
+            Some(fingerprint.clone())
+        );
+        assert_eq!(AgentCertificates::normalize_fingerprint("abcd"), None);
+
+        let certificate =
+            AgentCertificates::register(&connection, "host-1", &colons, "admin").await?;
+        assert_eq!(certificate.fingerprint, fingerprint);
+
+        let found = AgentCertificates::fetch_by_fingerprint(&connection, fingerprint).await?;
+        assert_eq!(found.name, "host-1");
+
+        assert!(
+            AgentCertificates::register(&connection, "host-2", "zz", "admin")
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
 //! # Authentification module
+pub mod certificates;
 pub mod sessions;
+pub mod tokens;
 pub mod users;
@@ -3,7 +3,15 @@
 use geekorm::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::utils::config::SessionsRoleConfig;
+use super::users::{UserRole, Users};
+use crate::{
+    models::{raw_query, ServerSettings, Setting},
+    utils::config::{SessionsConfig, SessionsRoleConfig},
+    KonarrError,
+};
+
+/// Hours after a session expired before it is cleaned up
+pub const SESSIONS_CLEANUP_GRACE: i64 = 24;
 
 /// User Session Model
 #[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
@@ -29,6 +37,10 @@ pub struct Sessions {
     /// Last valid access of the account
     #[geekorm(new = "chrono::Utc::now()")]
     pub last_accessed: chrono::DateTime<chrono::Utc>,
+
+    /// User of an application session (CLI), the browser session of a user is linked
+    /// from `Users.sessions` instead
+    pub user_id: Option<i32>,
 }
 
 impl Sessions {
@@ -41,6 +53,127 @@ impl Sessions {
 
         self.state == SessionState::Active && deltaresult < now
     }
+
+    /// Invalidate the oldest active sessions of a user over the `sessions.max_per_user`
+    /// setting (`0` disables the limit), returns the invalidated sessions
+    ///
+    /// The sessions of a user are its browser session (`Users.sessions`) and its
+    /// application sessions, the `current` session (just logged in) is always kept.
+    pub async fn limit_user<'a, T>(
+        connection: &'a T,
+        user: &Users,
+        current: &Sessions,
+    ) -> Result<Vec<Sessions>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let max: i64 = ServerSettings::get(connection, Setting::SessionsMaxPerUser)
+            .await?
+            .value
+            .parse()
+            .unwrap_or_default();
+        if max <= 0 {
+            return Ok(vec![]);
+        }
+
+        let mut values = Values::new();
+        values.push("state".to_string(), SessionState::Inactive);
+        values.push("active".to_string(), SessionState::Active);
+        values.push("current".to_string(), current.id);
+        values.push("user".to_string(), user.id);
+        values.push("browser".to_string(), user.sessions.key);
+        values.push("offset".to_string(), max - 1);
+        let invalidated = T::query::<Sessions>(
+            connection,
+            raw_query(
+                "UPDATE Sessions SET state = ? WHERE id IN (SELECT id FROM Sessions \
+                WHERE state = ? AND id != ? AND (user_id = ? OR id = ?) \
+                ORDER BY created_at DESC LIMIT -1 OFFSET ?) RETURNING *;",
+                values,
+            ),
+        )
+        .await?;
+        if !invalidated.is_empty() {
+            log::info!(
+                "Invalidated {} session(s) over the limit of {} - User({})",
+                invalidated.len(),
+                max,
+                user.id
+            );
+        }
+        Ok(invalidated)
+    }
+
+    /// Cleanup the expired sessions (returns the number of sessions cleaned up)
+    ///
+    /// Browser sessions (the token is regenerated on login) which expired more than
+    /// [SESSIONS_CLEANUP_GRACE] hours ago (based on the role of the user) are made
+    /// inactive. Application sessions are not reused, so the expired and inactive ones
+    /// are removed along with the sessions which are no longer linked to a user.
+    pub async fn cleanup<'a, T>(
+        connection: &'a T,
+        config: &SessionsConfig,
+    ) -> Result<u64, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let now = chrono::Utc::now();
+        let mut total = 0;
+
+        for (role, role_config) in [
+            (UserRole::Admin, &config.admins),
+            (UserRole::User, &config.users),
+            (UserRole::Agent, &config.agents),
+        ] {
+            let before = now
+                - chrono::TimeDelta::hours(role_config.expires.into())
+                - chrono::TimeDelta::hours(SESSIONS_CLEANUP_GRACE);
+
+            let mut values = Values::new();
+            values.push("state".to_string(), SessionState::Inactive);
+            values.push("active".to_string(), SessionState::Active);
+            values.push("before".to_string(), before);
+            values.push("role".to_string(), role.clone());
+            let expired = T::query::<Sessions>(
+                connection,
+                raw_query(
+                    "UPDATE Sessions SET state = ? WHERE state = ? AND last_accessed < ? \
+                    AND id IN (SELECT sessions FROM Users WHERE role = ?) RETURNING *;",
+                    values,
+                ),
+            )
+            .await?;
+            total += expired.len() as u64;
+
+            let mut values = Values::new();
+            values.push("role".to_string(), role);
+            values.push("state".to_string(), SessionState::Inactive);
+            values.push("before".to_string(), before);
+            let applications = T::query::<Sessions>(
+                connection,
+                raw_query(
+                    "DELETE FROM Sessions WHERE user_id IN (SELECT id FROM Users WHERE role = ?) \
+                    AND (state = ? OR last_accessed < ?) RETURNING *;",
+                    values,
+                ),
+            )
+            .await?;
+            total += applications.len() as u64;
+        }
+
+        let orphaned = T::query::<Sessions>(
+            connection,
+            raw_query(
+                "DELETE FROM Sessions WHERE id NOT IN (SELECT sessions FROM Users) \
+                AND (user_id IS NULL OR user_id NOT IN (SELECT id FROM Users)) RETURNING *;",
+                Values::new(),
+            ),
+        )
+        .await?;
+        total += orphaned.len() as u64;
+
+        Ok(total)
+    }
 }
 
 /// Session State
@@ -63,6 +196,122 @@ pub enum SessionType {
     Application,
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Users;
+
+    #[tokio::test]
+    async fn test_sessions_cleanup() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+        let config = SessionsConfig::default();
+
+        let mut expired = Sessions::new(SessionType::User, SessionState::Active);
+        expired.last_accessed = chrono::Utc::now() - chrono::TimeDelta::hours(72);
+        expired.save(&connection).await?;
+        Users::new("expired", "password", UserRole::User, expired.id)
+            .save(&connection)
+            .await?;
+
+        // Agents have a longer expiry
+        let mut agent = Sessions::new(SessionType::User, SessionState::Active);
+        agent.last_accessed = chrono::Utc::now() - chrono::TimeDelta::hours(72);
+        agent.save(&connection).await?;
+        Users::new("agent", "password", UserRole::Agent, agent.id)
+            .save(&connection)
+            .await?;
+
+        let mut orphaned = Sessions::new(SessionType::User, SessionState::Active);
+        orphaned.save(&connection).await?;
+
+        // Application sessions (CLI) of the user
+        let user = Users::fetch_by_username(&connection, "expired").await?;
+        let mut sessions = vec![];
+        for (state, age) in [
+            (SessionState::Active, 0),
+            (SessionState::Active, 72),
+            (SessionState::Inactive, 0),
+        ] {
+            let mut session = Sessions::new(SessionType::Application, state);
+            session.user_id = Some(user.id.into());
+            session.last_accessed = chrono::Utc::now() - chrono::TimeDelta::hours(age);
+            session.save(&connection).await?;
+            sessions.push(session);
+        }
+
+        assert_eq!(Sessions::cleanup(&connection, &config).await?, 4);
+        assert_eq!(
+            Sessions::fetch_by_primary_key(&connection, expired.id)
+                .await?
+                .state,
+            SessionState::Inactive
+        );
+        assert_eq!(
+            Sessions::fetch_by_primary_key(&connection, agent.id)
+                .await?
+                .state,
+            SessionState::Active
+        );
+        assert!(Sessions::fetch_by_primary_key(&connection, orphaned.id)
+            .await
+            .is_err());
+        assert!(Sessions::fetch_by_primary_key(&connection, sessions[0].id)
+            .await
+            .is_ok());
+        for session in sessions.iter().skip(1) {
+            assert!(Sessions::fetch_by_primary_key(&connection, session.id)
+                .await
+                .is_err());
+        }
+
+        // Nothing left to cleanup
+        assert_eq!(Sessions::cleanup(&connection, &config).await?, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sessions_limit_user() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+        Users::create(&connection, "user", "password").await?;
+
+        let (user, browser) = Users::login(&connection, "user", "password").await?;
+        let (_, first) = Users::login_application(&connection, "user", "password").await?;
+        let (_, second) = Users::login_application(&connection, "user", "password").await?;
+
+        // No limit by default
+        assert!(Sessions::limit_user(&connection, &user, &second)
+            .await?
+            .is_empty());
+
+        ServerSettings::get(&connection, Setting::SessionsMaxPerUser)
+            .await?
+            .set_update(&connection, "2")
+            .await?;
+        let invalidated = Sessions::limit_user(&connection, &user, &second).await?;
+        assert_eq!(invalidated.len(), 1);
+        assert_eq!(invalidated[0].id, browser.id);
+
+        for (session, state) in [
+            (&browser, SessionState::Inactive),
+            (&first, SessionState::Active),
+            (&second, SessionState::Active),
+        ] {
+            assert_eq!(
+                Sessions::fetch_by_primary_key(&connection, session.id)
+                    .await?
+                    .state,
+                state
+            );
+        }
+
+        // Under the limit
+        assert!(Sessions::limit_user(&connection, &user, &second)
+            .await?
+            .is_empty());
+        Ok(())
+    }
+}
+
 // impl From<&str> for SessionType {
 //     fn from(session_type: &str) -> Self {
 //         match session_type {
@@ -0,0 +1,251 @@
+//! # Agent Tokens
+//!
+//! Scoped (per machine) agent tokens which can expire and be revoked individually.
+//! Only the SHA256 hash of the token is stored, the token is returned once on creation.
+//!
+//! A token can be restricted to a project subtree (`project_scope`), the ancestry of the
+//! target project is checked on every request so re-parented projects are handled.
+
+use chrono::{DateTime, Utc};
+use geekorm::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+
+use crate::{models::Projects, utils::rand::generate_random_string};
+
+/// Prefix for scoped agent tokens
+pub const AGENT_TOKEN_PREFIX: &str = "konarr-agent-";
+
+/// Agent Token State
+#[derive(Data, Debug, Default, Clone, PartialEq, Eq)]
+pub enum AgentTokenState {
+    /// Token can be used
+    #[default]
+    Active,
+    /// Token was revoked
+    Revoked,
+}
+
+/// Agent Tokens Model
+#[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AgentTokens {
+    /// Primary Key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+
+    /// Name of the token (machine / host)
+    #[geekorm(unique)]
+    pub name: String,
+
+    /// SHA256 hash of the token
+    #[geekorm(unique)]
+    pub token_hash: String,
+
+    /// Token State
+    pub state: AgentTokenState,
+
+    /// Expiry of the token (never expires if not set)
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// Time the token was created
+    #[geekorm(new = "Utc::now()")]
+    pub created_at: DateTime<Utc>,
+    /// Time the token was revoked
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// Last time the token was used
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// Konarr version of the agent using the token (`User-Agent`)
+    pub agent_version: Option<String>,
+    /// Project whose subtree the token is restricted to (every project if not set)
+    pub project_scope: Option<i32>,
+}
+
+impl AgentTokens {
+    /// Initialise the Agent Tokens table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::create_table(connection).await?;
+        Ok(())
+    }
+
+    /// Create (mint) a new agent token
+    ///
+    /// Returns the model and the plain text token (which is not stored).
+    pub async fn create<'a, T>(
+        connection: &'a T,
+        name: impl Into<String>,
+        expires_at: Option<DateTime<Utc>>,
+        project_scope: Option<i32>,
+    ) -> Result<(Self, String), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let name = name.into();
+        if name.is_empty() {
+            return Err(crate::KonarrError::InvalidData(
+                "Agent token name can not be empty".to_string(),
+            ));
+        }
+        if let Some(scope) = project_scope {
+            if Projects::fetch_by_primary_key(connection, scope)
+                .await
+                .is_err()
+            {
+                return Err(crate::KonarrError::InvalidData(format!(
+                    "Project scope `{}` does not exist",
+                    scope
+                )));
+            }
+        }
+        let token = format!("{}{}", AGENT_TOKEN_PREFIX, generate_random_string(42));
+
+        let mut agent_token = AgentTokens {
+            name,
+            token_hash: Self::hash(&token),
+            expires_at,
+            project_scope,
+            created_at: Utc::now(),
+            ..Default::default()
+        };
+        agent_token.save(connection).await?;
+        Ok((agent_token, token))
+    }
+
+    /// Hash a token
+    pub fn hash(token: &str) -> String {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(token.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Find a token by its plain text value
+    pub async fn fetch_by_token<'a, T>(
+        connection: &'a T,
+        token: &str,
+    ) -> Result<Self, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Self::fetch_by_token_hash(connection, Self::hash(token)).await?)
+    }
+
+    /// Check if the token is active and not expired
+    pub fn is_valid(&self) -> bool {
+        self.state == AgentTokenState::Active
+            && self.expires_at.map(|e| e > Utc::now()).unwrap_or(true)
+    }
+
+    /// Check if the token can access the project (the project is in the scope subtree)
+    pub async fn allows<'a, T>(
+        &self,
+        connection: &'a T,
+        project: &Projects,
+    ) -> Result<bool, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        match self.project_scope {
+            Some(scope) => project.is_within(connection, scope).await,
+            None => Ok(true),
+        }
+    }
+
+    /// Revoke the token
+    pub async fn revoke<'a, T>(&mut self, connection: &'a T) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        self.state = AgentTokenState::Revoked;
+        self.revoked_at = Some(Utc::now());
+        self.update(connection).await?;
+        Ok(())
+    }
+
+    /// Record that the token was used (and the agent version if reported)
+    pub async fn touch<'a, T>(
+        &mut self,
+        connection: &'a T,
+        agent_version: Option<String>,
+    ) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        self.last_used_at = Some(Utc::now());
+        if agent_version.is_some() {
+            self.agent_version = agent_version;
+        }
+        self.update(connection).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_agent_tokens() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let (token, plain) = AgentTokens::create(&connection, "homelab-01", None, None).await?;
+        assert!(plain.starts_with(AGENT_TOKEN_PREFIX));
+        assert_ne!(token.token_hash, plain);
+
+        let mut found = AgentTokens::fetch_by_token(&connection, &plain).await?;
+        assert_eq!(found.id, token.id);
+        assert!(found.is_valid());
+        assert!(
+            AgentTokens::fetch_by_token(&connection, "konarr-agent-invalid")
+                .await
+                .is_err()
+        );
+
+        found.revoke(&connection).await?;
+        let found = AgentTokens::fetch_by_token(&connection, &plain).await?;
+        assert_eq!(found.state, AgentTokenState::Revoked);
+        assert!(!found.is_valid());
+
+        let (expired, _) = AgentTokens::create(
+            &connection,
+            "homelab-02",
+            Some(Utc::now() - chrono::TimeDelta::hours(1)),
+            None,
+        )
+        .await?;
+        assert!(!expired.is_valid());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_agent_token_scope() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut group = Projects::new("ci", crate::models::ProjectType::Group);
+        group.save(&connection).await?;
+        let mut other = Projects::new("other", crate::models::ProjectType::Group);
+        other.save(&connection).await?;
+        let mut web = Projects::new("ci/web", crate::models::ProjectType::Container);
+        web.parent = group.id.into();
+        web.save(&connection).await?;
+
+        assert!(AgentTokens::create(&connection, "ci-bad", None, Some(999))
+            .await
+            .is_err());
+        let (token, _) =
+            AgentTokens::create(&connection, "ci", None, Some(group.id.into())).await?;
+        assert!(token.allows(&connection, &group).await?);
+        assert!(token.allows(&connection, &web).await?);
+        assert!(!token.allows(&connection, &other).await?);
+
+        // Ancestry is checked at request time
+        web.transfer(&connection, &other, false, "admin").await?;
+        assert!(!token.allows(&connection, &web).await?);
+
+        let (unscoped, _) = AgentTokens::create(&connection, "homelab", None, None).await?;
+        assert!(unscoped.allows(&connection, &other).await?);
+        Ok(())
+    }
+}
@@ -89,17 +89,74 @@ impl Users {
     }
 
     /// User Login function
+    ///
+    /// Regenerates the token of the browser session of the user.
     pub async fn login<'a, T>(
         connection: &'a T,
         username: impl Into<String>,
         password: impl Into<String>,
     ) -> Result<(Self, Sessions), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut user = Self::authenticate(connection, username, password).await?;
+        log::info!("Logging in user: {:?}", user.id);
+        let login_time = chrono::Utc::now();
+
+        let mut session = user.fetch_sessions(connection).await?;
+        session.state = SessionState::Active;
+        session.regenerate_token();
+        session.created_at = login_time.clone();
+        session.last_accessed = login_time.clone();
+        session.update(connection).await?;
+        user.sessions.data = session.clone();
+
+        log::info!("Created new session for user");
+        user.last_login = login_time;
+        user.update(connection).await?;
+
+        Ok((user, session))
+    }
+
+    /// Application (non-browser client) Login function
+    ///
+    /// Creates a new application session for the user (used by the CLI), which is not
+    /// changed by the browser login and is revoked on its own.
+    pub async fn login_application<'a, T>(
+        connection: &'a T,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Result<(Self, Sessions), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut user = Self::authenticate(connection, username, password).await?;
+        log::info!("Logging in user (application): {:?}", user.id);
+
+        user.last_login = chrono::Utc::now();
+        user.update(connection).await?;
+
+        let mut session = Sessions::new(SessionType::Application, SessionState::Active);
+        session.user_id = Some(user.id.into());
+        session.save(connection).await?;
+        user.sessions.data = session.clone();
+
+        log::info!("Created new application session for user");
+        Ok((user, session))
+    }
+
+    /// Check the credentials of the user
+    async fn authenticate<'a, T>(
+        connection: &'a T,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Result<Self, KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
         let username = username.into();
         let password = password.into();
-        let mut user = match Users::fetch_by_username(connection, username).await {
+        let user = match Users::fetch_by_username(connection, username).await {
             Ok(u) => u,
             Err(e) => {
                 log::warn!("Failed to login due to error: {}", e);
@@ -114,28 +171,15 @@ impl Users {
         }
 
         if !user.check_password(password)? {
-            Err(KonarrError::AuthenticationError(
+            return Err(KonarrError::AuthenticationError(
                 "Invalid credentials".to_string(),
-            ))
-        } else {
-            log::info!("Logging in user: {:?}", user.id);
-            let login_time = chrono::Utc::now();
-
-            let mut session = user.fetch_sessions(connection).await?;
-            session.state = SessionState::Active;
-            session.regenerate_token();
-            session.last_accessed = login_time.clone();
-            session.update(connection).await?;
-
-            log::info!("Created new session for user");
-            user.last_login = login_time;
-            user.update(connection).await?;
-
-            Ok((user, session))
+            ));
         }
+        Ok(user)
     }
 
-    /// Revoke the current session of the user
+    /// Revoke the current session of the user (the browser or application session the
+    /// user was authenticated with)
     pub async fn logout<'a, T>(&mut self, connection: &'a T) -> Result<(), geekorm::Error>
     where
         T: GeekConnection<Connection = T> + 'a,
@@ -0,0 +1,251 @@
+//! # Component Annotations
+//!
+//! Notes left by users on a component ("vendored, upstream fix tracked in JIRA-123"),
+//! globally or for a single project. The text is stored as is (rendering the markdown is
+//! up to the frontend) but its length is limited.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use geekorm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Component;
+use crate::{models::raw_query, KonarrError};
+
+/// Maximum length (characters) of an annotation
+pub const COMPONENT_ANNOTATION_MAX_LENGTH: usize = 4096;
+
+#[derive(Debug, Deserialize)]
+struct AnnotationCountRow {
+    component_id: i32,
+    count: i64,
+}
+
+/// Component annotations table
+#[derive(Table, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ComponentAnnotations {
+    /// Primary key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+
+    /// Component the annotation is for
+    pub component_id: i32,
+    /// Project the annotation applies to (all projects if not set)
+    pub project_id: Option<i32>,
+
+    /// User who wrote the annotation
+    pub author: String,
+    /// Text of the annotation (raw markdown)
+    pub text: String,
+
+    /// Created at
+    #[geekorm(new = "Utc::now()")]
+    pub created_at: DateTime<Utc>,
+    /// Updated at
+    #[geekorm(new = "Utc::now()", on_update = "Utc::now()")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ComponentAnnotations {
+    /// Initialise the Component Annotations table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::create_table(connection).await?;
+        Ok(())
+    }
+
+    /// Validate the text of an annotation (not empty and at most
+    /// [COMPONENT_ANNOTATION_MAX_LENGTH] characters)
+    pub fn validate(text: impl Into<String>) -> Result<String, KonarrError> {
+        let text = text.into().trim().to_string();
+        if text.is_empty() {
+            return Err(KonarrError::InvalidData(
+                "Annotation text can not be empty".to_string(),
+            ));
+        }
+        let length = text.chars().count();
+        if length > COMPONENT_ANNOTATION_MAX_LENGTH {
+            return Err(KonarrError::InvalidData(format!(
+                "Annotation text is too long ({} > {} characters)",
+                length, COMPONENT_ANNOTATION_MAX_LENGTH
+            )));
+        }
+        Ok(text)
+    }
+
+    /// Create an annotation for a component
+    pub async fn create<'a, T>(
+        connection: &'a T,
+        component_id: i32,
+        project_id: Option<i32>,
+        author: impl Into<String>,
+        text: impl Into<String>,
+    ) -> Result<Self, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let text = Self::validate(text)?;
+        Component::fetch_by_primary_key(connection, component_id).await?;
+        if let Some(project_id) = project_id {
+            crate::models::Projects::fetch_by_primary_key(connection, project_id).await?;
+        }
+
+        let mut annotation = Self::new(component_id, author, text);
+        annotation.project_id = project_id;
+        annotation.save(connection).await?;
+        Ok(annotation)
+    }
+
+    /// Update the text of the annotation
+    pub async fn set_text<'a, T>(
+        &mut self,
+        connection: &'a T,
+        text: impl Into<String>,
+    ) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        self.text = Self::validate(text)?;
+        self.update(connection).await?;
+        Ok(())
+    }
+
+    /// Check if the user can edit or delete the annotation (the author or an admin)
+    pub fn can_modify(&self, username: &str, admin: bool) -> bool {
+        admin || self.author == username
+    }
+
+    /// Fetch the annotations of a component (oldest first)
+    ///
+    /// When a project is provided only the global annotations and the annotations of the
+    /// project are returned.
+    pub async fn fetch_for_component<'a, T>(
+        connection: &'a T,
+        component_id: i32,
+        project_id: Option<i32>,
+    ) -> Result<Vec<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let annotations = Self::query(
+            connection,
+            Self::query_select()
+                .where_eq("component_id", component_id)
+                .order_by("id", QueryOrder::Asc)
+                .build()?,
+        )
+        .await?;
+        Ok(annotations
+            .into_iter()
+            .filter(|a| {
+                project_id.is_none() || a.project_id.is_none() || a.project_id == project_id
+            })
+            .collect())
+    }
+
+    /// Number of annotations of each component (keyed by component ID)
+    pub async fn counts<'a, T>(connection: &'a T) -> Result<HashMap<i32, i64>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let rows = T::query::<AnnotationCountRow>(
+            connection,
+            raw_query(
+                "SELECT component_id, COUNT(*) AS count FROM ComponentAnnotations \
+                GROUP BY component_id;",
+                Values::new(),
+            ),
+        )
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.component_id, row.count))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ProjectType, Projects};
+
+    #[tokio::test]
+    async fn test_annotations() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let (mut component, _) = Component::from_purl("pkg:deb/debian/openssl@3.0.1")?;
+        component.find_or_create(&connection).await?;
+        let component_id: i32 = component.id.into();
+        let mut project = Projects::new("myhost/app", ProjectType::Container);
+        project.save(&connection).await?;
+        let project_id: i32 = project.id.into();
+
+        let global = ComponentAnnotations::create(
+            &connection,
+            component_id,
+            None,
+            "alice",
+            "  This is **vendored**, upstream fix tracked in JIRA-123\n",
+        )
+        .await?;
+        assert_eq!(
+            global.text,
+            "This is **vendored**, upstream fix tracked in JIRA-123"
+        );
+        ComponentAnnotations::create(
+            &connection,
+            component_id,
+            Some(project_id),
+            "bob",
+            "Only in <app>",
+        )
+        .await?;
+
+        // Validation
+        assert!(
+            ComponentAnnotations::create(&connection, component_id, None, "alice", "  ")
+                .await
+                .is_err()
+        );
+        let long = "a".repeat(COMPONENT_ANNOTATION_MAX_LENGTH + 1);
+        assert!(
+            ComponentAnnotations::create(&connection, component_id, None, "alice", long)
+                .await
+                .is_err()
+        );
+        assert!(
+            ComponentAnnotations::create(&connection, 1000, None, "alice", "Missing")
+                .await
+                .is_err()
+        );
+
+        let all =
+            ComponentAnnotations::fetch_for_component(&connection, component_id, None).await?;
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[1].text, "Only in <app>");
+        let other =
+            ComponentAnnotations::fetch_for_component(&connection, component_id, Some(1000))
+                .await?;
+        assert_eq!(other.len(), 1);
+        assert_eq!(
+            ComponentAnnotations::counts(&connection)
+                .await?
+                .get(&component_id),
+            Some(&2)
+        );
+
+        // Only the author (or an admin) can modify the annotation
+        assert!(global.can_modify("alice", false));
+        assert!(!global.can_modify("bob", false));
+        assert!(global.can_modify("bob", true));
+
+        let mut global = global;
+        global.set_text(&connection, "Fixed upstream").await?;
+        let global = ComponentAnnotations::fetch_by_primary_key(&connection, global.id).await?;
+        assert_eq!(global.text, "Fixed upstream");
+        Ok(())
+    }
+}
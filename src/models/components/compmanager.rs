@@ -2,6 +2,25 @@
 
 use geekorm::Data;
 
+use crate::models::Setting;
+
+/// All the package managers
+pub const COMPONENT_MANAGERS: [ComponentManager; 13] = [
+    ComponentManager::Apk,
+    ComponentManager::Cargo,
+    ComponentManager::Composer,
+    ComponentManager::Deb,
+    ComponentManager::Gem,
+    ComponentManager::Generic,
+    ComponentManager::Npm,
+    ComponentManager::Golang,
+    ComponentManager::Maven,
+    ComponentManager::PyPi,
+    ComponentManager::Nuget,
+    ComponentManager::Rpm,
+    ComponentManager::Unknown,
+];
+
 /// Dependency Manager Enum
 ///
 /// https://github.com/package-url/purl-spec/blob/master/PURL-TYPES.rst
@@ -48,3 +67,104 @@ pub enum ComponentManager {
     #[default]
     Unknown,
 }
+
+impl ComponentManager {
+    /// Ecosystem of the package manager (OS or language packages)
+    pub fn ecosystem(&self) -> ComponentEcosystem {
+        match self {
+            ComponentManager::Apk | ComponentManager::Deb | ComponentManager::Rpm => {
+                ComponentEcosystem::Os
+            }
+            ComponentManager::Cargo
+            | ComponentManager::Composer
+            | ComponentManager::Gem
+            | ComponentManager::Npm
+            | ComponentManager::Golang
+            | ComponentManager::Maven
+            | ComponentManager::PyPi
+            | ComponentManager::Nuget => ComponentEcosystem::Language,
+            ComponentManager::Generic | ComponentManager::Unknown => ComponentEcosystem::Other,
+        }
+    }
+
+    /// Statistic of the number of dependencies using the package manager
+    pub fn statistic(&self) -> Setting {
+        match self {
+            ComponentManager::Apk => Setting::StatsManagerApk,
+            ComponentManager::Cargo => Setting::StatsManagerCargo,
+            ComponentManager::Composer => Setting::StatsManagerComposer,
+            ComponentManager::Deb => Setting::StatsManagerDeb,
+            ComponentManager::Gem => Setting::StatsManagerGem,
+            ComponentManager::Generic => Setting::StatsManagerGeneric,
+            ComponentManager::Npm => Setting::StatsManagerNpm,
+            ComponentManager::Golang => Setting::StatsManagerGolang,
+            ComponentManager::Maven => Setting::StatsManagerMaven,
+            ComponentManager::PyPi => Setting::StatsManagerPyPi,
+            ComponentManager::Nuget => Setting::StatsManagerNuget,
+            ComponentManager::Rpm => Setting::StatsManagerRpm,
+            ComponentManager::Unknown => Setting::StatsManagerUnknown,
+        }
+    }
+}
+
+/// Component Ecosystem Enum
+///
+/// Groups the package managers into OS packages (apk, deb, rpm) and application
+/// level language packages (npm, cargo, pypi, ...).
+#[derive(Data, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[geekorm(from_string = "lowercase", to_string = "lowercase")]
+pub enum ComponentEcosystem {
+    /// Operating System packages
+    #[geekorm(aliases = "os,system")]
+    Os,
+    /// Language / application packages
+    #[geekorm(aliases = "language,lang,application")]
+    Language,
+    /// Other (generic or unknown) packages
+    #[default]
+    #[geekorm(aliases = "other")]
+    Other,
+}
+
+impl ComponentEcosystem {
+    /// Package managers in the ecosystem
+    pub fn managers(&self) -> Vec<ComponentManager> {
+        COMPONENT_MANAGERS
+            .into_iter()
+            .filter(|manager| manager.ecosystem() == *self)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ecosystem() {
+        assert_eq!(ComponentManager::Deb.ecosystem(), ComponentEcosystem::Os);
+        assert_eq!(
+            ComponentManager::Npm.ecosystem(),
+            ComponentEcosystem::Language
+        );
+        assert_eq!(
+            ComponentManager::Unknown.ecosystem(),
+            ComponentEcosystem::Other
+        );
+
+        assert_eq!(ComponentEcosystem::from("OS"), ComponentEcosystem::Os);
+        assert_eq!(
+            ComponentEcosystem::from("language"),
+            ComponentEcosystem::Language
+        );
+        assert_eq!(ComponentEcosystem::Os.to_string(), "os");
+        assert_eq!(
+            ComponentEcosystem::Os.managers(),
+            vec![
+                ComponentManager::Apk,
+                ComponentManager::Deb,
+                ComponentManager::Rpm
+            ]
+        );
+    }
+}
@@ -4,10 +4,33 @@ use geekorm::prelude::*;
 use log::{debug, info};
 use purl::GenericPurl;
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use super::{ComponentManager, ComponentType, ComponentVersion};
-use crate::{tasks, utils::catalogue::Catalogue};
+use crate::{models::raw_query, tasks, utils::catalogue::Catalogue};
+
+/// Exact (case insensitive) name matches, uses the `ComponentNameIndex`
+const AUTOCOMPLETE_EXACT: &str = "SELECT * FROM Component WHERE name = ? COLLATE NOCASE";
+/// Name prefix matches, the NOCASE ordering lets the `ComponentNameIndex` also sort the rows
+const AUTOCOMPLETE_PREFIX: &str =
+    "SELECT * FROM Component WHERE name LIKE ? ESCAPE '\\' ORDER BY name COLLATE NOCASE ASC";
+/// Name substring matches (not indexed)
+const AUTOCOMPLETE_SUBSTRING: &str =
+    "SELECT * FROM Component WHERE name LIKE ? ESCAPE '\\' ORDER BY name ASC";
+
+/// Escape the `LIKE` wildcards (`%`, `_`) and the escape character (`\`) of user input
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[derive(Debug, Deserialize)]
+struct ComponentProjectsRow {
+    component_id: i32,
+    count: i64,
+}
 
 /// Component Model
 #[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
@@ -25,6 +48,44 @@ pub struct Component {
     pub namespace: Option<String>,
     /// Package Name
     pub name: String,
+    /// Source / origin package name (deb source, apk origin) if it differs from the name
+    pub origin: Option<String>,
+}
+
+/// SQL expression of the full coordinate of a Component (`namespace/name`)
+pub(crate) const COMPONENT_COORDINATE: &str = "(COALESCE(namespace || '/', '') || name)";
+
+/// Parse a Package URL
+///
+/// Scoped npm packages (`pkg:npm/@scope/name`) are accepted without the `@` of the
+/// scope being URL-encoded (`%40scope`), as the namespace would otherwise be read as
+/// the version.
+pub(crate) fn parse_purl(value: &str) -> Result<GenericPurl<String>, crate::KonarrError> {
+    let (body, suffix) = match value.find(['?', '#']) {
+        Some(index) => value.split_at(index),
+        None => (value, ""),
+    };
+    let segments: Vec<&str> = body.split('/').collect();
+    let last = segments.len().saturating_sub(1);
+    let body = segments
+        .iter()
+        .enumerate()
+        .map(|(index, segment)| match segment.strip_prefix('@') {
+            Some(scope) if index > 0 && index < last => format!("%40{}", scope),
+            _ => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    GenericPurl::<String>::from_str(format!("{}{}", body, suffix).as_str())
+        .map_err(|e| crate::KonarrError::InvalidData(e.to_string()))
+}
+
+/// Grouped count of Components
+#[derive(Debug, Clone, Deserialize)]
+struct ComponentCount {
+    name: String,
+    count: i64,
 }
 
 impl Component {
@@ -39,6 +100,21 @@ impl Component {
         debug!("Creating and Initialising Component Table");
         Component::create_table(connection).await?;
 
+        debug!("Creating Component name index");
+        Component::execute(
+            connection,
+            Query::new(
+                QueryType::Create,
+                "CREATE INDEX IF NOT EXISTS ComponentNameIndex ON Component (name COLLATE NOCASE);"
+                    .to_string(),
+                Values::new(),
+                Values::new(),
+                vec![],
+                Component::table(),
+            ),
+        )
+        .await?;
+
         let purls = vec!["pkg:deb/debian", "pkg:apk/alpine"];
         for purl in purls.iter() {
             let (mut comp, _version) = Component::from_purl(purl.to_string()).unwrap();
@@ -63,12 +139,53 @@ impl Component {
         purl
     }
 
+    /// Display name of the Component (full coordinate)
+    ///
+    /// Maven uses `group:artifact`, other managers use `namespace/name`
+    /// (`@scope/name`, `github.com/org/module`).
+    pub fn display_name(&self) -> String {
+        match &self.namespace {
+            Some(namespace) if !namespace.is_empty() => match self.manager {
+                ComponentManager::Maven => format!("{}:{}", namespace, self.name),
+                _ => format!("{}/{}", namespace, self.name),
+            },
+            _ => self.name.clone(),
+        }
+    }
+
+    /// WHERE clause to search Components by name, namespace or full coordinate
+    ///
+    /// Maven coordinates (`group:artifact`) are matched the same as `group/artifact`.
+    pub(crate) fn search_filter(term: &str, values: &mut Values) -> String {
+        let like = format!("%{}%", term);
+        values.push("name".to_string(), like.clone());
+        values.push("namespace".to_string(), like);
+        values.push(
+            "coordinate".to_string(),
+            format!("%{}%", term.replace(':', "/")),
+        );
+        format!(
+            "(name LIKE ? OR namespace LIKE ? OR {} LIKE ?)",
+            COMPONENT_COORDINATE
+        )
+    }
+
+    /// ORDER BY clause of a search (exact matches, then prefix matches, then by name)
+    pub(crate) fn search_order(term: &str, values: &mut Values) -> String {
+        values.push("order_name".to_string(), term.to_string());
+        values.push("order_coordinate".to_string(), term.replace(':', "/"));
+        values.push("order_prefix".to_string(), format!("{}%", term));
+        format!(
+            "CASE WHEN name = ? OR {} = ? THEN 0 WHEN name LIKE ? THEN 1 ELSE 2 END, name ASC",
+            COMPONENT_COORDINATE
+        )
+    }
+
     /// Create Component from Package URL
     pub fn from_purl(
         value: impl Into<String>,
     ) -> Result<(Self, ComponentVersion), crate::KonarrError> {
-        let purl = GenericPurl::<String>::from_str(value.into().as_str())
-            .map_err(|e| crate::KonarrError::UnknownError(e.to_string()))?;
+        let purl = parse_purl(value.into().as_str())?;
 
         let mut component = Component::new(purl.package_type(), purl.name().to_string());
         Catalogue::catalogue_old(&mut component)?;
@@ -78,7 +195,7 @@ impl Component {
         }
 
         let version: ComponentVersion = if let Some(version) = purl.version() {
-            let v = version.replace('v', "");
+            let v = ComponentVersion::normalize(version, &component.manager);
             ComponentVersion::new(component.id, v)
         } else {
             ComponentVersion::new(component.id, "0.0.0".to_string())
@@ -108,14 +225,32 @@ impl Component {
         let select_final = select.build()?;
 
         match Component::query_first(connection, select_final).await {
-            Ok(dep) => {
+            Ok(mut dep) => {
                 self.id = dep.id;
+                // Origin from a newer SBOM
+                if self.origin.is_some() && dep.origin != self.origin {
+                    dep.origin = self.origin.clone();
+                    dep.update(connection).await?;
+                } else {
+                    self.origin = dep.origin;
+                }
                 Ok(())
             }
             Err(_) => self.save(connection).await.map_err(|e| e.into()),
         }
     }
 
+    /// Names the advisories of the component can be published against
+    ///
+    /// The package name then the source / origin package (`libssl3` then `openssl`).
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = vec![self.name.as_str()];
+        if let Some(origin) = self.origin.as_deref().filter(|o| *o != self.name) {
+            names.push(origin);
+        }
+        names
+    }
+
     /// Get the top components
     pub async fn top<'a, T>(
         connection: &'a T,
@@ -139,6 +274,24 @@ impl Component {
         .await?)
     }
 
+    /// Count the top components (see [Component::top])
+    pub async fn count_top<'a, T>(connection: &'a T) -> Result<u64, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Component::row_count(
+            connection,
+            Component::query_count()
+                .where_ne("component_type", ComponentType::Library)
+                .and()
+                .where_ne("component_type", ComponentType::Unknown)
+                .and()
+                .where_ne("component_type", ComponentType::Framework)
+                .build()?,
+        )
+        .await? as u64)
+    }
+
     /// Find Component by Name
     pub async fn find_by_name<'a, T>(
         connection: &'a T,
@@ -149,14 +302,341 @@ impl Component {
         T: GeekConnection<Connection = T> + 'a,
     {
         let name = name.into();
-        let select = Component::query_select()
-            .where_like("name", format!("%{}%", name))
-            .or()
-            .where_like("namespace", format!("%{}%", name))
-            .page(page)
-            .build()?;
+        let mut values = Values::new();
+        let filter = Self::search_filter(&name, &mut values);
+        let order = Self::search_order(&name, &mut values);
+        values.push("limit".to_string(), page.limit() as i32);
+        values.push("offset".to_string(), page.offset() as i32);
 
-        Ok(Component::query(connection, select).await?)
+        Ok(T::query::<Component>(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT * FROM Component WHERE {} ORDER BY {} LIMIT ? OFFSET ?;",
+                    filter, order
+                ),
+                values,
+            ),
+        )
+        .await?)
+    }
+
+    /// Count the Components matching [Component::find_by_name]
+    pub async fn count_by_name<'a, T>(
+        connection: &'a T,
+        name: &str,
+    ) -> Result<u64, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        let filter = Self::search_filter(name, &mut values);
+        Ok(Component::row_count(
+            connection,
+            raw_query(
+                format!("SELECT COUNT(*) FROM Component WHERE {};", filter),
+                values,
+            ),
+        )
+        .await? as u64)
+    }
+
+    /// Search Components by name, namespace, full coordinate or package manager
+    pub async fn search<'a, T>(
+        connection: &'a T,
+        term: &str,
+        limit: usize,
+    ) -> Result<Vec<Component>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        let filter = Self::search_filter(term, &mut values);
+        values.push("manager".to_string(), format!("%{}%", term));
+        let order = Self::search_order(term, &mut values);
+        values.push("limit".to_string(), limit as i32);
+
+        Ok(T::query::<Component>(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT * FROM Component WHERE {} OR manager LIKE ? ORDER BY {} LIMIT ?;",
+                    filter, order
+                ),
+                values,
+            ),
+        )
+        .await?)
+    }
+
+    /// Autocomplete Components by name
+    ///
+    /// Results are ranked with exact matches first, then prefix matches (which use the
+    /// name index), and finally substring matches.
+    pub async fn autocomplete<'a, T>(
+        connection: &'a T,
+        prefix: impl Into<String>,
+        limit: usize,
+    ) -> Result<Vec<Component>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let prefix = prefix.into();
+        if prefix.is_empty() || limit == 0 {
+            return Ok(vec![]);
+        }
+
+        // Exact matches (case insensitive)
+        let mut values = Values::new();
+        values.push("name".to_string(), prefix.clone());
+        let mut results = T::query::<Component>(
+            connection,
+            raw_query(format!("{} LIMIT {};", AUTOCOMPLETE_EXACT, limit), values),
+        )
+        .await?;
+
+        // Prefix matches (uses the NOCASE name index)
+        let pattern = escape_like(&prefix);
+        if results.len() < limit {
+            let mut values = Values::new();
+            values.push("name".to_string(), format!("{}%", pattern));
+            let prefixed = T::query::<Component>(
+                connection,
+                raw_query(format!("{} LIMIT {};", AUTOCOMPLETE_PREFIX, limit), values),
+            )
+            .await?;
+            Self::autocomplete_extend(&mut results, prefixed, limit);
+        }
+
+        // Substring matches
+        if results.len() < limit {
+            let mut values = Values::new();
+            values.push("name".to_string(), format!("%{}%", pattern));
+            let substrings = T::query::<Component>(
+                connection,
+                raw_query(
+                    format!("{} LIMIT {};", AUTOCOMPLETE_SUBSTRING, limit * 2),
+                    values,
+                ),
+            )
+            .await?;
+            Self::autocomplete_extend(&mut results, substrings, limit);
+        }
+
+        Ok(results)
+    }
+
+    fn autocomplete_extend(results: &mut Vec<Component>, others: Vec<Component>, limit: usize) {
+        for comp in others {
+            if results.len() >= limit {
+                break;
+            }
+            if !results.iter().any(|r| r.id == comp.id) {
+                results.push(comp);
+            }
+        }
+    }
+
+    /// Count the Projects using the Components in their latest Snapshot
+    ///
+    /// Returns the number of projects keyed by component ID (components that are not
+    /// used by any project are missing).
+    pub async fn project_counts<'a, T>(
+        connection: &'a T,
+        components: &[i32],
+    ) -> Result<HashMap<i32, i64>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        if components.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut values = Values::new();
+        for (index, id) in components.iter().enumerate() {
+            values.push(format!("component_{}", index), *id);
+        }
+
+        let rows = T::query::<ComponentProjectsRow>(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT d.component_id AS component_id, \
+                        COUNT(DISTINCT ps.project_id) AS count FROM ProjectSnapshots ps \
+                    INNER JOIN Dependencies d ON d.snapshot_id = ps.snapshot_id \
+                    WHERE d.component_id IN ({}) AND ps.snapshot_id = \
+                    (SELECT MAX(snapshot_id) FROM ProjectSnapshots WHERE project_id = ps.project_id) \
+                    GROUP BY d.component_id;",
+                    vec!["?"; components.len()].join(", ")
+                ),
+                values,
+            ),
+        )
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.component_id, row.count))
+            .collect())
+    }
+
+    /// Count Dependencies grouped by Package Manager
+    ///
+    /// If a snapshot is provided, only the dependencies of that snapshot are counted,
+    /// otherwise the dependencies of the latest snapshot of every project.
+    pub async fn count_by_manager<'a, T>(
+        connection: &'a T,
+        snapshot: Option<i32>,
+    ) -> Result<Vec<(ComponentManager, i64)>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Self::count_by_column(connection, "manager", snapshot)
+            .await?
+            .into_iter()
+            .map(|c| (ComponentManager::from(c.name), c.count))
+            .collect())
+    }
+
+    /// Count Dependencies grouped by Component Type
+    ///
+    /// If a snapshot is provided, only the dependencies of that snapshot are counted,
+    /// otherwise the dependencies of the latest snapshot of every project.
+    pub async fn count_by_component_type<'a, T>(
+        connection: &'a T,
+        snapshot: Option<i32>,
+    ) -> Result<Vec<(ComponentType, i64)>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(
+            Self::count_by_column(connection, "component_type", snapshot)
+                .await?
+                .into_iter()
+                .map(|c| (ComponentType::from(c.name), c.count))
+                .collect(),
+        )
+    }
+
+    /// Top unclassified (library or unknown) component names by the number of dependencies
+    pub async fn top_unclassified<'a, T>(
+        connection: &'a T,
+        limit: u32,
+    ) -> Result<Vec<(String, i64)>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("library".to_string(), ComponentType::Library);
+        values.push("unknown".to_string(), ComponentType::Unknown);
+        values.push("limit".to_string(), limit as i32);
+
+        Ok(T::query::<ComponentCount>(
+            connection,
+            Query::new(
+                QueryType::Select,
+                "SELECT c.name AS name, COUNT(d.id) AS count FROM Component c \
+                    LEFT JOIN Dependencies d ON d.component_id = c.id \
+                    WHERE c.component_type IN (?, ?) \
+                    GROUP BY c.name ORDER BY count DESC, c.name ASC LIMIT ?;"
+                    .to_string(),
+                values,
+                Values::new(),
+                vec![],
+                Component::table(),
+            ),
+        )
+        .await?
+        .into_iter()
+        .map(|c| (c.name, c.count))
+        .collect())
+    }
+
+    /// GROUP BY count of Dependencies for a (trusted) Component column
+    async fn count_by_column<'a, T>(
+        connection: &'a T,
+        column: &'static str,
+        snapshot: Option<i32>,
+    ) -> Result<Vec<ComponentCount>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        let query = if let Some(snapshot) = snapshot {
+            values.push("snapshot_id".to_string(), snapshot);
+            format!(
+                "SELECT c.{column} AS name, COUNT(d.id) AS count FROM Dependencies d \
+                    INNER JOIN Component c ON c.id = d.component_id \
+                    WHERE d.snapshot_id = ? GROUP BY c.{column};"
+            )
+        } else {
+            format!(
+                "SELECT c.{column} AS name, COUNT(d.id) AS count FROM Dependencies d \
+                    INNER JOIN Component c ON c.id = d.component_id \
+                    WHERE d.snapshot_id IN \
+                        (SELECT MAX(snapshot_id) FROM ProjectSnapshots GROUP BY project_id) \
+                    GROUP BY c.{column};"
+            )
+        };
+
+        Ok(T::query::<ComponentCount>(
+            connection,
+            Query::new(
+                QueryType::Select,
+                query,
+                values,
+                Values::new(),
+                vec![],
+                Component::table(),
+            ),
+        )
+        .await?)
+    }
+
+    /// Find Components with a tag (see [super::ComponentTags])
+    pub async fn find_by_tag<'a, T>(
+        connection: &'a T,
+        tag: &str,
+        page: &Pagination,
+    ) -> Result<Vec<Component>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("tag".to_string(), super::ComponentTags::normalize(tag)?);
+        values.push("limit".to_string(), page.limit() as i32);
+        values.push("offset".to_string(), page.offset() as i32);
+
+        Ok(T::query::<Component>(
+            connection,
+            raw_query(
+                "SELECT c.* FROM Component c \
+                    INNER JOIN ComponentTags t ON t.component_id = c.id \
+                    WHERE t.tag = ? ORDER BY c.name ASC LIMIT ? OFFSET ?;",
+                values,
+            ),
+        )
+        .await?)
+    }
+
+    /// Count the Components with a tag
+    pub async fn count_by_tag<'a, T>(
+        connection: &'a T,
+        tag: &str,
+    ) -> Result<u64, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("tag".to_string(), super::ComponentTags::normalize(tag)?);
+        Ok(Component::row_count(
+            connection,
+            raw_query(
+                "SELECT COUNT(*) FROM Component c \
+                    INNER JOIN ComponentTags t ON t.component_id = c.id \
+                    WHERE t.tag = ?;",
+                values,
+            ),
+        )
+        .await? as u64)
     }
 
     /// Find Component by type
@@ -178,6 +658,23 @@ impl Component {
         )
         .await?)
     }
+
+    /// Count the Components of a type (see [Component::find_by_component_type])
+    pub async fn count_of_component_type<'a, T>(
+        connection: &'a T,
+        ctype: impl Into<ComponentType>,
+    ) -> Result<u64, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Self::row_count(
+            connection,
+            Self::query_count()
+                .where_eq("component_type", ctype.into())
+                .build()?,
+        )
+        .await? as u64)
+    }
 }
 
 #[cfg(test)]
@@ -229,4 +726,270 @@ mod tests {
             assert_eq!(comp.purl(), purl.to_string());
         }
     }
+
+    #[test]
+    fn test_namespaced_purls() {
+        let (comp, version) =
+            Component::from_purl("pkg:maven/org.apache.logging.log4j/log4j-core@2.17.1").unwrap();
+        assert_eq!(comp.namespace, Some("org.apache.logging.log4j".to_string()));
+        assert_eq!(comp.name, "log4j-core");
+        assert_eq!(comp.display_name(), "org.apache.logging.log4j:log4j-core");
+        assert_eq!(version.version, "2.17.1");
+
+        let (comp, _) = Component::from_purl("pkg:golang/github.com/gorilla/mux@v1.8.0").unwrap();
+        assert_eq!(comp.namespace, Some("github.com/gorilla".to_string()));
+        assert_eq!(comp.display_name(), "github.com/gorilla/mux");
+        assert_eq!(comp.purl(), "pkg:golang/github.com/gorilla/mux");
+
+        // Scoped npm packages, with and without the scope being URL-encoded
+        for purl in [
+            "pkg:npm/%40angular/core@16.0.0",
+            "pkg:npm/@angular/core@16.0.0",
+            "pkg:npm/@angular/core",
+        ] {
+            let (comp, _) = Component::from_purl(purl).unwrap();
+            assert_eq!(comp.namespace, Some("@angular".to_string()));
+            assert_eq!(comp.name, "core");
+            assert_eq!(comp.display_name(), "@angular/core");
+        }
+
+        let (comp, _) = Component::from_purl("pkg:npm/react@18.0.0").unwrap();
+        assert_eq!(comp.display_name(), "react");
+    }
+
+    #[tokio::test]
+    async fn test_search_coordinates() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let snapshot = crate::models::Snapshot::create(&connection).await?;
+        for purl in [
+            "pkg:maven/org.apache.logging.log4j/log4j-core@2.17.1",
+            "pkg:maven/org.apache.logging.log4j/log4j-api@2.17.1",
+            "pkg:golang/github.com/gorilla/mux@v1.8.0",
+            "pkg:npm/%40angular/core@16.0.0",
+            "pkg:npm/core-js@3.0.0",
+        ] {
+            let mut dependency =
+                crate::models::Dependencies::from_purl(&connection, purl.to_string()).await?;
+            dependency.snapshot_id = snapshot.id.into();
+            dependency.save(&connection).await?;
+        }
+        let search = |term: &'static str| {
+            let connection = connection.clone();
+            async move {
+                Component::find_by_name(&connection, term, &Pagination::new())
+                    .await
+                    .map(|comps| comps.iter().map(|c| c.display_name()).collect::<Vec<_>>())
+            }
+        };
+
+        assert_eq!(
+            search("log4j-core").await?,
+            vec!["org.apache.logging.log4j:log4j-core"]
+        );
+        assert_eq!(search("org.apache.logging.log4j").await?.len(), 2);
+        assert_eq!(
+            search("org.apache.logging.log4j:log4j-core").await?,
+            vec!["org.apache.logging.log4j:log4j-core"]
+        );
+        assert_eq!(
+            search("github.com/gorilla/mux").await?,
+            vec!["github.com/gorilla/mux"]
+        );
+        // Exact coordinate matches first
+        assert_eq!(search("core").await?[0], "@angular/core");
+        assert_eq!(search("@angular/core").await?, vec!["@angular/core"]);
+
+        // Package URLs (namespaces with dots, multiple segments and scopes)
+        for (purl, name) in [
+            (
+                "pkg:maven/org.apache.logging.log4j/log4j-core",
+                "org.apache.logging.log4j:log4j-core",
+            ),
+            (
+                "pkg:golang/github.com/gorilla/mux",
+                "github.com/gorilla/mux",
+            ),
+            ("pkg:npm/@angular/core", "@angular/core"),
+            ("pkg:npm/%40angular/core", "@angular/core"),
+        ] {
+            let deps = crate::models::Dependencies::find_by_purl(&connection, purl).await?;
+            assert_eq!(deps.len(), 1, "{}", purl);
+            assert_eq!(deps[0].display_name(), name);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_autocomplete() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        for name in ["crate-01a", "crate-01b", "crate-02a"] {
+            let mut comp = Component::new(ComponentManager::Cargo, name.to_string());
+            comp.save(&connection).await?;
+        }
+        for name in ["serde", "serde_json", "serde_yaml", "my-serde"] {
+            let mut comp = Component::new(ComponentManager::Cargo, name.to_string());
+            comp.save(&connection).await?;
+        }
+
+        let results = Component::autocomplete(&connection, "serde", 10).await?;
+        let names: Vec<&str> = results.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["serde", "serde_json", "serde_yaml", "my-serde"]);
+
+        let results = Component::autocomplete(&connection, "SERDE", 1).await?;
+        assert_eq!(results[0].name, "serde");
+
+        let results = Component::autocomplete(&connection, "crate-01", 10).await?;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|c| c.name.starts_with("crate-01")));
+
+        // The LIKE wildcards of the input are matched literally
+        assert!(Component::autocomplete(&connection, "%", 10)
+            .await?
+            .is_empty());
+        let results = Component::autocomplete(&connection, "_", 10).await?;
+        let names: Vec<&str> = results.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["serde_json", "serde_yaml"]);
+        let results = Component::autocomplete(&connection, "serde_", 10).await?;
+        assert_eq!(results.len(), 2);
+
+        // The exact and prefix matches are searched with the name index
+        for (sql, value) in [
+            (AUTOCOMPLETE_EXACT, "serde"),
+            (AUTOCOMPLETE_PREFIX, "serde\\_%"),
+        ] {
+            let mut rows = connection
+                .query(&format!("EXPLAIN QUERY PLAN {};", sql), [value])
+                .await?;
+            let mut plan = Vec::new();
+            while let Some(row) = rows.next().await? {
+                plan.push(row.get::<String>(3)?);
+            }
+            assert!(
+                plan.iter()
+                    .all(|step| step.starts_with("SEARCH") && step.contains("ComponentNameIndex")),
+                "{}: {:?}",
+                sql,
+                plan
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_autocomplete_seeded() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut comp = Component::new(ComponentManager::Cargo, "crate".to_string());
+        comp.save(&connection).await?;
+        connection
+            .execute(
+                "WITH RECURSIVE seq(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM seq WHERE i < 100000) \
+                INSERT INTO Component (component_type, manager, name) \
+                SELECT component_type, manager, printf('crate-%06d', i) FROM seq, \
+                (SELECT component_type, manager FROM Component LIMIT 1);",
+                (),
+            )
+            .await?;
+
+        let start = std::time::Instant::now();
+        let results = Component::autocomplete(&connection, "crate-00001", 10).await?;
+        let elapsed = start.elapsed();
+
+        assert_eq!(results.len(), 10);
+        assert!(results.iter().all(|c| c.name.starts_with("crate-00001")));
+        // Generous bound (unoptimized builds), a table scan of the 100k rows is well above it
+        assert!(
+            elapsed < std::time::Duration::from_millis(250),
+            "Autocomplete took {:?}",
+            elapsed
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_project_counts() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let (mut serde, _) = Component::from_purl("pkg:cargo/serde@1.0.0")?;
+        serde.find_or_create(&connection).await?;
+        let mut version = ComponentVersion::new(serde.id, "1.0.0".to_string());
+        version.save(&connection).await?;
+        let (mut unused, _) = Component::from_purl("pkg:cargo/unused")?;
+        unused.find_or_create(&connection).await?;
+
+        for name in ["web", "api"] {
+            let mut project =
+                crate::models::Projects::new(name, crate::models::ProjectType::Container);
+            project.save(&connection).await?;
+            let snapshot = crate::models::Snapshot::create(&connection).await?;
+            let mut dependency =
+                crate::models::Dependencies::new(snapshot.id, serde.id, version.id);
+            dependency.save(&connection).await?;
+            project.add_snapshot(&connection, snapshot).await?;
+        }
+
+        let counts =
+            Component::project_counts(&connection, &[serde.id.into(), unused.id.into()]).await?;
+        assert_eq!(counts.get(&serde.id.into()), Some(&2));
+        assert_eq!(counts.get(&unused.id.into()), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_count_by_manager() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project =
+            crate::models::Projects::new("web", crate::models::ProjectType::Container);
+        project.save(&connection).await?;
+
+        // Only the dependencies of the latest snapshot are counted
+        let mut snapshots = Vec::new();
+        for purls in [
+            vec!["pkg:npm/left-pad@1.0.0"],
+            vec![
+                "pkg:npm/react@18.0.0",
+                "pkg:npm/vue@3.0.0",
+                "pkg:cargo/serde@1.0.0",
+            ],
+        ] {
+            let snapshot = crate::models::Snapshot::create(&connection).await?;
+            for purl in purls {
+                let (mut comp, version) = Component::from_purl(purl)?;
+                comp.find_or_create(&connection).await?;
+                let mut version = ComponentVersion::new(comp.id, version.version);
+                version.save(&connection).await?;
+                crate::models::Dependencies::new(snapshot.id, comp.id, version.id)
+                    .save(&connection)
+                    .await?;
+            }
+            snapshots.push(snapshot.id);
+            project.add_snapshot(&connection, snapshot).await?;
+        }
+        // Unused components are not counted
+        let (mut unused, _) = Component::from_purl("pkg:npm/unused")?;
+        unused.find_or_create(&connection).await?;
+
+        let count = |managers: &[(ComponentManager, i64)], manager: ComponentManager| {
+            managers
+                .iter()
+                .find(|(m, _)| *m == manager)
+                .map(|(_, c)| *c)
+        };
+
+        let managers = Component::count_by_manager(&connection, None).await?;
+        assert_eq!(count(&managers, ComponentManager::Npm), Some(2));
+        assert_eq!(count(&managers, ComponentManager::Cargo), Some(1));
+
+        let managers = Component::count_by_manager(&connection, Some(snapshots[0].into())).await?;
+        assert_eq!(count(&managers, ComponentManager::Npm), Some(1));
+        assert_eq!(count(&managers, ComponentManager::Cargo), None);
+
+        Ok(())
+    }
 }
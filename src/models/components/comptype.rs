@@ -55,6 +55,17 @@ pub enum ComponentType {
     Unknown,
 }
 
+impl ComponentType {
+    /// Parse a Component Type (`None` if the value is not a known type or alias)
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        match ComponentType::from(value) {
+            ComponentType::Unknown if !value.eq_ignore_ascii_case("unknown") => None,
+            component_type => Some(component_type),
+        }
+    }
+}
+
 impl From<BomComponentType> for ComponentType {
     fn from(value: BomComponentType) -> Self {
         match value {
@@ -97,4 +108,22 @@ mod tests {
             assert_eq!(pcrypto.to_string(), "CryptographyLibrary");
         }
     }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ComponentType::parse("CryptographyLibrary"),
+            Some(ComponentType::CryptographyLibrary)
+        );
+        assert_eq!(
+            ComponentType::parse(" os "),
+            Some(ComponentType::OperatingSystem)
+        );
+        assert_eq!(
+            ComponentType::parse("unknown"),
+            Some(ComponentType::Unknown)
+        );
+        assert_eq!(ComponentType::parse("spaceship"), None);
+        assert_eq!(ComponentType::parse(""), None);
+    }
 }
@@ -1,9 +1,14 @@
 //! # Component Version Model
 
 use geekorm::prelude::*;
+use log::{debug, info};
 use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::BTreeMap, collections::HashMap};
 
-use super::components::Component;
+use super::{components::Component, ComponentManager};
+use crate::models::raw_query;
+use crate::models::security::{SecuritySeverity, SecurityState};
+use crate::models::{Dependencies, Transaction, TransactionConnection};
 
 /// Component Dependency Model
 #[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
@@ -35,7 +40,53 @@ impl ComponentVersion {
         Ok(semver::Version::parse(self.version.as_str())?)
     }
 
+    /// Normalise a version string using the package manager conventions
+    ///
+    /// - Leading `v` is removed (`v1.2.3` -> `1.2.3`)
+    /// - Deb / RPM: the default `0:` epoch is removed
+    /// - Deb: the default `-0` revision is removed
+    /// - Nuget: a zero fourth segment is removed (`1.2.3.0` -> `1.2.3`)
+    /// - PyPi: lowercased and trailing zero segments after the third are removed
+    pub fn normalize(version: impl AsRef<str>, manager: &ComponentManager) -> String {
+        let mut version = version.as_ref().trim();
+
+        if let Some(stripped) = version.strip_prefix(['v', 'V']) {
+            if stripped.starts_with(|c: char| c.is_ascii_digit()) {
+                version = stripped;
+            }
+        }
+
+        match manager {
+            ComponentManager::Deb => version
+                .trim_start_matches("0:")
+                .strip_suffix("-0")
+                .unwrap_or(version.trim_start_matches("0:"))
+                .to_string(),
+            ComponentManager::Rpm => version.trim_start_matches("0:").to_string(),
+            ComponentManager::Nuget => {
+                let (release, suffix) = split_release(version);
+                let mut segments: Vec<&str> = release.split('.').collect();
+                if segments.len() == 4 && segments[3] == "0" {
+                    segments.pop();
+                }
+                format!("{}{}", segments.join("."), suffix)
+            }
+            ComponentManager::PyPi => {
+                let version = version.to_lowercase();
+                let (release, suffix) = split_release(&version);
+                let mut segments: Vec<&str> = release.split('.').collect();
+                while segments.len() > 3 && segments.last() == Some(&"0") {
+                    segments.pop();
+                }
+                format!("{}{}", segments.join("."), suffix)
+            }
+            _ => version.to_string(),
+        }
+    }
+
     /// Find or Create Component Version
+    ///
+    /// The version is normalised before it is looked up (see [ComponentVersion::normalize])
     pub async fn find_or_crate<'a, T>(
         &mut self,
         connection: &'a T,
@@ -43,6 +94,8 @@ impl ComponentVersion {
     where
         T: GeekConnection<Connection = T> + 'a,
     {
+        self.version = Self::normalize(&self.version, &self.component_id.data.manager);
+
         let select = ComponentVersion::query_select()
             .where_eq("component_id", self.component_id.clone())
             .and()
@@ -57,4 +110,366 @@ impl ComponentVersion {
             Err(_) => self.save(connection).await.map_err(|e| e.into()),
         }
     }
+
+    /// Merge duplicate versions that normalise to the same value
+    ///
+    /// Dependencies are repointed to the oldest version row and the duplicates are removed.
+    /// Everything runs in a single transaction and returns the number of merged versions.
+    pub async fn dedupe<'a, T>(connection: &'a T) -> Result<usize, crate::KonarrError>
+    where
+        T: TransactionConnection + 'a,
+    {
+        let transaction = Transaction::begin(connection).await?;
+        let result = Self::dedupe_inner(transaction.connection()).await;
+        let merged = transaction.finish(result).await?;
+        info!("Merged {} duplicate component versions", merged);
+        Ok(merged)
+    }
+
+    async fn dedupe_inner<'a, T>(connection: &'a T) -> Result<usize, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let managers: HashMap<i32, ComponentManager> = Component::fetch_all(connection)
+            .await?
+            .into_iter()
+            .map(|c| (c.id.into(), c.manager))
+            .collect();
+
+        let mut versions = ComponentVersion::fetch_all(connection).await?;
+        versions.sort_by_key(|v| i32::from(v.id));
+
+        // (component, normalised version) -> version row to keep
+        let mut keep: HashMap<(i32, String), ComponentVersion> = HashMap::new();
+        let mut merged = 0;
+
+        for mut version in versions {
+            let component: i32 = version.component_id.key;
+            let manager = managers.get(&component).cloned().unwrap_or_default();
+            let normalized = Self::normalize(&version.version, &manager);
+
+            match keep.get(&(component, normalized.clone())) {
+                Some(existing) => {
+                    debug!(
+                        "Merging version `{}` ({}) into `{}` ({})",
+                        version.version, version.id, existing.version, existing.id
+                    );
+                    let mut values = Values::new();
+                    values.push("new_id".to_string(), existing.id);
+                    values.push("old_id".to_string(), version.id);
+                    T::execute::<Dependencies>(
+                        connection,
+                        raw_query(
+                            "UPDATE Dependencies SET component_version_id = ? WHERE component_version_id = ?;",
+                            values,
+                        ),
+                    )
+                    .await?;
+                    version.delete(connection).await?;
+                    merged += 1;
+                }
+                None => {
+                    if version.version != normalized {
+                        version.version = normalized.clone();
+                        version.update(connection).await?;
+                    }
+                    keep.insert((component, normalized), version);
+                }
+            }
+        }
+
+        Ok(merged)
+    }
+
+    /// Versions of a component in the latest snapshot of the projects (newest first)
+    ///
+    /// Alerts are the open advisories (vulnerable or acknowledged) of the version,
+    /// an advisory found in several projects is only counted once.
+    pub async fn usage<'a, T>(
+        connection: &'a T,
+        component_id: i32,
+    ) -> Result<Vec<ComponentVersionUsage>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("component_id".to_string(), component_id);
+        let rows = T::query::<VersionProjectsRow>(
+            connection,
+            raw_query(
+                "SELECT MIN(cv.id) AS id, cv.version, COUNT(DISTINCT ps.project_id) AS projects \
+                FROM Dependencies d \
+                INNER JOIN ComponentVersion cv ON cv.id = d.component_version_id \
+                INNER JOIN ProjectSnapshots ps ON ps.snapshot_id = d.snapshot_id \
+                WHERE d.component_id = ? AND ps.snapshot_id = \
+                    (SELECT MAX(snapshot_id) FROM ProjectSnapshots WHERE project_id = ps.project_id) \
+                GROUP BY cv.version;",
+                values,
+            ),
+        )
+        .await?;
+
+        let mut values = Values::new();
+        values.push("component_id".to_string(), component_id);
+        values.push("vulnerable".to_string(), SecurityState::Vulnerable);
+        values.push("acknowledged".to_string(), SecurityState::Acknowledged);
+        let alerts = T::query::<VersionAlertsRow>(
+            connection,
+            raw_query(
+                "SELECT cv.version, adv.severity, \
+                    COUNT(DISTINCT a.advisory_id) AS alerts \
+                FROM Alerts a \
+                INNER JOIN Dependencies d ON d.id = a.dependency_id \
+                INNER JOIN ComponentVersion cv ON cv.id = d.component_version_id \
+                INNER JOIN ProjectSnapshots ps ON ps.snapshot_id = a.snapshot_id \
+                INNER JOIN Advisories adv ON adv.id = a.advisory_id \
+                WHERE d.component_id = ? AND a.state IN (?, ?) AND ps.snapshot_id = \
+                    (SELECT MAX(snapshot_id) FROM ProjectSnapshots WHERE project_id = ps.project_id) \
+                GROUP BY cv.version, adv.severity;",
+                values,
+            ),
+        )
+        .await?;
+
+        let mut usage: Vec<ComponentVersionUsage> = rows
+            .into_iter()
+            .map(|row| ComponentVersionUsage {
+                version_id: row.id,
+                version: row.version,
+                projects: row.projects,
+                ..Default::default()
+            })
+            .collect();
+        for row in alerts {
+            if let Some(version) = usage.iter_mut().find(|v| v.version == row.version) {
+                version.alerts += row.alerts;
+                *version
+                    .severities
+                    .entry(SecuritySeverity::from(row.severity))
+                    .or_default() += row.alerts;
+            }
+        }
+
+        usage.sort_by(|a, b| compare_versions(&b.version, &a.version));
+        Ok(usage)
+    }
+
+    /// Recommended version to converge on: the newest version without alerts,
+    /// or the version with the fewest alerts (`versions` ordered newest first)
+    pub fn recommended(versions: &[ComponentVersionUsage]) -> Option<&ComponentVersionUsage> {
+        versions.iter().min_by_key(|version| version.alerts)
+    }
+}
+
+/// Usage of a component version in the latest snapshot of the projects
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ComponentVersionUsage {
+    /// Component Version ID
+    pub version_id: i32,
+    /// Version
+    pub version: String,
+    /// Number of projects using the version
+    pub projects: i64,
+    /// Number of open advisories of the version
+    pub alerts: i64,
+    /// Number of open advisories by severity
+    pub severities: BTreeMap<SecuritySeverity, i64>,
+}
+
+impl ComponentVersionUsage {
+    /// Highest severity of the alerts (if any)
+    pub fn severity(&self) -> Option<SecuritySeverity> {
+        self.severities.keys().max().cloned()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionProjectsRow {
+    id: i32,
+    version: String,
+    projects: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionAlertsRow {
+    version: String,
+    severity: String,
+    alerts: i64,
+}
+
+/// Compare two versions (semver if possible, numeric segments otherwise)
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    if let (Ok(a), Ok(b)) = (semver::Version::parse(a), semver::Version::parse(b)) {
+        return a.cmp(&b);
+    }
+    let segments = |version: &str| -> Vec<u64> {
+        split_release(version)
+            .0
+            .split('.')
+            .filter_map(|segment| segment.parse().ok())
+            .collect()
+    };
+    segments(a).cmp(&segments(b)).then_with(|| a.cmp(b))
+}
+
+/// Split a version into the numeric release and the rest (`1.2.0rc1` -> `1.2.0`, `rc1`)
+fn split_release(version: &str) -> (&str, &str) {
+    let index = version
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(version.len());
+    let (release, suffix) = version.split_at(index);
+    // Keep a trailing separator with the suffix
+    match release.strip_suffix('.') {
+        Some(release) => (release, &version[release.len()..]),
+        None => (release, suffix),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let cases = [
+            ("v1.2.3", ComponentManager::Cargo, "1.2.3"),
+            (" 1.2.3 ", ComponentManager::Npm, "1.2.3"),
+            ("vendor", ComponentManager::Generic, "vendor"),
+            ("0:1.2-0", ComponentManager::Deb, "1.2"),
+            ("3.11.2-6", ComponentManager::Deb, "3.11.2-6"),
+            ("0:1.2.3-4.el9", ComponentManager::Rpm, "1.2.3-4.el9"),
+            ("1.2.3-r0", ComponentManager::Apk, "1.2.3-r0"),
+            ("1.2.3.0", ComponentManager::Nuget, "1.2.3"),
+            ("1.2.3.0-beta", ComponentManager::Nuget, "1.2.3-beta"),
+            ("2.0.0.0RC1", ComponentManager::PyPi, "2.0.0rc1"),
+        ];
+        for (version, manager, expected) in cases {
+            assert_eq!(ComponentVersion::normalize(version, &manager), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dedupe() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut comp = Component::new(ComponentManager::Cargo, "serde".to_string());
+        comp.save(&connection).await?;
+
+        let snapshot = crate::models::Snapshot::create(&connection).await?;
+
+        let mut ids = vec![];
+        for v in ["1.0.0", "v1.0.0", "1.0.1"] {
+            // Save directly to simulate rows created before normalisation
+            let mut version = ComponentVersion::new(comp.id, v.to_string());
+            version.save(&connection).await?;
+            let mut dep = Dependencies::new(snapshot.id, comp.id, version.id);
+            dep.save(&connection).await?;
+            ids.push(i32::from(version.id));
+        }
+
+        assert_eq!(ComponentVersion::dedupe(&connection).await?, 1);
+        // Idempotent
+        assert_eq!(ComponentVersion::dedupe(&connection).await?, 0);
+
+        let versions = ComponentVersion::fetch_all(&connection).await?;
+        assert_eq!(versions.len(), 2);
+
+        let deps = Dependencies::fetch_all(&connection).await?;
+        assert!(deps.iter().all(|d| d.component_version_id.key != ids[1]));
+        assert_eq!(
+            deps.iter()
+                .filter(|d| d.component_version_id.key == ids[0])
+                .count(),
+            2
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_version_usage() -> Result<(), crate::KonarrError> {
+        use crate::models::security::{Advisories, AdvisorySource, Alerts};
+        use crate::models::{ProjectType, Projects, Snapshot};
+
+        let connection = crate::models::database_test().await?;
+
+        // 1.0.0 (critical + high), 1.1.0 (no alerts) and 1.2.0 (low)
+        let fixtures: [(&str, &str, &[SecuritySeverity]); 4] = [
+            (
+                "project-a",
+                "1.0.0",
+                &[SecuritySeverity::Critical, SecuritySeverity::High],
+            ),
+            ("project-b", "1.1.0", &[]),
+            ("project-c", "1.1.0", &[]),
+            ("project-d", "1.2.0", &[SecuritySeverity::Low]),
+        ];
+        let mut dependencies = vec![];
+        for (name, version, severities) in fixtures {
+            let mut project = Projects::new(name, ProjectType::Container);
+            project.save(&connection).await?;
+            let snapshot = Snapshot::create(&connection).await?;
+            project.add_snapshot(&connection, snapshot.clone()).await?;
+
+            let mut dependency =
+                Dependencies::from_purl(&connection, format!("pkg:deb/debian/openssl@{}", version))
+                    .await?;
+            dependency.snapshot_id = snapshot.id.into();
+            dependency.save(&connection).await?;
+
+            for (index, severity) in severities.iter().enumerate() {
+                let mut advisory = Advisories::new(
+                    format!("CVE-{}-{}", version, index),
+                    AdvisorySource::Unknown,
+                    severity.clone(),
+                );
+                advisory.save(&connection).await?;
+                let mut alert = Alerts {
+                    dependency_id: Some(dependency.id.into()),
+                    ..Alerts::new(advisory.name.clone(), snapshot.id, advisory.id)
+                };
+                alert.save(&connection).await?;
+            }
+            dependencies.push(dependency);
+        }
+        let component: i32 = dependencies[0].component_id.key;
+
+        // 0.9.0 is only used in an old snapshot of project E
+        let mut project = Projects::new("project-e", ProjectType::Container);
+        project.save(&connection).await?;
+        let old = Snapshot::create(&connection).await?;
+        let mut dependency =
+            Dependencies::from_purl(&connection, "pkg:deb/debian/openssl@0.9.0".to_string())
+                .await?;
+        dependency.snapshot_id = old.id.into();
+        dependency.save(&connection).await?;
+        project.add_snapshot(&connection, old).await?;
+        project
+            .add_snapshot(&connection, Snapshot::create(&connection).await?)
+            .await?;
+
+        let usage = ComponentVersion::usage(&connection, component).await?;
+        assert_eq!(
+            usage
+                .iter()
+                .map(|v| (v.version.as_str(), v.projects, v.alerts))
+                .collect::<Vec<_>>(),
+            vec![("1.2.0", 1, 1), ("1.1.0", 2, 0), ("1.0.0", 1, 2)]
+        );
+        assert_eq!(usage[2].severity(), Some(SecuritySeverity::Critical));
+        assert_eq!(usage[2].severities.get(&SecuritySeverity::High), Some(&1));
+        assert_eq!(
+            ComponentVersion::recommended(&usage).map(|v| v.version.as_str()),
+            Some("1.1.0")
+        );
+
+        // Without a version free of alerts, the fewest alerts wins
+        let vulnerable: Vec<ComponentVersionUsage> =
+            usage.into_iter().filter(|v| v.alerts != 0).collect();
+        assert_eq!(
+            ComponentVersion::recommended(&vulnerable).map(|v| v.version.as_str()),
+            Some("1.2.0")
+        );
+        Ok(())
+    }
 }
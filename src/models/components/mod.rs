@@ -1,11 +1,16 @@
 //! Components Models
 
+pub mod annotations;
 pub mod compmanager;
 pub mod components;
 pub mod comptype;
 pub mod compversion;
+pub mod tags;
 
-pub use compmanager::ComponentManager;
+pub use annotations::ComponentAnnotations;
+pub use compmanager::{ComponentEcosystem, ComponentManager, COMPONENT_MANAGERS};
+pub(crate) use components::parse_purl;
 pub use components::Component;
 pub use comptype::ComponentType;
-pub use compversion::ComponentVersion;
+pub use compversion::{ComponentVersion, ComponentVersionUsage};
+pub use tags::ComponentTags;
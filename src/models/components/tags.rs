@@ -0,0 +1,324 @@
+//! # Component Tags
+//!
+//! Custom classification of the components (`internal-sdk`, ...) next to the built-in
+//! [super::ComponentType]. Tags are assigned by admins (a single component or every
+//! component matching a PURL pattern) or by the catalogue, and counted in the statistics.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use geekorm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Component;
+use crate::{bom::processors::glob_match, models::raw_query, KonarrError};
+
+/// Maximum length (characters) of a tag
+pub const COMPONENT_TAG_MAX_LENGTH: usize = 64;
+
+/// Number of components loaded per batch when tagging by PURL pattern
+const COMPONENT_TAG_BATCH_SIZE: usize = 500;
+
+#[derive(Debug, Deserialize)]
+struct TagCountRow {
+    tag: String,
+    count: i64,
+}
+
+/// Component tags table
+#[derive(Table, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ComponentTags {
+    /// Primary key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+
+    /// Component the tag is assigned to
+    pub component_id: i32,
+    /// Tag (lowercase, see [ComponentTags::normalize])
+    pub tag: String,
+
+    /// Created at
+    #[geekorm(new = "Utc::now()")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl ComponentTags {
+    /// Initialise the Component Tags table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::create_table(connection).await?;
+        Ok(())
+    }
+
+    /// Normalise a tag (lowercase, only `a-z`, `0-9`, `-`, `_` and `.`)
+    pub fn normalize(tag: impl AsRef<str>) -> Result<String, KonarrError> {
+        let tag = tag.as_ref().trim().to_lowercase();
+        if tag.is_empty() || tag.chars().count() > COMPONENT_TAG_MAX_LENGTH {
+            return Err(KonarrError::InvalidData(format!(
+                "Tag must be between 1 and {} characters",
+                COMPONENT_TAG_MAX_LENGTH
+            )));
+        }
+        if !tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(KonarrError::InvalidData(format!(
+                "Invalid tag `{}` (only letters, digits, `-`, `_` and `.`)",
+                tag
+            )));
+        }
+        Ok(tag)
+    }
+
+    /// Assign a tag to a component
+    ///
+    /// Returns `false` if the component already has the tag.
+    pub async fn assign<'a, T>(
+        connection: &'a T,
+        component_id: i32,
+        tag: impl AsRef<str>,
+    ) -> Result<bool, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let tag = Self::normalize(tag)?;
+        Component::fetch_by_primary_key(connection, component_id).await?;
+        Self::insert(connection, component_id, tag).await
+    }
+
+    /// Assign a tag to every component matching a PURL pattern (`*` wildcards, the
+    /// version is not part of the PURL)
+    ///
+    /// Returns the number of newly tagged components.
+    pub async fn assign_by_purl<'a, T>(
+        connection: &'a T,
+        pattern: &str,
+        tag: impl AsRef<str>,
+    ) -> Result<usize, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let tag = Self::normalize(tag)?;
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return Err(KonarrError::InvalidData(
+                "PURL pattern can not be empty".to_string(),
+            ));
+        }
+
+        let mut tagged = 0;
+        let mut offset = 0;
+        loop {
+            let comps = Component::query(
+                connection,
+                Component::query_select()
+                    .order_by("id", QueryOrder::Asc)
+                    .limit(COMPONENT_TAG_BATCH_SIZE)
+                    .offset(offset)
+                    .build()?,
+            )
+            .await?;
+            if comps.is_empty() {
+                break;
+            }
+            offset += comps.len();
+
+            for comp in comps {
+                if glob_match(pattern, &comp.purl())
+                    && Self::insert(connection, comp.id.into(), tag.clone()).await?
+                {
+                    tagged += 1;
+                }
+            }
+        }
+        log::info!(
+            "Tagged `{}` components matching `{}` with `{}`",
+            tagged,
+            pattern,
+            tag
+        );
+        Ok(tagged)
+    }
+
+    /// Remove a tag from a component
+    ///
+    /// Returns `false` if the component did not have the tag.
+    pub async fn remove<'a, T>(
+        connection: &'a T,
+        component_id: i32,
+        tag: impl AsRef<str>,
+    ) -> Result<bool, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let tag = Self::normalize(tag)?;
+        match Self::find(connection, component_id, &tag).await? {
+            Some(existing) => {
+                existing.delete(connection).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Tags of a component (sorted)
+    pub async fn fetch_for_component<'a, T>(
+        connection: &'a T,
+        component_id: i32,
+    ) -> Result<Vec<String>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Self::query(
+            connection,
+            Self::query_select()
+                .where_eq("component_id", component_id)
+                .order_by("tag", QueryOrder::Asc)
+                .build()?,
+        )
+        .await?
+        .into_iter()
+        .map(|t| t.tag)
+        .collect())
+    }
+
+    /// Number of components with each tag
+    pub async fn counts<'a, T>(connection: &'a T) -> Result<BTreeMap<String, i64>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(T::query::<TagCountRow>(
+            connection,
+            raw_query(
+                "SELECT tag, COUNT(DISTINCT component_id) AS count FROM ComponentTags GROUP BY tag;",
+                Values::new(),
+            ),
+        )
+        .await?
+        .into_iter()
+        .map(|row| (row.tag, row.count))
+        .collect())
+    }
+
+    async fn find<'a, T>(
+        connection: &'a T,
+        component_id: i32,
+        tag: &str,
+    ) -> Result<Option<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Self::query(
+            connection,
+            Self::query_select()
+                .where_eq("component_id", component_id)
+                .and()
+                .where_eq("tag", tag)
+                .build()?,
+        )
+        .await?
+        .into_iter()
+        .next())
+    }
+
+    /// Insert the (normalised) tag if the component does not have it yet
+    pub(crate) async fn insert<'a, T>(
+        connection: &'a T,
+        component_id: i32,
+        tag: String,
+    ) -> Result<bool, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        if Self::find(connection, component_id, &tag).await?.is_some() {
+            return Ok(false);
+        }
+        let mut component_tag = Self::new(component_id, tag);
+        component_tag.save(connection).await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Dependencies, Snapshot};
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            ComponentTags::normalize(" Internal-SDK ").unwrap(),
+            "internal-sdk"
+        );
+        assert!(ComponentTags::normalize("").is_err());
+        assert!(ComponentTags::normalize("internal sdk").is_err());
+        assert!(ComponentTags::normalize("a".repeat(65)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_component_tags() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let purls = [
+            "pkg:npm/%40acme/sdk-core@1.0.0",
+            "pkg:npm/%40acme/sdk-auth@1.2.0",
+            "pkg:npm/left-pad@1.3.0",
+        ];
+        let snapshot = Snapshot::create(&connection).await?;
+        let ingest = |purl: &'static str| {
+            let connection = connection.clone();
+            let snapshot = snapshot.id;
+            async move {
+                let mut dep = Dependencies::from_purl(&connection, purl.to_string()).await?;
+                dep.snapshot_id = snapshot.into();
+                dep.save(&connection).await?;
+                Ok::<_, KonarrError>(dep)
+            }
+        };
+        let mut deps = vec![];
+        for purl in purls {
+            deps.push(ingest(purl).await?);
+        }
+
+        assert_eq!(
+            ComponentTags::assign_by_purl(&connection, "pkg:npm/@acme/*", "internal-sdk").await?,
+            2
+        );
+        // Idempotent
+        assert_eq!(
+            ComponentTags::assign_by_purl(&connection, "pkg:npm/@acme/*", "internal-sdk").await?,
+            0
+        );
+        let left_pad: i32 = deps[2].component_id.key;
+        assert!(ComponentTags::assign(&connection, left_pad, "Vendored").await?);
+        assert!(!ComponentTags::assign(&connection, left_pad, "vendored").await?);
+        assert!(ComponentTags::assign(&connection, 999, "vendored")
+            .await
+            .is_err());
+
+        let counts = ComponentTags::counts(&connection).await?;
+        assert_eq!(counts.get("internal-sdk"), Some(&2));
+        assert_eq!(counts.get("vendored"), Some(&1));
+
+        // Re-ingesting the same components keeps the tags
+        for purl in purls {
+            ingest(purl).await?;
+        }
+        assert_eq!(ComponentTags::counts(&connection).await?, counts);
+        assert_eq!(
+            ComponentTags::fetch_for_component(&connection, left_pad).await?,
+            vec!["vendored".to_string()]
+        );
+
+        assert!(ComponentTags::remove(&connection, left_pad, "vendored").await?);
+        assert!(!ComponentTags::remove(&connection, left_pad, "vendored").await?);
+        assert_eq!(
+            ComponentTags::counts(&connection).await?.get("vendored"),
+            None
+        );
+        Ok(())
+    }
+}
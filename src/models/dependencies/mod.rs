@@ -1,14 +1,18 @@
 //! # Dependencies Model / Tables
 
 use geekorm::prelude::*;
-use purl::GenericPurl;
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
 
 pub mod snapshots;
 
-use super::{Component, ComponentManager, ComponentType, ComponentVersion};
-use crate::bom::sbom::BomComponent;
+use super::{
+    components::parse_purl, Component, ComponentEcosystem, ComponentManager, ComponentType,
+    ComponentVersion,
+};
+use crate::{
+    bom::sbom::{BomComponent, BomEvidence},
+    models::raw_query,
+};
 
 pub use snapshots::Snapshot;
 
@@ -30,9 +34,41 @@ pub struct Dependencies {
     /// Dependency Version ID
     #[geekorm(foreign_key = "ComponentVersion.id")]
     pub component_version_id: ForeignKey<i32, ComponentVersion>,
+
+    /// Scope of the dependency (if the SBOM provided one)
+    pub scope: Option<String>,
+    /// If the dependency is a direct dependency of the snapshot's main component
+    ///
+    /// Only set if the SBOM contains dependency graph data.
+    pub direct: Option<bool>,
+    /// Evidence of how the dependency was found (compact JSON, see [BomEvidence])
+    pub evidence: Option<String>,
 }
 
 impl Dependencies {
+    /// Initialise the Dependencies Table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Dependencies::create_table(connection).await?;
+
+        Dependencies::execute(
+            connection,
+            Query::new(
+                QueryType::Create,
+                "CREATE INDEX IF NOT EXISTS DependenciesComponentIndex ON Dependencies (component_id);"
+                    .to_string(),
+                Values::new(),
+                Values::new(),
+                vec![],
+                Dependencies::table(),
+            ),
+        )
+        .await?;
+        Ok(())
+    }
+
     /// Get component ID
     pub fn component_id(&self) -> PrimaryKey<i32> {
         self.component_id.data.id.clone()
@@ -45,6 +81,10 @@ impl Dependencies {
     pub fn manager(&self) -> ComponentManager {
         self.component_id.data.manager.clone()
     }
+    /// Get ecosystem (OS or language packages)
+    pub fn ecosystem(&self) -> ComponentEcosystem {
+        self.manager().ecosystem()
+    }
     /// Get name
     pub fn name(&self) -> String {
         self.component_id.data.name.clone()
@@ -58,6 +98,18 @@ impl Dependencies {
         Some(self.component_version_id.data.version.clone())
     }
 
+    /// Display name (full coordinate, see [Component::display_name])
+    pub fn display_name(&self) -> String {
+        self.component_id.data.display_name()
+    }
+
+    /// Evidence of how the dependency was found (if the SBOM provided any)
+    pub fn evidence(&self) -> Option<BomEvidence> {
+        self.evidence
+            .as_ref()
+            .and_then(|evidence| serde_json::from_str(evidence).ok())
+    }
+
     /// Package URL
     pub fn purl(&self) -> String {
         let mut purl = format!("pkg:{}", self.manager());
@@ -92,10 +144,13 @@ impl Dependencies {
     }
 
     /// Create new or find existing Dependency from BOM Component
+    ///
+    /// The evidence of the component is stored if it fits in the `evidence_budget` (bytes).
     pub async fn from_bom_compontent<'a, T>(
         connection: &'a T,
         snapshop: impl Into<PrimaryKey<i32>>,
         bom_component: &BomComponent,
+        evidence_budget: usize,
     ) -> Result<Self, crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
@@ -103,9 +158,11 @@ impl Dependencies {
         let snapshop = snapshop.into();
         let (mut component, mut version) = Component::from_purl(bom_component.purl.clone())?;
         // Update non-purl fields
+        component.origin = bom_component.origin.clone();
         component.find_or_create(connection).await?;
 
         version.component_id = component.id.into();
+        version.component_id.data = component.clone();
         version.find_or_crate(connection).await?;
 
         // Remove duplicate dependencies
@@ -125,6 +182,11 @@ impl Dependencies {
             Err(_) => {
                 let mut new_dep = Dependencies::new(0, component.id, version.id);
                 new_dep.snapshot_id = snapshop.into();
+                new_dep.scope = bom_component.scope.clone();
+                new_dep.direct = bom_component.direct;
+                new_dep.evidence = bom_component
+                    .evidence()
+                    .and_then(|evidence| evidence.to_json(evidence_budget));
                 new_dep.save(connection).await?;
                 Ok(new_dep)
             }
@@ -161,43 +223,47 @@ impl Dependencies {
     }
 
     /// Search for Dependencies
+    ///
+    /// If a Package Manager is provided, only Dependencies from that manager are returned.
     pub async fn search<'a, T>(
         connection: &'a T,
         snapshot_id: impl Into<PrimaryKey<i32>>,
         search: impl Into<String>,
+        manager: Option<&ComponentManager>,
     ) -> Result<Vec<Dependencies>, crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
         let search = search.into();
-        let snapshot_id = snapshot_id.into();
-
-        let query = Component::query_select()
-            .where_like("name", format!("%{}%", search))
-            .or()
-            .where_like("namespace", format!("%{}%", search))
-            .or()
-            .where_like("manager", format!("%{}%", search))
-            .limit(10);
-
-        let comps = Component::query(connection, query.build()?).await?;
-
-        let mut deps = Vec::new();
-        for comp in comps {
-            let mut instances = Dependencies::query(
-                connection,
-                Dependencies::query_select()
-                    .where_eq("snapshot_id", snapshot_id)
-                    .where_eq("component_id", comp.id)
-                    .build()?,
-            )
-            .await?;
+        let snapshot_id: PrimaryKey<i32> = snapshot_id.into();
+
+        let mut values = Values::new();
+        values.push("snapshot_id".to_string(), snapshot_id);
+        let mut filter = Component::search_filter(&search, &mut values);
+        values.push("manager".to_string(), format!("%{}%", search));
+        filter = format!("({} OR manager LIKE ?)", filter);
+        if let Some(manager) = manager {
+            values.push("manager_eq".to_string(), manager.clone());
+            filter.push_str(" AND manager = ?");
+        }
+        let order = Component::search_order(&search, &mut values);
 
-            for inst in instances.iter_mut() {
-                inst.fetch(connection).await?;
-            }
+        let mut deps = Dependencies::query(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT Dependencies.* FROM Dependencies \
+                        INNER JOIN Component ON Component.id = Dependencies.component_id \
+                        WHERE Dependencies.snapshot_id = ? AND {} ORDER BY {} LIMIT 10;",
+                    filter, order
+                ),
+                values,
+            ),
+        )
+        .await?;
 
-            deps.append(&mut instances);
+        for dep in deps.iter_mut() {
+            dep.fetch(connection).await?;
         }
 
         Ok(deps)
@@ -213,15 +279,7 @@ impl Dependencies {
     {
         let name = name.into();
 
-        let query = Component::query_select()
-            .where_like("name", format!("%{}%", name))
-            .or()
-            .where_like("namespace", format!("%{}%", name))
-            .or()
-            .where_like("manager", format!("%{}%", name))
-            .limit(10);
-
-        let comps = Component::query(connection, query.build()?).await?;
+        let comps = Component::search(connection, &name, 10).await?;
 
         let mut deps = Vec::new();
         for comp in comps {
@@ -247,8 +305,7 @@ impl Dependencies {
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        let purl = GenericPurl::<String>::from_str(purl.into().as_str())
-            .map_err(|e| crate::KonarrError::UnknownError(e.to_string()))?;
+        let purl = parse_purl(purl.into().as_str())?;
 
         let manager = ComponentManager::from(purl.package_type());
 
@@ -303,6 +360,36 @@ impl Dependencies {
         Ok(deps)
     }
 
+    /// Fetch the Dependencies of a snapshot built from a source / origin package
+    ///
+    /// Distro advisories are published against the source package (`openssl`), the
+    /// snapshot has the binary packages (`libssl3`, `libcrypto3`).
+    pub async fn fetch_by_origin<'a, T>(
+        connection: &'a T,
+        snapshot: impl Into<PrimaryKey<i32>>,
+        origin: &Component,
+    ) -> Result<Vec<Dependencies>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("snapshot_id".to_string(), snapshot.into());
+        values.push("manager".to_string(), origin.manager.clone());
+        values.push("origin".to_string(), origin.name.clone());
+        Ok(T::query::<Dependencies>(
+            connection,
+            raw_query(
+                "SELECT Dependencies.* FROM Dependencies \
+                JOIN Component ON Component.id = Dependencies.component_id \
+                WHERE Dependencies.snapshot_id = ? AND Component.manager = ? \
+                    AND Component.origin = ? \
+                ORDER BY Dependencies.id;",
+                values,
+            ),
+        )
+        .await?)
+    }
+
     /// Fetch single Dependency by snapshot ID
     pub async fn fetch_dependency_by_snapshot<'a, T>(
         connection: &'a T,
@@ -0,0 +1,484 @@
+//! # Snapshot Diff
+//!
+//! Dependency changes between a snapshot and the previous snapshot of its project
+//! (added, removed and upgraded components). The summary is stored in the `diff.*`
+//! snapshot metadata with a capped list of the notable changes.
+use std::collections::{BTreeMap, BTreeSet};
+
+use geekorm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{Snapshot, SnapshotMetadataKey, SnapshotState};
+use crate::models::{raw_query, ComponentType};
+
+/// Maximum number of notable changes stored with the summary
+pub const SNAPSHOT_DIFF_CHANGES_MAX: usize = 10;
+
+/// Component types listed first in the notable changes
+const SNAPSHOT_DIFF_NOTABLE_TYPES: [ComponentType; 3] = [
+    ComponentType::OperatingSystem,
+    ComponentType::ProgrammingLanguage,
+    ComponentType::CryptographyLibrary,
+];
+
+/// Kind of dependency change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotDiffKind {
+    /// Component is new in the snapshot
+    Added,
+    /// Component is no longer in the snapshot
+    Removed,
+    /// Version(s) of the component changed
+    Upgraded,
+}
+
+/// Change of a dependency between two snapshots
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotDiffChange {
+    /// Kind of change
+    pub kind: SnapshotDiffKind,
+    /// Package Manager of the component
+    pub manager: String,
+    /// Name of the component (`namespace/name`)
+    pub name: String,
+    /// Type of the component
+    pub component_type: String,
+    /// Previous version(s)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// New version(s)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+}
+
+/// Dependency changes of a snapshot compared to the previous snapshot
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    /// ID of the previous snapshot
+    pub previous: i32,
+    /// Number of added components
+    pub added: usize,
+    /// Number of removed components
+    pub removed: usize,
+    /// Number of components with a different version
+    pub upgraded: usize,
+    /// Notable changes (OS, languages and crypto libraries first, capped)
+    pub changes: Vec<SnapshotDiffChange>,
+}
+
+/// Dependency of a snapshot (component and version)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct SnapshotDiffRow {
+    /// Component ID
+    pub component_id: i32,
+    /// Component Type
+    pub component_type: String,
+    /// Package Manager
+    pub manager: String,
+    /// Namespace
+    pub namespace: Option<String>,
+    /// Name
+    pub name: String,
+    /// Version
+    pub version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PreviousSnapshotRow {
+    previous: Option<i32>,
+}
+
+/// Component (and its versions) of a snapshot
+struct DiffComponent<'r> {
+    row: &'r SnapshotDiffRow,
+    versions: BTreeSet<&'r str>,
+}
+
+/// Group the dependencies of a snapshot by component
+fn components(rows: &[SnapshotDiffRow]) -> BTreeMap<i32, DiffComponent<'_>> {
+    let mut components: BTreeMap<i32, DiffComponent> = BTreeMap::new();
+    for row in rows {
+        components
+            .entry(row.component_id)
+            .or_insert_with(|| DiffComponent {
+                row,
+                versions: BTreeSet::new(),
+            })
+            .versions
+            .insert(row.version.as_str());
+    }
+    components
+}
+
+impl SnapshotDiff {
+    /// Compare the dependencies of the previous snapshot with the current snapshot
+    ///
+    /// Components are compared by ID, a component with different versions counts
+    /// as upgraded (a component can have more than one version in a snapshot).
+    pub fn compare(previous: i32, before: &[SnapshotDiffRow], after: &[SnapshotDiffRow]) -> Self {
+        let before = components(before);
+        let after = components(after);
+        let versions = |component: &DiffComponent| {
+            component
+                .versions
+                .iter()
+                .copied()
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let mut diff = Self {
+            previous,
+            ..Default::default()
+        };
+        let mut changes = Vec::new();
+        for (id, component) in after.iter() {
+            match before.get(id) {
+                None => {
+                    diff.added += 1;
+                    changes.push(component.change(
+                        SnapshotDiffKind::Added,
+                        None,
+                        Some(versions(component)),
+                    ));
+                }
+                Some(old) if old.versions != component.versions => {
+                    diff.upgraded += 1;
+                    changes.push(component.change(
+                        SnapshotDiffKind::Upgraded,
+                        Some(versions(old)),
+                        Some(versions(component)),
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+        for (id, component) in before.iter() {
+            if !after.contains_key(id) {
+                diff.removed += 1;
+                changes.push(component.change(
+                    SnapshotDiffKind::Removed,
+                    Some(versions(component)),
+                    None,
+                ));
+            }
+        }
+
+        // Notable component types first (stable, so by kind and name otherwise)
+        changes.sort_by_key(|change| {
+            let notable = ComponentType::from(change.component_type.as_str());
+            (
+                !SNAPSHOT_DIFF_NOTABLE_TYPES.contains(&notable),
+                change.kind,
+                change.name.clone(),
+            )
+        });
+        changes.truncate(SNAPSHOT_DIFF_CHANGES_MAX);
+        diff.changes = changes;
+        diff
+    }
+
+    /// Total number of changed components
+    pub fn total(&self) -> usize {
+        self.added + self.upgraded + self.removed
+    }
+
+    /// If no dependencies changed
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    /// Short summary of the changes (`3 upgraded, 1 added, 2 removed`)
+    pub fn summary(&self) -> String {
+        [
+            (self.upgraded, "upgraded"),
+            (self.added, "added"),
+            (self.removed, "removed"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count != 0)
+        .map(|(count, kind)| format!("{} {}", count, kind))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+impl DiffComponent<'_> {
+    fn change(
+        &self,
+        kind: SnapshotDiffKind,
+        from: Option<String>,
+        to: Option<String>,
+    ) -> SnapshotDiffChange {
+        SnapshotDiffChange {
+            kind,
+            manager: self.row.manager.clone(),
+            name: match &self.row.namespace {
+                Some(namespace) if !namespace.is_empty() => {
+                    format!("{}/{}", namespace, self.row.name)
+                }
+                _ => self.row.name.clone(),
+            },
+            component_type: self.row.component_type.clone(),
+            from,
+            to,
+        }
+    }
+}
+
+impl Snapshot {
+    /// Fetch the Dependencies of the Snapshot to compare them with another Snapshot
+    pub async fn fetch_diff_rows<'a, T>(
+        &self,
+        connection: &'a T,
+    ) -> Result<Vec<SnapshotDiffRow>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("snapshot_id".to_string(), self.id);
+        Ok(T::query::<SnapshotDiffRow>(
+            connection,
+            raw_query(
+                "SELECT Dependencies.component_id AS component_id, \
+                    Component.component_type AS component_type, \
+                    Component.manager AS manager, \
+                    Component.namespace AS namespace, \
+                    Component.name AS name, \
+                    ComponentVersion.version AS version \
+                FROM Dependencies \
+                JOIN Component ON Component.id = Dependencies.component_id \
+                JOIN ComponentVersion ON ComponentVersion.id = Dependencies.component_version_id \
+                WHERE Dependencies.snapshot_id = ?;",
+                values,
+            ),
+        )
+        .await?)
+    }
+
+    /// Find the previous (completed) Snapshot of the first Project the Snapshot is linked to
+    pub async fn fetch_previous<'a, T>(
+        &self,
+        connection: &'a T,
+    ) -> Result<Option<Snapshot>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("snapshot_id".to_string(), self.id);
+        values.push("state".to_string(), SnapshotState::Completed);
+        values.push("current".to_string(), self.id);
+        let rows = T::query::<PreviousSnapshotRow>(
+            connection,
+            raw_query(
+                "SELECT MAX(ProjectSnapshots.snapshot_id) AS previous FROM ProjectSnapshots \
+                JOIN Snapshot ON Snapshot.id = ProjectSnapshots.snapshot_id \
+                WHERE ProjectSnapshots.project_id = (\
+                    SELECT MIN(project_id) FROM ProjectSnapshots WHERE snapshot_id = ?\
+                ) AND Snapshot.state = ? AND ProjectSnapshots.snapshot_id < ?;",
+                values,
+            ),
+        )
+        .await?;
+        match rows.first().and_then(|row| row.previous) {
+            Some(previous) => Ok(Some(
+                Snapshot::fetch_by_primary_key(connection, previous).await?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Calculate the Dependency changes since the previous Snapshot of the Project
+    ///
+    /// The summary is stored as metadata (`diff.*`). Returns `None` if the Snapshot is
+    /// not linked to a project or it is the first snapshot of the project.
+    pub async fn calculate_diff<'a, T>(
+        &mut self,
+        connection: &'a T,
+    ) -> Result<Option<SnapshotDiff>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let Some(previous) = self.fetch_previous(connection).await? else {
+            return Ok(None);
+        };
+        let diff = SnapshotDiff::compare(
+            previous.id.into(),
+            &previous.fetch_diff_rows(connection).await?,
+            &self.fetch_diff_rows(connection).await?,
+        );
+        log::debug!(
+            "Snapshot({}) :: changes since Snapshot({}): {}",
+            self.id,
+            previous.id,
+            diff.summary()
+        );
+
+        let metadata = [
+            (SnapshotMetadataKey::DiffPrevious, diff.previous.to_string()),
+            (SnapshotMetadataKey::DiffAdded, diff.added.to_string()),
+            (SnapshotMetadataKey::DiffRemoved, diff.removed.to_string()),
+            (SnapshotMetadataKey::DiffUpgraded, diff.upgraded.to_string()),
+            (
+                SnapshotMetadataKey::DiffChanges,
+                serde_json::to_string(&diff.changes)?,
+            ),
+        ];
+        for (key, value) in metadata {
+            let meta =
+                super::SnapshotMetadata::update_or_create(connection, self.id, &key, value).await?;
+            self.metadata.insert(key, meta);
+        }
+        Ok(Some(diff))
+    }
+
+    /// Dependency changes since the previous Snapshot (from the `diff.*` metadata)
+    pub fn diff(&self) -> Option<SnapshotDiff> {
+        let previous = self.metadata.get(&SnapshotMetadataKey::DiffPrevious)?;
+        let count = |key: &SnapshotMetadataKey| {
+            self.metadata
+                .get(key)
+                .and_then(|meta| meta.as_string().parse().ok())
+                .unwrap_or_default()
+        };
+        Some(SnapshotDiff {
+            previous: previous.as_string().parse().ok()?,
+            added: count(&SnapshotMetadataKey::DiffAdded),
+            removed: count(&SnapshotMetadataKey::DiffRemoved),
+            upgraded: count(&SnapshotMetadataKey::DiffUpgraded),
+            changes: self
+                .metadata
+                .get(&SnapshotMetadataKey::DiffChanges)
+                .and_then(|meta| serde_json::from_str(&meta.as_string()).ok())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Dependencies, ProjectType, Projects};
+
+    async fn snapshot(
+        connection: &libsql::Connection,
+        project: &mut Projects,
+        purls: &[&str],
+    ) -> Result<Snapshot, crate::KonarrError> {
+        let mut snapshot = Snapshot::create(connection).await?;
+        project.add_snapshot(connection, snapshot.clone()).await?;
+        for purl in purls {
+            let mut dependency = Dependencies::from_purl(connection, purl.to_string()).await?;
+            dependency.snapshot_id = snapshot.id.into();
+            dependency.save(connection).await?;
+        }
+        snapshot.calculate_diff(connection).await?;
+        snapshot
+            .set_state(connection, SnapshotState::Completed)
+            .await?;
+        Ok(snapshot)
+    }
+
+    #[test]
+    fn test_diff_compare() {
+        let row = |id: i32, name: &str, component_type: &str, version: &str| SnapshotDiffRow {
+            component_id: id,
+            component_type: component_type.to_string(),
+            manager: "deb".to_string(),
+            namespace: Some("debian".to_string()),
+            name: name.to_string(),
+            version: version.to_string(),
+        };
+        let before = vec![
+            row(1, "curl", "Library", "8.0.0"),
+            row(2, "openssl", "CryptographyLibrary", "3.0.1"),
+            row(3, "zlib", "Library", "1.3"),
+        ];
+        let after = vec![
+            row(1, "curl", "Library", "8.1.0"),
+            row(2, "openssl", "CryptographyLibrary", "3.0.2"),
+            row(4, "bash", "Library", "5.2"),
+        ];
+        let diff = SnapshotDiff::compare(1, &before, &after);
+        assert_eq!((diff.added, diff.removed, diff.upgraded), (1, 1, 2));
+        assert_eq!(diff.summary(), "2 upgraded, 1 added, 1 removed");
+
+        // Notable component types are listed first
+        assert_eq!(diff.changes[0].name, "debian/openssl");
+        assert_eq!(diff.changes[0].from.as_deref(), Some("3.0.1"));
+        assert_eq!(diff.changes[0].to.as_deref(), Some("3.0.2"));
+        assert_eq!(diff.changes[1].kind, SnapshotDiffKind::Added);
+        assert_eq!(diff.changes[1].name, "debian/bash");
+
+        // The notable changes are capped
+        let many: Vec<SnapshotDiffRow> = (0..20)
+            .map(|id| row(id, &format!("lib{}", id), "Library", "1.0"))
+            .collect();
+        let diff = SnapshotDiff::compare(1, &[], &many);
+        assert_eq!(diff.added, 20);
+        assert_eq!(diff.changes.len(), SNAPSHOT_DIFF_CHANGES_MAX);
+        assert!(SnapshotDiff::compare(1, &many, &many).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_diff() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("diff/nginx", ProjectType::Container);
+        project.save(&connection).await?;
+
+        // First snapshot of the project has nothing to compare with
+        let mut first = snapshot(
+            &connection,
+            &mut project,
+            &[
+                "pkg:deb/debian/openssl@3.0.1",
+                "pkg:deb/debian/curl@8.0.0",
+                "pkg:deb/debian/zlib@1.3",
+                "pkg:deb/debian/libxml2@2.9.14",
+            ],
+        )
+        .await?;
+        first.fetch_metadata(&connection).await?;
+        assert_eq!(first.diff(), None);
+
+        let mut second = snapshot(
+            &connection,
+            &mut project,
+            &[
+                "pkg:deb/debian/openssl@3.0.2",
+                "pkg:deb/debian/curl@8.1.0",
+                "pkg:deb/debian/libxml2@2.9.14",
+                "pkg:deb/debian/jq@1.7",
+            ],
+        )
+        .await?;
+        second.fetch_metadata(&connection).await?;
+        let diff = second.diff().expect("diff metadata");
+        assert_eq!(diff.previous, i32::from(first.id));
+        assert_eq!((diff.added, diff.removed, diff.upgraded), (1, 1, 2));
+        assert_eq!(diff.changes.len(), 4);
+        assert_eq!(diff.changes[0].name, "debian/openssl");
+        assert_eq!(diff.changes[0].kind, SnapshotDiffKind::Upgraded);
+        assert_eq!(second.find_metadata_usize("diff.upgraded"), 2);
+
+        // Snapshots are compared with the previous completed snapshot
+        let third = snapshot(
+            &connection,
+            &mut project,
+            &[
+                "pkg:deb/debian/openssl@3.0.2",
+                "pkg:deb/debian/curl@8.1.0",
+                "pkg:deb/debian/libxml2@2.9.14",
+                "pkg:deb/debian/jq@1.7",
+            ],
+        )
+        .await?;
+        let diff = third.diff().expect("diff metadata");
+        assert_eq!(diff.previous, i32::from(second.id));
+        assert!(diff.is_empty());
+        assert_eq!(diff.summary(), "");
+        Ok(())
+    }
+}
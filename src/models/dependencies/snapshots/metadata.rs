@@ -6,6 +6,7 @@ use log::debug;
 use serde::{Deserialize, Serialize};
 
 use super::Snapshot;
+use crate::utils::containers::{self, ContainerPort};
 
 /// Snapshot Metadata Model
 #[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
@@ -75,16 +76,19 @@ impl SnapshotMetadata {
         let value = value.into();
         debug!("Updating Metadata for Snapshot({:?}) :: {} ", snapshot, key);
 
-        Ok(match Self::find_by_key(connection, snapshot, &key).await {
-            Ok(Some(mut meta)) => {
-                meta.value = value;
-                meta.updated_at = chrono::Utc::now();
-
-                meta.update(connection).await?;
-                meta
-            }
-            _ => Self::add(connection, snapshot, key, value).await?,
+        crate::models::with_retries(|| async {
+            Ok(match Self::find_by_key(connection, snapshot, key).await {
+                Ok(Some(mut meta)) => {
+                    meta.value = value.clone();
+                    meta.updated_at = chrono::Utc::now();
+
+                    meta.update(connection).await?;
+                    meta
+                }
+                _ => Self::add(connection, snapshot, key, value.clone()).await?,
+            })
         })
+        .await
     }
     /// Add new Metadata to the Snapshot
     pub async fn add<'a, T>(
@@ -104,6 +108,27 @@ impl SnapshotMetadata {
         Ok(meta)
     }
 
+    /// Delete a key of the Snapshot metadata (returns if the key existed)
+    pub async fn delete_key<'a, T>(
+        connection: &'a T,
+        snapshot: impl Into<PrimaryKey<i32>>,
+        key: &SnapshotMetadataKey,
+    ) -> Result<bool, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let snapshot = snapshot.into();
+        debug!("Deleting Metadata of Snapshot({:?}) :: {} ", snapshot, key);
+
+        match Self::find_by_key(connection, snapshot, key).await {
+            Ok(Some(meta)) => {
+                meta.delete(connection).await?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
     /// Find Metadata by Key for a Snapshot
     pub async fn find_by_key<'a, T>(
         connection: &'a T,
@@ -198,6 +223,15 @@ pub enum SnapshotMetadataKey {
     Container,
     #[geekorm(key = "container.image")]
     ContainerImage,
+    /// Registry of the image (derived from `container.image`)
+    #[geekorm(key = "container.image.registry")]
+    ContainerImageRegistry,
+    /// Repository of the image (derived from `container.image`)
+    #[geekorm(key = "container.image.repository")]
+    ContainerImageRepository,
+    /// Tag of the image (derived from `container.image`)
+    #[geekorm(key = "container.image.tag")]
+    ContainerImageTag,
     /// SHA256 of the container
     #[geekorm(key = "container.sha")]
     ContainerSha,
@@ -213,6 +247,47 @@ pub enum SnapshotMetadataKey {
     #[geekorm(key = "container.authors")]
     ContainerAuthor,
 
+    // Container Runtime Info (updated by the agent on every run)
+    /// Container status (running, restarting, exited, etc)
+    #[geekorm(key = "container.status")]
+    ContainerStatus,
+    /// Health check status (healthy, unhealthy, starting, none)
+    #[geekorm(key = "container.health")]
+    ContainerHealth,
+    #[geekorm(key = "container.restart_count")]
+    ContainerRestartCount,
+    /// RFC 3339 datetime of when the container was started
+    #[geekorm(key = "container.started_at")]
+    ContainerStartedAt,
+    /// Seconds the container has been running for (when the agent last ran)
+    #[geekorm(key = "container.uptime")]
+    ContainerUptime,
+    /// Published / exposed ports (JSON array, see [crate::utils::containers::ContainerPort])
+    #[geekorm(key = "container.ports")]
+    ContainerPorts,
+    /// Networks the container is connected to (`bridge,frontend`)
+    #[geekorm(key = "container.networks")]
+    ContainerNetworks,
+
+    // Docker Swarm Service (updated by the agent on the manager node)
+    #[geekorm(key = "swarm.service.id")]
+    SwarmServiceId,
+    /// Stack the service was deployed with (`docker stack deploy`)
+    #[geekorm(key = "swarm.stack")]
+    SwarmStack,
+    /// Scheduling mode of the service (replicated, global, ...)
+    #[geekorm(key = "swarm.mode")]
+    SwarmMode,
+    /// Number of desired replicas (tasks)
+    #[geekorm(key = "swarm.replicas")]
+    SwarmReplicas,
+    /// Number of running replicas (tasks)
+    #[geekorm(key = "swarm.replicas.running")]
+    SwarmReplicasRunning,
+    /// Placement constraints of the service (`node.role==worker,node.labels.zone==eu`)
+    #[geekorm(key = "swarm.placement")]
+    SwarmPlacement,
+
     // BOM Data
     #[geekorm(key = "bom.type")]
     BomType,
@@ -230,6 +305,67 @@ pub enum SnapshotMetadataKey {
     /// Path to where the SBOM is stored
     #[geekorm(key = "bom.path")]
     BomPath,
+    /// When the stored SBOM was removed by the retention (the dependencies are kept)
+    #[geekorm(key = "bom.pruned")]
+    BomPruned,
+    /// Time it took to index the SBOM (in milliseconds)
+    #[geekorm(key = "bom.ingest.duration_ms")]
+    BomIngestDuration,
+    /// Sample of the components which failed to index (JSON list of `purl` and `reason`)
+    #[geekorm(key = "bom.errors")]
+    BomErrors,
+    /// Number of components which failed to index
+    #[geekorm(key = "bom.errors.count")]
+    BomErrorsCount,
+
+    // Scan Info (how the SBOM tool was run)
+    #[geekorm(key = "scan.tool")]
+    ScanTool,
+    #[geekorm(key = "scan.tool.version")]
+    ScanToolVersion,
+    /// Scan target (container image, directory, etc)
+    #[geekorm(key = "scan.target")]
+    ScanTarget,
+    /// Scope the tool scanned the image with (`all-layers`, `squashed`)
+    #[geekorm(key = "scan.scope")]
+    ScanScope,
+    /// Duration of the scan in milliseconds
+    #[geekorm(key = "scan.duration_ms", aliases = "scan.duration")]
+    ScanDuration,
+    /// Host the agent was running on
+    #[geekorm(key = "scan.agent.host")]
+    ScanAgentHost,
+    #[geekorm(key = "scan.agent.version")]
+    ScanAgentVersion,
+    /// The agent version is behind the server (see `agent.version.max-minor-gap`)
+    #[geekorm(key = "scan.agent.outdated")]
+    ScanAgentOutdated,
+    /// SHA256 of the container image the SBOM was generated from
+    #[geekorm(key = "scan.container.sha")]
+    ScanContainerSha,
+    /// The running container no longer matches the scanned image
+    #[geekorm(key = "scan.stale")]
+    ScanStale,
+
+    /// Snapshot is a baseline (never removed by the retention policies)
+    #[geekorm(key = "snapshot.baseline")]
+    SnapshotBaseline,
+
+    /// Previous snapshot the dependency changes are compared with
+    #[geekorm(key = "diff.previous")]
+    DiffPrevious,
+    /// Number of components added since the previous snapshot
+    #[geekorm(key = "diff.added")]
+    DiffAdded,
+    /// Number of components removed since the previous snapshot
+    #[geekorm(key = "diff.removed")]
+    DiffRemoved,
+    /// Number of components with a different version than the previous snapshot
+    #[geekorm(key = "diff.upgraded")]
+    DiffUpgraded,
+    /// Notable dependency changes (JSON list, capped)
+    #[geekorm(key = "diff.changes")]
+    DiffChanges,
 
     // Dependency Info
     #[geekorm(key = "dependencies.total", aliases = "bom.dependencies.count")]
@@ -285,12 +421,164 @@ pub enum SnapshotMetadataKey {
         aliases = "security.unknown.count,security.counts.unknown"
     )]
     SecurityAlertUnknown,
+    // Security Alert Exposure (only set if the SBOM contains dependency graph data)
+    /// Alerts in direct dependencies of the main component
+    #[geekorm(key = "security.alerts.total.direct")]
+    SecurityAlertTotalDirect,
+    #[geekorm(key = "security.alerts.critical.direct")]
+    SecurityAlertCriticalDirect,
+    #[geekorm(key = "security.alerts.high.direct")]
+    SecurityAlertHighDirect,
+    #[geekorm(key = "security.alerts.medium.direct")]
+    SecurityAlertMediumDirect,
+    #[geekorm(key = "security.alerts.low.direct")]
+    SecurityAlertLowDirect,
+    #[geekorm(key = "security.alerts.informational.direct")]
+    SecurityAlertInformationalDirect,
+    #[geekorm(key = "security.alerts.unmaintained.direct")]
+    SecurityAlertUnmaintainedDirect,
+    #[geekorm(key = "security.alerts.malware.direct")]
+    SecurityAlertMalwareDirect,
+    #[geekorm(key = "security.alerts.unknown.direct")]
+    SecurityAlertUnknownDirect,
+    /// Alerts in transitive (nested) dependencies
+    #[geekorm(key = "security.alerts.total.transitive")]
+    SecurityAlertTotalTransitive,
+    #[geekorm(key = "security.alerts.critical.transitive")]
+    SecurityAlertCriticalTransitive,
+    #[geekorm(key = "security.alerts.high.transitive")]
+    SecurityAlertHighTransitive,
+    #[geekorm(key = "security.alerts.medium.transitive")]
+    SecurityAlertMediumTransitive,
+    #[geekorm(key = "security.alerts.low.transitive")]
+    SecurityAlertLowTransitive,
+    #[geekorm(key = "security.alerts.informational.transitive")]
+    SecurityAlertInformationalTransitive,
+    #[geekorm(key = "security.alerts.unmaintained.transitive")]
+    SecurityAlertUnmaintainedTransitive,
+    #[geekorm(key = "security.alerts.malware.transitive")]
+    SecurityAlertMalwareTransitive,
+    #[geekorm(key = "security.alerts.unknown.transitive")]
+    SecurityAlertUnknownTransitive,
+    /// Number of open image policy findings
+    #[geekorm(key = "security.policy.total")]
+    SecurityPolicyTotal,
+    /// Number of open end-of-life findings (operating systems and runtimes)
+    #[geekorm(key = "security.eol.total")]
+    SecurityEolTotal,
+    /// Health of the project (`green`, `amber` or `red`) from the alert thresholds
+    #[geekorm(key = "security.health")]
+    SecurityHealth,
+    /// Build of the advisories (Grype) database the snapshot was last scanned with
+    #[geekorm(key = "security.scan.grype_build")]
+    SecurityScanGrypeBuild,
+    /// Version of the Konarr matcher the snapshot was last scanned with
+    /// (see [crate::models::security::ALERTS_MATCHER_VERSION])
+    #[geekorm(key = "security.scan.matcher_version")]
+    SecurityScanMatcherVersion,
 
     #[geekorm(key = "unknown")]
     #[default]
     Unknown,
 }
 
+impl SnapshotMetadataKey {
+    /// Check if the key is part of the `scan` metadata block
+    pub fn is_scan(&self) -> bool {
+        matches!(
+            self,
+            SnapshotMetadataKey::ScanTool
+                | SnapshotMetadataKey::ScanToolVersion
+                | SnapshotMetadataKey::ScanTarget
+                | SnapshotMetadataKey::ScanScope
+                | SnapshotMetadataKey::ScanDuration
+                | SnapshotMetadataKey::ScanAgentHost
+                | SnapshotMetadataKey::ScanAgentVersion
+        )
+    }
+
+    /// Check if the key is part of the `diff` metadata block
+    pub fn is_diff(&self) -> bool {
+        matches!(
+            self,
+            SnapshotMetadataKey::DiffPrevious
+                | SnapshotMetadataKey::DiffAdded
+                | SnapshotMetadataKey::DiffRemoved
+                | SnapshotMetadataKey::DiffUpgraded
+                | SnapshotMetadataKey::DiffChanges
+        )
+    }
+
+    /// Check if the key is computed by the server (from the SBOM, the diff or the alerts)
+    ///
+    /// These keys feed the summaries of the snapshot and can't be removed by the API.
+    pub fn is_computed(&self) -> bool {
+        self.is_diff()
+            || self.to_string().starts_with("security.")
+            || matches!(
+                self,
+                SnapshotMetadataKey::DependenciesTotal
+                    | SnapshotMetadataKey::BomSha
+                    | SnapshotMetadataKey::BomPath
+                    | SnapshotMetadataKey::BomPruned
+                    | SnapshotMetadataKey::BomIngestDuration
+                    | SnapshotMetadataKey::BomErrors
+                    | SnapshotMetadataKey::BomErrorsCount
+            )
+    }
+
+    /// Validate and normalize a value for the key
+    pub fn normalize(&self, value: impl Into<String>) -> Result<String, crate::KonarrError> {
+        let value = value.into().trim().to_string();
+        match self {
+            SnapshotMetadataKey::ScanTool
+            | SnapshotMetadataKey::ScanScope
+            | SnapshotMetadataKey::ContainerStatus
+            | SnapshotMetadataKey::ContainerHealth
+            | SnapshotMetadataKey::SwarmMode => Ok(value.to_lowercase()),
+            SnapshotMetadataKey::ScanToolVersion | SnapshotMetadataKey::ScanAgentVersion => {
+                Ok(value.trim_start_matches('v').to_string())
+            }
+            SnapshotMetadataKey::ContainerStartedAt => chrono::DateTime::parse_from_rfc3339(&value)
+                .map(|v| v.with_timezone(&chrono::Utc).to_rfc3339())
+                .map_err(|_| {
+                    crate::KonarrError::InvalidData(format!(
+                        "Invalid value for `{}`: {}",
+                        self, value
+                    ))
+                }),
+            // Older agents report the ports in the `docker ps` format
+            SnapshotMetadataKey::ContainerPorts => Ok(ContainerPort::to_metadata(
+                ContainerPort::parse_list(&value),
+            )),
+            SnapshotMetadataKey::ContainerNetworks => Ok(containers::networks_metadata(
+                containers::parse_networks(&value),
+            )),
+            SnapshotMetadataKey::SnapshotBaseline => match value.to_lowercase().as_str() {
+                "true" | "1" | "yes" => Ok("true".to_string()),
+                "false" | "0" | "no" => Ok("false".to_string()),
+                _ => Err(crate::KonarrError::InvalidData(format!(
+                    "Invalid value for `{}`: {}",
+                    self, value
+                ))),
+            },
+            SnapshotMetadataKey::ScanDuration
+            | SnapshotMetadataKey::ContainerRestartCount
+            | SnapshotMetadataKey::ContainerUptime
+            | SnapshotMetadataKey::SwarmReplicas
+            | SnapshotMetadataKey::SwarmReplicasRunning => {
+                value.parse::<u64>().map(|v| v.to_string()).map_err(|_| {
+                    crate::KonarrError::InvalidData(format!(
+                        "Invalid value for `{}`: {}",
+                        self, value
+                    ))
+                })
+            }
+            _ => Ok(value),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -308,4 +596,87 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn scan_keys() {
+        let key = SnapshotMetadataKey::from("scan.tool.version");
+        assert_eq!(key, SnapshotMetadataKey::ScanToolVersion);
+        assert!(key.is_scan());
+        assert!(!SnapshotMetadataKey::BomTool.is_scan());
+        assert!(SnapshotMetadataKey::from("scan.scope").is_scan());
+        assert_eq!(
+            SnapshotMetadataKey::ScanScope
+                .normalize("All-Layers")
+                .unwrap(),
+            "all-layers"
+        );
+
+        assert_eq!(
+            SnapshotMetadataKey::ScanTool.normalize(" Syft ").unwrap(),
+            "syft"
+        );
+        assert_eq!(key.normalize("v1.2.3").unwrap(), "1.2.3");
+        assert_eq!(
+            SnapshotMetadataKey::ScanDuration.normalize("1500").unwrap(),
+            "1500"
+        );
+        assert!(SnapshotMetadataKey::ScanDuration.normalize("1.5s").is_err());
+    }
+
+    #[test]
+    fn computed_keys() {
+        assert!(SnapshotMetadataKey::SecurityAlertCritical.is_computed());
+        assert!(SnapshotMetadataKey::SecurityHealth.is_computed());
+        assert!(SnapshotMetadataKey::DependenciesTotal.is_computed());
+        assert!(SnapshotMetadataKey::DiffAdded.is_computed());
+        assert!(!SnapshotMetadataKey::ContainerLicenses.is_computed());
+        assert!(!SnapshotMetadataKey::BomTool.is_computed());
+    }
+
+    #[test]
+    fn container_runtime_keys() {
+        assert_eq!(
+            SnapshotMetadataKey::from("container.restart_count"),
+            SnapshotMetadataKey::ContainerRestartCount
+        );
+        assert_eq!(
+            SnapshotMetadataKey::ContainerHealth
+                .normalize("Unhealthy")
+                .unwrap(),
+            "unhealthy"
+        );
+        assert_eq!(
+            SnapshotMetadataKey::ContainerStartedAt
+                .normalize("2024-10-01T12:00:00.123456789Z")
+                .unwrap(),
+            "2024-10-01T12:00:00.123456789+00:00"
+        );
+        assert!(SnapshotMetadataKey::ContainerRestartCount
+            .normalize("many")
+            .is_err());
+        assert_eq!(
+            SnapshotMetadataKey::from("container.uptime"),
+            SnapshotMetadataKey::ContainerUptime
+        );
+        assert_eq!(
+            SnapshotMetadataKey::ContainerUptime
+                .normalize(" 3600 ")
+                .unwrap(),
+            "3600"
+        );
+
+        // Ports reported by older agents are stored as JSON
+        assert_eq!(
+            SnapshotMetadataKey::ContainerPorts
+                .normalize("443/tcp,0.0.0.0:8080->80/tcp")
+                .unwrap(),
+            r#"[{"protocol":"tcp","port":80,"host_ip":"0.0.0.0","host_port":8080},{"protocol":"tcp","port":443}]"#
+        );
+        assert_eq!(
+            SnapshotMetadataKey::from("container.networks")
+                .normalize("frontend, bridge")
+                .unwrap(),
+            "bridge,frontend"
+        );
+    }
 }
@@ -1,27 +1,120 @@
 //! # Snapshot Model
 
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
 
 use chrono::{DateTime, Utc};
 use geekorm::prelude::*;
-use log::{debug, info};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    bom::BillOfMaterials,
+    bom::{github::GitHubSnapshot, sbom::BomComponent, BillOfMaterials, BomProcessors},
     models::{
-        security::{SecuritySeverity, SecurityState},
-        Alerts, Dependencies, ServerSettings,
+        raw_query,
+        security::{
+            policy::ImageReference, HealthThresholds, ProjectHealth, SecuritySeverity,
+            SECURITY_SEVERITY,
+        },
+        Alerts, Component, ComponentEcosystem, ComponentManager, ComponentType, Dependencies,
+        ProjectSnapshots, Projects, ServerSettings, Setting, Transaction, TransactionConnection,
+        VexStatements,
     },
     KonarrError,
 };
 
+pub mod diff;
 pub mod metadata;
+pub mod uploads;
 
+pub use diff::{SnapshotDiff, SnapshotDiffChange, SnapshotDiffKind};
 pub use metadata::{SnapshotMetadata, SnapshotMetadataKey};
+pub use uploads::{SbomUploadResult, SbomUploads, SnapshotSbomSize};
+
+/// Number of dependencies written per transaction when indexing an SBOM
+pub const BOM_INGEST_BATCH_SIZE: usize = 500;
+/// Minutes after which a Snapshot still `Processing` is considered stuck (server crashed)
+pub const SNAPSHOT_PROCESSING_TIMEOUT_MINUTES: i64 = 30;
+
+/// Image label of the container description
+const CONTAINER_LABEL_DESCRIPTION: &str = "org.opencontainers.image.description";
+
+/// Image labels stored as container metadata (same keys as the agent)
+const CONTAINER_LABELS: [(&str, SnapshotMetadataKey); 5] = [
+    (
+        CONTAINER_LABEL_DESCRIPTION,
+        SnapshotMetadataKey::ContainerDescription,
+    ),
+    (
+        "org.opencontainers.image.url",
+        SnapshotMetadataKey::ContainerUrl,
+    ),
+    (
+        "org.opencontainers.image.licenses",
+        SnapshotMetadataKey::ContainerLicenses,
+    ),
+    (
+        "org.opencontainers.image.version",
+        SnapshotMetadataKey::ContainerVersion,
+    ),
+    (
+        "org.opencontainers.image.authors",
+        SnapshotMetadataKey::ContainerAuthor,
+    ),
+];
+
+/// Maximum number of component errors stored in the `bom.errors` metadata
+pub const BOM_ERRORS_SAMPLE_SIZE: usize = 20;
+
+/// Result of indexing an SBOM into a Snapshot
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BomIngest {
+    /// Number of components indexed
+    pub components: usize,
+    /// Number of transactions (batches) the dependencies were written in
+    pub batches: usize,
+    /// Time it took to index the SBOM (in milliseconds)
+    pub duration_ms: u128,
+    /// Number of components which failed to index
+    pub failed: usize,
+    /// Sample of the component errors (at most [BOM_ERRORS_SAMPLE_SIZE])
+    pub errors: Vec<BomComponentError>,
+}
+
+impl BomIngest {
+    /// Percentage of the components which failed to index
+    pub fn failed_percent(&self) -> usize {
+        if self.components == 0 {
+            return 0;
+        }
+        (self.failed * 100) / self.components
+    }
+}
+
+/// Component of an SBOM which failed to index
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BomComponentError {
+    /// Package URL of the component
+    pub purl: String,
+    /// Reason the component failed
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DependencyCountRow {
+    total: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DependencyDirectRow {
+    id: i32,
+    direct: bool,
+}
 
-/// HashMap of Alerts Summary
-pub type AlertsSummary = HashMap<SecuritySeverity, u16>;
+/// Alerts Summary (ordered from the least to the most severe)
+pub type AlertsSummary = BTreeMap<SecuritySeverity, u16>;
 
 /// Snapshot Model
 #[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
@@ -37,6 +130,8 @@ pub struct Snapshot {
     /// Datetime Created
     #[geekorm(new = "Utc::now()")]
     pub created_at: DateTime<Utc>,
+    /// Datetime the state was last changed
+    pub updated_at: Option<DateTime<Utc>>,
 
     /// Components
     #[geekorm(skip)]
@@ -80,6 +175,53 @@ impl Snapshot {
         .await?)
     }
 
+    /// Get a page of Snapshots
+    ///
+    /// With a `scope`, only the snapshots of that project and its children are
+    /// returned (filtered before paginating).
+    pub async fn fetch_page<'a, T>(
+        connection: &'a T,
+        scope: Option<i32>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Self>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let Some(scope) = scope else {
+            return Ok(Snapshot::query(
+                connection,
+                Snapshot::query_select()
+                    .order_by("id", QueryOrder::Asc)
+                    .limit(limit)
+                    .offset(offset)
+                    .build()?,
+            )
+            .await?);
+        };
+
+        let mut values = Values::new();
+        values.push("project_id".to_string(), scope);
+        Ok(T::query::<Snapshot>(
+            connection,
+            raw_query(
+                format!(
+                    "WITH RECURSIVE subtree(id) AS (\
+                    SELECT ? UNION SELECT Projects.id FROM Projects \
+                    INNER JOIN subtree ON Projects.parent = subtree.id) \
+                    SELECT Snapshot.* FROM Snapshot \
+                    WHERE Snapshot.id IN (\
+                    SELECT ProjectSnapshots.snapshot_id FROM ProjectSnapshots \
+                    INNER JOIN subtree ON ProjectSnapshots.project_id = subtree.id) \
+                    ORDER BY Snapshot.id ASC LIMIT {} OFFSET {};",
+                    limit, offset
+                ),
+                values,
+            ),
+        )
+        .await?)
+    }
+
     /// Find or create a new Snapshot from Bill of Materials
     ///
     /// If the snapshot already exists, it will return the existing snapshot.
@@ -88,7 +230,7 @@ impl Snapshot {
         bom: &BillOfMaterials,
     ) -> Result<Self, crate::KonarrError>
     where
-        T: GeekConnection<Connection = T> + 'a,
+        T: GeekConnection<Connection = T> + TransactionConnection + 'a,
     {
         let connection = connection.into();
         // Based on the SHA, check if the snapshot already exists
@@ -101,6 +243,17 @@ impl Snapshot {
                     snap.fetch(connection).await?;
                     snap.fetch_metadata(connection).await?;
 
+                    SbomUploads::record(
+                        connection,
+                        snap.id,
+                        bom.sha.clone(),
+                        None,
+                        None,
+                        SbomUploadResult::Deduplicated,
+                        None,
+                    )
+                    .await?;
+
                     snap
                 }
                 _ => Self::create(connection).await?,
@@ -112,14 +265,42 @@ impl Snapshot {
     }
 
     /// Add Bill of Materials to the Snapshot
+    ///
+    /// The built-in SBOM processors (configured by the `bom.*` settings) are applied
+    /// before the SBOM is indexed.
     pub async fn add_bom<'a, T>(
         &mut self,
         connection: &'a T,
         bom: &BillOfMaterials,
-    ) -> Result<(), crate::KonarrError>
+    ) -> Result<BomIngest, crate::KonarrError>
     where
-        T: GeekConnection<Connection = T> + 'a,
+        T: GeekConnection<Connection = T> + TransactionConnection + 'a,
+    {
+        let processors = BomProcessors::from_settings(connection).await?;
+        self.add_bom_with(connection, bom, &processors).await
+    }
+
+    /// Add Bill of Materials to the Snapshot using a custom list of SBOM processors
+    ///
+    /// Dependencies are written in transactions of [BOM_INGEST_BATCH_SIZE] components
+    /// (one round trip per insert is slow on remote databases) and the time it took is
+    /// stored in the `bom.ingest.duration_ms` metadata.
+    pub async fn add_bom_with<'a, T>(
+        &mut self,
+        connection: &'a T,
+        bom: &BillOfMaterials,
+        processors: &BomProcessors,
+    ) -> Result<BomIngest, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + TransactionConnection + 'a,
     {
+        let started = std::time::Instant::now();
+        let mut bom = bom.clone();
+        if !processors.is_empty() {
+            info!("Applying `{}` SBOM processors", processors.len());
+            processors.apply(&mut bom)?;
+        }
+
         let metadata = vec![
             (SnapshotMetadataKey::BomType, bom.sbom_type.to_string()),
             (SnapshotMetadataKey::BomVersion, bom.version.clone()),
@@ -164,15 +345,8 @@ impl Snapshot {
 
         // Container Metadata
         if let Some(image) = &bom.container.image {
-            // TODO: Assume its from docker.io by default? Latest?
-            SnapshotMetadata::update_or_create(
-                connection,
-                self.id,
-                &SnapshotMetadataKey::ContainerImage,
-                image.clone(),
-            )
-            .await?;
-            // TODO: Parse the image to get the registry, repository, tag
+            self.set_metadata(connection, SnapshotMetadataKey::ContainerImage, image)
+                .await?;
         }
         // TODO: Assume latest?
         if let Some(version) = &bom.container.version {
@@ -184,12 +358,68 @@ impl Snapshot {
             )
             .await?;
         }
+        self.add_container_labels(connection, &bom.container.labels)
+            .await?;
 
-        for comp in bom.components.iter() {
-            // Create dependency from PURL
-            Dependencies::from_bom_compontent(connection, self.id, comp).await?;
+        let mut ingest = BomIngest {
+            components: bom.components.len(),
+            ..Default::default()
+        };
+        let evidence_budget: usize =
+            ServerSettings::fetch_by_name(connection, Setting::BomEvidenceMaxBytes)
+                .await
+                .ok()
+                .and_then(|s| s.value.parse().ok())
+                .unwrap_or_default();
+        for batch in bom.components.chunks(BOM_INGEST_BATCH_SIZE) {
+            self.add_dependencies(connection, batch, evidence_budget, &mut ingest)
+                .await?;
+            ingest.batches += 1;
+        }
+        info!(
+            "Finished indexing {} dependencies ({} batches, {} failed)",
+            ingest.components, ingest.batches, ingest.failed
+        );
+
+        SnapshotMetadata::update_or_create(
+            connection,
+            self.id,
+            &SnapshotMetadataKey::BomErrorsCount,
+            ingest.failed.to_string(),
+        )
+        .await?;
+        if ingest.failed != 0 {
+            SnapshotMetadata::update_or_create(
+                connection,
+                self.id,
+                &SnapshotMetadataKey::BomErrors,
+                serde_json::to_string(&ingest.errors)?,
+            )
+            .await?;
+
+            let max_percent: usize =
+                ServerSettings::fetch_by_name(connection, Setting::BomErrorsMaxPercent)
+                    .await
+                    .ok()
+                    .and_then(|s| s.value.parse().ok())
+                    .unwrap_or(10);
+            if ingest.failed == ingest.components || ingest.failed_percent() > max_percent {
+                return Err(KonarrError::IndexingError(format!(
+                    "{} out of {} components failed to index (first error: {})",
+                    ingest.failed,
+                    ingest.components,
+                    ingest
+                        .errors
+                        .first()
+                        .map(|e| format!("{} - {}", e.purl, e.reason))
+                        .unwrap_or_default()
+                )));
+            }
+            warn!(
+                "Snapshot({}) :: {} out of {} components failed to index",
+                self.id, ingest.failed, ingest.components
+            );
         }
-        info!("Finished indexing dependencies");
 
         if ServerSettings::feature_security(connection).await? {
             info!("Indexing Security Alerts from BillOfMaterials");
@@ -205,11 +435,172 @@ impl Snapshot {
             )
             .await?;
 
+            // VEX statements of the project
+            VexStatements::apply(connection, self).await?;
+
             // Calculate the totals
             info!("Calculating Security Alert Totals");
             self.calculate_alerts_summary(connection).await?;
         }
 
+        // Dependency changes since the previous snapshot of the project
+        if let Err(e) = self.calculate_diff(connection).await {
+            warn!(
+                "Snapshot({}) :: failed to calculate the changes: {}",
+                self.id, e
+            );
+        }
+
+        ingest.duration_ms = started.elapsed().as_millis();
+        SnapshotMetadata::update_or_create(
+            connection,
+            self.id,
+            &SnapshotMetadataKey::BomIngestDuration,
+            ingest.duration_ms.to_string(),
+        )
+        .await?;
+
+        Ok(ingest)
+    }
+
+    /// Add a batch of SBOM components as dependencies in a single transaction
+    ///
+    /// Components which fail are recorded in the ingest result and skipped.
+    async fn add_dependencies<'a, T>(
+        &self,
+        connection: &'a T,
+        components: &[BomComponent],
+        evidence_budget: usize,
+        ingest: &mut BomIngest,
+    ) -> Result<(), crate::KonarrError>
+    where
+        T: TransactionConnection + 'a,
+    {
+        let transaction = Transaction::begin(connection).await?;
+        let connection = transaction.connection();
+
+        for comp in components.iter() {
+            // Create dependency from PURL
+            if let Err(e) = crate::models::with_retries(|| {
+                Dependencies::from_bom_compontent(connection, self.id, comp, evidence_budget)
+            })
+            .await
+            {
+                debug!(
+                    "Snapshot({}) :: failed to index `{}`: {}",
+                    self.id, comp.purl, e
+                );
+                ingest.failed += 1;
+                if ingest.errors.len() < BOM_ERRORS_SAMPLE_SIZE {
+                    ingest.errors.push(BomComponentError {
+                        purl: comp.purl.clone(),
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        transaction.finish(Ok(())).await
+    }
+
+    /// Start processing the Snapshot
+    ///
+    /// Atomically moves a `Created` or `Failed` Snapshot into the `Processing` state
+    /// (compare-and-set), a Snapshot stuck in `Processing` for longer than
+    /// [SNAPSHOT_PROCESSING_TIMEOUT_MINUTES] can be claimed again.
+    /// Returns `false` if the Snapshot is already being processed or completed.
+    pub async fn start_processing<'a, T>(
+        &mut self,
+        connection: &'a T,
+    ) -> Result<bool, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let now = Utc::now();
+        let mut values = Values::new();
+        values.push("state".to_string(), SnapshotState::Processing);
+        values.push("updated_at".to_string(), now);
+        values.push("id".to_string(), self.id);
+        values.push("created".to_string(), SnapshotState::Created);
+        values.push("failed".to_string(), SnapshotState::Failed);
+        values.push("processing".to_string(), SnapshotState::Processing);
+        values.push(
+            "stuck".to_string(),
+            now - chrono::Duration::minutes(SNAPSHOT_PROCESSING_TIMEOUT_MINUTES),
+        );
+
+        let updated = Snapshot::query(
+            connection,
+            Query::new(
+                QueryType::Select,
+                "UPDATE Snapshot SET state = ?, updated_at = ? WHERE id = ? AND \
+                (state IN (?, ?) OR (state = ? AND (updated_at IS NULL OR updated_at < ?))) \
+                RETURNING *;"
+                    .to_string(),
+                values,
+                Values::new(),
+                vec![],
+                Snapshot::table(),
+            ),
+        )
+        .await?;
+
+        if updated.is_empty() {
+            debug!("Snapshot({}) is already being processed", self.id);
+            return Ok(false);
+        }
+        self.state = SnapshotState::Processing;
+        self.updated_at = Some(now);
+        Ok(true)
+    }
+
+    /// Mark the Snapshots stuck in `Processing` (for longer than
+    /// [SNAPSHOT_PROCESSING_TIMEOUT_MINUTES]) as `Failed`, returns the number of snapshots
+    pub async fn fail_stuck<'a, T>(connection: &'a T) -> Result<u64, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let now = Utc::now();
+        let mut values = Values::new();
+        values.push("state".to_string(), SnapshotState::Failed);
+        values.push("updated_at".to_string(), now);
+        values.push("processing".to_string(), SnapshotState::Processing);
+        values.push(
+            "stuck".to_string(),
+            now - chrono::Duration::minutes(SNAPSHOT_PROCESSING_TIMEOUT_MINUTES),
+        );
+
+        let failed = Snapshot::query(
+            connection,
+            raw_query(
+                "UPDATE Snapshot SET state = ?, updated_at = ? WHERE state = ? AND \
+                (updated_at IS NULL OR updated_at < ?) RETURNING *;",
+                values,
+            ),
+        )
+        .await?;
+        for snapshot in failed.iter() {
+            warn!(
+                "Snapshot({}) was stuck processing, marked as failed",
+                snapshot.id
+            );
+        }
+        Ok(failed.len() as u64)
+    }
+
+    /// Set the state of the Snapshot
+    pub async fn set_state<'a, T>(
+        &mut self,
+        connection: &'a T,
+        state: SnapshotState,
+    ) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        debug!("Setting Snapshot({}) state to {:?}", self.id, state);
+        self.state = state;
+        self.updated_at = Some(Utc::now());
+        self.update(connection).await?;
         Ok(())
     }
 
@@ -235,6 +626,201 @@ impl Snapshot {
         .map_err(|e| e.into())
     }
 
+    /// Fetch Dependencies for the Snapshot from a Package Manager
+    pub async fn fetch_dependencies_by_manager<'a, T>(
+        &self,
+        connection: &'a T,
+        manager: &ComponentManager,
+        page: usize,
+        limit: usize,
+    ) -> Result<Vec<Dependencies>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Dependencies::query(
+            connection,
+            Dependencies::query_select()
+                .join(Component::table())
+                .where_eq("snapshot_id", self.id)
+                .and()
+                .where_eq("Component.manager", manager.clone())
+                .limit(limit)
+                .offset(page * limit)
+                .build()?,
+        )
+        .await
+        .map_err(|e| e.into())
+    }
+
+    /// Count the Dependencies for the Snapshot from a Package Manager
+    pub async fn count_dependencies_by_manager<'a, T>(
+        &self,
+        connection: &'a T,
+        manager: &ComponentManager,
+    ) -> Result<usize, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Dependencies::row_count(
+            connection,
+            Dependencies::query_count()
+                .join(Component::table())
+                .where_eq("snapshot_id", self.id)
+                .and()
+                .where_eq("Component.manager", manager.clone())
+                .build()?,
+        )
+        .await? as usize)
+    }
+
+    /// Fetch Dependencies for the Snapshot of a Component Type
+    pub async fn fetch_dependencies_by_component_type<'a, T>(
+        &self,
+        connection: &'a T,
+        component_type: &ComponentType,
+        page: usize,
+        limit: usize,
+    ) -> Result<Vec<Dependencies>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Dependencies::query(
+            connection,
+            Dependencies::query_select()
+                .join(Component::table())
+                .where_eq("snapshot_id", self.id)
+                .and()
+                .where_eq("Component.component_type", component_type.clone())
+                .limit(limit)
+                .offset(page * limit)
+                .build()?,
+        )
+        .await
+        .map_err(|e| e.into())
+    }
+
+    /// Count the Dependencies for the Snapshot of a Component Type
+    pub async fn count_dependencies_by_component_type<'a, T>(
+        &self,
+        connection: &'a T,
+        component_type: &ComponentType,
+    ) -> Result<usize, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Dependencies::row_count(
+            connection,
+            Dependencies::query_count()
+                .join(Component::table())
+                .where_eq("snapshot_id", self.id)
+                .and()
+                .where_eq("Component.component_type", component_type.clone())
+                .build()?,
+        )
+        .await? as usize)
+    }
+
+    /// Fetch the Dependencies (with their component and version) of a Component Type
+    pub async fn fetch_dependencies_by_type<'a, T>(
+        &self,
+        connection: &'a T,
+        component_type: &ComponentType,
+    ) -> Result<Vec<Dependencies>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut dependencies = Dependencies::query(
+            connection,
+            Dependencies::query_select()
+                .join(Component::table())
+                .where_eq("snapshot_id", self.id)
+                .and()
+                .where_eq("Component.component_type", component_type.clone())
+                .build()?,
+        )
+        .await?;
+        for dependency in dependencies.iter_mut() {
+            dependency.fetch(connection).await?;
+        }
+        Ok(dependencies)
+    }
+
+    /// Fetch Dependencies for the Snapshot from an Ecosystem (OS or language packages)
+    pub async fn fetch_dependencies_by_ecosystem<'a, T>(
+        &self,
+        connection: &'a T,
+        ecosystem: &ComponentEcosystem,
+        page: usize,
+        limit: usize,
+    ) -> Result<Vec<Dependencies>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let (filter, mut values) = self.ecosystem_filter(ecosystem);
+        values.push("limit".to_string(), limit);
+        values.push("offset".to_string(), page * limit);
+        Ok(T::query::<Dependencies>(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT Dependencies.* FROM Dependencies \
+                    JOIN Component ON Component.id = Dependencies.component_id \
+                    WHERE {} LIMIT ? OFFSET ?;",
+                    filter
+                ),
+                values,
+            ),
+        )
+        .await?)
+    }
+
+    /// Count the Dependencies for the Snapshot from an Ecosystem
+    pub async fn count_dependencies_by_ecosystem<'a, T>(
+        &self,
+        connection: &'a T,
+        ecosystem: &ComponentEcosystem,
+    ) -> Result<usize, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let (filter, values) = self.ecosystem_filter(ecosystem);
+        let rows = T::query::<DependencyCountRow>(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT COUNT(Dependencies.id) AS total FROM Dependencies \
+                    JOIN Component ON Component.id = Dependencies.component_id \
+                    WHERE {};",
+                    filter
+                ),
+                values,
+            ),
+        )
+        .await?;
+        Ok(rows
+            .first()
+            .map(|row| row.total as usize)
+            .unwrap_or_default())
+    }
+
+    /// `WHERE` clause (and values) for the dependencies of an ecosystem
+    fn ecosystem_filter(&self, ecosystem: &ComponentEcosystem) -> (String, Values) {
+        let managers = ecosystem.managers();
+        let mut values = Values::new();
+        values.push("snapshot_id".to_string(), self.id);
+        // Values are bound by name, each manager needs a unique name
+        for (index, manager) in managers.iter().enumerate() {
+            values.push(format!("manager{}", index), manager.clone());
+        }
+        (
+            format!(
+                "Dependencies.snapshot_id = ? AND Component.manager IN ({})",
+                vec!["?"; managers.len()].join(", ")
+            ),
+            values,
+        )
+    }
+
     /// Find Metadata by Key
     pub fn find_metadata(&self, key: &str) -> Option<&SnapshotMetadata> {
         let key = SnapshotMetadataKey::from_str(key).ok()?;
@@ -257,9 +843,55 @@ impl Snapshot {
     {
         let key = key.into();
         SnapshotMetadata::update_or_create(connection, self.id, &key, value).await?;
+
+        // The registry, repository and tag are derived from the image
+        if key == SnapshotMetadataKey::ContainerImage {
+            if let Some(image) = ImageReference::parse(value) {
+                let tag = match &image.tag {
+                    Some(tag) => Some(tag.clone()),
+                    None if image.is_latest() => Some("latest".to_string()),
+                    None => None,
+                };
+                let derived = [
+                    (
+                        SnapshotMetadataKey::ContainerImageRegistry,
+                        Some(image.registry().to_string()),
+                    ),
+                    (
+                        SnapshotMetadataKey::ContainerImageRepository,
+                        Some(image.repository.clone()),
+                    ),
+                    (SnapshotMetadataKey::ContainerImageTag, tag),
+                ];
+                for (key, value) in derived {
+                    match value {
+                        Some(value) => {
+                            SnapshotMetadata::update_or_create(connection, self.id, &key, value)
+                                .await?;
+                        }
+                        None => {
+                            SnapshotMetadata::delete_key(connection, self.id, &key).await?;
+                        }
+                    }
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Remove a metadata key from the Snapshot (returns if the key existed)
+    pub async fn delete_metadata<'a, T>(
+        &mut self,
+        connection: &'a T,
+        key: &SnapshotMetadataKey,
+    ) -> Result<bool, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        self.metadata.remove(key);
+        SnapshotMetadata::delete_key(connection, self.id, key).await
+    }
+
     /// Fetch Snapshot by ID
     pub async fn fetch_metadata<'a, T>(
         &mut self,
@@ -281,6 +913,31 @@ impl Snapshot {
         Ok(())
     }
 
+    /// Fetch the Projects the Snapshot is linked to
+    ///
+    /// A snapshot is shared by the projects tracking the same container image.
+    pub async fn fetch_projects<'a, T>(
+        &self,
+        connection: &'a T,
+    ) -> Result<Vec<Projects>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("snapshot_id".to_string(), self.id);
+        Ok(T::query::<Projects>(
+            connection,
+            raw_query(
+                "SELECT Projects.* FROM Projects \
+                JOIN ProjectSnapshots ON ProjectSnapshots.project_id = Projects.id \
+                WHERE ProjectSnapshots.snapshot_id = ? \
+                ORDER BY Projects.id ASC;",
+                values,
+            ),
+        )
+        .await?)
+    }
+
     /// Fetch Alerts for the Snapshot
     pub async fn fetch_alerts<'a, T>(
         &mut self,
@@ -316,7 +973,7 @@ impl Snapshot {
         .await? as usize)
     }
 
-    /// Fetch Alerts for the Snapshot with Pagination
+    /// Fetch Alerts for the Snapshot with Pagination (most severe first)
     pub async fn fetch_alerts_page<'a, T>(
         &self,
         connection: &'a T,
@@ -325,19 +982,7 @@ impl Snapshot {
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        let mut alerts = Alerts::query(
-            connection,
-            Alerts::query_select()
-                .where_eq("snapshot_id", self.id)
-                .page(page)
-                .build()?,
-        )
-        .await?;
-
-        for alert in alerts.iter_mut() {
-            alert.fetch(connection).await?;
-        }
-        Ok(alerts)
+        Alerts::fetch_snapshot_page(connection, self.id.into(), None, None, None, None, page).await
     }
 
     /// Calculate a Summary of the Alerts and store in Metadata
@@ -348,22 +993,62 @@ impl Snapshot {
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        let mut summary: HashMap<SecuritySeverity, u16> = HashMap::new();
+        let mut summary = AlertsSummary::new();
+        let mut direct_summary = AlertsSummary::new();
+        let mut transitive_summary = AlertsSummary::new();
+        let mut policy = 0;
+        let mut eol = 0;
+
+        // Direct / transitive dependencies (empty without dependency graph data)
+        let exposure = self.fetch_dependencies_direct(connection).await?;
 
         let mut alerts = Alerts::fetch_by_snapshot_id(connection, self.id).await?;
         log::debug!("Calculating Alert Summary for {} Alerts", alerts.len());
 
         for alert in alerts.iter_mut() {
-            if alert.state != SecurityState::Vulnerable {
+            if !alert.state.is_open() {
+                continue;
+            }
+            // Policy findings are counted separately from the vulnerabilities
+            if alert.is_policy() {
+                policy += 1;
+                continue;
+            }
+            // End-of-life findings are informational (not vulnerabilities)
+            if alert.is_eol() {
+                eol += 1;
                 continue;
             }
             let advisory = alert.fetch_advisory_id(connection).await?;
             let severity = advisory.severity.clone();
 
+            match alert.dependency_id.and_then(|id| exposure.get(&id)) {
+                Some(true) => *direct_summary.entry(severity.clone()).or_insert(0) += 1,
+                Some(false) => *transitive_summary.entry(severity.clone()).or_insert(0) += 1,
+                None => {}
+            }
             *summary.entry(severity).or_insert(0) += 1;
         }
 
         self.calculate_alerts(connection, &summary).await?;
+        if !exposure.is_empty() {
+            self.calculate_alerts_exposure(connection, "direct", &direct_summary)
+                .await?;
+            self.calculate_alerts_exposure(connection, "transitive", &transitive_summary)
+                .await?;
+        }
+        self.set_metadata(
+            connection,
+            SnapshotMetadataKey::SecurityPolicyTotal,
+            &policy.to_string(),
+        )
+        .await?;
+        self.set_metadata(
+            connection,
+            SnapshotMetadataKey::SecurityEolTotal,
+            &eol.to_string(),
+        )
+        .await?;
         Ok(summary)
     }
 
@@ -371,7 +1056,7 @@ impl Snapshot {
     pub async fn calculate_alerts<'a, T>(
         &mut self,
         connection: &'a T,
-        summary: &HashMap<SecuritySeverity, u16>,
+        summary: &AlertsSummary,
     ) -> Result<(), KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
@@ -391,13 +1076,368 @@ impl Snapshot {
         debug!("Alert Summary for Snapshot({}): {:?}", self.id, total);
         self.set_metadata(connection, "security.alerts.total", &total.to_string())
             .await?;
+        self.calculate_health(connection, summary, &[]).await?;
 
         Ok(())
     }
+
+    /// Calculate the Health of the Snapshot from the Alert Totals and the health of the
+    /// children (the worst of both)
+    ///
+    /// The thresholds of the first project the snapshot is linked to are used (the server
+    /// settings if it is not linked to a project).
+    pub async fn calculate_health<'a, T>(
+        &mut self,
+        connection: &'a T,
+        summary: &AlertsSummary,
+        children: &[ProjectHealth],
+    ) -> Result<ProjectHealth, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let project = ProjectSnapshots::query_first(
+            connection,
+            ProjectSnapshots::query_select()
+                .where_eq("snapshot_id", self.id)
+                .order_by("project_id", QueryOrder::Asc)
+                .build()?,
+        )
+        .await
+        .ok();
+        let thresholds = match project {
+            Some(link) => {
+                HealthThresholds::load_project(connection, link.project_id.into()).await?
+            }
+            None => HealthThresholds::load(connection).await?,
+        };
+
+        let health = thresholds.health(summary, children);
+        debug!("Health of Snapshot({}): {}", self.id, health);
+        let key = SnapshotMetadataKey::SecurityHealth;
+        let metadata =
+            SnapshotMetadata::update_or_create(connection, self.id, &key, health.to_string())
+                .await?;
+        self.metadata.insert(key, metadata);
+        Ok(health)
+    }
+
+    /// Health of the Snapshot (`security.health` metadata, green if not calculated)
+    pub fn health(&self) -> ProjectHealth {
+        self.find_metadata("security.health")
+            .map(|health| ProjectHealth::from(health.as_string()))
+            .unwrap_or_default()
+    }
+
+    /// Calculate the Alert Totals for the direct or transitive dependencies
+    ///
+    /// Every severity is stored (`security.alerts.<severity>.<exposure>`) so severities
+    /// without alerts are reset.
+    async fn calculate_alerts_exposure<'a, T>(
+        &mut self,
+        connection: &'a T,
+        exposure: &str,
+        summary: &AlertsSummary,
+    ) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut total = 0;
+        for severity in SECURITY_SEVERITY {
+            let count = summary
+                .get(&SecuritySeverity::from(severity))
+                .copied()
+                .unwrap_or_default();
+            self.set_metadata(
+                connection,
+                &format!("security.alerts.{}.{}", severity.to_lowercase(), exposure),
+                &count.to_string(),
+            )
+            .await?;
+            total += count;
+        }
+        self.set_metadata(
+            connection,
+            &format!("security.alerts.total.{}", exposure),
+            &total.to_string(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Fetch if the dependencies are direct dependencies of the main component (by ID)
+    ///
+    /// Dependencies without dependency graph data are not included.
+    pub async fn fetch_dependencies_direct<'a, T>(
+        &self,
+        connection: &'a T,
+    ) -> Result<HashMap<i32, bool>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("snapshot_id".to_string(), self.id);
+        Ok(T::query::<DependencyDirectRow>(
+            connection,
+            raw_query(
+                "SELECT id, direct FROM Dependencies \
+                WHERE snapshot_id = ? AND direct IS NOT NULL;",
+                values,
+            ),
+        )
+        .await?
+        .into_iter()
+        .map(|row| (row.id, row.direct))
+        .collect())
+    }
+
+    /// Export the dependencies in the GitHub dependency submission format
+    ///
+    /// The manifest is named after the container image (or scan target), the `sha` and
+    /// `ref` of the snapshot need to be set by the caller. Dependencies GitHub does not
+    /// support are skipped and counted in the `konarr.skipped` metadata.
+    pub async fn export_github<'a, T>(
+        &self,
+        connection: &'a T,
+    ) -> Result<GitHubSnapshot, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let name = [
+            SnapshotMetadataKey::ContainerImage,
+            SnapshotMetadataKey::ScanTarget,
+        ]
+        .iter()
+        .find_map(|key| self.metadata.get(key).map(|value| value.as_string()))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| format!("snapshot-{}", self.id));
+
+        let mut export = GitHubSnapshot::new(format!("konarr:{}", name), self.id.to_string());
+        export.scanned = self.created_at;
+
+        let mut dependencies = Dependencies::query(
+            connection,
+            Dependencies::query_select()
+                .where_eq("snapshot_id", self.id)
+                .build()?,
+        )
+        .await?;
+        let mut skipped = 0;
+        let manifest = export.manifest(name);
+        for dependency in dependencies.iter_mut() {
+            dependency.fetch(connection).await?;
+            if !manifest.add_dependency(
+                &dependency.manager().to_string(),
+                dependency.namespace().as_deref(),
+                &dependency.name(),
+                dependency.version().as_deref(),
+                dependency.direct,
+                dependency.scope.as_deref(),
+            ) {
+                debug!(
+                    "Skipping dependency not supported by GitHub: {}",
+                    dependency.display_name()
+                );
+                skipped += 1;
+            }
+        }
+        if skipped > 0 {
+            warn!(
+                "Snapshot({}) :: {} dependencies are not supported by GitHub",
+                self.id, skipped
+            );
+            export.add_skipped(skipped);
+        }
+        Ok(export)
+    }
+
+    /// Store the well-known image labels of the SBOM as container metadata
+    ///
+    /// Uses the same keys as the agent (which reads the labels from Docker) so SBOMs
+    /// uploaded directly get the same details. The description is also set on the
+    /// projects of the snapshot which do not have one.
+    async fn add_container_labels<'a, T>(
+        &self,
+        connection: &'a T,
+        labels: &BTreeMap<String, String>,
+    ) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        for (label, key) in CONTAINER_LABELS {
+            if let Some(value) = labels.get(label).filter(|value| !value.is_empty()) {
+                SnapshotMetadata::update_or_create(connection, self.id, &key, value.clone())
+                    .await?;
+            }
+        }
+
+        let Some(description) = labels
+            .get(CONTAINER_LABEL_DESCRIPTION)
+            .filter(|description| !description.is_empty())
+        else {
+            return Ok(());
+        };
+        let mut values = Values::new();
+        values.push("snapshot_id".to_string(), self.id);
+        let projects = T::query::<Projects>(
+            connection,
+            raw_query(
+                "SELECT Projects.* FROM Projects \
+                JOIN ProjectSnapshots ON ProjectSnapshots.project_id = Projects.id \
+                WHERE ProjectSnapshots.snapshot_id = ? \
+                AND (Projects.description IS NULL OR Projects.description = '');",
+                values,
+            ),
+        )
+        .await?;
+        for mut project in projects {
+            if project.sync_labels(None, Some(description.clone())) {
+                debug!(
+                    "Project({}) :: description from the image labels",
+                    project.id
+                );
+                project.update(connection).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Regenerate a CycloneDX (v1.6) SBOM from the indexed dependencies
+    ///
+    /// Used when the stored SBOM is not available (removed by the retention), only the
+    /// data Konarr indexed is included (marked with the `konarr:regenerated` property).
+    pub async fn export_cyclonedx<'a, T>(
+        &self,
+        connection: &'a T,
+    ) -> Result<serde_json::Value, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let name = [
+            SnapshotMetadataKey::ContainerImage,
+            SnapshotMetadataKey::ScanTarget,
+        ]
+        .iter()
+        .find_map(|key| self.metadata.get(key).map(|value| value.as_string()))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| format!("snapshot-{}", self.id));
+
+        let mut dependencies = Dependencies::query(
+            connection,
+            Dependencies::query_select()
+                .where_eq("snapshot_id", self.id)
+                .order_by("id", QueryOrder::Asc)
+                .build()?,
+        )
+        .await?;
+        let mut components = Vec::with_capacity(dependencies.len());
+        for dependency in dependencies.iter_mut() {
+            dependency.fetch(connection).await?;
+            let comp_type = match dependency.component_type() {
+                ComponentType::Application => "application",
+                ComponentType::Framework => "framework",
+                ComponentType::OperatingSystem => "operating-system",
+                ComponentType::Container => "container",
+                ComponentType::Firmware => "firmware",
+                _ => "library",
+            };
+            let mut component = serde_json::json!({
+                "bom-ref": dependency.purl(),
+                "type": comp_type,
+                "name": dependency.name(),
+                "purl": dependency.purl(),
+            });
+            if let Some(version) = dependency.version() {
+                component["version"] = version.into();
+            }
+            if let Some(scope) = dependency.scope.as_ref() {
+                component["scope"] = scope.clone().into();
+            }
+            components.push(component);
+        }
+
+        Ok(serde_json::json!({
+            "$schema": "http://cyclonedx.org/schema/bom-1.6.schema.json",
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.6",
+            "version": 1,
+            "metadata": {
+                "timestamp": self.created_at,
+                "tools": {
+                    "components": [
+                        { "type": "application", "name": "konarr", "version": crate::KONARR_VERSION }
+                    ]
+                },
+                "component": { "bom-ref": name, "type": "container", "name": name },
+                "properties": [{ "name": "konarr:regenerated", "value": "true" }]
+            },
+            "components": components,
+        }))
+    }
+
+    /// Remove the Snapshot with its dependencies, metadata, alerts and uploads
+    ///
+    /// The snapshot is also removed from every project, the stored SBOM file is kept.
+    pub async fn purge<'a, T>(&self, connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: TransactionConnection + 'a,
+    {
+        debug!("Removing Snapshot({})", self.id);
+        let transaction = Transaction::begin(connection).await?;
+        let result = self.purge_inner(transaction.connection()).await;
+        transaction.finish(result).await
+    }
+
+    async fn purge_inner<'a, T>(&self, connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        for query in [
+            "DELETE FROM Alerts WHERE snapshot_id = ?;",
+            "DELETE FROM VexStatements WHERE snapshot_id = ?;",
+            "DELETE FROM Dependencies WHERE snapshot_id = ?;",
+            "DELETE FROM SnapshotMetadata WHERE snapshot_id = ?;",
+            "DELETE FROM SbomUploads WHERE snapshot_id = ?;",
+            "DELETE FROM ProjectSnapshots WHERE snapshot_id = ?;",
+            "DELETE FROM Snapshot WHERE id = ?;",
+        ] {
+            let mut values = Values::new();
+            values.push("snapshot_id".to_string(), self.id);
+            T::execute::<Snapshot>(connection, raw_query(query, values)).await?;
+        }
+        Ok(())
+    }
+
+    /// Find the latest completed Snapshot scanned from a container image digest
+    ///
+    /// Uses the `scan.container.sha` metadata (the image the SBOM was generated from).
+    pub async fn find_by_container_sha<'a, T>(
+        connection: &'a T,
+        sha: impl Into<String>,
+    ) -> Result<Option<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("state".to_string(), SnapshotState::Completed);
+        values.push("key".to_string(), SnapshotMetadataKey::ScanContainerSha);
+        values.push("sha".to_string(), sha.into());
+
+        let mut snapshots = T::query::<Snapshot>(
+            connection,
+            raw_query(
+                "SELECT * FROM Snapshot WHERE state = ? AND id IN \
+                (SELECT snapshot_id FROM SnapshotMetadata WHERE key = ? AND CAST(value AS TEXT) = ?) \
+                ORDER BY id DESC LIMIT 1;",
+                values,
+            ),
+        )
+        .await?;
+        Ok(snapshots.pop())
+    }
 }
 
 /// Snapshot State
-#[derive(Data, Debug, Clone, Default)]
+#[derive(Data, Debug, Clone, Default, PartialEq)]
 pub enum SnapshotState {
     /// Snapshot Created (but not processed)
     #[default]
@@ -409,3 +1449,396 @@ pub enum SnapshotState {
     /// Snapshot Failed (error during processing)
     Failed,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bom::sbom::BomEvidence;
+
+    #[tokio::test]
+    async fn test_start_processing() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut snapshot = Snapshot::create(&connection).await?;
+        assert!(snapshot.start_processing(&connection).await?);
+        assert_eq!(snapshot.state, SnapshotState::Processing);
+
+        // Second upload is rejected while processing
+        let mut other = Snapshot::fetch_by_primary_key(&connection, snapshot.id).await?;
+        assert!(!other.start_processing(&connection).await?);
+
+        // Re-upload is allowed after failing
+        snapshot
+            .set_state(&connection, SnapshotState::Failed)
+            .await?;
+        assert!(other.start_processing(&connection).await?);
+
+        // A completed snapshot can't be claimed again
+        other
+            .set_state(&connection, SnapshotState::Completed)
+            .await?;
+        assert!(!snapshot.start_processing(&connection).await?);
+
+        // Stuck processing (the server stopped during the upload)
+        let mut stuck = Snapshot::create(&connection).await?;
+        assert!(stuck.start_processing(&connection).await?);
+        assert_eq!(Snapshot::fail_stuck(&connection).await?, 0);
+        stuck.updated_at =
+            Some(Utc::now() - chrono::Duration::minutes(SNAPSHOT_PROCESSING_TIMEOUT_MINUTES + 1));
+        stuck.update(&connection).await?;
+        assert!(stuck.start_processing(&connection).await?);
+
+        stuck.updated_at =
+            Some(Utc::now() - chrono::Duration::minutes(SNAPSHOT_PROCESSING_TIMEOUT_MINUTES + 1));
+        stuck.update(&connection).await?;
+        assert_eq!(Snapshot::fail_stuck(&connection).await?, 1);
+        let stuck = Snapshot::fetch_by_primary_key(&connection, stuck.id).await?;
+        assert_eq!(stuck.state, SnapshotState::Failed);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_metadata() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut snapshot = Snapshot::create(&connection).await?;
+        snapshot
+            .set_metadata(&connection, SnapshotMetadataKey::ContainerLicenses, "bogus")
+            .await?;
+        snapshot
+            .set_metadata(&connection, SnapshotMetadataKey::ContainerImage, "nginx")
+            .await?;
+        snapshot.fetch_metadata(&connection).await?;
+
+        let key = SnapshotMetadataKey::ContainerLicenses;
+        assert!(snapshot.delete_metadata(&connection, &key).await?);
+        assert!(!snapshot.metadata.contains_key(&key));
+        // Already removed
+        assert!(!snapshot.delete_metadata(&connection, &key).await?);
+
+        let mut fetched = Snapshot::fetch_by_primary_key(&connection, snapshot.id).await?;
+        fetched.fetch_metadata(&connection).await?;
+        assert!(!fetched.metadata.contains_key(&key));
+        assert!(fetched
+            .metadata
+            .contains_key(&SnapshotMetadataKey::ContainerImage));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_projects() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let snapshot = Snapshot::create(&connection).await?;
+        assert!(snapshot.fetch_projects(&connection).await?.is_empty());
+
+        let mut first = Projects::new("ci/web", crate::models::ProjectType::Container);
+        first.save(&connection).await?;
+        let mut second = Projects::new("homelab/web", crate::models::ProjectType::Container);
+        second.save(&connection).await?;
+        second.add_snapshot(&connection, snapshot.clone()).await?;
+        first.add_snapshot(&connection, snapshot.clone()).await?;
+
+        let projects = snapshot.fetch_projects(&connection).await?;
+        assert_eq!(
+            projects.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(),
+            vec!["ci/web", "homelab/web"]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_bom_container_labels() -> Result<(), KonarrError> {
+        use crate::bom::{BomParser, Parsers};
+        use crate::models::ProjectType;
+
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("ci/nginx-demo", ProjectType::Container);
+        project.save(&connection).await?;
+        let mut described = Projects::new("ci/other", ProjectType::Container);
+        described.description = Some("Described by a user".to_string());
+        described.save(&connection).await?;
+
+        // SBOM uploaded without the agent (labels only in the SBOM)
+        let bom = Parsers::parse(include_bytes!(
+            "../../../bom/testdata/syft-nginx-labels.cdx.json"
+        ))?;
+        let mut snapshot = Snapshot::create(&connection).await?;
+        project.add_snapshot(&connection, snapshot.clone()).await?;
+        described
+            .add_snapshot(&connection, snapshot.clone())
+            .await?;
+        snapshot.add_bom(&connection, &bom).await?;
+
+        snapshot.fetch_metadata(&connection).await?;
+        for (key, value) in [
+            ("container.url", "https://github.com/42ByteLabs/nginx-demo"),
+            ("container.licenses", "Apache-2.0"),
+            ("container.version", "1.27.2"),
+            ("container.authors", "42ByteLabs"),
+            ("container.description", "NGINX demo image"),
+        ] {
+            assert_eq!(
+                snapshot.find_metadata(key).map(|m| m.as_string()),
+                Some(value.to_string()),
+                "{}",
+                key
+            );
+        }
+
+        // The description is only set on projects without one
+        let project = Projects::fetch_by_primary_key(&connection, project.id).await?;
+        assert_eq!(project.description.as_deref(), Some("NGINX demo image"));
+        let described = Projects::fetch_by_primary_key(&connection, described.id).await?;
+        assert_eq!(
+            described.description.as_deref(),
+            Some("Described by a user")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_bom_batches() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+        let connection = crate::models::testing::CountingConnection::new(connection);
+
+        let mut bom =
+            BillOfMaterials::new(crate::bom::sbom::BomType::CycloneDX_1_6, "1.6".to_string());
+        bom.components = (0..10_000)
+            .map(|i| BomComponent::from_purl(format!("pkg:npm/package-{}@1.0.{}", i, i % 10)))
+            .collect();
+
+        let mut snapshot = Snapshot::create(&connection).await?;
+        connection.take();
+        let ingest = snapshot
+            .add_bom_with(&connection, &bom, &BomProcessors::default())
+            .await?;
+
+        // One transaction per batch
+        assert_eq!(ingest.components, 10_000);
+        assert_eq!(ingest.batches, 10_000 / BOM_INGEST_BATCH_SIZE);
+        assert_eq!(connection.statements("BEGIN;"), ingest.batches);
+        assert_eq!(connection.statements("COMMIT;"), ingest.batches);
+        assert_eq!(connection.statements("ROLLBACK;"), 0);
+        assert_eq!(
+            Dependencies::row_count(
+                &connection,
+                Dependencies::query_count()
+                    .where_eq("snapshot_id", snapshot.id)
+                    .build()?
+            )
+            .await?,
+            10_000
+        );
+
+        snapshot.fetch_metadata(&connection).await?;
+        assert_eq!(
+            snapshot.find_metadata_usize("bom.ingest.duration_ms") as u128,
+            ingest.duration_ms
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_bom_component_errors() -> Result<(), KonarrError> {
+        use crate::bom::BomParser;
+
+        let connection = crate::models::database_test().await?;
+
+        // 2 out of 10 components have broken PURLs
+        let bom = crate::bom::cyclonedx::spec_v1_6::Bom::parse(include_bytes!(
+            "../../../bom/testdata/broken-purls.cdx.json"
+        ))?;
+        let max_percent = |value: &'static str| {
+            let connection = connection.clone();
+            async move {
+                ServerSettings::fetch_by_name(&connection, Setting::BomErrorsMaxPercent)
+                    .await?
+                    .set_update(&connection, value)
+                    .await
+            }
+        };
+
+        max_percent("25").await?;
+        let mut snapshot = Snapshot::create(&connection).await?;
+        let ingest = snapshot
+            .add_bom_with(&connection, &bom, &BomProcessors::default())
+            .await?;
+        assert_eq!(ingest.components, 10);
+        assert_eq!(ingest.failed, 2);
+        assert_eq!(ingest.errors[0].purl, "not-a-purl@1.0.0");
+        assert_eq!(
+            Dependencies::fetch_by_snapshot_id(&connection, snapshot.id)
+                .await?
+                .len(),
+            8
+        );
+
+        snapshot.fetch_metadata(&connection).await?;
+        assert_eq!(snapshot.find_metadata_usize("bom.errors.count"), 2);
+        let errors: Vec<BomComponentError> = serde_json::from_str(
+            &snapshot
+                .find_metadata("bom.errors")
+                .map(|m| m.as_string())
+                .unwrap_or_default(),
+        )?;
+        assert_eq!(errors, ingest.errors);
+
+        // Over the threshold
+        max_percent("10").await?;
+        let mut snapshot = Snapshot::create(&connection).await?;
+        assert!(snapshot
+            .add_bom_with(&connection, &bom, &BomProcessors::default())
+            .await
+            .is_err());
+        snapshot.fetch_metadata(&connection).await?;
+        assert_eq!(snapshot.find_metadata_usize("bom.errors.count"), 2);
+
+        // Nothing indexed
+        max_percent("100").await?;
+        let mut broken = bom.clone();
+        broken
+            .components
+            .retain(|c| ingest.errors.iter().any(|e| e.purl == c.purl));
+        assert!(Snapshot::create(&connection)
+            .await?
+            .add_bom_with(&connection, &broken, &BomProcessors::default())
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_bom_evidence() -> Result<(), KonarrError> {
+        use crate::bom::BomParser;
+
+        let connection = crate::models::database_test().await?;
+
+        let bom = crate::bom::cyclonedx::spec_v1_6::Bom::parse(include_bytes!(
+            "../../../bom/testdata/syft-alpine.cdx.json"
+        ))?;
+        ServerSettings::fetch_by_name(&connection, Setting::BomEvidenceMaxBytes)
+            .await?
+            .set_update(&connection, "100")
+            .await?;
+
+        let mut snapshot = Snapshot::create(&connection).await?;
+        snapshot
+            .add_bom_with(&connection, &bom, &BomProcessors::default())
+            .await?;
+
+        let deps = Dependencies::fetch_by_snapshot_id(&connection, snapshot.id).await?;
+        assert_eq!(deps.len(), 3);
+        let evidence: Vec<Option<BomEvidence>> = deps.iter().map(|d| d.evidence()).collect();
+        assert_eq!(
+            evidence[0].as_ref().unwrap().found_by.as_deref(),
+            Some("apk-db-cataloger")
+        );
+        // Truncated to the budget
+        assert_eq!(
+            evidence[1].as_ref().unwrap().paths,
+            vec!["/usr/local/bin/docker"]
+        );
+        assert!(deps[1].evidence.as_ref().unwrap().len() <= 100);
+        assert!(evidence[2].is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dependencies_by_ecosystem() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let snapshot = Snapshot::create(&connection).await?;
+        for purl in [
+            "pkg:deb/debian/zlib1g@1.2.13",
+            "pkg:apk/alpine/musl@1.2.4",
+            "pkg:npm/express@4.19.2",
+        ] {
+            let component = crate::bom::sbom::BomComponent {
+                direct: Some(purl.starts_with("pkg:npm")),
+                ..crate::bom::sbom::BomComponent::from_purl(purl.to_string())
+            };
+            Dependencies::from_bom_compontent(&connection, snapshot.id, &component, 1024).await?;
+        }
+
+        let os = ComponentEcosystem::Os;
+        assert_eq!(
+            snapshot
+                .count_dependencies_by_ecosystem(&connection, &os)
+                .await?,
+            2
+        );
+        let deps = snapshot
+            .fetch_dependencies_by_ecosystem(&connection, &os, 0, 1)
+            .await?;
+        assert_eq!(deps.len(), 1);
+
+        let mut deps = snapshot
+            .fetch_dependencies_by_ecosystem(&connection, &ComponentEcosystem::Language, 0, 10)
+            .await?;
+        assert_eq!(deps.len(), 1);
+        deps[0].fetch(&connection).await?;
+        assert_eq!(deps[0].name(), "express");
+        assert_eq!(deps[0].direct, Some(true));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_github() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut snapshot = Snapshot::create(&connection).await?;
+        snapshot
+            .set_metadata(
+                &connection,
+                SnapshotMetadataKey::ContainerImage,
+                "ghcr.io/42bytelabs/web:1.0",
+            )
+            .await?;
+        for (purl, direct, scope) in [
+            ("pkg:npm/express@4.18.2", Some(true), Some("required")),
+            ("pkg:pypi/requests@2.31.0", Some(false), None),
+            ("pkg:deb/debian/openssl@3.0.11", None, None),
+            ("pkg:generic/busybox@1.36.1", None, None),
+        ] {
+            let mut dependency = Dependencies::from_purl(&connection, purl.to_string()).await?;
+            dependency.snapshot_id = snapshot.id.into();
+            dependency.direct = direct;
+            dependency.scope = scope.map(|s| s.to_string());
+            dependency.save(&connection).await?;
+        }
+        snapshot.fetch_metadata(&connection).await?;
+
+        let export = snapshot.export_github(&connection).await?;
+        assert_eq!(export.job.correlator, "konarr:ghcr.io/42bytelabs/web:1.0");
+        assert_eq!(export.job.id, snapshot.id.to_string());
+        assert_eq!(export.scanned, snapshot.created_at);
+        assert_eq!(export.skipped(), 2);
+
+        let manifest = export
+            .manifests
+            .get("ghcr.io/42bytelabs/web:1.0")
+            .expect("manifest keyed by the container image");
+        let mut purls: Vec<(&str, Option<&str>, Option<&str>)> = manifest
+            .resolved
+            .values()
+            .map(|dep| {
+                (
+                    dep.package_url.as_str(),
+                    dep.relationship.as_deref(),
+                    dep.scope.as_deref(),
+                )
+            })
+            .collect();
+        purls.sort();
+        assert_eq!(
+            purls,
+            vec![
+                ("pkg:npm/express@4.18.2", Some("direct"), Some("runtime")),
+                ("pkg:pypi/requests@2.31.0", Some("indirect"), None),
+            ]
+        );
+        Ok(())
+    }
+}
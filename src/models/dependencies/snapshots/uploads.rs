@@ -0,0 +1,204 @@
+//! # SBOM Upload History
+//!
+//! Audit trail of every SBOM uploaded to a snapshot (including failed and deduplicated uploads).
+
+use chrono::{DateTime, Utc};
+use geekorm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Snapshot;
+use crate::models::raw_query;
+
+/// Outcome of an SBOM upload
+#[derive(Data, Debug, Default, Clone, PartialEq)]
+pub enum SbomUploadResult {
+    /// SBOM was parsed and added to the snapshot
+    #[default]
+    Success,
+    /// SBOM failed to be read, parsed or indexed
+    Failed,
+    /// SBOM matched an existing snapshot (same SHA)
+    Deduplicated,
+}
+
+/// Size of the SBOM stored for a snapshot
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SnapshotSbomSize {
+    /// Snapshot ID
+    pub snapshot_id: i32,
+    /// Size of the largest successful upload
+    pub size_bytes: i64,
+}
+
+/// SBOM Uploads Model
+#[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SbomUploads {
+    /// Primary Key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+
+    /// Snapshot the SBOM was uploaded to
+    #[geekorm(foreign_key = "Snapshot.id")]
+    pub snapshot_id: ForeignKey<i32, Snapshot>,
+
+    /// SHA256 of the uploaded data
+    pub sha: String,
+    /// Size of the uploaded data (if known)
+    pub size_bytes: Option<i64>,
+    /// Username of the uploader (if known)
+    pub uploaded_by: Option<String>,
+    /// Name of the uploaded file (multipart uploads)
+    pub filename: Option<String>,
+    /// Datetime of the upload
+    #[geekorm(new = "Utc::now()")]
+    pub uploaded_at: DateTime<Utc>,
+
+    /// Outcome of the upload
+    pub parse_result: SbomUploadResult,
+    /// Error message if the upload failed
+    pub error: Option<String>,
+}
+
+impl SbomUploads {
+    /// Initialise the SBOM Uploads table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::create_table(connection).await?;
+        Ok(())
+    }
+
+    /// Record an upload for a snapshot
+    pub async fn record<'a, T>(
+        connection: &'a T,
+        snapshot: impl Into<PrimaryKey<i32>>,
+        sha: impl Into<String>,
+        size_bytes: Option<usize>,
+        uploaded_by: Option<String>,
+        parse_result: SbomUploadResult,
+        error: Option<String>,
+    ) -> Result<Self, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut upload = SbomUploads {
+            snapshot_id: snapshot.into().into(),
+            sha: sha.into(),
+            size_bytes: size_bytes.map(|s| s as i64),
+            uploaded_by,
+            uploaded_at: Utc::now(),
+            parse_result,
+            error,
+            ..Default::default()
+        };
+        upload.save(connection).await?;
+        Ok(upload)
+    }
+
+    /// Fetch the uploads for a snapshot (newest first)
+    pub async fn fetch_by_snapshot<'a, T>(
+        connection: &'a T,
+        snapshot: impl Into<PrimaryKey<i32>>,
+    ) -> Result<Vec<Self>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let uploads = Self::query(
+            connection,
+            Self::query_select()
+                .where_eq("snapshot_id", snapshot.into())
+                .order_by("id", QueryOrder::Desc)
+                .build()?,
+        )
+        .await?;
+        Ok(uploads)
+    }
+
+    /// Snapshots with the largest (successfully uploaded) SBOMs
+    pub async fn largest<'a, T>(
+        connection: &'a T,
+        limit: u32,
+    ) -> Result<Vec<SnapshotSbomSize>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("parse_result".to_string(), SbomUploadResult::Success);
+        values.push("limit".to_string(), limit as i32);
+
+        let sizes = T::query::<SnapshotSbomSize>(
+            connection,
+            raw_query(
+                "SELECT snapshot_id, MAX(size_bytes) AS size_bytes FROM SbomUploads \
+                    WHERE parse_result = ? AND size_bytes IS NOT NULL \
+                    GROUP BY snapshot_id ORDER BY size_bytes DESC LIMIT ?;",
+                values,
+            ),
+        )
+        .await?;
+        Ok(sizes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_upload_history() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let snapshot = Snapshot::create(&connection).await?;
+
+        SbomUploads::record(
+            &connection,
+            snapshot.id,
+            "",
+            Some(42),
+            Some("konarr-agent".to_string()),
+            SbomUploadResult::Failed,
+            Some("Failed to parse SBOM".to_string()),
+        )
+        .await?;
+        SbomUploads::record(
+            &connection,
+            snapshot.id,
+            "abcdef",
+            Some(1024),
+            Some("konarr-agent".to_string()),
+            SbomUploadResult::Success,
+            None,
+        )
+        .await?;
+
+        let uploads = SbomUploads::fetch_by_snapshot(&connection, snapshot.id).await?;
+        assert_eq!(uploads.len(), 2);
+        assert_eq!(uploads[0].parse_result, SbomUploadResult::Success);
+        assert_eq!(uploads[0].size_bytes, Some(1024));
+        assert_eq!(uploads[1].parse_result, SbomUploadResult::Failed);
+        assert_eq!(uploads[1].error, Some("Failed to parse SBOM".to_string()));
+
+        let other = Snapshot::create(&connection).await?;
+        assert!(SbomUploads::fetch_by_snapshot(&connection, other.id)
+            .await?
+            .is_empty());
+
+        SbomUploads::record(
+            &connection,
+            other.id,
+            "123456",
+            Some(4096),
+            None,
+            SbomUploadResult::Success,
+            None,
+        )
+        .await?;
+        let largest = SbomUploads::largest(&connection, 5).await?;
+        assert_eq!(largest.len(), 2);
+        assert_eq!(largest[0].snapshot_id, i32::from(other.id));
+        assert_eq!(largest[0].size_bytes, 4096);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,543 @@
+//! # Database Migrations
+//!
+//! Konarr compares the tables defined by the models with the tables in the database and
+//! plans the DDL statements needed to bring the database up to date.
+//!
+//! The schema version is stored in the SQLite `user_version` pragma.
+//!
+//! SQLite can not change the constraints of an existing column, so when a column
+//! becomes nullable (or is missing a foreign key) the table is rebuilt (renamed,
+//! re-created and the rows copied).
+
+use geekorm::prelude::*;
+use log::{debug, info};
+use serde::Deserialize;
+
+use super::{
+    raw_query, Advisories, AdvisoriesMetadata, AgentCertificates, AgentTokens, AlertEvents,
+    AlertIgnoreRules, Alerts, AuditLog, Component, ComponentAnnotations, ComponentTags,
+    ComponentVersion, Dependencies, EventsOutbox, ProjectAliases, ProjectSettings,
+    ProjectSnapshots, ProjectTransfers, Projects, Reports, SbomUploads, ServerSettings, Sessions,
+    Snapshot, SnapshotMetadata, TaskRuns, Transaction, TransactionConnection, Users, VexStatements,
+};
+use crate::KonarrError;
+
+/// Current Database Schema Version
+pub const DATABASE_SCHEMA_VERSION: i64 = 25;
+
+/// Foreign keys of nullable columns (`table`, `column`, `references`)
+///
+/// The models only support foreign keys on required columns, these are added to the
+/// `CREATE TABLE` statement of the table.
+const NULLABLE_FOREIGN_KEYS: &[(&str, &str, &str)] =
+    &[("Alerts", "dependency_id", "Dependencies(id)")];
+
+/// Migration Plan
+#[derive(Debug, Clone, Default)]
+pub struct MigrationPlan {
+    /// Current schema version of the database
+    pub current: i64,
+    /// Target schema version
+    pub target: i64,
+    /// DDL statements that need to run
+    pub statements: Vec<MigrationStatement>,
+    /// If the database has no tables yet (created instead of migrated)
+    pub new_database: bool,
+}
+
+/// Single Migration Statement
+#[derive(Debug, Clone)]
+pub struct MigrationStatement {
+    /// Table the statement is for
+    pub table: String,
+    /// Description of the migration
+    pub description: String,
+    /// SQL statement
+    pub sql: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserVersion {
+    user_version: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TableColumn {
+    name: String,
+    #[serde(default)]
+    notnull: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TableForeignKey {
+    from: String,
+}
+
+impl MigrationPlan {
+    /// Plan the migration for the database
+    pub async fn plan<'a, T>(connection: &'a T) -> Result<Self, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut plan = Self {
+            current: Self::schema_version(connection).await?,
+            target: DATABASE_SCHEMA_VERSION,
+            statements: vec![],
+            new_database: true,
+        };
+
+        if plan.current > plan.target {
+            return Err(KonarrError::MigrationError(format!(
+                "Database schema version `{}` is newer than the supported version `{}`, please upgrade Konarr",
+                plan.current, plan.target
+            )));
+        }
+
+        plan.table::<T, ServerSettings>(connection).await?;
+        plan.table::<T, ProjectSettings>(connection).await?;
+        plan.table::<T, Sessions>(connection).await?;
+        plan.table::<T, Users>(connection).await?;
+        plan.table::<T, AgentTokens>(connection).await?;
+        plan.table::<T, AgentCertificates>(connection).await?;
+        plan.table::<T, ComponentVersion>(connection).await?;
+        plan.table::<T, Component>(connection).await?;
+        plan.table::<T, ComponentAnnotations>(connection).await?;
+        plan.table::<T, ComponentTags>(connection).await?;
+        plan.table::<T, Snapshot>(connection).await?;
+        plan.table::<T, SnapshotMetadata>(connection).await?;
+        plan.table::<T, SbomUploads>(connection).await?;
+        plan.table::<T, Dependencies>(connection).await?;
+        plan.table::<T, Advisories>(connection).await?;
+        plan.table::<T, AdvisoriesMetadata>(connection).await?;
+        plan.table::<T, Alerts>(connection).await?;
+        plan.table::<T, AlertIgnoreRules>(connection).await?;
+        plan.table::<T, VexStatements>(connection).await?;
+        plan.table::<T, AlertEvents>(connection).await?;
+        plan.table::<T, Projects>(connection).await?;
+        plan.table::<T, ProjectSnapshots>(connection).await?;
+        plan.table::<T, ProjectTransfers>(connection).await?;
+        plan.table::<T, ProjectAliases>(connection).await?;
+        plan.table::<T, TaskRuns>(connection).await?;
+        plan.table::<T, AuditLog>(connection).await?;
+        plan.table::<T, Reports>(connection).await?;
+        plan.table::<T, EventsOutbox>(connection).await?;
+
+        Ok(plan)
+    }
+
+    /// Check if the database needs to be migrated
+    pub fn is_required(&self) -> bool {
+        !self.statements.is_empty() || self.current != self.target
+    }
+
+    /// Apply the migration plan
+    ///
+    /// The statements run in a single transaction, a failed statement leaves the database
+    /// unchanged.
+    pub async fn apply<'a, T>(&self, connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: TransactionConnection + 'a,
+    {
+        if !self.is_required() {
+            debug!("Database is up to date (v{})", self.current);
+            return Ok(());
+        }
+        info!(
+            "Migrating database from v{} to v{} ({} statements)",
+            self.current,
+            self.target,
+            self.statements.len()
+        );
+
+        let transaction = Transaction::begin(connection).await?;
+        let result = self.apply_statements(transaction.connection()).await;
+        transaction.finish(result).await?;
+        info!("Database migrated to v{}", self.target);
+        Ok(())
+    }
+
+    async fn apply_statements<'a, T>(&self, connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        for statement in self.statements.iter() {
+            debug!("Migration :: {}", statement.sql);
+            T::execute::<TableColumn>(connection, raw_query(&statement.sql, Values::new()))
+                .await
+                .map_err(|e| {
+                    KonarrError::MigrationError(format!(
+                        "Missing migration for `{}` ({}) failed: {}",
+                        statement.table, statement.description, e
+                    ))
+                })?;
+        }
+
+        T::execute::<TableColumn>(
+            connection,
+            raw_query(
+                format!("PRAGMA user_version = {};", self.target),
+                Values::new(),
+            ),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Get the current schema version of the database
+    pub async fn schema_version<'a, T>(connection: &'a T) -> Result<i64, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let version =
+            T::query::<UserVersion>(connection, raw_query("PRAGMA user_version;", Values::new()))
+                .await?;
+        Ok(version.first().map(|v| v.user_version).unwrap_or_default())
+    }
+
+    /// Plan the statements for a single table
+    async fn table<'a, T, M>(&mut self, connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+        M: TableBuilder + QueryBuilderTrait,
+    {
+        let table = M::table();
+        let existing = T::query::<TableColumn>(
+            connection,
+            raw_query(format!("PRAGMA table_info({});", table.name), Values::new()),
+        )
+        .await?;
+
+        if existing.is_empty() {
+            self.statements.push(MigrationStatement {
+                table: table.name.clone(),
+                description: "create table".to_string(),
+                sql: Self::create_statement::<M>()?,
+            });
+            return Ok(());
+        }
+        self.new_database = false;
+
+        let create = M::query_create();
+
+        // Columns which are no longer `NOT NULL` require the table to be rebuilt
+        let mut relaxed = vec![];
+        for column in table.columns.columns.iter() {
+            if column.skip {
+                continue;
+            }
+            if let Some(current) = existing.iter().find(|c| c.name == column.name) {
+                if current.notnull == 1 && !column.on_create(&create)?.contains("NOT NULL") {
+                    relaxed.push(column.name.clone());
+                }
+            }
+        }
+        if !relaxed.is_empty() {
+            self.rebuild::<M>(&existing, format!("nullable {}", relaxed.join(", ")))?;
+            return Ok(());
+        }
+
+        // Foreign keys of nullable columns also require the table to be rebuilt
+        let foreign_keys = T::query::<TableForeignKey>(
+            connection,
+            raw_query(
+                format!("PRAGMA foreign_key_list({});", table.name),
+                Values::new(),
+            ),
+        )
+        .await?;
+        let missing = NULLABLE_FOREIGN_KEYS
+            .iter()
+            .filter(|(name, column, _)| {
+                *name == table.name
+                    && existing.iter().any(|c| c.name == *column)
+                    && !foreign_keys.iter().any(|key| key.from == *column)
+            })
+            .map(|(_, column, _)| *column)
+            .collect::<Vec<&str>>();
+        if !missing.is_empty() {
+            self.rebuild::<M>(&existing, format!("foreign key {}", missing.join(", ")))?;
+            return Ok(());
+        }
+
+        for column in table.columns.columns.iter() {
+            if column.skip || existing.iter().any(|c| c.name == column.name) {
+                continue;
+            }
+            let definition = column.on_create(&create)?.replace(" UNIQUE", "");
+            // SQLite requires a default value when adding a NOT NULL column
+            let definition = if definition.contains("NOT NULL") && !definition.contains("DEFAULT") {
+                if definition.contains("INTEGER") || definition.contains("REAL") {
+                    format!("{} DEFAULT 0", definition)
+                } else {
+                    format!("{} DEFAULT ''", definition)
+                }
+            } else {
+                definition
+            };
+
+            self.statements.push(MigrationStatement {
+                table: table.name.clone(),
+                description: format!("add column `{}`", column.name),
+                sql: format!("ALTER TABLE {} ADD COLUMN {};", table.name, definition),
+            });
+        }
+        Ok(())
+    }
+
+    /// Plan the statements to rebuild a table (keeping the existing rows)
+    fn rebuild<M>(&mut self, existing: &[TableColumn], reason: String) -> Result<(), KonarrError>
+    where
+        M: TableBuilder + QueryBuilderTrait,
+    {
+        let table = M::table();
+        let backup = format!("_{}_old", table.name);
+        let description = format!("rebuild table ({})", reason);
+
+        let columns = table
+            .columns
+            .columns
+            .iter()
+            .filter(|c| !c.skip && existing.iter().any(|e| e.name == c.name))
+            .map(|c| c.name.clone())
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        for sql in [
+            format!("ALTER TABLE {} RENAME TO {};", table.name, backup),
+            Self::create_statement::<M>()?,
+            format!(
+                "INSERT INTO {} ({}) SELECT {} FROM {};",
+                table.name, columns, columns, backup
+            ),
+            format!("DROP TABLE {};", backup),
+        ] {
+            self.statements.push(MigrationStatement {
+                table: table.name.clone(),
+                description: description.clone(),
+                sql,
+            });
+        }
+        Ok(())
+    }
+
+    /// `CREATE TABLE` statement of the model (with the [NULLABLE_FOREIGN_KEYS])
+    fn create_statement<M>() -> Result<String, KonarrError>
+    where
+        M: TableBuilder + QueryBuilderTrait,
+    {
+        let table = M::table();
+        let mut sql = M::query_create().build()?.query;
+        let foreign_keys = NULLABLE_FOREIGN_KEYS
+            .iter()
+            .filter(|(name, _, _)| *name == table.name)
+            .map(|(_, column, references)| {
+                format!(", FOREIGN KEY ({}) REFERENCES {}", column, references)
+            })
+            .collect::<String>();
+        if let Some(end) = sql.rfind(')') {
+            sql.insert_str(end, &foreign_keys);
+        }
+        Ok(sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::security::{AdvisorySource, SecuritySeverity};
+
+    #[tokio::test]
+    async fn test_migration_plan() -> Result<(), KonarrError> {
+        let connection = libsql::Builder::new_local(":memory:")
+            .build()
+            .await?
+            .connect()?;
+
+        let plan = MigrationPlan::plan(&connection).await?;
+        assert_eq!(plan.current, 0);
+        assert_eq!(plan.target, DATABASE_SCHEMA_VERSION);
+        assert!(plan
+            .statements
+            .iter()
+            .any(|s| s.table == "Projects" && s.description == "create table"));
+
+        plan.apply(&connection).await?;
+
+        let plan = MigrationPlan::plan(&connection).await?;
+        assert_eq!(plan.current, DATABASE_SCHEMA_VERSION);
+        assert!(!plan.is_required());
+        assert!(!plan.new_database);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_migration_rollback() -> Result<(), KonarrError> {
+        let connection = libsql::Builder::new_local(":memory:")
+            .build()
+            .await?
+            .connect()?;
+
+        let plan = MigrationPlan {
+            current: 0,
+            target: DATABASE_SCHEMA_VERSION,
+            statements: vec![
+                MigrationStatement {
+                    table: "Example".to_string(),
+                    description: "create table".to_string(),
+                    sql: "CREATE TABLE Example (id INTEGER PRIMARY KEY);".to_string(),
+                },
+                MigrationStatement {
+                    table: "Example".to_string(),
+                    description: "add column `name`".to_string(),
+                    sql: "ALTER TABLE Missing ADD COLUMN name TEXT;".to_string(),
+                },
+            ],
+            new_database: true,
+        };
+        assert!(matches!(
+            plan.apply(&connection).await,
+            Err(KonarrError::MigrationError(_))
+        ));
+
+        // Nothing was applied
+        assert_eq!(MigrationPlan::schema_version(&connection).await?, 0);
+        let tables = connection
+            .query(
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'Example';",
+                (),
+            )
+            .await?
+            .next()
+            .await?;
+        assert!(tables.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_database_create_outdated() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        // Database of an older Konarr
+        connection
+            .execute("ALTER TABLE Snapshot DROP COLUMN updated_at;", ())
+            .await?;
+        connection.execute("PRAGMA user_version = 22;", ()).await?;
+
+        assert!(matches!(
+            crate::models::database_create(&connection).await,
+            Err(KonarrError::MigrationError(_))
+        ));
+
+        let plan = MigrationPlan::plan(&connection).await?;
+        assert!(!plan.new_database);
+        plan.apply(&connection).await?;
+        crate::models::database_create(&connection).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_migration_nullable_rebuild() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let snapshot = Snapshot::create(&connection).await?;
+        let mut dependency =
+            Dependencies::from_purl(&connection, "pkg:deb/debian/openssl@3.0.1".to_string())
+                .await?;
+        dependency.snapshot_id = snapshot.id.into();
+        dependency.save(&connection).await?;
+        let mut advisory =
+            Advisories::new("CVE-0001", AdvisorySource::Unknown, SecuritySeverity::High);
+        advisory.save(&connection).await?;
+
+        // Alerts table from schema v1 (the dependency was required)
+        connection
+            .execute_batch(
+                "DROP TABLE Alerts; \
+                CREATE TABLE Alerts (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, \
+                    state TEXT NOT NULL, snapshot_id INTEGER NOT NULL, dependency_id INTEGER NOT NULL, \
+                    advisory_id INTEGER NOT NULL, created_at TEXT NOT NULL, updated_at TEXT NOT NULL); \
+                INSERT INTO Alerts (name, state, snapshot_id, dependency_id, advisory_id, created_at, updated_at) \
+                    SELECT 'CVE-0001', 'Vulnerable', id, 1, 1, created_at, created_at FROM Snapshot; \
+                PRAGMA user_version = 1;",
+            )
+            .await?;
+
+        let plan = MigrationPlan::plan(&connection).await?;
+        let rebuild = plan
+            .statements
+            .iter()
+            .filter(|s| s.table == "Alerts")
+            .collect::<Vec<_>>();
+        assert_eq!(rebuild.len(), 4);
+        assert!(rebuild[0].description.contains("dependency_id"));
+
+        plan.apply(&connection).await?;
+        assert!(!MigrationPlan::plan(&connection).await?.is_required());
+
+        let alerts = Alerts::query(&connection, Alerts::query_select().build()?).await?;
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].dependency_id, Some(1));
+        assert_eq!(
+            alert_foreign_keys(&connection).await?,
+            vec!["dependency_id"]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_migration_foreign_key_rebuild() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+        assert_eq!(
+            alert_foreign_keys(&connection).await?,
+            vec!["dependency_id"]
+        );
+
+        // Alerts table from schema v23 (nullable dependency without a foreign key)
+        connection
+            .execute_batch(
+                "DROP TABLE Alerts; \
+                CREATE TABLE Alerts (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, \
+                    state TEXT NOT NULL, snapshot_id INTEGER NOT NULL, dependency_id INTEGER, \
+                    advisory_id INTEGER NOT NULL, created_at TEXT NOT NULL, updated_at TEXT NOT NULL, \
+                    FOREIGN KEY (snapshot_id) REFERENCES Snapshot(id), \
+                    FOREIGN KEY (advisory_id) REFERENCES Advisories(id)); \
+                PRAGMA user_version = 23;",
+            )
+            .await?;
+
+        let plan = MigrationPlan::plan(&connection).await?;
+        let rebuild = plan
+            .statements
+            .iter()
+            .filter(|s| s.table == "Alerts")
+            .collect::<Vec<_>>();
+        assert_eq!(rebuild.len(), 4);
+        assert!(rebuild[0].description.contains("foreign key dependency_id"));
+
+        plan.apply(&connection).await?;
+        assert!(!MigrationPlan::plan(&connection).await?.is_required());
+        assert_eq!(
+            alert_foreign_keys(&connection).await?,
+            vec!["dependency_id"]
+        );
+        Ok(())
+    }
+
+    /// Columns of the Alerts table with a foreign key (other than the snapshot / advisory)
+    async fn alert_foreign_keys(
+        connection: &libsql::Connection,
+    ) -> Result<Vec<String>, KonarrError> {
+        let keys = <libsql::Connection as GeekConnection>::query::<TableForeignKey>(
+            connection,
+            raw_query("PRAGMA foreign_key_list(Alerts);", Values::new()),
+        )
+        .await?;
+        Ok(keys
+            .into_iter()
+            .map(|key| key.from)
+            .filter(|column| column != "snapshot_id" && column != "advisory_id")
+            .collect())
+    }
+}
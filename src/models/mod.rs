@@ -3,62 +3,425 @@
 use geekorm::prelude::*;
 use log::debug;
 
+pub mod audit;
 pub mod auth;
 pub mod components;
 pub mod dependencies;
+pub mod migrations;
+pub mod outbox;
 pub mod projects;
+pub mod reports;
+pub mod search;
 pub mod security;
+pub mod seed;
 pub mod settings;
+pub mod tasks;
+#[cfg(test)]
+pub(crate) mod testing;
 
+pub use audit::AuditLog;
+pub use auth::certificates::AgentCertificates;
 pub use auth::sessions::{SessionState, SessionType, Sessions};
+pub use auth::tokens::{AgentTokenState, AgentTokens};
 pub use auth::users::{UserRole, Users};
-pub use components::{Component, ComponentManager, ComponentType, ComponentVersion};
-pub use dependencies::snapshots::{Snapshot, SnapshotMetadata, SnapshotMetadataKey};
+pub use components::{
+    Component, ComponentAnnotations, ComponentEcosystem, ComponentManager, ComponentTags,
+    ComponentType, ComponentVersion, ComponentVersionUsage, COMPONENT_MANAGERS,
+};
+pub use dependencies::snapshots::{
+    BomComponentError, BomIngest, SbomUploadResult, SbomUploads, Snapshot, SnapshotDiff,
+    SnapshotMetadata, SnapshotMetadataKey, SnapshotState,
+};
 pub use dependencies::Dependencies;
-pub use projects::{ProjectSnapshots, ProjectStatus, ProjectType, Projects};
+pub use outbox::{EventsOutbox, OutboxState};
+pub use projects::{
+    DuplicateImage, ExposedProject, ProjectAliases, ProjectChild, ProjectChildren,
+    ProjectChildrenSort, ProjectFilters, ProjectMerge, ProjectMergeSummary, ProjectMetrics,
+    ProjectSnapshots, ProjectStatus, ProjectTransfers, ProjectType, Projects,
+    PROJECT_METADATA_FILTERS,
+};
+pub use reports::Reports;
 pub use security::advisories::AdvisoriesMetadata;
-pub use security::{Advisories, Alerts};
-pub use settings::{ServerSettings, Setting};
+pub use security::{Advisories, AlertEvents, AlertIgnoreRules, Alerts, VexStatements};
+pub use settings::{ProjectSetting, ProjectSettings, ServerSettings, Setting};
+pub use tasks::TaskRuns;
 
 use crate::KonarrError;
 
+/// Time (in milliseconds) SQLite waits for a lock before returning `SQLITE_BUSY`
+pub const DATABASE_BUSY_TIMEOUT_MS: u64 = 5000;
+/// Number of retries of a write which failed with a busy / locked error
+pub const DATABASE_BUSY_RETRIES: u32 = 5;
+/// Initial delay between the retries (doubled after each retry)
+const DATABASE_BUSY_BACKOFF: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Configure a new database connection (`busy_timeout`)
+///
+/// Remote databases might not support the pragma, which is only logged.
+pub async fn connection_init(connection: &libsql::Connection) -> Result<(), KonarrError> {
+    // `PRAGMA busy_timeout` returns the timeout (a row), so it can't be executed
+    if let Err(e) = connection
+        .query(
+            &format!("PRAGMA busy_timeout = {};", DATABASE_BUSY_TIMEOUT_MS),
+            (),
+        )
+        .await
+    {
+        log::debug!("Failed to set the database busy timeout: {}", e);
+    }
+    Ok(())
+}
+
+/// Check if a database error message is a SQLite busy / locked error
+pub fn is_database_busy(message: impl AsRef<str>) -> bool {
+    let message = message.as_ref().to_lowercase();
+    message.contains("database is locked")
+        || message.contains("database table is locked")
+        || message.contains("database is busy")
+        || message.contains("sqlite_busy")
+        || message.contains("sqlite_locked")
+}
+
+/// Retry a database write which failed with a busy / locked error
+///
+/// The write is retried up to [DATABASE_BUSY_RETRIES] times with an exponential backoff,
+/// any other error is returned straight away.
+pub async fn with_retries<F, Fut, R>(mut write: F) -> Result<R, KonarrError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<R, KonarrError>>,
+{
+    let mut delay = DATABASE_BUSY_BACKOFF;
+    let mut attempt = 0;
+    loop {
+        match write().await {
+            Err(e) if e.is_database_busy() && attempt < DATABASE_BUSY_RETRIES => {
+                attempt += 1;
+                log::warn!(
+                    "Database is busy, retrying in {}ms ({}/{})",
+                    delay.as_millis(),
+                    attempt,
+                    DATABASE_BUSY_RETRIES
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Connection which can run a [Transaction]
+///
+/// The server shares one connection behind a mutex which is locked for each statement,
+/// a transaction keeps it locked until it is committed or rolled back so the statements
+/// of other requests can't end up in (or be rolled back with) the transaction.
+pub trait TransactionConnection: Send + Sync {
+    /// Connection the statements of the transaction run on
+    type Connection: GeekConnection<Connection = Self::Connection> + Send + Sync;
+    /// Exclusive access to the connection
+    type Guard<'a>: std::ops::Deref<Target = Self::Connection> + Send + Sync
+    where
+        Self: 'a;
+
+    /// Get exclusive access to the connection (waits for the running statements)
+    fn exclusive(&self) -> impl std::future::Future<Output = Self::Guard<'_>> + Send;
+}
+
+impl TransactionConnection for libsql::Connection {
+    type Connection = libsql::Connection;
+    type Guard<'a> = &'a libsql::Connection;
+
+    async fn exclusive(&self) -> Self::Guard<'_> {
+        self
+    }
+}
+
+impl<C> TransactionConnection for std::sync::Arc<tokio::sync::Mutex<C>>
+where
+    C: GeekConnection<Connection = C> + Send + Sync,
+{
+    type Connection = C;
+    type Guard<'a>
+        = tokio::sync::MutexGuard<'a, C>
+    where
+        C: 'a;
+
+    async fn exclusive(&self) -> Self::Guard<'_> {
+        self.lock().await
+    }
+}
+
+/// Database transaction
+///
+/// Holds the connection (see [TransactionConnection]) from `BEGIN` until [Transaction::finish].
+/// Starting a transaction while another one is running on the connection is an error.
+pub struct Transaction<'a, T>
+where
+    T: TransactionConnection + 'a,
+{
+    guard: T::Guard<'a>,
+}
+
+impl<'a, T> Transaction<'a, T>
+where
+    T: TransactionConnection + 'a,
+{
+    /// Lock the connection and start the transaction
+    pub async fn begin(connection: &'a T) -> Result<Self, KonarrError> {
+        let guard = connection.exclusive().await;
+        T::Connection::execute::<TaskRuns>(&guard, raw_query("BEGIN;", Values::new())).await?;
+        Ok(Self { guard })
+    }
+
+    /// Connection to run the statements of the transaction on
+    pub fn connection(&self) -> &T::Connection {
+        &self.guard
+    }
+
+    /// Commit the transaction if the result is ok, roll it back otherwise
+    ///
+    /// A failed rollback is logged, the original error is returned.
+    pub async fn finish<R>(self, result: Result<R, KonarrError>) -> Result<R, KonarrError> {
+        match result {
+            Ok(value) => {
+                T::Connection::execute::<TaskRuns>(
+                    &self.guard,
+                    raw_query("COMMIT;", Values::new()),
+                )
+                .await?;
+                Ok(value)
+            }
+            Err(e) => {
+                if let Err(rollback) = T::Connection::execute::<TaskRuns>(
+                    &self.guard,
+                    raw_query("ROLLBACK;", Values::new()),
+                )
+                .await
+                {
+                    log::error!("Failed to roll back the transaction: {}", rollback);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
 /// Initialize the database with the necessary tables.
+///
+/// A database with an older schema is not migrated (see [migrations::MigrationPlan]), it
+/// is an error.
 pub async fn database_create<'a, T>(connection: &'a T) -> Result<(), KonarrError>
 where
-    T: GeekConnection<Connection = T> + 'a,
+    T: GeekConnection<Connection = T> + TransactionConnection + 'a,
 {
     let connection = connection.into();
+
+    debug!("Checking database migrations");
+    let plan = migrations::MigrationPlan::plan(connection).await?;
+    if plan.is_required() && !plan.new_database {
+        return Err(KonarrError::MigrationError(format!(
+            "Database schema is v{} but v{} is required ({} pending statements), run `konarr-cli database migrate`",
+            plan.current,
+            plan.target,
+            plan.statements.len()
+        )));
+    }
+    plan.apply(connection).await?;
+
     // Session
     debug!("Creating tables");
 
     debug!("Creating Server Settings table");
     ServerSettings::init(connection).await?;
+    debug!("Creating Project Settings table");
+    ProjectSettings::init(connection).await?;
 
     debug!("Creating Sessions table");
     Sessions::create_table(connection).await?;
     // Users
     debug!("Creating Users table");
     Users::create_table(connection).await?;
+    debug!("Creating Agent Tokens table");
+    AgentTokens::init(connection).await?;
+    AgentCertificates::init(connection).await?;
 
     // Components
     debug!("Creating Components table...");
     ComponentVersion::init(connection).await?;
     Component::init(connection).await?;
+    ComponentAnnotations::init(connection).await?;
+    ComponentTags::init(connection).await?;
 
     debug!("Creating Snapshots table...");
     Snapshot::create_table(connection).await?;
     SnapshotMetadata::init(connection).await?;
+    SbomUploads::init(connection).await?;
     debug!("Creating Dependencies table...");
-    Dependencies::create_table(connection).await?;
+    Dependencies::init(connection).await?;
 
     debug!("Security tables...");
     Advisories::create_table(connection).await?;
     AdvisoriesMetadata::create_table(connection).await?;
     Alerts::init(connection).await?;
+    AlertIgnoreRules::init(connection).await?;
+    VexStatements::init(connection).await?;
+    AlertEvents::init(connection).await?;
 
     debug!("Creating Projects tables...");
     Projects::init(connection).await?;
     ProjectSnapshots::create_table(connection).await?;
+    ProjectTransfers::create_table(connection).await?;
+    ProjectAliases::create_table(connection).await?;
+
+    debug!("Creating Task Runs table...");
+    TaskRuns::init(connection).await?;
+    debug!("Creating Audit Log table...");
+    AuditLog::init(connection).await?;
+    debug!("Creating Reports table...");
+    Reports::init(connection).await?;
+    debug!("Creating Events Outbox table...");
+    EventsOutbox::init(connection).await?;
 
     Ok(())
 }
+
+/// Create a new in-memory database with all the tables (test fixture)
+#[cfg(test)]
+pub(crate) async fn database_test() -> Result<libsql::Connection, KonarrError> {
+    let connection = libsql::Builder::new_local(":memory:")
+        .build()
+        .await?
+        .connect()?;
+    database_create(&connection).await?;
+    Ok(connection)
+}
+
+/// Build a raw SQL query (for queries the query builder does not support)
+pub(crate) fn raw_query(sql: impl Into<String>, values: Values) -> Query {
+    Query::new(
+        QueryType::Create,
+        sql.into(),
+        values,
+        Values::new(),
+        vec![],
+        BuilderTable::default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_database_busy() {
+        assert!(is_database_busy("SQLite failure: `database is locked`"));
+        assert!(is_database_busy("SQLITE_BUSY"));
+        assert!(!is_database_busy("UNIQUE constraint failed"));
+
+        let error = KonarrError::GeekOrm(geekorm::Error::LibSQLError(
+            "database is locked".to_string(),
+        ));
+        assert!(error.is_database_busy());
+        assert!(error.is_retryable());
+        assert_eq!(error.status_code(), 503);
+    }
+
+    #[tokio::test]
+    async fn test_nested_transaction() -> Result<(), KonarrError> {
+        let connection = database_test().await?;
+
+        let transaction = Transaction::begin(&connection).await?;
+        assert!(Transaction::begin(&connection).await.is_err());
+        transaction.finish(Ok(())).await?;
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_transaction_shared_connection() -> Result<(), KonarrError> {
+        let connection = std::sync::Arc::new(tokio::sync::Mutex::new(database_test().await?));
+
+        let transaction = Transaction::begin(&connection).await?;
+        ServerSettings::update_statistic_at(
+            transaction.connection(),
+            Setting::StatsProjectsTotal,
+            1,
+            chrono::Utc::now(),
+        )
+        .await?;
+
+        // Another request writing while the transaction is running
+        let shared = connection.clone();
+        let writer = tokio::spawn(async move {
+            ServerSettings::update_statistic_at(
+                &shared,
+                Setting::StatsUsersTotal,
+                42,
+                chrono::Utc::now(),
+            )
+            .await
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(!writer.is_finished());
+
+        let result: Result<(), KonarrError> = Err(KonarrError::InvalidData("rollback".to_string()));
+        assert!(matches!(
+            transaction.finish(result).await,
+            Err(KonarrError::InvalidData(_))
+        ));
+        writer.await.expect("writer panicked")?;
+
+        // The write of the other request is not rolled back
+        let projects = ServerSettings::get(&connection, Setting::StatsProjectsTotal).await?;
+        assert_eq!(projects.value, "0");
+        let users = ServerSettings::get(&connection, Setting::StatsUsersTotal).await?;
+        assert_eq!(users.value, "42");
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_writes() -> Result<(), KonarrError> {
+        let path = std::env::temp_dir().join(format!("konarr-test-busy-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let database = std::sync::Arc::new(libsql::Builder::new_local(&path).build().await?);
+
+        let connection = database.connect()?;
+        connection_init(&connection).await?;
+        database_create(&connection).await?;
+        let mut snapshot = Snapshot::new();
+        snapshot.save(&connection).await?;
+
+        let mut writers = Vec::new();
+        for writer in 0..8 {
+            let database = database.clone();
+            let snapshot = snapshot.id;
+            writers.push(tokio::spawn(async move {
+                let connection = database.connect()?;
+                connection_init(&connection).await?;
+                for i in 0..25 {
+                    ServerSettings::update_statistic_at(
+                        &connection,
+                        Setting::StatsDependenciesTotal,
+                        writer * 100 + i,
+                        chrono::Utc::now(),
+                    )
+                    .await?;
+                    SnapshotMetadata::update_or_create(
+                        &connection,
+                        snapshot,
+                        &SnapshotMetadataKey::Os,
+                        format!("writer-{}-{}", writer, i),
+                    )
+                    .await?;
+                }
+                Ok::<_, KonarrError>(())
+            }));
+        }
+        for writer in writers {
+            writer.await.expect("writer panicked")?;
+        }
+
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+}
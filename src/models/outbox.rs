@@ -0,0 +1,341 @@
+//! # Events Outbox
+//!
+//! Events (notifications, reports, ...) are written to the outbox with the data change
+//! which caused them, the `outbox` task delivers them to the webhooks and retries the
+//! failed deliveries with an exponential backoff. Events which failed
+//! `notifications.attempts` times are kept for inspection and can be redelivered.
+//!
+//! Events are posted as a versioned envelope (see [EventsOutbox::envelope]):
+//!
+//! ```json
+//! {
+//!   "id": 1,
+//!   "type": "projects.stale",
+//!   "version": 1,
+//!   "createdAt": "2026-10-15T12:00:00Z",
+//!   "text": "1 project(s) became stale: homelab",
+//!   "data": { "projects": ["homelab"] }
+//! }
+//! ```
+
+use chrono::{DateTime, Utc};
+use geekorm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{models::raw_query, KonarrError};
+
+/// Version of the event envelope / data schema
+pub const OUTBOX_EVENT_VERSION: i32 = 1;
+/// Delay before the first retry (doubled after each failed attempt)
+pub const OUTBOX_BACKOFF_SECONDS: i64 = 30;
+/// Maximum delay between the retries
+pub const OUTBOX_BACKOFF_MAX_SECONDS: i64 = 3600;
+
+/// Projects which became stale
+pub const EVENT_PROJECTS_STALE: &str = "projects.stale";
+/// Dependency changes of a new snapshot
+pub const EVENT_SNAPSHOT_DIFF: &str = "snapshot.diff";
+/// Security digest report generated
+pub const EVENT_REPORT_READY: &str = "report.ready";
+
+/// Delivery state of an event
+#[derive(Data, Debug, Clone, Default, PartialEq)]
+pub enum OutboxState {
+    /// Waiting to be delivered (or retried)
+    #[default]
+    Pending,
+    /// Delivered to the webhook
+    Delivered,
+    /// Gave up after the maximum number of attempts
+    Failed,
+}
+
+/// Events Outbox Model
+#[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct EventsOutbox {
+    /// Primary Key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+
+    /// Type of the event (`projects.stale`, `report.ready`, ...)
+    pub event: String,
+    /// Version of the event schema
+    #[geekorm(new = "OUTBOX_EVENT_VERSION")]
+    pub version: i32,
+    /// Webhook URL the event is delivered to
+    pub endpoint: String,
+    /// Summary of the event (chat webhooks)
+    pub text: String,
+    /// Data of the event (JSON)
+    pub data: String,
+
+    /// Delivery state
+    #[geekorm(new = "OutboxState::Pending")]
+    pub state: OutboxState,
+    /// Number of delivery attempts
+    #[geekorm(new = "0")]
+    pub attempts: i32,
+    /// HTTP status code of the last attempt
+    pub status_code: Option<i32>,
+    /// Error of the last failed attempt
+    pub error: Option<String>,
+
+    /// Time of the next delivery attempt
+    #[geekorm(new = "Utc::now()")]
+    pub next_attempt_at: DateTime<Utc>,
+    /// Time the event was created
+    #[geekorm(new = "Utc::now()")]
+    pub created_at: DateTime<Utc>,
+    /// Time the event was delivered
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl EventsOutbox {
+    /// Initialise the Events Outbox table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::create_table(connection).await?;
+        Ok(())
+    }
+
+    /// Add an event to the outbox (delivered by the `outbox` task)
+    pub async fn enqueue<'a, T>(
+        connection: &'a T,
+        event: impl Into<String>,
+        endpoint: impl Into<String>,
+        text: impl Into<String>,
+        data: &serde_json::Value,
+    ) -> Result<Self, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut entry = Self::new(event.into(), endpoint.into(), text.into(), data.to_string());
+        entry.save(connection).await?;
+        log::debug!("Outbox({}) :: queued `{}`", entry.id, entry.event);
+        Ok(entry)
+    }
+
+    /// Envelope posted to the webhook
+    pub fn envelope(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "type": self.event,
+            "version": self.version,
+            "createdAt": self.created_at,
+            "text": self.text,
+            "data": serde_json::from_str::<serde_json::Value>(&self.data)
+                .unwrap_or(serde_json::Value::Null),
+        })
+    }
+
+    /// Pending events due for a delivery attempt (oldest first)
+    pub async fn fetch_due<'a, T>(
+        connection: &'a T,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("state".to_string(), OutboxState::Pending);
+        values.push("next_attempt_at".to_string(), now);
+        Ok(T::query::<Self>(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT * FROM EventsOutbox WHERE state = ? AND next_attempt_at <= ? \
+                    ORDER BY id ASC LIMIT {};",
+                    limit
+                ),
+                values,
+            ),
+        )
+        .await?)
+    }
+
+    /// Latest events (most recent first), optionally in a state
+    pub async fn fetch_latest<'a, T>(
+        connection: &'a T,
+        state: Option<OutboxState>,
+        limit: usize,
+    ) -> Result<Vec<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut query = Self::query_select();
+        if let Some(state) = state {
+            query = query.where_eq("state", state);
+        }
+        Ok(Self::query(
+            connection,
+            query
+                .order_by("id", QueryOrder::Desc)
+                .limit(limit)
+                .build()?,
+        )
+        .await?)
+    }
+
+    /// Delay before the next attempt after a number of failed attempts
+    pub fn backoff(attempts: i32) -> chrono::Duration {
+        let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+        chrono::Duration::seconds(
+            OUTBOX_BACKOFF_SECONDS
+                .saturating_mul(2_i64.pow(exponent))
+                .min(OUTBOX_BACKOFF_MAX_SECONDS),
+        )
+    }
+
+    /// Mark the event as delivered
+    pub async fn delivered<'a, T>(
+        &mut self,
+        connection: &'a T,
+        status_code: u16,
+    ) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        self.attempts += 1;
+        self.state = OutboxState::Delivered;
+        self.status_code = Some(status_code as i32);
+        self.error = None;
+        self.delivered_at = Some(Utc::now());
+        self.update(connection).await?;
+        Ok(())
+    }
+
+    /// Record a failed attempt, the event is retried (see [Self::backoff]) until it failed
+    /// `max_attempts` times
+    pub async fn attempt_failed<'a, T>(
+        &mut self,
+        connection: &'a T,
+        status_code: Option<u16>,
+        error: impl Into<String>,
+        max_attempts: i32,
+    ) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        self.attempts += 1;
+        self.status_code = status_code.map(|code| code as i32);
+        self.error = Some(error.into());
+        if self.attempts >= max_attempts {
+            log::warn!(
+                "Outbox({}) :: giving up `{}` after {} attempts",
+                self.id,
+                self.event,
+                self.attempts
+            );
+            self.state = OutboxState::Failed;
+        } else {
+            self.next_attempt_at = Utc::now() + Self::backoff(self.attempts);
+        }
+        self.update(connection).await?;
+        Ok(())
+    }
+
+    /// Queue the event for a new delivery (the attempts are reset)
+    pub async fn redeliver<'a, T>(&mut self, connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        self.state = OutboxState::Pending;
+        self.attempts = 0;
+        self.error = None;
+        self.next_attempt_at = Utc::now();
+        self.update(connection).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(EventsOutbox::backoff(1), chrono::Duration::seconds(30));
+        assert_eq!(EventsOutbox::backoff(2), chrono::Duration::seconds(60));
+        assert_eq!(EventsOutbox::backoff(4), chrono::Duration::seconds(240));
+        assert_eq!(EventsOutbox::backoff(20), chrono::Duration::seconds(3600));
+    }
+
+    #[tokio::test]
+    async fn test_outbox() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut event = EventsOutbox::enqueue(
+            &connection,
+            EVENT_PROJECTS_STALE,
+            "http://localhost/webhook",
+            "1 project(s) became stale: homelab",
+            &serde_json::json!({ "projects": ["homelab"] }),
+        )
+        .await?;
+        let envelope = event.envelope();
+        assert_eq!(envelope["type"], EVENT_PROJECTS_STALE);
+        assert_eq!(envelope["version"], OUTBOX_EVENT_VERSION);
+        assert_eq!(envelope["data"]["projects"][0], "homelab");
+
+        let now = Utc::now();
+        assert_eq!(
+            EventsOutbox::fetch_due(&connection, now, 10).await?.len(),
+            1
+        );
+
+        // Retried later
+        event
+            .attempt_failed(&connection, Some(502), "Bad Gateway", 2)
+            .await?;
+        assert_eq!(event.state, OutboxState::Pending);
+        assert!(EventsOutbox::fetch_due(&connection, now, 10)
+            .await?
+            .is_empty());
+        let later = now + chrono::Duration::minutes(1);
+        assert_eq!(
+            EventsOutbox::fetch_due(&connection, later, 10).await?.len(),
+            1
+        );
+
+        // Kept for inspection after the last attempt
+        event
+            .attempt_failed(&connection, None, "timeout", 2)
+            .await?;
+        assert_eq!(event.state, OutboxState::Failed);
+        let failed = EventsOutbox::fetch_latest(&connection, Some(OutboxState::Failed), 10).await?;
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].attempts, 2);
+        assert_eq!(failed[0].error.as_deref(), Some("timeout"));
+
+        event.redeliver(&connection).await?;
+        assert_eq!(
+            EventsOutbox::fetch_due(&connection, later, 10).await?.len(),
+            1
+        );
+        event.delivered(&connection, 204).await?;
+        assert!(EventsOutbox::fetch_due(&connection, later, 10)
+            .await?
+            .is_empty());
+        assert_eq!(event.status_code, Some(204));
+
+        // Oldest due events first, up to the limit
+        for index in 0..3 {
+            EventsOutbox::enqueue(
+                &connection,
+                EVENT_PROJECTS_STALE,
+                "http://localhost/webhook",
+                format!("event {}", index),
+                &serde_json::json!({}),
+            )
+            .await?;
+        }
+        let due = EventsOutbox::fetch_due(&connection, later, 2).await?;
+        assert_eq!(due.len(), 2);
+        assert_eq!(due[0].text, "event 0");
+        assert_eq!(due[1].text, "event 1");
+        Ok(())
+    }
+}
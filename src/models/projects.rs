@@ -3,10 +3,46 @@
 use geekorm::prelude::*;
 
 use chrono::{DateTime, Utc};
-use log::{debug, warn};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 
-use super::{Dependencies, Snapshot};
+use super::{
+    dependencies::snapshots::AlertsSummary,
+    raw_query,
+    security::{ProjectHealth, SecuritySeverity, SECURITY_SEVERITY},
+    Dependencies, Snapshot, SnapshotMetadata, SnapshotMetadataKey, Transaction,
+    TransactionConnection,
+};
+use crate::utils::{
+    containers::{self, ContainerPort},
+    names::{
+        normalize_project_name, project_name_title, project_relative_name, transfer_project_name,
+    },
+};
+
+/// Active projects with open image policy findings in their latest snapshot
+const POLICY_VIOLATIONS_FILTER: &str = "status != ? AND EXISTS (\
+    SELECT 1 FROM SnapshotMetadata WHERE key = ? \
+    AND CAST(CAST(value AS TEXT) AS INTEGER) > 0 \
+    AND snapshot_id = (SELECT MAX(snapshot_id) FROM ProjectSnapshots WHERE project_id = Projects.id))";
+
+/// Active projects flagged stale (`scan.stale`) in their latest snapshot
+const STALE_FILTER: &str = "status != ? AND EXISTS (\
+    SELECT 1 FROM SnapshotMetadata WHERE key = ? AND CAST(value AS TEXT) = 'true' \
+    AND snapshot_id = (SELECT MAX(snapshot_id) FROM ProjectSnapshots WHERE project_id = Projects.id))";
+
+/// Latest snapshot of a project (used by the metadata JOINs)
+const LATEST_SNAPSHOT: &str =
+    "(SELECT MAX(snapshot_id) FROM ProjectSnapshots WHERE project_id = Projects.id)";
+
+/// Maximum number of values bound in a single `IN (...)` query
+const PROJECT_BATCH_SIZE: usize = 500;
+
+/// Prefix of the project feed tokens
+pub const PROJECT_FEED_TOKEN_PREFIX: &str = "konarr-feed-";
+
+/// Maximum depth of the project hierarchy walked when checking the ancestry
+const PROJECT_MAX_DEPTH: usize = 32;
 
 /// Status of the Project
 #[derive(Data, Debug, Default, Clone, PartialEq)]
@@ -45,6 +81,26 @@ pub struct Projects {
     #[geekorm(new = "0")]
     pub parent: i32,
 
+    /// The title or description was edited by a user (agents no longer sync them from labels)
+    #[geekorm(new = "false")]
+    #[serde(default)]
+    pub edited_by_user: bool,
+
+    /// Token to read the alerts feed of the project without a session (feed readers)
+    #[serde(default)]
+    pub feed_token: Option<String>,
+
+    /// Maximum number of snapshots kept (overrides the retention policy of the project type)
+    #[serde(default)]
+    pub retention_max_snapshots: Option<i32>,
+    /// Maximum age (days) of the snapshots kept (overrides the retention policy of the type)
+    #[serde(default)]
+    pub retention_max_age_days: Option<i32>,
+
+    /// Datetime the Project was archived (cleared when restored)
+    #[serde(default)]
+    pub archived_at: Option<DateTime<Utc>>,
+
     /// Children of the Project
     #[geekorm(skip)]
     #[serde(skip)]
@@ -70,19 +126,27 @@ impl Projects {
         Projects::create_table(connection).await?;
 
         // Create a Default Project
-        let mut main_server = Projects::new("Main Server", ProjectType::Server);
-        main_server.description =
-            Some("This is a sample server to show how Konarr works".to_string());
-        main_server.fetch_or_create(connection).await?;
+        let main_server = match Projects::fetch_by_normalized_name(connection, "Main Server").await
+        {
+            Ok(project) => project,
+            Err(_) => {
+                let mut main_server = Projects::with_title("Main Server", ProjectType::Server);
+                main_server.description =
+                    Some("This is a sample server to show how Konarr works".to_string());
+                main_server.save(connection).await?;
+                main_server
+            }
+        };
 
         debug!("Server Project Created: {:?}", main_server);
 
-        match Projects::fetch_by_name(connection, "Main Container").await {
+        match Projects::fetch_by_normalized_name(connection, "Main Container").await {
             Ok(_) => {
                 debug!("Server `Main Container` already exists");
             }
             Err(_) => {
-                let mut container_project = Projects::new("Main Container", ProjectType::Container);
+                let mut container_project =
+                    Projects::with_title("Main Container", ProjectType::Container);
                 container_project.description =
                     Some("This is a sample container to show how Konarr works".to_string());
                 container_project.parent = main_server.id.into();
@@ -95,6 +159,63 @@ impl Projects {
         Ok(())
     }
 
+    /// Create a new Project with a normalized name
+    ///
+    /// The original name is kept as the title (the last segment of the name).
+    pub fn with_title(name: impl AsRef<str>, project_type: ProjectType) -> Self {
+        let mut project = Projects::new(normalize_project_name(name.as_ref()), project_type);
+        project.title = Some(project_name_title(name.as_ref()));
+        project
+    }
+
+    /// Edit the title and / or description (by a user)
+    ///
+    /// An empty description removes it. Returns `true` if the project changed.
+    pub fn edit(&mut self, title: Option<String>, description: Option<String>) -> bool {
+        let changed = self.set_details(title, description);
+        if changed {
+            self.edited_by_user = true;
+        }
+        changed
+    }
+
+    /// Sync the title and / or description from the container labels (by an agent)
+    ///
+    /// Projects edited by a user are never updated so manual edits always win.
+    /// Returns `true` if the project changed.
+    pub fn sync_labels(&mut self, title: Option<String>, description: Option<String>) -> bool {
+        if self.edited_by_user {
+            debug!(
+                "Project `{}` was edited by a user, skipping label sync",
+                self.name
+            );
+            return false;
+        }
+        self.set_details(title, description)
+    }
+
+    fn set_details(&mut self, title: Option<String>, description: Option<String>) -> bool {
+        let mut changed = false;
+        if let Some(title) = title {
+            if self.title.as_ref() != Some(&title) {
+                self.title = Some(title);
+                changed = true;
+            }
+        }
+        if let Some(description) = description {
+            let description = if description.is_empty() {
+                None
+            } else {
+                Some(description)
+            };
+            if self.description != description {
+                self.description = description;
+                changed = true;
+            }
+        }
+        changed
+    }
+
     /// Get all Projects
     pub async fn all<'a, T>(
         connection: &'a T,
@@ -116,7 +237,6 @@ impl Projects {
         .await?;
 
         for proj in projects.iter_mut() {
-            proj.fetch_children(connection).await?;
             proj.fetch_snapshots(connection).await?;
         }
 
@@ -137,6 +257,197 @@ impl Projects {
         .await?)
     }
 
+    /// Fetch the active Projects with image policy violations in their latest snapshot
+    pub async fn fetch_policy_violations<'a, T>(
+        connection: &'a T,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Self>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Self::policy_violations_values();
+        values.push("limit".to_string(), limit as i32);
+        values.push("offset".to_string(), offset as i32);
+
+        let mut projects = T::query::<Projects>(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT * FROM Projects WHERE {} ORDER BY created_at DESC LIMIT ? OFFSET ?;",
+                    POLICY_VIOLATIONS_FILTER
+                ),
+                values,
+            ),
+        )
+        .await?;
+        for proj in projects.iter_mut() {
+            proj.fetch_snapshots(connection).await?;
+        }
+        Ok(projects)
+    }
+
+    /// Count the active Projects with image policy violations in their latest snapshot
+    pub async fn count_policy_violations<'a, T>(
+        connection: &'a T,
+    ) -> Result<i64, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Projects::row_count(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT COUNT(*) FROM Projects WHERE {};",
+                    POLICY_VIOLATIONS_FILTER
+                ),
+                Self::policy_violations_values(),
+            ),
+        )
+        .await?)
+    }
+
+    fn policy_violations_values() -> Values {
+        let mut values = Values::new();
+        values.push("status".to_string(), ProjectStatus::Archived);
+        values.push("key".to_string(), SnapshotMetadataKey::SecurityPolicyTotal);
+        values
+    }
+
+    /// Fetch the active Projects flagged stale in their latest snapshot (stale scans task)
+    pub async fn fetch_stale<'a, T>(
+        connection: &'a T,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Self>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Self::stale_values();
+        values.push("limit".to_string(), limit as i32);
+        values.push("offset".to_string(), offset as i32);
+
+        let mut projects = T::query::<Projects>(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT * FROM Projects WHERE {} ORDER BY created_at DESC LIMIT ? OFFSET ?;",
+                    STALE_FILTER
+                ),
+                values,
+            ),
+        )
+        .await?;
+        for proj in projects.iter_mut() {
+            proj.fetch_snapshots(connection).await?;
+        }
+        Ok(projects)
+    }
+
+    /// Count the active Projects flagged stale in their latest snapshot
+    pub async fn count_stale<'a, T>(connection: &'a T) -> Result<i64, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Projects::row_count(
+            connection,
+            raw_query(
+                format!("SELECT COUNT(*) FROM Projects WHERE {};", STALE_FILTER),
+                Self::stale_values(),
+            ),
+        )
+        .await?)
+    }
+
+    fn stale_values() -> Values {
+        let mut values = Values::new();
+        values.push("status".to_string(), ProjectStatus::Archived);
+        values.push("key".to_string(), SnapshotMetadataKey::ScanStale);
+        values
+    }
+
+    /// Fetch the active Projects matching the metric filters (latest snapshot metadata)
+    ///
+    /// The metric values are returned with the project, so they can be displayed.
+    pub async fn fetch_filtered<'a, T>(
+        connection: &'a T,
+        filters: &ProjectFilters,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<ProjectMetrics>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let (query, mut values) = filters.query();
+        values.push("limit".to_string(), limit as i32);
+        values.push("offset".to_string(), offset as i32);
+
+        let rows = T::query::<ProjectMetricsRow>(
+            connection,
+            raw_query(
+                format!(
+                    "{} ORDER BY Projects.created_at DESC LIMIT ? OFFSET ?;",
+                    query
+                ),
+                values,
+            ),
+        )
+        .await?;
+
+        let mut projects = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut project = Projects::fetch_by_primary_key(connection, row.id).await?;
+            project.fetch_snapshots(connection).await?;
+            projects.push(ProjectMetrics {
+                project,
+                alerts: row.alerts,
+                dependencies: row.dependencies,
+            });
+        }
+        Ok(projects)
+    }
+
+    /// Count the active Projects matching the metric filters
+    pub async fn count_filtered<'a, T>(
+        connection: &'a T,
+        filters: &ProjectFilters,
+    ) -> Result<i64, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let (query, values) = filters.query();
+        Ok(Projects::row_count(
+            connection,
+            raw_query(format!("SELECT COUNT(*) FROM ({});", query), values),
+        )
+        .await?)
+    }
+
+    /// Fetch the Archived Projects (most recently archived first)
+    pub async fn fetch_archived<'a, T>(
+        connection: &'a T,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Self>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut projects = Projects::query(
+            connection,
+            Projects::query_select()
+                .where_eq("status", ProjectStatus::Archived)
+                .order_by("archived_at", QueryOrder::Desc)
+                .limit(limit)
+                .offset(offset)
+                .build()?,
+        )
+        .await?;
+        for proj in projects.iter_mut() {
+            proj.fetch_snapshots(connection).await?;
+        }
+        Ok(projects)
+    }
+
     /// Count the Archived Projects
     pub async fn count_archived<'a, T>(connection: &'a T) -> Result<i64, crate::KonarrError>
     where
@@ -197,6 +508,41 @@ impl Projects {
         .await?)
     }
 
+    /// Count the active Top-Level Projects
+    pub async fn count_top_level<'a, T>(connection: &'a T) -> Result<i64, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Projects::row_count(
+            connection,
+            Projects::query_count()
+                .where_eq("status", ProjectStatus::Active)
+                .and()
+                .where_eq("parent", 0)
+                .build()?,
+        )
+        .await?)
+    }
+
+    /// Count the active Projects by type
+    pub async fn count_project_type<'a, T>(
+        connection: &'a T,
+        project_type: impl Into<ProjectType>,
+    ) -> Result<i64, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Projects::row_count(
+            connection,
+            Projects::query_count()
+                .where_eq("project_type", project_type.into())
+                .and()
+                .where_eq("status", ProjectStatus::Active)
+                .build()?,
+        )
+        .await?)
+    }
+
     /// Search for Projects
     pub async fn search_title<'a, T>(
         connection: &'a T,
@@ -217,291 +563,2422 @@ impl Projects {
         )
         .await?;
         for proj in projects.iter_mut() {
-            proj.fetch_children(connection).await?;
             proj.fetch_snapshots(connection).await?;
         }
         Ok(projects)
     }
 
-    /// Get Top-Level Projects and their children
-    pub async fn fetch_top_level<'a, T>(
+    /// Fetch a Project by its (normalized) name
+    ///
+    /// The name is normalized before the lookup. The names of the merged projects resolve
+    /// to the project they were merged into (see [ProjectAliases]). Projects created before
+    /// the names were normalized are matched by comparing their normalized name (active
+    /// projects first).
+    pub async fn fetch_by_normalized_name<'a, T>(
         connection: &'a T,
-        limit: usize,
-        offset: usize,
-    ) -> Result<Vec<Self>, crate::KonarrError>
+        name: impl AsRef<str>,
+    ) -> Result<Self, crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        debug!("Fetching top level projects");
+        let normalized = normalize_project_name(name.as_ref());
+        if let Ok(project) = Projects::fetch_by_name(connection, &normalized).await {
+            if project.status != ProjectStatus::Archived {
+                return Ok(project);
+            }
+        }
+        // Name of a project merged into another project
+        if let Ok(project) = Projects::fetch_by_alias(connection, &normalized).await {
+            return Ok(project);
+        }
 
-        let mut projects = Projects::query(
+        let mut projects: Vec<Self> = Projects::query(
             connection,
             Projects::query_select()
-                .where_eq("status", ProjectStatus::Active)
-                .and()
-                .where_eq("parent", 0)
-                .order_by("created_at", QueryOrder::Desc)
-                .limit(limit)
-                .offset(offset)
+                .order_by("id", QueryOrder::Asc)
                 .build()?,
         )
-        .await?;
-
-        for proj in projects.iter_mut() {
-            proj.fetch_children(connection).await?;
-            proj.fetch_snapshots(connection).await?;
-        }
+        .await?
+        .into_iter()
+        .filter(|p| !normalized.is_empty() && normalize_project_name(&p.name) == normalized)
+        .collect();
+        projects.sort_by_key(|p| p.status == ProjectStatus::Archived);
 
-        Ok(projects)
+        projects
+            .into_iter()
+            .next()
+            .ok_or_else(|| crate::KonarrError::ProjectNotFound(normalized))
     }
 
-    /// Fetch active projects by type
-    pub async fn fetch_project_type<'a, T>(
+    /// Find the Projects which have the same normalized name (not archived)
+    ///
+    /// The first project of each group is the one to keep (the project which already
+    /// has the normalized name, otherwise the oldest project).
+    pub async fn fetch_duplicate_names<'a, T>(
         connection: &'a T,
-        project_type: impl Into<ProjectType>,
-        limit: usize,
-        offset: usize,
-    ) -> Result<Vec<Self>, crate::KonarrError>
+    ) -> Result<Vec<Vec<Self>>, crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        let mut projects = Projects::query(
+        let projects = Projects::query(
             connection,
             Projects::query_select()
-                .where_eq("status", ProjectStatus::Active)
-                .and()
-                .where_eq("project_type", project_type.into())
-                .order_by("created_at", QueryOrder::Desc)
-                .limit(limit)
-                .offset(offset)
+                .where_ne("status", ProjectStatus::Archived)
+                .order_by("id", QueryOrder::Asc)
                 .build()?,
         )
         .await?;
-        for proj in projects.iter_mut() {
-            proj.fetch_children(connection).await?;
-            proj.fetch_snapshots(connection).await?;
-        }
 
-        Ok(projects)
+        let mut groups: Vec<(String, Vec<Self>)> = Vec::new();
+        for project in projects {
+            let normalized = normalize_project_name(&project.name);
+            match groups.iter_mut().find(|(name, _)| *name == normalized) {
+                Some((_, group)) => group.push(project),
+                None => groups.push((normalized, vec![project])),
+            }
+        }
+        Ok(groups
+            .into_iter()
+            .filter(|(_, group)| group.len() > 1)
+            .map(|(normalized, mut group)| {
+                group.sort_by_key(|p| p.name != normalized);
+                group
+            })
+            .collect())
     }
 
-    /// Find a list of projects by component in latest snapshot
-    pub async fn find_project_by_component<'a, T>(
+    /// Find the container images (by digest) tracked by more than one Project (not archived)
+    ///
+    /// Projects are grouped by the `container.sha` metadata of their latest snapshot.
+    pub async fn fetch_duplicate_images<'a, T>(
         connection: &'a T,
-        component_id: i32,
-    ) -> Result<Vec<Self>, crate::KonarrError>
+    ) -> Result<Vec<DuplicateImage>, crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        let mut results = vec![];
-        // TODO: This is a terrible way to do this
-        let mut projects = Projects::query(connection, Projects::query_all()).await?;
+        let mut values = Values::new();
+        values.push("status".to_string(), ProjectStatus::Archived);
+        values.push("key".to_string(), SnapshotMetadataKey::ContainerSha);
 
-        for proj in projects.iter_mut() {
-            if let Some(snap) = proj.fetch_latest_snapshot(connection).await? {
-                let dep = Dependencies::query(
-                    connection,
-                    Dependencies::query_select()
-                        .where_eq("snapshot_id", snap.id)
-                        .and()
-                        .where_eq("component_id", component_id)
-                        .limit(1)
-                        .build()?,
-                )
-                .await?;
+        let rows = T::query::<ProjectDigestRow>(
+            connection,
+            raw_query(
+                "SELECT Projects.id AS project_id, SnapshotMetadata.snapshot_id AS snapshot_id, \
+                CAST(SnapshotMetadata.value AS TEXT) AS digest \
+                FROM Projects JOIN SnapshotMetadata ON SnapshotMetadata.snapshot_id = \
+                (SELECT MAX(snapshot_id) FROM ProjectSnapshots WHERE project_id = Projects.id) \
+                WHERE Projects.status != ? AND SnapshotMetadata.key = ? \
+                ORDER BY Projects.id ASC;",
+                values,
+            ),
+        )
+        .await?;
 
-                if dep.len() == 1 {
-                    results.push(proj.clone());
-                }
+        let mut groups: Vec<(String, Vec<ProjectDigestRow>)> = Vec::new();
+        for row in rows.into_iter().filter(|row| !row.digest.is_empty()) {
+            match groups.iter_mut().find(|(digest, _)| *digest == row.digest) {
+                Some((_, group)) => group.push(row),
+                None => groups.push((row.digest.clone(), vec![row])),
             }
         }
 
-        Ok(results)
+        let mut duplicates = Vec::new();
+        for (digest, group) in groups.into_iter().filter(|(_, group)| group.len() > 1) {
+            let mut duplicate = DuplicateImage {
+                digest,
+                ..Default::default()
+            };
+            for row in group {
+                if !duplicate.snapshots.contains(&row.snapshot_id) {
+                    duplicate.snapshots.push(row.snapshot_id);
+                }
+                duplicate
+                    .projects
+                    .push(Projects::fetch_by_primary_key(connection, row.project_id).await?);
+            }
+            duplicates.push(duplicate);
+        }
+        Ok(duplicates)
     }
 
-    /// Find all the possible parents
-    pub async fn find_parents<'a, T>(connection: &'a T) -> Result<Vec<Self>, crate::KonarrError>
+    /// Find the Projects (not archived) with ports published on the host
+    ///
+    /// Uses the `container.ports` and `container.networks` metadata of the latest snapshot.
+    pub async fn fetch_exposed<'a, T>(
+        connection: &'a T,
+    ) -> Result<Vec<ExposedProject>, crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        debug!("Finding all parent projects");
-        Ok(Projects::query(
+        let mut values = Values::new();
+        values.push("ports".to_string(), SnapshotMetadataKey::ContainerPorts);
+        values.push(
+            "networks".to_string(),
+            SnapshotMetadataKey::ContainerNetworks,
+        );
+        values.push("status".to_string(), ProjectStatus::Archived);
+
+        let rows = T::query::<ProjectExposureRow>(
             connection,
-            Projects::query_select()
-                .where_eq("status", ProjectStatus::Active)
-                .and()
-                .where_eq("project_type", ProjectType::Server)
-                .order_by("name", QueryOrder::Asc)
-                .build()?,
+            raw_query(
+                "SELECT Projects.id AS project_id, CAST(ports.value AS TEXT) AS ports, \
+                CAST(COALESCE(networks.value, '') AS TEXT) AS networks \
+                FROM Projects JOIN SnapshotMetadata ports ON ports.snapshot_id = \
+                (SELECT MAX(snapshot_id) FROM ProjectSnapshots WHERE project_id = Projects.id) \
+                AND ports.key = ? \
+                LEFT JOIN SnapshotMetadata networks ON networks.snapshot_id = ports.snapshot_id \
+                AND networks.key = ? \
+                WHERE Projects.status != ? AND ports.value LIKE '%host_port%' \
+                ORDER BY Projects.name ASC;",
+                values,
+            ),
         )
-        .await?)
+        .await?;
+
+        let mut exposed = Vec::new();
+        for row in rows {
+            let ports: Vec<ContainerPort> = ContainerPort::parse_list(&row.ports)
+                .into_iter()
+                .filter(|port| port.is_published())
+                .collect();
+            if ports.is_empty() {
+                continue;
+            }
+            exposed.push(ExposedProject {
+                project: Projects::fetch_by_primary_key(connection, row.project_id).await?,
+                ports,
+                networks: containers::parse_networks(&row.networks),
+            });
+        }
+        Ok(exposed)
     }
 
-    /// Get the projects children
-    pub async fn fetch_children<'a, T>(
+    /// Merge the Project into another project of the same type
+    ///
+    /// The snapshots (a snapshot already linked to the target is not linked twice),
+    /// children, alert events, ignore rules, VEX statements, annotations, settings (the
+    /// settings of the target win), transfers and aliases are moved to the target. The
+    /// emptied project is archived, or removed with [ProjectMerge::delete_source]. The
+    /// merge runs in a transaction.
+    pub async fn merge_into<'a, T>(
         &mut self,
         connection: &'a T,
-    ) -> Result<(), crate::KonarrError>
+        target: &Projects,
+        options: &ProjectMerge,
+    ) -> Result<ProjectMergeSummary, crate::KonarrError>
     where
-        T: GeekConnection<Connection = T> + 'a,
+        T: GeekConnection<Connection = T> + TransactionConnection + 'a,
     {
-        debug!("Fetching Children for Project: {:?}", self.id);
-
-        self.children = Projects::query(
-            connection,
-            Projects::query_select()
-                .where_eq("status", ProjectStatus::Active)
-                .and()
-                .where_eq("parent", self.id)
-                .order_by("created_at", QueryOrder::Desc)
-                .build()?,
-        )
-        .await?;
-        for child in self.children.iter_mut() {
-            child.fetch_snapshots(connection).await?;
+        if self.id == target.id {
+            return Err(crate::KonarrError::InvalidData(
+                "Can not merge a project into itself".to_string(),
+            ));
+        }
+        if self.project_type != target.project_type {
+            return Err(crate::KonarrError::InvalidData(format!(
+                "Can not merge the {} `{}` into the {} `{}`",
+                self.project_type, self.name, target.project_type, target.name
+            )));
+        }
+        if target.is_within(connection, self.id.into()).await? {
+            return Err(crate::KonarrError::InvalidData(format!(
+                "Project `{}` is a child of `{}`",
+                target.name, self.name
+            )));
         }
 
-        Ok(())
+        debug!("Merging Project({}) into Project({})", self.id, target.id);
+        let transaction = Transaction::begin(connection).await?;
+        let result = self
+            .merge_into_inner(transaction.connection(), target, options)
+            .await;
+        let summary = transaction.finish(result).await?;
+        info!(
+            "Merged Project `{}` into `{}` ({} snapshots)",
+            self.name, target.name, summary.snapshots
+        );
+        Ok(summary)
     }
 
-    /// Fetch latest Snapshot
-    pub async fn fetch_latest_snapshot<'a, T>(
+    async fn merge_into_inner<'a, T>(
+        &mut self,
+        connection: &'a T,
+        target: &Projects,
+        options: &ProjectMerge,
+    ) -> Result<ProjectMergeSummary, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut summary = ProjectMergeSummary {
+            snapshots: ProjectSnapshots::row_count(
+                connection,
+                ProjectSnapshots::query_count()
+                    .where_eq("project_id", self.id)
+                    .build()?,
+            )
+            .await? as usize,
+            children: Projects::row_count(
+                connection,
+                Projects::query_count()
+                    .where_eq("parent", self.id)
+                    .build()?,
+            )
+            .await? as usize,
+            ..Default::default()
+        };
+
+        for query in [
+            // Snapshots already linked to the target (shared images)
+            "DELETE FROM ProjectSnapshots WHERE project_id = ? AND snapshot_id IN \
+            (SELECT snapshot_id FROM ProjectSnapshots WHERE project_id = ?);",
+            // Settings of the target win
+            "DELETE FROM ProjectSettings WHERE project_id = ? AND name IN \
+            (SELECT name FROM ProjectSettings WHERE project_id = ?);",
+        ] {
+            let mut values = Values::new();
+            values.push("project".to_string(), i32::from(self.id));
+            values.push("target".to_string(), i32::from(target.id));
+            T::execute::<Self>(connection, raw_query(query, values)).await?;
+        }
+        for query in [
+            "UPDATE ProjectSnapshots SET project_id = ? WHERE project_id = ?;",
+            "UPDATE Projects SET parent = ? WHERE parent = ?;",
+            "UPDATE ProjectSettings SET project_id = ? WHERE project_id = ?;",
+            "UPDATE ProjectTransfers SET project_id = ? WHERE project_id = ?;",
+            "UPDATE ProjectAliases SET project_id = ? WHERE project_id = ?;",
+            "UPDATE AlertEvents SET project_id = ? WHERE project_id = ?;",
+            "UPDATE AlertIgnoreRules SET project_id = ? WHERE project_id = ?;",
+            "UPDATE VexStatements SET project_id = ? WHERE project_id = ?;",
+            "UPDATE ComponentAnnotations SET project_id = ? WHERE project_id = ?;",
+        ] {
+            let mut values = Values::new();
+            values.push("target".to_string(), i32::from(target.id));
+            values.push("project".to_string(), i32::from(self.id));
+            T::execute::<Self>(connection, raw_query(query, values)).await?;
+        }
+
+        // Reload the project (its parent might have been merged too)
+        *self = Projects::fetch_by_primary_key(connection, self.id).await?;
+
+        if options.alias && self.name != target.name {
+            let mut alias = ProjectAliases::new(target.id, self.name.clone());
+            match ProjectAliases::fetch_by_alias(connection, self.name.clone()).await {
+                Ok(mut existing) => {
+                    existing.project_id = target.id.into();
+                    existing.update(connection).await?;
+                }
+                Err(_) => alias.save(connection).await?,
+            }
+            summary.alias = Some(self.name.clone());
+        }
+
+        if options.delete_source {
+            self.delete(connection).await?;
+        } else {
+            self.archive(connection).await?;
+        }
+
+        if let Some(mut latest) = target.fetch_latest_snapshot(connection).await? {
+            latest.calculate_alerts_summary(connection).await?;
+        }
+        Ok(summary)
+    }
+
+    /// Fetch the active Project an alias (name of a merged project) resolves to
+    pub async fn fetch_by_alias<'a, T>(
+        connection: &'a T,
+        name: impl AsRef<str>,
+    ) -> Result<Self, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let name = normalize_project_name(name.as_ref());
+        let alias = ProjectAliases::fetch_by_alias(connection, name.clone())
+            .await
+            .map_err(|_| crate::KonarrError::ProjectNotFound(name.clone()))?;
+        let project = Projects::fetch_by_primary_key(connection, alias.project_id).await?;
+        if project.status == ProjectStatus::Archived {
+            return Err(crate::KonarrError::ProjectNotFound(name));
+        }
+        Ok(project)
+    }
+
+    /// Fetch the aliases of the Project
+    pub async fn fetch_aliases<'a, T>(
         &self,
         connection: &'a T,
-    ) -> Result<Option<Snapshot>, crate::KonarrError>
+    ) -> Result<Vec<ProjectAliases>, crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        match ProjectSnapshots::query_first(
+        ProjectAliases::query(
             connection,
-            ProjectSnapshots::query_select()
+            ProjectAliases::query_select()
                 .where_eq("project_id", self.id)
-                .order_by("snapshot_id", QueryOrder::Desc)
-                .limit(1)
+                .order_by("id", QueryOrder::Asc)
                 .build()?,
         )
         .await
-        {
-            Ok(snap) => Ok(Some(
-                Snapshot::fetch_by_primary_key(connection, snap.snapshot_id).await?,
-            )),
-            Err(_) => Ok(None),
+        .map_err(|e| e.into())
+    }
+
+    /// If the project type can have children (Servers, Groups and Clusters)
+    pub fn is_parent_type(&self) -> bool {
+        matches!(
+            self.project_type,
+            ProjectType::Server | ProjectType::Group | ProjectType::Cluster
+        )
+    }
+
+    /// Check if the project is (or is a child of) the ancestor project
+    pub async fn is_within<'a, T>(
+        &self,
+        connection: &'a T,
+        ancestor: i32,
+    ) -> Result<bool, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut project = self.clone();
+        // Bounded in case the hierarchy has a cycle
+        for _ in 0..PROJECT_MAX_DEPTH {
+            if i32::from(project.id) == ancestor {
+                return Ok(true);
+            }
+            if project.parent <= 0 {
+                break;
+            }
+            project = match Projects::fetch_by_primary_key(connection, project.parent).await {
+                Ok(parent) => parent,
+                Err(_) => break,
+            };
         }
+        Ok(false)
     }
 
-    /// Add snapshot to project
-    pub async fn add_snapshot<'a, T>(
+    /// Transfer the Project to another parent
+    ///
+    /// The target has to be an active Server, Group or Cluster which is not the project
+    /// (or one of its children). When `rename` is set the prefix of the name is changed to
+    /// the name of the new parent (`server-a/web` -> `server-b/web`). The move is recorded
+    /// in the transfers history of the project.
+    pub async fn transfer<'a, T>(
         &mut self,
         connection: &'a T,
-        snapshot: Snapshot,
-    ) -> Result<(), crate::KonarrError>
+        target: &Projects,
+        rename: bool,
+        user: impl Into<String>,
+    ) -> Result<ProjectTransfers, crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        debug!("Adding Snapshot to Project: {:?}", self.id);
-        let mut snap = ProjectSnapshots {
-            project_id: self.id.into(),
-            snapshot_id: snapshot.id.into(),
-            ..Default::default()
-        };
+        if !target.is_parent_type() {
+            return Err(crate::KonarrError::InvalidData(format!(
+                "Project `{}` is a {} (not a Server, Group or Cluster)",
+                target.name, target.project_type
+            )));
+        }
+        if target.status == ProjectStatus::Archived {
+            return Err(crate::KonarrError::InvalidData(format!(
+                "Project `{}` is archived",
+                target.name
+            )));
+        }
+        // The target can not be the project or one of its children
+        let mut ancestor = target.clone();
+        loop {
+            if ancestor.id == self.id {
+                return Err(crate::KonarrError::InvalidData(format!(
+                    "Project `{}` can not be moved into itself",
+                    self.name
+                )));
+            }
+            if ancestor.parent <= 0 {
+                break;
+            }
+            ancestor = Projects::fetch_by_primary_key(connection, ancestor.parent).await?;
+        }
 
-        snap.save(connection).await?;
+        let from_parent = self.parent;
+        let old_name = self.name.clone();
+        if rename {
+            let old_prefix = match from_parent {
+                0 => None,
+                parent => Projects::fetch_by_primary_key(connection, parent)
+                    .await
+                    .ok()
+                    .map(|p| p.name),
+            };
+            let name = transfer_project_name(&self.name, old_prefix.as_deref(), &target.name);
+            if name != self.name {
+                if let Ok(existing) = Projects::fetch_by_name(connection, &name).await {
+                    return Err(crate::KonarrError::InvalidData(format!(
+                        "Project `{}` already exists ({})",
+                        existing.name, existing.id
+                    )));
+                }
+                self.name = name;
+            }
+        }
 
-        self.snapshots.push(snapshot);
+        info!(
+            "Transferring Project({}) from Project({}) to Project({})",
+            self.id, from_parent, target.id
+        );
+        self.parent = target.id.into();
+        self.update(connection).await?;
 
-        Ok(())
+        let mut transfer = ProjectTransfers::new(
+            self.id,
+            from_parent,
+            i32::from(target.id),
+            old_name,
+            self.name.clone(),
+            user.into(),
+        );
+        transfer.save(connection).await?;
+        Ok(transfer)
     }
-    /// Fetch Snapshots for the Project
-    pub async fn fetch_snapshots<'a, T>(
-        &mut self,
+
+    /// Find a Project which was transferred (agents still using the previous location)
+    ///
+    /// A project renamed by a transfer is found by its previous name, a project which was
+    /// transferred to the parent without being renamed is found by its name relative to
+    /// the parent (`server-a/web` matches `server-b/web` when looking in `server-b`).
+    pub async fn fetch_transferred<'a, T>(
         connection: &'a T,
-    ) -> Result<(), crate::KonarrError>
+        name: impl AsRef<str>,
+        parent: i32,
+    ) -> Result<Self, crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        let snaps = ProjectSnapshots::fetch_by_project_id(connection, self.id).await?;
+        let name = normalize_project_name(name.as_ref());
 
-        for snap in snaps {
-            let mut snaps = Snapshot::fetch_by_primary_key(connection, snap.snapshot_id).await?;
-            snaps.fetch_metadata(connection).await?;
+        let transfers = ProjectTransfers::query(
+            connection,
+            ProjectTransfers::query_select()
+                .where_eq("old_name", name.clone())
+                .order_by("id", QueryOrder::Desc)
+                .build()?,
+        )
+        .await?;
+        for transfer in transfers {
+            let project = Projects::fetch_by_primary_key(connection, transfer.project_id).await?;
+            if project.status != ProjectStatus::Archived {
+                return Ok(project);
+            }
+        }
 
-            self.snapshots.push(snaps);
+        if parent > 0 {
+            if let Some(relative) = project_relative_name(&name) {
+                let children = Projects::query(
+                    connection,
+                    Projects::query_select()
+                        .where_ne("status", ProjectStatus::Archived)
+                        .and()
+                        .where_eq("parent", parent)
+                        .order_by("id", QueryOrder::Asc)
+                        .build()?,
+                )
+                .await?;
+                if let Some(project) = children
+                    .into_iter()
+                    .find(|p| project_relative_name(&p.name).as_deref() == Some(relative.as_str()))
+                {
+                    return Ok(project);
+                }
+            }
         }
-        Ok(())
+
+        Err(crate::KonarrError::ProjectNotFound(name))
     }
 
-    /// Calculate Alerts for all projects with snapshots
-    pub async fn calculate_alerts<'a, T>(
+    /// Fetch the transfers history of the Project (newest first)
+    pub async fn fetch_transfers<'a, T>(
+        &self,
         connection: &'a T,
-        projects: &mut Vec<Self>,
-    ) -> Result<(), crate::KonarrError>
+    ) -> Result<Vec<ProjectTransfers>, crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        for project in projects.iter_mut() {
-            if let Some(mut snapshot) = project.fetch_latest_snapshot(connection).await? {
-                match project.project_type {
-                    ProjectType::Container => {
-                        snapshot.calculate_alerts_summary(connection).await?;
-                    }
-                    _ => {
-                        warn!(
-                            "Project Type not supported for alerts: {:?}",
-                            project.project_type
-                        );
-                    }
+        ProjectTransfers::query(
+            connection,
+            ProjectTransfers::query_select()
+                .where_eq("project_id", self.id)
+                .order_by("id", QueryOrder::Desc)
+                .build()?,
+        )
+        .await
+        .map_err(|e| e.into())
+    }
+
+    /// Recalculate the alerts roll-up of a parent from the latest snapshots of its children
+    ///
+    /// Every severity is stored so a severity without alerts is reset to zero, a snapshot
+    /// shared by children (same image digest) is only counted once. The parent is as
+    /// unhealthy as its worst child.
+    pub async fn calculate_group_alerts<'a, T>(
+        &self,
+        connection: &'a T,
+    ) -> Result<AlertsSummary, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut summary: AlertsSummary = SECURITY_SEVERITY
+            .iter()
+            .map(|severity| (SecuritySeverity::from(severity.to_string()), 0))
+            .collect();
+
+        let children = Projects::query(
+            connection,
+            Projects::query_select()
+                .where_eq("status", ProjectStatus::Active)
+                .and()
+                .where_eq("parent", self.id)
+                .build()?,
+        )
+        .await?;
+        let mut counted = Vec::new();
+        let mut children_health = Vec::new();
+        for child in children.iter() {
+            if let Some(mut snapshot) = child.fetch_latest_snapshot(connection).await? {
+                if counted.contains(&snapshot.id) {
+                    continue;
                 }
+                counted.push(snapshot.id);
+                for (severity, count) in snapshot.calculate_alerts_summary(connection).await? {
+                    *summary.entry(severity).or_insert(0) += count;
+                }
+                children_health.push(snapshot.health());
             }
         }
 
-        Ok(())
+        if let Some(mut snapshot) = self.fetch_latest_snapshot(connection).await? {
+            debug!("Group('{}', snapshot='{}')", self.name, snapshot.id);
+            snapshot.calculate_alerts(connection, &summary).await?;
+            snapshot
+                .calculate_health(connection, &summary, &children_health)
+                .await?;
+        }
+        Ok(summary)
     }
 
-    /// Archive the Project
-    pub async fn archive<'a, T>(&mut self, connection: &'a T) -> Result<(), crate::KonarrError>
+    /// Get Top-Level Projects (see [Projects::fetch_children_batch] for their children)
+    pub async fn fetch_top_level<'a, T>(
+        connection: &'a T,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Self>, crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        self.status = ProjectStatus::Archived;
-        self.update(connection).await.map_err(|e| e.into())
+        debug!("Fetching top level projects");
+
+        let mut projects = Projects::query(
+            connection,
+            Projects::query_select()
+                .where_eq("status", ProjectStatus::Active)
+                .and()
+                .where_eq("parent", 0)
+                .order_by("created_at", QueryOrder::Desc)
+                .limit(limit)
+                .offset(offset)
+                .build()?,
+        )
+        .await?;
+
+        for proj in projects.iter_mut() {
+            proj.fetch_snapshots(connection).await?;
+        }
+
+        Ok(projects)
     }
-}
 
-/// Project Snapshots
-#[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
-pub struct ProjectSnapshots {
-    /// Primary Key
-    #[geekorm(primary_key, auto_increment)]
-    pub id: PrimaryKey<i32>,
-    /// Project ID
-    #[geekorm(foreign_key = "Projects.id")]
-    pub project_id: ForeignKey<i32, Projects>,
-    /// Snapshot ID
-    #[geekorm(foreign_key = "Snapshot.id")]
-    pub snapshot_id: ForeignKey<i32, Snapshot>,
+    /// Fetch active projects by type
+    pub async fn fetch_project_type<'a, T>(
+        connection: &'a T,
+        project_type: impl Into<ProjectType>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Self>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut projects = Projects::query(
+            connection,
+            Projects::query_select()
+                .where_eq("status", ProjectStatus::Active)
+                .and()
+                .where_eq("project_type", project_type.into())
+                .order_by("created_at", QueryOrder::Desc)
+                .limit(limit)
+                .offset(offset)
+                .build()?,
+        )
+        .await?;
+        for proj in projects.iter_mut() {
+            proj.fetch_snapshots(connection).await?;
+        }
 
-    /// Datetime Created
-    #[geekorm(new = "Utc::now()")]
-    pub created_at: DateTime<Utc>,
-}
+        Ok(projects)
+    }
 
-/// Project Type
-#[derive(Data, Debug, Default, Clone, PartialEq)]
-pub enum ProjectType {
-    /// Group of Projects
-    #[geekorm(aliases = "group,groups")]
-    Group,
-    /// Single Application
-    #[default]
-    #[geekorm(aliases = "app,application,applications")]
-    Application,
-    /// Server
-    #[geekorm(aliases = "server,servers")]
-    Server,
-    /// Cluster (Kubernetes, Docker Swarm, etc.)
-    #[geekorm(aliases = "cluster")]
-    Cluster,
-    /// Container
-    #[geekorm(aliases = "container,containers,docker")]
-    Container,
+    /// Find a list of projects by component in latest snapshot
+    pub async fn find_project_by_component<'a, T>(
+        connection: &'a T,
+        component_id: i32,
+    ) -> Result<Vec<Self>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut results = vec![];
+        // TODO: This is a terrible way to do this
+        let mut projects = Projects::query(connection, Projects::query_all()).await?;
+
+        for proj in projects.iter_mut() {
+            if let Some(snap) = proj.fetch_latest_snapshot(connection).await? {
+                let dep = Dependencies::query(
+                    connection,
+                    Dependencies::query_select()
+                        .where_eq("snapshot_id", snap.id)
+                        .and()
+                        .where_eq("component_id", component_id)
+                        .limit(1)
+                        .build()?,
+                )
+                .await?;
+
+                if dep.len() == 1 {
+                    results.push(proj.clone());
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Find all the possible parents
+    pub async fn find_parents<'a, T>(connection: &'a T) -> Result<Vec<Self>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        debug!("Finding all parent projects");
+        Ok(Projects::query(
+            connection,
+            Projects::query_select()
+                .where_eq("status", ProjectStatus::Active)
+                .and()
+                .where_eq("project_type", ProjectType::Server)
+                .order_by("name", QueryOrder::Asc)
+                .build()?,
+        )
+        .await?)
+    }
+
+    /// Get the projects children
+    pub async fn fetch_children<'a, T>(
+        &mut self,
+        connection: &'a T,
+    ) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        debug!("Fetching Children for Project: {:?}", self.id);
+
+        self.children = Projects::query(
+            connection,
+            Projects::query_select()
+                .where_eq("status", ProjectStatus::Active)
+                .and()
+                .where_eq("parent", self.id)
+                .order_by("created_at", QueryOrder::Desc)
+                .build()?,
+        )
+        .await?;
+        for child in self.children.iter_mut() {
+            child.fetch_snapshots(connection).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fetch the summaries of the children of the projects (active children only)
+    ///
+    /// The health and alerts come from the latest snapshot metadata so no snapshot is
+    /// loaded, the children of all the parents are fetched with two queries.
+    pub async fn fetch_child_summaries<'a, T>(
+        connection: &'a T,
+        parents: &[i32],
+        sort: ProjectChildrenSort,
+    ) -> Result<Vec<ProjectChild>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut children = Vec::new();
+        for batch in parents.chunks(PROJECT_BATCH_SIZE) {
+            let placeholders = vec!["?"; batch.len()].join(", ");
+            let mut values = Values::new();
+            values.push("status".to_string(), ProjectStatus::Active);
+            for (index, parent) in batch.iter().enumerate() {
+                values.push(format!("parent_{}", index), *parent);
+            }
+            let projects = T::query::<Projects>(
+                connection,
+                raw_query(
+                    format!(
+                        "SELECT * FROM Projects WHERE status = ? AND parent IN ({});",
+                        placeholders
+                    ),
+                    values,
+                ),
+            )
+            .await?;
+
+            let mut values = Values::new();
+            values.push(
+                "health_key".to_string(),
+                SnapshotMetadataKey::SecurityHealth,
+            );
+            values.push(
+                "alerts_key".to_string(),
+                SnapshotMetadataKey::SecurityAlertTotal,
+            );
+            values.push("status".to_string(), ProjectStatus::Active);
+            for (index, parent) in batch.iter().enumerate() {
+                values.push(format!("parent_{}", index), *parent);
+            }
+            let metrics: std::collections::HashMap<i32, ProjectChildRow> =
+                T::query::<ProjectChildRow>(
+                    connection,
+                    raw_query(
+                        format!(
+                            "SELECT Projects.id AS id, \
+                            COALESCE(CAST(health.value AS TEXT), 'green') AS health, \
+                            COALESCE(CAST(CAST(alerts.value AS TEXT) AS INTEGER), 0) AS alerts \
+                            FROM Projects \
+                            LEFT JOIN SnapshotMetadata AS health ON health.snapshot_id = {latest} \
+                                AND health.key = ? \
+                            LEFT JOIN SnapshotMetadata AS alerts ON alerts.snapshot_id = {latest} \
+                                AND alerts.key = ? \
+                            WHERE Projects.status = ? AND Projects.parent IN ({placeholders});",
+                            latest = LATEST_SNAPSHOT,
+                            placeholders = placeholders
+                        ),
+                        values,
+                    ),
+                )
+                .await?
+                .into_iter()
+                .map(|row| (row.id, row))
+                .collect();
+
+            children.extend(projects.into_iter().map(|project| {
+                let row = metrics.get(&project.id.into());
+                ProjectChild {
+                    health: row
+                        .map(|row| ProjectHealth::from(row.health.as_str()))
+                        .unwrap_or_default(),
+                    alerts: row.map(|row| row.alerts).unwrap_or_default(),
+                    project,
+                }
+            }));
+        }
+        sort.sort(&mut children);
+        Ok(children)
+    }
+
+    /// Fetch the children of the projects with their snapshots
+    ///
+    /// Same as [Projects::fetch_children] for a list of projects but the snapshots of all
+    /// the children are loaded in batches, the metadata is only loaded for the latest
+    /// snapshot of each child.
+    pub async fn fetch_children_batch<'a, T>(
+        connection: &'a T,
+        projects: &mut [Projects],
+        sort: ProjectChildrenSort,
+    ) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let parents: Vec<i32> = projects.iter().map(|project| project.id.into()).collect();
+        let mut children: Vec<Projects> = Self::fetch_child_summaries(connection, &parents, sort)
+            .await?
+            .into_iter()
+            .map(|child| child.project)
+            .collect();
+        let ids: Vec<i32> = children.iter().map(|child| child.id.into()).collect();
+
+        // Snapshots of the children
+        let mut links: Vec<ProjectSnapshots> = Vec::new();
+        for batch in ids.chunks(PROJECT_BATCH_SIZE) {
+            let mut values = Values::new();
+            for (index, id) in batch.iter().enumerate() {
+                values.push(format!("project_{}", index), *id);
+            }
+            links.extend(
+                T::query::<ProjectSnapshots>(
+                    connection,
+                    raw_query(
+                        format!(
+                            "SELECT * FROM ProjectSnapshots WHERE project_id IN ({});",
+                            vec!["?"; batch.len()].join(", ")
+                        ),
+                        values,
+                    ),
+                )
+                .await?,
+            );
+        }
+        let mut snapshot_ids: Vec<i32> = links
+            .iter()
+            .map(|link| link.snapshot_id.clone().into())
+            .collect();
+        snapshot_ids.sort();
+        snapshot_ids.dedup();
+
+        let mut snapshots: std::collections::HashMap<i32, Snapshot> =
+            std::collections::HashMap::new();
+        for batch in snapshot_ids.chunks(PROJECT_BATCH_SIZE) {
+            let mut values = Values::new();
+            for (index, id) in batch.iter().enumerate() {
+                values.push(format!("snapshot_{}", index), *id);
+            }
+            for snapshot in T::query::<Snapshot>(
+                connection,
+                raw_query(
+                    format!(
+                        "SELECT * FROM Snapshot WHERE id IN ({});",
+                        vec!["?"; batch.len()].join(", ")
+                    ),
+                    values,
+                ),
+            )
+            .await?
+            {
+                snapshots.insert(snapshot.id.into(), snapshot);
+            }
+        }
+
+        // Metadata of the latest snapshots
+        let mut latest: Vec<i32> = ids
+            .iter()
+            .filter_map(|id| {
+                links
+                    .iter()
+                    .filter(|link| link.project_id.key == *id)
+                    .map(|link| link.snapshot_id.key)
+                    .max()
+            })
+            .collect();
+        latest.sort();
+        latest.dedup();
+        for batch in latest.chunks(PROJECT_BATCH_SIZE) {
+            let mut values = Values::new();
+            for (index, id) in batch.iter().enumerate() {
+                values.push(format!("snapshot_{}", index), *id);
+            }
+            for metadata in T::query::<SnapshotMetadata>(
+                connection,
+                raw_query(
+                    format!(
+                        "SELECT * FROM SnapshotMetadata WHERE snapshot_id IN ({});",
+                        vec!["?"; batch.len()].join(", ")
+                    ),
+                    values,
+                ),
+            )
+            .await?
+            {
+                if let Some(snapshot) = snapshots.get_mut(&metadata.snapshot_id.key) {
+                    snapshot.metadata.insert(metadata.key.clone(), metadata);
+                }
+            }
+        }
+
+        for child in children.iter_mut() {
+            let mut ids: Vec<i32> = links
+                .iter()
+                .filter(|link| link.project_id.key == i32::from(child.id))
+                .map(|link| link.snapshot_id.key)
+                .collect();
+            ids.sort();
+            child.snapshots = ids
+                .iter()
+                .filter_map(|id| snapshots.get(id).cloned())
+                .collect();
+        }
+        for project in projects.iter_mut() {
+            let id: i32 = project.id.into();
+            project.children = children
+                .iter()
+                .filter(|child| child.parent == id)
+                .cloned()
+                .collect();
+        }
+        Ok(())
+    }
+
+    /// Fetch latest Snapshot
+    ///
+    /// This does not change the loaded snapshots so it can be called any number of times,
+    /// use [Projects::refresh_latest_snapshot] to update the loaded snapshots.
+    pub async fn fetch_latest_snapshot<'a, T>(
+        &self,
+        connection: &'a T,
+    ) -> Result<Option<Snapshot>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        match ProjectSnapshots::query_first(
+            connection,
+            ProjectSnapshots::query_select()
+                .where_eq("project_id", self.id)
+                .order_by("snapshot_id", QueryOrder::Desc)
+                .limit(1)
+                .build()?,
+        )
+        .await
+        {
+            Ok(snap) => Ok(Some(
+                Snapshot::fetch_by_primary_key(connection, snap.snapshot_id).await?,
+            )),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Refresh the latest Snapshot (with metadata) in the loaded snapshots
+    ///
+    /// The snapshot is replaced in place if it is already loaded, otherwise it is appended.
+    pub async fn refresh_latest_snapshot<'a, T>(
+        &mut self,
+        connection: &'a T,
+    ) -> Result<Option<&Snapshot>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let Some(mut latest) = self.fetch_latest_snapshot(connection).await? else {
+            return Ok(None);
+        };
+        latest.fetch_metadata(connection).await?;
+        let latest_id = latest.id;
+
+        match self.snapshots.iter().position(|s| s.id == latest_id) {
+            Some(index) => self.snapshots[index] = latest,
+            None => {
+                self.snapshots.push(latest);
+                self.snapshots.sort_by_key(|s| i32::from(s.id));
+            }
+        }
+        Ok(self.snapshots.iter().find(|s| s.id == latest_id))
+    }
+
+    /// Add snapshot to project
+    pub async fn add_snapshot<'a, T>(
+        &mut self,
+        connection: &'a T,
+        snapshot: Snapshot,
+    ) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        debug!("Adding Snapshot to Project: {:?}", self.id);
+        let mut snap = ProjectSnapshots {
+            project_id: self.id.into(),
+            snapshot_id: snapshot.id.into(),
+            ..Default::default()
+        };
+
+        snap.save(connection).await?;
+
+        self.snapshots.push(snapshot);
+
+        Ok(())
+    }
+    /// Remove a Snapshot from the project
+    ///
+    /// The snapshot itself is only removed if no other project uses it (shared image
+    /// snapshots). Returns if the snapshot was removed.
+    pub async fn remove_snapshot<'a, T>(
+        &mut self,
+        connection: &'a T,
+        snapshot: &Snapshot,
+    ) -> Result<bool, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + TransactionConnection + 'a,
+    {
+        self.snapshots.retain(|s| s.id != snapshot.id);
+
+        let shared = ProjectSnapshots::row_count(
+            connection,
+            ProjectSnapshots::query_count()
+                .where_eq("snapshot_id", snapshot.id)
+                .and()
+                .where_ne("project_id", self.id)
+                .build()?,
+        )
+        .await?;
+        if shared > 0 {
+            debug!(
+                "Snapshot({}) is shared, unlinking from Project({})",
+                snapshot.id, self.id
+            );
+            let mut values = Values::new();
+            values.push("project_id".to_string(), self.id);
+            values.push("snapshot_id".to_string(), snapshot.id);
+            T::execute::<Self>(
+                connection,
+                raw_query(
+                    "DELETE FROM ProjectSnapshots WHERE project_id = ? AND snapshot_id = ?;",
+                    values,
+                ),
+            )
+            .await?;
+            return Ok(false);
+        }
+
+        snapshot.purge(connection).await?;
+        Ok(true)
+    }
+
+    /// Fetch Snapshots for the Project
+    pub async fn fetch_snapshots<'a, T>(
+        &mut self,
+        connection: &'a T,
+    ) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let snaps = ProjectSnapshots::fetch_by_project_id(connection, self.id).await?;
+
+        // Reload the snapshots (calling this more than once must not duplicate them)
+        self.snapshots.clear();
+        for snap in snaps {
+            let mut snaps = Snapshot::fetch_by_primary_key(connection, snap.snapshot_id).await?;
+            snaps.fetch_metadata(connection).await?;
+
+            self.snapshots.push(snaps);
+        }
+        Ok(())
+    }
+
+    /// Last time the project was scanned by the agent (latest snapshot)
+    ///
+    /// The agent reuses the snapshot while the SBOM is unchanged but reports the runtime
+    /// metadata (`container.sha` for containers, `os` for servers) on every run.
+    /// Requires the snapshots to be fetched.
+    pub fn last_scanned_at(&self) -> Option<DateTime<Utc>> {
+        let snapshot = self.snapshots.last()?;
+        ["container.sha", "os"]
+            .iter()
+            .filter_map(|key| snapshot.find_metadata(key))
+            .map(|metadata| metadata.updated_at)
+            .chain([snapshot.created_at])
+            .max()
+    }
+
+    /// Check if the project was not scanned in the last `days` days (0 disables the check)
+    ///
+    /// Projects without snapshots were never scanned and are not outdated.
+    pub fn is_scan_outdated(&self, now: DateTime<Utc>, days: i64) -> bool {
+        match self.last_scanned_at() {
+            Some(scanned) if days > 0 => now - scanned > chrono::Duration::days(days),
+            _ => false,
+        }
+    }
+
+    /// Check if the latest scan is stale (the running container drifted from the scanned image)
+    ///
+    /// The agent reports the running digest (`container.sha`) on every run and the digest
+    /// the SBOM was generated from (`scan.container.sha`) when it scans the container.
+    /// Requires the snapshots to be fetched.
+    pub fn is_scan_stale(&self) -> bool {
+        let sha = |snapshot: &Snapshot, key: &str| {
+            snapshot
+                .find_metadata(key)
+                .map(|m| m.as_string())
+                .filter(|sha| !sha.is_empty())
+        };
+
+        let Some(running) = self
+            .snapshots
+            .last()
+            .and_then(|snap| sha(snap, "container.sha"))
+        else {
+            return false;
+        };
+        match self
+            .snapshots
+            .iter()
+            .rev()
+            .find_map(|snap| sha(snap, "scan.container.sha"))
+        {
+            Some(scanned) => scanned != running,
+            None => false,
+        }
+    }
+
+    /// Calculate Alerts for all projects with snapshots
+    pub async fn calculate_alerts<'a, T>(
+        connection: &'a T,
+        projects: &mut Vec<Self>,
+    ) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        for project in projects.iter_mut() {
+            if let Some(mut snapshot) = project.fetch_latest_snapshot(connection).await? {
+                match project.project_type {
+                    ProjectType::Container => {
+                        snapshot.calculate_alerts_summary(connection).await?;
+                    }
+                    _ => {
+                        warn!(
+                            "Project Type not supported for alerts: {:?}",
+                            project.project_type
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Generate a new feed token (the previous token no longer works)
+    pub async fn regenerate_feed_token<'a, T>(
+        &mut self,
+        connection: &'a T,
+    ) -> Result<String, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let token = format!(
+            "{}{}",
+            PROJECT_FEED_TOKEN_PREFIX,
+            crate::utils::rand::generate_random_string(32)
+        );
+        self.feed_token = Some(token.clone());
+        self.update(connection).await?;
+        Ok(token)
+    }
+
+    /// Check the feed token of the project (projects without a token never match)
+    pub fn check_feed_token(&self, token: &str) -> bool {
+        match &self.feed_token {
+            Some(feed_token) if !feed_token.is_empty() && feed_token.len() == token.len() => {
+                // Compare every byte so the time does not depend on the matching prefix
+                feed_token
+                    .bytes()
+                    .zip(token.bytes())
+                    .fold(0, |acc, (a, b)| acc | (a ^ b))
+                    == 0
+            }
+            _ => false,
+        }
+    }
+
+    /// Archive the Project
+    ///
+    /// The snapshots of the project are kept (and not removed by the retention policies)
+    /// so the project can be restored.
+    pub async fn archive<'a, T>(&mut self, connection: &'a T) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        self.status = ProjectStatus::Archived;
+        self.archived_at = Some(Utc::now());
+        self.update(connection).await.map_err(|e| e.into())
+    }
+
+    /// Restore an archived Project (Archived -> Active)
+    pub async fn restore<'a, T>(&mut self, connection: &'a T) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        if self.status != ProjectStatus::Archived {
+            return Err(crate::KonarrError::InvalidData(format!(
+                "Project `{}` is not archived",
+                self.name
+            )));
+        }
+        self.status = ProjectStatus::Active;
+        self.archived_at = None;
+        self.update(connection).await.map_err(|e| e.into())
+    }
+}
+
+/// Project Snapshots
+#[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProjectSnapshots {
+    /// Primary Key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+    /// Project ID
+    #[geekorm(foreign_key = "Projects.id")]
+    pub project_id: ForeignKey<i32, Projects>,
+    /// Snapshot ID
+    #[geekorm(foreign_key = "Snapshot.id")]
+    pub snapshot_id: ForeignKey<i32, Snapshot>,
+
+    /// Datetime Created
+    #[geekorm(new = "Utc::now()")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Container image tracked by more than one Project
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DuplicateImage {
+    /// Image digest (`container.sha` metadata)
+    pub digest: String,
+    /// Latest snapshots of the projects (a snapshot shared by projects is only listed once)
+    pub snapshots: Vec<i32>,
+    /// Projects tracking the image
+    pub projects: Vec<Projects>,
+}
+
+/// Project with ports published on the host (see [Projects::fetch_exposed])
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ExposedProject {
+    /// Project
+    pub project: Projects,
+    /// Ports published on the host
+    pub ports: Vec<ContainerPort>,
+    /// Networks the container is connected to
+    pub networks: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectExposureRow {
+    project_id: i32,
+    ports: String,
+    networks: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectDigestRow {
+    project_id: i32,
+    snapshot_id: i32,
+    digest: String,
+}
+
+/// Project Transfers (history of the parents of a project)
+#[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProjectTransfers {
+    /// Primary Key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+    /// Project ID
+    #[geekorm(foreign_key = "Projects.id")]
+    pub project_id: ForeignKey<i32, Projects>,
+    /// Previous parent (0 for top-level projects)
+    pub from_parent: i32,
+    /// New parent
+    pub to_parent: i32,
+    /// Name before the transfer
+    pub old_name: String,
+    /// Name after the transfer (same as the old name if it was not renamed)
+    pub new_name: String,
+    /// User who transferred the project
+    pub user: String,
+
+    /// Datetime Created
+    #[geekorm(new = "Utc::now()")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Project Aliases (names of the projects merged into a project)
+#[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProjectAliases {
+    /// Primary Key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+    /// Project the alias resolves to
+    #[geekorm(foreign_key = "Projects.id")]
+    pub project_id: ForeignKey<i32, Projects>,
+    /// Name of the merged project
+    #[geekorm(unique)]
+    pub alias: String,
+
+    /// Datetime Created
+    #[geekorm(new = "Utc::now()")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Options of a Project merge (see [Projects::merge_into])
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProjectMerge {
+    /// Keep the name of the merged project as an alias of the target
+    pub alias: bool,
+    /// Remove the merged project (archived by default)
+    pub delete_source: bool,
+}
+
+/// Summary of a Project merge
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProjectMergeSummary {
+    /// Snapshots of the merged project (including the snapshots already in the target)
+    pub snapshots: usize,
+    /// Children moved to the target
+    pub children: usize,
+    /// Alias created for the name of the merged project
+    pub alias: Option<String>,
+}
+
+/// Children of the projects included in the responses
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectChildren {
+    /// No children
+    None,
+    /// Summary of the children (name, type and health), no snapshot is loaded
+    #[default]
+    Summary,
+    /// Children with their latest snapshot
+    Full,
+}
+
+impl std::str::FromStr for ProjectChildren {
+    type Err = crate::KonarrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(ProjectChildren::None),
+            "summary" => Ok(ProjectChildren::Summary),
+            "full" => Ok(ProjectChildren::Full),
+            _ => Err(crate::KonarrError::InvalidData(format!(
+                "Unknown children `{}` (available: none, summary, full)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Order of the children of the projects
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectChildrenSort {
+    /// Title (or name) of the children
+    #[default]
+    Name,
+    /// Newest children first
+    Created,
+    /// Children with the most alerts first
+    Alerts,
+}
+
+impl ProjectChildrenSort {
+    /// Sort the children
+    pub fn sort(&self, children: &mut [ProjectChild]) {
+        let title = |child: &ProjectChild| {
+            child
+                .project
+                .title
+                .clone()
+                .unwrap_or_else(|| child.project.name.clone())
+                .to_lowercase()
+        };
+        match self {
+            ProjectChildrenSort::Name => children.sort_by_key(title),
+            ProjectChildrenSort::Created => {
+                children.sort_by_key(|child| std::cmp::Reverse(child.project.created_at))
+            }
+            ProjectChildrenSort::Alerts => {
+                children.sort_by(|a, b| b.alerts.cmp(&a.alerts).then(title(a).cmp(&title(b))))
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for ProjectChildrenSort {
+    type Err = crate::KonarrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "name" => Ok(ProjectChildrenSort::Name),
+            "created" => Ok(ProjectChildrenSort::Created),
+            "alerts" => Ok(ProjectChildrenSort::Alerts),
+            _ => Err(crate::KonarrError::InvalidData(format!(
+                "Unknown children sort `{}` (available: name, created, alerts)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Child of a Project with the health and alerts of its latest snapshot
+#[derive(Debug, Clone)]
+pub struct ProjectChild {
+    /// Child project (the snapshots are not loaded)
+    pub project: Projects,
+    /// Health of the latest snapshot
+    pub health: ProjectHealth,
+    /// Number of alerts of the latest snapshot
+    pub alerts: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectChildRow {
+    id: i32,
+    health: String,
+    alerts: i64,
+}
+
+/// Project Type
+#[derive(Data, Debug, Default, Clone, PartialEq)]
+pub enum ProjectType {
+    /// Group of Projects
+    #[geekorm(aliases = "group,groups")]
+    Group,
+    /// Single Application
+    #[default]
+    #[geekorm(aliases = "app,application,applications")]
+    Application,
+    /// Server
+    #[geekorm(aliases = "server,servers")]
+    Server,
+    /// Cluster (Kubernetes, Docker Swarm, etc.)
+    #[geekorm(aliases = "cluster")]
+    Cluster,
+    /// Container
+    #[geekorm(aliases = "container,containers,docker")]
+    Container,
+}
+
+/// Filters on the metrics of the latest snapshot of the projects
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ProjectFilters {
+    /// Project type
+    pub project_type: Option<ProjectType>,
+    /// Minimum number of alerts (of `severity_at_least` or more severe if set)
+    pub min_alerts: Option<u32>,
+    /// Only count the alerts of this severity or more severe
+    pub severity_at_least: Option<SecuritySeverity>,
+    /// Minimum number of dependencies
+    pub min_dependencies: Option<u32>,
+    /// Health of the project (`security.health`, projects without it are green)
+    pub health: Option<ProjectHealth>,
+    /// Metadata values of the latest snapshot (case-insensitive, see [PROJECT_METADATA_FILTERS])
+    pub metadata: Vec<(SnapshotMetadataKey, String)>,
+}
+
+/// Metadata keys the projects can be filtered on (`metadata.<name>=<value>`)
+///
+/// Only a few indexed keys are allowed so the filters can't be used on arbitrary metadata.
+pub const PROJECT_METADATA_FILTERS: [(&str, SnapshotMetadataKey); 6] = [
+    ("registry", SnapshotMetadataKey::ContainerImageRegistry),
+    ("repository", SnapshotMetadataKey::ContainerImageRepository),
+    ("tag", SnapshotMetadataKey::ContainerImageTag),
+    ("os", SnapshotMetadataKey::Os),
+    ("arch", SnapshotMetadataKey::OsArch),
+    ("engine", SnapshotMetadataKey::ContainerEngine),
+];
+
+/// Project with the metrics used by the [ProjectFilters]
+#[derive(Debug, Clone)]
+pub struct ProjectMetrics {
+    /// Project
+    pub project: Projects,
+    /// Number of alerts (of `severity_at_least` or more severe if set)
+    pub alerts: i64,
+    /// Number of dependencies
+    pub dependencies: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectMetricsRow {
+    id: i32,
+    alerts: i64,
+    dependencies: i64,
+}
+
+impl ProjectFilters {
+    /// If any metric filter is set
+    pub fn is_empty(&self) -> bool {
+        self.min_alerts.is_none()
+            && self.severity_at_least.is_none()
+            && self.min_dependencies.is_none()
+            && self.health.is_none()
+            && self.metadata.is_empty()
+    }
+
+    /// Add a metadata filter by its name (see [PROJECT_METADATA_FILTERS])
+    pub fn with_metadata(
+        mut self,
+        name: &str,
+        value: impl Into<String>,
+    ) -> Result<Self, crate::KonarrError> {
+        let key = PROJECT_METADATA_FILTERS
+            .iter()
+            .find(|(filter, _)| *filter == name)
+            .map(|(_, key)| key.clone())
+            .ok_or_else(|| {
+                crate::KonarrError::InvalidData(format!("Unknown metadata filter: {}", name))
+            })?;
+        self.metadata.push((key, value.into()));
+        Ok(self)
+    }
+
+    /// Metadata keys summed for the alerts metric
+    fn alert_keys(&self) -> Vec<SnapshotMetadataKey> {
+        match &self.severity_at_least {
+            Some(severity) => SECURITY_SEVERITY
+                .iter()
+                .map(|name| SecuritySeverity::from(*name))
+                .filter(|other| other >= severity)
+                .map(|other| other.metadata_key())
+                .collect(),
+            None => vec![SnapshotMetadataKey::SecurityAlertTotal],
+        }
+    }
+
+    /// Query (without ordering) of the matching projects and their metrics
+    ///
+    /// The metadata values are stored as bytes so they are cast to text then integers.
+    fn query(&self) -> (String, Values) {
+        let alert_keys = self.alert_keys();
+        let mut values = Values::new();
+        for (index, key) in alert_keys.iter().enumerate() {
+            values.push(format!("alerts_key_{}", index), key.clone());
+        }
+        values.push(
+            "dependencies_key".to_string(),
+            SnapshotMetadataKey::DependenciesTotal,
+        );
+        if self.health.is_some() {
+            values.push(
+                "health_key".to_string(),
+                SnapshotMetadataKey::SecurityHealth,
+            );
+        }
+        for (index, (key, value)) in self.metadata.iter().enumerate() {
+            values.push(format!("metadata_key_{}", index), key.clone());
+            values.push(format!("metadata_value_{}", index), value.clone());
+        }
+        values.push("status".to_string(), ProjectStatus::Active);
+
+        let mut query = format!(
+            "SELECT Projects.id AS id, \
+            COALESCE(SUM(CAST(CAST(alerts.value AS TEXT) AS INTEGER)), 0) AS alerts, \
+            COALESCE(MAX(CAST(CAST(dependencies.value AS TEXT) AS INTEGER)), 0) AS dependencies \
+            FROM Projects \
+            LEFT JOIN SnapshotMetadata AS alerts ON alerts.snapshot_id = {latest} \
+                AND alerts.key IN ({keys}) \
+            LEFT JOIN SnapshotMetadata AS dependencies ON dependencies.snapshot_id = {latest} \
+                AND dependencies.key = ? \
+            {health} \
+            {metadata} \
+            WHERE Projects.status = ?",
+            latest = LATEST_SNAPSHOT,
+            keys = vec!["?"; alert_keys.len()].join(", "),
+            health = match self.health {
+                Some(_) => format!(
+                    "LEFT JOIN SnapshotMetadata AS health ON health.snapshot_id = {} \
+                        AND health.key = ?",
+                    LATEST_SNAPSHOT
+                ),
+                None => String::new(),
+            },
+            metadata = (0..self.metadata.len())
+                .map(|index| format!(
+                    "JOIN SnapshotMetadata AS metadata_{index} ON metadata_{index}.snapshot_id = {latest} \
+                        AND metadata_{index}.key = ? \
+                        AND LOWER(CAST(metadata_{index}.value AS TEXT)) = LOWER(?)",
+                    index = index,
+                    latest = LATEST_SNAPSHOT
+                ))
+                .collect::<Vec<String>>()
+                .join(" "),
+        );
+        if let Some(project_type) = &self.project_type {
+            query.push_str(" AND Projects.project_type = ?");
+            values.push("project_type".to_string(), project_type.clone());
+        }
+        if let Some(health) = &self.health {
+            query.push_str(" AND COALESCE(CAST(health.value AS TEXT), 'green') = ?");
+            values.push("health".to_string(), health.to_string());
+        }
+        // Filtering on a severity without a minimum matches any open alert
+        let min_alerts = self
+            .min_alerts
+            .or(self.severity_at_least.as_ref().map(|_| 1))
+            .unwrap_or(0);
+        query.push_str(" GROUP BY Projects.id HAVING alerts >= ? AND dependencies >= ?");
+        values.push("min_alerts".to_string(), min_alerts as i32);
+        values.push(
+            "min_dependencies".to_string(),
+            self.min_dependencies.unwrap_or(0) as i32,
+        );
+        (query, values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_feed_token() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("server/app", ProjectType::Container);
+        project.save(&connection).await?;
+        // No token (feeds require a session)
+        assert!(!project.check_feed_token(""));
+
+        let token = project.regenerate_feed_token(&connection).await?;
+        assert!(token.starts_with(PROJECT_FEED_TOKEN_PREFIX));
+        let project = Projects::fetch_by_primary_key(&connection, project.id).await?;
+        assert!(project.check_feed_token(&token));
+        assert!(!project.check_feed_token("konarr-feed-invalid"));
+
+        // Regenerating revokes the previous token
+        let mut project = project;
+        let new_token = project.regenerate_feed_token(&connection).await?;
+        assert_ne!(token, new_token);
+        assert!(!project.check_feed_token(&token));
+        assert!(project.check_feed_token(&new_token));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sync_labels() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("server/app", ProjectType::Container);
+        project.description = Some("Old description".to_string());
+        project.save(&connection).await?;
+
+        // Agent updates from the labels
+        assert!(project.sync_labels(None, Some("New description".to_string())));
+        assert!(!project.sync_labels(None, Some("New description".to_string())));
+        project.update(&connection).await?;
+
+        let mut project = Projects::fetch_by_primary_key(&connection, project.id).await?;
+        assert_eq!(project.description, Some("New description".to_string()));
+        assert!(!project.edited_by_user);
+
+        // Manual edits win over the labels
+        assert!(project.edit(Some("My App".to_string()), None));
+        project.update(&connection).await?;
+
+        let mut project = Projects::fetch_by_primary_key(&connection, project.id).await?;
+        assert!(project.edited_by_user);
+        assert!(!project.sync_labels(
+            Some("app".to_string()),
+            Some("Label description".to_string())
+        ));
+        assert_eq!(project.title, Some("My App".to_string()));
+        assert_eq!(project.description, Some("New description".to_string()));
+
+        // Editing without changes does not mark the project
+        let mut other = Projects::new("server/other", ProjectType::Container);
+        assert!(!other.edit(None, Some(String::new())));
+        assert!(!other.edited_by_user);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_latest_snapshot_reuse() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("server/app", ProjectType::Container);
+        project.save(&connection).await?;
+        assert!(project.fetch_latest_snapshot(&connection).await?.is_none());
+        assert!(project
+            .refresh_latest_snapshot(&connection)
+            .await?
+            .is_none());
+
+        let first = Snapshot::create(&connection).await?;
+        project.add_snapshot(&connection, first.clone()).await?;
+
+        // Calling the methods more than once on the same instance
+        for _ in 0..2 {
+            let latest = project.fetch_latest_snapshot(&connection).await?.unwrap();
+            assert_eq!(latest.id, first.id);
+            project.fetch_snapshots(&connection).await?;
+            assert_eq!(project.snapshots.len(), 1);
+            project.refresh_latest_snapshot(&connection).await?;
+            assert_eq!(project.snapshots.len(), 1);
+        }
+
+        // A snapshot added through another instance
+        let mut other = Projects::fetch_by_primary_key(&connection, project.id).await?;
+        let second = Snapshot::create(&connection).await?;
+        other.add_snapshot(&connection, second.clone()).await?;
+
+        let latest = project.refresh_latest_snapshot(&connection).await?.unwrap();
+        assert_eq!(latest.id, second.id);
+        assert_eq!(project.snapshots.len(), 2);
+        assert_eq!(project.snapshots.last().map(|s| s.id), Some(second.id));
+
+        // Wrappers fetching the latest snapshot
+        let mut projects = vec![project.clone(), project.clone()];
+        Projects::calculate_alerts(&connection, &mut projects).await?;
+        Projects::calculate_alerts(&connection, &mut projects).await?;
+        assert!(projects.iter().all(|p| p.snapshots.len() == 2));
+
+        let mut dependency =
+            Dependencies::from_purl(&connection, "pkg:deb/debian/openssl@3.0.1".to_string())
+                .await?;
+        dependency.snapshot_id = second.id.into();
+        dependency.save(&connection).await?;
+        for _ in 0..2 {
+            let found =
+                Projects::find_project_by_component(&connection, dependency.component_id.key)
+                    .await?;
+            assert_eq!(found.len(), 1);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transfer() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut server_a = Projects::new("server-a", ProjectType::Server);
+        server_a.save(&connection).await?;
+        let mut server_b = Projects::new("server-b", ProjectType::Server);
+        server_b.save(&connection).await?;
+
+        let mut web = Projects::new("server-a/web", ProjectType::Container);
+        web.parent = server_a.id.into();
+        web.save(&connection).await?;
+        let mut db = Projects::new("server-a/db", ProjectType::Container);
+        db.parent = server_a.id.into();
+        db.save(&connection).await?;
+
+        // Containers, the project itself and its children are not valid targets
+        assert!(web
+            .transfer(&connection, &db, false, "admin")
+            .await
+            .is_err());
+        assert!(server_a
+            .transfer(&connection, &server_a.clone(), false, "admin")
+            .await
+            .is_err());
+        let mut group = Projects::new("group", ProjectType::Group);
+        group.parent = server_a.id.into();
+        group.save(&connection).await?;
+        assert!(server_a
+            .transfer(&connection, &group, false, "admin")
+            .await
+            .is_err());
+
+        // Transfer without renaming
+        let transfer = db.transfer(&connection, &server_b, false, "admin").await?;
+        assert_eq!(transfer.from_parent, i32::from(server_a.id));
+        assert_eq!(transfer.to_parent, i32::from(server_b.id));
+        assert_eq!(transfer.old_name, transfer.new_name);
+        let db = Projects::fetch_by_primary_key(&connection, db.id).await?;
+        assert_eq!(db.parent, i32::from(server_b.id));
+        assert_eq!(db.name, "server-a/db");
+        // The agent on the new server finds the project by its relative name
+        let found =
+            Projects::fetch_transferred(&connection, "server-b/db", server_b.id.into()).await?;
+        assert_eq!(found.id, db.id);
+
+        // Transfer with the new prefix
+        web.transfer(&connection, &server_b, true, "admin").await?;
+        let web = Projects::fetch_by_primary_key(&connection, web.id).await?;
+        assert_eq!(web.name, "server-b/web");
+        // Agents using the previous name find the transferred project
+        let found =
+            Projects::fetch_transferred(&connection, "server-a/web", server_a.id.into()).await?;
+        assert_eq!(found.id, web.id);
+        assert!(
+            Projects::fetch_transferred(&connection, "server-a/other", server_a.id.into())
+                .await
+                .is_err()
+        );
+
+        // The new name is already used
+        let mut other = Projects::new("server-a/web", ProjectType::Container);
+        other.parent = server_a.id.into();
+        other.save(&connection).await?;
+        let mut web = web;
+        assert!(web
+            .transfer(&connection, &server_a, true, "admin")
+            .await
+            .is_err());
+        web.transfer(&connection, &server_a, false, "admin").await?;
+
+        let transfers = web.fetch_transfers(&connection).await?;
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[0].to_parent, i32::from(server_a.id));
+        assert_eq!(transfers[1].old_name, "server-a/web");
+        assert_eq!(transfers[1].new_name, "server-b/web");
+
+        // Roll-up of the alerts (every severity is reset)
+        let mut snapshot = Snapshot::create(&connection).await?;
+        server_b.add_snapshot(&connection, snapshot.clone()).await?;
+        let summary = server_b.calculate_group_alerts(&connection).await?;
+        assert!(summary.values().all(|count| *count == 0));
+        snapshot.fetch_metadata(&connection).await?;
+        assert_eq!(snapshot.find_metadata_usize("security.alerts.critical"), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_images() -> Result<(), crate::KonarrError> {
+        use crate::models::{
+            dependencies::snapshots::SnapshotState,
+            security::{Advisories, AdvisorySource, Alerts},
+        };
+
+        let connection = crate::models::database_test().await?;
+
+        let mut server = Projects::new("server", ProjectType::Server);
+        server.save(&connection).await?;
+        server
+            .add_snapshot(&connection, Snapshot::create(&connection).await?)
+            .await?;
+
+        let mut snapshot = Snapshot::create(&connection).await?;
+        let mut other = Snapshot::create(&connection).await?;
+        for (snap, sha) in [(&mut snapshot, "sha256:aaaa"), (&mut other, "sha256:bbbb")] {
+            snap.set_metadata(&connection, SnapshotMetadataKey::ContainerSha, sha)
+                .await?;
+            snap.set_metadata(&connection, SnapshotMetadataKey::ScanContainerSha, sha)
+                .await?;
+        }
+        let mut dependency =
+            Dependencies::from_purl(&connection, "pkg:deb/debian/openssl@3.0.1".to_string())
+                .await?;
+        dependency.snapshot_id = snapshot.id.into();
+        dependency.save(&connection).await?;
+        let mut advisory = Advisories::new(
+            "CVE-0001",
+            AdvisorySource::Unknown,
+            SecuritySeverity::Critical,
+        );
+        advisory.save(&connection).await?;
+        let mut alert = Alerts {
+            dependency_id: Some(dependency.id.into()),
+            ..Alerts::new(advisory.name.clone(), snapshot.id, advisory.id)
+        };
+        alert.save(&connection).await?;
+
+        // Only completed snapshots are found by the digest
+        assert!(Snapshot::find_by_container_sha(&connection, "sha256:aaaa")
+            .await?
+            .is_none());
+        snapshot
+            .set_state(&connection, SnapshotState::Completed)
+            .await?;
+        let found = Snapshot::find_by_container_sha(&connection, "sha256:aaaa")
+            .await?
+            .unwrap();
+        assert_eq!(found.id, snapshot.id);
+
+        // Two projects sharing the snapshot, one with another image
+        for (name, snap) in [("web", &snapshot), ("web-copy", &snapshot), ("db", &other)] {
+            let mut project = Projects::new(format!("server/{}", name), ProjectType::Container);
+            project.parent = server.id.into();
+            project.save(&connection).await?;
+            project.add_snapshot(&connection, snap.clone()).await?;
+        }
+
+        let duplicates = Projects::fetch_duplicate_images(&connection).await?;
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].digest, "sha256:aaaa");
+        assert_eq!(duplicates[0].snapshots, vec![i32::from(snapshot.id)]);
+        let names: Vec<&str> = duplicates[0]
+            .projects
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["server/web", "server/web-copy"]);
+
+        // The shared snapshot is only counted once by the parent
+        let summary = server.calculate_group_alerts(&connection).await?;
+        assert_eq!(summary.get(&SecuritySeverity::Critical), Some(&1));
+
+        // A critical alert is red (default thresholds), the parent is as red as its child
+        let mut latest = server.fetch_latest_snapshot(&connection).await?.unwrap();
+        latest.fetch_metadata(&connection).await?;
+        assert_eq!(latest.health(), ProjectHealth::Red);
+        let filters = |health: ProjectHealth| ProjectFilters {
+            health: Some(health),
+            ..Default::default()
+        };
+        assert_eq!(
+            Projects::count_filtered(&connection, &filters(ProjectHealth::Red)).await?,
+            3
+        );
+        let green = Projects::fetch_filtered(&connection, &filters(ProjectHealth::Green), 10, 0)
+            .await?
+            .into_iter()
+            .map(|metrics| metrics.project.name)
+            .collect::<Vec<String>>();
+        assert!(green.contains(&"server/db".to_string()));
+        assert!(!green.contains(&"server/web".to_string()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exposed_projects() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        for (name, ports) in [
+            ("web", "0.0.0.0:8080->80/tcp,443/tcp"),
+            ("db", "5432/tcp"),
+            ("archived", "0.0.0.0:9000->9000/tcp"),
+        ] {
+            let mut project = Projects::new(name, ProjectType::Container);
+            project.save(&connection).await?;
+            let mut snapshot = Snapshot::create(&connection).await?;
+            project.add_snapshot(&connection, snapshot.clone()).await?;
+            snapshot
+                .set_metadata(
+                    &connection,
+                    SnapshotMetadataKey::ContainerPorts,
+                    &SnapshotMetadataKey::ContainerPorts.normalize(ports)?,
+                )
+                .await?;
+            snapshot
+                .set_metadata(
+                    &connection,
+                    SnapshotMetadataKey::ContainerNetworks,
+                    "web,proxy",
+                )
+                .await?;
+            if name == "archived" {
+                project.archive(&connection).await?;
+            }
+        }
+
+        let exposed = Projects::fetch_exposed(&connection).await?;
+        assert_eq!(exposed.len(), 1);
+        assert_eq!(exposed[0].project.name, "web");
+        assert_eq!(exposed[0].ports.len(), 1);
+        assert_eq!(exposed[0].ports[0].to_string(), "0.0.0.0:8080->80/tcp");
+        assert_eq!(exposed[0].networks, vec!["web", "proxy"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_project_filters() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        // (name, type, metadata of the latest snapshot)
+        let fixtures: [(&str, ProjectType, &[(&str, &str)]); 4] = [
+            (
+                "web",
+                ProjectType::Container,
+                &[
+                    ("dependencies.total", "812"),
+                    ("security.alerts.total", "7"),
+                    ("security.alerts.critical", "2"),
+                    ("security.alerts.high", "1"),
+                    ("security.alerts.low", "4"),
+                ],
+            ),
+            (
+                "db",
+                ProjectType::Container,
+                &[
+                    ("dependencies.total", "120"),
+                    ("security.alerts.total", "3"),
+                    ("security.alerts.medium", "3"),
+                ],
+            ),
+            ("cache", ProjectType::Container, &[]),
+            (
+                "homelab",
+                ProjectType::Server,
+                &[
+                    ("dependencies.total", "1500"),
+                    ("security.alerts.total", "1"),
+                    ("security.alerts.critical", "1"),
+                ],
+            ),
+        ];
+        for (name, project_type, metadata) in fixtures {
+            let mut project = Projects::new(name, project_type);
+            project.save(&connection).await?;
+
+            // Older snapshot with more alerts (only the latest is used)
+            let mut old = Snapshot::create(&connection).await?;
+            old.set_metadata(
+                &connection,
+                SnapshotMetadataKey::SecurityAlertCritical,
+                "50",
+            )
+            .await?;
+            project.add_snapshot(&connection, old).await?;
+
+            let mut snapshot = Snapshot::create(&connection).await?;
+            for (key, value) in metadata {
+                snapshot.set_metadata(&connection, *key, *value).await?;
+            }
+            project.add_snapshot(&connection, snapshot).await?;
+        }
+
+        let names = |projects: Vec<ProjectMetrics>| {
+            projects
+                .into_iter()
+                .map(|p| (p.project.name, p.alerts, p.dependencies))
+                .collect::<Vec<(String, i64, i64)>>()
+        };
+
+        // All container projects with more than 0 critical alerts
+        let filters = ProjectFilters {
+            project_type: Some(ProjectType::Container),
+            severity_at_least: Some(SecuritySeverity::Critical),
+            ..Default::default()
+        };
+        assert_eq!(
+            names(Projects::fetch_filtered(&connection, &filters, 10, 0).await?),
+            vec![("web".to_string(), 2, 812)]
+        );
+        assert_eq!(Projects::count_filtered(&connection, &filters).await?, 1);
+
+        // High or more severe (critical + high)
+        let filters = ProjectFilters {
+            min_alerts: Some(3),
+            severity_at_least: Some(SecuritySeverity::High),
+            ..Default::default()
+        };
+        assert_eq!(
+            names(Projects::fetch_filtered(&connection, &filters, 10, 0).await?),
+            vec![("web".to_string(), 3, 812)]
+        );
+
+        // Total alerts (newest project first)
+        let filters = ProjectFilters {
+            min_alerts: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            names(Projects::fetch_filtered(&connection, &filters, 10, 0).await?),
+            vec![
+                ("homelab".to_string(), 1, 1500),
+                ("db".to_string(), 3, 120),
+                ("web".to_string(), 7, 812),
+            ]
+        );
+        assert_eq!(Projects::count_filtered(&connection, &filters).await?, 3);
+        assert_eq!(
+            Projects::fetch_filtered(&connection, &filters, 2, 1)
+                .await?
+                .len(),
+            2
+        );
+
+        // Projects containing more than 500 dependencies
+        let filters = ProjectFilters {
+            min_dependencies: Some(501),
+            ..Default::default()
+        };
+        assert_eq!(Projects::count_filtered(&connection, &filters).await?, 2);
+
+        // Projects without metadata are only matched without thresholds
+        let filters = ProjectFilters {
+            project_type: Some(ProjectType::Container),
+            ..Default::default()
+        };
+        assert!(filters.is_empty());
+        let found = names(Projects::fetch_filtered(&connection, &filters, 10, 0).await?);
+        assert!(found.contains(&("cache".to_string(), 0, 0)));
+        assert_eq!(
+            Projects::count_filtered(&connection, &filters).await?,
+            found.len() as i64
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_project_metadata_filters() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        // (name, image and arch of the latest snapshot)
+        let fixtures = [
+            ("web", "ghcr.io/42bytelabs/konarr:0.4.0", "amd64"),
+            ("proxy", "nginx:1.27", "ARM64"),
+            ("cache", "docker.io/library/redis", "amd64"),
+            ("app", "GHCR.io/42bytelabs/app@sha256:abcd", "arm64"),
+        ];
+        for (name, image, arch) in fixtures {
+            let mut project = Projects::new(name, ProjectType::Container);
+            project.save(&connection).await?;
+
+            // Only the latest snapshot is used
+            let mut old = Snapshot::create(&connection).await?;
+            old.set_metadata(
+                &connection,
+                SnapshotMetadataKey::ContainerImage,
+                "quay.io/old",
+            )
+            .await?;
+            project.add_snapshot(&connection, old).await?;
+
+            let mut snapshot = Snapshot::create(&connection).await?;
+            snapshot
+                .set_metadata(&connection, SnapshotMetadataKey::ContainerImage, image)
+                .await?;
+            snapshot
+                .set_metadata(&connection, SnapshotMetadataKey::OsArch, arch)
+                .await?;
+            project.add_snapshot(&connection, snapshot).await?;
+        }
+
+        let names = |projects: Vec<ProjectMetrics>| {
+            projects
+                .into_iter()
+                .map(|p| p.project.name)
+                .collect::<Vec<String>>()
+        };
+
+        let filters = ProjectFilters::default().with_metadata("registry", "ghcr.io")?;
+        assert!(!filters.is_empty());
+        assert_eq!(
+            names(Projects::fetch_filtered(&connection, &filters, 10, 0).await?),
+            vec!["app", "web"]
+        );
+        assert_eq!(Projects::count_filtered(&connection, &filters).await?, 2);
+        assert_eq!(
+            names(Projects::fetch_filtered(&connection, &filters, 1, 1).await?),
+            vec!["web"]
+        );
+
+        // Images without a registry are from Docker Hub, and without a tag are `latest`
+        let filters = ProjectFilters::default()
+            .with_metadata("registry", "docker.io")?
+            .with_metadata("tag", "LATEST")?;
+        assert_eq!(
+            names(Projects::fetch_filtered(&connection, &filters, 10, 0).await?),
+            vec!["cache"]
+        );
+
+        // Case-insensitive values, combined filters
+        let filters = ProjectFilters::default()
+            .with_metadata("arch", "arm64")?
+            .with_metadata("repository", "NGINX")?;
+        assert_eq!(
+            names(Projects::fetch_filtered(&connection, &filters, 10, 0).await?),
+            vec!["proxy"]
+        );
+
+        // Older snapshots are ignored
+        let filters = ProjectFilters::default().with_metadata("registry", "quay.io")?;
+        assert_eq!(Projects::count_filtered(&connection, &filters).await?, 0);
+
+        // Only the whitelisted keys
+        assert!(ProjectFilters::default()
+            .with_metadata("container.image", "nginx")
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_archive_restore() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("homelab/web", ProjectType::Container);
+        project.retention_max_snapshots = Some(1);
+        project.save(&connection).await?;
+        for _ in 0..3 {
+            let snapshot = Snapshot::create(&connection).await?;
+            project.add_snapshot(&connection, snapshot).await?;
+        }
+        assert!(project.archived_at.is_none());
+
+        project.archive(&connection).await?;
+        let project = Projects::fetch_by_primary_key(&connection, project.id).await?;
+        assert_eq!(project.status, ProjectStatus::Archived);
+        assert!(project.archived_at.is_some());
+        let archived = Projects::fetch_archived(&connection, 10, 0).await?;
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].id, project.id);
+        assert_eq!(archived[0].snapshots.len(), 3);
+
+        let mut project = project;
+        project.restore(&connection).await?;
+        let mut project = Projects::fetch_by_primary_key(&connection, project.id).await?;
+        assert_eq!(project.status, ProjectStatus::Active);
+        assert!(project.archived_at.is_none());
+        assert!(Projects::fetch_archived(&connection, 10, 0)
+            .await?
+            .is_empty());
+        assert!(project.restore(&connection).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_projects() -> Result<(), crate::KonarrError> {
+        use crate::models::settings::{ProjectSetting, ProjectSettings};
+
+        let connection = crate::models::database_test().await?;
+
+        // Compose stack renamed, the agent created a new project
+        let mut old = Projects::new("homelab/stack/web", ProjectType::Container);
+        old.save(&connection).await?;
+        let mut new = Projects::new("homelab/app/web", ProjectType::Container);
+        new.save(&connection).await?;
+        let shared = Snapshot::create(&connection).await?;
+        for _ in 0..2 {
+            old.add_snapshot(&connection, Snapshot::create(&connection).await?)
+                .await?;
+        }
+        old.add_snapshot(&connection, shared.clone()).await?;
+        new.add_snapshot(&connection, shared.clone()).await?;
+        ProjectSettings::set(
+            &connection,
+            old.id.into(),
+            ProjectSetting::BadgesPublic,
+            "true",
+        )
+        .await?;
+
+        // Incompatible types
+        let mut server = Projects::new("homelab", ProjectType::Server);
+        server.save(&connection).await?;
+        assert!(server
+            .clone()
+            .merge_into(&connection, &new, &ProjectMerge::default())
+            .await
+            .is_err());
+        assert!(old
+            .clone()
+            .merge_into(&connection, &old, &ProjectMerge::default())
+            .await
+            .is_err());
+
+        let summary = old
+            .merge_into(
+                &connection,
+                &new,
+                &ProjectMerge {
+                    alias: true,
+                    delete_source: false,
+                },
+            )
+            .await?;
+        assert_eq!(summary.snapshots, 3);
+        assert_eq!(summary.alias.as_deref(), Some("homelab/stack/web"));
+
+        // History is kept (the shared snapshot is only linked once)
+        let mut merged = Projects::fetch_by_primary_key(&connection, new.id).await?;
+        merged.fetch_snapshots(&connection).await?;
+        assert_eq!(merged.snapshots.len(), 3);
+        assert!(
+            ProjectSettings::get_bool(&connection, new.id.into(), ProjectSetting::BadgesPublic)
+                .await?
+        );
+        let old = Projects::fetch_by_primary_key(&connection, old.id).await?;
+        assert_eq!(old.status, ProjectStatus::Archived);
+        assert!(ProjectSnapshots::fetch_by_project_id(&connection, old.id)
+            .await?
+            .is_empty());
+
+        // The agent still using the old name finds the merged project
+        let found = Projects::fetch_by_normalized_name(&connection, "HomeLab/Stack/Web").await?;
+        assert_eq!(found.id, new.id);
+        assert_eq!(merged.fetch_aliases(&connection).await?.len(), 1);
+
+        // Merged and removed
+        let mut other = Projects::new("homelab/other", ProjectType::Container);
+        other.save(&connection).await?;
+        other
+            .add_snapshot(&connection, Snapshot::create(&connection).await?)
+            .await?;
+        other
+            .merge_into(
+                &connection,
+                &new,
+                &ProjectMerge {
+                    alias: true,
+                    delete_source: true,
+                },
+            )
+            .await?;
+        assert!(Projects::fetch_by_primary_key(&connection, other.id)
+            .await
+            .is_err());
+        let found = Projects::fetch_by_normalized_name(&connection, "homelab/other").await?;
+        assert_eq!(found.id, new.id);
+        merged.fetch_snapshots(&connection).await?;
+        assert_eq!(merged.snapshots.len(), 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_children_queries() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut server = Projects::new("homelab", ProjectType::Server);
+        server.save(&connection).await?;
+        for index in 0..30 {
+            let mut child =
+                Projects::new(format!("homelab/app-{:02}", index), ProjectType::Container);
+            child.parent = server.id.into();
+            child.save(&connection).await?;
+            for _ in 0..2 {
+                let mut snapshot = Snapshot::create(&connection).await?;
+                snapshot
+                    .set_metadata(
+                        &connection,
+                        SnapshotMetadataKey::SecurityAlertTotal,
+                        &index.to_string(),
+                    )
+                    .await?;
+                if index % 10 == 0 {
+                    snapshot
+                        .set_metadata(&connection, SnapshotMetadataKey::SecurityHealth, "red")
+                        .await?;
+                }
+                child.add_snapshot(&connection, snapshot).await?;
+            }
+        }
+        let connection = crate::models::testing::CountingConnection::new(connection);
+
+        // Children with their snapshots, one at a time
+        let mut loaded = server.clone();
+        loaded.fetch_children(&connection).await?;
+        let baseline = connection.take();
+        assert_eq!(loaded.children.len(), 30);
+        assert!(baseline > 150, "baseline: {}", baseline);
+
+        // Summary, no snapshot is loaded
+        let children = Projects::fetch_child_summaries(
+            &connection,
+            &[server.id.into()],
+            ProjectChildrenSort::Alerts,
+        )
+        .await?;
+        assert_eq!(connection.take(), 2);
+        assert_eq!(children.len(), 30);
+        assert_eq!(children[0].project.name, "homelab/app-29");
+        assert_eq!(children[0].alerts, 29);
+        assert_eq!(children[29].alerts, 0);
+        assert_eq!(children[29].health, ProjectHealth::Red);
+        assert_eq!(children[1].health, ProjectHealth::Green);
+
+        // Full, the snapshots are loaded in batches
+        let mut projects = vec![server.clone()];
+        Projects::fetch_children_batch(&connection, &mut projects, ProjectChildrenSort::Name)
+            .await?;
+        let batched = connection.take();
+        assert_eq!(batched, 5);
+        let children = &projects[0].children;
+        assert_eq!(children[0].name, "homelab/app-00");
+        for child in children {
+            let expected = loaded.children.iter().find(|c| c.id == child.id).unwrap();
+            assert_eq!(child.snapshots.len(), 2);
+            assert_eq!(
+                child.snapshots.last().map(|s| s.id),
+                expected.snapshots.last().map(|s| s.id)
+            );
+            assert_eq!(
+                child.snapshots.last().unwrap().health(),
+                expected.snapshots.last().unwrap().health()
+            );
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,577 @@
+//! # Reports
+//!
+//! Periodic security digests (new and resolved alerts, new projects, dependency growth
+//! and the riskiest components) generated by the reports task. A report is identified by
+//! its period (`2026-W41` / `2026-10-14`) and only uses data timestamped inside the
+//! period, so generating the same period again gives the same digest and replaces the
+//! stored report.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc};
+use geekorm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    raw_query,
+    security::{AlertEventKind, SecuritySeverity, SECURITY_SEVERITY},
+};
+use crate::KonarrError;
+
+/// Number of components listed in the top risky components of a digest
+pub const REPORT_TOP_COMPONENTS: u32 = 5;
+
+/// Schedule of the reports (`reports.schedule` setting)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportSchedule {
+    /// Report of the previous day
+    Daily,
+    /// Report of the previous (ISO) week
+    Weekly,
+}
+
+impl ReportSchedule {
+    /// Parse the schedule setting (`disabled` or unknown values disable the reports)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "daily" => Some(ReportSchedule::Daily),
+            "weekly" => Some(ReportSchedule::Weekly),
+            _ => None,
+        }
+    }
+
+    /// Last complete period before the time
+    pub fn period(&self, now: DateTime<Utc>) -> ReportPeriod {
+        let today = now.date_naive();
+        let (start, days) = match self {
+            ReportSchedule::Daily => (today - Duration::days(1), 1),
+            ReportSchedule::Weekly => {
+                let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+                (monday - Duration::days(7), 7)
+            }
+        };
+        let name = match self {
+            ReportSchedule::Daily => start.format("%Y-%m-%d").to_string(),
+            ReportSchedule::Weekly => {
+                let week = start.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+        };
+        let start = start.and_time(NaiveTime::MIN).and_utc();
+        ReportPeriod {
+            name,
+            start,
+            end: start + Duration::days(days),
+        }
+    }
+}
+
+/// Period covered by a report (`start` inclusive, `end` exclusive)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportPeriod {
+    /// Name of the period (`2026-W41`, `2026-10-14`)
+    pub name: String,
+    /// Start of the period
+    pub start: DateTime<Utc>,
+    /// End of the period
+    pub end: DateTime<Utc>,
+}
+
+/// Security digest of a period
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReportDigest {
+    /// Name of the period
+    pub period: String,
+    /// Start of the period
+    pub start: DateTime<Utc>,
+    /// End of the period
+    pub end: DateTime<Utc>,
+    /// Alerts detected (or reopened) in the period, by severity
+    pub alerts_new: BTreeMap<String, u64>,
+    /// Alerts resolved in the period
+    pub alerts_resolved: u64,
+    /// Projects created in the period (names)
+    pub projects_new: Vec<String>,
+    /// Dependencies in the latest snapshots of the projects at the start of the period
+    pub dependencies_start: u64,
+    /// Dependencies in the latest snapshots of the projects at the end of the period
+    pub dependencies_end: u64,
+    /// Components with the most (and most severe) new alerts
+    pub top_components: Vec<ReportComponent>,
+}
+
+/// Component of the top risky components of a digest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportComponent {
+    /// Component (`namespace/name@version`)
+    pub component: String,
+    /// Number of new alerts
+    pub alerts: u64,
+    /// Highest severity of the new alerts
+    pub severity: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeverityCountRow {
+    severity: String,
+    count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CountRow {
+    count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct NameRow {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopComponentRow {
+    namespace: Option<String>,
+    name: String,
+    version: Option<String>,
+    alerts: i64,
+    rank: i64,
+}
+
+impl ReportDigest {
+    /// Build the digest of a period
+    pub async fn build<'a, T>(connection: &'a T, period: &ReportPeriod) -> Result<Self, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let range = || {
+            let mut values = Values::new();
+            values.push("start".to_string(), period.start);
+            values.push("end".to_string(), period.end);
+            values
+        };
+
+        let mut values = range();
+        values.push("detected".to_string(), AlertEventKind::Detected);
+        values.push("reopened".to_string(), AlertEventKind::Reopened);
+        let alerts_new = T::query::<SeverityCountRow>(
+            connection,
+            raw_query(
+                "SELECT adv.severity, COUNT(*) AS count FROM AlertEvents e \
+                INNER JOIN Advisories adv ON adv.id = e.advisory_id \
+                WHERE e.created_at >= ? AND e.created_at < ? AND e.event IN (?, ?) \
+                GROUP BY adv.severity;",
+                values,
+            ),
+        )
+        .await?
+        .into_iter()
+        .map(|row| {
+            (
+                SecuritySeverity::from(row.severity).to_string(),
+                row.count as u64,
+            )
+        })
+        .fold(BTreeMap::new(), |mut map, (severity, count)| {
+            *map.entry(severity).or_insert(0) += count;
+            map
+        });
+
+        let mut values = range();
+        values.push("resolved".to_string(), AlertEventKind::Resolved);
+        let alerts_resolved = T::query::<CountRow>(
+            connection,
+            raw_query(
+                "SELECT COUNT(*) AS count FROM AlertEvents \
+                WHERE created_at >= ? AND created_at < ? AND event = ?;",
+                values,
+            ),
+        )
+        .await?
+        .first()
+        .map(|row| row.count as u64)
+        .unwrap_or_default();
+
+        let projects_new = T::query::<NameRow>(
+            connection,
+            raw_query(
+                "SELECT name FROM Projects WHERE created_at >= ? AND created_at < ? \
+                ORDER BY name ASC;",
+                range(),
+            ),
+        )
+        .await?
+        .into_iter()
+        .map(|row| row.name)
+        .collect();
+
+        let mut values = range();
+        values.push("detected".to_string(), AlertEventKind::Detected);
+        values.push("reopened".to_string(), AlertEventKind::Reopened);
+        values.push("limit".to_string(), REPORT_TOP_COMPONENTS as i32);
+        let top_components = T::query::<TopComponentRow>(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT c.namespace, c.name, v.version, COUNT(*) AS alerts, \
+                        MAX({}) AS rank \
+                    FROM AlertEvents e \
+                    INNER JOIN Advisories adv ON adv.id = e.advisory_id \
+                    INNER JOIN Alerts a ON a.id = e.alert_id \
+                    INNER JOIN Dependencies d ON d.id = a.dependency_id \
+                    INNER JOIN Component c ON c.id = d.component_id \
+                    LEFT JOIN ComponentVersion v ON v.id = d.component_version_id \
+                    WHERE e.created_at >= ? AND e.created_at < ? AND e.event IN (?, ?) \
+                    GROUP BY c.id, v.id \
+                    ORDER BY rank DESC, alerts DESC, c.name ASC, v.version ASC \
+                    LIMIT ?;",
+                    SecuritySeverity::rank_sql("adv.severity")
+                ),
+                values,
+            ),
+        )
+        .await?
+        .into_iter()
+        .map(|row| {
+            let name = match row.namespace.filter(|ns| !ns.is_empty()) {
+                Some(namespace) => format!("{}/{}", namespace, row.name),
+                None => row.name,
+            };
+            ReportComponent {
+                component: match row.version {
+                    Some(version) => format!("{}@{}", name, version),
+                    None => name,
+                },
+                alerts: row.alerts as u64,
+                severity: SECURITY_SEVERITY
+                    .iter()
+                    .map(|severity| SecuritySeverity::from(*severity))
+                    .find(|severity| severity.rank() as i64 == row.rank)
+                    .unwrap_or_default()
+                    .to_string(),
+            }
+        })
+        .collect();
+
+        Ok(Self {
+            period: period.name.clone(),
+            start: period.start,
+            end: period.end,
+            alerts_new,
+            alerts_resolved,
+            projects_new,
+            dependencies_start: Self::dependencies_at(connection, period.start).await?,
+            dependencies_end: Self::dependencies_at(connection, period.end).await?,
+            top_components,
+        })
+    }
+
+    /// Number of dependencies in the latest snapshot of each project before the time
+    async fn dependencies_at<'a, T>(
+        connection: &'a T,
+        before: DateTime<Utc>,
+    ) -> Result<u64, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("before".to_string(), before);
+        Ok(T::query::<CountRow>(
+            connection,
+            raw_query(
+                "SELECT COUNT(*) AS count FROM Dependencies WHERE snapshot_id IN \
+                (SELECT MAX(snapshot_id) FROM ProjectSnapshots WHERE created_at < ? \
+                GROUP BY project_id);",
+                values,
+            ),
+        )
+        .await?
+        .first()
+        .map(|row| row.count as u64)
+        .unwrap_or_default())
+    }
+
+    /// Total of the new alerts
+    pub fn alerts_new_total(&self) -> u64 {
+        self.alerts_new.values().sum()
+    }
+
+    /// Growth of the dependencies over the period
+    pub fn dependencies_growth(&self) -> i64 {
+        self.dependencies_end as i64 - self.dependencies_start as i64
+    }
+
+    /// Render the digest as Markdown
+    pub fn markdown(&self) -> String {
+        let mut lines = vec![
+            format!("# Konarr Security Digest ({})", self.period),
+            String::new(),
+            format!(
+                "{} to {}",
+                self.start.format("%Y-%m-%d"),
+                (self.end - Duration::days(1)).format("%Y-%m-%d")
+            ),
+            String::new(),
+            "## Alerts".to_string(),
+            String::new(),
+            format!("- New: {}", self.alerts_new_total()),
+        ];
+        for severity in SECURITY_SEVERITY.iter() {
+            if let Some(count) = self.alerts_new.get(*severity) {
+                lines.push(format!("  - {}: {}", severity, count));
+            }
+        }
+        lines.push(format!("- Resolved: {}", self.alerts_resolved));
+
+        lines.push(String::new());
+        lines.push(format!("## New Projects ({})", self.projects_new.len()));
+        lines.push(String::new());
+        if self.projects_new.is_empty() {
+            lines.push("No new projects".to_string());
+        }
+        for project in self.projects_new.iter() {
+            lines.push(format!("- {}", project));
+        }
+
+        lines.push(String::new());
+        lines.push("## Dependencies".to_string());
+        lines.push(String::new());
+        lines.push(format!(
+            "{} → {} ({:+})",
+            self.dependencies_start,
+            self.dependencies_end,
+            self.dependencies_growth()
+        ));
+
+        lines.push(String::new());
+        lines.push("## Top Risky Components".to_string());
+        lines.push(String::new());
+        if self.top_components.is_empty() {
+            lines.push("No new alerts".to_string());
+        }
+        for (index, component) in self.top_components.iter().enumerate() {
+            lines.push(format!(
+                "{}. `{}` :: {} new alerts (highest: {})",
+                index + 1,
+                component.component,
+                component.alerts,
+                component.severity
+            ));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+/// Reports Model
+#[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Reports {
+    /// Primary Key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+
+    /// Name of the period (`2026-W41`, `2026-10-14`)
+    #[geekorm(unique)]
+    pub period: String,
+    /// Start of the period
+    pub period_start: DateTime<Utc>,
+    /// End of the period
+    pub period_end: DateTime<Utc>,
+
+    /// Digest (JSON, see [ReportDigest])
+    pub body: String,
+    /// Digest rendered as Markdown
+    pub markdown: String,
+
+    /// Time the report was generated
+    #[geekorm(new = "Utc::now()")]
+    pub generated_at: DateTime<Utc>,
+    /// Time the report was delivered to the webhook
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl Reports {
+    /// Initialise the Reports table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::create_table(connection).await?;
+        Ok(())
+    }
+
+    /// Generate (or regenerate) the report of a period
+    pub async fn generate<'a, T>(
+        connection: &'a T,
+        period: &ReportPeriod,
+    ) -> Result<Self, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let digest = ReportDigest::build(connection, period).await?;
+        let body =
+            serde_json::to_string(&digest).map_err(|e| KonarrError::InvalidData(e.to_string()))?;
+        let markdown = digest.markdown();
+
+        match Self::fetch_by_period(connection, period.name.clone()).await {
+            Ok(mut report) => {
+                report.body = body;
+                report.markdown = markdown;
+                report.generated_at = Utc::now();
+                report.update(connection).await?;
+                Ok(report)
+            }
+            Err(_) => {
+                let mut report = Self::new(
+                    period.name.clone(),
+                    period.start,
+                    period.end,
+                    body,
+                    markdown,
+                );
+                report.save(connection).await?;
+                Ok(report)
+            }
+        }
+    }
+
+    /// Parse the digest of the report
+    pub fn digest(&self) -> Result<ReportDigest, KonarrError> {
+        serde_json::from_str(&self.body).map_err(|e| KonarrError::InvalidData(e.to_string()))
+    }
+
+    /// Fetch the latest reports (most recent period first)
+    pub async fn fetch_latest<'a, T>(
+        connection: &'a T,
+        limit: usize,
+    ) -> Result<Vec<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Self::query(
+            connection,
+            Self::query_select()
+                .order_by("period_start", QueryOrder::Desc)
+                .limit(limit)
+                .build()?,
+        )
+        .await?)
+    }
+
+    /// Remove the reports of the periods which ended before the date (returns the
+    /// number of reports removed)
+    pub async fn prune<'a, T>(connection: &'a T, before: DateTime<Utc>) -> Result<u64, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let count = Self::row_count(connection, Self::query_count().build()?).await?;
+
+        let mut values = Values::new();
+        values.push("before".to_string(), before);
+        T::execute::<Self>(
+            connection,
+            raw_query("DELETE FROM Reports WHERE period_end < ?;", values),
+        )
+        .await?;
+
+        let remaining = Self::row_count(connection, Self::query_count().build()?).await?;
+        Ok(count.saturating_sub(remaining) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        security::{Advisories, AdvisorySource, Alerts},
+        AlertEvents, Dependencies, ProjectType, Projects, Snapshot,
+    };
+
+    #[test]
+    fn test_report_period() {
+        let now = DateTime::parse_from_rfc3339("2026-10-15T09:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let weekly = ReportSchedule::Weekly.period(now);
+        assert_eq!(weekly.name, "2026-W41");
+        assert_eq!(weekly.start.to_rfc3339(), "2026-10-05T00:00:00+00:00");
+        assert_eq!(weekly.end.to_rfc3339(), "2026-10-12T00:00:00+00:00");
+        // Same period for every time in the week
+        assert_eq!(ReportSchedule::Weekly.period(weekly.end), weekly);
+
+        let daily = ReportSchedule::Daily.period(now);
+        assert_eq!(daily.name, "2026-10-14");
+        assert_eq!(daily.end - daily.start, Duration::days(1));
+
+        assert_eq!(
+            ReportSchedule::parse("Weekly"),
+            Some(ReportSchedule::Weekly)
+        );
+        assert_eq!(ReportSchedule::parse("disabled"), None);
+    }
+
+    #[tokio::test]
+    async fn test_reports() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let period = ReportSchedule::Weekly.period(Utc::now());
+        let during = period.start + Duration::days(2);
+
+        let mut project = Projects::new("homelab/web", ProjectType::Container);
+        project.created_at = during;
+        project.save(&connection).await?;
+        let snapshot = Snapshot::create(&connection).await?;
+        project.add_snapshot(&connection, snapshot.clone()).await?;
+
+        let mut dependency =
+            Dependencies::from_purl(&connection, "pkg:deb/debian/openssl@3.0.1".to_string())
+                .await?;
+        dependency.snapshot_id = snapshot.id.into();
+        dependency.save(&connection).await?;
+
+        let mut advisory =
+            Advisories::new("CVE-0001", AdvisorySource::Unknown, SecuritySeverity::High);
+        advisory.save(&connection).await?;
+        let mut alert = Alerts {
+            dependency_id: Some(dependency.id.into()),
+            ..Alerts::new(advisory.name.clone(), snapshot.id, advisory.id)
+        };
+        alert.save(&connection).await?;
+
+        let (project_id, advisory_id): (i32, i32) = (project.id.into(), advisory.id.into());
+        for (event, created_at) in [
+            (AlertEventKind::Detected, during),
+            (AlertEventKind::Resolved, during + Duration::hours(1)),
+            // Outside of the period
+            (AlertEventKind::Reopened, period.end + Duration::hours(1)),
+        ] {
+            let mut item = AlertEvents::new(project_id, advisory_id, event, "system");
+            item.alert_id = Some(alert.id.into());
+            item.created_at = created_at;
+            item.save(&connection).await?;
+        }
+
+        let report = Reports::generate(&connection, &period).await?;
+        let digest = report.digest()?;
+        assert_eq!(digest.alerts_new.get("High"), Some(&1));
+        assert_eq!(digest.alerts_new_total(), 1);
+        assert_eq!(digest.alerts_resolved, 1);
+        assert_eq!(digest.projects_new, vec!["homelab/web".to_string()]);
+        assert_eq!(digest.top_components.len(), 1);
+        assert_eq!(digest.top_components[0].component, "debian/openssl@3.0.1");
+        assert_eq!(digest.top_components[0].severity, "High");
+        assert!(report.markdown.contains("# Konarr Security Digest"));
+
+        // Generating the period again replaces the report
+        let again = Reports::generate(&connection, &period).await?;
+        assert_eq!(again.id, report.id);
+        assert_eq!(again.body, report.body);
+        assert_eq!(Reports::fetch_latest(&connection, 10).await?.len(), 1);
+
+        assert_eq!(Reports::prune(&connection, period.start).await?, 0);
+        assert_eq!(
+            Reports::prune(&connection, period.end + Duration::days(1)).await?,
+            1
+        );
+        Ok(())
+    }
+}
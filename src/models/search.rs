@@ -0,0 +1,264 @@
+//! # Instance-wide Search
+//!
+//! Searches across Projects, Components and Advisories with one bounded
+//! query per category (plus a count query for the totals).
+
+use geekorm::prelude::*;
+use log::debug;
+
+use super::{raw_query, Advisories, Component, ProjectStatus, Projects};
+use crate::KonarrError;
+
+/// Search Category
+#[derive(Data, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SearchCategory {
+    /// Projects (name / title)
+    #[default]
+    #[geekorm(key = "projects", aliases = "project,projects")]
+    Projects,
+    /// Components (name / namespace / full coordinate / purl)
+    #[geekorm(key = "components", aliases = "component,components,dependencies")]
+    Components,
+    /// Security Advisories (CVE, GHSA, etc)
+    #[geekorm(key = "advisories", aliases = "advisory,advisories,security")]
+    Advisories,
+}
+
+/// Single search result
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    /// Category of the result
+    pub category: SearchCategory,
+    /// Primary key of the result (Project, Component or Advisory ID)
+    pub id: i32,
+    /// Name of the result
+    pub name: String,
+    /// Additional information (project title, purl, severity)
+    pub description: Option<String>,
+}
+
+/// Search results for a category
+#[derive(Debug, Clone)]
+pub struct SearchResults {
+    /// Category of the results
+    pub category: SearchCategory,
+    /// Total number of matches (not limited)
+    pub total: i64,
+    /// Limited list of matches
+    pub results: Vec<SearchResult>,
+}
+
+impl SearchCategory {
+    /// All the categories (in the order the results are returned)
+    pub const ALL: [SearchCategory; 3] = [
+        SearchCategory::Projects,
+        SearchCategory::Components,
+        SearchCategory::Advisories,
+    ];
+
+    /// Search across all the categories
+    pub async fn search_all<'a, T>(
+        connection: &'a T,
+        term: impl Into<String>,
+        limit: u32,
+    ) -> Result<Vec<SearchResults>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let term = term.into();
+        let mut results = Vec::with_capacity(Self::ALL.len());
+        for category in Self::ALL.iter() {
+            results.push(category.search(connection, &term, limit).await?);
+        }
+        Ok(results)
+    }
+
+    /// Search a single category
+    pub async fn search<'a, T>(
+        &self,
+        connection: &'a T,
+        term: impl Into<String>,
+        limit: u32,
+    ) -> Result<SearchResults, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let term = term.into().trim().to_string();
+        debug!("Searching {} for `{}`", self, term);
+
+        let (filter, values) = self.filter(&term);
+        let mut order_values = values.clone();
+        let order = self.order(&term, &mut order_values);
+        let table = match self {
+            SearchCategory::Projects => Projects::table(),
+            SearchCategory::Components => Component::table(),
+            SearchCategory::Advisories => Advisories::table(),
+        };
+
+        let total = Projects::row_count(
+            connection,
+            raw_query(
+                format!("SELECT COUNT(*) FROM {} WHERE {};", table.name, filter),
+                values.clone(),
+            ),
+        )
+        .await?;
+
+        let mut values = order_values;
+        values.push("limit".to_string(), limit as i32);
+        let query = raw_query(
+            format!(
+                "SELECT * FROM {} WHERE {} ORDER BY {} LIMIT ?;",
+                table.name, filter, order
+            ),
+            values,
+        );
+
+        let results = match self {
+            SearchCategory::Projects => T::query::<Projects>(connection, query)
+                .await?
+                .into_iter()
+                .map(|p| SearchResult {
+                    category: *self,
+                    id: p.id.into(),
+                    name: p.name,
+                    description: p.title,
+                })
+                .collect(),
+            SearchCategory::Components => T::query::<Component>(connection, query)
+                .await?
+                .into_iter()
+                .map(|c| SearchResult {
+                    category: *self,
+                    id: c.id.into(),
+                    description: Some(c.purl()),
+                    name: c.display_name(),
+                })
+                .collect(),
+            SearchCategory::Advisories => T::query::<Advisories>(connection, query)
+                .await?
+                .into_iter()
+                .map(|a| SearchResult {
+                    category: *self,
+                    id: a.id.into(),
+                    name: a.name,
+                    description: Some(a.severity.to_string()),
+                })
+                .collect(),
+        };
+
+        Ok(SearchResults {
+            category: *self,
+            total,
+            results,
+        })
+    }
+
+    /// WHERE clause and values for the category
+    fn filter(&self, term: &str) -> (String, Values) {
+        let mut values = Values::new();
+        let like = format!("%{}%", term);
+
+        let filter = match self {
+            SearchCategory::Projects => {
+                values.push("status".to_string(), ProjectStatus::Archived);
+                values.push("name".to_string(), like.clone());
+                values.push("title".to_string(), like);
+                "status != ? AND (name LIKE ? OR title LIKE ?)".to_string()
+            }
+            SearchCategory::Components => match Component::from_purl(term) {
+                Ok((component, _)) if term.starts_with("pkg:") => {
+                    // Package URL (version is ignored)
+                    values.push("manager".to_string(), component.manager);
+                    values.push("name".to_string(), format!("{}%", component.name));
+                    match component.namespace {
+                        Some(namespace) => {
+                            values.push("namespace".to_string(), namespace);
+                            "manager = ? AND name LIKE ? AND namespace = ?".to_string()
+                        }
+                        None => "manager = ? AND name LIKE ?".to_string(),
+                    }
+                }
+                _ => Component::search_filter(term, &mut values),
+            },
+            SearchCategory::Advisories => {
+                values.push("name".to_string(), like);
+                "name LIKE ?".to_string()
+            }
+        };
+        (filter, values)
+    }
+
+    /// ORDER BY clause for the category
+    fn order(&self, term: &str, values: &mut Values) -> String {
+        match self {
+            SearchCategory::Components if !term.starts_with("pkg:") => {
+                Component::search_order(term, values)
+            }
+            _ => "name ASC".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{security::AdvisorySource, security::SecuritySeverity, ComponentManager};
+
+    #[tokio::test]
+    async fn test_search_all() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        for name in ["openssl-server", "web", "ssl-proxy"] {
+            let mut project = Projects::new(name, crate::models::ProjectType::Container);
+            project.save(&connection).await?;
+        }
+        let mut archived = Projects::new("old-openssl", crate::models::ProjectType::Container);
+        archived.status = ProjectStatus::Archived;
+        archived.save(&connection).await?;
+
+        for name in ["openssl", "libssl3", "zlib"] {
+            let mut comp = Component::new(ComponentManager::Deb, name.to_string());
+            comp.save(&connection).await?;
+        }
+
+        let mut advisory = Advisories::new(
+            "CVE-2024-0001",
+            AdvisorySource::Unknown,
+            SecuritySeverity::High,
+        );
+        advisory.save(&connection).await?;
+
+        let results = SearchCategory::search_all(&connection, "ssl", 1).await?;
+        assert_eq!(results.len(), 3);
+
+        assert_eq!(results[0].category, SearchCategory::Projects);
+        assert_eq!(results[0].total, 2);
+        assert_eq!(results[0].results.len(), 1);
+
+        assert_eq!(results[1].category, SearchCategory::Components);
+        assert_eq!(results[1].total, 2);
+        assert_eq!(results[1].results[0].name, "libssl3");
+
+        assert_eq!(results[2].total, 0);
+
+        // Package URL search
+        let components = SearchCategory::Components
+            .search(&connection, "pkg:deb/openssl", 10)
+            .await?;
+        assert_eq!(components.total, 1);
+        assert_eq!(
+            components.results[0].description,
+            Some("pkg:deb/openssl".to_string())
+        );
+
+        // Advisory ID search
+        let advisories = SearchCategory::Advisories
+            .search(&connection, "cve-2024", 10)
+            .await?;
+        assert_eq!(advisories.total, 1);
+        assert_eq!(advisories.results[0].description, Some("High".to_string()));
+
+        Ok(())
+    }
+}
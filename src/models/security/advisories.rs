@@ -52,6 +52,12 @@ pub enum AdvisorySource {
     /// Custom source of security information
     #[geekorm(aliases = "custom")]
     Custom,
+    /// Server side image policy (see [crate::models::security::PolicyConfig])
+    #[geekorm(aliases = "policy")]
+    Policy,
+    /// End-of-life operating systems and runtimes (see [crate::models::security::EolConfig])
+    #[geekorm(aliases = "eol,endoflife,end-of-life")]
+    EndOfLife,
     /// Unknown
     #[default]
     Unknown,
@@ -3,12 +3,21 @@
 use geekorm::prelude::*;
 use log::debug;
 
-use super::{advisories::AdvisoriesMetadata, SecuritySeverity};
+use super::{
+    advisories::AdvisoriesMetadata,
+    eol::EOL_PREFIX,
+    events::{AlertEventKind, AlertEvents},
+    policy::POLICY_PREFIX,
+    rules::AlertIgnoreRules,
+    vex::VexStatements,
+    SecuritySeverity, SECURITY_SEVERITY,
+};
 use crate::{
     bom::sbom::BomVulnerability,
     models::{
+        raw_query,
         security::{Advisories, AdvisorySource},
-        Component, Dependencies, Snapshot,
+        Component, ComponentManager, ComponentType, ComponentVersion, Dependencies, Snapshot,
     },
     KonarrError,
 };
@@ -23,8 +32,195 @@ pub enum SecurityState {
     Secure,
     /// Unfixable state
     Unfixable,
+    /// Ignored by an ignore rule (not counted in the summaries)
+    Ignored,
+    /// Acknowledged by a user (still vulnerable)
+    #[geekorm(aliases = "acknowledged")]
+    Acknowledged,
+    /// Not affected according to a VEX statement (not counted in the summaries)
+    #[geekorm(aliases = "not_affected,not-affected")]
+    NotAffected,
+    /// Marked as a false positive by a user (not counted in the summaries)
+    #[geekorm(aliases = "false_positive,false-positive")]
+    FalsePositive,
+}
+
+impl SecurityState {
+    /// If the alert is open (vulnerable, acknowledged or not)
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self,
+            SecurityState::Vulnerable | SecurityState::Acknowledged
+        )
+    }
+}
+
+/// Kind of alert (based on the name of the alert, not stored)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AlertKind {
+    /// Vulnerability from an advisory (CVE, GHSA, etc.)
+    #[default]
+    Vulnerability,
+    /// Image policy finding (see [super::PolicyConfig])
+    Policy,
+    /// End-of-life operating system or runtime (see [super::EolConfig])
+    Eol,
+}
+
+impl AlertKind {
+    /// Parse the kind of alert (`vulnerability`, `policy` or `eol`)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "vulnerability" | "vulnerabilities" | "vuln" => Some(AlertKind::Vulnerability),
+            "policy" => Some(AlertKind::Policy),
+            "eol" | "end-of-life" => Some(AlertKind::Eol),
+            _ => None,
+        }
+    }
+
+    /// SQL filter (on `Alerts.name`) for the kind of alert
+    fn filter(&self, values: &mut Values) -> String {
+        match self {
+            AlertKind::Vulnerability => {
+                values.push("kind_policy".to_string(), format!("{}%", POLICY_PREFIX));
+                values.push("kind_eol".to_string(), format!("{}%", EOL_PREFIX));
+                "Alerts.name NOT LIKE ? AND Alerts.name NOT LIKE ?".to_string()
+            }
+            AlertKind::Policy => {
+                values.push("kind_policy".to_string(), format!("{}%", POLICY_PREFIX));
+                "Alerts.name LIKE ?".to_string()
+            }
+            AlertKind::Eol => {
+                values.push("kind_eol".to_string(), format!("{}%", EOL_PREFIX));
+                "Alerts.name LIKE ?".to_string()
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for AlertKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlertKind::Vulnerability => write!(f, "vulnerability"),
+            AlertKind::Policy => write!(f, "policy"),
+            AlertKind::Eol => write!(f, "eol"),
+        }
+    }
+}
+
+/// SQL filter (on `Alerts.dependency_id`) for alerts in direct or transitive dependencies
+///
+/// Alerts without dependency graph data never match.
+fn direct_filter(direct: bool, values: &mut Values) -> String {
+    values.push("direct".to_string(), direct);
+    "EXISTS (SELECT 1 FROM Dependencies \
+        WHERE Dependencies.id = Alerts.dependency_id AND Dependencies.direct = ?)"
+        .to_string()
 }
 
+fn component_type_filter(component_type: ComponentType, values: &mut Values) -> String {
+    values.push("component_type".to_string(), component_type);
+    "EXISTS (SELECT 1 FROM Dependencies \
+        INNER JOIN Component ON Component.id = Dependencies.component_id \
+        WHERE Dependencies.id = Alerts.dependency_id AND Component.component_type = ?)"
+        .to_string()
+}
+
+/// Component with the most vulnerable alerts across all projects
+#[derive(Debug, Clone, Default)]
+pub struct AlertComponentSummary {
+    /// Component
+    pub component: Component,
+    /// Highest severity of the alerts
+    pub severity: SecuritySeverity,
+    /// Total number of alerts
+    pub alerts: i64,
+    /// Number of projects affected
+    pub projects: i64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AlertDependencyRow {
+    id: i32,
+    snapshot_id: i32,
+    component_id: i32,
+    component_version_id: i32,
+    direct: Option<bool>,
+    component_type: String,
+    manager: String,
+    namespace: Option<String>,
+    name: String,
+    origin: Option<String>,
+    version: String,
+}
+
+impl From<AlertDependencyRow> for Dependencies {
+    fn from(row: AlertDependencyRow) -> Self {
+        let component = Component {
+            id: row.component_id.into(),
+            component_type: ComponentType::from(row.component_type),
+            manager: ComponentManager::from(row.manager),
+            namespace: row.namespace,
+            name: row.name,
+            origin: row.origin,
+        };
+        let mut dependency =
+            Dependencies::new(row.snapshot_id, row.component_id, row.component_version_id);
+        dependency.id = row.id.into();
+        dependency.direct = row.direct;
+        dependency.component_version_id.data = ComponentVersion {
+            id: row.component_version_id.into(),
+            component_id: row.component_id.into(),
+            version: row.version,
+        };
+        dependency.component_id.data = component;
+        dependency
+    }
+}
+
+/// Alert with its advisory and dependency (see [Alerts::fetch_details])
+#[derive(Debug, serde::Deserialize)]
+struct AlertDetailsRow {
+    alert_id: i32,
+    advisory_name: String,
+    advisory_source: AdvisorySource,
+    advisory_severity: SecuritySeverity,
+    advisory_created_at: chrono::DateTime<chrono::Utc>,
+    advisory_updated_at: chrono::DateTime<chrono::Utc>,
+    // Dependency (if the alert matched one)
+    id: Option<i32>,
+    snapshot_id: Option<i32>,
+    component_id: Option<i32>,
+    component_version_id: Option<i32>,
+    direct: Option<bool>,
+    component_type: Option<String>,
+    manager: Option<String>,
+    namespace: Option<String>,
+    name: Option<String>,
+    origin: Option<String>,
+    version: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AlertComponentRow {
+    id: i32,
+    component_type: String,
+    manager: String,
+    namespace: Option<String>,
+    name: String,
+    origin: Option<String>,
+    severity: i64,
+    alerts: i64,
+    projects: i64,
+}
+
+/// Version of the matching of the scan results to the dependencies
+///
+/// Bump it when the matching logic changes ([Alerts::from_bom_vulnerability]), the
+/// snapshots scanned with an older version are re-evaluated after the next advisories
+/// database update.
+pub const ALERTS_MATCHER_VERSION: i32 = 1;
+
 /// Security alerts table
 #[derive(Table, Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct Alerts {
@@ -44,14 +240,42 @@ pub struct Alerts {
     #[geekorm(foreign_key = "Snapshot.id")]
     pub snapshot_id: ForeignKey<i32, Snapshot>,
 
-    /// Foreign key to the dependencies table
-    #[geekorm(foreign_key = "Dependencies.id")]
-    pub dependency_id: ForeignKey<i32, Dependencies>,
+    /// Dependency (row) that triggered the alert
+    ///
+    /// This is `None` when the vulnerable component did not match any dependency
+    /// in the snapshot (for example, a bad `bom-ref` in the SBOM). The foreign key to
+    /// `Dependencies.id` is added by the migrations (nullable foreign keys).
+    pub dependency_id: Option<i32>,
+
+    /// Dependency with its component and version (see [Alerts::fetch])
+    #[serde(skip)]
+    #[geekorm(skip)]
+    pub dependency: Option<Dependencies>,
 
     /// Foreign key to the advisories table
     #[geekorm(foreign_key = "Advisories.id")]
     pub advisory_id: ForeignKey<i32, Advisories>,
 
+    /// Ignore rule which suppresses the alert (see [AlertIgnoreRules])
+    #[serde(default)]
+    pub ignore_rule_id: Option<i32>,
+
+    /// VEX statement which suppresses the alert (see [VexStatements])
+    #[serde(default)]
+    pub vex_statement_id: Option<i32>,
+
+    /// VEX statement (see [Alerts::fetch])
+    #[serde(skip)]
+    #[geekorm(skip)]
+    pub vex: Option<VexStatements>,
+
+    /// Build of the advisories (Grype) database of the scan which created / updated the alert
+    #[serde(default)]
+    pub grype_build: Option<String>,
+    /// Matcher version of the scan which created / updated the alert (see [ALERTS_MATCHER_VERSION])
+    #[serde(default)]
+    pub matcher_version: Option<i32>,
+
     /// Metadata
     #[serde(skip)]
     #[geekorm(skip)]
@@ -81,25 +305,27 @@ impl Alerts {
     where
         T: geekorm::GeekConnection<Connection = T> + 'a,
     {
-        let item = Self::query_first(
+        // The dependency can be NULL so is matched after the query
+        let items = Self::query(
             connection,
             Self::query_select()
                 .where_eq("name", self.name.clone())
                 .and()
                 .where_eq("snapshot_id", self.snapshot_id.clone())
                 .and()
-                .where_eq("dependency_id", self.dependency_id.clone())
-                .and()
                 .where_eq("advisory_id", self.advisory_id.clone())
                 .build()?,
         )
-        .await;
+        .await?;
 
-        match item {
-            Ok(alert) => {
+        match items
+            .into_iter()
+            .find(|a| a.dependency_id == self.dependency_id)
+        {
+            Some(alert) => {
                 self.id = alert.id;
             }
-            _ => {
+            None => {
                 self.save(connection).await?;
             }
         }
@@ -107,6 +333,363 @@ impl Alerts {
         Ok(())
     }
 
+    /// Record the scan the alert was (last) found by
+    ///
+    /// Only the provenance columns are updated, the state of the alert is kept.
+    pub async fn set_provenance<'a, T>(
+        &mut self,
+        connection: &'a T,
+        grype_build: Option<&str>,
+        matcher_version: i32,
+    ) -> Result<(), crate::KonarrError>
+    where
+        T: geekorm::GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push(
+            "grype_build".to_string(),
+            grype_build.map(|b| b.to_string()),
+        );
+        values.push("matcher_version".to_string(), matcher_version);
+        values.push("id".to_string(), self.id);
+        T::execute::<Alerts>(
+            connection,
+            raw_query(
+                "UPDATE Alerts SET grype_build = ?, matcher_version = ? WHERE id = ?;",
+                values,
+            ),
+        )
+        .await?;
+        self.grype_build = grype_build.map(|b| b.to_string());
+        self.matcher_version = Some(matcher_version);
+        Ok(())
+    }
+
+    /// Apply the active ignore rules to the alert (component Package URL and version)
+    ///
+    /// Alerts matching a rule are ignored, ignored alerts which no longer match a rule
+    /// become vulnerable again.
+    pub async fn apply_ignore_rules<'a, T>(
+        &mut self,
+        connection: &'a T,
+        purl: &str,
+        version: Option<&str>,
+    ) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let rule = AlertIgnoreRules::find_match(
+            connection,
+            self.snapshot_id.key,
+            purl,
+            version,
+            &self.name,
+        )
+        .await?;
+
+        let (state, rule_id) = match (rule, &self.state) {
+            (Some(rule), SecurityState::Vulnerable | SecurityState::Ignored) => {
+                (SecurityState::Ignored, Some(rule.id.into()))
+            }
+            (None, SecurityState::Ignored) => (SecurityState::Vulnerable, None),
+            _ => return Ok(()),
+        };
+        if state != self.state || rule_id != self.ignore_rule_id {
+            debug!("Alert({}) :: {:?} (rule: {:?})", self.id, state, rule_id);
+            self.state = state;
+            self.ignore_rule_id = rule_id;
+            self.updated_at = chrono::Utc::now();
+            self.update(connection).await?;
+        }
+        Ok(())
+    }
+
+    /// Fetch the snapshot, advisory, dependency (with component and version) and VEX
+    /// statement for the alert
+    ///
+    /// This replaces the derived `GeekConnector::fetch` as the dependency is optional.
+    pub async fn fetch<'a, T>(&mut self, connection: &'a T) -> Result<(), geekorm::Error>
+    where
+        T: geekorm::GeekConnection<Connection = T> + 'a,
+    {
+        self.fetch_snapshot_id(connection).await?;
+        self.fetch_advisory_id(connection).await?;
+        self.fetch_dependency(connection).await?;
+        self.vex = match self.vex_statement_id {
+            Some(id) => Some(VexStatements::fetch_by_primary_key(connection, id).await?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Fetch the dependency with its component and version (single query)
+    pub async fn fetch_dependency<'a, T>(
+        &mut self,
+        connection: &'a T,
+    ) -> Result<Option<&Dependencies>, geekorm::Error>
+    where
+        T: geekorm::GeekConnection<Connection = T> + 'a,
+    {
+        let Some(dependency_id) = self.dependency_id else {
+            self.dependency = None;
+            return Ok(None);
+        };
+
+        let mut values = Values::new();
+        values.push("id".to_string(), dependency_id);
+
+        let row = T::query_first::<AlertDependencyRow>(
+            connection,
+            raw_query(
+                "SELECT d.id, d.snapshot_id, d.component_id, d.component_version_id, d.direct, \
+                    c.component_type, c.manager, c.namespace, c.name, c.origin, v.version \
+                FROM Dependencies d \
+                INNER JOIN Component c ON c.id = d.component_id \
+                INNER JOIN ComponentVersion v ON v.id = d.component_version_id \
+                WHERE d.id = ?;",
+                values,
+            ),
+        )
+        .await?;
+
+        self.dependency = Some(row.into());
+        Ok(self.dependency.as_ref())
+    }
+
+    /// Fetch the advisory, dependency (with its component and version) and VEX
+    /// statement of a page of alerts (see [Alerts::fetch], the snapshot is not fetched)
+    ///
+    /// The advisories and dependencies are fetched with a single query.
+    pub async fn fetch_details<'a, T>(
+        connection: &'a T,
+        alerts: &mut [Alerts],
+    ) -> Result<(), geekorm::Error>
+    where
+        T: geekorm::GeekConnection<Connection = T> + 'a,
+    {
+        if alerts.is_empty() {
+            return Ok(());
+        }
+        let mut values = Values::new();
+        for (index, alert) in alerts.iter().enumerate() {
+            values.push(format!("alert_{}", index), alert.id);
+        }
+        let rows = T::query::<AlertDetailsRow>(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT a.id AS alert_id, adv.name AS advisory_name, adv.source AS advisory_source, \
+                        adv.severity AS advisory_severity, adv.created_at AS advisory_created_at, \
+                        adv.updated_at AS advisory_updated_at, d.id, d.snapshot_id, d.component_id, \
+                        d.component_version_id, d.direct, c.component_type, c.manager, c.namespace, \
+                        c.name, c.origin, v.version \
+                    FROM Alerts a \
+                    INNER JOIN Advisories adv ON adv.id = a.advisory_id \
+                    LEFT JOIN Dependencies d ON d.id = a.dependency_id \
+                    LEFT JOIN Component c ON c.id = d.component_id \
+                    LEFT JOIN ComponentVersion v ON v.id = d.component_version_id \
+                    WHERE a.id IN ({});",
+                    vec!["?"; alerts.len()].join(", ")
+                ),
+                values,
+            ),
+        )
+        .await?;
+
+        let vex_ids: Vec<i32> = alerts.iter().filter_map(|a| a.vex_statement_id).collect();
+        let vex = if vex_ids.is_empty() {
+            vec![]
+        } else {
+            let mut values = Values::new();
+            for (index, id) in vex_ids.iter().enumerate() {
+                values.push(format!("vex_{}", index), *id);
+            }
+            T::query::<VexStatements>(
+                connection,
+                raw_query(
+                    format!(
+                        "SELECT * FROM VexStatements WHERE id IN ({});",
+                        vec!["?"; vex_ids.len()].join(", ")
+                    ),
+                    values,
+                ),
+            )
+            .await?
+        };
+
+        let mut rows: std::collections::HashMap<i32, AlertDetailsRow> =
+            rows.into_iter().map(|row| (row.alert_id, row)).collect();
+        for alert in alerts.iter_mut() {
+            let Some(row) = rows.remove(&alert.id.into()) else {
+                continue;
+            };
+            alert.advisory_id.data = Advisories {
+                id: alert.advisory_id.key.into(),
+                name: row.advisory_name,
+                source: row.advisory_source,
+                severity: row.advisory_severity,
+                created_at: row.advisory_created_at,
+                updated_at: row.advisory_updated_at,
+                metadata: std::mem::take(&mut alert.advisory_id.data.metadata),
+            };
+            alert.dependency = match (
+                row.id,
+                row.snapshot_id,
+                row.component_id,
+                row.component_version_id,
+            ) {
+                (Some(id), Some(snapshot_id), Some(component_id), Some(component_version_id)) => {
+                    Some(
+                        AlertDependencyRow {
+                            id,
+                            snapshot_id,
+                            component_id,
+                            component_version_id,
+                            direct: row.direct,
+                            component_type: row.component_type.unwrap_or_default(),
+                            manager: row.manager.unwrap_or_default(),
+                            namespace: row.namespace,
+                            name: row.name.unwrap_or_default(),
+                            origin: row.origin,
+                            version: row.version.unwrap_or_default(),
+                        }
+                        .into(),
+                    )
+                }
+                _ => None,
+            };
+            alert.vex = alert
+                .vex_statement_id
+                .and_then(|id| vex.iter().find(|v| v.id == id.into()).cloned());
+        }
+        Ok(())
+    }
+
+    /// Fetch all the alerts for a dependency (row)
+    pub async fn fetch_by_dependency<'a, T>(
+        connection: &'a T,
+        dependency: impl Into<PrimaryKey<i32>>,
+    ) -> Result<Vec<Self>, geekorm::Error>
+    where
+        T: geekorm::GeekConnection<Connection = T> + 'a,
+    {
+        let dependency: i32 = dependency.into().into();
+        let mut alerts = Self::query(
+            connection,
+            Self::query_select()
+                .where_eq("dependency_id", dependency)
+                .order_by("id", QueryOrder::Asc)
+                .build()?,
+        )
+        .await?;
+        for alert in alerts.iter_mut() {
+            alert.fetch(connection).await?;
+            alert.fetch_metadata(connection).await?;
+        }
+        Ok(alerts)
+    }
+
+    /// If the alert did not match a dependency in the snapshot
+    pub fn is_unmatched(&self) -> bool {
+        self.dependency_id.is_none()
+    }
+
+    /// If the alert is in a direct dependency of the main component (see [Alerts::fetch])
+    ///
+    /// `None` if the snapshot has no dependency graph data (or the alert has no dependency).
+    pub fn direct(&self) -> Option<bool> {
+        self.dependency.as_ref().and_then(|dep| dep.direct)
+    }
+
+    /// Kind of the alert
+    pub fn kind(&self) -> AlertKind {
+        if self.is_policy() {
+            AlertKind::Policy
+        } else if self.is_eol() {
+            AlertKind::Eol
+        } else {
+            AlertKind::Vulnerability
+        }
+    }
+
+    /// Filter alerts by kind and / or direct dependencies (and state)
+    pub async fn filter_kind<'a, T>(
+        connection: &'a T,
+        kind: Option<AlertKind>,
+        direct: Option<bool>,
+        state: SecurityState,
+        page: &Pagination,
+    ) -> Result<Vec<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        let filter = Self::kind_filter(kind, direct, state, &mut values);
+        values.push("limit".to_string(), page.limit() as i32);
+        values.push("offset".to_string(), page.offset() as i32);
+
+        let mut alerts = T::query::<Alerts>(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT Alerts.* FROM Alerts \
+                    WHERE {} \
+                    ORDER BY Alerts.id ASC \
+                    LIMIT ? OFFSET ?;",
+                    filter
+                ),
+                values,
+            ),
+        )
+        .await?;
+        for alert in alerts.iter_mut() {
+            alert.fetch(connection).await?;
+            alert.fetch_metadata(connection).await?;
+        }
+        Ok(alerts)
+    }
+
+    /// Count the alerts matching [Alerts::filter_kind]
+    pub async fn count_kind<'a, T>(
+        connection: &'a T,
+        kind: Option<AlertKind>,
+        direct: Option<bool>,
+        state: SecurityState,
+    ) -> Result<u64, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        let filter = Self::kind_filter(kind, direct, state, &mut values);
+        Ok(Alerts::row_count(
+            connection,
+            raw_query(
+                format!("SELECT COUNT(*) FROM Alerts WHERE {};", filter),
+                values,
+            ),
+        )
+        .await? as u64)
+    }
+
+    /// WHERE clause of [Alerts::filter_kind]
+    fn kind_filter(
+        kind: Option<AlertKind>,
+        direct: Option<bool>,
+        state: SecurityState,
+        values: &mut Values,
+    ) -> String {
+        let mut filter = String::from("1 = 1");
+        if let Some(kind) = kind {
+            filter.push_str(&format!(" AND {}", kind.filter(values)));
+        }
+        if let Some(direct) = direct {
+            filter.push_str(&format!(" AND {}", direct_filter(direct, values)));
+        }
+        values.push("state".to_string(), state);
+        filter.push_str(" AND Alerts.state = ?");
+        filter
+    }
+
     /// Filter alerts by severity
     pub async fn filter_severity<'a, T>(
         connection: &'a T,
@@ -132,6 +715,141 @@ impl Alerts {
         Ok(alerts)
     }
 
+    /// Count the alerts matching [Alerts::filter_severity]
+    pub async fn count_severity<'a, T>(
+        connection: &'a T,
+        severity: SecuritySeverity,
+    ) -> Result<u64, geekorm::Error>
+    where
+        T: geekorm::GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("severity".to_string(), severity);
+        Ok(Alerts::row_count(
+            connection,
+            raw_query(
+                "SELECT COUNT(*) FROM Alerts \
+                INNER JOIN Advisories ON Advisories.id = Alerts.advisory_id \
+                WHERE Advisories.severity = ?;",
+                values,
+            ),
+        )
+        .await? as u64)
+    }
+
+    /// Fetch the alerts for a Snapshot ordered by severity (most severe first)
+    pub async fn fetch_snapshot_page<'a, T>(
+        connection: &'a T,
+        snapshot_id: i32,
+        severity: Option<SecuritySeverity>,
+        kind: Option<AlertKind>,
+        direct: Option<bool>,
+        component_type: Option<ComponentType>,
+        page: &Pagination,
+    ) -> Result<Vec<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        let filter = Self::snapshot_filter(
+            snapshot_id,
+            severity,
+            kind,
+            direct,
+            component_type,
+            &mut values,
+        );
+        values.push("limit".to_string(), page.limit() as i32);
+        values.push("offset".to_string(), page.offset() as i32);
+
+        let mut alerts = T::query::<Alerts>(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT Alerts.* FROM Alerts \
+                    INNER JOIN Advisories ON Advisories.id = Alerts.advisory_id \
+                    WHERE {} \
+                    ORDER BY {} DESC, Alerts.id ASC \
+                    LIMIT ? OFFSET ?;",
+                    filter,
+                    SecuritySeverity::rank_sql("Advisories.severity")
+                ),
+                values,
+            ),
+        )
+        .await?;
+        for alert in alerts.iter_mut() {
+            alert.fetch(connection).await?;
+        }
+        Ok(alerts)
+    }
+
+    /// Count the alerts matching [Alerts::fetch_snapshot_page]
+    pub async fn count_snapshot_page<'a, T>(
+        connection: &'a T,
+        snapshot_id: i32,
+        severity: Option<SecuritySeverity>,
+        kind: Option<AlertKind>,
+        direct: Option<bool>,
+        component_type: Option<ComponentType>,
+    ) -> Result<u64, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        let filter = Self::snapshot_filter(
+            snapshot_id,
+            severity,
+            kind,
+            direct,
+            component_type,
+            &mut values,
+        );
+        Ok(Alerts::row_count(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT COUNT(*) FROM Alerts \
+                    INNER JOIN Advisories ON Advisories.id = Alerts.advisory_id \
+                    WHERE {};",
+                    filter
+                ),
+                values,
+            ),
+        )
+        .await? as u64)
+    }
+
+    /// WHERE clause of [Alerts::fetch_snapshot_page]
+    fn snapshot_filter(
+        snapshot_id: i32,
+        severity: Option<SecuritySeverity>,
+        kind: Option<AlertKind>,
+        direct: Option<bool>,
+        component_type: Option<ComponentType>,
+        values: &mut Values,
+    ) -> String {
+        values.push("snapshot_id".to_string(), snapshot_id);
+        let mut filter = String::from("Alerts.snapshot_id = ?");
+        if let Some(severity) = severity {
+            values.push("severity".to_string(), severity);
+            filter.push_str(" AND Advisories.severity = ?");
+        }
+        if let Some(kind) = kind {
+            filter.push_str(&format!(" AND {}", kind.filter(values)));
+        }
+        if let Some(direct) = direct {
+            filter.push_str(&format!(" AND {}", direct_filter(direct, values)));
+        }
+        if let Some(component_type) = component_type {
+            filter.push_str(&format!(
+                " AND {}",
+                component_type_filter(component_type, values)
+            ));
+        }
+        filter
+    }
+
     /// Count Vulnerable alerts
     pub async fn count_vulnerable<'a, T>(connection: &'a T) -> Result<u32, geekorm::Error>
     where
@@ -146,6 +864,169 @@ impl Alerts {
         .await? as u32)
     }
 
+    /// Top vulnerable components
+    ///
+    /// Only vulnerable alerts for the latest snapshot of each project are counted.
+    pub async fn top_components<'a, T>(
+        connection: &'a T,
+        limit: u32,
+    ) -> Result<Vec<AlertComponentSummary>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        // Rank the severities so `MIN` returns the highest severity
+        let rank = SECURITY_SEVERITY
+            .iter()
+            .enumerate()
+            .map(|(index, severity)| format!("WHEN '{}' THEN {}", severity, index))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        let mut values = Values::new();
+        values.push("state".to_string(), SecurityState::Vulnerable);
+        values.push("limit".to_string(), limit as i32);
+
+        let rows = T::query::<AlertComponentRow>(
+            connection,
+            raw_query(
+                format!(
+                    "SELECT c.id, c.component_type, c.manager, c.namespace, c.name, c.origin, \
+                        MIN(CASE adv.severity {} ELSE {} END) AS severity, \
+                        COUNT(DISTINCT a.id) AS alerts, \
+                        COUNT(DISTINCT ps.project_id) AS projects \
+                    FROM Alerts a \
+                    INNER JOIN ProjectSnapshots ps ON ps.snapshot_id = a.snapshot_id \
+                    INNER JOIN Dependencies d ON d.id = a.dependency_id \
+                    INNER JOIN Component c ON c.id = d.component_id \
+                    INNER JOIN Advisories adv ON adv.id = a.advisory_id \
+                    WHERE a.state = ? AND ps.snapshot_id = \
+                        (SELECT MAX(snapshot_id) FROM ProjectSnapshots WHERE project_id = ps.project_id) \
+                    GROUP BY c.id \
+                    ORDER BY alerts DESC, severity ASC, c.name ASC \
+                    LIMIT ?;",
+                    rank,
+                    SECURITY_SEVERITY.len() - 1
+                ),
+                values,
+            ),
+        )
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| AlertComponentSummary {
+                component: Component {
+                    id: row.id.into(),
+                    component_type: ComponentType::from(row.component_type),
+                    manager: ComponentManager::from(row.manager),
+                    namespace: row.namespace,
+                    name: row.name,
+                    origin: row.origin,
+                },
+                severity: SECURITY_SEVERITY
+                    .get(row.severity as usize)
+                    .map(|s| SecuritySeverity::from(*s))
+                    .unwrap_or_default(),
+                alerts: row.alerts,
+                projects: row.projects,
+            })
+            .collect())
+    }
+
+    /// Acknowledge the alert (recorded in the timeline of the alert's projects)
+    pub async fn acknowledge<'a, T>(
+        &mut self,
+        connection: &'a T,
+        actor: impl Into<String>,
+    ) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        if self.state != SecurityState::Vulnerable {
+            return Err(KonarrError::Conflict(format!(
+                "Only vulnerable alerts can be acknowledged (state: {:?})",
+                self.state
+            )));
+        }
+        self.set_state(
+            connection,
+            SecurityState::Acknowledged,
+            AlertEventKind::Acknowledged,
+            actor,
+        )
+        .await
+    }
+
+    /// Mark an open alert as a false positive (recorded in the timeline of the alert's projects)
+    pub async fn false_positive<'a, T>(
+        &mut self,
+        connection: &'a T,
+        actor: impl Into<String>,
+    ) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        if !self.state.is_open() {
+            return Err(KonarrError::Conflict(format!(
+                "Only open alerts can be marked as false positives (state: {:?})",
+                self.state
+            )));
+        }
+        self.set_state(
+            connection,
+            SecurityState::FalsePositive,
+            AlertEventKind::FalsePositive,
+            actor,
+        )
+        .await
+    }
+
+    /// Reopen an acknowledged or false positive alert (recorded in the timeline of the
+    /// alert's projects)
+    pub async fn reopen<'a, T>(
+        &mut self,
+        connection: &'a T,
+        actor: impl Into<String>,
+    ) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        if !matches!(
+            self.state,
+            SecurityState::Acknowledged | SecurityState::FalsePositive
+        ) {
+            return Err(KonarrError::Conflict(format!(
+                "Only acknowledged or false positive alerts can be reopened (state: {:?})",
+                self.state
+            )));
+        }
+        self.set_state(
+            connection,
+            SecurityState::Vulnerable,
+            AlertEventKind::Reopened,
+            actor,
+        )
+        .await
+    }
+
+    async fn set_state<'a, T>(
+        &mut self,
+        connection: &'a T,
+        state: SecurityState,
+        event: AlertEventKind,
+        actor: impl Into<String>,
+    ) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        debug!("Alert({}) :: {:?} -> {:?}", self.id, self.state, state);
+        self.state = state;
+        self.updated_at = chrono::Utc::now();
+        self.update(connection).await?;
+        AlertEvents::record_alert(connection, self, event, actor).await?;
+        Ok(())
+    }
+
     /// Close An Alert
     pub async fn close<'a, T>(&mut self, connection: &'a T) -> Result<(), geekorm::Error>
     where
@@ -189,27 +1070,53 @@ impl Alerts {
         let mut alerts = Vec::new();
 
         for affected in &vulnerability.components {
-            let (mut component, _) = Component::from_purl(affected.purl.clone())?;
+            let (mut component, version) = Component::from_purl(affected.purl.clone())?;
             component.find_or_create(connection).await?;
             debug!("Alert Component: {:?}", component);
 
-            let dependency = match Dependencies::fetch_dependency_by_snapshot(
-                connection,
-                snapshot.id,
-                component.id,
-            )
-            .await
-            {
-                Ok(dep) => dep,
-                Err(_) => {
-                    log::error!(
-                        "Failed to fetch dependency for BOM vulnerability: {}",
-                        affected.purl
+            // Dependency (purl and version the ignore rules are matched against)
+            let mut dependencies: Vec<(Option<i32>, String, String)> =
+                match Dependencies::fetch_dependency_by_snapshot(
+                    connection,
+                    snapshot.id,
+                    component.id,
+                )
+                .await
+                {
+                    Ok(dep) => vec![(
+                        Some(dep.id.into()),
+                        component.purl(),
+                        version.version.clone(),
+                    )],
+                    Err(_) => vec![],
+                };
+            if dependencies.is_empty() {
+                // Advisory of a source package, alert the binary packages built from it
+                for mut dep in
+                    Dependencies::fetch_by_origin(connection, snapshot.id, &component).await?
+                {
+                    dep.fetch(connection).await?;
+                    debug!(
+                        "Alert Dependency from origin `{}`: {}",
+                        component.name,
+                        dep.purl()
                     );
-                    continue;
+                    dependencies.push((
+                        Some(dep.id.into()),
+                        dep.purl(),
+                        dep.component_version_id.data.version.clone(),
+                    ));
                 }
-            };
-            debug!("Alert Dependency: {:?}", dependency);
+            }
+            if dependencies.is_empty() {
+                log::warn!(
+                    "No dependency found for BOM vulnerability `{}`, storing unmatched alert: {}",
+                    vulnerability.name,
+                    affected.purl
+                );
+                dependencies.push((None, component.purl(), version.version.clone()));
+            }
+            debug!("Alert Dependencies: {:?}", dependencies);
 
             let mut advisory = Advisories::new(
                 vulnerability.name.clone(),
@@ -240,15 +1147,18 @@ impl Alerts {
             // TODO: Metadata for the advisory
             debug!("Alert Advisory: {:?}", advisory);
 
-            let mut alert = Alerts::new(
-                vulnerability.name.clone(),
-                snapshot.id,
-                dependency.id,
-                advisory.id,
-            );
-            alert.find_or_create(connection).await?;
-            debug!("Alert: {:?}", alert);
-            alerts.push(alert);
+            for (dependency, purl, version) in dependencies {
+                let mut alert = Alerts {
+                    dependency_id: dependency,
+                    ..Alerts::new(vulnerability.name.clone(), snapshot.id, advisory.id)
+                };
+                alert.find_or_create(connection).await?;
+                alert
+                    .apply_ignore_rules(connection, &purl, Some(&version))
+                    .await?;
+                debug!("Alert: {:?}", alert);
+                alerts.push(alert);
+            }
         }
 
         Ok(alerts)
@@ -283,3 +1193,466 @@ impl From<Option<String>> for SecurityState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ProjectType, Projects};
+
+    async fn add_alert(
+        connection: &libsql::Connection,
+        snapshot: &Snapshot,
+        purl: &str,
+        advisory: &str,
+        severity: SecuritySeverity,
+    ) -> Result<Alerts, KonarrError> {
+        let mut dependency = Dependencies::from_purl(connection, purl.to_string()).await?;
+        dependency.snapshot_id = snapshot.id.into();
+        dependency.save(connection).await?;
+
+        let mut advisory = Advisories::new(advisory, AdvisorySource::Unknown, severity);
+        advisory.save(connection).await?;
+
+        let mut alert = Alerts {
+            dependency_id: Some(dependency.id.into()),
+            ..Alerts::new(advisory.name.clone(), snapshot.id, advisory.id)
+        };
+        alert.save(connection).await?;
+        Ok(alert)
+    }
+
+    #[tokio::test]
+    async fn test_top_components() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut projects = vec![];
+        for name in ["project-a", "project-b", "project-c"] {
+            let mut project = Projects::new(name, ProjectType::Container);
+            project.save(&connection).await?;
+            projects.push(project);
+        }
+
+        // Project A: openssl (critical) and zlib (low + a closed alert)
+        let snapshot = Snapshot::create(&connection).await?;
+        projects[0]
+            .add_snapshot(&connection, snapshot.clone())
+            .await?;
+        add_alert(
+            &connection,
+            &snapshot,
+            "pkg:deb/debian/openssl@3.0.1",
+            "CVE-0001",
+            SecuritySeverity::Critical,
+        )
+        .await?;
+        add_alert(
+            &connection,
+            &snapshot,
+            "pkg:deb/debian/zlib@1.2.13",
+            "CVE-0002",
+            SecuritySeverity::Low,
+        )
+        .await?;
+        add_alert(
+            &connection,
+            &snapshot,
+            "pkg:deb/debian/zlib@1.2.13",
+            "CVE-0003",
+            SecuritySeverity::High,
+        )
+        .await?
+        .close(&connection)
+        .await?;
+
+        // Project B: openssl at a different version (high)
+        let snapshot = Snapshot::create(&connection).await?;
+        projects[1]
+            .add_snapshot(&connection, snapshot.clone())
+            .await?;
+        add_alert(
+            &connection,
+            &snapshot,
+            "pkg:deb/debian/openssl@3.0.2",
+            "CVE-0004",
+            SecuritySeverity::High,
+        )
+        .await?;
+
+        // Project C: openssl only in an old snapshot
+        let old = Snapshot::create(&connection).await?;
+        projects[2].add_snapshot(&connection, old.clone()).await?;
+        add_alert(
+            &connection,
+            &old,
+            "pkg:deb/debian/openssl@1.1.1",
+            "CVE-0005",
+            SecuritySeverity::Critical,
+        )
+        .await?;
+        let latest = Snapshot::create(&connection).await?;
+        projects[2].add_snapshot(&connection, latest).await?;
+
+        let top = Alerts::top_components(&connection, 20).await?;
+        assert_eq!(top.len(), 2);
+
+        assert_eq!(top[0].component.name, "openssl");
+        assert_eq!(top[0].severity, SecuritySeverity::Critical);
+        assert_eq!(top[0].alerts, 2);
+        assert_eq!(top[0].projects, 2);
+
+        assert_eq!(top[1].component.name, "zlib");
+        assert_eq!(top[1].severity, SecuritySeverity::Low);
+        assert_eq!(top[1].alerts, 1);
+        assert_eq!(top[1].projects, 1);
+
+        assert_eq!(Alerts::top_components(&connection, 1).await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_alerts_order() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let snapshot = Snapshot::create(&connection).await?;
+        for (purl, advisory, severity) in [
+            (
+                "pkg:deb/debian/zlib@1.2.13",
+                "CVE-0001",
+                SecuritySeverity::Low,
+            ),
+            (
+                "pkg:deb/debian/curl@8.0.0",
+                "CVE-0002",
+                SecuritySeverity::Unknown,
+            ),
+            (
+                "pkg:deb/debian/openssl@3.0.1",
+                "CVE-0003",
+                SecuritySeverity::Critical,
+            ),
+            (
+                "pkg:deb/debian/bash@5.2.0",
+                "CVE-0004",
+                SecuritySeverity::Medium,
+            ),
+        ] {
+            add_alert(&connection, &snapshot, purl, advisory, severity).await?;
+        }
+
+        let alerts = Alerts::fetch_snapshot_page(
+            &connection,
+            snapshot.id.into(),
+            None,
+            None,
+            None,
+            None,
+            &Pagination::new(),
+        )
+        .await?;
+        let names: Vec<&str> = alerts.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["CVE-0003", "CVE-0004", "CVE-0001", "CVE-0002"]);
+
+        let alerts = Alerts::fetch_snapshot_page(
+            &connection,
+            snapshot.id.into(),
+            Some(SecuritySeverity::Medium),
+            None,
+            None,
+            None,
+            &Pagination::new(),
+        )
+        .await?;
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].name, "CVE-0004");
+
+        // Only the alerts of cryptography libraries
+        let openssl = Dependencies::find_by_purl(&connection, "pkg:deb/debian/openssl".to_string())
+            .await?
+            .remove(0);
+        let mut component =
+            Component::fetch_by_primary_key(&connection, openssl.component_id).await?;
+        component.component_type = ComponentType::CryptographyLibrary;
+        component.update(&connection).await?;
+        let alerts = Alerts::fetch_snapshot_page(
+            &connection,
+            snapshot.id.into(),
+            None,
+            None,
+            None,
+            Some(ComponentType::CryptographyLibrary),
+            &Pagination::new(),
+        )
+        .await?;
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].name, "CVE-0003");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_alert_dependency() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let snapshot = Snapshot::create(&connection).await?;
+        let matched = add_alert(
+            &connection,
+            &snapshot,
+            "pkg:deb/debian/openssl@3.0.1",
+            "CVE-0001",
+            SecuritySeverity::High,
+        )
+        .await?;
+
+        let mut alert = Alerts::fetch_by_primary_key(&connection, matched.id).await?;
+        alert.fetch(&connection).await?;
+        assert!(!alert.is_unmatched());
+        let dependency = alert.dependency.clone().unwrap();
+        assert_eq!(dependency.name(), "openssl");
+        assert_eq!(dependency.version(), Some("3.0.1".to_string()));
+        assert_eq!(alert.advisory_id.data.name, "CVE-0001");
+
+        let alerts = Alerts::fetch_by_dependency(&connection, dependency.id).await?;
+        assert_eq!(alerts.len(), 1);
+
+        // Vulnerable component which is not a dependency of the snapshot
+        let mut vulnerability =
+            BomVulnerability::new("CVE-0002".into(), "nvd".into(), "critical".into());
+        vulnerability
+            .components
+            .push(crate::bom::sbom::BomComponent::from_purl(
+                "pkg:deb/debian/zlib@1.2.13".to_string(),
+            ));
+        let alerts = Alerts::from_bom_vulnerability(&connection, &snapshot, &vulnerability).await?;
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].is_unmatched());
+
+        // Creating the alert again does not duplicate it
+        Alerts::from_bom_vulnerability(&connection, &snapshot, &vulnerability).await?;
+        let mut unmatched = Alerts::fetch_by_primary_key(&connection, alerts[0].id).await?;
+        unmatched.fetch(&connection).await?;
+        assert!(unmatched.dependency.is_none());
+        assert_eq!(
+            Alerts::row_count(&connection, Alerts::query_count().build()?).await?,
+            2
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_details() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let snapshot = Snapshot::create(&connection).await?;
+        for (purl, name) in [
+            ("pkg:deb/debian/openssl@3.0.1", "CVE-0001"),
+            ("pkg:deb/debian/zlib@1.2.13", "CVE-0002"),
+        ] {
+            add_alert(&connection, &snapshot, purl, name, SecuritySeverity::High).await?;
+        }
+        let mut vulnerability =
+            BomVulnerability::new("CVE-0003".into(), "nvd".into(), "critical".into());
+        vulnerability
+            .components
+            .push(crate::bom::sbom::BomComponent::from_purl(
+                "pkg:deb/debian/curl@8.0.0".to_string(),
+            ));
+        Alerts::from_bom_vulnerability(&connection, &snapshot, &vulnerability).await?;
+
+        let connection = crate::models::testing::CountingConnection::new(connection);
+        let mut alerts = Alerts::query(
+            &connection,
+            Alerts::query_select()
+                .order_by("id", QueryOrder::Asc)
+                .build()?,
+        )
+        .await?;
+        assert_eq!(alerts.len(), 3);
+        connection.take();
+        Alerts::fetch_details(&connection, &mut alerts).await?;
+        assert_eq!(connection.take(), 1);
+
+        // Same as fetching the alerts one by one
+        for alert in alerts.iter() {
+            let mut expected = Alerts::fetch_by_primary_key(&connection, alert.id).await?;
+            expected.fetch(&connection).await?;
+            assert_eq!(alert.advisory_id.data.name, expected.advisory_id.data.name);
+            assert_eq!(
+                alert.advisory_id.data.severity,
+                expected.advisory_id.data.severity
+            );
+            assert_eq!(
+                alert.dependency.as_ref().map(|d| d.purl()),
+                expected.dependency.as_ref().map(|d| d.purl())
+            );
+        }
+        assert!(alerts[2].dependency.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_alert_origin() -> Result<(), KonarrError> {
+        use crate::bom::{cyclonedx::CycloneDx, BomParser};
+
+        let connection = crate::models::database_test().await?;
+
+        let snapshot = Snapshot::create(&connection).await?;
+        let bom = CycloneDx::parse(include_bytes!(
+            "../../bom/testdata/syft-debian-origin.cdx.json"
+        ))?;
+        for component in bom.components.iter() {
+            Dependencies::from_bom_compontent(&connection, snapshot.id, component, 1024).await?;
+        }
+
+        // Advisories of the source packages alert the binary packages
+        for (purl, expected) in [
+            ("pkg:deb/debian/glibc@2.36-9", vec!["libc6"]),
+            ("pkg:apk/alpine/openssl@3.3.2-r0", vec!["libcrypto3"]),
+            // The source package is also installed
+            ("pkg:deb/debian/openssl@3.0.11-1~deb12u2", vec!["openssl"]),
+        ] {
+            let mut vulnerability =
+                BomVulnerability::new(format!("CVE-{}", purl), "debian".into(), "high".into());
+            vulnerability
+                .components
+                .push(crate::bom::sbom::BomComponent::from_purl(purl.to_string()));
+            let alerts =
+                Alerts::from_bom_vulnerability(&connection, &snapshot, &vulnerability).await?;
+
+            let mut names = vec![];
+            for mut alert in alerts {
+                let dependency = alert.fetch_dependency(&connection).await?.unwrap();
+                names.push(dependency.name());
+            }
+            assert_eq!(names, expected);
+        }
+
+        let (mut libssl, _) = Component::from_purl("pkg:deb/debian/libssl3")?;
+        libssl.find_or_create(&connection).await?;
+        assert_eq!(libssl.origin.as_deref(), Some("openssl"));
+        assert_eq!(libssl.names(), vec!["libssl3", "openssl"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_alert_direct() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        // Without dependency graph data
+        let mut snapshot = Snapshot::create(&connection).await?;
+        let mut alert = add_alert(
+            &connection,
+            &snapshot,
+            "pkg:npm/express@4.0.0",
+            "CVE-0000",
+            SecuritySeverity::Critical,
+        )
+        .await?;
+        alert.fetch(&connection).await?;
+        assert_eq!(alert.direct(), None);
+        snapshot.calculate_alerts_summary(&connection).await?;
+        snapshot.fetch_metadata(&connection).await?;
+        assert_eq!(snapshot.find_metadata_usize("security.alerts.critical"), 1);
+        assert!(snapshot
+            .find_metadata("security.alerts.critical.direct")
+            .is_none());
+
+        // With dependency graph data
+        let mut snapshot = Snapshot::create(&connection).await?;
+        for (purl, advisory, severity, direct) in [
+            (
+                "pkg:npm/express@4.0.0",
+                "CVE-0001",
+                SecuritySeverity::Critical,
+                true,
+            ),
+            (
+                "pkg:npm/qs@6.0.0",
+                "CVE-0002",
+                SecuritySeverity::Critical,
+                false,
+            ),
+            (
+                "pkg:npm/debug@2.0.0",
+                "CVE-0003",
+                SecuritySeverity::Low,
+                false,
+            ),
+        ] {
+            let alert = add_alert(&connection, &snapshot, purl, advisory, severity).await?;
+            let mut dependency =
+                Dependencies::fetch_by_primary_key(&connection, alert.dependency_id.unwrap())
+                    .await?;
+            dependency.direct = Some(direct);
+            dependency.update(&connection).await?;
+        }
+
+        let page = Pagination::new();
+        let alerts = Alerts::fetch_snapshot_page(
+            &connection,
+            snapshot.id.into(),
+            None,
+            None,
+            Some(true),
+            None,
+            &page,
+        )
+        .await?;
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].name, "CVE-0001");
+        assert_eq!(alerts[0].direct(), Some(true));
+        let alerts = Alerts::fetch_snapshot_page(
+            &connection,
+            snapshot.id.into(),
+            None,
+            None,
+            Some(false),
+            None,
+            &page,
+        )
+        .await?;
+        assert_eq!(alerts.len(), 2);
+        assert!(alerts.iter().all(|a| a.direct() == Some(false)));
+
+        // Only the alerts of the snapshot with graph data
+        let alerts = Alerts::filter_kind(
+            &connection,
+            Some(AlertKind::Vulnerability),
+            Some(true),
+            SecurityState::Vulnerable,
+            &page,
+        )
+        .await?;
+        assert_eq!(alerts.len(), 1);
+
+        snapshot.calculate_alerts_summary(&connection).await?;
+        snapshot.fetch_metadata(&connection).await?;
+        assert_eq!(snapshot.find_metadata_usize("security.alerts.critical"), 2);
+        assert_eq!(
+            snapshot.find_metadata_usize("security.alerts.critical.direct"),
+            1
+        );
+        assert_eq!(
+            snapshot.find_metadata_usize("security.alerts.critical.transitive"),
+            1
+        );
+        assert_eq!(
+            snapshot.find_metadata_usize("security.alerts.low.transitive"),
+            1
+        );
+        assert_eq!(
+            snapshot.find_metadata_usize("security.alerts.total.direct"),
+            1
+        );
+        assert_eq!(
+            snapshot.find_metadata_usize("security.alerts.total.transitive"),
+            2
+        );
+        assert!(snapshot
+            .find_metadata("security.alerts.high.direct")
+            .is_some());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,386 @@
+//! # Bulk alert actions
+//!
+//! Acknowledge, mark as false positive or reopen every alert matching a filter (a false
+//! positive advisory across all the containers running an image). Only the alerts of the
+//! latest snapshots of the active projects are changed, in a single transaction.
+
+use geekorm::prelude::*;
+use log::{debug, info};
+use std::collections::BTreeSet;
+
+use super::{alerts::SecurityState, Alerts, SecuritySeverity};
+use crate::{
+    bom::processors::glob_match,
+    models::{raw_query, ProjectStatus, Snapshot, Transaction, TransactionConnection},
+    KonarrError,
+};
+
+/// Action applied to the matching alerts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertBulkAction {
+    /// Acknowledge the vulnerable alerts
+    Acknowledge,
+    /// Mark the open alerts as false positives
+    FalsePositive,
+    /// Reopen the acknowledged and false positive alerts
+    Reopen,
+}
+
+impl AlertBulkAction {
+    /// Parse the action (`acknowledge`, `false-positive` or `reopen`)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace('_', "-").as_str() {
+            "acknowledge" | "ack" => Some(AlertBulkAction::Acknowledge),
+            "false-positive" => Some(AlertBulkAction::FalsePositive),
+            "reopen" => Some(AlertBulkAction::Reopen),
+            _ => None,
+        }
+    }
+
+    /// If the action changes an alert in this state
+    pub fn applies_to(&self, state: &SecurityState) -> bool {
+        match self {
+            AlertBulkAction::Acknowledge => *state == SecurityState::Vulnerable,
+            AlertBulkAction::FalsePositive => state.is_open(),
+            AlertBulkAction::Reopen => matches!(
+                state,
+                SecurityState::Acknowledged | SecurityState::FalsePositive
+            ),
+        }
+    }
+
+    async fn apply<'a, T>(
+        &self,
+        connection: &'a T,
+        alert: &mut Alerts,
+        actor: &str,
+    ) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        match self {
+            AlertBulkAction::Acknowledge => alert.acknowledge(connection, actor).await,
+            AlertBulkAction::FalsePositive => alert.false_positive(connection, actor).await,
+            AlertBulkAction::Reopen => alert.reopen(connection, actor).await,
+        }
+    }
+}
+
+impl std::fmt::Display for AlertBulkAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlertBulkAction::Acknowledge => write!(f, "acknowledge"),
+            AlertBulkAction::FalsePositive => write!(f, "false-positive"),
+            AlertBulkAction::Reopen => write!(f, "reopen"),
+        }
+    }
+}
+
+/// Filter of the alerts a bulk action applies to
+///
+/// At least one criterion is required so an action can't change every alert.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlertBulkFilter {
+    /// Advisory glob (`CVE-2024-1234`, `GHSA-*`)
+    pub advisory: Option<String>,
+    /// Severity of the advisory
+    pub severity: Option<SecuritySeverity>,
+    /// Project and its children (all the active projects if not set)
+    pub project_id: Option<i32>,
+    /// Package URL glob, matched with and without the version (`pkg:deb/debian/perl*`)
+    pub purl: Option<String>,
+}
+
+/// Alerts matching a bulk action filter
+#[derive(Debug, Clone, Default)]
+pub struct AlertBulkResult {
+    /// Number of alerts matching the filter
+    pub matched: usize,
+    /// Alerts changed by the action (or which would be, for a dry run)
+    pub changed: Vec<Alerts>,
+}
+
+impl AlertBulkFilter {
+    /// If no criterion is set
+    pub fn is_empty(&self) -> bool {
+        self.advisory.as_deref().map_or(true, str::is_empty)
+            && self.severity.is_none()
+            && self.project_id.is_none()
+            && self.purl.as_deref().map_or(true, str::is_empty)
+    }
+
+    /// Filter as JSON (recorded in the audit log)
+    pub fn details(&self) -> serde_json::Value {
+        serde_json::json!({
+            "advisory": self.advisory,
+            "severity": self.severity.as_ref().map(|s| s.to_string()),
+            "project": self.project_id,
+            "purl": self.purl,
+        })
+    }
+
+    /// Alerts of the latest snapshots of the active projects matching the filter
+    pub async fn fetch<'a, T>(&self, connection: &'a T) -> Result<Vec<Alerts>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        if self.is_empty() {
+            return Err(KonarrError::InvalidData(
+                "At least one filter (advisory, severity, project or purl) is required".to_string(),
+            ));
+        }
+
+        let mut values = Values::new();
+        let mut query = String::new();
+        let mut projects = String::new();
+        if let Some(project_id) = self.project_id {
+            values.push("project_id".to_string(), project_id);
+            query.push_str(
+                "WITH RECURSIVE subtree(id) AS (SELECT ? \
+                    UNION SELECT Projects.id FROM Projects \
+                    INNER JOIN subtree ON Projects.parent = subtree.id) ",
+            );
+            projects.push_str(" AND Projects.id IN (SELECT id FROM subtree)");
+        }
+        values.push("status".to_string(), ProjectStatus::Active);
+        query.push_str(&format!(
+            "SELECT Alerts.* FROM Alerts \
+            INNER JOIN Advisories ON Advisories.id = Alerts.advisory_id \
+            WHERE Alerts.snapshot_id IN (\
+                SELECT MAX(ProjectSnapshots.snapshot_id) FROM ProjectSnapshots \
+                INNER JOIN Projects ON Projects.id = ProjectSnapshots.project_id \
+                WHERE Projects.status = ?{} \
+                GROUP BY ProjectSnapshots.project_id)",
+            projects
+        ));
+        if let Some(severity) = &self.severity {
+            values.push("severity".to_string(), severity.clone());
+            query.push_str(" AND Advisories.severity = ?");
+        }
+        // Exact advisories are filtered by the query, globs after
+        let advisory = self.advisory.as_deref().filter(|a| !a.is_empty());
+        if let Some(advisory) = advisory.filter(|a| !a.contains('*')) {
+            values.push("advisory".to_string(), advisory.to_string());
+            query.push_str(" AND Alerts.name = ?");
+        }
+        query.push_str(" ORDER BY Alerts.id;");
+
+        let alerts = T::query::<Alerts>(connection, raw_query(query, values)).await?;
+
+        let purl = self.purl.as_deref().filter(|p| !p.is_empty());
+        let mut matched = Vec::with_capacity(alerts.len());
+        for mut alert in alerts {
+            if let Some(advisory) = advisory {
+                if !glob_match(advisory, &alert.name) {
+                    continue;
+                }
+            }
+            if let Some(purl) = purl {
+                // Alerts without a matched dependency have no Package URL
+                let Some(dependency) = alert.fetch_dependency(connection).await? else {
+                    continue;
+                };
+                let component = dependency.component_id.data.purl();
+                let version = &dependency.component_version_id.data.version;
+                if !glob_match(purl, &component)
+                    && !glob_match(purl, &format!("{}@{}", component, version))
+                {
+                    continue;
+                }
+            }
+            matched.push(alert);
+        }
+        Ok(matched)
+    }
+
+    /// Apply the action to the matching alerts (only reports the changes for a dry run)
+    ///
+    /// The alerts and the summaries of their snapshots are updated in a single transaction.
+    pub async fn apply<'a, T>(
+        &self,
+        connection: &'a T,
+        action: AlertBulkAction,
+        actor: &str,
+        dry_run: bool,
+    ) -> Result<AlertBulkResult, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + TransactionConnection + 'a,
+    {
+        let alerts = self.fetch(connection).await?;
+        let matched = alerts.len();
+        let changed: Vec<Alerts> = alerts
+            .into_iter()
+            .filter(|alert| action.applies_to(&alert.state))
+            .collect();
+        debug!(
+            "Bulk {} :: {} matched, {} to change (dry run: {})",
+            action,
+            matched,
+            changed.len(),
+            dry_run
+        );
+        if dry_run || changed.is_empty() {
+            return Ok(AlertBulkResult { matched, changed });
+        }
+
+        let transaction = Transaction::begin(connection).await?;
+        let result = Self::apply_inner(transaction.connection(), action, actor, changed).await;
+        let changed = transaction.finish(result).await?;
+        info!("Bulk {} :: {} alerts changed", action, changed.len());
+        Ok(AlertBulkResult { matched, changed })
+    }
+
+    async fn apply_inner<'a, T>(
+        connection: &'a T,
+        action: AlertBulkAction,
+        actor: &str,
+        mut alerts: Vec<Alerts>,
+    ) -> Result<Vec<Alerts>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut snapshots = BTreeSet::new();
+        for alert in alerts.iter_mut() {
+            action.apply(connection, alert, actor).await?;
+            snapshots.insert(alert.snapshot_id.key);
+        }
+        // False positives are no longer counted in the summaries
+        for snapshot_id in snapshots {
+            let mut snapshot = Snapshot::fetch_by_primary_key(connection, snapshot_id).await?;
+            snapshot.fetch_metadata(connection).await?;
+            snapshot.calculate_alerts_summary(connection).await?;
+        }
+        Ok(alerts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        security::{Advisories, AdvisorySource},
+        Dependencies, ProjectType, Projects,
+    };
+
+    async fn alert(
+        connection: &libsql::Connection,
+        snapshot: &Snapshot,
+        advisory: &Advisories,
+        purl: &str,
+    ) -> Result<(), KonarrError> {
+        let mut dependency = Dependencies::from_purl(connection, purl.to_string()).await?;
+        dependency.snapshot_id = snapshot.id.into();
+        dependency.save(connection).await?;
+
+        let mut alert = Alerts {
+            dependency_id: Some(dependency.id.into()),
+            ..Alerts::new(advisory.name.clone(), snapshot.id, advisory.id)
+        };
+        alert.find_or_create(connection).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bulk_actions() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut high = Advisories::new(
+            "CVE-2024-0001",
+            AdvisorySource::Unknown,
+            SecuritySeverity::High,
+        );
+        high.save(&connection).await?;
+        let mut low = Advisories::new(
+            "CVE-2024-0002",
+            AdvisorySource::Unknown,
+            SecuritySeverity::Low,
+        );
+        low.save(&connection).await?;
+
+        let mut server = Projects::new("server", ProjectType::Server);
+        server.save(&connection).await?;
+        let mut other = Projects::new("other", ProjectType::Server);
+        other.save(&connection).await?;
+
+        let mut snapshots = vec![];
+        for (name, parent) in [("web", &server), ("db", &server), ("app", &other)] {
+            let mut project =
+                Projects::new(format!("{}/{}", parent.name, name), ProjectType::Container);
+            project.parent = parent.id.into();
+            project.save(&connection).await?;
+
+            // Alerts of older snapshots are never changed
+            let old = Snapshot::create(&connection).await?;
+            alert(&connection, &old, &high, "pkg:deb/debian/perl@5.36.0").await?;
+            project.add_snapshot(&connection, old).await?;
+
+            let snapshot = Snapshot::create(&connection).await?;
+            alert(&connection, &snapshot, &high, "pkg:deb/debian/perl@5.36.0").await?;
+            alert(&connection, &snapshot, &low, "pkg:npm/lodash@4.17.20").await?;
+            project.add_snapshot(&connection, snapshot.clone()).await?;
+            snapshots.push(snapshot);
+        }
+
+        // Unbounded
+        assert!(AlertBulkFilter::default()
+            .apply(&connection, AlertBulkAction::Acknowledge, "admin", true)
+            .await
+            .is_err());
+
+        // Dry run
+        let filter = AlertBulkFilter {
+            advisory: Some("CVE-2024-0001".to_string()),
+            ..Default::default()
+        };
+        let preview = filter
+            .apply(&connection, AlertBulkAction::FalsePositive, "admin", true)
+            .await?;
+        assert_eq!(preview.matched, 3);
+        assert_eq!(preview.changed.len(), 3);
+        assert_eq!(
+            filter.fetch(&connection).await?[0].state,
+            SecurityState::Vulnerable
+        );
+
+        // Project subtree and Package URL glob
+        let filter = AlertBulkFilter {
+            project_id: Some(server.id.into()),
+            purl: Some("pkg:deb/debian/perl*".to_string()),
+            ..Default::default()
+        };
+        let result = filter
+            .apply(&connection, AlertBulkAction::FalsePositive, "admin", false)
+            .await?;
+        assert_eq!(result.changed.len(), 2);
+        for alert in filter.fetch(&connection).await? {
+            assert_eq!(alert.state, SecurityState::FalsePositive);
+        }
+
+        // The summaries no longer count the false positives
+        let mut snapshot = Snapshot::fetch_by_primary_key(&connection, snapshots[0].id).await?;
+        snapshot.fetch_metadata(&connection).await?;
+        assert_eq!(snapshot.find_metadata_usize("security.alerts.high"), 0);
+        assert_eq!(snapshot.find_metadata_usize("security.alerts.low"), 1);
+
+        // False positives are not acknowledged, only the remaining alert is
+        let filter = AlertBulkFilter {
+            advisory: Some("CVE-*-0001".to_string()),
+            severity: Some(SecuritySeverity::High),
+            ..Default::default()
+        };
+        let result = filter
+            .apply(&connection, AlertBulkAction::Acknowledge, "admin", false)
+            .await?;
+        assert_eq!(result.matched, 3);
+        assert_eq!(result.changed.len(), 1);
+
+        let result = filter
+            .apply(&connection, AlertBulkAction::Reopen, "admin", false)
+            .await?;
+        assert_eq!(result.changed.len(), 3);
+        for alert in filter.fetch(&connection).await? {
+            assert_eq!(alert.state, SecurityState::Vulnerable);
+        }
+        Ok(())
+    }
+}
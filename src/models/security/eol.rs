@@ -0,0 +1,352 @@
+//! # End-of-life (EOL)
+//!
+//! Operating systems and language runtimes (classified by the catalogue) which are past
+//! their end-of-life date, or within the warning window (`security.eol.warning_days`),
+//! are stored as informational alerts for the `EndOfLife` advisories (named
+//! `eol.<product>.<cycle>`) and counted in the `security.eol.total` snapshot metadata.
+
+use chrono::NaiveDate;
+use geekorm::prelude::*;
+use log::debug;
+
+use super::{Advisories, AdvisorySource, Alerts, SecuritySeverity, SecurityState};
+use crate::{
+    models::{settings::keys::Setting, ComponentType, Dependencies, ServerSettings, Snapshot},
+    utils::eol::{EolSchedule, EolStatus},
+    KonarrError,
+};
+
+/// Prefix of the end-of-life advisories / alerts
+pub const EOL_PREFIX: &str = "eol.";
+
+/// Default warning window (days before the end-of-life date)
+pub const EOL_WARNING_DAYS: i64 = 90;
+
+/// Component types checked against the end-of-life schedules
+pub const EOL_COMPONENT_TYPES: [ComponentType; 2] = [
+    ComponentType::OperatingSystem,
+    ComponentType::ProgrammingLanguage,
+];
+
+/// End-of-life finding for a dependency
+#[derive(Debug, Clone, PartialEq)]
+pub struct EolFinding {
+    /// Product (endoflife.date name)
+    pub product: String,
+    /// Release cycle of the product
+    pub cycle: String,
+    /// Support status (past end-of-life or within the warning window)
+    pub status: EolStatus,
+    /// Dependency (row) of the operating system / runtime
+    pub dependency_id: i32,
+}
+
+impl EolFinding {
+    /// Name of the advisory / alert
+    pub fn name(&self) -> String {
+        format!("{}{}.{}", EOL_PREFIX, self.product, self.cycle)
+    }
+
+    /// End-of-life date (if known)
+    pub fn date(&self) -> Option<NaiveDate> {
+        match self.status {
+            EolStatus::Warning(date) => Some(date),
+            EolStatus::EndOfLife(date) => date,
+            EolStatus::Supported => None,
+        }
+    }
+
+    /// Description of the finding (stored on the advisory)
+    pub fn description(&self) -> String {
+        match (&self.status, self.date()) {
+            (EolStatus::Warning(_), Some(date)) => format!(
+                "{} {} reaches end-of-life on {}",
+                self.product, self.cycle, date
+            ),
+            (_, Some(date)) => format!(
+                "{} {} reached end-of-life on {}",
+                self.product, self.cycle, date
+            ),
+            (_, None) => format!("{} {} reached end-of-life", self.product, self.cycle),
+        }
+    }
+}
+
+/// End-of-life checks configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct EolConfig {
+    /// Number of days before the end-of-life date a cycle is flagged
+    pub warning_days: i64,
+}
+
+impl Default for EolConfig {
+    fn default() -> Self {
+        Self {
+            warning_days: EOL_WARNING_DAYS,
+        }
+    }
+}
+
+impl EolConfig {
+    /// Load the configuration from the server settings
+    pub async fn load<'a, T>(connection: &'a T) -> Result<Self, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let warning_days =
+            ServerSettings::fetch_by_name(connection, Setting::SecurityEolWarningDays)
+                .await
+                .ok()
+                .and_then(|setting| setting.value.trim().parse::<i64>().ok())
+                .filter(|days| *days >= 0)
+                .unwrap_or(EOL_WARNING_DAYS);
+        Ok(Self { warning_days })
+    }
+
+    /// Evaluate the dependencies (operating systems and runtimes) against the schedules
+    pub fn evaluate(
+        &self,
+        schedule: &EolSchedule,
+        dependencies: &[Dependencies],
+        today: NaiveDate,
+    ) -> Vec<EolFinding> {
+        dependencies
+            .iter()
+            .filter_map(|dependency| {
+                let version = dependency.version()?;
+                let (product, cycle) = schedule.lookup(&dependency.name(), &version)?;
+                match cycle.status(today, self.warning_days) {
+                    EolStatus::Supported => None,
+                    status => Some(EolFinding {
+                        product: product.to_string(),
+                        cycle: cycle.cycle.clone(),
+                        status,
+                        dependency_id: dependency.id.into(),
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    /// Check the operating systems and runtimes of a snapshot and store the findings
+    ///
+    /// Findings are stored as alerts (re-opened if they were resolved) and the end-of-life
+    /// alerts which no longer apply are resolved.
+    pub async fn apply<'a, T>(
+        &self,
+        connection: &'a T,
+        schedule: &EolSchedule,
+        snapshot: &Snapshot,
+    ) -> Result<Vec<EolFinding>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut dependencies = Vec::new();
+        for component_type in EOL_COMPONENT_TYPES.iter() {
+            dependencies.extend(
+                snapshot
+                    .fetch_dependencies_by_type(connection, component_type)
+                    .await?,
+            );
+        }
+        let findings = self.evaluate(schedule, &dependencies, chrono::Utc::now().date_naive());
+        debug!(
+            "Snapshot({}) :: {} end-of-life findings",
+            snapshot.id,
+            findings.len()
+        );
+
+        let existing: Vec<Alerts> = Alerts::fetch_by_snapshot_id(connection, snapshot.id)
+            .await?
+            .into_iter()
+            .filter(|alert| alert.is_eol())
+            .collect();
+
+        let mut current = Vec::new();
+        for finding in findings.iter() {
+            let advisory = eol_advisory(connection, finding).await?;
+            let mut alert = Alerts {
+                dependency_id: Some(finding.dependency_id),
+                ..Alerts::new(finding.name(), snapshot.id, advisory.id)
+            };
+            alert.find_or_create(connection).await?;
+            current.push(alert.id);
+
+            if let Some(mut alert) = existing.iter().find(|a| a.id == alert.id).cloned() {
+                if alert.state == SecurityState::Secure {
+                    alert.state = SecurityState::Vulnerable;
+                    alert.updated_at = chrono::Utc::now();
+                    alert.update(connection).await?;
+                }
+            }
+        }
+
+        for alert in existing.iter() {
+            if alert.state != SecurityState::Secure && !current.contains(&alert.id) {
+                alert.clone().close(connection).await?;
+            }
+        }
+
+        Ok(findings)
+    }
+}
+
+impl Alerts {
+    /// If the alert is an end-of-life finding (not a vulnerability)
+    pub fn is_eol(&self) -> bool {
+        self.name.starts_with(EOL_PREFIX)
+    }
+}
+
+/// Find or create the advisory for a finding (the date is updated if the schedule changed)
+async fn eol_advisory<'a, T>(
+    connection: &'a T,
+    finding: &EolFinding,
+) -> Result<Advisories, KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let mut advisory = match Advisories::fetch_by_name(connection, finding.name()).await {
+        Ok(advisory) => advisory,
+        Err(_) => {
+            let mut advisory = Advisories::new(
+                finding.name(),
+                AdvisorySource::EndOfLife,
+                SecuritySeverity::Informational,
+            );
+            advisory.save(connection).await?;
+            advisory
+                .add_metadata(
+                    connection,
+                    "url",
+                    format!("https://endoflife.date/{}", finding.product),
+                )
+                .await?;
+            advisory
+        }
+    };
+
+    let values = [
+        ("description", finding.description()),
+        (
+            "eol.date",
+            finding.date().map(|d| d.to_string()).unwrap_or_default(),
+        ),
+    ];
+    for (key, value) in values {
+        match advisory.get_metadata(connection, key).await? {
+            Some(mut meta) if meta.value != value => {
+                meta.value = value;
+                meta.update(connection).await?;
+            }
+            Some(_) => {}
+            None => advisory.add_metadata(connection, key, value).await?,
+        }
+    }
+    Ok(advisory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ProjectType, Projects};
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_eol_finding() {
+        let finding = EolFinding {
+            product: "debian".to_string(),
+            cycle: "10".to_string(),
+            status: EolStatus::EndOfLife(Some(date("2022-09-10"))),
+            dependency_id: 1,
+        };
+        assert_eq!(finding.name(), "eol.debian.10");
+        assert_eq!(
+            finding.description(),
+            "debian 10 reached end-of-life on 2022-09-10"
+        );
+
+        let finding = EolFinding {
+            status: EolStatus::Warning(date("2026-06-10")),
+            ..finding
+        };
+        assert_eq!(
+            finding.description(),
+            "debian 10 reaches end-of-life on 2026-06-10"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_eol_apply() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("debian", ProjectType::Container);
+        project.save(&connection).await?;
+        let mut snapshot = Snapshot::create(&connection).await?;
+        project.add_snapshot(&connection, snapshot.clone()).await?;
+
+        for (purl, component_type) in [
+            ("pkg:deb/debian@10.13", ComponentType::OperatingSystem),
+            (
+                "pkg:deb/debian/python3@3.7.3-1",
+                ComponentType::ProgrammingLanguage,
+            ),
+            (
+                "pkg:deb/debian/openssl@1.1.1n",
+                ComponentType::CryptographyLibrary,
+            ),
+        ] {
+            let mut dependency = Dependencies::from_purl(&connection, purl.to_string()).await?;
+            dependency.snapshot_id = snapshot.id.into();
+            dependency.save(&connection).await?;
+            let mut component = dependency.fetch_component_id(&connection).await?;
+            component.component_type = component_type;
+            component.update(&connection).await?;
+        }
+
+        let config = EolConfig::load(&connection).await?;
+        assert_eq!(config.warning_days, EOL_WARNING_DAYS);
+        let schedule = EolSchedule::bundled();
+
+        let findings = config.apply(&connection, &schedule, &snapshot).await?;
+        let mut names: Vec<String> = findings.iter().map(|f| f.name()).collect();
+        names.sort();
+        assert_eq!(names, vec!["eol.debian.10", "eol.python.3.7"]);
+        // Applying again does not duplicate the alerts
+        config.apply(&connection, &schedule, &snapshot).await?;
+        let alerts = Alerts::fetch_by_snapshot_id(&connection, snapshot.id).await?;
+        assert_eq!(alerts.len(), 2);
+        assert!(alerts.iter().all(|a| a.is_eol()));
+        assert!(alerts.iter().all(|a| a.dependency_id.is_some()));
+
+        let mut advisory = Advisories::fetch_by_name(&connection, "eol.debian.10").await?;
+        assert_eq!(advisory.source, AdvisorySource::EndOfLife);
+        assert_eq!(advisory.severity, SecuritySeverity::Informational);
+        let meta = advisory
+            .get_metadata(&connection, "eol.date")
+            .await?
+            .unwrap();
+        assert_eq!(meta.value, "2022-09-10");
+
+        // End-of-life findings are not counted as vulnerabilities
+        let summary = snapshot.calculate_alerts_summary(&connection).await?;
+        assert!(summary.is_empty());
+        snapshot.fetch_metadata(&connection).await?;
+        assert_eq!(snapshot.find_metadata_usize("security.eol.total"), 2);
+
+        // Product no longer in the schedule, the findings are resolved
+        config
+            .apply(&connection, &EolSchedule::default(), &snapshot)
+            .await?;
+        let alerts = Alerts::fetch_by_snapshot_id(&connection, snapshot.id).await?;
+        assert!(alerts.iter().all(|a| a.state == SecurityState::Secure));
+        snapshot.calculate_alerts_summary(&connection).await?;
+        snapshot.fetch_metadata(&connection).await?;
+        assert_eq!(snapshot.find_metadata_usize("security.eol.total"), 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,674 @@
+//! # Alert events
+//!
+//! Timeline of the alert state transitions for an advisory in a project (detected,
+//! acknowledged, reopened and resolved). Alerts are stored per snapshot, the events
+//! follow the advisory and the vulnerable component (not its version) across the
+//! snapshots of the project.
+//!
+//! An advisory is resolved (fixed) once a new snapshot no longer has it for the
+//! component. Upgrading the component to a version which is still vulnerable does not
+//! resolve it.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use geekorm::prelude::*;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use super::{alerts::SecurityState, Alerts, SecuritySeverity};
+use crate::{models::raw_query, KonarrError};
+
+/// Actor recorded for the events created by the background tasks
+pub const ALERT_EVENTS_SYSTEM: &str = "system";
+/// Maximum number of entries in the alerts feed of a project
+pub const ALERT_FEED_LIMIT: u32 = 50;
+/// Number of days of the recently resolved alerts counts
+pub const ALERTS_RESOLVED_RECENT_DAYS: i64 = 30;
+
+/// Alert event kind
+#[derive(Data, Debug, Clone, Default, PartialEq)]
+pub enum AlertEventKind {
+    /// Advisory detected in the project for the first time
+    #[default]
+    #[geekorm(aliases = "detected")]
+    Detected,
+    /// Alert acknowledged by a user
+    #[geekorm(aliases = "acknowledged")]
+    Acknowledged,
+    /// Advisory detected again after being resolved (or an acknowledgement reverted)
+    #[geekorm(aliases = "reopened")]
+    Reopened,
+    /// Advisory no longer present in the latest snapshot of the project
+    #[geekorm(aliases = "resolved")]
+    Resolved,
+    /// Alert marked as a false positive by a user
+    #[geekorm(aliases = "false_positive")]
+    FalsePositive,
+}
+
+impl AlertEventKind {
+    /// If the advisory is still open after the event
+    pub fn is_open(&self) -> bool {
+        !matches!(self, AlertEventKind::Resolved)
+    }
+}
+
+/// Newly detected alert (entry of the alerts feed of a project)
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertFeedEntry {
+    /// ID of the event
+    pub id: i32,
+    /// Advisory ID
+    pub advisory_id: i32,
+    /// Advisory name (CVE / GHSA ID)
+    pub advisory: String,
+    /// Severity of the advisory
+    pub severity: SecuritySeverity,
+    /// Vulnerable component (`name@version`, if the alert is matched to a dependency)
+    pub component: Option<String>,
+    /// Snapshot the alert was detected in
+    pub snapshot_id: Option<i32>,
+    /// Time the alert was detected
+    pub created_at: DateTime<Utc>,
+}
+
+/// Identity of an alert across the snapshots of a project (advisory, component)
+type AlertIdentity = (i32, Option<i32>);
+
+#[derive(Debug, Deserialize)]
+struct AlertComponentRow {
+    id: i32,
+    advisory_id: i32,
+    state: SecurityState,
+    component_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CountRow {
+    count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlertFeedRow {
+    id: i32,
+    advisory_id: i32,
+    advisory: String,
+    severity: String,
+    snapshot_id: Option<i32>,
+    created_at: DateTime<Utc>,
+    namespace: Option<String>,
+    name: Option<String>,
+    version: Option<String>,
+}
+
+/// Alert events table
+#[derive(Table, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertEvents {
+    /// Primary key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+
+    /// Project the event happened in
+    pub project_id: i32,
+    /// Advisory of the alert
+    pub advisory_id: i32,
+    /// Vulnerable component (`None` if the alert did not match a dependency, or for
+    /// the events recorded before the events followed the component)
+    pub component_id: Option<i32>,
+    /// Alert (row) which triggered the event
+    pub alert_id: Option<i32>,
+    /// Snapshot the transition was observed in
+    pub snapshot_id: Option<i32>,
+
+    /// Kind of event
+    pub event: AlertEventKind,
+    /// User who changed the state ([ALERT_EVENTS_SYSTEM] for the background tasks)
+    pub actor: String,
+
+    /// Time of the event
+    #[geekorm(new = "Utc::now()")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl AlertEvents {
+    /// Initialise the Alert Events table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::create_table(connection).await?;
+        Ok(())
+    }
+
+    /// Record an event for an alert in every project of the alert's snapshot
+    pub async fn record_alert<'a, T>(
+        connection: &'a T,
+        alert: &Alerts,
+        event: AlertEventKind,
+        actor: impl Into<String>,
+    ) -> Result<Vec<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let actor = actor.into();
+        let projects = crate::models::ProjectSnapshots::query(
+            connection,
+            crate::models::ProjectSnapshots::query_select()
+                .where_eq("snapshot_id", alert.snapshot_id.key)
+                .build()?,
+        )
+        .await?;
+
+        let component_id = match alert.dependency_id {
+            Some(dependency) => {
+                crate::models::Dependencies::fetch_by_primary_key(connection, dependency)
+                    .await
+                    .ok()
+                    .map(|d| d.component_id.key)
+            }
+            None => None,
+        };
+
+        let mut events = Vec::new();
+        for project in projects {
+            let mut item = Self::new(
+                project.project_id.key,
+                alert.advisory_id.key,
+                event.clone(),
+                actor.clone(),
+            );
+            item.component_id = component_id;
+            item.alert_id = Some(alert.id.into());
+            item.snapshot_id = Some(alert.snapshot_id.key);
+            item.save(connection).await?;
+            events.push(item);
+        }
+        Ok(events)
+    }
+
+    /// Fetch the timeline of an advisory in a project (oldest first)
+    pub async fn fetch_timeline<'a, T>(
+        connection: &'a T,
+        project_id: i32,
+        advisory_id: i32,
+    ) -> Result<Vec<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Self::query(
+            connection,
+            Self::query_select()
+                .where_eq("project_id", project_id)
+                .and()
+                .where_eq("advisory_id", advisory_id)
+                .order_by("id", QueryOrder::Asc)
+                .build()?,
+        )
+        .await?)
+    }
+
+    /// Fetch the latest event of each alert in a project (keyed by advisory and component)
+    pub async fn fetch_latest<'a, T>(
+        connection: &'a T,
+        project_id: i32,
+    ) -> Result<HashMap<AlertIdentity, Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("project_id".to_string(), project_id);
+        let events = T::query::<Self>(
+            connection,
+            raw_query(
+                "SELECT * FROM AlertEvents WHERE id IN \
+                (SELECT MAX(id) FROM AlertEvents WHERE project_id = ? \
+                GROUP BY advisory_id, component_id);",
+                values,
+            ),
+        )
+        .await?;
+        Ok(events
+            .into_iter()
+            .map(|e| ((e.advisory_id, e.component_id), e))
+            .collect())
+    }
+
+    /// Reconcile the timeline of a project with the alerts of its latest snapshot
+    ///
+    /// Alerts are identified by the advisory and the vulnerable component, alerts which
+    /// are new (or came back after being resolved) are detected (reopened), open alerts
+    /// which are no longer in the snapshot are resolved by the snapshot.
+    ///
+    /// Events without a component (recorded before the events followed the component, or
+    /// for alerts not matched to a dependency) stand for the whole advisory: they are
+    /// only resolved once the advisory is gone from the snapshot.
+    /// Returns the number of events recorded.
+    pub async fn reconcile<'a, T>(
+        connection: &'a T,
+        project_id: i32,
+        snapshot_id: i32,
+    ) -> Result<u32, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("snapshot_id".to_string(), snapshot_id);
+        let alerts = T::query::<AlertComponentRow>(
+            connection,
+            raw_query(
+                "SELECT a.id, a.advisory_id, a.state, d.component_id FROM Alerts a \
+                LEFT JOIN Dependencies d ON d.id = a.dependency_id \
+                WHERE a.snapshot_id = ? \
+                ORDER BY a.id;",
+                values,
+            ),
+        )
+        .await?;
+
+        let mut present: HashMap<AlertIdentity, i32> = HashMap::new();
+        for alert in alerts.iter() {
+            if alert.state != SecurityState::Secure {
+                present
+                    .entry((alert.advisory_id, alert.component_id))
+                    .or_insert(alert.id);
+            }
+        }
+        let advisories: std::collections::HashSet<i32> =
+            present.keys().map(|(advisory, _)| *advisory).collect();
+        let latest = Self::fetch_latest(connection, project_id).await?;
+
+        let mut changes: Vec<(AlertIdentity, Option<i32>, AlertEventKind)> = Vec::new();
+        for (identity, alert) in present.iter() {
+            // Fallback on the event of the whole advisory
+            let event = latest
+                .get(identity)
+                .or_else(|| latest.get(&(identity.0, None)));
+            match event {
+                None => changes.push((*identity, Some(*alert), AlertEventKind::Detected)),
+                Some(event) if !event.event.is_open() => {
+                    changes.push((*identity, Some(*alert), AlertEventKind::Reopened))
+                }
+                Some(_) => {}
+            }
+        }
+        for (identity, event) in latest.iter() {
+            let gone = match identity {
+                (advisory, None) => !advisories.contains(advisory),
+                _ => !present.contains_key(identity),
+            };
+            if event.event.is_open() && gone {
+                changes.push((*identity, event.alert_id, AlertEventKind::Resolved));
+            }
+        }
+        // Keep the timeline stable (by advisory and component)
+        changes.sort_by_key(|(identity, _, _)| *identity);
+
+        for ((advisory, component), alert, event) in changes.iter() {
+            debug!(
+                "Project({}) :: Advisory({}) Component({:?}) {:?}",
+                project_id, advisory, component, event
+            );
+            let mut item = Self::new(project_id, *advisory, event.clone(), ALERT_EVENTS_SYSTEM);
+            item.component_id = *component;
+            item.alert_id = *alert;
+            item.snapshot_id = Some(snapshot_id);
+            item.save(connection).await?;
+        }
+        Ok(changes.len() as u32)
+    }
+
+    /// Count the resolved (fixed) alerts, of a project or all the projects, since the time
+    pub async fn count_resolved<'a, T>(
+        connection: &'a T,
+        project_id: Option<i32>,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<i64, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("resolved".to_string(), AlertEventKind::Resolved);
+        let mut sql = "SELECT COUNT(*) AS count FROM AlertEvents WHERE event = ?".to_string();
+        if let Some(project_id) = project_id {
+            values.push("project_id".to_string(), project_id);
+            sql.push_str(" AND project_id = ?");
+        }
+        if let Some(since) = since {
+            values.push("since".to_string(), since);
+            sql.push_str(" AND created_at >= ?");
+        }
+        sql.push(';');
+
+        let rows = T::query::<CountRow>(connection, raw_query(sql, values)).await?;
+        Ok(rows.first().map(|row| row.count).unwrap_or(0))
+    }
+
+    /// Fetch the newly detected (or reopened) alerts of a project (newest first)
+    pub async fn fetch_feed<'a, T>(
+        connection: &'a T,
+        project_id: i32,
+        limit: u32,
+    ) -> Result<Vec<AlertFeedEntry>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("project_id".to_string(), project_id);
+        values.push("detected".to_string(), AlertEventKind::Detected);
+        values.push("reopened".to_string(), AlertEventKind::Reopened);
+        values.push("limit".to_string(), limit as i32);
+
+        let rows = T::query::<AlertFeedRow>(
+            connection,
+            raw_query(
+                "SELECT e.id, e.advisory_id, adv.name AS advisory, adv.severity, \
+                    e.snapshot_id, e.created_at, c.namespace, c.name, v.version \
+                FROM AlertEvents e \
+                INNER JOIN Advisories adv ON adv.id = e.advisory_id \
+                LEFT JOIN Alerts a ON a.id = e.alert_id \
+                LEFT JOIN Dependencies d ON d.id = a.dependency_id \
+                LEFT JOIN Component c ON c.id = d.component_id \
+                LEFT JOIN ComponentVersion v ON v.id = d.component_version_id \
+                WHERE e.project_id = ? AND e.event IN (?, ?) \
+                ORDER BY e.id DESC \
+                LIMIT ?;",
+                values,
+            ),
+        )
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| AlertFeedEntry {
+                id: row.id,
+                advisory_id: row.advisory_id,
+                advisory: row.advisory,
+                severity: SecuritySeverity::from(row.severity),
+                component: row.name.map(|name| {
+                    let name = match row.namespace.filter(|ns| !ns.is_empty()) {
+                        Some(namespace) => format!("{}/{}", namespace, name),
+                        None => name,
+                    };
+                    match row.version {
+                        Some(version) => format!("{}@{}", name, version),
+                        None => name,
+                    }
+                }),
+                snapshot_id: row.snapshot_id,
+                created_at: row.created_at,
+            })
+            .collect())
+    }
+
+    /// Remove the events older than the date (returns the number of events removed)
+    ///
+    /// The latest event of each advisory in a project is always kept so the
+    /// current state of the timeline is not lost.
+    pub async fn prune<'a, T>(connection: &'a T, before: DateTime<Utc>) -> Result<u64, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let count = Self::row_count(connection, Self::query_count().build()?).await?;
+
+        let mut values = Values::new();
+        values.push("before".to_string(), before);
+        T::execute::<Self>(
+            connection,
+            raw_query(
+                "DELETE FROM AlertEvents WHERE created_at < ? AND id NOT IN \
+                (SELECT MAX(id) FROM AlertEvents GROUP BY project_id, advisory_id);",
+                values,
+            ),
+        )
+        .await?;
+
+        let remaining = Self::row_count(connection, Self::query_count().build()?).await?;
+        Ok(count.saturating_sub(remaining) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        security::{Advisories, AdvisorySource, SecuritySeverity},
+        Dependencies, ProjectType, Projects, Snapshot,
+    };
+
+    async fn snapshot_with_alert(
+        connection: &libsql::Connection,
+        project: &mut Projects,
+        advisory: &Advisories,
+        vulnerable: bool,
+    ) -> Result<(Snapshot, Option<Alerts>), KonarrError> {
+        let snapshot = Snapshot::create(connection).await?;
+        project.add_snapshot(connection, snapshot.clone()).await?;
+        if !vulnerable {
+            return Ok((snapshot, None));
+        }
+
+        let mut dependency =
+            Dependencies::from_purl(connection, "pkg:deb/debian/openssl@3.0.1".to_string()).await?;
+        dependency.snapshot_id = snapshot.id.into();
+        dependency.save(connection).await?;
+
+        let mut alert = Alerts {
+            dependency_id: Some(dependency.id.into()),
+            ..Alerts::new(advisory.name.clone(), snapshot.id, advisory.id)
+        };
+        alert.save(connection).await?;
+        Ok((snapshot, Some(alert)))
+    }
+
+    #[tokio::test]
+    async fn test_alert_timeline() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("project", ProjectType::Container);
+        project.save(&connection).await?;
+        let mut advisory =
+            Advisories::new("CVE-0001", AdvisorySource::Unknown, SecuritySeverity::High);
+        advisory.save(&connection).await?;
+        let project_id: i32 = project.id.into();
+        let advisory_id: i32 = advisory.id.into();
+
+        // Detect
+        let (snapshot, alert) =
+            snapshot_with_alert(&connection, &mut project, &advisory, true).await?;
+        assert_eq!(
+            AlertEvents::reconcile(&connection, project_id, snapshot.id.into()).await?,
+            1
+        );
+        // Nothing changed
+        assert_eq!(
+            AlertEvents::reconcile(&connection, project_id, snapshot.id.into()).await?,
+            0
+        );
+
+        // Acknowledge
+        let mut alert = alert.unwrap();
+        alert.acknowledge(&connection, "admin").await?;
+        assert_eq!(alert.state, SecurityState::Acknowledged);
+        assert_eq!(
+            AlertEvents::reconcile(&connection, project_id, snapshot.id.into()).await?,
+            0
+        );
+
+        // Resolve (new snapshot without the vulnerable component)
+        let (snapshot, _) =
+            snapshot_with_alert(&connection, &mut project, &advisory, false).await?;
+        assert_eq!(
+            AlertEvents::reconcile(&connection, project_id, snapshot.id.into()).await?,
+            1
+        );
+
+        // Regress
+        let (snapshot, _) = snapshot_with_alert(&connection, &mut project, &advisory, true).await?;
+        AlertEvents::reconcile(&connection, project_id, snapshot.id.into()).await?;
+
+        let timeline = AlertEvents::fetch_timeline(&connection, project_id, advisory_id).await?;
+        let events: Vec<(AlertEventKind, &str)> = timeline
+            .iter()
+            .map(|e| (e.event.clone(), e.actor.as_str()))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (AlertEventKind::Detected, ALERT_EVENTS_SYSTEM),
+                (AlertEventKind::Acknowledged, "admin"),
+                (AlertEventKind::Resolved, ALERT_EVENTS_SYSTEM),
+                (AlertEventKind::Reopened, ALERT_EVENTS_SYSTEM),
+            ]
+        );
+        assert_eq!(timeline[3].snapshot_id, Some(snapshot.id.into()));
+
+        // Feed of the detected / reopened alerts (newest first)
+        let feed = AlertEvents::fetch_feed(&connection, project_id, ALERT_FEED_LIMIT).await?;
+        assert_eq!(feed.len(), 2);
+        assert_eq!(feed[0].id, i32::from(timeline[3].id));
+        assert_eq!(feed[0].advisory, "CVE-0001");
+        assert_eq!(feed[0].severity, SecuritySeverity::High);
+        assert_eq!(feed[0].component, Some("debian/openssl@3.0.1".to_string()));
+        assert_eq!(feed[1].id, i32::from(timeline[0].id));
+        assert_eq!(
+            AlertEvents::fetch_feed(&connection, project_id, 1)
+                .await?
+                .len(),
+            1
+        );
+
+        // Pruning keeps the latest event of the advisory
+        let removed =
+            AlertEvents::prune(&connection, Utc::now() + chrono::Duration::days(1)).await?;
+        assert_eq!(removed, 3);
+        let timeline = AlertEvents::fetch_timeline(&connection, project_id, advisory_id).await?;
+        assert_eq!(timeline.len(), 1);
+        assert_eq!(timeline[0].event, AlertEventKind::Reopened);
+
+        Ok(())
+    }
+    /// New snapshot of the project with an alert of the advisory on each of the PURLs
+    async fn snapshot_with_alerts(
+        connection: &libsql::Connection,
+        project: &mut Projects,
+        advisory: &Advisories,
+        purls: &[&str],
+    ) -> Result<Snapshot, KonarrError> {
+        let snapshot = Snapshot::create(connection).await?;
+        project.add_snapshot(connection, snapshot.clone()).await?;
+        for purl in purls {
+            let mut dependency = Dependencies::from_purl(connection, purl.to_string()).await?;
+            dependency.snapshot_id = snapshot.id.into();
+            dependency.save(connection).await?;
+
+            let mut alert = Alerts {
+                dependency_id: Some(dependency.id.into()),
+                ..Alerts::new(advisory.name.clone(), snapshot.id, advisory.id)
+            };
+            alert.save(connection).await?;
+        }
+        Ok(snapshot)
+    }
+
+    #[tokio::test]
+    async fn test_alert_resolution() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("project", ProjectType::Container);
+        project.save(&connection).await?;
+        let mut advisory =
+            Advisories::new("CVE-0002", AdvisorySource::Unknown, SecuritySeverity::High);
+        advisory.save(&connection).await?;
+        let project_id: i32 = project.id.into();
+
+        let snapshot = snapshot_with_alerts(
+            &connection,
+            &mut project,
+            &advisory,
+            &[
+                "pkg:deb/debian/openssl@3.0.1",
+                "pkg:deb/debian/libssl@3.0.1",
+            ],
+        )
+        .await?;
+        assert_eq!(
+            AlertEvents::reconcile(&connection, project_id, snapshot.id.into()).await?,
+            2
+        );
+
+        // openssl upgraded but still vulnerable, libssl fixed
+        let snapshot = snapshot_with_alerts(
+            &connection,
+            &mut project,
+            &advisory,
+            &["pkg:deb/debian/openssl@3.0.2"],
+        )
+        .await?;
+        assert_eq!(
+            AlertEvents::reconcile(&connection, project_id, snapshot.id.into()).await?,
+            1
+        );
+        let timeline =
+            AlertEvents::fetch_timeline(&connection, project_id, advisory.id.into()).await?;
+        let resolved = timeline.last().unwrap();
+        assert_eq!(resolved.event, AlertEventKind::Resolved);
+        assert_eq!(resolved.snapshot_id, Some(snapshot.id.into()));
+        let (mut libssl, _) = crate::models::Component::from_purl("pkg:deb/debian/libssl@3.0.1")?;
+        libssl.find_or_create(&connection).await?;
+        assert_eq!(resolved.component_id, Some(libssl.id.into()));
+
+        assert_eq!(
+            AlertEvents::count_resolved(&connection, Some(project_id), None).await?,
+            1
+        );
+        assert_eq!(
+            AlertEvents::count_resolved(
+                &connection,
+                None,
+                Some(Utc::now() - chrono::Duration::days(ALERTS_RESOLVED_RECENT_DAYS))
+            )
+            .await?,
+            1
+        );
+        assert_eq!(
+            AlertEvents::count_resolved(&connection, Some(project_id + 1), None).await?,
+            0
+        );
+
+        // Events recorded before the events followed the component (whole advisory)
+        let mut legacy =
+            Advisories::new("CVE-0003", AdvisorySource::Unknown, SecuritySeverity::Low);
+        legacy.save(&connection).await?;
+        AlertEvents::new(
+            project_id,
+            i32::from(legacy.id),
+            AlertEventKind::Detected,
+            "system",
+        )
+        .save(&connection)
+        .await?;
+        let snapshot = snapshot_with_alerts(
+            &connection,
+            &mut project,
+            &legacy,
+            &["pkg:deb/debian/zlib@1.3"],
+        )
+        .await?;
+        assert_eq!(
+            AlertEvents::reconcile(&connection, project_id, snapshot.id.into()).await?,
+            // CVE-0002 (openssl) is resolved
+            1
+        );
+        let snapshot = snapshot_with_alerts(&connection, &mut project, &legacy, &[]).await?;
+        assert_eq!(
+            AlertEvents::reconcile(&connection, project_id, snapshot.id.into()).await?,
+            1
+        );
+        let timeline =
+            AlertEvents::fetch_timeline(&connection, project_id, legacy.id.into()).await?;
+        assert_eq!(timeline.last().unwrap().event, AlertEventKind::Resolved);
+        assert_eq!(timeline.last().unwrap().component_id, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+//! # Project Health
+//!
+//! The health of a project (green / amber / red) is derived from the number of open
+//! alerts per severity using thresholds (`security.health.<severity>.<red|amber>`).
+//! The server settings are the defaults and each project can override them with the
+//! project settings of the same name (empty inherits the server setting).
+//!
+//! Parents (servers, groups, ...) are as unhealthy as their worst child.
+use geekorm::prelude::*;
+
+use super::SecuritySeverity;
+use crate::models::{
+    dependencies::snapshots::AlertsSummary, ProjectSetting, ProjectSettings, ServerSettings,
+    Setting,
+};
+
+/// Health of a Project
+#[derive(Data, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProjectHealth {
+    /// No alerts over the thresholds
+    #[default]
+    #[geekorm(key = "green", aliases = "Green")]
+    Green,
+    /// Alerts over an amber threshold
+    #[geekorm(key = "amber", aliases = "Amber")]
+    Amber,
+    /// Alerts over a red threshold (failing)
+    #[geekorm(key = "red", aliases = "Red")]
+    Red,
+}
+
+/// Health thresholds of a severity (number of alerts, `0` disables a threshold)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HealthThreshold {
+    /// Number of alerts from which the project is red
+    pub red: u32,
+    /// Number of alerts from which the project is amber
+    pub amber: u32,
+}
+
+/// Health thresholds of the severities
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HealthThresholds {
+    /// Critical alerts
+    pub critical: HealthThreshold,
+    /// High alerts
+    pub high: HealthThreshold,
+    /// Medium alerts
+    pub medium: HealthThreshold,
+    /// Low alerts
+    pub low: HealthThreshold,
+}
+
+/// Server and project setting of a threshold
+type HealthSetting = (Setting, ProjectSetting);
+
+/// Settings of the thresholds (severity, red and amber settings)
+const HEALTH_SETTINGS: [(SecuritySeverity, HealthSetting, HealthSetting); 4] = [
+    (
+        SecuritySeverity::Critical,
+        (
+            Setting::SecurityHealthCriticalRed,
+            ProjectSetting::SecurityHealthCriticalRed,
+        ),
+        (
+            Setting::SecurityHealthCriticalAmber,
+            ProjectSetting::SecurityHealthCriticalAmber,
+        ),
+    ),
+    (
+        SecuritySeverity::High,
+        (
+            Setting::SecurityHealthHighRed,
+            ProjectSetting::SecurityHealthHighRed,
+        ),
+        (
+            Setting::SecurityHealthHighAmber,
+            ProjectSetting::SecurityHealthHighAmber,
+        ),
+    ),
+    (
+        SecuritySeverity::Medium,
+        (
+            Setting::SecurityHealthMediumRed,
+            ProjectSetting::SecurityHealthMediumRed,
+        ),
+        (
+            Setting::SecurityHealthMediumAmber,
+            ProjectSetting::SecurityHealthMediumAmber,
+        ),
+    ),
+    (
+        SecuritySeverity::Low,
+        (
+            Setting::SecurityHealthLowRed,
+            ProjectSetting::SecurityHealthLowRed,
+        ),
+        (
+            Setting::SecurityHealthLowAmber,
+            ProjectSetting::SecurityHealthLowAmber,
+        ),
+    ),
+];
+
+impl HealthThreshold {
+    /// Health of a number of alerts
+    pub fn health(&self, count: u32) -> ProjectHealth {
+        if self.red > 0 && count >= self.red {
+            ProjectHealth::Red
+        } else if self.amber > 0 && count >= self.amber {
+            ProjectHealth::Amber
+        } else {
+            ProjectHealth::Green
+        }
+    }
+}
+
+impl HealthThresholds {
+    /// Load the thresholds from the server settings
+    pub async fn load<'a, T>(connection: &'a T) -> Result<Self, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let value = |setting: Setting| async move {
+            ServerSettings::fetch_by_name(connection, setting)
+                .await
+                .ok()
+                .and_then(|setting| setting.value.trim().parse::<u32>().ok())
+                .unwrap_or_default()
+        };
+        let mut thresholds = Self::default();
+        for (severity, (red, _), (amber, _)) in HEALTH_SETTINGS {
+            if let Some(threshold) = thresholds.threshold_mut(&severity) {
+                threshold.red = value(red).await;
+                threshold.amber = value(amber).await;
+            }
+        }
+        Ok(thresholds)
+    }
+
+    /// Load the thresholds of a project (the project settings override the server settings)
+    pub async fn load_project<'a, T>(
+        connection: &'a T,
+        project_id: i32,
+    ) -> Result<Self, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let settings = ProjectSettings::fetch_by_project_id(connection, project_id).await?;
+        Ok(Self::load(connection)
+            .await?
+            .with_overrides(settings.iter().map(|s| (&s.name, s.value.as_str()))))
+    }
+
+    /// Thresholds with the project overrides (empty or invalid values are inherited)
+    pub fn with_overrides<'s>(
+        mut self,
+        overrides: impl IntoIterator<Item = (&'s ProjectSetting, &'s str)>,
+    ) -> Self {
+        for (name, value) in overrides {
+            let Ok(value) = value.trim().parse::<u32>() else {
+                continue;
+            };
+            for (severity, (_, red), (_, amber)) in HEALTH_SETTINGS.iter() {
+                if let Some(threshold) = self.threshold_mut(severity) {
+                    if name == red {
+                        threshold.red = value;
+                    } else if name == amber {
+                        threshold.amber = value;
+                    }
+                }
+            }
+        }
+        self
+    }
+
+    fn threshold_mut(&mut self, severity: &SecuritySeverity) -> Option<&mut HealthThreshold> {
+        match severity {
+            SecuritySeverity::Critical => Some(&mut self.critical),
+            SecuritySeverity::High => Some(&mut self.high),
+            SecuritySeverity::Medium => Some(&mut self.medium),
+            SecuritySeverity::Low => Some(&mut self.low),
+            _ => None,
+        }
+    }
+
+    /// Health of an alerts summary and the health of the children (the worst of both)
+    pub fn health(&self, summary: &AlertsSummary, children: &[ProjectHealth]) -> ProjectHealth {
+        let thresholds = [
+            (SecuritySeverity::Critical, &self.critical),
+            (SecuritySeverity::High, &self.high),
+            (SecuritySeverity::Medium, &self.medium),
+            (SecuritySeverity::Low, &self.low),
+        ];
+        thresholds
+            .iter()
+            .map(|(severity, threshold)| {
+                threshold.health(summary.get(severity).copied().unwrap_or_default() as u32)
+            })
+            .chain(children.iter().copied())
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_thresholds() {
+        let thresholds = HealthThresholds {
+            critical: HealthThreshold { red: 1, amber: 0 },
+            high: HealthThreshold { red: 10, amber: 5 },
+            ..Default::default()
+        };
+        let summary = |critical: u16, high: u16, low: u16| -> AlertsSummary {
+            AlertsSummary::from([
+                (SecuritySeverity::Critical, critical),
+                (SecuritySeverity::High, high),
+                (SecuritySeverity::Low, low),
+            ])
+        };
+
+        assert_eq!(
+            thresholds.health(&AlertsSummary::new(), &[]),
+            ProjectHealth::Green
+        );
+        // Disabled thresholds never trigger
+        assert_eq!(
+            thresholds.health(&summary(0, 0, 100), &[]),
+            ProjectHealth::Green
+        );
+        // Boundaries (a threshold triggers from its value)
+        assert_eq!(
+            thresholds.health(&summary(0, 4, 0), &[]),
+            ProjectHealth::Green
+        );
+        assert_eq!(
+            thresholds.health(&summary(0, 5, 0), &[]),
+            ProjectHealth::Amber
+        );
+        assert_eq!(
+            thresholds.health(&summary(0, 9, 0), &[]),
+            ProjectHealth::Amber
+        );
+        assert_eq!(
+            thresholds.health(&summary(0, 10, 0), &[]),
+            ProjectHealth::Red
+        );
+        assert_eq!(
+            thresholds.health(&summary(1, 0, 0), &[]),
+            ProjectHealth::Red
+        );
+
+        // Parents are as unhealthy as their worst child
+        assert_eq!(
+            thresholds.health(
+                &AlertsSummary::new(),
+                &[ProjectHealth::Green, ProjectHealth::Amber]
+            ),
+            ProjectHealth::Amber
+        );
+        assert_eq!(
+            thresholds.health(&summary(0, 5, 0), &[ProjectHealth::Red]),
+            ProjectHealth::Red
+        );
+
+        assert_eq!(ProjectHealth::from("red"), ProjectHealth::Red);
+        assert_eq!(ProjectHealth::Amber.to_string(), "amber");
+        assert!("purple".parse::<ProjectHealth>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_health_overrides() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        // Defaults (a critical alert is red, 6 high alerts are amber)
+        let thresholds = HealthThresholds::load(&connection).await?;
+        assert_eq!(thresholds.critical, HealthThreshold { red: 1, amber: 0 });
+        assert_eq!(thresholds.high, HealthThreshold { red: 0, amber: 6 });
+
+        ServerSettings::fetch_by_name(&connection, Setting::SecurityHealthHighRed)
+            .await?
+            .set_update(&connection, "20")
+            .await?;
+        ProjectSettings::set(
+            &connection,
+            1,
+            ProjectSetting::SecurityHealthCriticalRed,
+            "3",
+        )
+        .await?;
+        ProjectSettings::set(&connection, 1, ProjectSetting::SecurityHealthHighRed, "").await?;
+
+        // The project overrides take precedence, empty values inherit the server settings
+        let project = HealthThresholds::load_project(&connection, 1).await?;
+        assert_eq!(project.critical, HealthThreshold { red: 3, amber: 0 });
+        assert_eq!(project.high, HealthThreshold { red: 20, amber: 6 });
+        let summary = AlertsSummary::from([(SecuritySeverity::Critical, 2)]);
+        assert_eq!(project.health(&summary, &[]), ProjectHealth::Green);
+
+        // Other projects use the server settings
+        let other = HealthThresholds::load_project(&connection, 2).await?;
+        assert_eq!(other.critical, HealthThreshold { red: 1, amber: 0 });
+        assert_eq!(other.health(&summary, &[]), ProjectHealth::Red);
+        Ok(())
+    }
+}
@@ -4,10 +4,24 @@ use geekorm::prelude::*;
 
 pub mod advisories;
 pub mod alerts;
+pub mod bulk;
+pub mod eol;
+pub mod events;
+pub mod health;
+pub mod policy;
+pub mod rules;
+pub mod vex;
 
 pub use crate::bom::sbom::BomVulnerabilitySeverity;
 pub use advisories::{Advisories, AdvisorySource};
-pub use alerts::{Alerts, SecurityState};
+pub use alerts::{AlertComponentSummary, AlertKind, Alerts, SecurityState, ALERTS_MATCHER_VERSION};
+pub use bulk::{AlertBulkAction, AlertBulkFilter, AlertBulkResult};
+pub use eol::{EolConfig, EolFinding};
+pub use events::{AlertEventKind, AlertEvents, AlertFeedEntry};
+pub use health::{HealthThresholds, ProjectHealth};
+pub use policy::{PolicyConfig, PolicyFinding, PolicyRule};
+pub use rules::AlertIgnoreRules;
+pub use vex::{VexStatements, VexStatus};
 
 /// List of Security Criticality
 pub const SECURITY_SEVERITY: [&'static str; 8] = [
@@ -51,6 +65,67 @@ pub enum SecuritySeverity {
     Unknown,
 }
 
+impl SecuritySeverity {
+    /// Rank of the severity (higher is more severe)
+    ///
+    /// Critical > High > Medium > Low > Informational > Malware > Unmantained > Unknown
+    pub fn rank(&self) -> u8 {
+        match self {
+            SecuritySeverity::Critical => 7,
+            SecuritySeverity::High => 6,
+            SecuritySeverity::Medium => 5,
+            SecuritySeverity::Low => 4,
+            SecuritySeverity::Informational => 3,
+            SecuritySeverity::Malware => 2,
+            SecuritySeverity::Unmantained => 1,
+            SecuritySeverity::Unknown => 0,
+        }
+    }
+
+    /// Snapshot metadata key of the alerts total for the severity
+    pub fn metadata_key(&self) -> crate::models::SnapshotMetadataKey {
+        use crate::models::SnapshotMetadataKey;
+        match self {
+            SecuritySeverity::Critical => SnapshotMetadataKey::SecurityAlertCritical,
+            SecuritySeverity::High => SnapshotMetadataKey::SecurityAlertHigh,
+            SecuritySeverity::Medium => SnapshotMetadataKey::SecurityAlertMedium,
+            SecuritySeverity::Low => SnapshotMetadataKey::SecurityAlertLow,
+            SecuritySeverity::Informational => SnapshotMetadataKey::SecurityAlertInformational,
+            SecuritySeverity::Unmantained => SnapshotMetadataKey::SecurityAlertUnmaintained,
+            SecuritySeverity::Malware => SnapshotMetadataKey::SecurityAlertMalware,
+            SecuritySeverity::Unknown => SnapshotMetadataKey::SecurityAlertUnknown,
+        }
+    }
+
+    /// SQL `CASE` expression ranking a severity column (used for `ORDER BY`)
+    pub(crate) fn rank_sql(column: &str) -> String {
+        let cases = SECURITY_SEVERITY
+            .iter()
+            .map(|severity| {
+                format!(
+                    "WHEN '{}' THEN {}",
+                    severity,
+                    SecuritySeverity::from(*severity).rank()
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        format!("CASE {} {} ELSE 0 END", column, cases)
+    }
+}
+
+impl Ord for SecuritySeverity {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
+impl PartialOrd for SecuritySeverity {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl From<&BomVulnerabilitySeverity> for SecuritySeverity {
     fn from(value: &BomVulnerabilitySeverity) -> Self {
         match value {
@@ -81,4 +156,36 @@ mod tests {
             assert_eq!(SecuritySeverity::from(crit), SecuritySeverity::Critical);
         }
     }
+
+    #[test]
+    fn test_ordering() {
+        assert!(SecuritySeverity::Critical > SecuritySeverity::High);
+        assert!(SecuritySeverity::High > SecuritySeverity::Medium);
+        assert!(SecuritySeverity::Medium > SecuritySeverity::Low);
+        assert!(SecuritySeverity::Low > SecuritySeverity::Informational);
+        for other in [
+            SecuritySeverity::Unmantained,
+            SecuritySeverity::Malware,
+            SecuritySeverity::Unknown,
+        ] {
+            assert!(SecuritySeverity::Informational > other);
+        }
+
+        let mut severities: Vec<SecuritySeverity> = SECURITY_SEVERITY
+            .iter()
+            .map(|s| SecuritySeverity::from(*s))
+            .collect();
+        severities.reverse();
+        severities.sort_by(|a, b| b.cmp(a));
+        assert_eq!(severities[0], SecuritySeverity::Critical);
+        assert_eq!(severities[4], SecuritySeverity::Informational);
+        assert_eq!(severities[7], SecuritySeverity::Unknown);
+    }
+
+    #[test]
+    fn test_rank_sql() {
+        let sql = SecuritySeverity::rank_sql("severity");
+        assert!(sql.starts_with("CASE severity WHEN 'Critical' THEN 7"));
+        assert!(sql.ends_with("ELSE 0 END"));
+    }
 }
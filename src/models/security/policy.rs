@@ -0,0 +1,399 @@
+//! # Image Policy
+//!
+//! Server side policy for the container images of the snapshots (`policy.*` settings).
+//! Violations are stored as alerts for the `Policy` advisories (named `policy.<rule>`)
+//! and counted in the `security.policy.total` snapshot metadata.
+
+use geekorm::prelude::*;
+use log::debug;
+
+use super::{Advisories, AdvisorySource, Alerts, SecuritySeverity, SecurityState};
+use crate::{
+    bom::processors::{glob_match, split_list},
+    models::{settings::keys::Setting, ServerSettings, Snapshot},
+    KonarrError,
+};
+
+/// Prefix of the policy advisories / alerts
+pub const POLICY_PREFIX: &str = "policy.";
+
+/// Registry used for images without a registry
+pub const DEFAULT_REGISTRY: &str = "docker.io";
+
+/// Policy Rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyRule {
+    /// Images must not use the `latest` tag (or no tag)
+    DenyLatestTag,
+    /// Images must come from one of the allowed registries
+    AllowedRegistries,
+}
+
+impl PolicyRule {
+    /// Name of the advisory / alert for the rule
+    pub fn name(&self) -> &'static str {
+        match self {
+            PolicyRule::DenyLatestTag => "policy.deny_latest_tag",
+            PolicyRule::AllowedRegistries => "policy.allowed_registries",
+        }
+    }
+
+    /// Severity of a violation
+    pub fn severity(&self) -> SecuritySeverity {
+        match self {
+            PolicyRule::DenyLatestTag => SecuritySeverity::Low,
+            PolicyRule::AllowedRegistries => SecuritySeverity::Medium,
+        }
+    }
+
+    /// Description of the rule (stored on the advisory)
+    pub fn description(&self) -> &'static str {
+        match self {
+            PolicyRule::DenyLatestTag => {
+                "The container image uses the `latest` tag (or no tag) so the scanned image can change without notice"
+            }
+            PolicyRule::AllowedRegistries => {
+                "The container image is not from one of the allowed registries"
+            }
+        }
+    }
+}
+
+/// Policy finding for an image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyFinding {
+    /// Rule which was violated
+    pub rule: PolicyRule,
+    /// Message describing the violation
+    pub message: String,
+}
+
+/// Image policy configuration
+#[derive(Debug, Clone, Default)]
+pub struct PolicyConfig {
+    /// Deny the `latest` tag (`policy.deny_latest_tag`)
+    pub deny_latest_tag: bool,
+    /// Allowed registries, globs are supported (`policy.allowed_registries`, empty allows all)
+    pub allowed_registries: Vec<String>,
+}
+
+/// Container image reference (`[registry/]repository[:tag][@digest]`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageReference {
+    /// Registry (if the image has one)
+    pub registry: Option<String>,
+    /// Repository
+    pub repository: String,
+    /// Tag
+    pub tag: Option<String>,
+    /// Digest
+    pub digest: Option<String>,
+}
+
+impl ImageReference {
+    /// Parse an image reference (returns `None` for an empty reference)
+    pub fn parse(image: &str) -> Option<Self> {
+        let image = image.trim();
+        let (image, digest) = match image.split_once('@') {
+            Some((image, digest)) => (image, Some(digest.to_string())),
+            None => (image, None),
+        };
+        if image.is_empty() {
+            return None;
+        }
+
+        // The first segment is a registry if it looks like a host
+        let (registry, path) = match image.split_once('/') {
+            Some((host, path))
+                if host.contains('.') || host.contains(':') || host == "localhost" =>
+            {
+                (Some(host.to_lowercase()), path)
+            }
+            _ => (None, image),
+        };
+
+        // The tag is after the last `:` of the last segment
+        let (repository, tag) = match path.rsplit_once(':') {
+            Some((repository, tag)) if !tag.contains('/') => {
+                (repository.to_string(), Some(tag.to_string()))
+            }
+            _ => (path.to_string(), None),
+        };
+
+        Some(Self {
+            registry,
+            repository,
+            tag,
+            digest,
+        })
+    }
+
+    /// Registry of the image (Docker Hub if the image has no registry)
+    pub fn registry(&self) -> &str {
+        match self.registry.as_deref() {
+            Some("index.docker.io") | Some("registry-1.docker.io") | None => DEFAULT_REGISTRY,
+            Some(registry) => registry,
+        }
+    }
+
+    /// If the image resolves to the `latest` tag (no tag and no digest defaults to `latest`)
+    pub fn is_latest(&self) -> bool {
+        match &self.tag {
+            Some(tag) => tag == "latest",
+            None => self.digest.is_none(),
+        }
+    }
+}
+
+impl PolicyConfig {
+    /// Load the policy from the server settings
+    pub async fn load<'a, T>(connection: &'a T) -> Result<Self, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let allowed = ServerSettings::fetch_by_name(connection, Setting::PolicyAllowedRegistries)
+            .await
+            .map(|s| s.value)
+            .unwrap_or_default();
+        Ok(Self {
+            deny_latest_tag: ServerSettings::get_bool(connection, Setting::PolicyDenyLatestTag)
+                .await
+                .unwrap_or(false),
+            allowed_registries: split_list(&allowed.to_lowercase()),
+        })
+    }
+
+    /// If any rule is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.deny_latest_tag || !self.allowed_registries.is_empty()
+    }
+
+    /// Evaluate the rules against an image
+    pub fn evaluate(&self, image: &ImageReference) -> Vec<PolicyFinding> {
+        let mut findings = Vec::new();
+
+        if self.deny_latest_tag && image.is_latest() {
+            findings.push(PolicyFinding {
+                rule: PolicyRule::DenyLatestTag,
+                message: format!(
+                    "Image `{}` uses the `{}` tag",
+                    image.repository,
+                    image.tag.as_deref().unwrap_or("latest")
+                ),
+            });
+        }
+
+        if !self.allowed_registries.is_empty() {
+            let registry = image.registry();
+            if !self
+                .allowed_registries
+                .iter()
+                .any(|allowed| glob_match(allowed, registry))
+            {
+                findings.push(PolicyFinding {
+                    rule: PolicyRule::AllowedRegistries,
+                    message: format!("Image registry `{}` is not allowed", registry),
+                });
+            }
+        }
+        findings
+    }
+
+    /// Evaluate the policy for the container image of a snapshot and store the findings
+    ///
+    /// Findings are stored as alerts (re-opened if they were resolved) and the policy
+    /// alerts which no longer apply are resolved. Snapshots without an image have no findings.
+    pub async fn apply<'a, T>(
+        &self,
+        connection: &'a T,
+        snapshot: &mut Snapshot,
+    ) -> Result<Vec<PolicyFinding>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        snapshot.fetch_metadata(connection).await?;
+        let findings = snapshot
+            .find_metadata("container.image")
+            .and_then(|image| ImageReference::parse(&image.as_string()))
+            .map(|image| self.evaluate(&image))
+            .unwrap_or_default();
+        debug!(
+            "Snapshot({}) :: {} policy findings",
+            snapshot.id,
+            findings.len()
+        );
+
+        let existing: Vec<Alerts> = Alerts::fetch_by_snapshot_id(connection, snapshot.id)
+            .await?
+            .into_iter()
+            .filter(|alert| alert.is_policy())
+            .collect();
+
+        for finding in findings.iter() {
+            let advisory = policy_advisory(connection, finding.rule).await?;
+            let mut alert = Alerts::new(finding.rule.name(), snapshot.id, advisory.id);
+            alert.find_or_create(connection).await?;
+
+            if let Some(mut current) = existing.iter().find(|a| a.id == alert.id).cloned() {
+                if current.state == SecurityState::Secure {
+                    current.state = SecurityState::Vulnerable;
+                    current.updated_at = chrono::Utc::now();
+                    current.update(connection).await?;
+                }
+            }
+        }
+
+        for alert in existing.iter() {
+            if alert.state != SecurityState::Secure
+                && !findings.iter().any(|f| f.rule.name() == alert.name)
+            {
+                alert.clone().close(connection).await?;
+            }
+        }
+
+        Ok(findings)
+    }
+}
+
+impl Alerts {
+    /// If the alert is a policy finding (not a vulnerability)
+    pub fn is_policy(&self) -> bool {
+        self.name.starts_with(POLICY_PREFIX)
+    }
+}
+
+/// Find or create the advisory for a policy rule
+async fn policy_advisory<'a, T>(
+    connection: &'a T,
+    rule: PolicyRule,
+) -> Result<Advisories, KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    if let Ok(advisory) = Advisories::fetch_by_name(connection, rule.name()).await {
+        return Ok(advisory);
+    }
+    let mut advisory = Advisories::new(rule.name(), AdvisorySource::Policy, rule.severity());
+    advisory.save(connection).await?;
+    advisory
+        .add_metadata(connection, "description", rule.description())
+        .await?;
+    Ok(advisory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ProjectType, Projects};
+
+    #[test]
+    fn test_image_reference() {
+        let image = ImageReference::parse("ghcr.io/42bytelabs/konarr:0.3.1").unwrap();
+        assert_eq!(image.registry(), "ghcr.io");
+        assert_eq!(image.repository, "42bytelabs/konarr");
+        assert_eq!(image.tag.as_deref(), Some("0.3.1"));
+        assert!(!image.is_latest());
+
+        // No registry or tag (Docker Hub, latest)
+        let image = ImageReference::parse("nginx").unwrap();
+        assert_eq!(image.registry, None);
+        assert_eq!(image.registry(), DEFAULT_REGISTRY);
+        assert_eq!(image.tag, None);
+        assert!(image.is_latest());
+
+        // Registry with a port and a digest
+        let image = ImageReference::parse("localhost:5000/app@sha256:abcd").unwrap();
+        assert_eq!(image.registry(), "localhost:5000");
+        assert_eq!(image.repository, "app");
+        assert_eq!(image.tag, None);
+        assert_eq!(image.digest.as_deref(), Some("sha256:abcd"));
+        assert!(!image.is_latest());
+
+        let image = ImageReference::parse("library/redis:latest").unwrap();
+        assert_eq!(image.registry(), DEFAULT_REGISTRY);
+        assert!(image.is_latest());
+
+        assert_eq!(ImageReference::parse(""), None);
+    }
+
+    #[test]
+    fn test_policy_evaluate() {
+        let policy = PolicyConfig::default();
+        assert!(!policy.is_enabled());
+        assert!(policy
+            .evaluate(&ImageReference::parse("nginx").unwrap())
+            .is_empty());
+
+        let policy = PolicyConfig {
+            deny_latest_tag: true,
+            allowed_registries: split_list("ghcr.io, *.example.com"),
+        };
+        let rules = |image: &str| -> Vec<PolicyRule> {
+            policy
+                .evaluate(&ImageReference::parse(image).unwrap())
+                .into_iter()
+                .map(|f| f.rule)
+                .collect()
+        };
+        assert_eq!(rules("ghcr.io/org/app:1.0"), vec![]);
+        assert_eq!(
+            rules("registry.example.com/app:latest"),
+            vec![PolicyRule::DenyLatestTag]
+        );
+        assert_eq!(
+            rules("nginx"),
+            vec![PolicyRule::DenyLatestTag, PolicyRule::AllowedRegistries]
+        );
+        assert_eq!(
+            rules("quay.io/org/app@sha256:abcd"),
+            vec![PolicyRule::AllowedRegistries]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_policy_apply() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("nginx", ProjectType::Container);
+        project.save(&connection).await?;
+        let mut snapshot = Snapshot::create(&connection).await?;
+        project.add_snapshot(&connection, snapshot.clone()).await?;
+        snapshot
+            .set_metadata(&connection, "container.image", "nginx:latest")
+            .await?;
+
+        let policy = PolicyConfig {
+            deny_latest_tag: true,
+            allowed_registries: vec![],
+        };
+        assert_eq!(policy.apply(&connection, &mut snapshot).await?.len(), 1);
+        // Applying again does not duplicate the alert
+        policy.apply(&connection, &mut snapshot).await?;
+        let alerts = Alerts::fetch_by_snapshot_id(&connection, snapshot.id).await?;
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].is_policy());
+
+        // Policy findings are not counted as vulnerabilities
+        let summary = snapshot.calculate_alerts_summary(&connection).await?;
+        assert!(summary.is_empty());
+        snapshot.fetch_metadata(&connection).await?;
+        assert_eq!(snapshot.find_metadata_usize("security.policy.total"), 1);
+        assert_eq!(Projects::count_policy_violations(&connection).await?, 1);
+        let projects = Projects::fetch_policy_violations(&connection, 10, 0).await?;
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].name, "nginx");
+
+        // Rule disabled, the finding is resolved
+        PolicyConfig::default()
+            .apply(&connection, &mut snapshot)
+            .await?;
+        let alerts = Alerts::fetch_by_snapshot_id(&connection, snapshot.id).await?;
+        assert_eq!(alerts[0].state, SecurityState::Secure);
+        snapshot.calculate_alerts_summary(&connection).await?;
+        snapshot.fetch_metadata(&connection).await?;
+        assert_eq!(snapshot.find_metadata_usize("security.policy.total"), 0);
+        assert_eq!(Projects::count_policy_violations(&connection).await?, 0);
+
+        Ok(())
+    }
+}
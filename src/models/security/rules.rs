@@ -0,0 +1,285 @@
+//! # Alert ignore rules
+//!
+//! Forward-looking rules to ignore advisories for a component (in a project or globally).
+//! Alerts matching an active rule are created in the [SecurityState::Ignored] state and
+//! are not counted in the alert summaries. Expired rules stop applying automatically.
+
+use chrono::{DateTime, Utc};
+use geekorm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{alerts::SecurityState, Alerts};
+use crate::{
+    bom::processors::glob_match,
+    models::{raw_query, ProjectSnapshots},
+    KonarrError,
+};
+
+#[derive(Debug, Deserialize)]
+struct RuleCountRow {
+    rule: i32,
+    alerts: i64,
+}
+
+/// Alert ignore rules table
+#[derive(Table, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertIgnoreRules {
+    /// Primary key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+
+    /// Project the rule applies to (global rule if not set)
+    pub project_id: Option<i32>,
+
+    /// Package URL glob (`pkg:deb/debian/perl`, `pkg:npm/@acme/*`)
+    ///
+    /// The glob is matched against the Package URL with and without the version.
+    pub purl: String,
+    /// Advisory glob (`CVE-2023-*`, `*` for every advisory)
+    pub advisory: String,
+
+    /// Reason for ignoring the alerts
+    pub reason: String,
+    /// User who created the rule
+    pub created_by: String,
+
+    /// Expiry of the rule (never expires if not set)
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// Creation date
+    #[geekorm(new = "Utc::now()")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl AlertIgnoreRules {
+    /// Initialise the Alert Ignore Rules table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::create_table(connection).await?;
+        Ok(())
+    }
+
+    /// Check if the rule has expired
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires| expires <= Utc::now())
+    }
+
+    /// Check if the rule matches an advisory for a component in one of the projects
+    pub fn matches(
+        &self,
+        projects: &[i32],
+        purl: &str,
+        version: Option<&str>,
+        advisory: &str,
+    ) -> bool {
+        if self.is_expired() {
+            return false;
+        }
+        if let Some(project) = self.project_id {
+            if !projects.contains(&project) {
+                return false;
+            }
+        }
+        let purl_match = glob_match(&self.purl, purl)
+            || version.is_some_and(|v| glob_match(&self.purl, &format!("{}@{}", purl, v)));
+        purl_match && glob_match(&self.advisory, advisory)
+    }
+
+    /// Fetch the rules which have not expired
+    pub async fn fetch_active<'a, T>(connection: &'a T) -> Result<Vec<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Self::fetch_all(connection)
+            .await?
+            .into_iter()
+            .filter(|rule| !rule.is_expired())
+            .collect())
+    }
+
+    /// Find the first active rule matching an advisory for a component in a snapshot
+    pub async fn find_match<'a, T>(
+        connection: &'a T,
+        snapshot_id: i32,
+        purl: &str,
+        version: Option<&str>,
+        advisory: &str,
+    ) -> Result<Option<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let rules = Self::fetch_active(connection).await?;
+        if rules.is_empty() {
+            return Ok(None);
+        }
+        let projects: Vec<i32> = if rules.iter().any(|r| r.project_id.is_some()) {
+            ProjectSnapshots::query(
+                connection,
+                ProjectSnapshots::query_select()
+                    .where_eq("snapshot_id", snapshot_id)
+                    .build()?,
+            )
+            .await?
+            .into_iter()
+            .map(|ps| ps.project_id.key)
+            .collect()
+        } else {
+            vec![]
+        };
+
+        Ok(rules
+            .into_iter()
+            .find(|rule| rule.matches(&projects, purl, version, advisory)))
+    }
+
+    /// Number of alerts suppressed by each rule (keyed by rule ID)
+    pub async fn suppressed<'a, T>(
+        connection: &'a T,
+    ) -> Result<std::collections::HashMap<i32, i64>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("state".to_string(), SecurityState::Ignored);
+        let rows = T::query::<RuleCountRow>(
+            connection,
+            raw_query(
+                "SELECT ignore_rule_id AS rule, COUNT(id) AS alerts FROM Alerts \
+                WHERE state = ? AND ignore_rule_id IS NOT NULL \
+                GROUP BY ignore_rule_id;",
+                values,
+            ),
+        )
+        .await?;
+        Ok(rows.into_iter().map(|row| (row.rule, row.alerts)).collect())
+    }
+
+    /// Remove the rule, the alerts it suppressed become vulnerable again
+    pub async fn remove<'a, T>(&self, connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::release(connection, &[self.id.into()]).await?;
+        self.delete(connection).await?;
+        Ok(())
+    }
+
+    /// Release the alerts suppressed by expired rules
+    ///
+    /// Returns the number of alerts which became vulnerable again.
+    pub async fn release_expired<'a, T>(connection: &'a T) -> Result<u64, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let expired: Vec<i32> = Self::fetch_all(connection)
+            .await?
+            .into_iter()
+            .filter(|rule| rule.is_expired())
+            .map(|rule| rule.id.into())
+            .collect();
+        Self::release(connection, &expired).await
+    }
+
+    async fn release<'a, T>(connection: &'a T, rules: &[i32]) -> Result<u64, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut released = 0;
+        for rule in rules {
+            let mut alerts = Alerts::query(
+                connection,
+                Alerts::query_select()
+                    .where_eq("ignore_rule_id", *rule)
+                    .and()
+                    .where_eq("state", SecurityState::Ignored)
+                    .build()?,
+            )
+            .await?;
+            for alert in alerts.iter_mut() {
+                log::debug!("Alert({}) no longer ignored by rule `{}`", alert.id, rule);
+                alert.state = SecurityState::Vulnerable;
+                alert.ignore_rule_id = None;
+                alert.update(connection).await?;
+                released += 1;
+            }
+        }
+        Ok(released)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        security::{Advisories, AdvisorySource, SecuritySeverity},
+        Dependencies, ProjectType, Projects, Snapshot,
+    };
+
+    #[test]
+    fn test_rule_matches() {
+        let mut rule = AlertIgnoreRules::new("pkg:deb/debian/perl", "*", "test-only", "admin");
+        assert!(rule.matches(&[], "pkg:deb/debian/perl", Some("5.36"), "CVE-0001"));
+        assert!(!rule.matches(&[], "pkg:deb/debian/openssl", None, "CVE-0001"));
+
+        rule.purl = "pkg:deb/debian/perl@5.36*".to_string();
+        rule.advisory = "CVE-2023-*".to_string();
+        assert!(rule.matches(&[], "pkg:deb/debian/perl", Some("5.36.0"), "CVE-2023-1"));
+        assert!(!rule.matches(&[], "pkg:deb/debian/perl", Some("5.38.0"), "CVE-2023-1"));
+        assert!(!rule.matches(&[], "pkg:deb/debian/perl", Some("5.36.0"), "CVE-2024-1"));
+
+        rule.project_id = Some(2);
+        assert!(!rule.matches(&[1], "pkg:deb/debian/perl", Some("5.36.0"), "CVE-2023-1"));
+        assert!(rule.matches(&[1, 2], "pkg:deb/debian/perl", Some("5.36.0"), "CVE-2023-1"));
+
+        rule.expires_at = Some(Utc::now() - chrono::Duration::hours(1));
+        assert!(!rule.matches(&[2], "pkg:deb/debian/perl", Some("5.36.0"), "CVE-2023-1"));
+    }
+
+    #[tokio::test]
+    async fn test_ignore_rules() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("project", ProjectType::Container);
+        project.save(&connection).await?;
+        let snapshot = Snapshot::create(&connection).await?;
+        project.add_snapshot(&connection, snapshot.clone()).await?;
+
+        let mut dependency =
+            Dependencies::from_purl(&connection, "pkg:deb/debian/perl@5.36.0".to_string()).await?;
+        dependency.snapshot_id = snapshot.id.into();
+        dependency.save(&connection).await?;
+
+        let mut rule = AlertIgnoreRules::new("pkg:deb/debian/perl", "*", "test-only", "admin");
+        rule.project_id = Some(project.id.into());
+        rule.save(&connection).await?;
+
+        let mut advisory =
+            Advisories::new("CVE-0001", AdvisorySource::Unknown, SecuritySeverity::High);
+        advisory.save(&connection).await?;
+        let mut alert = Alerts {
+            dependency_id: Some(dependency.id.into()),
+            ..Alerts::new("CVE-0001", snapshot.id, advisory.id)
+        };
+        alert.find_or_create(&connection).await?;
+        alert
+            .apply_ignore_rules(&connection, "pkg:deb/debian/perl", Some("5.36.0"))
+            .await?;
+        assert_eq!(alert.state, SecurityState::Ignored);
+        assert_eq!(alert.ignore_rule_id, Some(rule.id.into()));
+
+        let suppressed = AlertIgnoreRules::suppressed(&connection).await?;
+        assert_eq!(suppressed.get(&rule.id.into()), Some(&1));
+
+        // Expired rules no longer apply
+        rule.expires_at = Some(Utc::now() - chrono::Duration::minutes(1));
+        rule.update(&connection).await?;
+        assert_eq!(AlertIgnoreRules::release_expired(&connection).await?, 1);
+
+        let alert = Alerts::fetch_by_primary_key(&connection, alert.id).await?;
+        assert_eq!(alert.state, SecurityState::Vulnerable);
+        assert_eq!(alert.ignore_rule_id, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,371 @@
+//! # VEX statements
+//!
+//! Statements of the VEX documents uploaded for a snapshot (see [crate::bom::vex]).
+//! Alerts of the snapshot, and of the later snapshots of the same project, for an
+//! advisory the products are `not_affected` by are suppressed with the
+//! [SecurityState::NotAffected] state (not counted in the alert summaries).
+//!
+//! Unlike an acknowledgement, the suppression follows the statements: the latest
+//! statement for an advisory and product wins and alerts become vulnerable again when
+//! no `not_affected` statement matches them.
+
+use chrono::{DateTime, Utc};
+use geekorm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{alerts::SecurityState, AlertKind, Alerts};
+use crate::{
+    bom::vex::{BomVexStatus, VexDocument},
+    models::{raw_query, ProjectSnapshots, Snapshot},
+    KonarrError,
+};
+
+/// Status of an advisory for the products of a statement
+#[derive(Data, Debug, Clone, Default, PartialEq)]
+pub enum VexStatus {
+    /// The products are affected
+    #[geekorm(key = "affected", aliases = "Affected")]
+    Affected,
+    /// The products are not affected (the alerts are suppressed)
+    #[geekorm(key = "not_affected", aliases = "NotAffected")]
+    NotAffected,
+    /// The products are fixed
+    #[geekorm(key = "fixed", aliases = "Fixed")]
+    Fixed,
+    /// Not known yet
+    #[default]
+    #[geekorm(key = "under_investigation", aliases = "UnderInvestigation")]
+    UnderInvestigation,
+}
+
+impl From<BomVexStatus> for VexStatus {
+    fn from(value: BomVexStatus) -> Self {
+        match value {
+            BomVexStatus::Affected => VexStatus::Affected,
+            BomVexStatus::NotAffected => VexStatus::NotAffected,
+            BomVexStatus::Fixed => VexStatus::Fixed,
+            BomVexStatus::UnderInvestigation => VexStatus::UnderInvestigation,
+        }
+    }
+}
+
+/// VEX statements table
+#[derive(Table, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VexStatements {
+    /// Primary key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+
+    /// Snapshot the document was uploaded for
+    #[geekorm(foreign_key = "Snapshot.id")]
+    pub snapshot_id: ForeignKey<i32, Snapshot>,
+    /// Project of the snapshot (the statements apply to its later snapshots)
+    pub project_id: Option<i32>,
+
+    /// Document ID (`@id` / `serialNumber`)
+    pub document: String,
+    /// Format of the document (`openvex` or `cyclonedx`)
+    pub format: String,
+
+    /// Advisory (CVE, GHSA, etc.)
+    pub advisory: String,
+    /// Status of the advisory
+    pub status: VexStatus,
+    /// Justification
+    pub justification: Option<String>,
+    /// Products (JSON list of Package URLs, empty for every component of the snapshot)
+    pub products: String,
+
+    /// Creation date
+    #[geekorm(new = "Utc::now()")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Remove the qualifiers and subpath of a Package URL
+fn purl_base(purl: &str) -> String {
+    purl.split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .replace("%40", "@")
+        .to_lowercase()
+}
+
+impl VexStatements {
+    /// Initialise the VEX Statements table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::create_table(connection).await?;
+        Ok(())
+    }
+
+    /// Products of the statement
+    pub fn products(&self) -> Vec<String> {
+        serde_json::from_str(&self.products).unwrap_or_default()
+    }
+
+    /// Check if the statement matches an advisory for a component (Package URL and version)
+    ///
+    /// Statements without products or for a container image (`pkg:oci`, `pkg:docker`)
+    /// match every component of the snapshot.
+    pub fn matches(&self, advisory: &str, purl: Option<&str>, version: Option<&str>) -> bool {
+        if !self.advisory.eq_ignore_ascii_case(advisory) {
+            return false;
+        }
+        let products = self.products();
+        if products.is_empty() {
+            return true;
+        }
+        products.iter().map(|p| purl_base(p)).any(|product| {
+            if product.starts_with("pkg:oci/") || product.starts_with("pkg:docker/") {
+                return true;
+            }
+            let Some(purl) = purl.map(purl_base) else {
+                return false;
+            };
+            product == purl
+                || version.is_some_and(|v| product == format!("{}@{}", purl, v.to_lowercase()))
+        })
+    }
+
+    /// Store the statements of a document for a snapshot
+    ///
+    /// The statements of a document uploaded again for the snapshot are replaced.
+    pub async fn store<'a, T>(
+        connection: &'a T,
+        snapshot: &Snapshot,
+        document: &VexDocument,
+    ) -> Result<Vec<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("snapshot_id".to_string(), snapshot.id);
+        values.push("document".to_string(), document.id.clone());
+        T::execute::<Self>(
+            connection,
+            raw_query(
+                "DELETE FROM VexStatements WHERE snapshot_id = ? AND document = ?;",
+                values,
+            ),
+        )
+        .await?;
+
+        let project_id = ProjectSnapshots::fetch_by_snapshot_id(connection, snapshot.id)
+            .await?
+            .iter()
+            .map(|link| link.project_id.key)
+            .min();
+
+        let mut statements = Vec::with_capacity(document.statements.len());
+        for statement in document.statements.iter() {
+            let mut row = Self::new(
+                snapshot.id,
+                document.id.clone(),
+                document.format.to_string(),
+                statement.advisory.clone(),
+                VexStatus::from(statement.status),
+                serde_json::to_string(&statement.products)?,
+            );
+            row.project_id = project_id;
+            row.justification = statement.justification.clone();
+            row.save(connection).await?;
+            statements.push(row);
+        }
+        Ok(statements)
+    }
+
+    /// Statements of a snapshot and of its projects (latest first)
+    pub async fn fetch_for_snapshot<'a, T>(
+        connection: &'a T,
+        snapshot_id: i32,
+    ) -> Result<Vec<Self>, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut values = Values::new();
+        values.push("snapshot_id".to_string(), snapshot_id);
+        values.push("project_snapshot_id".to_string(), snapshot_id);
+        T::query::<Self>(
+            connection,
+            raw_query(
+                "SELECT * FROM VexStatements WHERE snapshot_id = ? OR project_id IN \
+                (SELECT project_id FROM ProjectSnapshots WHERE snapshot_id = ?) \
+                ORDER BY id DESC;",
+                values,
+            ),
+        )
+        .await
+        .map_err(KonarrError::from)
+    }
+
+    /// Apply the statements to the vulnerability alerts of a snapshot
+    ///
+    /// Acknowledged and ignored alerts are left as-is. Returns the number of alerts
+    /// suppressed by a statement.
+    pub async fn apply<'a, T>(connection: &'a T, snapshot: &Snapshot) -> Result<u64, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let statements = Self::fetch_for_snapshot(connection, snapshot.id.into()).await?;
+        let mut suppressed = 0;
+
+        let mut alerts = Alerts::fetch_by_snapshot_id(connection, snapshot.id).await?;
+        for alert in alerts.iter_mut() {
+            if alert.kind() != AlertKind::Vulnerability
+                || !matches!(
+                    alert.state,
+                    SecurityState::Vulnerable | SecurityState::NotAffected
+                )
+            {
+                continue;
+            }
+            if statements.is_empty() && alert.state != SecurityState::NotAffected {
+                continue;
+            }
+
+            let (purl, version) = match alert.fetch_dependency(connection).await? {
+                Some(dependency) => (
+                    Some(dependency.component_id.data.purl()),
+                    dependency.version(),
+                ),
+                None => (None, None),
+            };
+            // The latest statement for the advisory and component wins
+            let statement = statements
+                .iter()
+                .find(|s| s.matches(&alert.name, purl.as_deref(), version.as_deref()));
+
+            let (state, statement_id) = match statement {
+                Some(statement) if statement.status == VexStatus::NotAffected => {
+                    suppressed += 1;
+                    (SecurityState::NotAffected, Some(statement.id.into()))
+                }
+                _ => (SecurityState::Vulnerable, None),
+            };
+            if state != alert.state || statement_id != alert.vex_statement_id {
+                log::debug!(
+                    "Alert({}) :: {:?} (VEX statement: {:?})",
+                    alert.id,
+                    state,
+                    statement_id
+                );
+                alert.state = state;
+                alert.vex_statement_id = statement_id;
+                alert.updated_at = Utc::now();
+                alert.update(connection).await?;
+            }
+        }
+        Ok(suppressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bom::vex::{VexFormat, VexStatement},
+        models::{
+            security::{Advisories, AdvisorySource, SecuritySeverity},
+            Dependencies, ProjectType, Projects,
+        },
+    };
+
+    #[test]
+    fn test_statement_matches() {
+        let mut statement = VexStatements::new(
+            1,
+            "doc",
+            "openvex",
+            "CVE-2023-5678",
+            VexStatus::NotAffected,
+            r#"["pkg:deb/debian/openssl@3.0.11-1?arch=amd64"]"#,
+        );
+        let purl = Some("pkg:deb/debian/openssl");
+        assert!(statement.matches("cve-2023-5678", purl, Some("3.0.11-1")));
+        assert!(!statement.matches("CVE-2023-5678", purl, Some("3.0.13-1")));
+        assert!(!statement.matches("CVE-2023-0001", purl, Some("3.0.11-1")));
+        assert!(!statement.matches("CVE-2023-5678", None, None));
+
+        statement.products = r#"["pkg:deb/debian/openssl"]"#.to_string();
+        assert!(statement.matches("CVE-2023-5678", purl, Some("3.0.13-1")));
+
+        // Container images and statements without products match every component
+        statement.products = r#"["pkg:oci/nginx@sha256%3Aabc"]"#.to_string();
+        assert!(statement.matches("CVE-2023-5678", None, None));
+        statement.products = "[]".to_string();
+        assert!(statement.matches("CVE-2023-5678", purl, None));
+    }
+
+    #[tokio::test]
+    async fn test_vex_suppress() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("project", ProjectType::Container);
+        project.save(&connection).await?;
+        let snapshot = Snapshot::create(&connection).await?;
+        project.add_snapshot(&connection, snapshot.clone()).await?;
+
+        let mut dependency =
+            Dependencies::from_purl(&connection, "pkg:deb/debian/openssl@3.0.11-1".to_string())
+                .await?;
+        dependency.snapshot_id = snapshot.id.into();
+        dependency.save(&connection).await?;
+
+        let mut advisory = Advisories::new(
+            "CVE-2023-5678",
+            AdvisorySource::Unknown,
+            SecuritySeverity::High,
+        );
+        advisory.save(&connection).await?;
+        let mut alert = Alerts {
+            dependency_id: Some(dependency.id.into()),
+            ..Alerts::new("CVE-2023-5678", snapshot.id, advisory.id)
+        };
+        alert.save(&connection).await?;
+
+        let statement = |status: BomVexStatus, products: &[&str]| VexStatement {
+            advisory: "CVE-2023-5678".to_string(),
+            products: products.iter().map(|p| p.to_string()).collect(),
+            status,
+            justification: Some("code_not_reachable".to_string()),
+        };
+        let mut document = VexDocument {
+            id: "urn:uuid:vex-1".to_string(),
+            format: VexFormat::CycloneDx,
+            statements: vec![
+                statement(
+                    BomVexStatus::NotAffected,
+                    &["pkg:deb/debian/openssl@3.0.11-1?arch=amd64"],
+                ),
+                // Products which are not in the snapshot are tolerated
+                statement(BomVexStatus::NotAffected, &["pkg:npm/untracked@1.0.0"]),
+            ],
+        };
+        let stored = VexStatements::store(&connection, &snapshot, &document).await?;
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].project_id, Some(project.id.into()));
+        assert_eq!(VexStatements::apply(&connection, &snapshot).await?, 1);
+
+        let alert = Alerts::fetch_by_primary_key(&connection, alert.id).await?;
+        assert_eq!(alert.state, SecurityState::NotAffected);
+        assert_eq!(alert.vex_statement_id, Some(stored[0].id.into()));
+        assert!(!alert.state.is_open());
+
+        // The document is uploaded again (the products are affected)
+        document.statements = vec![statement(BomVexStatus::Affected, &[])];
+        VexStatements::store(&connection, &snapshot, &document).await?;
+        assert_eq!(
+            VexStatements::fetch_for_snapshot(&connection, snapshot.id.into())
+                .await?
+                .len(),
+            1
+        );
+        assert_eq!(VexStatements::apply(&connection, &snapshot).await?, 0);
+        let alert = Alerts::fetch_by_primary_key(&connection, alert.id).await?;
+        assert_eq!(alert.state, SecurityState::Vulnerable);
+        assert_eq!(alert.vex_statement_id, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+//! # Seed
+//!
+//! Deterministic demo data (projects, snapshots, dependencies, alerts, users and
+//! statistics) for evaluating Konarr and for the integration tests.
+//!
+//! Everything is created through the model APIs (the SBOMs are indexed like an agent
+//! upload) so seeding also works as a smoke test of the ingestion.
+//!
+//! ```text
+//! demo-server-01 (Server)
+//! ├── demo-server-01/nginx      debian-nginx.cdx.json
+//! └── demo-server-01/webapp     node-webapp.cdx.json
+//! demo-server-02 (Server)
+//! ├── demo-server-02/alpine     syft-alpine.cdx.json
+//! └── demo-server-02/python-app syft-venv-dir.cdx.json
+//! ```
+use geekorm::prelude::*;
+use log::{debug, info};
+
+use crate::{
+    bom::{BomParser, Parsers},
+    models::{
+        raw_query, ProjectType, Projects, ServerSettings, SessionState, SessionType, Sessions,
+        Setting, Snapshot, TransactionConnection, UserRole, Users,
+    },
+    KonarrError,
+};
+
+/// Password of the seeded users
+pub const SEED_PASSWORD: &str = "konarr-demo";
+
+/// Container project name and its SBOM fixture
+type SeedContainer = (&'static str, &'static [u8]);
+
+/// Projects of the demo profile (server, containers with the SBOM fixture)
+const DEMO_PROJECTS: [(&str, [SeedContainer; 2]); 2] = [
+    (
+        "demo-server-01",
+        [
+            ("nginx", include_bytes!("fixtures/debian-nginx.cdx.json")),
+            ("webapp", include_bytes!("fixtures/node-webapp.cdx.json")),
+        ],
+    ),
+    (
+        "demo-server-02",
+        [
+            (
+                "alpine",
+                include_bytes!("../../bom/testdata/syft-alpine.cdx.json"),
+            ),
+            (
+                "python-app",
+                include_bytes!("../../bom/testdata/syft-venv-dir.cdx.json"),
+            ),
+        ],
+    ),
+];
+
+/// Users of the demo profile (one per role)
+const DEMO_USERS: [(&str, UserRole); 3] = [
+    ("demo-admin", UserRole::Admin),
+    ("demo-user", UserRole::User),
+    ("demo-agent", UserRole::Agent),
+];
+
+/// Tables removed by [wipe] (children first)
+const SEED_TABLES: [&str; 26] = [
+    "ComponentTags",
+    "ComponentAnnotations",
+    "AlertEvents",
+    "Alerts",
+    "VexStatements",
+    "AlertIgnoreRules",
+    "AdvisoriesMetadata",
+    "Advisories",
+    "Dependencies",
+    "SbomUploads",
+    "SnapshotMetadata",
+    "ProjectSnapshots",
+    "ProjectTransfers",
+    "Snapshot",
+    "Projects",
+    "ComponentVersion",
+    "Component",
+    "AgentTokens",
+    "AgentCertificates",
+    "Users",
+    "Sessions",
+    "TaskRuns",
+    "AuditLog",
+    "Reports",
+    "ProjectSettings",
+    "ServerSettings",
+];
+
+/// Seed Profile
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SeedProfile {
+    /// Two servers with containers, alerts at each severity and a user per role
+    #[default]
+    Demo,
+}
+
+impl std::str::FromStr for SeedProfile {
+    type Err = KonarrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "demo" => Ok(SeedProfile::Demo),
+            _ => Err(KonarrError::InvalidData(format!(
+                "Unknown seed profile `{}` (available: demo)",
+                s
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for SeedProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SeedProfile::Demo => write!(f, "demo"),
+        }
+    }
+}
+
+/// Seed Summary
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SeedSummary {
+    /// Number of projects created
+    pub projects: usize,
+    /// Number of snapshots created
+    pub snapshots: usize,
+    /// Number of dependencies indexed
+    pub dependencies: usize,
+    /// Number of users created
+    pub users: usize,
+}
+
+/// Seed the database with the fixtures of the profile
+///
+/// Fails if the database was already seeded (use [wipe] first).
+pub async fn seed<'a, T>(
+    connection: &'a T,
+    profile: SeedProfile,
+) -> Result<SeedSummary, KonarrError>
+where
+    T: GeekConnection<Connection = T> + TransactionConnection + Send + Sync + 'a,
+{
+    info!("Seeding the database (profile: {})", profile);
+    crate::models::database_create(connection).await?;
+
+    if Projects::fetch_by_name(connection, DEMO_PROJECTS[0].0)
+        .await
+        .is_ok()
+    {
+        return Err(KonarrError::Conflict(
+            "Database is already seeded, wipe it first".to_string(),
+        ));
+    }
+    let mut summary = SeedSummary::default();
+
+    ServerSettings::get(connection, Setting::Initialized)
+        .await?
+        .set_update(connection, "true")
+        .await?;
+    // Alerts are indexed from the SBOMs
+    ServerSettings::get(connection, Setting::Security)
+        .await?
+        .set_update(connection, "enabled")
+        .await?;
+
+    for (username, role) in DEMO_USERS {
+        let mut session = Sessions::new(SessionType::User, SessionState::Inactive);
+        session.save(connection).await?;
+        let mut user = Users::new(username, SEED_PASSWORD, role, session.id);
+        user.save(connection).await?;
+        summary.users += 1;
+    }
+
+    for (server_name, containers) in DEMO_PROJECTS {
+        let mut server = Projects::with_title(server_name, ProjectType::Server);
+        server.save(connection).await?;
+        summary.projects += 1;
+
+        for (name, fixture) in containers {
+            let mut project =
+                Projects::with_title(format!("{}/{}", server_name, name), ProjectType::Container);
+            project.parent = server.id.into();
+            project.save(connection).await?;
+            summary.projects += 1;
+
+            let bom = Parsers::parse(fixture)?;
+            let mut snapshot = Snapshot::create(connection).await?;
+            project.add_snapshot(connection, snapshot.clone()).await?;
+            let ingest = snapshot.add_bom(connection, &bom).await?;
+            snapshot
+                .set_state(connection, crate::models::SnapshotState::Completed)
+                .await?;
+            debug!(
+                "Seeded Project({}) :: {} dependencies",
+                project.name, ingest.components
+            );
+            summary.snapshots += 1;
+            summary.dependencies += ingest.components - ingest.failed;
+        }
+    }
+
+    #[cfg(feature = "tasks")]
+    crate::tasks::statistics::statistics(connection).await?;
+
+    info!(
+        "Seeded {} projects, {} snapshots, {} dependencies and {} users",
+        summary.projects, summary.snapshots, summary.dependencies, summary.users
+    );
+    Ok(summary)
+}
+
+/// Remove all the data from the database (the tables are re-initialised)
+pub async fn wipe<'a, T>(connection: &'a T) -> Result<(), KonarrError>
+where
+    T: GeekConnection<Connection = T> + TransactionConnection + 'a,
+{
+    info!("Wiping the database");
+    for table in SEED_TABLES {
+        debug!("Wiping table :: {}", table);
+        T::execute::<Projects>(
+            connection,
+            raw_query(format!("DELETE FROM {};", table), Values::new()),
+        )
+        .await?;
+    }
+    crate::models::database_create(connection).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{security::SecuritySeverity, Alerts};
+
+    #[tokio::test]
+    async fn test_seed_demo() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let summary = seed(&connection, "demo".parse()?).await?;
+        assert_eq!(
+            summary,
+            SeedSummary {
+                projects: 6,
+                snapshots: 4,
+                dependencies: 24,
+                users: 3,
+            }
+        );
+        // Seeding twice is refused
+        assert!(seed(&connection, SeedProfile::Demo).await.is_err());
+
+        let web = Projects::fetch_by_name(&connection, "demo-server-01/webapp").await?;
+        let server = Projects::fetch_by_name(&connection, "demo-server-01").await?;
+        assert_eq!(web.parent, i32::from(server.id));
+
+        let alerts = Alerts::fetch_all(&connection).await?;
+        for severity in [
+            SecuritySeverity::Critical,
+            SecuritySeverity::High,
+            SecuritySeverity::Medium,
+            SecuritySeverity::Low,
+            SecuritySeverity::Informational,
+        ] {
+            let mut found = false;
+            for alert in alerts.iter() {
+                let mut alert = alert.clone();
+                alert.fetch(&connection).await?;
+                found |= alert.advisory_id.data.severity == severity;
+            }
+            assert!(found, "No {:?} alert", severity);
+        }
+        assert!(Users::login(&connection, "demo-admin", SEED_PASSWORD)
+            .await
+            .is_ok());
+
+        // Deterministic after a wipe
+        wipe(&connection).await?;
+        assert!(Projects::fetch_by_name(&connection, "demo-server-01")
+            .await
+            .is_err());
+        assert_eq!(seed(&connection, SeedProfile::Demo).await?, summary);
+        Ok(())
+    }
+}
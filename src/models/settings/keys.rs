@@ -4,7 +4,10 @@ use geekorm::prelude::*;
 
 use super::SettingType;
 
-#[derive(Data, Debug, Default, Clone, PartialEq)]
+/// Server Setting keys (`name` column of the ServerSettings table)
+///
+/// Unknown names are converted to [Setting::Unknown], use [Setting::parse] to reject them.
+#[derive(Data, Debug, Default, Clone, Copy, PartialEq)]
 #[allow(missing_docs)]
 pub enum Setting {
     // Setup Settings
@@ -13,17 +16,47 @@ pub enum Setting {
     // Registration
     #[geekorm(key = "registration")]
     Registration,
+    /// Show the build information to unauthenticated users
+    #[geekorm(key = "build.public")]
+    BuildPublic,
+    /// Maintenance mode (read-only server, the scheduled tasks are paused)
+    #[geekorm(key = "maintenance.enabled")]
+    MaintenanceEnabled,
+    /// Public (read-only) status page with the aggregated alerts
+    #[geekorm(key = "public.dashboard.enabled")]
+    PublicDashboardEnabled,
     // Agent Settings
     #[geekorm(key = "agent")]
     Agent,
     #[geekorm(key = "agent.key")]
     AgentKey,
+    /// Allow the legacy shared agent key (deprecated, use scoped agent tokens)
+    #[geekorm(key = "agent.key.legacy")]
+    AgentKeyLegacy,
     #[geekorm(key = "agent.tool")]
     AgentTool,
     #[geekorm(key = "agent.tool.auto-install")]
     AgentToolAutoInstall,
     #[geekorm(key = "agent.tool.auto-update")]
     AgentToolAutoUpdate,
+    /// Number of minor versions an agent can be behind the server before it is outdated
+    #[geekorm(key = "agent.version.max-minor-gap")]
+    AgentVersionMaxMinorGap,
+    /// Comma separated list of containers (names or images, globs) the agents do not scan
+    #[geekorm(key = "agent.exclude")]
+    AgentExclude,
+    /// Number of minutes between two runs of the agents in monitoring mode
+    #[geekorm(key = "agent.interval")]
+    AgentInterval,
+    /// Number of days before the agents rescan a container with a cached SBOM
+    #[geekorm(key = "agent.cache.expires")]
+    AgentCacheExpires,
+
+    // Sessions
+    /// Maximum number of active sessions per user, the oldest sessions are invalidated
+    /// at login (`0` disables the limit)
+    #[geekorm(key = "sessions.max_per_user")]
+    SessionsMaxPerUser,
 
     // Statistics - Projects
     #[geekorm(key = "stats.projects.total")]
@@ -40,6 +73,22 @@ pub enum Setting {
     StatsProjectsGroups,
     #[geekorm(key = "stats.projects.containers")]
     StatsProjectsContainers,
+    /// Container projects where the running digest differs from the scanned digest
+    #[geekorm(key = "stats.projects.stale")]
+    StatsProjectsStale,
+    /// Projects last scanned by an outdated agent
+    #[geekorm(key = "stats.projects.agent-outdated")]
+    StatsProjectsAgentOutdated,
+    /// Active projects by health (`security.health` of the latest snapshot)
+    #[geekorm(key = "stats.projects.health.green")]
+    StatsProjectsHealthGreen,
+    #[geekorm(key = "stats.projects.health.amber")]
+    StatsProjectsHealthAmber,
+    #[geekorm(key = "stats.projects.health.red")]
+    StatsProjectsHealthRed,
+    /// Agent tokens used by an outdated agent
+    #[geekorm(key = "stats.agents.outdated")]
+    StatsAgentsOutdated,
 
     // Statistics - Security
     #[geekorm(key = "security.alerts.total")]
@@ -69,6 +118,14 @@ pub enum Setting {
     #[geekorm(key = "stats.users.inactive")]
     StatsUsersInactive,
 
+    // Statistics - Alerts
+    /// Alerts resolved (fixed) by a new snapshot
+    #[geekorm(key = "stats.alerts.resolved")]
+    StatsAlertsResolved,
+    /// Alerts resolved in the last 30 days
+    #[geekorm(key = "stats.alerts.resolved.recent")]
+    StatsAlertsResolvedRecent,
+
     // Statistics - Dependencies
     #[geekorm(key = "stats.dependencies.total")]
     StatsDependenciesTotal,
@@ -95,6 +152,34 @@ pub enum Setting {
     #[geekorm(key = "stats.dependencies.middleware")]
     StatsMiddleware,
 
+    // Statistics - Dependencies (latest snapshots) by package manager
+    #[geekorm(key = "stats.dependencies.managers.apk")]
+    StatsManagerApk,
+    #[geekorm(key = "stats.dependencies.managers.cargo")]
+    StatsManagerCargo,
+    #[geekorm(key = "stats.dependencies.managers.composer")]
+    StatsManagerComposer,
+    #[geekorm(key = "stats.dependencies.managers.deb")]
+    StatsManagerDeb,
+    #[geekorm(key = "stats.dependencies.managers.gem")]
+    StatsManagerGem,
+    #[geekorm(key = "stats.dependencies.managers.generic")]
+    StatsManagerGeneric,
+    #[geekorm(key = "stats.dependencies.managers.npm")]
+    StatsManagerNpm,
+    #[geekorm(key = "stats.dependencies.managers.golang")]
+    StatsManagerGolang,
+    #[geekorm(key = "stats.dependencies.managers.maven")]
+    StatsManagerMaven,
+    #[geekorm(key = "stats.dependencies.managers.pypi")]
+    StatsManagerPyPi,
+    #[geekorm(key = "stats.dependencies.managers.nuget")]
+    StatsManagerNuget,
+    #[geekorm(key = "stats.dependencies.managers.rpm")]
+    StatsManagerRpm,
+    #[geekorm(key = "stats.dependencies.managers.unknown")]
+    StatsManagerUnknown,
+
     #[geekorm(key = "stats.dependencies.secure")]
     StatsDependenciesSecure,
     #[geekorm(key = "stats.dependencies.insecure")]
@@ -103,6 +188,60 @@ pub enum Setting {
     #[geekorm(key = "stats.dependencies.unused")]
     StatsDependenciesUnused,
 
+    // Statistics - Catalogue
+    /// Percentage of components classified (not a library or unknown)
+    #[geekorm(key = "catalogue.coverage.percent")]
+    CatalogueCoveragePercent,
+    /// Components reclassified in the last catalogue run
+    #[geekorm(key = "catalogue.coverage.reclassified")]
+    CatalogueReclassified,
+    /// Components still classified as a library or unknown
+    #[geekorm(key = "catalogue.coverage.unclassified")]
+    CatalogueUnclassified,
+
+    // Statistics - Storage
+    /// Size of the database file (bytes)
+    #[geekorm(key = "storage.database.size")]
+    StorageDatabaseSize,
+    /// Free disk space on the data path (bytes)
+    #[geekorm(key = "storage.disk.free")]
+    StorageDiskFree,
+    /// Size of the Grype database (bytes)
+    #[geekorm(key = "storage.grypedb.size")]
+    StorageGrypeSize,
+    /// Number of stored SBOM files
+    #[geekorm(key = "storage.sboms.count")]
+    StorageSbomsCount,
+    /// Total size of the stored SBOM files (bytes)
+    #[geekorm(key = "storage.sboms.size")]
+    StorageSbomsSize,
+
+    // Statistics - Integrity
+    /// Issues found in the last integrity check
+    #[geekorm(key = "integrity.issues")]
+    IntegrityIssues,
+    /// Issues repaired in the last integrity check
+    #[geekorm(key = "integrity.repaired")]
+    IntegrityRepaired,
+
+    // SBOM Processing
+    /// Comma separated Package URL globs of components to exclude
+    #[geekorm(key = "bom.exclude")]
+    BomExclude,
+    /// Comma separated list of internal namespaces
+    #[geekorm(key = "bom.internal.namespaces")]
+    BomInternalNamespaces,
+    /// Log and skip SBOM processors which fail (instead of failing the upload)
+    #[geekorm(key = "bom.processors.skip-failures")]
+    BomProcessorsSkipFailures,
+    /// Maximum size (in bytes) of the evidence stored per dependency (`0` disables it)
+    #[geekorm(key = "bom.evidence.max-bytes")]
+    BomEvidenceMaxBytes,
+    /// Maximum percentage of the SBOM components which can fail to index before the
+    /// snapshot is marked as failed
+    #[geekorm(key = "bom.errors.max-percent")]
+    BomErrorsMaxPercent,
+
     // Security
     #[geekorm(key = "security")]
     Security,
@@ -116,6 +255,9 @@ pub enum Setting {
     // Security Rescan Setting
     #[geekorm(key = "security.rescan")]
     SecurityRescan,
+    /// Rescan the projects when a new advisories (Grype) database build is synced
+    #[geekorm(key = "security.rescan_on_db_update")]
+    SecurityRescanOnDbUpdate,
 
     // Security Advisories
     #[geekorm(key = "security.advisories")]
@@ -128,6 +270,124 @@ pub enum Setting {
     SecurityAdvisoriesVersion,
     #[geekorm(key = "security.advisories.updated")]
     SecurityAdvisoriesUpdated,
+    /// When the advisories database sync was last attempted
+    #[geekorm(key = "security.advisories.sync.attempted")]
+    SecurityAdvisoriesSyncAttempted,
+    /// Result of the last advisories database sync (`updated`, `up-to-date` or `failed`)
+    #[geekorm(key = "security.advisories.sync.result")]
+    SecurityAdvisoriesSyncResult,
+    /// Error of the last failed advisories database sync
+    #[geekorm(key = "security.advisories.sync.error")]
+    SecurityAdvisoriesSyncError,
+    /// Number of days the alert timeline events are kept (0 keeps them forever)
+    #[geekorm(key = "security.events.retention")]
+    SecurityEventsRetention,
+    /// Number of days before the end-of-life date an operating system / runtime is flagged
+    #[geekorm(key = "security.eol.warning_days")]
+    SecurityEolWarningDays,
+    /// Refresh the end-of-life schedules from endoflife.date (bundled copy if disabled)
+    #[geekorm(key = "security.eol.refresh")]
+    SecurityEolRefresh,
+
+    // Project Health (number of open alerts from which a project is red / amber, `0`
+    // disables the threshold)
+    /// Critical alerts from which a project is red
+    #[geekorm(key = "security.health.critical.red")]
+    SecurityHealthCriticalRed,
+    /// Critical alerts from which a project is amber
+    #[geekorm(key = "security.health.critical.amber")]
+    SecurityHealthCriticalAmber,
+    /// High alerts from which a project is red
+    #[geekorm(key = "security.health.high.red")]
+    SecurityHealthHighRed,
+    /// High alerts from which a project is amber
+    #[geekorm(key = "security.health.high.amber")]
+    SecurityHealthHighAmber,
+    /// Medium alerts from which a project is red
+    #[geekorm(key = "security.health.medium.red")]
+    SecurityHealthMediumRed,
+    /// Medium alerts from which a project is amber
+    #[geekorm(key = "security.health.medium.amber")]
+    SecurityHealthMediumAmber,
+    /// Low alerts from which a project is red
+    #[geekorm(key = "security.health.low.red")]
+    SecurityHealthLowRed,
+    /// Low alerts from which a project is amber
+    #[geekorm(key = "security.health.low.amber")]
+    SecurityHealthLowAmber,
+
+    // Snapshot Retention (per project type, `0` disables the limit)
+    /// Maximum number of snapshots kept for container projects
+    #[geekorm(key = "retention.container.max_snapshots")]
+    RetentionContainerMaxSnapshots,
+    /// Maximum age (days) of the snapshots kept for container projects
+    #[geekorm(key = "retention.container.max_age_days")]
+    RetentionContainerMaxAgeDays,
+    /// Maximum number of snapshots kept for server projects
+    #[geekorm(key = "retention.server.max_snapshots")]
+    RetentionServerMaxSnapshots,
+    /// Maximum age (days) of the snapshots kept for server projects
+    #[geekorm(key = "retention.server.max_age_days")]
+    RetentionServerMaxAgeDays,
+    /// Maximum number of snapshots kept for application projects
+    #[geekorm(key = "retention.application.max_snapshots")]
+    RetentionApplicationMaxSnapshots,
+    /// Maximum age (days) of the snapshots kept for application projects
+    #[geekorm(key = "retention.application.max_age_days")]
+    RetentionApplicationMaxAgeDays,
+    /// Maximum number of snapshots kept for group projects
+    #[geekorm(key = "retention.group.max_snapshots")]
+    RetentionGroupMaxSnapshots,
+    /// Maximum age (days) of the snapshots kept for group projects
+    #[geekorm(key = "retention.group.max_age_days")]
+    RetentionGroupMaxAgeDays,
+    /// Maximum number of snapshots kept for cluster projects
+    #[geekorm(key = "retention.cluster.max_snapshots")]
+    RetentionClusterMaxSnapshots,
+    /// Maximum age (days) of the snapshots kept for cluster projects
+    #[geekorm(key = "retention.cluster.max_age_days")]
+    RetentionClusterMaxAgeDays,
+    /// Maximum age (days) of the stored raw SBOMs, the snapshots are kept (0 disables it)
+    #[geekorm(key = "retention.sbom_blobs.days")]
+    RetentionSbomBlobsDays,
+    /// Maximum total size (bytes) of the stored raw SBOMs, the oldest are removed first
+    /// (0 disables it)
+    #[geekorm(key = "retention.sbom_blobs.max_bytes")]
+    RetentionSbomBlobsMaxBytes,
+
+    // Scans
+    /// Number of days without a scan after which a container or server project is stale
+    /// (0 disables the check)
+    #[geekorm(key = "scan.freshness.days")]
+    ScanFreshnessDays,
+
+    // Notifications
+    /// Webhook URL the notifications (newly stale projects, ...) are posted to (empty
+    /// disables them)
+    #[geekorm(key = "notifications.webhook")]
+    NotificationsWebhook,
+    /// Number of delivery attempts of an event before giving up (see the events outbox)
+    #[geekorm(key = "notifications.attempts")]
+    NotificationsAttempts,
+
+    // Reports
+    /// Schedule of the security digest reports (`disabled`, `daily` or `weekly`)
+    #[geekorm(key = "reports.schedule")]
+    ReportsSchedule,
+    /// Number of days the reports are kept (0 keeps them forever)
+    #[geekorm(key = "reports.retention")]
+    ReportsRetention,
+    /// Webhook URL the Markdown of the new reports is posted to (empty disables it)
+    #[geekorm(key = "reports.webhook")]
+    ReportsWebhook,
+
+    // Image Policy
+    /// Flag container images using the `latest` tag
+    #[geekorm(key = "policy.deny_latest_tag")]
+    PolicyDenyLatestTag,
+    /// Comma separated list of allowed container registries (globs, empty allows all)
+    #[geekorm(key = "policy.allowed_registries")]
+    PolicyAllowedRegistries,
 
     // Deprecated
     #[geekorm(key = "security.polling")]
@@ -146,6 +406,79 @@ pub enum Setting {
     Unknown,
 }
 
+impl Setting {
+    /// Parse a setting name, unknown names are an error (not [Setting::Unknown])
+    pub fn parse(name: &str) -> Result<Self, crate::KonarrError> {
+        match name.parse::<Setting>() {
+            Ok(Setting::Unknown) | Err(_) => Err(crate::KonarrError::InvalidData(format!(
+                "Unknown setting `{}`",
+                name
+            ))),
+            Ok(setting) => Ok(setting),
+        }
+    }
+
+    /// Check if the setting is a secret (encrypted at rest, see [super::secrets])
+    pub fn is_secret(&self) -> bool {
+        SERVER_SETTINGS_SECRETS.contains(self)
+    }
+
+    /// Namespace of the setting (if it is part of one)
+    pub fn namespace(&self) -> Option<SettingNamespace> {
+        let name = self.to_string();
+        SETTING_NAMESPACES
+            .into_iter()
+            .find(|namespace| name.starts_with(namespace.prefix()))
+    }
+}
+
+/// Namespaces of the Server Settings (fetched together)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingNamespace {
+    /// Project, user and dependency statistics (`stats.`)
+    Stats,
+    /// Global alert counters (`security.alerts.`)
+    SecurityAlerts,
+    /// Storage diagnostics (`storage.`)
+    Storage,
+    /// Catalogue coverage (`catalogue.`)
+    Catalogue,
+    /// Integrity check results (`integrity.`)
+    Integrity,
+    /// Snapshot retention policies (`retention.`)
+    Retention,
+}
+
+/// List of the Server Settings namespaces
+pub const SETTING_NAMESPACES: [SettingNamespace; 6] = [
+    SettingNamespace::Stats,
+    SettingNamespace::SecurityAlerts,
+    SettingNamespace::Storage,
+    SettingNamespace::Catalogue,
+    SettingNamespace::Integrity,
+    SettingNamespace::Retention,
+];
+
+impl SettingNamespace {
+    /// Prefix of the settings names (including the trailing `.`)
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            SettingNamespace::Stats => "stats.",
+            SettingNamespace::SecurityAlerts => "security.alerts.",
+            SettingNamespace::Storage => "storage.",
+            SettingNamespace::Catalogue => "catalogue.",
+            SettingNamespace::Integrity => "integrity.",
+            SettingNamespace::Retention => "retention.",
+        }
+    }
+}
+
+impl std::fmt::Display for SettingNamespace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.prefix().trim_end_matches('.'))
+    }
+}
+
 /// List of depricated settings
 pub const SERVER_SETTINGS_DEPRICATED: [Setting; 4] = [
     Setting::SecurityPolling,
@@ -154,14 +487,32 @@ pub const SERVER_SETTINGS_DEPRICATED: [Setting; 4] = [
     Setting::SecurityAlertsInfomational,
 ];
 
+/// Secret settings (encrypted at rest when an encryption key is set)
+pub const SERVER_SETTINGS_SECRETS: [Setting; 3] = [
+    Setting::AgentKey,
+    Setting::NotificationsWebhook,
+    Setting::ReportsWebhook,
+];
+
 /// Server Settings Defaults
-pub const SERVER_SETTINGS_DEFAULTS: [(Setting, SettingType, &'static str); 34] = [
+pub const SERVER_SETTINGS_DEFAULTS: [(Setting, SettingType, &'static str); 91] = [
     // Registration Settings
     (Setting::Registration, SettingType::Toggle, "enabled"),
+    // Build information
+    (Setting::BuildPublic, SettingType::Toggle, "disabled"),
+    // Public status page
+    (
+        Setting::PublicDashboardEnabled,
+        SettingType::Toggle,
+        "disabled",
+    ),
+    // Maintenance mode
+    (Setting::MaintenanceEnabled, SettingType::Toggle, "disabled"),
     // If we are already initialized
     (Setting::Initialized, SettingType::Boolean, "false"),
     // Agent Settings
     (Setting::Agent, SettingType::Toggle, "disabled"),
+    (Setting::AgentKeyLegacy, SettingType::Toggle, "enabled"),
     (
         Setting::AgentToolAutoInstall,
         SettingType::Toggle,
@@ -172,6 +523,16 @@ pub const SERVER_SETTINGS_DEFAULTS: [(Setting, SettingType, &'static str); 34] =
         SettingType::Toggle,
         "disabled",
     ),
+    (
+        Setting::AgentVersionMaxMinorGap,
+        SettingType::SetString,
+        "2",
+    ),
+    (Setting::AgentExclude, SettingType::SetString, ""),
+    (Setting::AgentInterval, SettingType::SetString, "1"),
+    (Setting::AgentCacheExpires, SettingType::SetString, "7"),
+    // Sessions
+    (Setting::SessionsMaxPerUser, SettingType::SetString, "0"),
     // Statistics
     (Setting::StatsProjectsTotal, SettingType::Statistics, "0"),
     (Setting::StatsProjectsActive, SettingType::Statistics, "0"),
@@ -184,6 +545,28 @@ pub const SERVER_SETTINGS_DEFAULTS: [(Setting, SettingType, &'static str); 34] =
         SettingType::Statistics,
         "0",
     ),
+    (Setting::StatsProjectsStale, SettingType::Statistics, "0"),
+    (
+        Setting::StatsProjectsAgentOutdated,
+        SettingType::Statistics,
+        "0",
+    ),
+    (
+        Setting::StatsProjectsHealthGreen,
+        SettingType::Statistics,
+        "0",
+    ),
+    (
+        Setting::StatsProjectsHealthAmber,
+        SettingType::Statistics,
+        "0",
+    ),
+    (
+        Setting::StatsProjectsHealthRed,
+        SettingType::Statistics,
+        "0",
+    ),
+    (Setting::StatsAgentsOutdated, SettingType::Statistics, "0"),
     (
         Setting::StatsDependenciesTotal,
         SettingType::Statistics,
@@ -192,9 +575,30 @@ pub const SERVER_SETTINGS_DEFAULTS: [(Setting, SettingType, &'static str); 34] =
     (Setting::StatsUsersTotal, SettingType::Statistics, "0"),
     (Setting::StatsUsersActive, SettingType::Statistics, "0"),
     (Setting::StatsUsersInactive, SettingType::Statistics, "0"),
+    (Setting::StatsAlertsResolved, SettingType::Statistics, "0"),
+    (
+        Setting::StatsAlertsResolvedRecent,
+        SettingType::Statistics,
+        "0",
+    ),
+    // SBOM Processing
+    (Setting::BomExclude, SettingType::SetString, ""),
+    (Setting::BomInternalNamespaces, SettingType::SetString, ""),
+    (
+        Setting::BomProcessorsSkipFailures,
+        SettingType::Toggle,
+        "enabled",
+    ),
+    (Setting::BomEvidenceMaxBytes, SettingType::SetString, "1024"),
+    (Setting::BomErrorsMaxPercent, SettingType::SetString, "10"),
     // Security Features
     (Setting::Security, SettingType::Toggle, "disabled"),
     (Setting::SecurityRescan, SettingType::Toggle, "disabled"),
+    (
+        Setting::SecurityRescanOnDbUpdate,
+        SettingType::Toggle,
+        "enabled",
+    ),
     (Setting::SecurityToolsName, SettingType::SetString, "syft"),
     // Tools Settings
     (Setting::SecurityToolsAlerts, SettingType::Toggle, "enabled"),
@@ -215,11 +619,140 @@ pub const SERVER_SETTINGS_DEFAULTS: [(Setting, SettingType, &'static str); 34] =
         SettingType::Datetime,
         "Unknown",
     ),
+    (
+        Setting::SecurityAdvisoriesSyncAttempted,
+        SettingType::Datetime,
+        "Unknown",
+    ),
+    (
+        Setting::SecurityAdvisoriesSyncResult,
+        SettingType::String,
+        "Unknown",
+    ),
+    (
+        Setting::SecurityAdvisoriesSyncError,
+        SettingType::String,
+        "",
+    ),
     (
         Setting::SecurityAdvisoriesPolling,
         SettingType::Toggle,
         "disabled",
     ),
+    (
+        Setting::SecurityEventsRetention,
+        SettingType::SetString,
+        "365",
+    ),
+    // End-of-life
+    (
+        Setting::SecurityEolWarningDays,
+        SettingType::SetString,
+        "90",
+    ),
+    (Setting::SecurityEolRefresh, SettingType::Toggle, "enabled"),
+    // Project Health
+    (
+        Setting::SecurityHealthCriticalRed,
+        SettingType::SetString,
+        "1",
+    ),
+    (
+        Setting::SecurityHealthCriticalAmber,
+        SettingType::SetString,
+        "0",
+    ),
+    (Setting::SecurityHealthHighRed, SettingType::SetString, "0"),
+    (
+        Setting::SecurityHealthHighAmber,
+        SettingType::SetString,
+        "6",
+    ),
+    (
+        Setting::SecurityHealthMediumRed,
+        SettingType::SetString,
+        "0",
+    ),
+    (
+        Setting::SecurityHealthMediumAmber,
+        SettingType::SetString,
+        "0",
+    ),
+    (Setting::SecurityHealthLowRed, SettingType::SetString, "0"),
+    (Setting::SecurityHealthLowAmber, SettingType::SetString, "0"),
+    // Snapshot Retention
+    (
+        Setting::RetentionContainerMaxSnapshots,
+        SettingType::SetString,
+        "0",
+    ),
+    (
+        Setting::RetentionContainerMaxAgeDays,
+        SettingType::SetString,
+        "0",
+    ),
+    (
+        Setting::RetentionServerMaxSnapshots,
+        SettingType::SetString,
+        "0",
+    ),
+    (
+        Setting::RetentionServerMaxAgeDays,
+        SettingType::SetString,
+        "0",
+    ),
+    (
+        Setting::RetentionApplicationMaxSnapshots,
+        SettingType::SetString,
+        "0",
+    ),
+    (
+        Setting::RetentionApplicationMaxAgeDays,
+        SettingType::SetString,
+        "0",
+    ),
+    (
+        Setting::RetentionGroupMaxSnapshots,
+        SettingType::SetString,
+        "0",
+    ),
+    (
+        Setting::RetentionGroupMaxAgeDays,
+        SettingType::SetString,
+        "0",
+    ),
+    (
+        Setting::RetentionClusterMaxSnapshots,
+        SettingType::SetString,
+        "0",
+    ),
+    (
+        Setting::RetentionClusterMaxAgeDays,
+        SettingType::SetString,
+        "0",
+    ),
+    (Setting::RetentionSbomBlobsDays, SettingType::SetString, "0"),
+    (
+        Setting::RetentionSbomBlobsMaxBytes,
+        SettingType::SetString,
+        "0",
+    ),
+    // Scans
+    (Setting::ScanFreshnessDays, SettingType::SetString, "7"),
+    // Notifications
+    (Setting::NotificationsWebhook, SettingType::SetString, ""),
+    (Setting::NotificationsAttempts, SettingType::SetString, "5"),
+    // Reports
+    (Setting::ReportsSchedule, SettingType::SetString, "weekly"),
+    (Setting::ReportsRetention, SettingType::SetString, "365"),
+    (Setting::ReportsWebhook, SettingType::SetString, ""),
+    // Image Policy
+    (
+        Setting::PolicyDenyLatestTag,
+        SettingType::Toggle,
+        "disabled",
+    ),
+    (Setting::PolicyAllowedRegistries, SettingType::SetString, ""),
     (Setting::SecurityAlertsTotal, SettingType::Statistics, "0"),
     (
         Setting::SecurityAlertsCritical,
@@ -243,6 +776,30 @@ pub const SERVER_SETTINGS_DEFAULTS: [(Setting, SettingType, &'static str); 34] =
     (Setting::SecurityAlertsUnknown, SettingType::Statistics, "0"),
 ];
 
+/// Check that each default is a distinct (known) setting
+const fn defaults_are_distinct() -> bool {
+    let mut i = 0;
+    while i < SERVER_SETTINGS_DEFAULTS.len() {
+        if SERVER_SETTINGS_DEFAULTS[i].0 as usize == Setting::Unknown as usize {
+            return false;
+        }
+        let mut j = i + 1;
+        while j < SERVER_SETTINGS_DEFAULTS.len() {
+            if SERVER_SETTINGS_DEFAULTS[i].0 as usize == SERVER_SETTINGS_DEFAULTS[j].0 as usize {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+const _: () = assert!(
+    defaults_are_distinct(),
+    "SERVER_SETTINGS_DEFAULTS has a duplicated or unknown setting"
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -285,4 +842,85 @@ mod tests {
         let key = Setting::from("stats.projects.total");
         assert_eq!(key, Setting::StatsProjectsTotal);
     }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Setting::parse("registration").unwrap(),
+            Setting::Registration
+        );
+        assert_eq!(
+            Setting::parse("security.eol.refresh").unwrap(),
+            Setting::SecurityEolRefresh
+        );
+        // Typos are an error (not the `Unknown` setting)
+        assert!(Setting::parse("regstration").is_err());
+        assert!(Setting::parse("unknown").is_err());
+        assert_eq!(Setting::from("regstration"), Setting::Unknown);
+    }
+
+    #[test]
+    fn test_namespaces() {
+        assert_eq!(
+            Setting::SecurityAlertsCritical.namespace(),
+            Some(SettingNamespace::SecurityAlerts)
+        );
+        assert_eq!(
+            Setting::StorageSbomsSize.namespace(),
+            Some(SettingNamespace::Storage)
+        );
+        assert_eq!(
+            Setting::RetentionServerMaxAgeDays.namespace(),
+            Some(SettingNamespace::Retention)
+        );
+        assert_eq!(Setting::Security.namespace(), None);
+        assert_eq!(
+            SettingNamespace::SecurityAlerts.to_string(),
+            "security.alerts"
+        );
+    }
+
+    #[test]
+    fn test_defaults() {
+        let mut names = std::collections::HashSet::new();
+        for (setting, setting_type, default) in SERVER_SETTINGS_DEFAULTS.iter() {
+            let name = setting.to_string();
+            assert!(names.insert(name.clone()), "Duplicate setting: {}", name);
+            // Each default maps back to the same variant
+            assert_eq!(&Setting::parse(&name).unwrap(), setting);
+            assert!(!SERVER_SETTINGS_DEPRICATED.contains(setting));
+
+            // The default value matches the type of the setting
+            match setting_type {
+                SettingType::Toggle => assert!(
+                    *default == "enabled" || *default == "disabled",
+                    "{} is not a toggle",
+                    name
+                ),
+                SettingType::Boolean => assert!(
+                    *default == "true" || *default == "false",
+                    "{} is not a boolean",
+                    name
+                ),
+                SettingType::Statistics => {
+                    assert!(
+                        default.parse::<u64>().is_ok(),
+                        "{} is not a statistic",
+                        name
+                    );
+                    assert!(setting.namespace().is_some(), "{} has no namespace", name);
+                }
+                SettingType::Integer => {
+                    assert!(default.parse::<i64>().is_ok(), "{} is not an integer", name)
+                }
+                SettingType::Float => {
+                    assert!(default.parse::<f64>().is_ok(), "{} is not a float", name)
+                }
+                SettingType::Regenerate | SettingType::Delete => {
+                    panic!("{} can not have a default value", name)
+                }
+                SettingType::SetString | SettingType::String | SettingType::Datetime => {}
+            }
+        }
+    }
 }
@@ -5,7 +5,10 @@ use log::{debug, error, warn};
 use serde::{Deserialize, Serialize};
 
 pub mod keys;
-pub use keys::{Setting, SERVER_SETTINGS_DEFAULTS};
+pub mod projects;
+pub mod secrets;
+pub use keys::{Setting, SettingNamespace, SERVER_SETTINGS_DEFAULTS, SERVER_SETTINGS_SECRETS};
+pub use projects::{ProjectSetting, ProjectSettings};
 
 /// Setting Type
 #[derive(Data, Debug, Default, Clone, PartialEq)]
@@ -84,7 +87,7 @@ impl ServerSettings {
         ServerSettings::create_table(connection).await?;
 
         for (name, typ, value) in Self::defaults() {
-            match ServerSettings::fetch_by_name(connection, name.to_string()).await {
+            match ServerSettings::fetch_by_name(connection, name).await {
                 Ok(mut setting) => {
                     // Update setting type in case it has changed in newer versions
                     if setting.setting_type != typ {
@@ -95,13 +98,14 @@ impl ServerSettings {
                 }
                 Err(geekorm::Error::SerdeError(e)) => {
                     error!("Error fetching setting: `{}` ({})", name, e);
-                    return Err(crate::KonarrError::UnknownError(
-                        "Error fetching setting".to_string(),
-                    ));
+                    return Err(crate::KonarrError::InvalidData(format!(
+                        "Error fetching setting `{}`",
+                        name
+                    )));
                 }
                 Err(e) => {
                     debug!("Creating setting: `{}` ({})", name, e);
-                    let mut setting = ServerSettings::new(name, typ, value);
+                    let mut setting = ServerSettings::new(name, typ, value).encrypted()?;
                     setting.save(connection).await?;
                 }
             };
@@ -139,42 +143,54 @@ impl ServerSettings {
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        Ok(ServerSettings::query(
+        ServerSettings::query(
             connection,
             ServerSettings::query_select()
                 .where_ne("setting_type", SettingType::Statistics)
                 .build()?,
         )
-        .await?)
+        .await?
+        .into_iter()
+        .map(Self::decrypted)
+        .collect()
     }
 
-    /// Update Statistic Setting
-    pub async fn update_statistic<'a, T>(
+    /// Update Statistic Setting computed at a point in time
+    ///
+    /// The statistic is created or updated in a single statement, the value is only
+    /// written if no newer value was stored in the meantime (the last writer check on
+    /// `updated_at`) so a slow, stale computation can not overwrite a newer one.
+    pub async fn update_statistic_at<'a, T>(
         connection: &'a T,
         name: Setting,
         value: i64,
+        computed_at: chrono::DateTime<chrono::Utc>,
     ) -> Result<(), crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        match ServerSettings::fetch_by_name(connection, &name).await {
-            Ok(mut setting) => {
-                if value != setting.value.parse().unwrap_or(0) {
-                    debug!(
-                        "Updating statistic: {:?} = {} (was {})",
-                        name, value, setting.value
-                    );
-                    setting.value = value.to_string();
-                    setting.update(connection).await?;
-                }
-            }
-            Err(_) => {
-                let mut setting =
-                    ServerSettings::new(name, SettingType::Statistics, value.to_string());
-                setting.save(connection).await?;
-            }
-        }
-        Ok(())
+        debug!("Updating statistic: {:?} = {}", name, value);
+        crate::models::with_retries(|| async {
+            let mut values = Values::new();
+            values.push("name".to_string(), name);
+            values.push("setting_type".to_string(), SettingType::Statistics);
+            values.push("value".to_string(), value.to_string());
+            values.push("updated_at".to_string(), computed_at);
+            T::execute::<Self>(
+                connection,
+                crate::models::raw_query(
+                    "INSERT INTO ServerSettings (name, setting_type, value, updated_at) \
+                    VALUES (?, ?, ?, ?) \
+                    ON CONFLICT(name) DO UPDATE SET \
+                        value = excluded.value, updated_at = excluded.updated_at \
+                    WHERE ServerSettings.updated_at <= excluded.updated_at;",
+                    values,
+                ),
+            )
+            .await?;
+            Ok(())
+        })
+        .await
     }
 
     /// Set the Setting
@@ -197,37 +213,44 @@ impl ServerSettings {
     }
 
     /// Fetch the Setting by Name
+    ///
+    /// Unknown settings (names which are not a [Setting]) are an error.
     pub async fn get<'a, T>(
         connection: &'a T,
-        name: impl Into<String>,
+        name: impl Into<Setting>,
     ) -> Result<Self, crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        Ok(Self::fetch_by_name(connection, name.into()).await?)
+        let name = name.into();
+        if name == Setting::Unknown {
+            return Err(crate::KonarrError::InvalidData(
+                "Unknown setting".to_string(),
+            ));
+        }
+        Self::fetch_by_name(connection, name).await?.decrypted()
     }
 
-    /// Fetch the Setting by Namespace
+    /// Fetch the Settings in a Namespace
     pub async fn get_namespace<'a, T>(
         connection: &'a T,
-        name: impl Into<String>,
+        namespace: SettingNamespace,
     ) -> Result<Vec<Self>, crate::KonarrError>
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        let mut namespace = name.into();
-        if !namespace.ends_with('.') {
-            namespace.push('.');
-        }
-        log::debug!("Fetching settings in namespace: `{}%`", namespace);
+        log::debug!("Fetching settings in namespace: `{}%`", namespace.prefix());
 
-        Ok(Self::query(
+        Self::query(
             connection,
             Self::query_select()
-                .where_like("name", format!("{}%", namespace))
+                .where_like("name", format!("{}%", namespace.prefix()))
                 .build()?,
         )
-        .await?)
+        .await?
+        .into_iter()
+        .map(Self::decrypted)
+        .collect()
     }
 
     /// Get all Statistics Settings
@@ -244,7 +267,7 @@ impl ServerSettings {
         .await?)
     }
 
-    /// Fetch the Setting by Name as a Boolean
+    /// Fetch the Setting by Name as a Boolean (unknown settings are an error)
     pub async fn get_bool<'a, T>(
         connection: &'a T,
         name: impl Into<Setting>,
@@ -252,9 +275,7 @@ impl ServerSettings {
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        Ok(Self::fetch_by_name(connection, name.into())
-            .await?
-            .boolean())
+        Ok(Self::get(connection, name).await?.boolean())
     }
 
     /// Set and update the Setting
@@ -267,10 +288,104 @@ impl ServerSettings {
         T: GeekConnection<Connection = T> + 'a,
     {
         self.set(value.into());
-        self.update(connection).await?;
+        self.store(connection).await
+    }
+
+    /// Update the Setting (secret settings are encrypted when an encryption key is set)
+    pub async fn store<'a, T>(&mut self, connection: &'a T) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        self.encrypted()?.update(connection).await?;
         Ok(())
     }
 
+    /// Setting as stored in the database (the value of secret settings is encrypted)
+    fn encrypted(&self) -> Result<Self, crate::KonarrError> {
+        self.encrypted_with(secrets::encryption_key().as_ref())
+    }
+
+    fn encrypted_with(
+        &self,
+        key: Option<&secrets::EncryptionKey>,
+    ) -> Result<Self, crate::KonarrError> {
+        let mut setting = self.clone();
+        if self.name.is_secret() && !self.value.is_empty() {
+            // Plain text values can look encrypted (`enc:v1:`), only valid ciphertext is kept
+            if let Some(key) = key.filter(|key| key.decrypt(&self.value).is_err()) {
+                setting.value = key.encrypt(&self.value)?;
+            }
+        }
+        Ok(setting)
+    }
+
+    /// Decrypt the value of the Setting (values in plain text are kept as is)
+    fn decrypted(self) -> Result<Self, crate::KonarrError> {
+        self.decrypted_with(secrets::encryption_key().as_ref())
+    }
+
+    fn decrypted_with(
+        mut self,
+        key: Option<&secrets::EncryptionKey>,
+    ) -> Result<Self, crate::KonarrError> {
+        if secrets::is_encrypted(&self.value) {
+            let key = key.ok_or_else(|| {
+                crate::KonarrError::EncryptionError(format!(
+                    "The setting `{}` is encrypted but no encryption key is set (`KONARR_SERVER_ENCRYPTION_KEY`)",
+                    self.name
+                ))
+            })?;
+            self.value = key.decrypt(&self.value)?;
+        }
+        Ok(self)
+    }
+
+    /// Check the secret settings can be decrypted
+    ///
+    /// Returns the number of secret settings still stored in plain text. Encrypted
+    /// settings without (or with the wrong) encryption key are an error.
+    pub async fn verify_secrets<'a, T>(connection: &'a T) -> Result<usize, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut plaintext = 0;
+        for name in SERVER_SETTINGS_SECRETS {
+            if let Ok(setting) = Self::fetch_by_name(connection, name).await {
+                if !setting.value.is_empty() && !secrets::is_encrypted(&setting.value) {
+                    plaintext += 1;
+                }
+                setting.decrypted()?;
+            }
+        }
+        Ok(plaintext)
+    }
+
+    /// Encrypt the secret settings stored in plain text (requires an encryption key)
+    ///
+    /// Returns the number of settings encrypted.
+    pub async fn encrypt_secrets<'a, T>(connection: &'a T) -> Result<usize, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        if secrets::encryption_key().is_none() {
+            return Err(crate::KonarrError::EncryptionError(
+                "No encryption key is set (`KONARR_SERVER_ENCRYPTION_KEY`)".to_string(),
+            ));
+        }
+        let mut encrypted = 0;
+        for name in SERVER_SETTINGS_SECRETS {
+            if let Ok(setting) = Self::fetch_by_name(connection, name).await {
+                let mut stored = setting.encrypted()?;
+                if stored.value != setting.value {
+                    debug!("Encrypting setting: {:?}", name);
+                    stored.update(connection).await?;
+                    encrypted += 1;
+                }
+            }
+        }
+        Ok(encrypted)
+    }
+
     /// Toggle the Setting
     pub fn toggle(&mut self) {
         self.value = match self.setting_type {
@@ -305,7 +420,7 @@ impl ServerSettings {
     where
         T: GeekConnection<Connection = T> + 'a,
     {
-        Ok(Self::get_bool(connection, "security").await?)
+        Self::get_bool(connection, Setting::Security).await
     }
 
     /// Reset the Setting to the default value
@@ -318,12 +433,131 @@ impl ServerSettings {
             .find(|(name, _, _)| name == &self.name)
         {
             self.value = default.2.to_string();
-            self.update(connection).await?;
-            Ok(())
+            self.store(connection).await
         } else {
-            Err(crate::KonarrError::UnknownError(
-                "Unknown ServerSettings default value".to_string(),
-            ))
+            Err(crate::KonarrError::NotFound(format!(
+                "Default value of the setting `{}`",
+                self.name
+            )))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn statistic(connection: &libsql::Connection, name: Setting) -> String {
+        ServerSettings::fetch_by_name(connection, name)
+            .await
+            .unwrap()
+            .value
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_statistic_last_writer() -> Result<(), crate::KonarrError> {
+        let path =
+            std::env::temp_dir().join(format!("konarr-test-statistics-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let database = std::sync::Arc::new(libsql::Builder::new_local(&path).build().await?);
+        let connection = database.connect()?;
+        crate::models::connection_init(&connection).await?;
+        crate::models::database_create(&connection).await?;
+
+        let now = chrono::Utc::now();
+        let older = now - chrono::Duration::seconds(30);
+
+        // Two tasks (with their own connection) computing the statistic at different
+        // times, the newer value wins no matter which update is written last
+        let mut writers = Vec::new();
+        for (value, computed_at) in [(5, now), (3, older)] {
+            let database = database.clone();
+            writers.push(tokio::spawn(async move {
+                let connection = database.connect()?;
+                crate::models::connection_init(&connection).await?;
+                for _ in 0..10 {
+                    ServerSettings::update_statistic_at(
+                        &connection,
+                        Setting::StatsUsersTotal,
+                        value,
+                        computed_at,
+                    )
+                    .await?;
+                }
+                Ok::<_, crate::KonarrError>(())
+            }));
+        }
+        for writer in writers {
+            writer.await.expect("writer panicked")?;
+        }
+        assert_eq!(statistic(&connection, Setting::StatsUsersTotal).await, "5");
+
+        ServerSettings::update_statistic_at(&connection, Setting::StatsUsersTotal, 2, older)
+            .await?;
+        assert_eq!(statistic(&connection, Setting::StatsUsersTotal).await, "5");
+
+        let newer = now + chrono::Duration::seconds(1);
+        ServerSettings::update_statistic_at(&connection, Setting::StatsUsersTotal, 7, newer)
+            .await?;
+        assert_eq!(statistic(&connection, Setting::StatsUsersTotal).await, "7");
+
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_secrets_encryption() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+        let key = secrets::EncryptionKey::new("0123456789abcdef0123456789abcdef")?;
+
+        let mut webhook = ServerSettings::fetch_by_name(&connection, Setting::ReportsWebhook)
+            .await?
+            .decrypted_with(Some(&key))?;
+        webhook.set("https://hooks.example.com/T000/secret");
+        webhook
+            .encrypted_with(Some(&key))?
+            .update(&connection)
+            .await?;
+
+        // Encrypted at rest
+        let stored = statistic(&connection, Setting::ReportsWebhook).await;
+        assert!(secrets::is_encrypted(&stored));
+        assert!(!stored.contains("secret"));
+        let setting = ServerSettings::fetch_by_name(&connection, Setting::ReportsWebhook).await?;
+        assert_eq!(
+            setting.clone().decrypted_with(Some(&key))?.value,
+            "https://hooks.example.com/T000/secret"
+        );
+
+        // Missing or wrong key
+        assert!(matches!(
+            setting.clone().decrypted_with(None),
+            Err(crate::KonarrError::EncryptionError(_))
+        ));
+        let wrong = secrets::EncryptionKey::new("fedcba9876543210fedcba9876543210")?;
+        assert!(setting.decrypted_with(Some(&wrong)).is_err());
+
+        // Plain text values that look encrypted are still encrypted
+        webhook.set("enc:v1:not-encrypted");
+        let stored = webhook.encrypted_with(Some(&key))?;
+        assert_ne!(stored.value, "enc:v1:not-encrypted");
+        assert_eq!(
+            stored.encrypted_with(Some(&key))?.value,
+            stored.value,
+            "encrypted twice"
+        );
+        assert_eq!(
+            stored.decrypted_with(Some(&key))?.value,
+            "enc:v1:not-encrypted"
+        );
+
+        // Only the secret settings are encrypted
+        let mut registration = ServerSettings::fetch_by_name(&connection, Setting::Registration)
+            .await?
+            .encrypted_with(Some(&key))?;
+        assert_eq!(registration.value, "enabled");
+        registration.set("disabled");
+        assert_eq!(registration.encrypted_with(Some(&key))?.value, "disabled");
+        Ok(())
+    }
+}
@@ -0,0 +1,420 @@
+//! # Project Settings
+//!
+//! Per-project settings (badges, etc.) stored as key / value pairs.
+//! Settings which are not stored for a project use the defaults in [PROJECT_SETTINGS_DEFAULTS].
+
+use geekorm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::SettingType;
+
+/// Project Setting Keys
+#[derive(Data, Debug, Default, Clone, PartialEq)]
+pub enum ProjectSetting {
+    /// Badges of the project can be viewed without authentication
+    #[geekorm(key = "badges.public")]
+    BadgesPublic,
+    /// Project is listed (name and alerts severity only) on the public status page
+    #[geekorm(key = "public.listed")]
+    PublicListed,
+    /// Minimum number of hours between two scans of the container (`0` uses the
+    /// agent's scan cycle)
+    #[geekorm(key = "scan.interval_hours")]
+    ScanIntervalHours,
+    /// Critical alerts from which the project is red (empty uses the server setting)
+    #[geekorm(key = "security.health.critical.red")]
+    SecurityHealthCriticalRed,
+    /// Critical alerts from which the project is amber (empty uses the server setting)
+    #[geekorm(key = "security.health.critical.amber")]
+    SecurityHealthCriticalAmber,
+    /// High alerts from which the project is red (empty uses the server setting)
+    #[geekorm(key = "security.health.high.red")]
+    SecurityHealthHighRed,
+    /// High alerts from which the project is amber (empty uses the server setting)
+    #[geekorm(key = "security.health.high.amber")]
+    SecurityHealthHighAmber,
+    /// Medium alerts from which the project is red (empty uses the server setting)
+    #[geekorm(key = "security.health.medium.red")]
+    SecurityHealthMediumRed,
+    /// Medium alerts from which the project is amber (empty uses the server setting)
+    #[geekorm(key = "security.health.medium.amber")]
+    SecurityHealthMediumAmber,
+    /// Low alerts from which the project is red (empty uses the server setting)
+    #[geekorm(key = "security.health.low.red")]
+    SecurityHealthLowRed,
+    /// Low alerts from which the project is amber (empty uses the server setting)
+    #[geekorm(key = "security.health.low.amber")]
+    SecurityHealthLowAmber,
+
+    /// Unknown setting
+    #[default]
+    #[geekorm(key = "unknown")]
+    Unknown,
+}
+
+/// Project Settings Defaults
+pub const PROJECT_SETTINGS_DEFAULTS: [(ProjectSetting, SettingType, &str); 11] = [
+    (
+        ProjectSetting::BadgesPublic,
+        SettingType::Toggle,
+        "disabled",
+    ),
+    (
+        ProjectSetting::PublicListed,
+        SettingType::Toggle,
+        "disabled",
+    ),
+    (
+        ProjectSetting::ScanIntervalHours,
+        SettingType::SetString,
+        "0",
+    ),
+    (
+        ProjectSetting::SecurityHealthCriticalRed,
+        SettingType::SetString,
+        "",
+    ),
+    (
+        ProjectSetting::SecurityHealthCriticalAmber,
+        SettingType::SetString,
+        "",
+    ),
+    (
+        ProjectSetting::SecurityHealthHighRed,
+        SettingType::SetString,
+        "",
+    ),
+    (
+        ProjectSetting::SecurityHealthHighAmber,
+        SettingType::SetString,
+        "",
+    ),
+    (
+        ProjectSetting::SecurityHealthMediumRed,
+        SettingType::SetString,
+        "",
+    ),
+    (
+        ProjectSetting::SecurityHealthMediumAmber,
+        SettingType::SetString,
+        "",
+    ),
+    (
+        ProjectSetting::SecurityHealthLowRed,
+        SettingType::SetString,
+        "",
+    ),
+    (
+        ProjectSetting::SecurityHealthLowAmber,
+        SettingType::SetString,
+        "",
+    ),
+];
+
+/// Project Settings Table
+#[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProjectSettings {
+    /// Primary Key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+
+    /// Project the setting belongs to
+    pub project_id: i32,
+
+    /// Setting Name
+    pub name: ProjectSetting,
+    /// Setting Type
+    pub setting_type: SettingType,
+    /// Setting Value
+    pub value: String,
+
+    /// Updated At Datetime
+    #[geekorm(new = "chrono::Utc::now()", on_update = "chrono::Utc::now()")]
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ProjectSettings {
+    /// Initialise the Project Settings table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::create_table(connection).await?;
+        Ok(())
+    }
+
+    /// Default value of a setting (not stored)
+    pub fn default_for(project_id: i32, name: &ProjectSetting) -> Option<Self> {
+        PROJECT_SETTINGS_DEFAULTS
+            .iter()
+            .find(|(key, _, _)| key == name)
+            .map(|(key, setting_type, value)| {
+                Self::new(
+                    project_id,
+                    key.clone(),
+                    setting_type.clone(),
+                    value.to_string(),
+                )
+            })
+    }
+
+    /// Fetch all the settings of a project (defaults for the settings which are not stored)
+    pub async fn fetch_settings<'a, T>(
+        connection: &'a T,
+        project_id: i32,
+    ) -> Result<Vec<Self>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let stored = Self::fetch_by_project_id(connection, project_id).await?;
+
+        Ok(PROJECT_SETTINGS_DEFAULTS
+            .iter()
+            .filter_map(|(name, _, _)| {
+                stored
+                    .iter()
+                    .find(|s| &s.name == name)
+                    .cloned()
+                    .or_else(|| Self::default_for(project_id, name))
+            })
+            .collect())
+    }
+
+    /// Fetch a setting of a project (or its default)
+    pub async fn get<'a, T>(
+        connection: &'a T,
+        project_id: i32,
+        name: impl Into<ProjectSetting>,
+    ) -> Result<Self, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let name = name.into();
+        match Self::query_first(
+            connection,
+            Self::query_select()
+                .where_eq("project_id", project_id)
+                .and()
+                .where_eq("name", name.clone())
+                .build()?,
+        )
+        .await
+        {
+            Ok(setting) => Ok(setting),
+            Err(_) => Self::default_for(project_id, &name).ok_or_else(|| {
+                crate::KonarrError::NotFound(format!("Unknown project setting `{}`", name))
+            }),
+        }
+    }
+
+    /// Fetch a setting of a project as a Boolean
+    pub async fn get_bool<'a, T>(
+        connection: &'a T,
+        project_id: i32,
+        name: impl Into<ProjectSetting>,
+    ) -> Result<bool, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Self::get(connection, project_id, name).await?.boolean())
+    }
+
+    /// Fetch the projects (IDs) which enabled a setting
+    ///
+    /// Only for settings which are disabled by default (stored values only).
+    pub async fn fetch_enabled<'a, T>(
+        connection: &'a T,
+        name: impl Into<ProjectSetting>,
+    ) -> Result<Vec<i32>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(Self::query(
+            connection,
+            Self::query_select()
+                .where_eq("name", name.into())
+                .order_by("project_id", QueryOrder::Asc)
+                .build()?,
+        )
+        .await?
+        .into_iter()
+        .filter(|setting| setting.boolean())
+        .map(|setting| setting.project_id)
+        .collect())
+    }
+
+    /// Set (and store) a setting of a project
+    ///
+    /// Toggles are set to the value (`enabled` / `disabled`) instead of being flipped.
+    pub async fn set<'a, T>(
+        connection: &'a T,
+        project_id: i32,
+        name: impl Into<ProjectSetting>,
+        value: impl Into<String>,
+    ) -> Result<Self, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let mut setting = Self::get(connection, project_id, name).await?;
+        let value = value.into();
+
+        setting.value = match setting.setting_type {
+            SettingType::Toggle => match value.as_str() {
+                "enabled" | "true" | "1" => "enabled".to_string(),
+                "disabled" | "false" | "0" => "disabled".to_string(),
+                _ => {
+                    return Err(crate::KonarrError::InvalidData(format!(
+                        "Invalid value `{}` for project setting `{}`",
+                        value, setting.name
+                    )))
+                }
+            },
+            _ if setting.name == ProjectSetting::ScanIntervalHours => {
+                match value.trim().parse::<u32>() {
+                    Ok(hours) => hours.to_string(),
+                    Err(_) => {
+                        return Err(crate::KonarrError::InvalidData(format!(
+                            "Invalid value `{}` for project setting `{}` (hours)",
+                            value, setting.name
+                        )))
+                    }
+                }
+            }
+            _ if setting.is_health_threshold() => match value.trim() {
+                "" => String::new(),
+                threshold => match threshold.parse::<u32>() {
+                    Ok(threshold) => threshold.to_string(),
+                    Err(_) => {
+                        return Err(crate::KonarrError::InvalidData(format!(
+                            "Invalid value `{}` for project setting `{}` (number of alerts)",
+                            value, setting.name
+                        )))
+                    }
+                },
+            },
+            _ => value,
+        };
+
+        if i32::from(setting.id) == 0 {
+            setting.save(connection).await?;
+        } else {
+            setting.update(connection).await?;
+        }
+        Ok(setting)
+    }
+
+    /// Fetch the scan interval (hours) of a project, `None` uses the agent's scan cycle
+    pub async fn scan_interval_hours<'a, T>(
+        connection: &'a T,
+        project_id: i32,
+    ) -> Result<Option<u32>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(
+            Self::get(connection, project_id, ProjectSetting::ScanIntervalHours)
+                .await?
+                .value
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|hours| *hours > 0),
+        )
+    }
+
+    /// If the setting is a health threshold override (`security.health.*`)
+    pub fn is_health_threshold(&self) -> bool {
+        self.name.to_string().starts_with("security.health.")
+    }
+
+    /// Get the Setting as a Boolean
+    pub fn boolean(&self) -> bool {
+        self.value == "true" || self.value == "1" || self.value == "enabled"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_project_settings() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        // Defaults
+        assert!(!ProjectSettings::get_bool(&connection, 1, ProjectSetting::BadgesPublic).await?);
+        assert_eq!(
+            ProjectSettings::fetch_settings(&connection, 1).await?.len(),
+            PROJECT_SETTINGS_DEFAULTS.len()
+        );
+
+        ProjectSettings::set(&connection, 1, "badges.public", "enabled").await?;
+        assert!(ProjectSettings::get_bool(&connection, 1, ProjectSetting::BadgesPublic).await?);
+        // Other projects are not affected
+        assert!(!ProjectSettings::get_bool(&connection, 2, ProjectSetting::BadgesPublic).await?);
+
+        // Update the stored setting
+        ProjectSettings::set(&connection, 1, ProjectSetting::BadgesPublic, "false").await?;
+        let settings = ProjectSettings::fetch_by_project_id(&connection, 1).await?;
+        assert_eq!(settings.len(), 1);
+        assert_eq!(settings[0].value, "disabled");
+
+        assert!(
+            ProjectSettings::set(&connection, 1, ProjectSetting::BadgesPublic, "maybe")
+                .await
+                .is_err()
+        );
+        assert!(ProjectSettings::set(&connection, 1, "unknown.key", "x")
+            .await
+            .is_err());
+
+        // Projects opted-in to the public status page
+        ProjectSettings::set(&connection, 2, ProjectSetting::PublicListed, "enabled").await?;
+        ProjectSettings::set(&connection, 3, ProjectSetting::PublicListed, "enabled").await?;
+        ProjectSettings::set(&connection, 3, ProjectSetting::PublicListed, "disabled").await?;
+        assert_eq!(
+            ProjectSettings::fetch_enabled(&connection, ProjectSetting::PublicListed).await?,
+            vec![2]
+        );
+
+        // Scan interval overrides
+        assert_eq!(
+            ProjectSettings::scan_interval_hours(&connection, 1).await?,
+            None
+        );
+        ProjectSettings::set(&connection, 1, ProjectSetting::ScanIntervalHours, " 24").await?;
+        assert_eq!(
+            ProjectSettings::scan_interval_hours(&connection, 1).await?,
+            Some(24)
+        );
+        for value in ["-1", "daily", "1.5"] {
+            assert!(
+                ProjectSettings::set(&connection, 1, ProjectSetting::ScanIntervalHours, value)
+                    .await
+                    .is_err()
+            );
+        }
+        ProjectSettings::set(&connection, 1, ProjectSetting::ScanIntervalHours, "0").await?;
+        assert_eq!(
+            ProjectSettings::scan_interval_hours(&connection, 1).await?,
+            None
+        );
+
+        // Health threshold overrides (empty inherits the server setting)
+        let threshold =
+            ProjectSettings::set(&connection, 1, "security.health.high.red", " 12").await?;
+        assert_eq!(threshold.value, "12");
+        let threshold =
+            ProjectSettings::set(&connection, 1, ProjectSetting::SecurityHealthHighRed, "").await?;
+        assert_eq!(threshold.value, "");
+        assert!(ProjectSettings::set(
+            &connection,
+            1,
+            ProjectSetting::SecurityHealthHighRed,
+            "many"
+        )
+        .await
+        .is_err());
+        Ok(())
+    }
+}
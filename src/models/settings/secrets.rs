@@ -0,0 +1,177 @@
+//! # Settings Encryption
+//!
+//! The secret settings (see [super::keys::SERVER_SETTINGS_SECRETS]) are encrypted at rest
+//! with AES-256-GCM when an encryption key is set (`KONARR_SERVER_ENCRYPTION_KEY`), the
+//! AES key is derived from it with HKDF-SHA256.
+//!
+//! Encrypted values are stored as `enc:v1:<base64(nonce || ciphertext)>`. Values in plain
+//! text are still read, they are encrypted on the next write or with
+//! `konarr-cli database encrypt-secrets`.
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use base64::Engine;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::sync::RwLock;
+
+use crate::KonarrError;
+
+/// Prefix of the encrypted values (version of the format)
+pub const ENCRYPTED_PREFIX: &str = "enc:v1:";
+/// Minimum length of the encryption key
+pub const ENCRYPTION_KEY_MIN_LENGTH: usize = 32;
+
+/// HKDF info of the settings key (a different key per purpose)
+const HKDF_INFO: &[u8] = b"konarr.settings.v1";
+/// AES-GCM nonce length
+const NONCE_LENGTH: usize = 12;
+
+/// Encryption key used by the Server Settings (set on startup, see [init])
+static ENCRYPTION_KEY: RwLock<Option<EncryptionKey>> = RwLock::new(None);
+
+/// Settings Encryption Key
+#[derive(Clone)]
+pub struct EncryptionKey {
+    cipher: Aes256Gcm,
+}
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EncryptionKey(********)")
+    }
+}
+
+impl EncryptionKey {
+    /// Derive the encryption key from a secret (at least 32 characters)
+    pub fn new(secret: impl AsRef<[u8]>) -> Result<Self, KonarrError> {
+        let secret = secret.as_ref();
+        if secret.len() < ENCRYPTION_KEY_MIN_LENGTH {
+            return Err(KonarrError::EncryptionError(format!(
+                "The encryption key must be at least {} characters",
+                ENCRYPTION_KEY_MIN_LENGTH
+            )));
+        }
+        let mut key = [0u8; 32];
+        Hkdf::<Sha256>::new(None, secret)
+            .expand(HKDF_INFO, &mut key)
+            .map_err(|e| KonarrError::EncryptionError(e.to_string()))?;
+
+        Ok(Self {
+            cipher: Aes256Gcm::new(&key.into()),
+        })
+    }
+
+    /// Encrypt a value (`enc:v1:...`)
+    pub fn encrypt(&self, value: &str) -> Result<String, KonarrError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, value.as_bytes())
+            .map_err(|_| KonarrError::EncryptionError("Failed to encrypt value".to_string()))?;
+
+        let mut data = nonce.to_vec();
+        data.extend(ciphertext);
+        Ok(format!(
+            "{}{}",
+            ENCRYPTED_PREFIX,
+            base64::engine::general_purpose::STANDARD.encode(data)
+        ))
+    }
+
+    /// Decrypt an encrypted value
+    ///
+    /// Values encrypted with another key (or tampered with) are an error.
+    pub fn decrypt(&self, value: &str) -> Result<String, KonarrError> {
+        let data = value
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .and_then(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+            .filter(|data| data.len() > NONCE_LENGTH)
+            .ok_or_else(|| KonarrError::EncryptionError("Invalid encrypted value".to_string()))?;
+
+        let (nonce, ciphertext) = data.split_at(NONCE_LENGTH);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                KonarrError::EncryptionError(
+                    "Failed to decrypt value, is the encryption key correct?".to_string(),
+                )
+            })?;
+        Ok(String::from_utf8(plaintext)?)
+    }
+}
+
+/// Check if a value is encrypted
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Set (or unset) the encryption key of the Server Settings
+pub fn init(secret: Option<&str>) -> Result<(), KonarrError> {
+    let key = match secret.filter(|secret| !secret.is_empty()) {
+        Some(secret) => Some(EncryptionKey::new(secret)?),
+        None => None,
+    };
+    if let Ok(mut current) = ENCRYPTION_KEY.write() {
+        log::debug!("Settings encryption :: {}", key.is_some());
+        *current = key;
+    }
+    Ok(())
+}
+
+/// Encryption key of the Server Settings (if set)
+pub fn encryption_key() -> Option<EncryptionKey> {
+    ENCRYPTION_KEY.read().ok().and_then(|key| key.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn test_round_trip() {
+        let key = EncryptionKey::new(KEY).unwrap();
+        let encrypted = key.encrypt("kagent_secret").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("kagent_secret"));
+        assert_eq!(key.decrypt(&encrypted).unwrap(), "kagent_secret");
+
+        // Random nonce per value
+        assert_ne!(key.encrypt("kagent_secret").unwrap(), encrypted);
+        // Same secret, same key
+        let key = EncryptionKey::new(KEY).unwrap();
+        assert_eq!(key.decrypt(&encrypted).unwrap(), "kagent_secret");
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let key = EncryptionKey::new(KEY).unwrap();
+        let encrypted = key
+            .encrypt("https://hooks.example.com/T000/secret")
+            .unwrap();
+
+        let wrong = EncryptionKey::new("fedcba9876543210fedcba9876543210").unwrap();
+        assert!(matches!(
+            wrong.decrypt(&encrypted),
+            Err(KonarrError::EncryptionError(_))
+        ));
+
+        // Tampered or invalid values
+        let mut tampered = encrypted.clone();
+        tampered.pop();
+        tampered.push(if encrypted.ends_with('A') { 'B' } else { 'A' });
+        assert!(key.decrypt(&tampered).is_err());
+        assert!(key.decrypt("enc:v1:not-base64").is_err());
+        assert!(key.decrypt("plain").is_err());
+    }
+
+    #[test]
+    fn test_short_key() {
+        assert!(EncryptionKey::new("short").is_err());
+    }
+}
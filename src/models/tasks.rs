@@ -0,0 +1,142 @@
+//! # Task Runs
+//!
+//! History of the background task runs (duration, outcome and summary of each run).
+//! Only the last [TASK_RUNS_HISTORY] runs of each task are kept.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use geekorm::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::raw_query;
+
+/// Number of runs kept per task
+pub const TASK_RUNS_HISTORY: u32 = 50;
+
+#[derive(Debug, Deserialize)]
+struct TaskNameRow {
+    name: String,
+}
+
+/// Task Runs Model
+#[derive(Table, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TaskRuns {
+    /// Primary Key
+    #[geekorm(primary_key, auto_increment)]
+    pub id: PrimaryKey<i32>,
+
+    /// Name of the task
+    pub name: String,
+    /// If the task completed successfully
+    pub success: bool,
+    /// Duration of the run (milliseconds)
+    pub duration_ms: i32,
+    /// Summary of the run (`key=value` pairs) or the error if it failed
+    pub summary: String,
+
+    /// Time the run started
+    pub started_at: DateTime<Utc>,
+}
+
+impl TaskRuns {
+    /// Initialise the Task Runs table
+    pub async fn init<'a, T>(connection: &'a T) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Self::create_table(connection).await?;
+        Ok(())
+    }
+
+    /// Store the run and remove the runs outside of the history
+    pub async fn record<'a, T>(&mut self, connection: &'a T) -> Result<(), crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        self.save(connection).await?;
+
+        let mut values = Values::new();
+        values.push("name".to_string(), self.name.clone());
+        values.push("name".to_string(), self.name.clone());
+        values.push("limit".to_string(), TASK_RUNS_HISTORY as i32);
+        T::execute::<Self>(
+            connection,
+            raw_query(
+                "DELETE FROM TaskRuns WHERE name = ? AND id NOT IN \
+                (SELECT id FROM TaskRuns WHERE name = ? ORDER BY id DESC LIMIT ?);",
+                values,
+            ),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Fetch the last runs of every task (most recent first)
+    pub async fn fetch_history<'a, T>(
+        connection: &'a T,
+        limit: u32,
+    ) -> Result<BTreeMap<String, Vec<Self>>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let names = T::query::<TaskNameRow>(
+            connection,
+            raw_query("SELECT DISTINCT name FROM TaskRuns;", Values::new()),
+        )
+        .await?;
+
+        let mut history = BTreeMap::new();
+        for row in names {
+            let runs = Self::query(
+                connection,
+                Self::query_select()
+                    .where_eq("name", row.name.clone())
+                    .order_by("id", QueryOrder::Desc)
+                    .limit(limit as usize)
+                    .build()?,
+            )
+            .await?;
+            history.insert(row.name, runs);
+        }
+        Ok(history)
+    }
+
+    /// Number of consecutive successful runs (most recent first)
+    pub fn streak(runs: &[Self]) -> usize {
+        runs.iter().take_while(|run| run.success).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_task_runs_history() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        for index in 0..(TASK_RUNS_HISTORY + 5) {
+            let success = index != 1;
+            TaskRuns::new("alerts", success, 10, String::new(), Utc::now())
+                .record(&connection)
+                .await?;
+        }
+        TaskRuns::new("stale", false, 5, "failed".to_string(), Utc::now())
+            .record(&connection)
+            .await?;
+
+        let history = TaskRuns::fetch_history(&connection, 100).await?;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history["alerts"].len(), TASK_RUNS_HISTORY as usize);
+        assert_eq!(
+            TaskRuns::streak(&history["alerts"]),
+            TASK_RUNS_HISTORY as usize
+        );
+        assert_eq!(TaskRuns::streak(&history["stale"]), 0);
+
+        let history = TaskRuns::fetch_history(&connection, 3).await?;
+        assert_eq!(history["alerts"].len(), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+//! # Test helpers of the models
+
+use geekorm::prelude::*;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+use super::TransactionConnection;
+
+/// Connection counting the queries (statements) sent to the database
+pub(crate) struct CountingConnection {
+    inner: libsql::Connection,
+    queries: AtomicUsize,
+    statements: Mutex<Vec<String>>,
+}
+
+impl CountingConnection {
+    pub(crate) fn new(inner: libsql::Connection) -> Self {
+        Self {
+            inner,
+            queries: Default::default(),
+            statements: Default::default(),
+        }
+    }
+
+    /// Number of queries since the last call
+    pub(crate) fn take(&self) -> usize {
+        self.statements.lock().unwrap().clear();
+        self.queries.swap(0, Ordering::SeqCst)
+    }
+
+    /// Number of times a statement ran since the last [CountingConnection::take]
+    pub(crate) fn statements(&self, statement: &str) -> usize {
+        self.statements
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|sql| sql.as_str() == statement)
+            .count()
+    }
+
+    fn count(&self, query: &Query) {
+        self.queries.fetch_add(1, Ordering::SeqCst);
+        self.statements.lock().unwrap().push(query.query.clone());
+    }
+}
+
+impl GeekConnection for CountingConnection {
+    type Connection = CountingConnection;
+
+    async fn row_count(connection: &Self::Connection, query: Query) -> Result<i64, geekorm::Error> {
+        connection.count(&query);
+        <libsql::Connection as GeekConnection>::row_count(&connection.inner, query).await
+    }
+
+    async fn execute<T>(connection: &Self::Connection, query: Query) -> Result<(), geekorm::Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        connection.count(&query);
+        <libsql::Connection as GeekConnection>::execute::<T>(&connection.inner, query).await
+    }
+
+    async fn query<T>(connection: &Self::Connection, query: Query) -> Result<Vec<T>, geekorm::Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        connection.count(&query);
+        <libsql::Connection as GeekConnection>::query::<T>(&connection.inner, query).await
+    }
+
+    async fn query_first<T>(
+        connection: &Self::Connection,
+        query: Query,
+    ) -> Result<T, geekorm::Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        connection.count(&query);
+        <libsql::Connection as GeekConnection>::query_first::<T>(&connection.inner, query).await
+    }
+}
+
+impl TransactionConnection for CountingConnection {
+    type Connection = CountingConnection;
+    type Guard<'a> = &'a CountingConnection;
+
+    async fn exclusive(&self) -> Self::Guard<'_> {
+        self
+    }
+}
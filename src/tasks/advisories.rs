@@ -1,8 +1,17 @@
 //! # Task - Advisories
 
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
 use crate::{
     bom::{BomParser, Parsers},
-    models::{security::SecurityState, Alerts, Projects, ServerSettings, Setting},
+    models::{
+        security::{AlertKind, SecurityState, ALERTS_MATCHER_VERSION},
+        AlertEvents, Alerts, Projects, ServerSettings, Setting, Snapshot, SnapshotMetadata,
+        SnapshotMetadataKey,
+    },
     tools::{Grype, Tool},
     utils::grypedb::GrypeDatabase,
     Config, KonarrError,
@@ -28,30 +37,14 @@ where
 
     if ServerSettings::get_bool(connection, Setting::SecurityAdvisoriesPolling).await? {
         info!("Starting Advisory DB Polling");
-        match GrypeDatabase::sync(&grype_path).await {
-            Ok(new) => {
-                info!("Advisory Sync Complete");
-
-                if new {
-                    info!("New Advisory Data");
-
-                    let mut grypedb_connection = GrypeDatabase::connect(&grype_path).await?;
-                    grypedb_connection.fetch_vulnerabilities().await?;
-
-                    info!("Advisory Sync Complete");
-
-                    scan_projects(config, connection).await?;
-                }
-            }
-            Err(e) => {
-                warn!("Advisory Sync Error: {}", e);
-                reset_polling(connection).await?;
-            }
+        let Some(task) = AdvisoriesSyncTask::acquire(grype_path.clone()) else {
+            info!("Advisory DB sync is already running");
+            return Ok(());
         };
-        ServerSettings::fetch_by_name(connection, Setting::SecurityAdvisoriesUpdated)
-            .await?
-            .set_update(connection, chrono::Utc::now().to_rfc3339())
-            .await?;
+        if let Err(e) = task.sync_and_rescan(config, connection).await {
+            warn!("Advisory Sync Error: {}", e);
+            reset_polling(connection).await?;
+        }
     } else {
         debug!("Advisory Polling Disabled");
     }
@@ -82,6 +75,223 @@ where
     Ok(())
 }
 
+/// If an advisories database sync is running (see [AdvisoriesSyncTask])
+static ADVISORIES_SYNC_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Sync of the advisories (Grype) database
+///
+/// Only one sync runs at a time, the sync is released when the task is dropped.
+#[derive(Debug)]
+pub struct AdvisoriesSyncTask {
+    path: PathBuf,
+    force: bool,
+}
+
+impl AdvisoriesSyncTask {
+    /// Claim the sync of the database at the path (`None` if a sync is already running)
+    pub fn acquire(path: PathBuf) -> Option<Self> {
+        ADVISORIES_SYNC_RUNNING
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .ok()
+            .map(|_| Self { path, force: false })
+    }
+
+    /// If a sync is running
+    pub fn is_running() -> bool {
+        ADVISORIES_SYNC_RUNNING.load(Ordering::Acquire)
+    }
+
+    /// Download the latest database even if the current one is up to date
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Download the latest database build (returns if a new build was downloaded)
+    pub async fn download(&self) -> Result<bool, KonarrError> {
+        if self.force {
+            GrypeDatabase::sync_force(&self.path).await
+        } else {
+            GrypeDatabase::sync(&self.path).await
+        }
+    }
+
+    /// Download the latest database build and record the result in the settings
+    /// (see [GrypeSyncResult])
+    pub async fn run<'a, T>(&self, connection: &'a T) -> Result<bool, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let attempted_at = chrono::Utc::now();
+        let result = self.download().await;
+        let build = match &result {
+            Ok(_) => GrypeDatabase::status(&self.path).await.build,
+            Err(_) => None,
+        };
+        GrypeSyncResult::record(connection, attempted_at, &result, build).await?;
+        result
+    }
+
+    /// Sync the database and rescan the projects if a new build was downloaded
+    pub async fn sync_and_rescan<'a, T>(
+        &self,
+        config: &'a Config,
+        connection: &'a T,
+    ) -> Result<bool, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let new = self.run(connection).await?;
+        info!("Advisory Sync Complete");
+        if new {
+            info!("New Advisory Data");
+            let build = GrypeDatabase::connect(&self.path)
+                .await?
+                .fetch_grype()
+                .await?
+                .build_timestamp
+                .to_rfc3339();
+            super::instrument(connection, "rescan.grypedb", async {
+                rescan_projects(config, connection, &build)
+                    .await
+                    .map(|summary| super::TaskStats::from(&summary))
+            })
+            .await?;
+        }
+        Ok(new)
+    }
+}
+
+impl Drop for AdvisoriesSyncTask {
+    fn drop(&mut self) {
+        ADVISORIES_SYNC_RUNNING.store(false, Ordering::Release);
+    }
+}
+
+/// Outcome of an advisories database sync
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrypeSyncOutcome {
+    /// A new database build was downloaded
+    Updated,
+    /// The database was already the latest build
+    UpToDate,
+    /// The sync failed
+    Failed,
+}
+
+impl GrypeSyncOutcome {
+    /// Parse the `security.advisories.sync.result` setting
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "updated" => Some(Self::Updated),
+            "up-to-date" => Some(Self::UpToDate),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for GrypeSyncOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Updated => write!(f, "updated"),
+            Self::UpToDate => write!(f, "up-to-date"),
+            Self::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// Last advisories database sync (`security.advisories.sync.*` settings)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GrypeSyncResult {
+    /// When the sync was attempted
+    pub attempted_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Outcome of the sync
+    pub outcome: Option<GrypeSyncOutcome>,
+    /// Error of the sync (if it failed)
+    pub error: Option<String>,
+}
+
+impl GrypeSyncResult {
+    /// Load the last sync from the settings
+    pub async fn load<'a, T>(connection: &'a T) -> Result<Self, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let attempted_at =
+            ServerSettings::fetch_by_name(connection, Setting::SecurityAdvisoriesSyncAttempted)
+                .await?
+                .value;
+        let outcome =
+            ServerSettings::fetch_by_name(connection, Setting::SecurityAdvisoriesSyncResult)
+                .await?
+                .value;
+        let error = ServerSettings::fetch_by_name(connection, Setting::SecurityAdvisoriesSyncError)
+            .await?
+            .value;
+        Ok(Self {
+            attempted_at: chrono::DateTime::parse_from_rfc3339(&attempted_at)
+                .ok()
+                .map(|at| at.with_timezone(&chrono::Utc)),
+            outcome: GrypeSyncOutcome::parse(&outcome),
+            error: Some(error).filter(|e| !e.is_empty()),
+        })
+    }
+
+    /// Record the result of a sync in the settings
+    ///
+    /// A successful sync also updates the `security.advisories.version` (build of the
+    /// database) and `security.advisories.updated` settings.
+    pub async fn record<'a, T>(
+        connection: &'a T,
+        attempted_at: chrono::DateTime<chrono::Utc>,
+        result: &Result<bool, KonarrError>,
+        build: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<Self, KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let (outcome, error) = match result {
+            Ok(true) => (GrypeSyncOutcome::Updated, None),
+            Ok(false) => (GrypeSyncOutcome::UpToDate, None),
+            Err(e) => (GrypeSyncOutcome::Failed, Some(e.to_string())),
+        };
+        for (setting, value) in [
+            (
+                Setting::SecurityAdvisoriesSyncAttempted,
+                attempted_at.to_rfc3339(),
+            ),
+            (Setting::SecurityAdvisoriesSyncResult, outcome.to_string()),
+            (
+                Setting::SecurityAdvisoriesSyncError,
+                error.clone().unwrap_or_default(),
+            ),
+        ] {
+            ServerSettings::fetch_by_name(connection, setting)
+                .await?
+                .set_update(connection, value)
+                .await?;
+        }
+        if outcome != GrypeSyncOutcome::Failed {
+            ServerSettings::fetch_by_name(connection, Setting::SecurityAdvisoriesUpdated)
+                .await?
+                .set_update(connection, attempted_at.to_rfc3339())
+                .await?;
+            if let Some(build) = build {
+                ServerSettings::fetch_by_name(connection, Setting::SecurityAdvisoriesVersion)
+                    .await?
+                    .set_update(connection, build.to_string())
+                    .await?;
+            }
+        }
+        Ok(Self {
+            attempted_at: Some(attempted_at),
+            outcome: Some(outcome),
+            error,
+        })
+    }
+}
+
 /// Scan for security alerts
 pub async fn scan<'a, T>(config: &'a Config, connection: &'a T) -> Result<(), KonarrError>
 where
@@ -93,112 +303,319 @@ where
         scan_projects(config, connection).await?;
         Ok(())
     } else {
-        Err(KonarrError::UnknownError(
+        Err(KonarrError::Disabled(
             "Advisories Polling is disabled".to_string(),
         ))
     }
 }
 
+/// Number of Grype scans run at the same time when rescanning the projects
+pub const RESCAN_CONCURRENCY: usize = 2;
+
+/// Summary of a projects scan
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RescanSummary {
+    /// Snapshots scanned
+    pub scanned: usize,
+    /// Snapshots skipped (already scanned with the build, alerts from tools or no SBOM)
+    pub skipped: usize,
+    /// Snapshots which failed to scan
+    pub failed: usize,
+}
+
+impl From<&RescanSummary> for super::TaskStats {
+    fn from(summary: &RescanSummary) -> Self {
+        Self::from_iter([
+            ("scanned", summary.scanned),
+            ("skipped", summary.skipped),
+            ("failed", summary.failed),
+        ])
+    }
+}
+
 /// Scan every project for security alerts
 pub async fn scan_projects<'a, T>(config: &'a Config, connection: &'a T) -> Result<(), KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    // Record the build of the database used (if available)
+    let build = match config.grype_connection().await {
+        Ok(grype) => grype
+            .fetch_grype()
+            .await
+            .ok()
+            .map(|id| id.build_timestamp.to_rfc3339()),
+        Err(_) => None,
+    };
+    scan_snapshots(config, connection, build.as_deref(), false).await?;
+    Ok(())
+}
+
+/// Rescan the latest snapshot of every project after a new advisories database build
+///
+/// Snapshots already scanned with the build and the current matcher version
+/// (`security.scan.grype_build` and `security.scan.matcher_version` metadata) are
+/// skipped. Disabled by the `security.rescan_on_db_update` setting.
+pub async fn rescan_projects<'a, T>(
+    config: &'a Config,
+    connection: &'a T,
+    build: &str,
+) -> Result<RescanSummary, KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    if !ServerSettings::get_bool(connection, Setting::SecurityRescanOnDbUpdate).await? {
+        info!("Rescanning projects on advisories database updates is disabled");
+        return Ok(RescanSummary::default());
+    }
+    info!(
+        "Rescanning projects with advisories database build: {}",
+        build
+    );
+    scan_snapshots(config, connection, Some(build), true).await
+}
+
+/// Scan the latest snapshot of every project with Grype
+///
+/// Grype runs for up to [RESCAN_CONCURRENCY] snapshots at the same time, the
+/// results are stored one snapshot at a time.
+async fn scan_snapshots<'a, T>(
+    config: &'a Config,
+    connection: &'a T,
+    build: Option<&str>,
+    skip_scanned: bool,
+) -> Result<RescanSummary, KonarrError>
 where
     T: GeekConnection<Connection = T> + 'a,
 {
     info!("Scanning projects snapshots for security alerts");
+    let mut summary = RescanSummary::default();
 
     let mut projects = Projects::fetch_all(connection).await?;
     info!("Projects Count: {}", projects.len());
 
+    // Snapshots (and their SBOM) to scan
+    let mut targets: Vec<(Projects, Snapshot, PathBuf)> = Vec::new();
+
     for project in projects.iter_mut() {
         debug!("Project: {}", project.name);
-        if let Some(mut snapshot) = project.fetch_latest_snapshot(connection).await? {
-            debug!("Snapshot: {} :: {}", snapshot.id, snapshot.components.len());
-            snapshot.fetch_metadata(connection).await?;
+        let Some(mut snapshot) = project.fetch_latest_snapshot(connection).await? else {
+            warn!("No snapshots for project: {}", project.name);
+            continue;
+        };
+        debug!("Snapshot: {} :: {}", snapshot.id, snapshot.components.len());
+        snapshot.fetch_metadata(connection).await?;
 
-            // Fetch the alerts for the snapshot (previously stored)
-            let mut alerts = Alerts::fetch_by_snapshot_id(connection, snapshot.id).await?;
+        if skip_scanned && build.is_some() && ScanProvenance::from(&snapshot).is_current(build) {
+            debug!("Snapshot({}) already scanned with the build", snapshot.id);
+            summary.skipped += 1;
+            continue;
+        }
 
-            if let Some(tool_alerts) = snapshot.find_metadata("security.tools.alerts") {
-                if tool_alerts.as_bool() {
-                    // If the `tool alerts` setting is disabled, we
-                    if ServerSettings::get_bool(connection, Setting::SecurityToolsAlerts).await? {
-                        info!(
-                        "Project('{}', snapshot = '{}', components = '{}', vulnerabilities = '{}')",
+        if let Some(tool_alerts) = snapshot.find_metadata("security.tools.alerts") {
+            if tool_alerts.as_bool() {
+                // If the `tool alerts` setting is disabled, we
+                if ServerSettings::get_bool(connection, Setting::SecurityToolsAlerts).await? {
+                    info!(
+                        "Project('{}', snapshot = '{}', components = '{}')",
                         project.name,
                         snapshot.id,
                         snapshot.components.len(),
-                        alerts.len()
                     );
-                        info!("Security Alerts coming from tools, skipping");
-                        continue;
-                    } else {
-                        info!(
-                            "Security Tools Alerts setting is disabled, scanning project for security alerts"
-                        );
-                    }
+                    info!("Security Alerts coming from tools, skipping");
+                    summary.skipped += 1;
+                    continue;
+                } else {
+                    info!(
+                        "Security Tools Alerts setting is disabled, scanning project for security alerts"
+                    );
                 }
             }
+        }
 
-            let mut results = Vec::new();
+        if let Some(bom_path) = snapshot.find_metadata("bom.path") {
+            let full_path = config.sboms_path()?.join(bom_path.as_string());
+            if !full_path.exists() {
+                warn!("SBOM does not exist: {}", full_path.display());
+                summary.skipped += 1;
+                continue;
+            }
+            targets.push((project.clone(), snapshot, full_path));
+        } else {
+            // TODO: Should we write the SBOM to disk?
+            log::warn!(
+                "No SBOM path found for `{}`, skipping scanning of `{}`",
+                snapshot.id,
+                project.name,
+            );
+            // results = GrypeDatabase::matcher(connection, grypedb, &mut snapshot).await?;
+            let mut alerts = Alerts::fetch_by_snapshot_id(connection, snapshot.id).await?;
+            for alert in alerts
+                .iter_mut()
+                .filter(|a| a.kind() == AlertKind::Vulnerability)
+            {
+                alert.close(connection).await?;
+            }
+            AlertEvents::reconcile(connection, project.id.into(), snapshot.id.into()).await?;
+            summary.skipped += 1;
+        }
+    }
 
-            if let Some(bom_path) = snapshot.find_metadata("bom.path") {
-                let full_path = config.sboms_path()?.join(bom_path.as_string());
-                if !full_path.exists() {
-                    warn!("SBOM does not exist: {}", full_path.display());
-                    continue;
-                }
-                log::info!("Using Grype to scan SBOM: {}", full_path.display());
-
-                let config = Grype::init().await;
-                log::debug!("Grype Config: {:?}", config);
-
-                let bom = Grype::run(&config, full_path.display().to_string()).await?;
-                let sbom = Parsers::parse(bom.as_bytes())?;
-                log::debug!(
-                    "BillOfMaterials(comps='{}', vulns='{}')",
-                    sbom.components.len(),
-                    sbom.vulnerabilities.len()
-                );
-
-                for vuln in sbom.vulnerabilities.iter() {
-                    log::trace!("Vulnerability: {:?}", vuln);
-                    let alts = Alerts::from_bom_vulnerability(connection, &snapshot, vuln).await?;
-                    results.extend(alts);
-                }
-            } else {
-                // TODO: Should we write the SBOM to disk?
-                log::warn!(
-                    "No SBOM path found for `{}`, skipping scanning of `{}`",
-                    snapshot.id,
-                    project.name,
-                );
-                // results = GrypeDatabase::matcher(connection, grypedb, &mut snapshot).await?;
-                for alert in alerts.iter_mut() {
-                    alert.close(connection).await?;
-                }
+    let grype = Grype::init().await;
+    log::debug!("Grype Config: {:?}", grype);
+    let total = targets.len();
+
+    for (index, batch) in targets.chunks(RESCAN_CONCURRENCY).enumerate() {
+        let mut scans = tokio::task::JoinSet::new();
+        for (position, (_, snapshot, path)) in batch.iter().enumerate() {
+            // Each scan needs its own output file
+            let mut grype = grype.clone();
+            grype.output = grype
+                .output
+                .with_file_name(format!("grype-snapshot-{}.json", snapshot.id));
+            let path = path.display().to_string();
+
+            scans.spawn(async move {
+                log::info!("Using Grype to scan SBOM: {}", path);
+                let result = Grype::run(&grype, path).await;
+                tokio::fs::remove_file(&grype.output).await.ok();
+                (position, result)
+            });
+        }
+
+        let mut results: Vec<Option<Result<String, KonarrError>>> =
+            (0..batch.len()).map(|_| None).collect();
+        while let Some(joined) = scans.join_next().await {
+            match joined {
+                Ok((position, result)) => results[position] = Some(result),
+                Err(e) => warn!("Grype scan task failed: {}", e),
             }
+        }
 
-            // Find all the alerts that are not in results
-            for alert in alerts.iter_mut() {
-                if !results.iter().any(|r| r.id == alert.id) {
-                    debug!("Marking Alert as Resolved: {}", alert.id);
-                    alert.state = SecurityState::Secure;
-                    alert.update(connection).await?;
+        for ((project, snapshot, _), result) in batch.iter().zip(results) {
+            match result {
+                Some(Ok(bom)) => {
+                    store_scan(connection, project, snapshot, &bom, build).await?;
+                    summary.scanned += 1;
                 }
+                Some(Err(e)) => {
+                    warn!("Failed to scan snapshot({}): {}", snapshot.id, e);
+                    summary.failed += 1;
+                }
+                None => summary.failed += 1,
             }
+        }
+        info!(
+            "Scan progress: {}/{} snapshots",
+            (index * RESCAN_CONCURRENCY + batch.len()).min(total),
+            total
+        );
+    }
 
-            info!(
-                "Project('{}', snapshot = '{}', components = '{}', vulnerabilities = '{}')",
-                project.name,
-                snapshot.id,
-                snapshot.components.len(),
-                results.len()
-            );
-        } else {
-            warn!("No snapshots for project: {}", project.name);
+    Ok(summary)
+}
+
+/// Advisories database build and matcher version a snapshot was last scanned with
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ScanProvenance {
+    /// Build of the advisories (Grype) database (`security.scan.grype_build`)
+    pub grype_build: Option<String>,
+    /// Version of the matcher (`security.scan.matcher_version`)
+    pub matcher_version: Option<i32>,
+}
+
+impl ScanProvenance {
+    /// Scanned with the build and the current matcher version ([ALERTS_MATCHER_VERSION])
+    pub fn is_current(&self, build: Option<&str>) -> bool {
+        self.grype_build.as_deref() == build && self.matcher_version == Some(ALERTS_MATCHER_VERSION)
+    }
+}
+
+impl From<&Snapshot> for ScanProvenance {
+    fn from(snapshot: &Snapshot) -> Self {
+        Self {
+            grype_build: snapshot
+                .find_metadata("security.scan.grype_build")
+                .map(|b| b.as_string()),
+            matcher_version: snapshot
+                .find_metadata("security.scan.matcher_version")
+                .and_then(|v| v.as_string().parse().ok()),
+        }
+    }
+}
+
+/// Store the results of a Grype scan (SBOM with vulnerabilities) for a snapshot
+async fn store_scan<'a, T>(
+    connection: &'a T,
+    project: &Projects,
+    snapshot: &Snapshot,
+    bom: &str,
+    build: Option<&str>,
+) -> Result<(), KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    // Fetch the alerts for the snapshot (previously stored)
+    let mut alerts = Alerts::fetch_by_snapshot_id(connection, snapshot.id).await?;
+
+    let sbom = Parsers::parse(bom.as_bytes())?;
+    log::debug!(
+        "BillOfMaterials(comps='{}', vulns='{}')",
+        sbom.components.len(),
+        sbom.vulnerabilities.len()
+    );
+
+    let mut results = Vec::new();
+    for vuln in sbom.vulnerabilities.iter() {
+        log::trace!("Vulnerability: {:?}", vuln);
+        let alts = Alerts::from_bom_vulnerability(connection, snapshot, vuln).await?;
+        results.extend(alts);
+    }
+
+    for alert in results.iter_mut() {
+        alert
+            .set_provenance(connection, build, ALERTS_MATCHER_VERSION)
+            .await?;
+    }
+
+    // Find all the alerts that are not in results
+    for alert in alerts.iter_mut() {
+        // Policy and end-of-life findings are not from the scanner
+        if alert.kind() != AlertKind::Vulnerability {
+            continue;
+        }
+        if !results.iter().any(|r| r.id == alert.id) {
+            debug!("Marking Alert as Resolved: {}", alert.id);
+            alert.state = SecurityState::Secure;
+            alert.update(connection).await?;
         }
     }
+    AlertEvents::reconcile(connection, project.id.into(), snapshot.id.into()).await?;
+
+    if let Some(build) = build {
+        SnapshotMetadata::update_or_create(
+            connection,
+            snapshot.id,
+            &SnapshotMetadataKey::SecurityScanGrypeBuild,
+            build,
+        )
+        .await?;
+    }
+    SnapshotMetadata::update_or_create(
+        connection,
+        snapshot.id,
+        &SnapshotMetadataKey::SecurityScanMatcherVersion,
+        ALERTS_MATCHER_VERSION.to_string(),
+    )
+    .await?;
 
+    info!(
+        "Project('{}', snapshot = '{}', components = '{}', vulnerabilities = '{}')",
+        project.name,
+        snapshot.id,
+        snapshot.components.len(),
+        results.len()
+    );
     Ok(())
 }
 
@@ -213,3 +630,188 @@ where
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        security::{Advisories, AdvisorySource, SecuritySeverity},
+        ProjectType,
+    };
+
+    #[tokio::test]
+    async fn test_sync_result() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        // Never synced
+        assert_eq!(
+            GrypeSyncResult::load(&connection).await?,
+            GrypeSyncResult::default()
+        );
+
+        let attempted_at = chrono::Utc::now();
+        let build = chrono::DateTime::parse_from_rfc3339("2026-10-01T04:12:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        GrypeSyncResult::record(&connection, attempted_at, &Ok(true), Some(build)).await?;
+        let result = GrypeSyncResult::load(&connection).await?;
+        assert_eq!(result.outcome, Some(GrypeSyncOutcome::Updated));
+        assert_eq!(
+            result.attempted_at.map(|at| at.timestamp()),
+            Some(attempted_at.timestamp())
+        );
+        assert_eq!(result.error, None);
+        assert_eq!(
+            ServerSettings::fetch_by_name(&connection, Setting::SecurityAdvisoriesVersion)
+                .await?
+                .value,
+            build.to_string()
+        );
+
+        // A failed sync keeps the version of the last build
+        GrypeSyncResult::record(
+            &connection,
+            attempted_at,
+            &Err(KonarrError::NotFound("listing".to_string())),
+            None,
+        )
+        .await?;
+        let result = GrypeSyncResult::load(&connection).await?;
+        assert_eq!(result.outcome, Some(GrypeSyncOutcome::Failed));
+        assert!(result.error.unwrap().contains("listing"));
+        assert_eq!(
+            ServerSettings::fetch_by_name(&connection, Setting::SecurityAdvisoriesVersion)
+                .await?
+                .value,
+            build.to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_task_exclusive() {
+        let path = PathBuf::from("/tmp/konarr-grypedb");
+        let task = AdvisoriesSyncTask::acquire(path.clone()).unwrap();
+        assert!(AdvisoriesSyncTask::is_running());
+        assert!(AdvisoriesSyncTask::acquire(path.clone()).is_none());
+
+        drop(task);
+        assert!(!AdvisoriesSyncTask::is_running());
+        assert!(AdvisoriesSyncTask::acquire(path).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rescan_projects() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+        let config = Config::default();
+
+        // Already scanned with the build
+        let mut scanned = Projects::new("scanned", ProjectType::Container);
+        scanned.save(&connection).await?;
+        let mut snapshot = Snapshot::create(&connection).await?;
+        scanned.add_snapshot(&connection, snapshot.clone()).await?;
+        snapshot
+            .set_metadata(&connection, "security.scan.grype_build", "build-1")
+            .await?;
+        snapshot
+            .set_metadata(
+                &connection,
+                "security.scan.matcher_version",
+                &ALERTS_MATCHER_VERSION.to_string(),
+            )
+            .await?;
+
+        // No SBOM stored (alerts are closed)
+        let mut missing = Projects::new("missing", ProjectType::Container);
+        missing.save(&connection).await?;
+        let snapshot = Snapshot::create(&connection).await?;
+        missing.add_snapshot(&connection, snapshot.clone()).await?;
+        let mut advisory =
+            Advisories::new("CVE-0001", AdvisorySource::Unknown, SecuritySeverity::High);
+        advisory.save(&connection).await?;
+        let mut alert = Alerts::new("CVE-0001", snapshot.id, advisory.id);
+        alert.save(&connection).await?;
+
+        let summary = rescan_projects(&config, &connection, "build-1").await?;
+        assert_eq!(
+            summary,
+            RescanSummary {
+                scanned: 0,
+                skipped: 2,
+                failed: 0
+            }
+        );
+        let alert = Alerts::fetch_by_primary_key(&connection, alert.id).await?;
+        assert_eq!(alert.state, SecurityState::Secure);
+
+        // Opt out
+        ServerSettings::fetch_by_name(&connection, Setting::SecurityRescanOnDbUpdate)
+            .await?
+            .set_update(&connection, "disabled")
+            .await?;
+        assert_eq!(
+            rescan_projects(&config, &connection, "build-2").await?,
+            RescanSummary::default()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scan_provenance() -> Result<(), KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("debian", ProjectType::Container);
+        project.save(&connection).await?;
+        let mut snapshot = Snapshot::create(&connection).await?;
+        project.add_snapshot(&connection, snapshot.clone()).await?;
+        let mut dependency = crate::models::Dependencies::from_purl(
+            &connection,
+            "pkg:deb/debian/openssl@3.0.11-1".to_string(),
+        )
+        .await?;
+        dependency.snapshot_id = snapshot.id.into();
+        dependency.save(&connection).await?;
+
+        let bom = r#"{
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.6",
+            "components": [{
+                "bom-ref": "openssl",
+                "type": "library",
+                "name": "openssl",
+                "version": "3.0.11-1",
+                "purl": "pkg:deb/debian/openssl@3.0.11-1"
+            }],
+            "vulnerabilities": [{
+                "bom-ref": "CVE-2023-5678",
+                "id": "CVE-2023-5678",
+                "ratings": [{ "severity": "high" }],
+                "affects": [{ "ref": "pkg:deb/debian/openssl@3.0.11-1" }]
+            }]
+        }"#;
+
+        for build in ["2026-10-01T04:12:00+00:00", "2026-10-02T04:12:00+00:00"] {
+            store_scan(&connection, &project, &snapshot, bom, Some(build)).await?;
+
+            snapshot.fetch_metadata(&connection).await?;
+            let provenance = ScanProvenance::from(&snapshot);
+            assert_eq!(provenance.grype_build.as_deref(), Some(build));
+            assert_eq!(provenance.matcher_version, Some(ALERTS_MATCHER_VERSION));
+            assert!(provenance.is_current(Some(build)));
+
+            let alerts = Alerts::fetch_by_snapshot_id(&connection, snapshot.id).await?;
+            assert_eq!(alerts.len(), 1);
+            assert_eq!(alerts[0].grype_build.as_deref(), Some(build));
+            assert_eq!(alerts[0].matcher_version, Some(ALERTS_MATCHER_VERSION));
+        }
+        assert!(!ScanProvenance::from(&snapshot).is_current(Some("2026-10-01T04:12:00+00:00")));
+
+        // Scanned by an older matcher
+        snapshot
+            .set_metadata(&connection, "security.scan.matcher_version", "0")
+            .await?;
+        snapshot.fetch_metadata(&connection).await?;
+        assert!(!ScanProvenance::from(&snapshot).is_current(Some("2026-10-02T04:12:00+00:00")));
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+//! # Task - Agent Versions
+//!
+//! Flags the projects last scanned by an agent (and the agent tokens used by agents)
+//! which are more than `agent.version.max-minor-gap` minor versions behind the server.
+use geekorm::prelude::*;
+use log::{debug, warn};
+
+use crate::{
+    models::{
+        AgentTokenState, AgentTokens, ProjectStatus, Projects, ServerSettings, Setting,
+        SnapshotMetadataKey,
+    },
+    utils::version::is_outdated,
+    KONARR_VERSION,
+};
+
+/// Agent versions summary
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AgentVersionsSummary {
+    /// Projects last scanned by an outdated agent
+    pub projects: i64,
+    /// Active agent tokens used by an outdated agent
+    pub agents: i64,
+}
+
+impl From<&AgentVersionsSummary> for super::TaskStats {
+    fn from(summary: &AgentVersionsSummary) -> Self {
+        Self::from_iter([("projects", summary.projects), ("agents", summary.agents)])
+    }
+}
+
+/// Maximum number of minor versions an agent can be behind the server
+pub async fn agent_max_minor_gap<'a, T>(connection: &'a T) -> u64
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    ServerSettings::fetch_by_name(connection, Setting::AgentVersionMaxMinorGap)
+        .await
+        .ok()
+        .and_then(|setting| setting.value.trim().parse().ok())
+        .unwrap_or(2)
+}
+
+/// Agent versions task
+///
+/// Sets `scan.agent.outdated` on the latest snapshot of each active project and stores
+/// the totals in the `stats.projects.agent-outdated` / `stats.agents.outdated` statistics.
+pub async fn agent_versions<'a, T>(
+    connection: &'a T,
+) -> Result<AgentVersionsSummary, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let computed_at = chrono::Utc::now();
+    debug!("Task - Checking the agent versions");
+    let gap = agent_max_minor_gap(connection).await;
+    let mut summary = AgentVersionsSummary::default();
+
+    let projects = Projects::query(
+        connection,
+        Projects::query_select()
+            .where_eq("status", ProjectStatus::Active)
+            .build()?,
+    )
+    .await?;
+    for project in projects.iter() {
+        let Some(mut latest) = project.fetch_latest_snapshot(connection).await? else {
+            continue;
+        };
+        latest.fetch_metadata(connection).await?;
+
+        let outdated = latest
+            .metadata
+            .get(&SnapshotMetadataKey::ScanAgentVersion)
+            .map(|version| is_outdated(&version.as_string(), KONARR_VERSION, gap))
+            .unwrap_or(false);
+        if outdated {
+            warn!(
+                "Project `{}` was scanned by an outdated agent (server v{})",
+                project.name, KONARR_VERSION
+            );
+            summary.projects += 1;
+        }
+
+        let current = latest
+            .metadata
+            .get(&SnapshotMetadataKey::ScanAgentOutdated)
+            .map(|m| m.as_bool());
+        if current != Some(outdated) && (outdated || current.is_some()) {
+            latest
+                .set_metadata(
+                    connection,
+                    SnapshotMetadataKey::ScanAgentOutdated,
+                    &outdated.to_string(),
+                )
+                .await?;
+        }
+    }
+
+    let tokens = AgentTokens::query(
+        connection,
+        AgentTokens::query_select()
+            .where_eq("state", AgentTokenState::Active)
+            .build()?,
+    )
+    .await?;
+    summary.agents = tokens
+        .iter()
+        .filter(|token| {
+            token
+                .agent_version
+                .as_ref()
+                .is_some_and(|version| is_outdated(version, KONARR_VERSION, gap))
+        })
+        .count() as i64;
+
+    ServerSettings::update_statistic_at(
+        connection,
+        Setting::StatsProjectsAgentOutdated,
+        summary.projects,
+        computed_at,
+    )
+    .await?;
+    ServerSettings::update_statistic_at(
+        connection,
+        Setting::StatsAgentsOutdated,
+        summary.agents,
+        computed_at,
+    )
+    .await?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ProjectType, Snapshot};
+
+    #[tokio::test]
+    async fn test_agent_versions() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+        ServerSettings::fetch_by_name(&connection, Setting::AgentVersionMaxMinorGap)
+            .await?
+            .set_update(&connection, "0")
+            .await?;
+
+        let server = crate::utils::version::parse_version(KONARR_VERSION).unwrap();
+        let outdated = "0.1.0".to_string();
+        let newer = format!("{}.0.0", server.major + 1);
+
+        let mut project = Projects::new("homelab/web", ProjectType::Container);
+        project.save(&connection).await?;
+        let mut snapshot = Snapshot::create(&connection).await?;
+        snapshot
+            .set_metadata(
+                &connection,
+                SnapshotMetadataKey::ScanAgentVersion,
+                &outdated,
+            )
+            .await?;
+        project.add_snapshot(&connection, snapshot).await?;
+
+        let (mut token, _) = AgentTokens::create(&connection, "homelab", None, None).await?;
+        token
+            .touch(&connection, Some(format!("{}-dev", KONARR_VERSION)))
+            .await?;
+        let (mut token, _) = AgentTokens::create(&connection, "nas", None, None).await?;
+        token.touch(&connection, Some(outdated.clone())).await?;
+        let (mut token, _) = AgentTokens::create(&connection, "next", None, None).await?;
+        token.touch(&connection, Some(newer)).await?;
+
+        let summary = agent_versions(&connection).await?;
+        assert_eq!(summary.projects, 1);
+        assert_eq!(summary.agents, 1);
+        let mut latest = project.fetch_latest_snapshot(&connection).await?.unwrap();
+        latest.fetch_metadata(&connection).await?;
+        assert!(latest
+            .metadata
+            .get(&SnapshotMetadataKey::ScanAgentOutdated)
+            .is_some_and(|m| m.as_bool()));
+
+        // Agent upgraded
+        latest
+            .set_metadata(
+                &connection,
+                SnapshotMetadataKey::ScanAgentVersion,
+                KONARR_VERSION,
+            )
+            .await?;
+        assert_eq!(agent_versions(&connection).await?.projects, 0);
+        latest.fetch_metadata(&connection).await?;
+        assert!(latest
+            .metadata
+            .get(&SnapshotMetadataKey::ScanAgentOutdated)
+            .is_some_and(|m| !m.as_bool()));
+        Ok(())
+    }
+}
@@ -1,11 +1,14 @@
 //! Security Alerts Tasks
 
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap};
 
 use crate::models::{
-    dependencies::snapshots::AlertsSummary, security::SecuritySeverity, settings::Setting,
-    ProjectType, Projects, ServerSettings,
+    dependencies::snapshots::AlertsSummary,
+    security::{EolConfig, PolicyConfig, ProjectHealth, SecuritySeverity},
+    settings::{Setting, SettingNamespace},
+    AlertEvents, AlertIgnoreRules, ProjectType, Projects, ServerSettings, VexStatements,
 };
+use crate::utils::eol::EolSchedule;
 use geekorm::prelude::*;
 use log::{debug, info};
 
@@ -20,33 +23,69 @@ where
     }
     info!("Task - Running Alert Calculator");
 
+    let released = AlertIgnoreRules::release_expired(connection).await?;
+    if released != 0 {
+        info!("Expired ignore rules released `{}` alerts", released);
+    }
+
     let mut summary = AlertsSummary::new();
     let mut total = 0;
+    let policy = PolicyConfig::load(connection).await?;
+    let eol = EolConfig::load(connection).await?;
+    let schedule = EolSchedule::current();
 
     let mut projects =
         Projects::fetch_project_type(connection, ProjectType::Container, 1_000, 0).await?;
-    let mut project_summaries: HashMap<i32, AlertsSummary> = HashMap::new();
+    // Latest snapshot of each project and the summary of each snapshot (a snapshot can be
+    // shared by projects tracking the same image, it is only counted once)
+    let mut project_snapshots: HashMap<i32, i32> = HashMap::new();
+    let mut snapshot_summaries: HashMap<i32, AlertsSummary> = HashMap::new();
+    let mut snapshot_health: HashMap<i32, ProjectHealth> = HashMap::new();
 
     for project in projects.iter_mut() {
         if let Some(mut snapshot) = project.fetch_latest_snapshot(connection).await? {
             debug!("Project('{}', snapshot='{}')", project.name, snapshot.id);
-
-            let snap_summary = snapshot.calculate_alerts_summary(connection).await?;
-            for (key, value) in snap_summary.iter() {
-                *summary.entry(key.clone()).or_insert(0) += value;
-                total += value;
+            let snapshot_id: i32 = snapshot.id.into();
+            project_snapshots.insert(project.id.into(), snapshot_id);
+
+            if let Entry::Vacant(entry) = snapshot_summaries.entry(snapshot_id) {
+                policy.apply(connection, &mut snapshot).await?;
+                eol.apply(connection, &schedule, &snapshot).await?;
+                VexStatements::apply(connection, &snapshot).await?;
+                let snap_summary = snapshot.calculate_alerts_summary(connection).await?;
+                for (key, value) in snap_summary.iter() {
+                    *summary.entry(key.clone()).or_insert(0) += value;
+                    total += value;
+                }
+                entry.insert(snap_summary);
+                snapshot_health.insert(snapshot_id, snapshot.health());
+            } else {
+                debug!("Snapshot('{}') is shared, already counted", snapshot.id);
             }
 
-            project_summaries.insert(project.id.into(), snap_summary);
+            // Record the detected / resolved advisories in the alert timeline
+            let events =
+                AlertEvents::reconcile(connection, project.id.into(), snapshot.id.into()).await?;
+            if events != 0 {
+                debug!("Project('{}') :: {} alert events", project.name, events);
+            }
         }
     }
 
-    calculate_group_alerts(connection, &projects, &project_summaries).await?;
+    calculate_group_alerts(
+        connection,
+        &projects,
+        &project_snapshots,
+        &snapshot_summaries,
+        &snapshot_health,
+    )
+    .await?;
 
     debug!("Calculating Global Alerts Summary");
     debug!("Global Summary: {:?}", summary);
 
-    let mut global_alerts = ServerSettings::get_namespace(connection, "security.alerts").await?;
+    let mut global_alerts =
+        ServerSettings::get_namespace(connection, SettingNamespace::SecurityAlerts).await?;
     let mut total_check = 0;
 
     for galert in global_alerts.iter_mut() {
@@ -73,15 +112,23 @@ where
     }
 
     debug!("Global Alerts Updated");
+    super::summaries_changed();
 
     Ok(())
 }
 
 /// Calculate Group Alerts
+///
+/// `project_snapshots` maps the projects to their latest snapshot and `snapshot_summaries`
+/// the snapshots to their alerts summary, children sharing a snapshot are counted once.
+/// The health of a group is the worst of its own alerts and the health of its children
+/// (`snapshot_health`).
 pub async fn calculate_group_alerts<T>(
     connection: &T,
     projects: &Vec<Projects>,
-    project_summaries: &HashMap<i32, AlertsSummary>,
+    project_snapshots: &HashMap<i32, i32>,
+    snapshot_summaries: &HashMap<i32, AlertsSummary>,
+    snapshot_health: &HashMap<i32, ProjectHealth>,
 ) -> Result<(), crate::KonarrError>
 where
     T: GeekConnection<Connection = T> + Send + Sync + 'static,
@@ -106,23 +153,33 @@ where
             let mut group_summary = AlertsSummary::new();
             let mut group_total = 0;
 
-            let children: Vec<AlertsSummary> = projects
+            let mut children: Vec<i32> = projects
                 .iter()
                 .filter(|p| p.parent == group_id)
-                .filter_map(|c| project_summaries.get(&c.id.into()))
-                .cloned()
+                .filter_map(|c| project_snapshots.get(&c.id.into()))
+                .copied()
                 .collect();
+            children.sort();
+            children.dedup();
 
-            for child in children.iter() {
+            for child in children.iter().filter_map(|id| snapshot_summaries.get(id)) {
                 for (key, value) in child.iter() {
                     *group_summary.entry(key.clone()).or_insert(0) += value;
                     group_total += value;
                 }
             }
+            let children_health: Vec<ProjectHealth> = children
+                .iter()
+                .filter_map(|id| snapshot_health.get(id))
+                .copied()
+                .collect();
 
             snapshot
                 .calculate_alerts(connection, &group_summary)
                 .await?;
+            snapshot
+                .calculate_health(connection, &group_summary, &children_health)
+                .await?;
         }
     }
     Ok(())
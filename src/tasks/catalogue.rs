@@ -2,45 +2,205 @@
 use geekorm::prelude::*;
 
 use crate::{
-    models::{Component, ComponentType},
+    models::{settings::keys::Setting, Component, ComponentTags, ComponentType, ServerSettings},
     utils::catalogue::Catalogue,
 };
 
+/// Number of components loaded per batch
+const CATALOGUE_BATCH_SIZE: usize = 500;
+
+/// Catalogue task summary
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CatalogueSummary {
+    /// Total number of components checked
+    pub total: usize,
+    /// Components which had their type changed
+    pub reclassified: usize,
+    /// Components which are still a library or unknown
+    pub unclassified: usize,
+    /// Tags assigned from the catalogue
+    pub tagged: usize,
+}
+
+impl CatalogueSummary {
+    /// Percentage of the components which are classified
+    pub fn coverage(&self) -> i64 {
+        if self.total == 0 {
+            return 100;
+        }
+        (((self.total - self.unclassified) * 100) / self.total) as i64
+    }
+}
+
+impl From<&CatalogueSummary> for super::TaskStats {
+    fn from(summary: &CatalogueSummary) -> Self {
+        Self::from_iter([
+            ("total", summary.total),
+            ("reclassified", summary.reclassified),
+            ("unclassified", summary.unclassified),
+            ("tagged", summary.tagged),
+        ])
+    }
+}
+
 /// Catalogue the components task
-pub async fn catalogue<'a, T>(connection: &'a T, force: bool) -> Result<(), crate::KonarrError>
+///
+/// Components are classified using the catalogue (`data.yml`) first and then the name
+/// based fallback. Only components which changed are written back to the database.
+/// The catalogue tags are added to the components (existing tags are never removed).
+pub async fn catalogue<'a, T>(
+    connection: &'a T,
+    force: bool,
+) -> Result<CatalogueSummary, crate::KonarrError>
 where
     T: GeekConnection<Connection = T> + 'a,
 {
+    let computed_at = chrono::Utc::now();
     log::info!("Starting Catalogue Task");
     let catalogue = Catalogue::new();
+    let mut summary = CatalogueSummary::default();
+
+    let mut offset = 0;
+    loop {
+        let comps = Component::query(
+            connection,
+            Component::query_select()
+                .order_by("id", QueryOrder::Asc)
+                .limit(CATALOGUE_BATCH_SIZE)
+                .offset(offset)
+                .build()?,
+        )
+        .await?;
+        if comps.is_empty() {
+            break;
+        }
+        log::debug!(
+            "Checking component types for `{}` Components (offset {})",
+            comps.len(),
+            offset
+        );
+        offset += comps.len();
 
-    let mut counter = 0;
-    let mut comps = Component::fetch_all(connection).await?;
-    log::debug!("Checking component types for `{}` Components", comps.len());
+        for mut comp in comps {
+            summary.total += 1;
 
-    for mut comp in comps.iter_mut() {
-        if !force {
-            match comp.component_type {
-                ComponentType::Unknown | ComponentType::Library | ComponentType::Application => {
-                    if catalogue.catalogue(&mut comp)? {
-                        log::info!("Updating component_type: {}", comp.component_type);
+            let check = force
+                || matches!(
+                    comp.component_type,
+                    ComponentType::Unknown | ComponentType::Library | ComponentType::Application
+                );
+            if check {
+                if let Some(ctype) = catalogue.classify(&comp) {
+                    if ctype != comp.component_type {
+                        log::info!(
+                            "Updating component_type: {} ({} -> {})",
+                            comp.purl(),
+                            comp.component_type,
+                            ctype
+                        );
+                        comp.component_type = ctype;
                         comp.update(connection).await?;
-                        counter += 1;
+                        summary.reclassified += 1;
                     }
                 }
-                _ => {}
             }
-        } else {
-            if catalogue.catalogue(&mut comp)? {
-                log::info!("Updating component_type: {}", comp.component_type);
-                comp.update(connection).await?;
-                counter += 1;
+
+            if matches!(
+                comp.component_type,
+                ComponentType::Library | ComponentType::Unknown
+            ) {
+                summary.unclassified += 1;
+            }
+
+            for tag in catalogue.tags(&comp) {
+                let tag = ComponentTags::normalize(&tag)?;
+                if ComponentTags::insert(connection, comp.id.into(), tag).await? {
+                    summary.tagged += 1;
+                }
             }
         }
     }
-    if counter != 0 {
-        log::info!("Updated `{}` component out of `{}`", counter, comps.len());
+
+    if summary.reclassified != 0 {
+        log::info!(
+            "Updated `{}` component out of `{}`",
+            summary.reclassified,
+            summary.total
+        );
     }
+    log::info!(
+        "Catalogue coverage: {}% (`{}` unclassified)",
+        summary.coverage(),
+        summary.unclassified
+    );
+
+    ServerSettings::update_statistic_at(
+        connection,
+        Setting::CatalogueCoveragePercent,
+        summary.coverage(),
+        computed_at,
+    )
+    .await?;
+    ServerSettings::update_statistic_at(
+        connection,
+        Setting::CatalogueReclassified,
+        summary.reclassified as i64,
+        computed_at,
+    )
+    .await?;
+    ServerSettings::update_statistic_at(
+        connection,
+        Setting::CatalogueUnclassified,
+        summary.unclassified as i64,
+        computed_at,
+    )
+    .await?;
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ComponentManager;
 
-    Ok(())
+    #[tokio::test]
+    async fn test_catalogue_coverage() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        for name in ["libzstd1", "libpq5", "libfoo", "tar"] {
+            // Inserted as libraries (without the name based classification)
+            let mut comp = Component::new(ComponentManager::Deb, name.to_string());
+            comp.component_type = ComponentType::Library;
+            comp.save(&connection).await?;
+        }
+
+        let summary = catalogue(&connection, false).await?;
+        assert_eq!(summary.reclassified, 3);
+        // `libfoo` is the only unclassified component
+        assert_eq!(summary.unclassified, 1);
+
+        let comps = Component::fetch_all(&connection).await?;
+        let ctype = |name: &str| {
+            comps
+                .iter()
+                .find(|c| c.name == name)
+                .map(|c| c.component_type.clone())
+        };
+        assert_eq!(ctype("libzstd1"), Some(ComponentType::CompressionLibrary));
+        assert_eq!(ctype("libpq5"), Some(ComponentType::Database));
+
+        let coverage =
+            ServerSettings::fetch_by_name(&connection, Setting::CatalogueCoveragePercent).await?;
+        assert_eq!(coverage.value, summary.coverage().to_string());
+
+        // Second run does not rewrite anything
+        let summary = catalogue(&connection, false).await?;
+        assert_eq!(summary.reclassified, 0);
+
+        let top = Component::top_unclassified(&connection, 10).await?;
+        assert_eq!(top, vec![("libfoo".to_string(), 0)]);
+
+        Ok(())
+    }
 }
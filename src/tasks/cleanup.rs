@@ -0,0 +1,121 @@
+//! # Task - Cleanup
+//!
+//! Removes the data which is outside of the configured retention
+//! (alert timeline events older than `security.events.retention` days)
+//! the expired user sessions, the snapshots outside of the project retention
+//! policies and the stored SBOMs outside of their retention (see [`super::retention`]).
+//! Snapshots stuck processing (the server stopped during an upload) are marked as failed.
+use geekorm::prelude::*;
+use log::{debug, info};
+
+use crate::{
+    models::{AlertEvents, ServerSettings, Sessions, Setting, Snapshot, TransactionConnection},
+    Config,
+};
+
+/// Cleanup summary
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CleanupSummary {
+    /// Number of alert events removed
+    pub alert_events: u64,
+    /// Number of expired sessions cleaned up
+    pub sessions: u64,
+    /// Number of snapshots removed from projects (retention policies)
+    pub snapshots: u64,
+    /// Number of stored SBOMs removed (the snapshots are kept)
+    pub sbom_blobs: u64,
+    /// Number of snapshots stuck processing marked as failed
+    pub stuck_snapshots: u64,
+}
+
+impl From<&CleanupSummary> for super::TaskStats {
+    fn from(summary: &CleanupSummary) -> Self {
+        Self::from_iter([
+            ("alert_events", summary.alert_events),
+            ("sessions", summary.sessions),
+            ("snapshots", summary.snapshots),
+            ("sbom_blobs", summary.sbom_blobs),
+            ("stuck_snapshots", summary.stuck_snapshots),
+        ])
+    }
+}
+
+/// Cleanup task
+pub async fn cleanup<'a, T>(
+    config: &'a Config,
+    connection: &'a T,
+) -> Result<CleanupSummary, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + TransactionConnection + 'a,
+{
+    debug!("Task - Cleanup");
+    let mut summary = CleanupSummary {
+        sessions: Sessions::cleanup(connection, config.sessions()).await?,
+        ..Default::default()
+    };
+    if summary.sessions != 0 {
+        info!("Cleaned up {} expired sessions", summary.sessions);
+    }
+
+    let retention: i64 =
+        ServerSettings::fetch_by_name(connection, Setting::SecurityEventsRetention)
+            .await?
+            .value
+            .parse()
+            .unwrap_or_default();
+    if retention > 0 {
+        let before = chrono::Utc::now() - chrono::Duration::days(retention);
+        summary.alert_events = AlertEvents::prune(connection, before).await?;
+        if summary.alert_events != 0 {
+            info!(
+                "Removed {} alert events older than {} days",
+                summary.alert_events, retention
+            );
+        }
+    } else {
+        debug!("Alert events retention is disabled");
+    }
+
+    summary.snapshots = super::retention::retention(config, connection).await?;
+    summary.sbom_blobs = super::retention::sbom_retention(config, connection).await?;
+    summary.stuck_snapshots = Snapshot::fail_stuck(connection).await?;
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{security::AlertEventKind, AlertEvents};
+
+    #[tokio::test]
+    async fn test_cleanup() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+        let config = Config::default();
+
+        let old = chrono::Utc::now() - chrono::Duration::days(400);
+        for (advisory, event) in [
+            (1, AlertEventKind::Detected),
+            (1, AlertEventKind::Resolved),
+            (2, AlertEventKind::Detected),
+        ] {
+            let mut item = AlertEvents::new(1, advisory, event, "system");
+            item.created_at = old;
+            item.save(&connection).await?;
+        }
+
+        // Only the old events which are not the latest of an advisory are removed
+        assert_eq!(cleanup(&config, &connection).await?.alert_events, 1);
+
+        ServerSettings::fetch_by_name(&connection, Setting::SecurityEventsRetention)
+            .await?
+            .set_update(&connection, "0")
+            .await?;
+        let mut item = AlertEvents::new(1, 2, AlertEventKind::Resolved, "system");
+        item.created_at = old;
+        item.save(&connection).await?;
+        assert_eq!(cleanup(&config, &connection).await?.alert_events, 0);
+
+        Ok(())
+    }
+}
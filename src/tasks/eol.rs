@@ -0,0 +1,131 @@
+//! # Task - End-of-life
+//!
+//! Refreshes the end-of-life schedules from the endoflife.date API. Requests are
+//! conditional (unchanged products are served from the HTTP cache), the schedules are
+//! cached on disk (`eol.json` in the data path) and the bundled copy is used when the
+//! server is offline or the refresh is disabled (`security.eol.refresh`).
+use geekorm::prelude::*;
+
+use crate::{
+    models::{settings::keys::Setting, ServerSettings},
+    utils::eol::{EolSchedule, EOL_API_URL},
+    Config,
+};
+
+/// Name of the end-of-life schedules cache file (in the data path)
+pub const EOL_CACHE_FILE: &str = "eol.json";
+
+/// End-of-life task summary
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EolSummary {
+    /// Number of products in the schedules
+    pub products: usize,
+    /// Products which changed
+    pub updated: usize,
+    /// Products which failed to refresh (the previous schedules are kept)
+    pub failed: usize,
+    /// If the refresh is disabled (cached / bundled schedules only)
+    pub offline: bool,
+}
+
+impl From<&EolSummary> for super::TaskStats {
+    fn from(summary: &EolSummary) -> Self {
+        Self::from_iter([
+            ("products", summary.products.to_string()),
+            ("updated", summary.updated.to_string()),
+            ("failed", summary.failed.to_string()),
+            ("offline", summary.offline.to_string()),
+        ])
+    }
+}
+
+/// End-of-life schedules task
+///
+/// The refreshed schedules replace the process wide schedules (see [EolSchedule::current]).
+pub async fn eol<'a, T>(
+    config: &Config,
+    connection: &'a T,
+) -> Result<EolSummary, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let path = config.data_path()?.join(EOL_CACHE_FILE);
+    let mut schedule = EolSchedule::load(&path);
+    let mut summary = EolSummary {
+        products: schedule.products().len(),
+        ..Default::default()
+    };
+
+    if ServerSettings::get_bool(connection, Setting::SecurityEolRefresh)
+        .await
+        .unwrap_or(true)
+    {
+        log::info!("Refreshing the end-of-life schedules");
+        refresh(&mut schedule, EOL_API_URL, &mut summary).await;
+        if summary.updated != 0 {
+            schedule.save(&path)?;
+        }
+    } else {
+        log::debug!("End-of-life refresh is disabled, using the cached schedules");
+        summary.offline = true;
+    }
+
+    EolSchedule::set_current(schedule);
+    Ok(summary)
+}
+
+/// Refresh every product of the schedules from the API
+async fn refresh(schedule: &mut EolSchedule, base: &str, summary: &mut EolSummary) {
+    for product in schedule.products() {
+        match schedule.fetch_product(base, &product).await {
+            Ok(true) => summary.updated += 1,
+            Ok(false) => log::debug!("End-of-life schedule `{}` not modified", product),
+            Err(e) => {
+                log::warn!(
+                    "Failed to refresh the `{}` end-of-life schedule :: {}",
+                    product,
+                    e
+                );
+                summary.failed += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        eol::{EolCycle, EolDate},
+        http::tests::serve,
+    };
+
+    #[tokio::test]
+    async fn test_eol_refresh() {
+        let (url, handle) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\n\r\n[{\"cycle\": \"3.20\", \"eol\": \"2026-04-01\", \"latest\": \"3.20.3\"}]".to_string(),
+            "HTTP/1.1 500 Internal Server Error\r\nConnection: close\r\n\r\n".to_string(),
+        ])
+        .await;
+
+        let mut schedule = EolSchedule::default();
+        let cycles = vec![EolCycle {
+            cycle: "1".to_string(),
+            eol: EolDate::Flag(false),
+        }];
+        schedule.update_product("alpine", cycles.clone());
+        schedule.update_product("debian", cycles);
+
+        let mut summary = EolSummary::default();
+        refresh(&mut schedule, &url, &mut summary).await;
+        assert_eq!(summary.updated, 1);
+        assert_eq!(summary.failed, 1);
+        // The failed product keeps the previous schedule
+        assert!(schedule.lookup("alpine", "3.20.1").is_some());
+        assert!(schedule.lookup("debian", "1.0").is_some());
+
+        let requests = handle.await.unwrap();
+        assert!(requests[0].starts_with("get /alpine.json"));
+        assert!(requests[1].starts_with("get /debian.json"));
+    }
+}
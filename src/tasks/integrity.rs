@@ -0,0 +1,505 @@
+//! # Task - Integrity
+//!
+//! Verifies the stored SBOMs match their `bom.sha` metadata and finds rows which reference
+//! missing rows (`Dependencies` → `Component` / `ComponentVersion` / `Snapshot` and
+//! `Alerts` → `Advisories`) and projects which only differ by their normalized name
+//! (created before the project names were normalized, e.g. `MyHost` and `myhost`).
+//!
+//! Issues are only reported unless `repair` is set, in which case the metadata is
+//! re-derived from the stored SBOM, orphaned rows are removed and duplicate projects are
+//! merged into the project which already has the normalized name (or the oldest project).
+use std::collections::HashMap;
+
+use geekorm::prelude::*;
+use log::{info, warn};
+use serde::Deserialize;
+
+use crate::{
+    models::{
+        raw_query, settings::keys::Setting, Dependencies, ProjectMerge, Projects, ServerSettings,
+        SnapshotMetadata, SnapshotMetadataKey, TransactionConnection,
+    },
+    Config,
+};
+
+/// Kind of integrity issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrityIssueKind {
+    /// The `bom.sha` metadata does not match the stored SBOM (or is missing)
+    ShaMismatch,
+    /// The stored SBOM file is missing
+    MissingSbom,
+    /// Dependency references a missing component, version or snapshot
+    OrphanedDependency,
+    /// Alert references a missing advisory
+    OrphanedAlert,
+    /// Project has the same normalized name as another project
+    DuplicateProject,
+}
+
+impl std::fmt::Display for IntegrityIssueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntegrityIssueKind::ShaMismatch => write!(f, "sha-mismatch"),
+            IntegrityIssueKind::MissingSbom => write!(f, "missing-sbom"),
+            IntegrityIssueKind::OrphanedDependency => write!(f, "orphaned-dependency"),
+            IntegrityIssueKind::OrphanedAlert => write!(f, "orphaned-alert"),
+            IntegrityIssueKind::DuplicateProject => write!(f, "duplicate-project"),
+        }
+    }
+}
+
+/// Integrity issue
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntegrityIssue {
+    /// Kind of issue
+    pub kind: IntegrityIssueKind,
+    /// ID of the row (snapshot, dependency, alert or project)
+    pub id: i32,
+    /// Description of the issue
+    pub message: String,
+    /// If the issue was repaired
+    pub repaired: bool,
+}
+
+/// Integrity check report
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IntegrityReport {
+    /// Number of stored SBOMs checked
+    pub sboms_checked: u64,
+    /// Issues found
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    /// Number of repaired issues
+    pub fn repaired(&self) -> usize {
+        self.issues.iter().filter(|i| i.repaired).count()
+    }
+
+    fn push(&mut self, kind: IntegrityIssueKind, id: i32, message: String, repaired: bool) {
+        warn!("Integrity :: {} ({}) {}", kind, id, message);
+        self.issues.push(IntegrityIssue {
+            kind,
+            id,
+            message,
+            repaired,
+        });
+    }
+}
+
+impl From<&IntegrityReport> for super::TaskStats {
+    fn from(report: &IntegrityReport) -> Self {
+        Self::from_iter([
+            ("sboms", report.sboms_checked as usize),
+            ("issues", report.issues.len()),
+            ("repaired", report.repaired()),
+        ])
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OrphanedDependencyRow {
+    id: i32,
+    component: i32,
+    version: i32,
+    snapshot: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrphanedAlertRow {
+    id: i32,
+    advisory_id: i32,
+}
+
+/// Integrity check task
+pub async fn integrity<'a, T>(
+    config: &Config,
+    connection: &'a T,
+    repair: bool,
+) -> Result<IntegrityReport, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + TransactionConnection + 'a,
+{
+    let computed_at = chrono::Utc::now();
+    info!("Task - Checking data integrity (repair: {})", repair);
+    let mut report = IntegrityReport::default();
+
+    check_sboms(config, connection, repair, &mut report).await?;
+    check_dependencies(connection, repair, &mut report).await?;
+    check_alerts(connection, repair, &mut report).await?;
+    check_projects(connection, repair, &mut report).await?;
+
+    ServerSettings::update_statistic_at(
+        connection,
+        Setting::IntegrityIssues,
+        report.issues.len() as i64,
+        computed_at,
+    )
+    .await?;
+    ServerSettings::update_statistic_at(
+        connection,
+        Setting::IntegrityRepaired,
+        report.repaired() as i64,
+        computed_at,
+    )
+    .await?;
+
+    info!(
+        "Integrity check complete: {} SBOMs checked, {} issues ({} repaired)",
+        report.sboms_checked,
+        report.issues.len(),
+        report.repaired()
+    );
+    Ok(report)
+}
+
+/// Recompute the SHA256 of the stored SBOMs and compare with the `bom.sha` metadata
+async fn check_sboms<'a, T>(
+    config: &Config,
+    connection: &'a T,
+    repair: bool,
+    report: &mut IntegrityReport,
+) -> Result<(), crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let sboms_path = config.sboms_path()?;
+
+    let metadata_key = |key: SnapshotMetadataKey| async move {
+        SnapshotMetadata::query(
+            connection,
+            SnapshotMetadata::query_select()
+                .where_eq("key", key)
+                .build()?,
+        )
+        .await
+    };
+    let shas: HashMap<i32, String> = metadata_key(SnapshotMetadataKey::BomSha)
+        .await?
+        .into_iter()
+        .map(|m| (m.snapshot_id.key, m.as_string()))
+        .collect();
+
+    for path in metadata_key(SnapshotMetadataKey::BomPath).await? {
+        let snapshot_id = path.snapshot_id.key;
+        report.sboms_checked += 1;
+
+        let data = match std::fs::read(sboms_path.join(path.as_string())) {
+            Ok(data) => data,
+            Err(e) => {
+                report.push(
+                    IntegrityIssueKind::MissingSbom,
+                    snapshot_id,
+                    format!("Failed to read SBOM `{}`: {}", path.as_string(), e),
+                    false,
+                );
+                continue;
+            }
+        };
+
+        let sha = crate::bom::sha256(&data);
+        let expected = shas.get(&snapshot_id);
+        if expected != Some(&sha) {
+            if repair {
+                SnapshotMetadata::update_or_create(
+                    connection,
+                    snapshot_id,
+                    &SnapshotMetadataKey::BomSha,
+                    sha.clone(),
+                )
+                .await?;
+            }
+            report.push(
+                IntegrityIssueKind::ShaMismatch,
+                snapshot_id,
+                format!(
+                    "SBOM SHA `{}` does not match the metadata `{}`",
+                    sha,
+                    expected.cloned().unwrap_or_default()
+                ),
+                repair,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Find dependencies which reference a missing component, version or snapshot
+async fn check_dependencies<'a, T>(
+    connection: &'a T,
+    repair: bool,
+    report: &mut IntegrityReport,
+) -> Result<(), crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let rows = T::query::<OrphanedDependencyRow>(
+        connection,
+        raw_query(
+            "SELECT d.id, \
+                (c.id IS NULL) AS component, \
+                (v.id IS NULL) AS version, \
+                (s.id IS NULL) AS snapshot \
+            FROM Dependencies d \
+            LEFT JOIN Component c ON c.id = d.component_id \
+            LEFT JOIN ComponentVersion v ON v.id = d.component_version_id \
+            LEFT JOIN Snapshot s ON s.id = d.snapshot_id \
+            WHERE c.id IS NULL OR v.id IS NULL OR s.id IS NULL;",
+            Values::new(),
+        ),
+    )
+    .await?;
+
+    for row in rows.iter() {
+        let missing = [
+            (row.component, "component"),
+            (row.version, "component version"),
+            (row.snapshot, "snapshot"),
+        ]
+        .iter()
+        .filter(|(missing, _)| *missing != 0)
+        .map(|(_, name)| *name)
+        .collect::<Vec<&str>>()
+        .join(", ");
+
+        if repair {
+            delete_row(connection, "Dependencies", row.id).await?;
+        }
+        report.push(
+            IntegrityIssueKind::OrphanedDependency,
+            row.id,
+            format!("Dependency references a missing {}", missing),
+            repair,
+        );
+    }
+
+    if repair && !rows.is_empty() {
+        // Alerts for the removed dependencies are kept (but no longer matched)
+        T::execute::<Dependencies>(
+            connection,
+            raw_query(
+                "UPDATE Alerts SET dependency_id = NULL \
+                WHERE dependency_id IS NOT NULL \
+                AND dependency_id NOT IN (SELECT id FROM Dependencies);",
+                Values::new(),
+            ),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Find alerts which reference a missing advisory
+async fn check_alerts<'a, T>(
+    connection: &'a T,
+    repair: bool,
+    report: &mut IntegrityReport,
+) -> Result<(), crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let rows = T::query::<OrphanedAlertRow>(
+        connection,
+        raw_query(
+            "SELECT a.id, a.advisory_id FROM Alerts a \
+            LEFT JOIN Advisories adv ON adv.id = a.advisory_id \
+            WHERE adv.id IS NULL;",
+            Values::new(),
+        ),
+    )
+    .await?;
+
+    for row in rows {
+        if repair {
+            delete_row(connection, "Alerts", row.id).await?;
+        }
+        report.push(
+            IntegrityIssueKind::OrphanedAlert,
+            row.id,
+            format!("Alert references a missing advisory ({})", row.advisory_id),
+            repair,
+        );
+    }
+    Ok(())
+}
+
+/// Find projects with the same normalized name (merged into one project on repair)
+async fn check_projects<'a, T>(
+    connection: &'a T,
+    repair: bool,
+    report: &mut IntegrityReport,
+) -> Result<(), crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + TransactionConnection + 'a,
+{
+    for group in Projects::fetch_duplicate_names(connection).await? {
+        let Some((project, duplicates)) = group.split_first() else {
+            continue;
+        };
+        for duplicate in duplicates {
+            // Projects of another type are only reported
+            let repair = repair && duplicate.project_type == project.project_type;
+            if repair {
+                duplicate
+                    .clone()
+                    .merge_into(connection, project, &ProjectMerge::default())
+                    .await?;
+            }
+            report.push(
+                IntegrityIssueKind::DuplicateProject,
+                duplicate.id.into(),
+                format!(
+                    "Project `{}` has the same name as `{}` ({})",
+                    duplicate.name, project.name, project.id
+                ),
+                repair,
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn delete_row<'a, T>(
+    connection: &'a T,
+    table: &str,
+    id: i32,
+) -> Result<(), crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let mut values = Values::new();
+    values.push("id".to_string(), id);
+    T::execute::<Dependencies>(
+        connection,
+        raw_query(format!("DELETE FROM {} WHERE id = ?;", table), values),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        security::{AdvisorySource, SecuritySeverity},
+        Advisories, Alerts, ProjectStatus, ProjectType, Snapshot,
+    };
+
+    #[tokio::test]
+    async fn test_integrity() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut config = Config::default();
+        config.set_data_path(std::env::temp_dir().join("konarr-test-integrity"));
+        std::fs::create_dir_all(config.sboms_path()?)?;
+
+        // SBOM with a mismatched SHA and a missing SBOM
+        let mut snapshot = Snapshot::create(&connection).await?;
+        std::fs::write(config.sboms_path()?.join("a.json"), b"{}")?;
+        snapshot
+            .set_metadata(&connection, SnapshotMetadataKey::BomPath, "a.json")
+            .await?;
+        snapshot
+            .set_metadata(&connection, SnapshotMetadataKey::BomSha, "invalid")
+            .await?;
+        let mut missing = Snapshot::create(&connection).await?;
+        missing
+            .set_metadata(&connection, SnapshotMetadataKey::BomPath, "missing.json")
+            .await?;
+
+        // Dependency with a missing snapshot, alert with a missing advisory
+        // (only possible for databases created without foreign key enforcement)
+        connection.execute("PRAGMA foreign_keys = OFF;", ()).await?;
+        let mut dependency =
+            Dependencies::from_purl(&connection, "pkg:deb/debian/openssl@3.0.1".to_string())
+                .await?;
+        dependency.snapshot_id = 1000.into();
+        dependency.save(&connection).await?;
+        let mut advisory =
+            Advisories::new("CVE-0001", AdvisorySource::Unknown, SecuritySeverity::High);
+        advisory.save(&connection).await?;
+        let mut alert = Alerts::new("CVE-0001", snapshot.id, advisory.id);
+        alert.save(&connection).await?;
+        delete_row(&connection, "Advisories", advisory.id.into()).await?;
+
+        let report = integrity(&config, &connection, false).await?;
+        assert_eq!(report.sboms_checked, 2);
+        assert_eq!(report.issues.len(), 4);
+        assert_eq!(report.repaired(), 0);
+        let kinds: Vec<IntegrityIssueKind> = report.issues.iter().map(|i| i.kind).collect();
+        assert!(kinds.contains(&IntegrityIssueKind::ShaMismatch));
+        assert!(kinds.contains(&IntegrityIssueKind::MissingSbom));
+        assert!(kinds.contains(&IntegrityIssueKind::OrphanedDependency));
+        assert!(kinds.contains(&IntegrityIssueKind::OrphanedAlert));
+
+        let report = integrity(&config, &connection, true).await?;
+        assert_eq!(report.repaired(), 3);
+
+        // Only the missing SBOM can not be repaired
+        let report = integrity(&config, &connection, false).await?;
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, IntegrityIssueKind::MissingSbom);
+
+        let stat = ServerSettings::fetch_by_name(&connection, Setting::IntegrityIssues).await?;
+        assert_eq!(stat.value, "1");
+
+        std::fs::remove_dir_all(config.data_path()?).ok();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_integrity_duplicate_projects() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+        let config = Config::default();
+
+        // Projects created before the names were normalized
+        let mut host = Projects::new("MyHost", ProjectType::Server);
+        host.save(&connection).await?;
+        let mut service = Projects::new("MyHost/My Stack/Web", ProjectType::Container);
+        service.parent = host.id.into();
+        service.save(&connection).await?;
+        service
+            .add_snapshot(&connection, Snapshot::create(&connection).await?)
+            .await?;
+        // Created with the normalized names
+        let mut normalized = Projects::new("myhost", ProjectType::Server);
+        normalized.save(&connection).await?;
+        let mut compose = Projects::new("myhost/my-stack/web", ProjectType::Container);
+        compose.save(&connection).await?;
+
+        let report = integrity(&config, &connection, false).await?;
+        let duplicates: Vec<i32> = report
+            .issues
+            .iter()
+            .filter(|i| i.kind == IntegrityIssueKind::DuplicateProject)
+            .map(|i| i.id)
+            .collect();
+        // The projects with the normalized names are kept
+        assert_eq!(duplicates, vec![i32::from(host.id), i32::from(service.id)]);
+
+        let found = Projects::fetch_by_normalized_name(&connection, "  MYHOST ").await?;
+        assert_eq!(found.id, normalized.id);
+
+        let report = integrity(&config, &connection, true).await?;
+        assert_eq!(report.repaired(), 2);
+        assert!(Projects::fetch_duplicate_names(&connection)
+            .await?
+            .is_empty());
+
+        let host = Projects::fetch_by_primary_key(&connection, host.id).await?;
+        assert_eq!(host.status, ProjectStatus::Archived);
+        let mut compose = Projects::fetch_by_primary_key(&connection, compose.id).await?;
+        compose.fetch_snapshots(&connection).await?;
+        assert_eq!(compose.snapshots.len(), 1);
+        // Children of the merged project are moved
+        let service = Projects::fetch_by_primary_key(&connection, service.id).await?;
+        assert_eq!(service.parent, i32::from(normalized.id));
+
+        // Compose names are matched after the merge
+        let found = Projects::fetch_by_normalized_name(&connection, "MyHost/My Stack/Web").await?;
+        assert_eq!(found.id, compose.id);
+
+        Ok(())
+    }
+}
@@ -3,35 +3,271 @@
 use async_trait::async_trait;
 use geekorm::GeekConnection;
 use log::info;
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 use tokio::spawn;
 use tokio_schedule::Job;
 
 pub mod advisories;
+pub mod agents;
 pub mod alerts;
 pub mod catalogue;
+pub mod cleanup;
+pub mod eol;
+pub mod integrity;
+pub mod notifications;
+pub mod outbox;
+pub mod reports;
+pub mod retention;
+pub mod stale;
 pub mod statistics;
+pub mod storage;
 
-pub use advisories::sync_advisories;
+pub use advisories::{
+    sync_advisories, AdvisoriesSyncTask, GrypeSyncOutcome, GrypeSyncResult, ScanProvenance,
+};
+pub use agents::{agent_versions, AgentVersionsSummary};
 pub use alerts::alert_calculator;
-pub use catalogue::catalogue;
-pub use statistics::statistics;
+pub use catalogue::{catalogue, CatalogueSummary};
+pub use cleanup::{cleanup, CleanupSummary};
+pub use eol::{eol, EolSummary};
+pub use integrity::{integrity, IntegrityReport};
+pub use notifications::notify;
+pub use outbox::{outbox, OutboxSummary};
+pub use reports::{reports, ReportsSummary};
+pub use retention::{retention_plan, RetentionPlan, RetentionPolicy};
+pub use stale::{stale_scans, StaleSummary};
+pub use statistics::{statistics, RecomputeSummary, Statistics, StatisticsSummary};
+pub use storage::{storage, StorageSummary};
 
 use crate::{
-    models::{ServerSettings, Setting},
+    models::{ServerSettings, Setting, TaskRuns},
     Config,
 };
 
+/// Generation of the statistics and alert summaries (see [summaries_generation])
+static SUMMARIES_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Generation of the statistics and alert summaries
+///
+/// Bumped every time the statistics or the alerts are recalculated, caches of the
+/// summaries are stale when the generation changed.
+pub fn summaries_generation() -> u64 {
+    SUMMARIES_GENERATION.load(Ordering::Acquire)
+}
+
+/// Flag the statistics and alert summaries as changed (see [summaries_generation])
+pub fn summaries_changed() {
+    SUMMARIES_GENERATION.fetch_add(1, Ordering::AcqRel);
+}
+
+/// Summary of a task run (key / value pairs)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TaskStats(BTreeMap<String, String>);
+
+impl TaskStats {
+    /// Add a value to the summary
+    pub fn set(&mut self, key: impl Into<String>, value: impl ToString) -> &mut Self {
+        self.0.insert(key.into(), value.to_string());
+        self
+    }
+
+    /// Get a value from the summary
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|v| v.as_str())
+    }
+}
+
+impl<K: Into<String>, V: ToString> FromIterator<(K, V)> for TaskStats {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut stats = Self::default();
+        for (key, value) in iter {
+            stats.set(key, value);
+        }
+        stats
+    }
+}
+
+impl std::fmt::Display for TaskStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pairs: Vec<String> = self.0.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        write!(f, "{}", pairs.join(" "))
+    }
+}
+
+/// Run a task measuring the duration and recording the outcome (see [TaskRuns])
+///
+/// Failures to record the run are only logged, errors from the task are returned.
+pub async fn instrument<'a, C, F>(
+    connection: &'a C,
+    name: &str,
+    task: F,
+) -> Result<TaskStats, crate::KonarrError>
+where
+    C: GeekConnection<Connection = C> + 'a,
+    F: Future<Output = Result<TaskStats, crate::KonarrError>>,
+{
+    let started_at = chrono::Utc::now();
+    let timer = Instant::now();
+    let result = task.await;
+    let duration_ms = timer.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+    let summary = match &result {
+        Ok(stats) => {
+            info!(
+                "Task `{}` completed in {}ms :: {}",
+                name, duration_ms, stats
+            );
+            stats.to_string()
+        }
+        Err(e) => {
+            log::error!("Task `{}` failed in {}ms :: {}", name, duration_ms, e);
+            e.to_string()
+        }
+    };
+
+    let mut run = TaskRuns::new(name, result.is_ok(), duration_ms, summary, started_at);
+    if let Err(e) = run.record(connection).await {
+        log::warn!("Failed to record the `{}` task run :: {}", name, e);
+    }
+    result
+}
+
+/// If the server is in maintenance mode (`maintenance.enabled`), the scheduled tasks
+/// are paused while it is enabled
+pub async fn maintenance<'a, C>(connection: &'a C) -> bool
+where
+    C: GeekConnection<Connection = C> + 'a,
+{
+    let enabled = ServerSettings::get_bool(connection, Setting::MaintenanceEnabled)
+        .await
+        .unwrap_or(false);
+    if enabled {
+        info!("Maintenance mode is enabled, skipping the scheduled tasks");
+    }
+    enabled
+}
+
 /// Initialse background tasks
 ///
 /// Setup a timer to run every 1 minute to do the following:
+/// - Flag stale scans (container drift or no scan in `scan.freshness.days`)
+/// - Deliver the events outbox to the webhooks
+/// - Flag projects scanned by outdated agents
 /// - Calculate statistics
+///
+/// And every hour (and on startup) to collect the storage diagnostics,
+/// remove the data outside of the retention and generate the reports, and every day (and on startup) to
+/// refresh the end-of-life schedules.
+///
+/// The tasks are paused while the server is in maintenance mode (see [maintenance]).
 pub async fn init(
     config: Arc<Config>,
     database: Arc<libsql::Database>,
 ) -> Result<(), crate::KonarrError> {
     info!("Initializing Background Tasks...");
 
+    let storage_config = Arc::clone(&config);
+    let storage_database = Arc::clone(&database);
+    let storage_task = move || {
+        let connection = storage_database.connect();
+        let config = Arc::clone(&storage_config);
+        async move {
+            match connection {
+                Ok(connection) if maintenance(&connection).await => {}
+                Ok(connection) => {
+                    instrument(&connection, "storage", async {
+                        storage(&config, &connection)
+                            .await
+                            .map(|summary| TaskStats::from(&summary))
+                    })
+                    .await
+                    .ok();
+                }
+                Err(e) => log::error!("Storage Task Error :: {}", e),
+            }
+        }
+    };
+    spawn(storage_task());
+    spawn(tokio_schedule::every(1).hour().perform(storage_task));
+
+    let cleanup_config = Arc::clone(&config);
+    let cleanup_database = Arc::clone(&database);
+    let cleanup_task = move || {
+        let connection = cleanup_database.connect();
+        let config = Arc::clone(&cleanup_config);
+        async move {
+            match connection {
+                Ok(connection) if maintenance(&connection).await => {}
+                Ok(connection) => {
+                    instrument(&connection, "cleanup", async {
+                        cleanup(&config, &connection)
+                            .await
+                            .map(|summary| TaskStats::from(&summary))
+                    })
+                    .await
+                    .ok();
+                }
+                Err(e) => log::error!("Cleanup Task Error :: {}", e),
+            }
+        }
+    };
+    spawn(cleanup_task());
+    spawn(tokio_schedule::every(1).hour().perform(cleanup_task));
+
+    let reports_database = Arc::clone(&database);
+    let reports_task = move || {
+        let connection = reports_database.connect();
+        async move {
+            match connection {
+                Ok(connection) if maintenance(&connection).await => {}
+                Ok(connection) => {
+                    instrument(&connection, "reports", async {
+                        reports(&connection)
+                            .await
+                            .map(|summary| TaskStats::from(&summary))
+                    })
+                    .await
+                    .ok();
+                }
+                Err(e) => log::error!("Reports Task Error :: {}", e),
+            }
+        }
+    };
+    spawn(reports_task());
+    spawn(tokio_schedule::every(1).hour().perform(reports_task));
+
+    let eol_config = Arc::clone(&config);
+    let eol_database = Arc::clone(&database);
+    let eol_task = move || {
+        let connection = eol_database.connect();
+        let config = Arc::clone(&eol_config);
+        async move {
+            match connection {
+                Ok(connection) if maintenance(&connection).await => {}
+                Ok(connection) => {
+                    instrument(&connection, "eol", async {
+                        eol(&config, &connection)
+                            .await
+                            .map(|summary| TaskStats::from(&summary))
+                    })
+                    .await
+                    .ok();
+                }
+                Err(e) => log::error!("End-of-life Task Error :: {}", e),
+            }
+        }
+    };
+    spawn(eol_task());
+    spawn(tokio_schedule::every(1).day().perform(eol_task));
+
     let tasks = tokio_schedule::every(60).seconds().perform(move || {
         let database = Arc::clone(&database);
         let connection = database.connect().unwrap();
@@ -39,11 +275,17 @@ pub async fn init(
         log::info!("Running Background Tasks");
 
         async move {
-            match sync_advisories(&config, &connection).await {
-                Ok(_) => log::debug!("Advisories Synced"),
-                Err(e) => log::error!("Advisories Sync Error: {}", e),
+            if maintenance(&connection).await {
+                return;
             }
 
+            instrument(&connection, "advisories", async {
+                sync_advisories(&config, &connection).await?;
+                Ok(TaskStats::default())
+            })
+            .await
+            .ok();
+
             let rescan = ServerSettings::fetch_by_name(&connection, Setting::SecurityRescan)
                 .await
                 .map_err(|e| {
@@ -58,21 +300,53 @@ pub async fn init(
                         log::error!("Error resetting rescan flag: {}", e);
                     }
 
-                    if let Err(e) = advisories::scan(&config, &connection).await {
-                        log::error!("Error rescanning projects: {}", e);
-                    }
+                    instrument(&connection, "rescan", async {
+                        advisories::scan(&config, &connection).await?;
+                        Ok(TaskStats::default())
+                    })
+                    .await
+                    .ok();
                 }
             }
 
-            alert_calculator(&connection)
-                .await
-                .map_err(|e| log::error!("Task Error :: {}", e))
-                .unwrap();
+            instrument(&connection, "alerts", async {
+                alert_calculator(&connection).await?;
+                Ok(TaskStats::default())
+            })
+            .await
+            .unwrap();
 
-            statistics(&connection)
-                .await
-                .map_err(|e| log::error!("Task Error :: {}", e))
-                .unwrap();
+            instrument(&connection, "stale", async {
+                stale_scans(&connection)
+                    .await
+                    .map(|summary| TaskStats::from(&summary))
+            })
+            .await
+            .ok();
+
+            instrument(&connection, "outbox", async {
+                outbox(&connection)
+                    .await
+                    .map(|summary| TaskStats::from(&summary))
+            })
+            .await
+            .ok();
+
+            instrument(&connection, "agents", async {
+                agent_versions(&connection)
+                    .await
+                    .map(|summary| TaskStats::from(&summary))
+            })
+            .await
+            .ok();
+
+            instrument(&connection, "statistics", async {
+                statistics(&connection)
+                    .await
+                    .map(|summary| TaskStats::from(&summary))
+            })
+            .await
+            .unwrap();
         }
     });
     spawn(tasks);
@@ -81,7 +355,7 @@ pub async fn init(
 }
 
 /// Task Trait
-#[async_trait]
+#[async_trait(?Send)]
 pub trait TaskTrait<'a, C>
 where
     C: GeekConnection<Connection = C> + 'a,
@@ -93,13 +367,29 @@ where
         Ok(true)
     }
 
+    /// Name of the task (used for the task run history)
+    const NAME: &'static str;
+
     /// Run the task
     #[allow(unused_variables)]
-    async fn run(connection: &'a C) -> Result<(), crate::KonarrError>;
+    async fn run(connection: &'a C) -> Result<TaskStats, crate::KonarrError>;
 
     /// Finish / Done / Completed the tasks
     #[allow(unused_variables)]
     async fn done(connection: &'a C) -> Result<(), crate::KonarrError> {
         Ok(())
     }
+
+    /// Initialize, run and finish the task (instrumented, see [instrument])
+    async fn task(connection: &'a C) -> Result<TaskStats, crate::KonarrError> {
+        instrument(connection, Self::NAME, async {
+            if !Self::init(connection).await? {
+                return Ok(TaskStats::from_iter([("skipped", true)]));
+            }
+            let stats = Self::run(connection).await?;
+            Self::done(connection).await?;
+            Ok(stats)
+        })
+        .await
+    }
 }
@@ -0,0 +1,120 @@
+//! # Notifications
+//!
+//! Events of the background tasks (newly stale projects, ...) are added to the events
+//! outbox and delivered to the `notifications.webhook` setting by the `outbox` task
+//! (see [crate::models::outbox] for the envelope).
+use geekorm::prelude::*;
+use log::{debug, info};
+
+use crate::models::{
+    outbox::EVENT_SNAPSHOT_DIFF, EventsOutbox, ProjectSnapshots, Projects, ServerSettings, Setting,
+    Snapshot,
+};
+
+/// Queue a notification for the webhook (`notifications.webhook`)
+///
+/// The event is written to the outbox, run it in the transaction of the change which
+/// caused it. Returns `false` if the notifications are disabled (no webhook).
+pub async fn notify<'a, T>(
+    connection: &'a T,
+    event: &str,
+    text: impl Into<String>,
+    data: serde_json::Value,
+) -> Result<bool, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let webhook = ServerSettings::get(connection, Setting::NotificationsWebhook)
+        .await?
+        .value;
+    if webhook.trim().is_empty() {
+        debug!("Notifications are disabled, skipping `{}`", event);
+        return Ok(false);
+    }
+
+    info!("Queuing the `{}` notification", event);
+    EventsOutbox::enqueue(connection, event, webhook.trim(), text, &data).await?;
+    Ok(true)
+}
+
+/// Notify the dependency changes of a new snapshot (`snapshot.diff`)
+///
+/// Returns `false` if nothing changed or the notifications are disabled.
+pub async fn notify_snapshot_diff<'a, T>(
+    connection: &'a T,
+    snapshot: &Snapshot,
+) -> Result<bool, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let Some(diff) = snapshot.diff().filter(|diff| !diff.is_empty()) else {
+        return Ok(false);
+    };
+    let link = ProjectSnapshots::query_first(
+        connection,
+        ProjectSnapshots::query_select()
+            .where_eq("snapshot_id", snapshot.id)
+            .order_by("project_id", QueryOrder::Asc)
+            .build()?,
+    )
+    .await?;
+    let project = Projects::fetch_by_primary_key(connection, link.project_id).await?;
+
+    let notable = diff
+        .changes
+        .iter()
+        .take(3)
+        .map(|change| match (&change.from, &change.to) {
+            (Some(from), Some(to)) => format!("{} {} -> {}", change.name, from, to),
+            (None, Some(to)) => format!("+{} {}", change.name, to),
+            (Some(from), None) => format!("-{} {}", change.name, from),
+            (None, None) => change.name.clone(),
+        })
+        .collect::<Vec<_>>();
+    notify(
+        connection,
+        EVENT_SNAPSHOT_DIFF,
+        format!(
+            "{} changed: {} ({})",
+            project.name,
+            diff.summary(),
+            notable.join(", ")
+        ),
+        serde_json::json!({
+            "project": project.name,
+            "snapshot": snapshot.id,
+            "diff": diff,
+        }),
+    )
+    .await
+}
+
+/// Post a JSON body to a webhook, returns the status code of the response
+///
+/// Responses which are not successful are returned as the status code, the errors are
+/// the requests which could not be sent (connection, timeout, ...).
+#[cfg(feature = "client")]
+pub(crate) async fn send(
+    webhook: &str,
+    body: &serde_json::Value,
+) -> Result<u16, crate::KonarrError> {
+    let response = crate::utils::config::client_builder()
+        .user_agent(format!("Konarr/{}", crate::KONARR_VERSION))
+        .build()?
+        .post(webhook)
+        .json(body)
+        .send()
+        .await?;
+    Ok(response.status().as_u16())
+}
+
+/// Webhooks need the HTTP client (`client` feature)
+#[cfg(not(feature = "client"))]
+pub(crate) async fn send(
+    _webhook: &str,
+    _body: &serde_json::Value,
+) -> Result<u16, crate::KonarrError> {
+    Err(crate::KonarrError::Disabled(
+        "Webhooks are not available, the HTTP client is not enabled".to_string(),
+    ))
+}
@@ -0,0 +1,197 @@
+//! # Task - Events Outbox
+//!
+//! Delivers the pending events of the outbox (see [crate::models::outbox]) to their
+//! webhooks. Failed deliveries are retried with an exponential backoff until they failed
+//! `notifications.attempts` times.
+use geekorm::prelude::*;
+use log::{debug, info, warn};
+
+use crate::models::{
+    outbox::EVENT_REPORT_READY, reports::Reports, EventsOutbox, OutboxState, ServerSettings,
+    Setting,
+};
+
+/// Maximum number of events delivered per run
+const OUTBOX_BATCH: usize = 100;
+
+/// Events outbox task summary
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OutboxSummary {
+    /// Number of events delivered
+    pub delivered: u64,
+    /// Number of failed deliveries which will be retried
+    pub retrying: u64,
+    /// Number of events which reached the maximum number of attempts
+    pub failed: u64,
+}
+
+impl From<&OutboxSummary> for super::TaskStats {
+    fn from(summary: &OutboxSummary) -> Self {
+        Self::from_iter([
+            ("delivered", summary.delivered.to_string()),
+            ("retrying", summary.retrying.to_string()),
+            ("failed", summary.failed.to_string()),
+        ])
+    }
+}
+
+/// Events outbox task
+pub async fn outbox<'a, T>(connection: &'a T) -> Result<OutboxSummary, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    debug!("Task - Delivering the events outbox");
+    let mut summary = OutboxSummary::default();
+    let max_attempts = max_attempts(connection).await?;
+
+    for mut event in EventsOutbox::fetch_due(connection, chrono::Utc::now(), OUTBOX_BATCH).await? {
+        deliver(connection, &mut event, max_attempts).await?;
+        match event.state {
+            OutboxState::Delivered => summary.delivered += 1,
+            OutboxState::Failed => summary.failed += 1,
+            OutboxState::Pending => summary.retrying += 1,
+        }
+    }
+    if summary.delivered != 0 || summary.failed != 0 {
+        info!(
+            "Outbox :: {} delivered, {} retrying, {} failed",
+            summary.delivered, summary.retrying, summary.failed
+        );
+    }
+    Ok(summary)
+}
+
+/// Maximum number of delivery attempts (`notifications.attempts`)
+pub async fn max_attempts<'a, T>(connection: &'a T) -> Result<i32, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    Ok(
+        ServerSettings::get(connection, Setting::NotificationsAttempts)
+            .await?
+            .value
+            .parse::<i32>()
+            .unwrap_or(5)
+            .max(1),
+    )
+}
+
+/// Attempt to deliver an event and record the outcome
+///
+/// The errors are the database errors, a failed delivery is recorded on the event.
+pub async fn deliver<'a, T>(
+    connection: &'a T,
+    event: &mut EventsOutbox,
+    max_attempts: i32,
+) -> Result<(), crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    debug!("Outbox({}) :: delivering `{}`", event.id, event.event);
+    match super::notifications::send(&event.endpoint, &event.envelope()).await {
+        Ok(status) if (200..300).contains(&status) => {
+            event.delivered(connection, status).await?;
+            if event.event == EVENT_REPORT_READY {
+                report_delivered(connection, event).await?;
+            }
+        }
+        Ok(status) => {
+            warn!("Outbox({}) :: webhook returned {}", event.id, status);
+            event
+                .attempt_failed(
+                    connection,
+                    Some(status),
+                    format!("Webhook returned {}", status),
+                    max_attempts,
+                )
+                .await?;
+        }
+        Err(e) => {
+            warn!("Outbox({}) :: {}", event.id, e);
+            event
+                .attempt_failed(connection, None, e.to_string(), max_attempts)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Set the delivery time of the report of a `report.ready` event
+async fn report_delivered<'a, T>(
+    connection: &'a T,
+    event: &EventsOutbox,
+) -> Result<(), crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let data: serde_json::Value = serde_json::from_str(&event.data).unwrap_or_default();
+    if let Some(period) = data.get("period").and_then(|p| p.as_str()) {
+        if let Ok(mut report) = Reports::fetch_by_period(connection, period.to_string()).await {
+            report.delivered_at = event.delivered_at;
+            report.update(connection).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::http::tests::serve;
+
+    #[tokio::test]
+    async fn test_outbox_task() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let (url, handle) = serve(vec![
+            "HTTP/1.1 500 Internal Server Error\r\nConnection: close\r\n\r\n".to_string(),
+            "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n".to_string(),
+        ])
+        .await;
+        ServerSettings::get(&connection, Setting::NotificationsWebhook)
+            .await?
+            .set_update(&connection, url.as_str())
+            .await?;
+        assert!(
+            super::super::notify(
+                &connection,
+                "projects.stale",
+                "1 project(s) became stale: homelab",
+                serde_json::json!({ "projects": ["homelab"] }),
+            )
+            .await?
+        );
+
+        // Retried with a backoff
+        let summary = outbox(&connection).await?;
+        assert_eq!(summary.retrying, 1);
+        assert_eq!(outbox(&connection).await?, OutboxSummary::default());
+
+        let mut event = EventsOutbox::fetch_latest(&connection, None, 1)
+            .await?
+            .remove(0);
+        assert_eq!(event.status_code, Some(500));
+        deliver(&connection, &mut event, 5).await?;
+        assert_eq!(event.state, OutboxState::Delivered);
+        assert_eq!(event.attempts, 2);
+        assert_eq!(event.status_code, Some(204));
+
+        let requests = handle.await.unwrap();
+        assert!(requests[1].contains("\"type\":\"projects.stale\""));
+        assert!(requests[1].contains("\"version\":1"));
+
+        // Unreachable webhook, kept after the last attempt
+        let mut event = EventsOutbox::enqueue(
+            &connection,
+            "projects.stale",
+            url.as_str(),
+            "",
+            &serde_json::json!({}),
+        )
+        .await?;
+        deliver(&connection, &mut event, 1).await?;
+        assert_eq!(event.state, OutboxState::Failed);
+        assert!(event.error.is_some());
+        Ok(())
+    }
+}
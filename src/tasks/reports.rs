@@ -0,0 +1,154 @@
+//! # Task - Reports
+//!
+//! Generates the security digest of the last complete period (`reports.schedule`),
+//! queues its Markdown for the webhook (`reports.webhook`, see [crate::models::outbox])
+//! and removes the reports older than `reports.retention` days.
+//!
+//! A period is only generated once, the task can run as often as needed.
+use geekorm::prelude::*;
+use log::{debug, info};
+
+use crate::models::{
+    outbox::EVENT_REPORT_READY,
+    reports::{ReportPeriod, ReportSchedule, Reports},
+    EventsOutbox, ServerSettings, Setting, Transaction, TransactionConnection,
+};
+
+/// Reports task summary
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReportsSummary {
+    /// Period of the generated report (if a new report was generated)
+    pub generated: Option<String>,
+    /// If the report was queued for the webhook
+    pub queued: bool,
+    /// Number of reports removed (retention)
+    pub pruned: u64,
+}
+
+impl From<&ReportsSummary> for super::TaskStats {
+    fn from(summary: &ReportsSummary) -> Self {
+        Self::from_iter([
+            (
+                "generated",
+                summary
+                    .generated
+                    .clone()
+                    .unwrap_or_else(|| "none".to_string()),
+            ),
+            ("queued", summary.queued.to_string()),
+            ("pruned", summary.pruned.to_string()),
+        ])
+    }
+}
+
+/// Reports task
+pub async fn reports<'a, T>(connection: &'a T) -> Result<ReportsSummary, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + TransactionConnection + 'a,
+{
+    debug!("Task - Reports");
+    let mut summary = ReportsSummary::default();
+
+    let schedule = ServerSettings::get(connection, Setting::ReportsSchedule).await?;
+    if let Some(schedule) = ReportSchedule::parse(&schedule.value) {
+        let period = schedule.period(chrono::Utc::now());
+        if Reports::fetch_by_period(connection, period.name.clone())
+            .await
+            .is_err()
+        {
+            info!("Generating the report of {}", period.name);
+            let webhook = ServerSettings::get(connection, Setting::ReportsWebhook)
+                .await?
+                .value;
+
+            let transaction = Transaction::begin(connection).await?;
+            let result = generate(transaction.connection(), &period, webhook.trim()).await;
+            let report = transaction.finish(result).await?;
+            summary.queued = !webhook.trim().is_empty();
+            summary.generated = Some(report.period);
+        }
+    } else {
+        debug!("Reports are disabled");
+    }
+
+    let retention: i64 = ServerSettings::get(connection, Setting::ReportsRetention)
+        .await?
+        .value
+        .parse()
+        .unwrap_or_default();
+    if retention > 0 {
+        let before = chrono::Utc::now() - chrono::Duration::days(retention);
+        summary.pruned = Reports::prune(connection, before).await?;
+        if summary.pruned != 0 {
+            info!(
+                "Removed {} reports older than {} days",
+                summary.pruned, retention
+            );
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Generate the report and queue the `report.ready` event (if there is a webhook)
+///
+/// The `text` of the event is the Markdown and its data the period and the digest.
+async fn generate<'a, T>(
+    connection: &'a T,
+    period: &ReportPeriod,
+    webhook: &str,
+) -> Result<Reports, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let report = Reports::generate(connection, period).await?;
+    if !webhook.is_empty() {
+        EventsOutbox::enqueue(
+            connection,
+            EVENT_REPORT_READY,
+            webhook,
+            report.markdown.clone(),
+            &serde_json::json!({
+                "period": report.period,
+                "report": report.digest()?,
+            }),
+        )
+        .await?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reports_task() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        // Weekly by default, the period is only generated once
+        let summary = reports(&connection).await?;
+        assert!(summary.generated.is_some());
+        assert!(!summary.queued);
+        assert_eq!(reports(&connection).await?.generated, None);
+        assert_eq!(Reports::fetch_latest(&connection, 10).await?.len(), 1);
+
+        ServerSettings::get(&connection, Setting::ReportsSchedule)
+            .await?
+            .set_update(&connection, "disabled")
+            .await?;
+        let mut old = Reports::new(
+            "2020-W01",
+            chrono::Utc::now() - chrono::Duration::days(800),
+            chrono::Utc::now() - chrono::Duration::days(793),
+            "{}",
+            "",
+        );
+        old.save(&connection).await?;
+
+        let summary = reports(&connection).await?;
+        assert_eq!(summary.generated, None);
+        assert_eq!(summary.pruned, 1);
+        Ok(())
+    }
+}
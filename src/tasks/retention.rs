@@ -0,0 +1,708 @@
+//! # Task - Snapshot Retention
+//!
+//! Removes the old snapshots of the projects based on the retention policy of their
+//! project type (`retention.<type>.max_snapshots` / `retention.<type>.max_age_days`),
+//! which can be overridden per project. The latest snapshot of a project and the
+//! baseline snapshots (`snapshot.baseline` metadata) are always kept.
+//!
+//! Snapshots shared by projects tracking the same image are only unlinked from the
+//! project, they are removed with the last project using them.
+//!
+//! The stored SBOMs have their own retention (`retention.sbom_blobs.days` /
+//! `retention.sbom_blobs.max_bytes`), only the file is removed and the snapshot keeps
+//! its dependencies, alerts and metadata (`bom.pruned` records when it was removed).
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use geekorm::prelude::*;
+use log::{debug, info, warn};
+use serde::Deserialize;
+
+use crate::{
+    models::{
+        raw_query, settings::keys::Setting, ProjectStatus, ProjectType, Projects, ServerSettings,
+        Snapshot, SnapshotMetadata, SnapshotMetadataKey, TransactionConnection,
+    },
+    Config,
+};
+
+/// Snapshot retention policy (`0` disables a limit)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Maximum number of snapshots kept (baselines are not counted)
+    pub max_snapshots: u32,
+    /// Maximum age of the snapshots (days)
+    pub max_age_days: u32,
+}
+
+/// Snapshots of a project which are outside of its retention policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionPlan {
+    /// Project ID
+    pub project_id: i32,
+    /// Project name
+    pub project: String,
+    /// Project type (the policy used unless the project overrides it)
+    pub project_type: ProjectType,
+    /// Policy of the project
+    pub policy: RetentionPolicy,
+    /// Snapshots to remove (newest first)
+    pub snapshots: Vec<i32>,
+}
+
+/// Stored SBOM retention policy (`0` disables a limit)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SbomRetentionPolicy {
+    /// Maximum age of the stored SBOMs (days)
+    pub max_age_days: u32,
+    /// Maximum total size of the stored SBOMs (bytes)
+    pub max_bytes: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RetentionSbomRow {
+    id: i32,
+    created_at: DateTime<Utc>,
+    path: String,
+    baseline: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RetentionSnapshotRow {
+    id: i32,
+    created_at: DateTime<Utc>,
+    baseline: bool,
+}
+
+impl RetentionPolicy {
+    /// Load the retention policy of a project type from the server settings
+    pub async fn load<'a, T>(
+        connection: &'a T,
+        project_type: &ProjectType,
+    ) -> Result<Self, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let (max_snapshots, max_age_days) = match project_type {
+            ProjectType::Container => (
+                Setting::RetentionContainerMaxSnapshots,
+                Setting::RetentionContainerMaxAgeDays,
+            ),
+            ProjectType::Server => (
+                Setting::RetentionServerMaxSnapshots,
+                Setting::RetentionServerMaxAgeDays,
+            ),
+            ProjectType::Application => (
+                Setting::RetentionApplicationMaxSnapshots,
+                Setting::RetentionApplicationMaxAgeDays,
+            ),
+            ProjectType::Group => (
+                Setting::RetentionGroupMaxSnapshots,
+                Setting::RetentionGroupMaxAgeDays,
+            ),
+            ProjectType::Cluster => (
+                Setting::RetentionClusterMaxSnapshots,
+                Setting::RetentionClusterMaxAgeDays,
+            ),
+        };
+        let value = |setting: Setting| async move {
+            ServerSettings::fetch_by_name(connection, setting)
+                .await
+                .ok()
+                .and_then(|setting| setting.value.trim().parse::<u32>().ok())
+                .unwrap_or_default()
+        };
+        Ok(Self {
+            max_snapshots: value(max_snapshots).await,
+            max_age_days: value(max_age_days).await,
+        })
+    }
+
+    /// Policy of a project (the overrides of the project replace the type policy)
+    pub fn with_overrides(self, project: &Projects) -> Self {
+        Self {
+            max_snapshots: project
+                .retention_max_snapshots
+                .map_or(self.max_snapshots, |value| value.max(0) as u32),
+            max_age_days: project
+                .retention_max_age_days
+                .map_or(self.max_age_days, |value| value.max(0) as u32),
+        }
+    }
+
+    /// If the policy removes any snapshot
+    pub fn is_enabled(&self) -> bool {
+        self.max_snapshots > 0 || self.max_age_days > 0
+    }
+
+    /// Select the snapshots to remove (`(id, created_at, baseline)`, newest first)
+    ///
+    /// The latest snapshot and the baselines are always kept.
+    pub fn select(&self, snapshots: &[(i32, DateTime<Utc>, bool)], now: DateTime<Utc>) -> Vec<i32> {
+        let mut kept = 0;
+        let mut removed = Vec::new();
+        for (index, (id, created_at, baseline)) in snapshots.iter().enumerate() {
+            if index == 0 || *baseline {
+                kept += usize::from(!*baseline);
+                continue;
+            }
+            let over_count = self.max_snapshots > 0 && kept >= self.max_snapshots as usize;
+            let over_age = self.max_age_days > 0
+                && now.signed_duration_since(*created_at).num_days() >= self.max_age_days as i64;
+            if over_count || over_age {
+                removed.push(*id);
+            } else {
+                kept += 1;
+            }
+        }
+        removed
+    }
+}
+
+impl SbomRetentionPolicy {
+    /// Load the stored SBOM retention policy from the server settings
+    pub async fn load<'a, T>(connection: &'a T) -> Result<Self, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        let value = |setting: Setting| async move {
+            ServerSettings::fetch_by_name(connection, setting)
+                .await
+                .ok()
+                .and_then(|setting| setting.value.trim().parse::<u64>().ok())
+                .unwrap_or_default()
+        };
+        Ok(Self {
+            max_age_days: value(Setting::RetentionSbomBlobsDays).await as u32,
+            max_bytes: value(Setting::RetentionSbomBlobsMaxBytes).await,
+        })
+    }
+
+    /// If the policy removes any SBOM
+    pub fn is_enabled(&self) -> bool {
+        self.max_age_days > 0 || self.max_bytes > 0
+    }
+
+    /// Select the SBOMs to remove (`(created_at, size, baseline)`, oldest first)
+    ///
+    /// The SBOMs older than the maximum age are removed, then the oldest until the total
+    /// size fits. Baselines are always kept (but count towards the total size).
+    pub fn select(&self, sboms: &[(DateTime<Utc>, u64, bool)], now: DateTime<Utc>) -> Vec<usize> {
+        let mut total: u64 = sboms.iter().map(|(_, size, _)| size).sum();
+        let mut removed = Vec::new();
+        for (index, (created_at, size, baseline)) in sboms.iter().enumerate() {
+            if *baseline {
+                continue;
+            }
+            let over_size = self.max_bytes > 0 && total > self.max_bytes;
+            let over_age = self.max_age_days > 0
+                && now.signed_duration_since(*created_at).num_days() >= self.max_age_days as i64;
+            if over_size || over_age {
+                total = total.saturating_sub(*size);
+                removed.push(index);
+            }
+        }
+        removed
+    }
+}
+
+/// Plan which snapshots each policy removes (dry-run)
+pub async fn retention_plan<'a, T>(
+    connection: &'a T,
+) -> Result<Vec<RetentionPlan>, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let projects = Projects::query(
+        connection,
+        Projects::query_select()
+            .where_ne("status", ProjectStatus::Archived)
+            .order_by("id", QueryOrder::Asc)
+            .build()?,
+    )
+    .await?;
+
+    let now = Utc::now();
+    let mut plans = Vec::new();
+    for project in projects.iter() {
+        let policy = RetentionPolicy::load(connection, &project.project_type)
+            .await?
+            .with_overrides(project);
+        if !policy.is_enabled() {
+            continue;
+        }
+
+        let mut values = Values::new();
+        values.push("key".to_string(), SnapshotMetadataKey::SnapshotBaseline);
+        values.push("baseline".to_string(), "true");
+        values.push("project_id".to_string(), project.id);
+        let snapshots: Vec<(i32, DateTime<Utc>, bool)> = T::query::<RetentionSnapshotRow>(
+            connection,
+            raw_query(
+                "SELECT Snapshot.id AS id, Snapshot.created_at AS created_at, \
+                EXISTS (SELECT 1 FROM SnapshotMetadata WHERE snapshot_id = Snapshot.id \
+                    AND key = ? AND CAST(value AS TEXT) = ?) AS baseline \
+                FROM ProjectSnapshots JOIN Snapshot ON Snapshot.id = ProjectSnapshots.snapshot_id \
+                WHERE ProjectSnapshots.project_id = ? ORDER BY Snapshot.id DESC;",
+                values,
+            ),
+        )
+        .await?
+        .into_iter()
+        .map(|row| (row.id, row.created_at, row.baseline))
+        .collect();
+
+        let removed = policy.select(&snapshots, now);
+        if !removed.is_empty() {
+            plans.push(RetentionPlan {
+                project_id: project.id.into(),
+                project: project.name.clone(),
+                project_type: project.project_type.clone(),
+                policy,
+                snapshots: removed,
+            });
+        }
+    }
+    Ok(plans)
+}
+
+/// Apply the retention policies, returns the number of snapshots removed from projects
+pub async fn retention<'a, T>(config: &Config, connection: &'a T) -> Result<u64, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + TransactionConnection + 'a,
+{
+    let mut removed = 0;
+    for plan in retention_plan(connection).await? {
+        let mut project = Projects::fetch_by_primary_key(connection, plan.project_id).await?;
+        for snapshot_id in plan.snapshots.iter() {
+            let mut snapshot = Snapshot::fetch_by_primary_key(connection, *snapshot_id).await?;
+            snapshot.fetch_metadata(connection).await?;
+            let bom_path = snapshot
+                .metadata
+                .get(&SnapshotMetadataKey::BomPath)
+                .map(|path| path.as_string());
+
+            if project.remove_snapshot(connection, &snapshot).await? {
+                if let Some(path) = bom_path {
+                    remove_sbom(config, connection, &path).await?;
+                }
+            }
+            removed += 1;
+        }
+        info!(
+            "Project('{}') :: removed {} snapshots outside of the retention policy",
+            plan.project,
+            plan.snapshots.len()
+        );
+    }
+    Ok(removed)
+}
+
+/// Apply the stored SBOM retention, returns the number of SBOM files removed
+///
+/// A file shared by several snapshots is kept while any of them is a baseline and is
+/// aged by its newest snapshot.
+pub async fn sbom_retention<'a, T>(
+    config: &Config,
+    connection: &'a T,
+) -> Result<u64, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let policy = SbomRetentionPolicy::load(connection).await?;
+    if !policy.is_enabled() {
+        debug!("Stored SBOM retention is disabled");
+        return Ok(0);
+    }
+    let sboms_path = config.sboms_path()?;
+
+    let mut values = Values::new();
+    values.push(
+        "baseline_key".to_string(),
+        SnapshotMetadataKey::SnapshotBaseline,
+    );
+    values.push("baseline".to_string(), "true");
+    values.push("key".to_string(), SnapshotMetadataKey::BomPath);
+    let rows = T::query::<RetentionSbomRow>(
+        connection,
+        raw_query(
+            "SELECT Snapshot.id AS id, Snapshot.created_at AS created_at, \
+            CAST(SnapshotMetadata.value AS TEXT) AS path, \
+            EXISTS (SELECT 1 FROM SnapshotMetadata AS baseline \
+                WHERE baseline.snapshot_id = Snapshot.id AND baseline.key = ? \
+                AND CAST(baseline.value AS TEXT) = ?) AS baseline \
+            FROM SnapshotMetadata JOIN Snapshot ON Snapshot.id = SnapshotMetadata.snapshot_id \
+            WHERE SnapshotMetadata.key = ?;",
+            values,
+        ),
+    )
+    .await?;
+
+    // Group the snapshots by SBOM file (path -> (newest, baseline, snapshots))
+    let mut files: BTreeMap<String, (DateTime<Utc>, bool, Vec<i32>)> = BTreeMap::new();
+    for row in rows {
+        let entry = files
+            .entry(row.path)
+            .or_insert((row.created_at, false, Vec::new()));
+        entry.0 = entry.0.max(row.created_at);
+        entry.1 |= row.baseline;
+        entry.2.push(row.id);
+    }
+    let mut files: Vec<(String, DateTime<Utc>, bool, Vec<i32>)> = files
+        .into_iter()
+        .map(|(path, (created_at, baseline, snapshots))| (path, created_at, baseline, snapshots))
+        .collect();
+    files.sort_by_key(|(_, created_at, _, _)| *created_at);
+
+    let sboms: Vec<(DateTime<Utc>, u64, bool)> = files
+        .iter()
+        .map(|(path, created_at, baseline, _)| {
+            let size = std::fs::metadata(sboms_path.join(path)).map_or(0, |m| m.len());
+            (*created_at, size, *baseline)
+        })
+        .collect();
+
+    let now = Utc::now();
+    let mut removed = 0;
+    for index in policy.select(&sboms, now) {
+        let (path, _, _, snapshots) = &files[index];
+        for snapshot_id in snapshots {
+            let mut values = Values::new();
+            values.push("snapshot_id".to_string(), *snapshot_id);
+            values.push("key".to_string(), SnapshotMetadataKey::BomPath);
+            T::execute::<Snapshot>(
+                connection,
+                raw_query(
+                    "DELETE FROM SnapshotMetadata WHERE snapshot_id = ? AND key = ?;",
+                    values,
+                ),
+            )
+            .await?;
+            SnapshotMetadata::update_or_create(
+                connection,
+                *snapshot_id,
+                &SnapshotMetadataKey::BomPruned,
+                now.to_rfc3339(),
+            )
+            .await?;
+        }
+        remove_sbom(config, connection, path).await?;
+        removed += 1;
+    }
+    if removed > 0 {
+        info!(
+            "Removed {} stored SBOMs outside of the retention policy",
+            removed
+        );
+    }
+    Ok(removed)
+}
+
+/// Remove a stored SBOM file (if no other snapshot uses it)
+async fn remove_sbom<'a, T>(
+    config: &Config,
+    connection: &'a T,
+    path: &str,
+) -> Result<(), crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let mut values = Values::new();
+    values.push("key".to_string(), SnapshotMetadataKey::BomPath);
+    values.push("path".to_string(), path.to_string());
+    let used = T::query::<RetentionSnapshotRow>(
+        connection,
+        raw_query(
+            "SELECT snapshot_id AS id, created_at, 0 AS baseline FROM SnapshotMetadata \
+            WHERE key = ? AND CAST(value AS TEXT) = ? LIMIT 1;",
+            values,
+        ),
+    )
+    .await?;
+    if !used.is_empty() {
+        debug!("SBOM `{}` is used by another snapshot", path);
+        return Ok(());
+    }
+
+    let full_path = config.sboms_path()?.join(path);
+    if full_path.exists() {
+        if let Err(e) = std::fs::remove_file(&full_path) {
+            warn!("Failed to remove SBOM `{}`: {}", full_path.display(), e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Dependencies;
+
+    fn days_ago(days: i64) -> DateTime<Utc> {
+        Utc::now() - chrono::Duration::days(days)
+    }
+
+    #[test]
+    fn test_select() {
+        let now = Utc::now();
+        let snapshots = vec![
+            (6, days_ago(0), false),
+            (5, days_ago(10), false),
+            (4, days_ago(20), true),
+            (3, days_ago(30), false),
+            (2, days_ago(100), false),
+            (1, days_ago(200), false),
+        ];
+
+        let policy = RetentionPolicy::default();
+        assert!(!policy.is_enabled());
+        assert!(policy.select(&snapshots, now).is_empty());
+
+        // Baselines are kept and not counted
+        let policy = RetentionPolicy {
+            max_snapshots: 2,
+            ..Default::default()
+        };
+        assert_eq!(policy.select(&snapshots, now), vec![3, 2, 1]);
+
+        let policy = RetentionPolicy {
+            max_age_days: 90,
+            ..Default::default()
+        };
+        assert_eq!(policy.select(&snapshots, now), vec![2, 1]);
+
+        // The latest snapshot is always kept
+        let policy = RetentionPolicy {
+            max_snapshots: 1,
+            max_age_days: 1,
+        };
+        assert_eq!(
+            policy.select(&[(1, days_ago(400), false)], now),
+            Vec::<i32>::new()
+        );
+    }
+
+    #[test]
+    fn test_select_sboms() {
+        let now = Utc::now();
+        let sboms = vec![
+            (days_ago(200), 100, false),
+            (days_ago(100), 100, true),
+            (days_ago(50), 100, false),
+            (days_ago(10), 100, false),
+            (days_ago(0), 100, false),
+        ];
+        let policy = SbomRetentionPolicy::default();
+        assert!(!policy.is_enabled());
+        assert!(policy.select(&sboms, now).is_empty());
+
+        let policy = SbomRetentionPolicy {
+            max_age_days: 30,
+            ..Default::default()
+        };
+        assert_eq!(policy.select(&sboms, now), vec![0, 2]);
+
+        // Oldest first until it fits, baselines are kept
+        let policy = SbomRetentionPolicy {
+            max_bytes: 250,
+            ..Default::default()
+        };
+        assert_eq!(policy.select(&sboms, now), vec![0, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_retention() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+        let config = Config::default();
+
+        // Server keeps 90 days, containers the last 2 snapshots
+        for (setting, value) in [
+            (Setting::RetentionServerMaxAgeDays, "90"),
+            (Setting::RetentionContainerMaxSnapshots, "2"),
+        ] {
+            ServerSettings::fetch_by_name(&connection, setting)
+                .await?
+                .set_update(&connection, value)
+                .await?;
+        }
+
+        let mut server = Projects::new("homelab", ProjectType::Server);
+        server.save(&connection).await?;
+        let mut container = Projects::new("homelab/web", ProjectType::Container);
+        container.save(&connection).await?;
+        let mut pinned = Projects::new("homelab/db", ProjectType::Container);
+        pinned.retention_max_snapshots = Some(0);
+        pinned.save(&connection).await?;
+
+        let mut history = Vec::new();
+        for (project, days) in [
+            (&mut server, vec![120, 95, 89, 30, 0]),
+            (&mut container, vec![120, 95, 89, 30, 0]),
+            (&mut pinned, vec![120, 95, 89, 30, 0]),
+        ] {
+            let mut ids = Vec::new();
+            for age in days {
+                let mut snapshot = Snapshot::create(&connection).await?;
+                snapshot.created_at = days_ago(age);
+                snapshot.update(&connection).await?;
+                project.add_snapshot(&connection, snapshot.clone()).await?;
+                ids.push(snapshot.id);
+            }
+            history.push(ids);
+        }
+        // Baseline of the container (oldest snapshot)
+        let mut baseline = Snapshot::fetch_by_primary_key(&connection, history[1][0]).await?;
+        baseline
+            .set_metadata(&connection, SnapshotMetadataKey::SnapshotBaseline, "true")
+            .await?;
+        // The container shares its oldest non-baseline snapshot with the server
+        let shared = Snapshot::fetch_by_primary_key(&connection, history[1][1]).await?;
+        server.add_snapshot(&connection, shared.clone()).await?;
+        let mut dependency =
+            Dependencies::from_purl(&connection, "pkg:deb/debian/openssl@3.0.1".to_string())
+                .await?;
+        dependency.snapshot_id = history[1][2].into();
+        dependency.save(&connection).await?;
+
+        // Dry-run
+        let plans = retention_plan(&connection).await?;
+        assert_eq!(plans.len(), 2);
+        assert_eq!(plans[0].project, "homelab");
+        assert_eq!(
+            plans[0].snapshots,
+            vec![i32::from(history[0][1]), i32::from(history[0][0])]
+        );
+        assert_eq!(plans[1].project, "homelab/web");
+        assert_eq!(plans[1].policy.max_snapshots, 2);
+        assert_eq!(
+            plans[1].snapshots,
+            vec![i32::from(history[1][2]), i32::from(history[1][1])]
+        );
+        assert_eq!(Snapshot::all(&connection).await?.len(), 15);
+
+        // Enforced by the cleanup task
+        assert_eq!(
+            super::super::cleanup(&config, &connection).await?.snapshots,
+            4
+        );
+        assert!(retention_plan(&connection).await?.is_empty());
+
+        // The shared snapshot is kept for the server (latest snapshot)
+        assert!(Snapshot::fetch_by_primary_key(&connection, shared.id)
+            .await
+            .is_ok());
+        assert!(Snapshot::fetch_by_primary_key(&connection, history[1][2])
+            .await
+            .is_err());
+        assert!(
+            Dependencies::fetch_by_snapshot_id(&connection, history[1][2])
+                .await?
+                .is_empty()
+        );
+        assert!(Snapshot::fetch_by_primary_key(&connection, baseline.id)
+            .await
+            .is_ok());
+        let mut container = Projects::fetch_by_primary_key(&connection, container.id).await?;
+        container.fetch_snapshots(&connection).await?;
+        assert_eq!(container.snapshots.len(), 3);
+        let mut pinned = Projects::fetch_by_primary_key(&connection, pinned.id).await?;
+        pinned.fetch_snapshots(&connection).await?;
+        assert_eq!(pinned.snapshots.len(), 5);
+
+        // Snapshots of archived projects are kept (until the project is restored)
+        pinned.retention_max_snapshots = Some(1);
+        pinned.archive(&connection).await?;
+        assert!(retention_plan(&connection).await?.is_empty());
+        pinned.restore(&connection).await?;
+        assert_eq!(retention_plan(&connection).await?[0].snapshots.len(), 4);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sbom_retention() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+        let mut config = Config::default();
+        config.set_data_path(std::env::temp_dir().join("konarr-test-sbom-retention"));
+        let sboms_path = config.sboms_path()?;
+
+        let mut project = Projects::new("homelab/web", ProjectType::Container);
+        project.save(&connection).await?;
+        let mut snapshots = Vec::new();
+        for (age, path) in [
+            (120, "old.json"),
+            (100, "baseline.json"),
+            (90, "shared.json"),
+            (10, "shared.json"),
+            (0, "latest.json"),
+        ] {
+            std::fs::write(sboms_path.join(path), b"{}")?;
+            let mut snapshot = Snapshot::create(&connection).await?;
+            snapshot.created_at = days_ago(age);
+            snapshot.update(&connection).await?;
+            snapshot
+                .set_metadata(&connection, SnapshotMetadataKey::BomPath, path)
+                .await?;
+            project.add_snapshot(&connection, snapshot.clone()).await?;
+            snapshots.push(snapshot);
+        }
+        snapshots[1]
+            .set_metadata(&connection, SnapshotMetadataKey::SnapshotBaseline, "true")
+            .await?;
+        let mut dependency =
+            Dependencies::from_purl(&connection, "pkg:deb/debian/openssl@3.0.1".to_string())
+                .await?;
+        dependency.snapshot_id = snapshots[0].id.into();
+        dependency.save(&connection).await?;
+
+        // Disabled by default
+        assert_eq!(
+            super::super::cleanup(&config, &connection)
+                .await?
+                .sbom_blobs,
+            0
+        );
+
+        ServerSettings::fetch_by_name(&connection, Setting::RetentionSbomBlobsDays)
+            .await?
+            .set_update(&connection, "30")
+            .await?;
+        assert_eq!(
+            super::super::cleanup(&config, &connection)
+                .await?
+                .sbom_blobs,
+            1
+        );
+
+        // The snapshot and its dependencies are kept
+        let mut old = Snapshot::fetch_by_primary_key(&connection, snapshots[0].id).await?;
+        old.fetch_metadata(&connection).await?;
+        assert!(old.metadata.get(&SnapshotMetadataKey::BomPath).is_none());
+        assert!(old.metadata.get(&SnapshotMetadataKey::BomPruned).is_some());
+        assert!(!sboms_path.join("old.json").exists());
+        assert_eq!(
+            Dependencies::fetch_by_snapshot_id(&connection, old.id)
+                .await?
+                .len(),
+            1
+        );
+        // Baselines and files used by a newer snapshot are kept
+        assert!(sboms_path.join("baseline.json").exists());
+        assert!(sboms_path.join("shared.json").exists());
+
+        // Total size (2 bytes per SBOM)
+        ServerSettings::fetch_by_name(&connection, Setting::RetentionSbomBlobsMaxBytes)
+            .await?
+            .set_update(&connection, "4")
+            .await?;
+        assert_eq!(sbom_retention(&config, &connection).await?, 1);
+        assert!(!sboms_path.join("shared.json").exists());
+        assert!(sboms_path.join("latest.json").exists());
+        let mut shared = Snapshot::fetch_by_primary_key(&connection, snapshots[2].id).await?;
+        shared.fetch_metadata(&connection).await?;
+        assert!(shared.metadata.get(&SnapshotMetadataKey::BomPath).is_none());
+
+        let _ = std::fs::remove_dir_all(config.data_path()?);
+        Ok(())
+    }
+}
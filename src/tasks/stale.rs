@@ -0,0 +1,254 @@
+//! # Task - Stale Scans
+//!
+//! Flags the projects whose scan no longer reflects what is running:
+//!
+//! - container projects where the running image digest drifted from the scanned image
+//!   (for example a container recreated from a newer image with the same tag)
+//! - container and server projects not scanned in the last `scan.freshness.days` days
+//!   (for example an agent which stopped running)
+//!
+//! The newly stale projects are sent as a notification (see [super::notify]), queued
+//! in the same transaction as the `scan.stale` flags.
+use geekorm::prelude::*;
+use log::{debug, warn};
+
+use crate::models::{
+    outbox::EVENT_PROJECTS_STALE, ProjectType, Projects, ServerSettings, Setting,
+    SnapshotMetadataKey, Transaction, TransactionConnection,
+};
+
+/// Stale scans task summary
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StaleSummary {
+    /// Number of stale projects
+    pub stale: i64,
+    /// Names of the projects which became stale in this run
+    pub newly_stale: Vec<String>,
+    /// If the newly stale projects were queued as a notification
+    pub notified: bool,
+}
+
+impl From<&StaleSummary> for super::TaskStats {
+    fn from(summary: &StaleSummary) -> Self {
+        Self::from_iter([
+            ("stale", summary.stale.to_string()),
+            ("newly_stale", summary.newly_stale.len().to_string()),
+            ("notified", summary.notified.to_string()),
+        ])
+    }
+}
+
+/// Stale scans task
+///
+/// Sets `scan.stale` on the latest snapshot of each container and server project, the
+/// number of stale projects is stored in the `stats.projects.stale` statistic.
+pub async fn stale_scans<'a, T>(connection: &'a T) -> Result<StaleSummary, crate::KonarrError>
+where
+    T: TransactionConnection + 'a,
+{
+    debug!("Task - Checking for stale scans");
+    let mut summary = StaleSummary::default();
+
+    let transaction = Transaction::begin(connection).await?;
+    let result = stale_scans_inner(transaction.connection(), &mut summary).await;
+    transaction.finish(result).await?;
+    Ok(summary)
+}
+
+async fn stale_scans_inner<'a, T>(
+    connection: &'a T,
+    summary: &mut StaleSummary,
+) -> Result<(), crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let freshness: i64 = ServerSettings::get(connection, Setting::ScanFreshnessDays)
+        .await?
+        .value
+        .parse()
+        .unwrap_or_default();
+    let now = chrono::Utc::now();
+
+    let mut projects =
+        Projects::fetch_project_type(connection, ProjectType::Container, 1_000, 0).await?;
+    projects.extend(Projects::fetch_project_type(connection, ProjectType::Server, 1_000, 0).await?);
+
+    for project in projects.iter_mut() {
+        let drifted = project.is_scan_stale();
+        let outdated = project.is_scan_outdated(now, freshness);
+        if drifted {
+            warn!(
+                "Project `{}` is running an image which has not been scanned",
+                project.name
+            );
+        }
+        if outdated {
+            warn!(
+                "Project `{}` has not been scanned in the last {} days",
+                project.name, freshness
+            );
+        }
+        let stale = drifted || outdated;
+        if stale {
+            summary.stale += 1;
+        }
+
+        if let Some(latest) = project.snapshots.last_mut() {
+            let current = latest.find_metadata("scan.stale").map(|m| m.as_bool());
+            if current != Some(stale) {
+                if stale {
+                    summary.newly_stale.push(project.name.clone());
+                }
+                latest
+                    .set_metadata(
+                        connection,
+                        SnapshotMetadataKey::ScanStale,
+                        &stale.to_string(),
+                    )
+                    .await?;
+            }
+        }
+    }
+
+    ServerSettings::update_statistic_at(
+        connection,
+        Setting::StatsProjectsStale,
+        summary.stale,
+        now,
+    )
+    .await?;
+
+    if !summary.newly_stale.is_empty() {
+        summary.notified = super::notify(
+            connection,
+            EVENT_PROJECTS_STALE,
+            format!(
+                "{} project(s) became stale: {}",
+                summary.newly_stale.len(),
+                summary.newly_stale.join(", ")
+            ),
+            serde_json::json!({ "projects": summary.newly_stale }),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Snapshot;
+
+    #[tokio::test]
+    async fn test_stale_scans() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("server/app", ProjectType::Container);
+        project.save(&connection).await?;
+
+        // Scanned snapshot
+        let mut scanned = Snapshot::create(&connection).await?;
+        project.add_snapshot(&connection, scanned.clone()).await?;
+        scanned
+            .set_metadata(&connection, "scan.container.sha", "sha256:aaa")
+            .await?;
+        scanned
+            .set_metadata(&connection, "container.sha", "sha256:aaa")
+            .await?;
+        assert_eq!(stale_scans(&connection).await?.stale, 0);
+
+        // Container recreated from a newer image (same tag), not scanned yet
+        let mut latest = Snapshot::create(&connection).await?;
+        project.add_snapshot(&connection, latest.clone()).await?;
+        latest
+            .set_metadata(&connection, "container.sha", "sha256:bbb")
+            .await?;
+        assert_eq!(stale_scans(&connection).await?.stale, 1);
+
+        latest.fetch_metadata(&connection).await?;
+        assert!(latest.find_metadata("scan.stale").unwrap().as_bool());
+        let stat = ServerSettings::fetch_by_name(&connection, Setting::StatsProjectsStale).await?;
+        assert_eq!(stat.value, "1");
+
+        // Scanning the new image resolves it
+        latest
+            .set_metadata(&connection, "scan.container.sha", "sha256:bbb")
+            .await?;
+        assert_eq!(stale_scans(&connection).await?.stale, 0);
+        latest.fetch_metadata(&connection).await?;
+        assert!(!latest.find_metadata("scan.stale").unwrap().as_bool());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stale_freshness() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut server = Projects::new("homelab", ProjectType::Server);
+        server.save(&connection).await?;
+        let mut snapshot = Snapshot::create(&connection).await?;
+        server.add_snapshot(&connection, snapshot.clone()).await?;
+        snapshot.set_metadata(&connection, "os", "linux").await?;
+
+        let summary = stale_scans(&connection).await?;
+        assert_eq!(summary.stale, 0);
+        assert!(summary.newly_stale.is_empty());
+
+        // The agent stopped reporting 10 days ago
+        let scanned_at = chrono::Utc::now() - chrono::Duration::days(10);
+        snapshot.created_at = scanned_at;
+        snapshot.update(&connection).await?;
+        snapshot.fetch_metadata(&connection).await?;
+        for metadata in snapshot.metadata.values_mut() {
+            metadata.updated_at = scanned_at;
+            metadata.update(&connection).await?;
+        }
+
+        let summary = stale_scans(&connection).await?;
+        assert_eq!(summary.stale, 1);
+        assert_eq!(summary.newly_stale, vec!["homelab".to_string()]);
+        assert!(!summary.notified);
+        assert_eq!(
+            Projects::fetch_stale(&connection, 10, 0).await?[0].name,
+            "homelab"
+        );
+        assert_eq!(Projects::count_stale(&connection).await?, 1);
+
+        // Only notified once
+        assert!(stale_scans(&connection).await?.newly_stale.is_empty());
+
+        // Disabled
+        ServerSettings::get(&connection, Setting::ScanFreshnessDays)
+            .await?
+            .set_update(&connection, "0")
+            .await?;
+        assert_eq!(stale_scans(&connection).await?.stale, 0);
+        assert_eq!(Projects::count_stale(&connection).await?, 0);
+
+        // The agent reports again
+        ServerSettings::get(&connection, Setting::ScanFreshnessDays)
+            .await?
+            .set_update(&connection, "7")
+            .await?;
+        snapshot.set_metadata(&connection, "os", "linux").await?;
+        assert_eq!(stale_scans(&connection).await?.stale, 0);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_stale_scans_overlapping() -> Result<(), crate::KonarrError> {
+        let connection = std::sync::Arc::new(tokio::sync::Mutex::new(
+            crate::models::database_test().await?,
+        ));
+        let mut project = Projects::new("server/app", ProjectType::Container);
+        project.save(&connection).await?;
+
+        // The scheduler and a statistics recompute running at the same time
+        let (scheduler, recompute) =
+            tokio::join!(stale_scans(&connection), stale_scans(&connection));
+        assert_eq!(scheduler?.stale, 0);
+        assert_eq!(recompute?.stale, 0);
+        Ok(())
+    }
+}
@@ -1,19 +1,141 @@
 //! # Tasks - Statistics
+use std::collections::BTreeMap;
+
 use geekorm::{GeekConnection, GeekConnector, QueryBuilderTrait};
 
-use crate::models::{Component, ComponentType, Projects, ServerSettings, Setting, Users};
+use crate::models::{
+    security::{events::ALERTS_RESOLVED_RECENT_DAYS, ProjectHealth},
+    settings::SettingNamespace,
+    AlertEvents, Component, ComponentTags, ComponentType, ProjectFilters, Projects, ServerSettings,
+    Setting, TransactionConnection, Users, COMPONENT_MANAGERS,
+};
+
+/// Statistics task summary
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StatisticsSummary {
+    /// Number of components with each custom tag (see [ComponentTags])
+    pub tags: BTreeMap<String, i64>,
+}
+
+impl From<&StatisticsSummary> for super::TaskStats {
+    fn from(summary: &StatisticsSummary) -> Self {
+        Self::from_iter(
+            summary
+                .tags
+                .iter()
+                .map(|(tag, count)| (format!("dependencies.tags.{}", tag), count)),
+        )
+    }
+}
+
+/// Statistic which was corrected by [Statistics::recompute_all]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatisticDrift {
+    /// Statistic setting
+    pub name: Setting,
+    /// Stored value before the recompute (`None` if it did not exist)
+    pub previous: Option<i64>,
+    /// Value derived from the source of truth
+    pub value: i64,
+}
+
+/// Recompute summary
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RecomputeSummary {
+    /// Number of statistics recomputed
+    pub statistics: usize,
+    /// Statistics which had drifted from the source of truth
+    pub corrected: Vec<StatisticDrift>,
+}
+
+impl From<&RecomputeSummary> for super::TaskStats {
+    fn from(summary: &RecomputeSummary) -> Self {
+        Self::from_iter([
+            ("statistics", summary.statistics),
+            ("corrected", summary.corrected.len()),
+        ])
+    }
+}
+
+/// Statistics
+pub struct Statistics;
+
+impl Statistics {
+    /// Recompute every statistic from the source of truth (users, projects, alerts,
+    /// dependencies, agents and stale scans), correcting any drift
+    ///
+    /// Only the `stats.` counters are compared; gauges such as the storage
+    /// diagnostics are refreshed by their own task and change without drifting.
+    pub async fn recompute_all<'a, T>(
+        connection: &'a T,
+    ) -> Result<RecomputeSummary, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + TransactionConnection + Send + Sync + 'a,
+    {
+        log::info!("Task - Recomputing all Statistics");
+        let previous = Self::values(connection).await?;
+
+        statistics(connection).await?;
+        super::agent_versions(connection).await?;
+        super::stale_scans(connection).await?;
+
+        let current = Self::values(connection).await?;
+        let corrected = current
+            .iter()
+            .filter_map(|(name, value)| {
+                let before = previous
+                    .iter()
+                    .find(|(previous, _)| previous == name)
+                    .map(|(_, value)| *value);
+                (before != Some(*value)).then_some(StatisticDrift {
+                    name: *name,
+                    previous: before,
+                    value: *value,
+                })
+            })
+            .collect::<Vec<_>>();
+        for drift in corrected.iter() {
+            log::warn!(
+                "Statistic `{}` drifted: {:?} -> {}",
+                drift.name,
+                drift.previous,
+                drift.value
+            );
+        }
+        super::summaries_changed();
+
+        Ok(RecomputeSummary {
+            statistics: current.len(),
+            corrected,
+        })
+    }
+
+    async fn values<'a, T>(connection: &'a T) -> Result<Vec<(Setting, i64)>, crate::KonarrError>
+    where
+        T: GeekConnection<Connection = T> + 'a,
+    {
+        Ok(ServerSettings::fetch_statistics(connection)
+            .await?
+            .into_iter()
+            .filter(|setting| setting.name.namespace() == Some(SettingNamespace::Stats))
+            .map(|setting| (setting.name, setting.value.parse().unwrap_or(0)))
+            .collect())
+    }
+}
 
 /// Calculate Statistics Task
-pub async fn statistics<'a, T>(connection: &'a T) -> Result<(), crate::KonarrError>
+pub async fn statistics<'a, T>(connection: &'a T) -> Result<StatisticsSummary, crate::KonarrError>
 where
     T: GeekConnection<Connection = T> + Send + Sync + 'a,
 {
     log::info!("Task - Calculating Statistics");
     user_statistics(connection).await?;
     project_statistics(connection).await?;
-    dependencies_statistics(connection).await?;
+    alerts_statistics(connection).await?;
+    let tags = dependencies_statistics(connection).await?;
+    super::summaries_changed();
 
-    Ok(())
+    Ok(StatisticsSummary { tags })
 }
 
 /// User Statistics Task
@@ -21,22 +143,51 @@ pub async fn user_statistics<'a, T>(connection: &'a T) -> Result<(), crate::Kona
 where
     T: GeekConnection<Connection = T> + Send + Sync + 'a,
 {
-    ServerSettings::update_statistic(
+    let computed_at = chrono::Utc::now();
+    ServerSettings::update_statistic_at(
         connection,
         Setting::StatsUsersTotal,
         Users::total(connection).await?,
+        computed_at,
     )
     .await?;
-    ServerSettings::update_statistic(
+    ServerSettings::update_statistic_at(
         connection,
         Setting::StatsUsersActive,
         Users::count_active(connection).await?,
+        computed_at,
     )
     .await?;
-    ServerSettings::update_statistic(
+    ServerSettings::update_statistic_at(
         connection,
         Setting::StatsUsersInactive,
         Users::count_inactive(connection).await?,
+        computed_at,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Alerts Statistics Task (resolved alerts, see [AlertEvents::reconcile])
+pub async fn alerts_statistics<'a, T>(connection: &'a T) -> Result<(), crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + Send + Sync + 'a,
+{
+    let computed_at = chrono::Utc::now();
+    ServerSettings::update_statistic_at(
+        connection,
+        Setting::StatsAlertsResolved,
+        AlertEvents::count_resolved(connection, None, None).await?,
+        computed_at,
+    )
+    .await?;
+    let since = computed_at - chrono::Duration::days(ALERTS_RESOLVED_RECENT_DAYS);
+    ServerSettings::update_statistic_at(
+        connection,
+        Setting::StatsAlertsResolvedRecent,
+        AlertEvents::count_resolved(connection, None, Some(since)).await?,
+        computed_at,
     )
     .await?;
 
@@ -48,49 +199,78 @@ pub async fn project_statistics<'a, T>(connection: &'a T) -> Result<(), crate::K
 where
     T: GeekConnection<Connection = T> + Send + Sync + 'a,
 {
-    ServerSettings::update_statistic(
+    let computed_at = chrono::Utc::now();
+    ServerSettings::update_statistic_at(
         connection,
         Setting::StatsProjectsTotal,
         Projects::count_active(connection).await?,
+        computed_at,
     )
     .await?;
-    ServerSettings::update_statistic(
+    ServerSettings::update_statistic_at(
         connection,
         Setting::StatsProjectsInactive,
         Projects::count_inactive(connection).await?,
+        computed_at,
     )
     .await?;
-    ServerSettings::update_statistic(
+    ServerSettings::update_statistic_at(
         connection,
         Setting::StatsProjectsArchived,
         Projects::count_archived(connection).await?,
+        computed_at,
     )
     .await?;
-    ServerSettings::update_statistic(
+    ServerSettings::update_statistic_at(
         connection,
         Setting::StatsProjectsServers,
         Projects::count_servers(connection).await?,
+        computed_at,
     )
     .await?;
-    ServerSettings::update_statistic(
+    ServerSettings::update_statistic_at(
         connection,
         Setting::StatsProjectsContainers,
         Projects::count_containers(connection).await?,
+        computed_at,
     )
     .await?;
+    for (health, setting) in [
+        (ProjectHealth::Green, Setting::StatsProjectsHealthGreen),
+        (ProjectHealth::Amber, Setting::StatsProjectsHealthAmber),
+        (ProjectHealth::Red, Setting::StatsProjectsHealthRed),
+    ] {
+        let filters = ProjectFilters {
+            health: Some(health),
+            ..Default::default()
+        };
+        ServerSettings::update_statistic_at(
+            connection,
+            setting,
+            Projects::count_filtered(connection, &filters).await?,
+            computed_at,
+        )
+        .await?;
+    }
 
     Ok(())
 }
 
 /// Dependency Statistics Task
-pub async fn dependencies_statistics<'a, T>(connection: &'a T) -> Result<(), crate::KonarrError>
+///
+/// The custom tags are not stored as settings, their counts are returned.
+pub async fn dependencies_statistics<'a, T>(
+    connection: &'a T,
+) -> Result<BTreeMap<String, i64>, crate::KonarrError>
 where
     T: GeekConnection<Connection = T> + Send + Sync + 'a,
 {
-    ServerSettings::update_statistic(
+    let computed_at = chrono::Utc::now();
+    ServerSettings::update_statistic_at(
         connection,
         Setting::StatsDependenciesTotal,
         Component::total(connection).await?,
+        computed_at,
     )
     .await?;
 
@@ -130,7 +310,95 @@ where
         )
         .await?;
 
-        ServerSettings::update_statistic(connection, setting, count).await?;
+        ServerSettings::update_statistic_at(connection, setting, count, computed_at).await?;
+    }
+
+    let managers = Component::count_by_manager(connection, None).await?;
+    for manager in COMPONENT_MANAGERS {
+        let count = managers
+            .iter()
+            .find(|(m, _)| *m == manager)
+            .map(|(_, count)| *count)
+            .unwrap_or(0);
+        ServerSettings::update_statistic_at(connection, manager.statistic(), count, computed_at)
+            .await?;
+    }
+
+    ComponentTags::counts(connection).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProjectType;
+
+    #[tokio::test]
+    async fn test_recompute_all() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("server/app", ProjectType::Container);
+        project.save(&connection).await?;
+        Statistics::recompute_all(&connection).await?;
+
+        // Drift (e.g. a lost update)
+        ServerSettings::update_statistic_at(
+            &connection,
+            Setting::StatsProjectsTotal,
+            42,
+            chrono::Utc::now(),
+        )
+        .await?;
+        // Gauges change between runs without drifting
+        ServerSettings::update_statistic_at(
+            &connection,
+            Setting::StorageDiskFree,
+            1024,
+            chrono::Utc::now(),
+        )
+        .await?;
+
+        let summary = Statistics::recompute_all(&connection).await?;
+        let active = Projects::count_active(&connection).await?;
+        assert!(summary.statistics > 0);
+        assert_eq!(
+            summary.corrected,
+            vec![StatisticDrift {
+                name: Setting::StatsProjectsTotal,
+                previous: Some(42),
+                value: active,
+            }]
+        );
+        let total = ServerSettings::fetch_by_name(&connection, Setting::StatsProjectsTotal).await?;
+        assert_eq!(total.value, active.to_string());
+        let free = ServerSettings::fetch_by_name(&connection, Setting::StorageDiskFree).await?;
+        assert_eq!(free.value, "1024");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_manager_statistics() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut project = Projects::new("web", ProjectType::Container);
+        project.save(&connection).await?;
+        let snapshot = crate::models::Snapshot::create(&connection).await?;
+        for purl in ["pkg:npm/react@18.0.0", "pkg:npm/vue@3.0.0"] {
+            let (mut component, version) = Component::from_purl(purl)?;
+            component.find_or_create(&connection).await?;
+            let mut version = crate::models::ComponentVersion::new(component.id, version.version);
+            version.save(&connection).await?;
+            crate::models::Dependencies::new(snapshot.id, component.id, version.id)
+                .save(&connection)
+                .await?;
+        }
+        project.add_snapshot(&connection, snapshot).await?;
+
+        dependencies_statistics(&connection).await?;
+
+        let stats = ServerSettings::fetch_statistics(&connection).await?;
+        let find = |setting| crate::models::settings::find_statistic(&stats, setting);
+        assert_eq!(find(Setting::StatsManagerNpm), 2);
+        assert_eq!(find(Setting::StatsManagerCargo), 0);
+        Ok(())
     }
-    Ok(())
 }
@@ -0,0 +1,154 @@
+//! # Task - Storage
+//!
+//! Collects the storage diagnostics (database, Grype DB and SBOM sizes, free disk space)
+//! and caches them as statistics settings.
+use geekorm::prelude::*;
+use std::path::Path;
+
+use crate::{
+    models::{settings::keys::Setting, ServerSettings},
+    Config,
+};
+
+/// Storage diagnostics
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StorageSummary {
+    /// Size of the database file (bytes)
+    pub database_size: u64,
+    /// Free disk space on the data path (bytes, if known)
+    pub disk_free: Option<u64>,
+    /// Size of the Grype database (bytes)
+    pub grypedb_size: u64,
+    /// Number of stored SBOM files
+    pub sboms_count: u64,
+    /// Total size of the stored SBOM files (bytes)
+    pub sboms_size: u64,
+}
+
+impl StorageSummary {
+    /// Collect the storage diagnostics from the file system
+    pub fn collect(config: &Config) -> Result<Self, crate::KonarrError> {
+        let data_path = config.data_path()?;
+
+        let database_size = config
+            .database
+            .path
+            .as_ref()
+            .and_then(|path| std::fs::metadata(path).ok())
+            .map(|meta| meta.len())
+            .unwrap_or_default();
+
+        let (_, grypedb_size) = directory_size(&data_path.join("grypedb"));
+        let (sboms_count, sboms_size) = directory_size(&config.sboms_path()?);
+
+        Ok(Self {
+            database_size,
+            disk_free: disk_free(data_path),
+            grypedb_size,
+            sboms_count,
+            sboms_size,
+        })
+    }
+}
+
+impl From<&StorageSummary> for super::TaskStats {
+    fn from(summary: &StorageSummary) -> Self {
+        Self::from_iter([
+            ("database", summary.database_size),
+            ("grypedb", summary.grypedb_size),
+            ("sboms", summary.sboms_count),
+            ("sboms_size", summary.sboms_size),
+        ])
+    }
+}
+
+/// Storage diagnostics task
+pub async fn storage<'a, T>(
+    config: &Config,
+    connection: &'a T,
+) -> Result<StorageSummary, crate::KonarrError>
+where
+    T: GeekConnection<Connection = T> + 'a,
+{
+    let computed_at = chrono::Utc::now();
+    log::info!("Collecting storage diagnostics");
+    let summary = StorageSummary::collect(config)?;
+    log::debug!("Storage diagnostics: {:?}", summary);
+
+    let statistics = [
+        (Setting::StorageDatabaseSize, summary.database_size),
+        (
+            Setting::StorageDiskFree,
+            summary.disk_free.unwrap_or_default(),
+        ),
+        (Setting::StorageGrypeSize, summary.grypedb_size),
+        (Setting::StorageSbomsCount, summary.sboms_count),
+        (Setting::StorageSbomsSize, summary.sboms_size),
+    ];
+    for (setting, value) in statistics {
+        ServerSettings::update_statistic_at(connection, setting, value as i64, computed_at).await?;
+    }
+
+    Ok(summary)
+}
+
+/// Number of files and total size of a directory (recursive)
+fn directory_size(path: &Path) -> (u64, u64) {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return (0, 0);
+    };
+    entries
+        .flatten()
+        .fold((0, 0), |(count, size), entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => {
+                let (c, s) = directory_size(&entry.path());
+                (count + c, size + s)
+            }
+            Ok(meta) => (count + 1, size + meta.len()),
+            Err(_) => (count, size),
+        })
+}
+
+/// Free disk space available for the path (bytes)
+#[cfg(unix)]
+pub fn disk_free(path: &Path) -> Option<u64> {
+    rustix::fs::statvfs(path)
+        .map(|stat| stat.f_bavail * stat.f_frsize)
+        .map_err(|e| log::warn!("Failed to get free disk space: {}", e))
+        .ok()
+}
+
+/// Free disk space available for the path (bytes)
+#[cfg(not(unix))]
+pub fn disk_free(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_storage() -> Result<(), crate::KonarrError> {
+        let connection = crate::models::database_test().await?;
+
+        let mut config = Config::default();
+        config.set_data_path(std::env::temp_dir().join("konarr-test-storage"));
+        std::fs::create_dir_all(config.sboms_path()?.join("nested"))?;
+        std::fs::write(config.sboms_path()?.join("a.cdx.json"), [0u8; 100])?;
+        std::fs::write(config.sboms_path()?.join("nested/b.cdx.json"), [0u8; 50])?;
+
+        let summary = storage(&config, &connection).await?;
+        assert_eq!(summary.sboms_count, 2);
+        assert_eq!(summary.sboms_size, 150);
+        assert_eq!(summary.grypedb_size, 0);
+        #[cfg(unix)]
+        assert!(summary.disk_free.is_some());
+
+        let count = ServerSettings::fetch_by_name(&connection, Setting::StorageSbomsSize).await?;
+        assert_eq!(count.value, "150");
+
+        std::fs::remove_dir_all(config.data_path()?).ok();
+        Ok(())
+    }
+}
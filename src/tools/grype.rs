@@ -8,6 +8,9 @@ use log::info;
 use super::{Tool, ToolConfig};
 use crate::KonarrError;
 
+/// Image scope Grype scans with (`--scope`)
+pub(crate) const GRYPE_SCOPE: &str = "all-layers";
+
 /// Syft Tool
 #[derive(Debug)]
 pub struct Grype;
@@ -55,7 +58,7 @@ impl Tool for Grype {
 
             log::debug!("Run Grype (all layers, output to temp file)");
             let output = tokio::process::Command::new(&path)
-                .args(&["-s", "all-layers", "-o", opath.as_str(), image.as_str()])
+                .args(&["-s", GRYPE_SCOPE, "-o", opath.as_str(), image.as_str()])
                 .envs([
                     // Disable auto update
                     ("GRYPE_DB_AUTO_UPDATE", "false"),
@@ -1,5 +1,9 @@
 //! Tools to analyze the BOM of a container image
-use std::{fmt::Display, path::PathBuf};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
 use crate::{Config, KonarrError};
 use async_trait::async_trait;
@@ -40,28 +44,12 @@ pub trait Tool {
         }
     }
 
-    /// Find the Tool
+    /// Find the Tool (see [ToolPaths::search_dirs])
     fn find(binary: &str) -> Result<PathBuf, KonarrError>
     where
         Self: Sized,
     {
-        let locations = vec![
-            "/usr/local/toolcache",
-            "/usr/local/bin/",
-            "/usr/bin/",
-            "/bin/",
-            "/snap/bin/",
-        ];
-        for loc in locations {
-            let bin = PathBuf::from(loc).join(binary);
-            if bin.exists() {
-                return Ok(bin);
-            }
-        }
-        Err(KonarrError::ToolError(format!(
-            "Can not find tool {}",
-            binary
-        )))
+        ToolPaths::from_env().find(binary)
     }
 
     /// Get the remote version of the Tool
@@ -78,19 +66,24 @@ pub trait Tool {
         Self: Sized;
 }
 
-/// Gets a list of available tools
+/// Run the tool to generate the SBOM for the image
 pub async fn run(config: &Config, image: impl Into<String>) -> Result<String, KonarrError> {
+    Ok(scan(config, image).await?.sbom)
+}
+
+/// Run the tool to generate the SBOM for the image and return the scan information
+pub async fn scan(config: &Config, image: impl Into<String>) -> Result<ToolScan, KonarrError> {
     let image = image.into();
 
     let mut tools = ToolConfig::tools().await?;
 
-    if let Some(tool_name) = &config.agent.tool {
+    let tool = if let Some(tool_name) = &config.agent.tool {
         log::info!("Using tool: {}", tool_name);
 
         let tool = tools
             .iter_mut()
             .find(|t| t.name.to_lowercase() == tool_name.to_lowercase())
-            .ok_or(KonarrError::ToolError(format!(
+            .ok_or(KonarrError::ToolNotAvailable(format!(
                 "Tool not found: {}",
                 tool_name
             )))?;
@@ -101,27 +94,103 @@ pub async fn run(config: &Config, image: impl Into<String>) -> Result<String, Ko
                 tool.install().await?;
             } else {
                 log::info!("Tool is not available: {}", tool.name);
-                return Err(KonarrError::ToolError(format!(
+                return Err(KonarrError::ToolNotAvailable(format!(
                     "Tool not available: {}",
                     tool_name
                 )));
             }
         }
         log::info!("Tool is available: {}", tool);
-
-        log::info!("Running tool: {}", tool);
-        tool.run(image).await
+        tool.clone()
     } else {
         log::info!("No tool specified, trying to find a tool");
 
-        for tool in tools.iter() {
-            if tool.is_available() {
-                log::info!("Running tool: {}", tool);
-                return tool.run(image).await;
-            }
+        tools
+            .into_iter()
+            .find(|t| t.is_available())
+            .ok_or(KonarrError::ToolNotAvailable("No tools found".to_string()))?
+    };
+
+    log::info!("Running tool: {}", tool);
+    let start = std::time::Instant::now();
+    let sbom = tool.run(image.clone()).await?;
+
+    Ok(ToolScan {
+        scope: tool.scope().map(|s| s.to_string()),
+        tool: tool.name,
+        tool_version: tool.version,
+        target: image,
+        duration: start.elapsed(),
+        sbom,
+    })
+}
+
+/// Run Syft against a directory (`dir:<path>`) and return the scan information
+///
+/// Used for the extra scan paths of the agent, Syft is used whatever tool is configured
+/// as the other tools do not scan directories the same way.
+pub async fn scan_dir(
+    config: &Config,
+    path: impl AsRef<std::path::Path>,
+) -> Result<ToolScan, KonarrError> {
+    let mut tool = Syft::init().await;
+    if !tool.is_available() {
+        if config.agent.tool_auto_install {
+            log::info!("Syft is not available, trying to install it");
+            tool.install().await?;
+        } else {
+            return Err(KonarrError::ToolNotAvailable(
+                "Syft is required to scan the extra paths".to_string(),
+            ));
         }
+    }
+
+    let target = format!("dir:{}", path.as_ref().display());
+    log::info!("Running tool: {} ({})", tool, target);
+    let start = std::time::Instant::now();
+    let sbom = tool.run(target.clone()).await?;
+
+    Ok(ToolScan {
+        tool: tool.name,
+        tool_version: tool.version,
+        target,
+        // Directories don't have layers
+        scope: None,
+        duration: start.elapsed(),
+        sbom,
+    })
+}
 
-        Err(KonarrError::ToolError("No tools found".to_string()))
+/// Tool Scan Results
+#[derive(Debug, Clone)]
+pub struct ToolScan {
+    /// Name of the tool used
+    pub tool: String,
+    /// Version of the tool used
+    pub tool_version: String,
+    /// Scan target (container image)
+    pub target: String,
+    /// Scope the image was scanned with (see [ToolConfig::scope])
+    pub scope: Option<String>,
+    /// Duration of the scan
+    pub duration: std::time::Duration,
+    /// SBOM output of the tool
+    pub sbom: String,
+}
+
+impl ToolScan {
+    /// Scan metadata to be added to the Snapshot (`scan.*` keys)
+    pub fn metadata(&self) -> Vec<(&'static str, String)> {
+        let mut metadata = vec![
+            ("scan.tool", self.tool.clone()),
+            ("scan.tool.version", self.tool_version.clone()),
+            ("scan.target", self.target.clone()),
+            ("scan.duration_ms", self.duration.as_millis().to_string()),
+        ];
+        if let Some(scope) = &self.scope {
+            metadata.push(("scan.scope", scope.clone()));
+        }
+        metadata
     }
 }
 
@@ -142,7 +211,166 @@ pub struct ToolConfig {
     pub output: PathBuf,
 }
 
+/// System wide directories the tools are installed to
 const TOOLCACHE_DIRS: &[&str] = &["/usr/local/toolcache", "/usr/local/bin/"];
+/// System directories the tools are searched in
+const TOOL_SEARCH_DIRS: &[&str] = &["/usr/bin/", "/bin/", "/snap/bin/"];
+
+/// Toolcache directory from the agent configuration (`agent.toolcache_dir`)
+static TOOLCACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Directories the tools are searched in and installed to
+///
+/// In order: the configured toolcache (`agent.toolcache_dir` / `KONARR_AGENT_TOOLCACHE`),
+/// the system directories, `$HOME/.local/bin`, `$XDG_DATA_HOME/konarr/tools` and the
+/// temporary directory. The `PATH` is searched last.
+#[derive(Debug, Clone, Default)]
+pub struct ToolPaths {
+    /// Configured toolcache directory
+    pub toolcache: Option<PathBuf>,
+    /// System wide install directories
+    pub system: Vec<PathBuf>,
+    /// Home directory of the user (`$HOME`)
+    pub home: Option<PathBuf>,
+    /// Data directory of the user (`$XDG_DATA_HOME`, default `$HOME/.local/share`)
+    pub data_home: Option<PathBuf>,
+    /// Directories of the `PATH` environment variable
+    pub path: Vec<PathBuf>,
+}
+
+impl ToolPaths {
+    /// Set the process wide toolcache directory (from the agent configuration)
+    pub fn init(toolcache: Option<PathBuf>) {
+        if let Some(toolcache) = toolcache.filter(|dir| !dir.as_os_str().is_empty()) {
+            if TOOLCACHE_DIR.set(toolcache).is_err() {
+                log::debug!("Toolcache directory already initialised");
+            }
+        }
+    }
+
+    /// Directories from the configuration and the environment variables
+    pub fn from_env() -> Self {
+        let env = |name: &str| {
+            std::env::var_os(name)
+                .filter(|value| !value.is_empty())
+                .map(PathBuf::from)
+        };
+        Self {
+            toolcache: TOOLCACHE_DIR
+                .get()
+                .cloned()
+                .or_else(|| env("KONARR_AGENT_TOOLCACHE")),
+            system: TOOLCACHE_DIRS.iter().map(PathBuf::from).collect(),
+            home: env("HOME"),
+            data_home: env("XDG_DATA_HOME"),
+            path: std::env::var_os("PATH")
+                .map(|path| std::env::split_paths(&path).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Directories of the user (`$HOME/.local/bin` and `$XDG_DATA_HOME/konarr/tools`)
+    pub fn user_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = vec![];
+        if let Some(home) = &self.home {
+            dirs.push(home.join(".local").join("bin"));
+        }
+        match (&self.data_home, &self.home) {
+            (Some(data), _) => dirs.push(data.join("konarr").join("tools")),
+            (None, Some(home)) => dirs.push(home.join(".local/share/konarr/tools")),
+            (None, None) => {}
+        }
+        dirs
+    }
+
+    /// Directories the tools can be installed to (in order of preference)
+    pub fn install_dirs(&self) -> Vec<PathBuf> {
+        self.toolcache
+            .iter()
+            .cloned()
+            .chain(self.system.iter().cloned())
+            .chain(self.user_dirs())
+            .chain(std::iter::once(
+                std::env::temp_dir().join("konarr").join("tools"),
+            ))
+            .collect()
+    }
+
+    /// Directories the tools are searched in (the install directories, the system
+    /// directories and the `PATH`)
+    pub fn search_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = vec![];
+        let system = TOOL_SEARCH_DIRS.iter().map(PathBuf::from);
+        for dir in self
+            .install_dirs()
+            .into_iter()
+            .chain(system)
+            .chain(self.path.iter().cloned())
+        {
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+        dirs
+    }
+
+    /// Find a tool binary
+    pub fn find(&self, binary: &str) -> Result<PathBuf, KonarrError> {
+        self.search_dirs()
+            .into_iter()
+            .map(|dir| dir.join(binary))
+            .find(|bin| bin.is_file())
+            .ok_or_else(|| KonarrError::ToolError(format!("Can not find tool {}", binary)))
+    }
+
+    /// First writable directory to install the tools to
+    ///
+    /// The user directories are created if missing. The error lists every directory
+    /// which was tried and why it was rejected.
+    pub fn install_dir(&self) -> Result<PathBuf, KonarrError> {
+        let system = self.system.clone();
+        let mut rejected = vec![];
+        for dir in self.install_dirs() {
+            match Self::check_writable(&dir, !system.contains(&dir)) {
+                Ok(()) => return Ok(dir),
+                Err(reason) => {
+                    log::debug!(
+                        "Toolcache directory rejected: {} ({})",
+                        dir.display(),
+                        reason
+                    );
+                    rejected.push(format!("{} ({})", dir.display(), reason));
+                }
+            }
+        }
+        Err(KonarrError::ToolError(format!(
+            "No writable directory to install the tools, tried: {}",
+            rejected.join(", ")
+        )))
+    }
+
+    /// Check a directory can be written to (creating it if allowed)
+    fn check_writable(dir: &Path, create: bool) -> Result<(), String> {
+        if !dir.exists() {
+            if !create {
+                return Err("does not exist".to_string());
+            }
+            std::fs::create_dir_all(dir).map_err(|e| format!("can not create: {}", e))?;
+        }
+        let metadata = dir.metadata().map_err(|e| e.to_string())?;
+        if !metadata.is_dir() {
+            return Err("not a directory".to_string());
+        }
+        if metadata.permissions().readonly() {
+            return Err("read-only".to_string());
+        }
+        // The permissions do not tell if the current user can write to it
+        let probe = dir.join(format!(".konarr-{}", std::process::id()));
+        std::fs::write(&probe, b"")
+            .and_then(|_| std::fs::remove_file(&probe))
+            .map_err(|e| format!("not writable: {}", e))
+    }
+}
 
 impl ToolConfig {
     /// New Tool Configuration
@@ -167,6 +395,18 @@ impl ToolConfig {
         Ok(tools)
     }
 
+    /// Image scope (layers) the Tool scans with
+    ///
+    /// Grype is run with `--scope all-layers`, Syft uses its default (`squashed`) and Trivy
+    /// doesn't have a scope option.
+    pub fn scope(&self) -> Option<&'static str> {
+        match self.name.as_str() {
+            "grype" => Some(grype::GRYPE_SCOPE),
+            "syft" => Some("squashed"),
+            _ => None,
+        }
+    }
+
     /// Check if the Tool is available
     pub fn is_available(&self) -> bool {
         if self.path.is_some() && !self.version.is_empty() {
@@ -230,16 +470,10 @@ impl ToolConfig {
         if let Some(ipath) = &self.install_path {
             log::debug!("Running install script: {}", ipath.display());
 
-            let output: PathBuf = TOOLCACHE_DIRS
-                .iter()
-                .map(|d| PathBuf::from(d))
-                .find(|d| {
-                    d.is_dir() && d.exists() && !d.metadata().unwrap().permissions().readonly()
-                })
-                .unwrap_or_else(|| std::env::temp_dir());
+            let output = ToolPaths::from_env().install_dir()?;
             log::debug!("Toolcache directory: {}", output.display());
 
-            tokio::process::Command::new("sh")
+            let result = tokio::process::Command::new("sh")
                 .arg(ipath)
                 .args(&["-b", output.display().to_string().as_str()])
                 .output()
@@ -247,6 +481,14 @@ impl ToolConfig {
                 .map_err(|e| {
                     KonarrError::ToolError(format!("Failed to run install script: {}", e))
                 })?;
+            if !result.status.success() {
+                return Err(KonarrError::ToolError(format!(
+                    "Failed to install {} to {}: {}",
+                    self.name,
+                    output.display(),
+                    String::from_utf8_lossy(&result.stderr).trim()
+                )));
+            }
             log::info!("Successfully installed {}", self.name);
 
             self.path = self.find().ok();
@@ -287,21 +529,30 @@ impl ToolConfig {
             reqwest::header::ACCEPT,
             reqwest::header::HeaderValue::from_static("application/vnd.github.v3+json"),
         );
+        // Optional token to raise the GitHub API rate limits
+        if let Some(token) = std::env::var("GITHUB_TOKEN").ok().filter(|t| !t.is_empty()) {
+            let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|e| KonarrError::ToolError(format!("Invalid GITHUB_TOKEN: {}", e)))?;
+            value.set_sensitive(true);
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
 
-        let client = reqwest::Client::builder()
+        let client = crate::utils::config::client_builder()
             .user_agent(format!("Konarr/{}", crate::KONARR_VERSION))
             .default_headers(headers)
             .build()?;
-        let response = client.get(&url).send().await?;
-
-        if !response.status().is_success() {
-            log::error!("Failed to get release from GitHub: {}", repository);
-            return Err(KonarrError::ToolError(format!(
-                "Failed to get release: {}",
-                response.status()
-            )));
+        let response = crate::utils::http::HttpCache::global()
+            .get_conditional(&client, &url)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to get release from GitHub: {}", repository);
+                KonarrError::ToolError(format!("Failed to get release: {}", e))
+            })?;
+        if !response.is_modified() {
+            log::debug!("GitHub release not modified: {}", repository);
         }
-        let json: serde_json::Value = response.json().await?;
+
+        let json: serde_json::Value = serde_json::from_slice(response.body())?;
         let version = json["tag_name"]
             .as_str()
             .ok_or(KonarrError::ToolError("No tag_name".to_string()))?;
@@ -338,3 +589,89 @@ impl Display for ToolConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_tool_paths_readonly() -> Result<(), KonarrError> {
+        let base = std::env::temp_dir().join(format!("konarr-test-tools-{}", std::process::id()));
+        let readonly = base.join("readonly");
+        std::fs::create_dir_all(&readonly)?;
+        std::fs::set_permissions(&readonly, std::fs::Permissions::from_mode(0o555))?;
+
+        let paths = ToolPaths {
+            toolcache: Some(readonly.clone()),
+            system: vec![base.join("missing")],
+            home: Some(base.join("home")),
+            data_home: None,
+            path: vec![base.join("path")],
+        };
+        assert_eq!(paths.install_dirs()[0], readonly);
+        assert_eq!(paths.user_dirs()[0], base.join("home/.local/bin"));
+        assert_eq!(
+            paths.user_dirs()[1],
+            base.join("home/.local/share/konarr/tools")
+        );
+
+        // The read-only toolcache and missing system directory fall back to the user
+        let install = paths.install_dir()?;
+        assert_eq!(install, base.join("home/.local/bin"));
+        assert!(install.is_dir());
+
+        // Installed tools and the `PATH` are searched
+        std::fs::write(install.join("syft"), b"")?;
+        std::fs::create_dir_all(base.join("path"))?;
+        std::fs::write(base.join("path").join("trivy"), b"")?;
+        assert_eq!(paths.find("syft")?, install.join("syft"));
+        assert_eq!(paths.find("trivy")?, base.join("path/trivy"));
+        assert!(paths.find("konarr-missing-tool").is_err());
+
+        // Reasons the directories were rejected
+        assert_eq!(
+            ToolPaths::check_writable(&readonly, true),
+            Err("read-only".to_string())
+        );
+        assert_eq!(
+            ToolPaths::check_writable(&base.join("missing"), false),
+            Err("does not exist".to_string())
+        );
+
+        std::fs::set_permissions(&readonly, std::fs::Permissions::from_mode(0o755))?;
+        std::fs::remove_dir_all(&base)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_metadata() {
+        let grype = ToolConfig {
+            name: "grype".to_string(),
+            ..Default::default()
+        };
+        let scan = ToolScan {
+            tool: grype.name.clone(),
+            tool_version: "0.80.0".to_string(),
+            target: "nginx:latest".to_string(),
+            scope: grype.scope().map(|s| s.to_string()),
+            duration: std::time::Duration::from_millis(1500),
+            sbom: String::new(),
+        };
+        let metadata = scan.metadata();
+        assert!(metadata.contains(&("scan.scope", "all-layers".to_string())));
+        assert!(metadata.contains(&("scan.duration_ms", "1500".to_string())));
+
+        // Trivy doesn't have a scope option
+        let trivy = ToolConfig {
+            name: "trivy".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(trivy.scope(), None);
+        let scan = ToolScan {
+            scope: None,
+            ..scan
+        };
+        assert!(!scan.metadata().iter().any(|(key, _)| *key == "scan.scope"));
+    }
+}
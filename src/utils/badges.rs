@@ -0,0 +1,175 @@
+//! # Badges
+//!
+//! Small (shields.io style) SVG badges for the projects, rendered from a template
+//! without any external service.
+
+/// Red (critical alerts)
+pub const BADGE_RED: &str = "#e05d44";
+/// Orange (high alerts)
+pub const BADGE_ORANGE: &str = "#fe7d37";
+/// Yellow (medium / low alerts)
+pub const BADGE_YELLOW: &str = "#dfb317";
+/// Green (no alerts)
+pub const BADGE_GREEN: &str = "#4c1";
+/// Blue (informational)
+pub const BADGE_BLUE: &str = "#007ec6";
+/// Grey (unknown / no data)
+pub const BADGE_GREY: &str = "#9f9f9f";
+
+/// Badge Style
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BadgeStyle {
+    /// Rounded corners with a gradient
+    #[default]
+    Plastic,
+    /// Flat (no gradient)
+    Flat,
+}
+
+impl From<&str> for BadgeStyle {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "flat" => BadgeStyle::Flat,
+            _ => BadgeStyle::Plastic,
+        }
+    }
+}
+
+/// Badge (label and message)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Badge {
+    /// Label (left side)
+    pub label: String,
+    /// Message (right side)
+    pub message: String,
+    /// Colour of the message
+    pub color: &'static str,
+}
+
+impl Badge {
+    /// Create a new badge
+    pub fn new(label: impl Into<String>, message: impl Into<String>, color: &'static str) -> Self {
+        Self {
+            label: label.into(),
+            message: message.into(),
+            color,
+        }
+    }
+
+    /// Alerts badge (coloured by the highest severity present)
+    pub fn alerts(total: u32, critical: u32, high: u32) -> Self {
+        let color = if critical > 0 {
+            BADGE_RED
+        } else if high > 0 {
+            BADGE_ORANGE
+        } else if total > 0 {
+            BADGE_YELLOW
+        } else {
+            BADGE_GREEN
+        };
+        Self::new("alerts", total.to_string(), color)
+    }
+
+    /// Dependencies badge
+    pub fn dependencies(total: u32) -> Self {
+        Self::new("dependencies", total.to_string(), BADGE_BLUE)
+    }
+
+    /// Badge for a project without any data (no snapshot)
+    pub fn unknown(label: impl Into<String>) -> Self {
+        Self::new(label, "unknown", BADGE_GREY)
+    }
+
+    /// Render the badge as an SVG
+    pub fn render(&self, style: BadgeStyle) -> String {
+        let label = escape(&self.label);
+        let message = escape(&self.message);
+        let label_width = text_width(&self.label);
+        let message_width = text_width(&self.message);
+        let width = label_width + message_width;
+
+        let (radius, gradient) = match style {
+            BadgeStyle::Plastic => (
+                3,
+                "<linearGradient id=\"s\" x2=\"0\" y2=\"100%\">\
+                <stop offset=\"0\" stop-color=\"#bbb\" stop-opacity=\".1\"/>\
+                <stop offset=\"1\" stop-opacity=\".1\"/></linearGradient>",
+            ),
+            BadgeStyle::Flat => (0, ""),
+        };
+        let overlay = if gradient.is_empty() {
+            String::new()
+        } else {
+            format!("<rect width=\"{width}\" height=\"20\" fill=\"url(#s)\"/>")
+        };
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"20\" role=\"img\" aria-label=\"{label}: {message}\">\
+            <title>{label}: {message}</title>{gradient}\
+            <clipPath id=\"r\"><rect width=\"{width}\" height=\"20\" rx=\"{radius}\" fill=\"#fff\"/></clipPath>\
+            <g clip-path=\"url(#r)\">\
+            <rect width=\"{label_width}\" height=\"20\" fill=\"#555\"/>\
+            <rect x=\"{label_width}\" width=\"{message_width}\" height=\"20\" fill=\"{color}\"/>{overlay}</g>\
+            <g fill=\"#fff\" text-anchor=\"middle\" font-family=\"Verdana,Geneva,DejaVu Sans,sans-serif\" font-size=\"11\">\
+            <text x=\"{label_x}\" y=\"14\">{label}</text>\
+            <text x=\"{message_x}\" y=\"14\">{message}</text></g></svg>",
+            color = self.color,
+            label_x = label_width / 2,
+            message_x = label_width + message_width / 2,
+        )
+    }
+}
+
+/// Approximate width of the text (Verdana 11px) including the padding
+fn text_width(text: &str) -> u32 {
+    let width: u32 = text
+        .chars()
+        .map(|c| match c {
+            'i' | 'l' | 'j' | 't' | 'f' | 'r' | '.' | ',' | ':' | ' ' => 4,
+            'm' | 'w' | 'M' | 'W' => 10,
+            _ if c.is_uppercase() => 8,
+            _ => 7,
+        })
+        .sum();
+    width + 10
+}
+
+/// Escape the XML special characters
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_badges() {
+        assert_eq!(Badge::alerts(3, 1, 2).color, BADGE_RED);
+        assert_eq!(Badge::alerts(3, 0, 2).color, BADGE_ORANGE);
+        assert_eq!(Badge::alerts(3, 0, 0).color, BADGE_YELLOW);
+        let clean = Badge::alerts(0, 0, 0);
+        assert_eq!(clean.color, BADGE_GREEN);
+        assert_eq!(clean.message, "0");
+    }
+
+    #[test]
+    fn test_render() {
+        let svg = Badge::dependencies(42).render(BadgeStyle::default());
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains("<title>dependencies: 42</title>"));
+        assert!(svg.contains("linearGradient"));
+        assert!(svg.contains(BADGE_BLUE));
+
+        let flat = Badge::dependencies(42).render(BadgeStyle::from("flat"));
+        assert!(!flat.contains("linearGradient"));
+        assert!(flat.contains("rx=\"0\""));
+
+        let escaped = Badge::new("<a>", "b&c", BADGE_GREY).render(BadgeStyle::Flat);
+        assert!(escaped.contains("&lt;a&gt;: b&amp;c"));
+    }
+}
@@ -1,7 +1,10 @@
 //! # Catalogue
 use std::collections::HashMap;
 
-use crate::models::{Component, ComponentManager, ComponentType};
+use crate::{
+    bom::processors::glob_match,
+    models::{Component, ComponentManager, ComponentType},
+};
 
 const CATALOGUE: &str = include_str!("data.yml");
 
@@ -12,6 +15,9 @@ pub struct Catalogue {
     aliases: HashMap<String, String>,
     /// Component Mapping Table
     catalogue: HashMap<String, ComponentType>,
+    /// Custom tags by PURL pattern (`*` wildcards)
+    #[serde(default)]
+    tags: HashMap<String, Vec<String>>,
 }
 
 impl Catalogue {
@@ -30,34 +36,62 @@ impl Catalogue {
     ///
     /// Match manager -> type
     pub fn catalogue(&self, component: &mut Component) -> Result<bool, crate::KonarrError> {
-        let comp_purl = component.purl();
-
-        // Exact PURL matching
-        if let Some(comp) = self.catalogue.get(&comp_purl) {
-            if component.component_type != *comp {
-                log::debug!("Updating component type for: {}", comp_purl);
-                component.component_type = comp.clone();
+        if let Some(comp) = self.lookup(component) {
+            if component.component_type != comp {
+                log::debug!("Updating component type for: {}", component.purl());
+                component.component_type = comp;
                 return Ok(true);
             }
-        } else {
-            let wildcards = vec![
-                format!("pkg:*/{}", component.name),
-                format!("pkg:{}/*", component.manager),
-            ];
-
-            for wildcard in wildcards.iter() {
-                if let Some(comp) = self.catalogue.get(wildcard) {
-                    if component.component_type != *comp {
-                        log::debug!("Updating component type for: {}", comp_purl);
-                        component.component_type = comp.clone();
-                        return Ok(true);
-                    }
-                }
-            }
         }
         Ok(false)
     }
 
+    /// Lookup the component type in the catalogue
+    ///
+    /// Exact PURL matches are checked first and then the wildcards (`pkg:*/name` and `pkg:manager/*`).
+    pub fn lookup(&self, component: &Component) -> Option<ComponentType> {
+        let comp_purl = component.purl();
+
+        if let Some(comp) = self.catalogue.get(&comp_purl) {
+            return Some(comp.clone());
+        }
+        [
+            format!("pkg:*/{}", component.name),
+            format!("pkg:{}/*", component.manager),
+        ]
+        .iter()
+        .find_map(|wildcard| self.catalogue.get(wildcard).cloned())
+    }
+
+    /// Custom tags of the component (every matching PURL pattern, sorted)
+    pub fn tags(&self, component: &Component) -> Vec<String> {
+        let comp_purl = component.purl();
+        let mut tags: Vec<String> = self
+            .tags
+            .iter()
+            .filter(|(pattern, _)| glob_match(pattern, &comp_purl))
+            .flat_map(|(_, tags)| tags.iter().cloned())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Classify the component using the catalogue and the name based fallback
+    ///
+    /// Returns `None` if the component could not be classified (the fallback only
+    /// counts if it is more specific than a library).
+    pub fn classify(&self, component: &Component) -> Option<ComponentType> {
+        self.lookup(component).or_else(|| {
+            let mut fallback = component.clone();
+            Self::catalogue_old(&mut fallback).ok()?;
+            match fallback.component_type {
+                ComponentType::Library | ComponentType::Unknown => None,
+                comp => Some(comp),
+            }
+        })
+    }
+
     /// Set the component type based on the name of the component
     ///
     /// This is a simple and quick method to set the component type based on the name of the
@@ -92,10 +126,29 @@ impl Catalogue {
                     component.component_type = ComponentType::CryptographyLibrary;
                 }
                 // Databases
-                "mysql" | "mariadb" | "postgresql" | "sqlite" | "mongodb" | "redis"
-                | "cassandra" => {
+                "mysql"
+                | "mariadb"
+                | "postgresql"
+                | "sqlite"
+                | "mongodb"
+                | "redis"
+                | "cassandra"
+                | "libsqlite3-0"
+                | "sqlite-libs"
+                | "libpq"
+                | "libpq5"
+                | "mariadb-connector-c"
+                | "libmariadb3"
+                | "libmysqlclient21" => {
                     component.component_type = ComponentType::Database;
                 }
+                // Compression Libraries
+                "gzip" | "bzip2" | "tar" | "zip" | "unzip" | "zlib" | "zlib1g" | "libbz2"
+                | "libbz2-1.0" | "xz" | "xz-libs" | "xz-utils" | "liblzma5" | "zstd"
+                | "zstd-libs" | "libzstd1" | "lz4-libs" | "liblz4-1" | "brotli" | "brotli-libs"
+                | "libbrotli1" | "libarchive" | "libarchive13" => {
+                    component.component_type = ComponentType::CompressionLibrary;
+                }
                 // Applications
                 "curl" | "wget" | "git" | "grep" | "jq" | "nginx" => {
                     component.component_type = ComponentType::Application;
@@ -159,6 +212,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tags() {
+        let mut catalogue = Catalogue::default();
+        catalogue.tags.insert(
+            "pkg:npm/@acme/*".to_string(),
+            vec!["internal-sdk".to_string()],
+        );
+        catalogue
+            .tags
+            .insert("pkg:*/openssl".to_string(), vec!["crypto".to_string()]);
+
+        let (comp, _ver) = Component::from_purl("pkg:npm/%40acme/sdk-core@1.0.0").unwrap();
+        assert_eq!(catalogue.tags(&comp), vec!["internal-sdk".to_string()]);
+        let (comp, _ver) = Component::from_purl("pkg:deb/debian/openssl").unwrap();
+        assert_eq!(catalogue.tags(&comp), vec!["crypto".to_string()]);
+        let (comp, _ver) = Component::from_purl("pkg:npm/left-pad").unwrap();
+        assert!(catalogue.tags(&comp).is_empty());
+    }
+
     #[test]
     fn test_catalogue() {
         let catalogue = Catalogue::new();
@@ -191,4 +263,36 @@ mod tests {
             assert_eq!(&comp.component_type, expected);
         }
     }
+
+    #[test]
+    fn test_classify() {
+        let catalogue = Catalogue::new();
+
+        let data = vec![
+            // Catalogue (wildcards)
+            (
+                "pkg:apk/alpine/zstd-libs",
+                Some(ComponentType::CompressionLibrary),
+            ),
+            (
+                "pkg:deb/debian/tar",
+                Some(ComponentType::CompressionLibrary),
+            ),
+            ("pkg:apk/alpine/sqlite-libs", Some(ComponentType::Database)),
+            // Name based fallback
+            (
+                "pkg:deb/debian/libzstd1",
+                Some(ComponentType::CompressionLibrary),
+            ),
+            ("pkg:deb/debian/libpq5", Some(ComponentType::Database)),
+            // Unclassified
+            ("pkg:deb/debian/libfoo", None),
+            ("pkg:cargo/serde", None),
+        ];
+
+        for (purl, expected) in data {
+            let (comp, _ver) = Component::from_purl(purl).unwrap();
+            assert_eq!(catalogue.classify(&comp), expected, "{}", purl);
+        }
+    }
 }
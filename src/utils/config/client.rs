@@ -7,6 +7,19 @@ impl Config {
     pub async fn database(&self) -> Result<libsql::Database, Error> {
         self.database.database().await
     }
+
+    /// Get the Konarr Client for an Agent (token and optional client certificate)
+    pub fn agent_client(&self, token: String) -> Result<KonarrClient, Error> {
+        let mut builder = KonarrClient::init()
+            .base(self.server.api_url()?)?
+            .token(token);
+
+        if let (Some(cert), Some(key)) = (&self.agent.client_cert, &self.agent.client_key) {
+            log::debug!("Loading agent client certificate: {}", cert.display());
+            builder = builder.client_identity(&std::fs::read(cert)?, &std::fs::read(key)?)?;
+        }
+        builder.build()
+    }
 }
 
 impl ServerConfig {
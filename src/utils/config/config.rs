@@ -7,32 +7,76 @@ use url::Url;
 
 impl Config {
     /// Load the Configuration
+    ///
+    /// If the configuration file does not exist and `KONARR_CONFIG_OPTIONAL` is set,
+    /// the configuration is built only from the environment (see [Config::load_env]).
     pub fn load(path: &PathBuf) -> Result<Self, Error> {
+        if !path.exists() && Self::config_optional() {
+            debug!(
+                "Configuration file {:?} not found, using environment only",
+                path
+            );
+            return Self::load_env();
+        }
         debug!("Loading Configuration: {:?}", path);
 
         let figment = Figment::new()
             .merge(figment::providers::Yaml::file(path))
             .merge(figment::providers::Env::prefixed("KONARR_"));
 
+        let mut config = Self::extract(figment)?;
+        config.path = path.clone();
+
+        debug!("Finished Loading Configuration");
+        Ok(config)
+    }
+
+    /// Load the Configuration only from the environment variables
+    ///
+    /// No configuration file is read and [Config::autosave] is disabled.
+    pub fn load_env() -> Result<Self, Error> {
+        debug!("Loading Configuration from environment");
+
+        let figment = Figment::new().merge(figment::providers::Env::prefixed("KONARR_"));
+
+        let mut config = Self::extract(figment)?;
+        config.env_only = true;
+        Ok(config)
+    }
+
+    /// Check if the configuration file is optional (`KONARR_CONFIG_OPTIONAL`)
+    pub fn config_optional() -> bool {
+        std::env::var("KONARR_CONFIG_OPTIONAL")
+            .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+            .unwrap_or(false)
+    }
+
+    /// If the configuration was loaded only from the environment
+    pub fn is_env_only(&self) -> bool {
+        self.env_only
+    }
+
+    /// Configuration file path
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    fn extract(figment: Figment) -> Result<Self, Error> {
         let mut config: Self = figment.extract()?;
         // TODO: Redo this to be more dynamic
         config.database = DatabaseConfig::figment(&config.database).extract()?;
         config.server = ServerConfig::figment(&config.server).extract()?;
         config.agent = AgentConfig::figment(&config.agent).extract()?;
 
-        // Generate a secret if one is not provided
-        if config.server.secret.is_empty() {
-            config.server.secret = ServerConfig::generate_secret();
-        }
         // Set the data path
-        if std::env::var("KONARR_DATA_PATH").is_ok() {
-            config.data_path = PathBuf::from(std::env::var("KONARR_DATA_PATH").unwrap());
+        if let Ok(data_path) = std::env::var("KONARR_DATA_PATH") {
+            config.data_path = PathBuf::from(data_path);
         } else {
             config.data_path = PathBuf::from("./data");
         }
-        config.path = path.clone();
-
-        debug!("Finished Loading Configuration");
+        config.network.init();
+        #[cfg(feature = "tools")]
+        crate::tools::ToolPaths::init(config.agent.toolcache_dir.clone());
         Ok(config)
     }
 
@@ -49,6 +93,9 @@ impl Config {
         config.database = DatabaseConfig::figment(&config.database).extract()?;
         config.server = ServerConfig::figment(&config.server).extract()?;
         config.agent = AgentConfig::figment(&config.agent).extract()?;
+        config.network.init();
+        #[cfg(feature = "tools")]
+        crate::tools::ToolPaths::init(config.agent.toolcache_dir.clone());
         Ok(config)
     }
 
@@ -64,7 +111,13 @@ impl Config {
     }
 
     /// Automatically save the Configuration
+    ///
+    /// This is a no-op when the configuration was loaded from the environment only.
     pub fn autosave(&self) -> Result<(), Error> {
+        if self.env_only {
+            debug!("Environment only configuration, skipping autosave");
+            return Ok(());
+        }
         self.save(&self.path)
     }
 
@@ -86,6 +139,11 @@ impl Config {
         Ok(&self.data_path)
     }
 
+    /// Set the data path
+    pub fn set_data_path(&mut self, path: impl Into<PathBuf>) {
+        self.data_path = path.into();
+    }
+
     /// SBOMs Path in data directory
     pub fn sboms_path(&self) -> Result<PathBuf, Error> {
         let path = self.data_path()?.join("sboms");
@@ -30,7 +30,16 @@ mod config;
 mod grypedb;
 #[cfg(feature = "models")]
 mod models;
+mod network;
+mod scan_paths;
 mod server;
+mod validate;
+
+#[cfg(feature = "client")]
+pub use network::client_builder;
+pub use network::{NetworkConfig, ProxyConfig};
+pub use scan_paths::AgentScanPath;
+pub use validate::{ConfigIssue, ConfigIssueLevel, ConfigSource, ConfigValue};
 
 /// Application Configuration
 ///
@@ -62,6 +71,9 @@ pub struct Config {
     #[serde(skip)]
     data_path: PathBuf,
 
+    #[serde(skip)]
+    env_only: bool,
+
     /// Database Configuration
     #[serde(default)]
     pub database: DatabaseConfig,
@@ -77,6 +89,10 @@ pub struct Config {
     /// Sessions Configuration
     #[serde(default)]
     pub sessions: SessionsConfig,
+
+    /// Network Configuration (proxy settings)
+    #[serde(default = "NetworkConfig::from_env")]
+    pub network: NetworkConfig,
 }
 
 /// Database Configuration
@@ -130,22 +146,42 @@ impl Default for DatabaseConfig {
 /// Server Configuration
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ServerConfig {
-    /// Server / Rocket Secret (default to random)
+    /// Server / Rocket Secret (generated and saved by the server on first start)
     ///
     /// Env: `KONARR_SERVER_SECRET`
     #[serde(default)]
     pub secret: String,
+    /// Key used to encrypt the secret settings (agent key, webhooks) at rest
+    ///
+    /// Any string (at least 32 characters), the encryption key is derived from it.
+    /// Settings are stored in plain text when it is not set.
+    ///
+    /// Only read from the configuration, it is never written back to the file (autosave).
+    ///
+    /// Env: `KONARR_SERVER_ENCRYPTION_KEY`
+    #[serde(skip_serializing)]
+    pub encryption_key: Option<String>,
 
     /// Server Domain
     ///
     /// Env: `KONARR_SERVER_DOMAIN`
     #[serde(skip_serializing_if = "Option::is_none")]
     pub domain: Option<String>,
-    /// Port
+    /// Port (of the external URL, also the port the server binds to)
     ///
     /// Env: `KONARR_SERVER_PORT`
     #[serde(skip_serializing_if = "Option::is_none")]
     pub port: Option<i32>,
+    /// Address the server binds to (`0.0.0.0`, or `::` for IPv6)
+    ///
+    /// Env: `KONARR_SERVER_BIND_ADDRESS`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bind_address: Option<String>,
+    /// Port the server binds to when it differs from the external URL (reverse proxy)
+    ///
+    /// Env: `KONARR_SERVER_BIND_PORT`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bind_port: Option<u16>,
     /// Scheme
     ///
     /// Env: `KONARR_SERVER_SCHEME`
@@ -170,6 +206,83 @@ pub struct ServerConfig {
     /// Env: `KONARR_SERVER_API`
     #[serde(skip_serializing_if = "Option::is_none")]
     pub api: Option<String>,
+
+    /// Request body size limits
+    #[serde(default)]
+    pub limits: ServerLimitsConfig,
+
+    /// TLS Configuration (HTTPS and agent client certificates)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<ServerTlsConfig>,
+}
+
+/// TLS Configuration
+///
+/// ```yaml
+/// server:
+///   tls:
+///     certs: /etc/konarr/tls/server.crt
+///     key: /etc/konarr/tls/server.key
+///     client_ca: /etc/konarr/tls/agents-ca.crt
+///     agent_mtls: true
+/// ```
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ServerTlsConfig {
+    /// Certificate chain (PEM)
+    pub certs: PathBuf,
+    /// Private key (PEM)
+    pub key: PathBuf,
+    /// CA certificates (PEM) used to validate the client certificates
+    ///
+    /// Client certificates are optional for the browser / CLI (session) traffic.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_ca: Option<PathBuf>,
+    /// Require a registered client certificate for the agent requests (mTLS)
+    ///
+    /// Requires `client_ca` to be set.
+    #[serde(default)]
+    pub agent_mtls: bool,
+}
+
+impl ServerTlsConfig {
+    /// Check if agents need to present a registered client certificate
+    pub fn agent_mtls(&self) -> bool {
+        self.agent_mtls && self.client_ca.is_some()
+    }
+}
+
+/// Request body size limits (`512KiB`, `10MB`, `50MiB`, etc.)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ServerLimitsConfig {
+    /// SBOM uploads (default: `50MiB`)
+    ///
+    /// Env: `KONARR_SERVER_LIMITS_SBOM`
+    #[serde(default = "ServerLimitsConfig::default_sbom")]
+    pub sbom: String,
+    /// JSON request bodies (default: `1MiB`)
+    ///
+    /// Env: `KONARR_SERVER_LIMITS_JSON`
+    #[serde(default = "ServerLimitsConfig::default_json")]
+    pub json: String,
+}
+
+impl ServerLimitsConfig {
+    fn default_sbom() -> String {
+        "50MiB".to_string()
+    }
+
+    fn default_json() -> String {
+        "1MiB".to_string()
+    }
+}
+
+impl Default for ServerLimitsConfig {
+    fn default() -> Self {
+        Self {
+            sbom: Self::default_sbom(),
+            json: Self::default_json(),
+        }
+    }
 }
 
 impl Default for ServerConfig {
@@ -183,12 +296,17 @@ impl Default for ServerConfig {
 
         Self {
             secret: String::new(),
+            encryption_key: None,
             domain: None,
             port: None,
+            bind_address: None,
+            bind_port: None,
             scheme: None,
             cors: true,
             frontend,
             api: Some("/api".to_string()),
+            limits: ServerLimitsConfig::default(),
+            tls: None,
         }
     }
 }
@@ -279,11 +397,118 @@ pub struct AgentConfig {
     /// Env: `KONARR_AGENT_TOOL_AUTO_UPDATE`
     #[serde(default)]
     pub tool_auto_update: bool,
+    /// Directory the tools are installed to (searched first)
+    ///
+    /// Env: `KONARR_AGENT_TOOLCACHE`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toolcache_dir: Option<PathBuf>,
+    /// Sync the project title / description from the container labels (default: true)
+    ///
+    /// Env: `KONARR_AGENT_SYNC_LABELS`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_labels: Option<bool>,
+    /// Disable the local cache of uploaded SBOMs (always run the SBOM tool)
+    ///
+    /// Env: `KONARR_AGENT_NO_CACHE`
+    #[serde(default)]
+    pub no_cache: bool,
+    /// Number of days before a cached SBOM is rescanned (default: 7)
+    ///
+    /// Env: `KONARR_AGENT_CACHE_EXPIRES`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_expires: Option<u32>,
+    /// Link the snapshot of another project tracking the same image (by digest) instead of
+    /// scanning the container again
+    ///
+    /// Env: `KONARR_AGENT_SHARE_SNAPSHOTS`
+    #[serde(default)]
+    pub share_snapshots: bool,
+    /// Client certificate (PEM) used for mutual TLS with the server
+    ///
+    /// Env: `KONARR_AGENT_CLIENT_CERT`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<PathBuf>,
+    /// Client certificate private key (PEM, PKCS#8)
+    ///
+    /// Env: `KONARR_AGENT_CLIENT_KEY`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<PathBuf>,
+    /// Extra paths scanned inside the containers and merged into their SBOM
+    /// (see [AgentScanPath], each path adds a copy and a Syft scan per new snapshot)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_scan_paths: Vec<AgentScanPath>,
+    /// Docker Swarm mode (scan the services from the manager node)
+    #[serde(default)]
+    pub swarm: AgentSwarmConfig,
+    /// Containers which are not scanned (container names or images, `*` globs)
+    ///
+    /// Env: `KONARR_AGENT_EXCLUDE`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Number of minutes between two runs in monitoring mode (default: 1)
+    ///
+    /// Env: `KONARR_AGENT_INTERVAL`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u32>,
+}
+
+/// Agent Docker Swarm Configuration
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct AgentSwarmConfig {
+    /// Enumerate the Swarm services when running on a manager node (the containers of the
+    /// services are skipped on every node)
+    ///
+    /// Env: `KONARR_AGENT_SWARM={enabled=true}`
+    #[serde(default)]
+    pub enabled: bool,
+    /// Name of the Cluster project the services are created under (default: `swarm`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<String>,
+}
+
+impl AgentSwarmConfig {
+    /// Name of the Cluster project
+    pub fn cluster(&self) -> String {
+        self.cluster
+            .clone()
+            .filter(|cluster| !cluster.is_empty())
+            .unwrap_or_else(|| "swarm".to_string())
+    }
 }
 
 impl AgentConfig {
+    /// Time before a cached SBOM expires and the container is rescanned
+    pub fn cache_expires(&self) -> chrono::Duration {
+        chrono::Duration::days(self.cache_expires.unwrap_or(7) as i64)
+    }
+
+    /// Check if the project details are synced from the container labels
+    pub fn sync_labels(&self) -> bool {
+        self.sync_labels.unwrap_or(true)
+    }
+
+    /// Minutes between two runs in monitoring mode
+    pub fn interval(&self) -> u32 {
+        self.interval.filter(|interval| *interval > 0).unwrap_or(1)
+    }
+
+    /// Check if a container (by name or image) is excluded from the scans
+    pub fn is_excluded(&self, name: &str, image: &str) -> bool {
+        self.exclude.iter().any(|pattern| {
+            crate::bom::processors::glob_match(pattern, name)
+                || crate::bom::processors::glob_match(pattern, image)
+        })
+    }
+
     pub(crate) fn figment(base: &Self) -> Figment {
-        Figment::from(Serialized::defaults(base))
-            .merge(figment::providers::Env::prefixed("KONARR_AGENT_"))
+        Figment::from(Serialized::defaults(base)).merge(
+            figment::providers::Env::prefixed("KONARR_AGENT_").map(|key| {
+                if key == "toolcache" {
+                    "toolcache_dir".into()
+                } else {
+                    key.into()
+                }
+            }),
+        )
     }
 }
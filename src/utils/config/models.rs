@@ -26,7 +26,7 @@ impl DatabaseConfig {
                 }
                 path if path.starts_with("libsql:") => {
                     let token = self.token.clone().ok_or_else(|| {
-                        Error::UnknownError("libsql database requires a token".to_string())
+                        Error::ConfigParseError("libsql database requires a token".to_string())
                     })?;
 
                     Ok(libsql::Builder::new_remote(path.to_string(), token)
@@ -45,7 +45,7 @@ impl DatabaseConfig {
 
                     Ok(libsql::Builder::new_local(path).build().await?)
                 }
-                _ => Err(Error::UnknownError(format!(
+                _ => Err(Error::ConfigParseError(format!(
                     "Invalid database path: {}",
                     path
                 ))),
@@ -61,7 +61,9 @@ impl DatabaseConfig {
     /// Create / Connect to the Database
     pub async fn connection(&self) -> Result<libsql::Connection, Error> {
         let database = self.database().await?;
-        Ok(database.connect()?)
+        let connection = database.connect()?;
+        crate::models::connection_init(&connection).await?;
+        Ok(connection)
     }
 }
 
@@ -0,0 +1,206 @@
+//! # Network Configuration
+//!
+//! Proxy settings used for all outbound HTTP(S) requests (GrypeDB, GitHub releases, Konarr client).
+//!
+//! ```yaml
+//! network:
+//!   proxy:
+//!     http: http://proxy.internal:3128
+//!     https: http://proxy.internal:3128
+//!   no_proxy: localhost,.internal,registry.example.com
+//! ```
+use std::sync::OnceLock;
+use url::Url;
+
+/// Network configuration set when the configuration is loaded
+static NETWORK_CONFIG: OnceLock<NetworkConfig> = OnceLock::new();
+
+/// Network Configuration
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NetworkConfig {
+    /// Proxy Configuration
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+    /// Comma separated list of hosts / domains that should not use the proxy
+    ///
+    /// Env: `NO_PROXY` / `no_proxy`
+    #[serde(
+        default = "NetworkConfig::env_no_proxy",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub no_proxy: Option<String>,
+}
+
+/// Proxy Configuration
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ProxyConfig {
+    /// HTTP Proxy
+    ///
+    /// Env: `HTTP_PROXY` / `http_proxy`
+    #[serde(
+        default = "ProxyConfig::env_http",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub http: Option<String>,
+    /// HTTPS Proxy
+    ///
+    /// Env: `HTTPS_PROXY` / `https_proxy`
+    #[serde(
+        default = "ProxyConfig::env_https",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub https: Option<String>,
+}
+
+impl ProxyConfig {
+    fn env_http() -> Option<String> {
+        env_var(&["HTTP_PROXY", "http_proxy"])
+    }
+    fn env_https() -> Option<String> {
+        env_var(&["HTTPS_PROXY", "https_proxy"])
+    }
+}
+
+impl NetworkConfig {
+    /// Load the network configuration from the conventional environment variables
+    pub fn from_env() -> Self {
+        Self {
+            proxy: ProxyConfig {
+                http: ProxyConfig::env_http(),
+                https: ProxyConfig::env_https(),
+            },
+            no_proxy: Self::env_no_proxy(),
+        }
+    }
+
+    fn env_no_proxy() -> Option<String> {
+        env_var(&["NO_PROXY", "no_proxy"])
+    }
+
+    /// Set the process wide network configuration (first call wins)
+    pub fn init(&self) {
+        if NETWORK_CONFIG.set(self.clone()).is_err() {
+            log::debug!("Network configuration already initialised");
+        }
+    }
+
+    /// Get the process wide network configuration
+    ///
+    /// Falls back to the environment variables if the configuration was not loaded.
+    pub fn global() -> NetworkConfig {
+        NETWORK_CONFIG.get().cloned().unwrap_or_else(Self::from_env)
+    }
+
+    /// Check if the host should bypass the proxy
+    pub fn is_no_proxy(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_lowercase();
+        let Some(no_proxy) = &self.no_proxy else {
+            return false;
+        };
+
+        no_proxy
+            .split(',')
+            .map(|entry| entry.trim().to_lowercase())
+            .filter(|entry| !entry.is_empty())
+            .any(|entry| {
+                if entry == "*" {
+                    return true;
+                }
+                // Strip the port if present (`host:port`)
+                let entry = match entry.rsplit_once(':') {
+                    Some((h, port)) if port.chars().all(|c| c.is_ascii_digit()) => h.to_string(),
+                    _ => entry,
+                };
+                let domain = entry.trim_start_matches("*.").trim_start_matches('.');
+                host == domain || host.ends_with(&format!(".{}", domain))
+            })
+    }
+
+    /// Get the proxy to use for a URL (if any)
+    pub fn proxy_for(&self, url: &Url) -> Option<Url> {
+        if let Some(host) = url.host_str() {
+            if self.is_no_proxy(host) {
+                return None;
+            }
+        }
+        let proxy = match url.scheme() {
+            "https" | "wss" => self.proxy.https.as_ref().or(self.proxy.http.as_ref()),
+            _ => self.proxy.http.as_ref(),
+        }?;
+        Url::parse(proxy).ok()
+    }
+
+    /// Create a reqwest client builder with the proxy settings applied
+    #[cfg(feature = "client")]
+    pub fn client_builder(&self) -> reqwest::ClientBuilder {
+        let builder = reqwest::Client::builder();
+
+        if self.proxy.http.is_none() && self.proxy.https.is_none() {
+            log::debug!("No proxy configured");
+            return builder.no_proxy();
+        }
+        log::debug!(
+            "Using proxy - http: {:?}, https: {:?}, no_proxy: {:?}",
+            self.proxy.http,
+            self.proxy.https,
+            self.no_proxy
+        );
+
+        let network = self.clone();
+        builder.proxy(reqwest::Proxy::custom(move |url| network.proxy_for(url)))
+    }
+}
+
+/// Create a reqwest client builder using the process wide network configuration
+#[cfg(feature = "client")]
+pub fn client_builder() -> reqwest::ClientBuilder {
+    NetworkConfig::global().client_builder()
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network() -> NetworkConfig {
+        NetworkConfig {
+            proxy: ProxyConfig {
+                http: Some("http://proxy.internal:3128".to_string()),
+                https: None,
+            },
+            no_proxy: Some("localhost, .internal,registry.example.com:5000".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_no_proxy() {
+        let network = network();
+
+        assert!(network.is_no_proxy("localhost"));
+        assert!(network.is_no_proxy("konarr.internal"));
+        assert!(network.is_no_proxy("registry.example.com"));
+        assert!(!network.is_no_proxy("example.com"));
+        assert!(!network.is_no_proxy("api.github.com"));
+    }
+
+    #[test]
+    fn test_proxy_for() {
+        let network = network();
+        let proxy = Url::parse("http://proxy.internal:3128").unwrap();
+
+        assert_eq!(
+            network.proxy_for(&Url::parse("https://api.github.com/repos").unwrap()),
+            Some(proxy)
+        );
+        assert_eq!(
+            network.proxy_for(&Url::parse("http://konarr.internal:9000").unwrap()),
+            None
+        );
+    }
+}
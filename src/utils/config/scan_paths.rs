@@ -0,0 +1,127 @@
+//! # Agent Extra Scan Paths
+//!
+//! Paths inside the containers which are scanned separately (Syft `dir:` scan) and merged
+//! into the SBOM of the container, for packages the image scan misses (virtualenvs or
+//! `node_modules` installed in nonstandard paths).
+//!
+//! ```yaml
+//! agent:
+//!   extra_scan_paths:
+//!     # Scanned in every container
+//!     - /opt/venv
+//!     # Only scanned in the containers of the projects
+//!     - path: /srv/app/node_modules
+//!       projects:
+//!         - homelab/web
+//! ```
+//!
+//! Each path is copied out of the container (`docker cp`) and scanned on every new
+//! snapshot of the container, which adds the size of the path in disk I/O and the
+//! duration of a Syft scan per path. Keep the list to the paths the image scan misses.
+use serde::{Deserialize, Serialize};
+
+use super::AgentConfig;
+
+/// Extra path to scan in the containers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AgentScanPath {
+    /// Path scanned in every container
+    Path(String),
+    /// Path scanned in the containers of the listed projects (by name)
+    Scoped {
+        /// Path inside the container
+        path: String,
+        /// Project names (all the projects if empty)
+        #[serde(default)]
+        projects: Vec<String>,
+    },
+}
+
+impl AgentScanPath {
+    /// Path inside the container
+    pub fn path(&self) -> &str {
+        match self {
+            AgentScanPath::Path(path) => path,
+            AgentScanPath::Scoped { path, .. } => path,
+        }
+    }
+
+    /// Check if the path is scanned for the project
+    pub fn applies_to(&self, project: &str) -> bool {
+        match self {
+            AgentScanPath::Path(_) => true,
+            AgentScanPath::Scoped { projects, .. } => {
+                projects.is_empty()
+                    || projects
+                        .iter()
+                        .any(|name| name.trim_matches('/').eq_ignore_ascii_case(project))
+            }
+        }
+    }
+}
+
+impl AgentConfig {
+    /// Extra paths to scan in the container of a project (absolute paths only)
+    pub fn extra_scan_paths(&self, project: &str) -> Vec<&str> {
+        self.extra_scan_paths
+            .iter()
+            .filter(|scan_path| scan_path.applies_to(project))
+            .map(|scan_path| scan_path.path())
+            .filter(|path| {
+                if path.starts_with('/') {
+                    true
+                } else {
+                    log::warn!("Extra scan path `{}` is not absolute, skipping", path);
+                    false
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extra_scan_paths() {
+        let config: AgentConfig = serde_yaml::from_str(
+            r#"
+            extra_scan_paths:
+              - /opt/venv
+              - path: /srv/app/node_modules
+                projects:
+                  - homelab/web
+              - path: /usr/lib/python3/dist-packages
+              - relative/path
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.extra_scan_paths.len(), 4);
+        assert_eq!(
+            config.extra_scan_paths[1],
+            AgentScanPath::Scoped {
+                path: "/srv/app/node_modules".to_string(),
+                projects: vec!["homelab/web".to_string()],
+            }
+        );
+
+        assert_eq!(
+            config.extra_scan_paths("homelab/web"),
+            vec![
+                "/opt/venv",
+                "/srv/app/node_modules",
+                "/usr/lib/python3/dist-packages"
+            ]
+        );
+        assert_eq!(
+            config.extra_scan_paths("homelab/db"),
+            vec!["/opt/venv", "/usr/lib/python3/dist-packages"]
+        );
+
+        // Not configured
+        let config: AgentConfig = serde_yaml::from_str("host: homelab").unwrap();
+        assert!(config.extra_scan_paths("homelab/web").is_empty());
+    }
+}
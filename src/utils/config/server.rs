@@ -1,6 +1,7 @@
-use super::ServerConfig;
+use super::{ServerConfig, ServerLimitsConfig};
 use crate::{error::KonarrError, utils::rand::generate_random_string};
 use base64::Engine;
+use std::net::{IpAddr, Ipv4Addr};
 use url::Url;
 
 impl ServerConfig {
@@ -63,6 +64,50 @@ impl ServerConfig {
         Ok(url.join(api_base.as_str())?)
     }
 
+    /// Address the server binds to
+    ///
+    /// `None` keeps Rocket's address (`ROCKET_ADDRESS` / `Rocket.toml`), IPv6 addresses
+    /// can be wrapped in brackets (`[::]`).
+    ///
+    /// ```rust
+    /// let mut config = konarr::Config::default();
+    /// config.server.bind_address = Some("[::]".to_string());
+    ///
+    /// assert_eq!(config.server.bind_address().unwrap(), Some("::".parse().unwrap()));
+    /// ```
+    pub fn bind_address(&self) -> Result<Option<IpAddr>, KonarrError> {
+        let Some(address) = &self.bind_address else {
+            return Ok(None);
+        };
+        let address = address.trim().trim_start_matches('[').trim_end_matches(']');
+        match address {
+            "localhost" => Ok(Some(IpAddr::V4(Ipv4Addr::LOCALHOST))),
+            _ => address.parse().map(Some).map_err(|_| {
+                KonarrError::ConfigParseError(format!("Invalid bind address `{}`", address))
+            }),
+        }
+    }
+
+    /// Port the server binds to
+    ///
+    /// Precedence: `bind_port`, then `port` (the port of the external URL), otherwise
+    /// `None` keeps Rocket's port (`ROCKET_PORT` / `Rocket.toml`).
+    pub fn bind_port(&self) -> Result<Option<u16>, KonarrError> {
+        if let Some(port) = self.bind_port {
+            return Ok(Some(port));
+        }
+        match self.port {
+            Some(port) => match u16::try_from(port) {
+                Ok(port) => Ok(Some(port)),
+                Err(_) => Err(KonarrError::ConfigParseError(format!(
+                    "Invalid port `{}`",
+                    port
+                ))),
+            },
+            None => Ok(None),
+        }
+    }
+
     /// Generate a base64 encoded secret
     pub fn generate_secret() -> String {
         log::debug!("Generating Server Secret...");
@@ -71,3 +116,53 @@ impl ServerConfig {
         secret64
     }
 }
+
+impl ServerLimitsConfig {
+    /// SBOM upload limit in bytes
+    pub fn sbom_bytes(&self) -> Result<u64, KonarrError> {
+        Self::parse_size(&self.sbom)
+    }
+
+    /// JSON request body limit in bytes
+    pub fn json_bytes(&self) -> Result<u64, KonarrError> {
+        Self::parse_size(&self.json)
+    }
+
+    /// Parse a size with an optional unit (`B`, `KB`, `KiB`, `MB`, `MiB`, `GB`, `GiB`)
+    ///
+    /// ```rust
+    /// use konarr::utils::config::ServerLimitsConfig;
+    ///
+    /// assert_eq!(ServerLimitsConfig::parse_size("512").unwrap(), 512);
+    /// assert_eq!(ServerLimitsConfig::parse_size("10 MB").unwrap(), 10_000_000);
+    /// assert_eq!(ServerLimitsConfig::parse_size("50MiB").unwrap(), 50 * 1024 * 1024);
+    /// assert!(ServerLimitsConfig::parse_size("ten").is_err());
+    /// ```
+    pub fn parse_size(value: &str) -> Result<u64, KonarrError> {
+        let value = value.trim();
+        let index = value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(value.len());
+        let (number, unit) = value.split_at(index);
+
+        let number: u64 = number
+            .parse()
+            .map_err(|_| KonarrError::ConfigParseError(format!("Invalid size `{}`", value)))?;
+        let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+            "" | "b" => 1,
+            "kb" => 1_000,
+            "kib" => 1 << 10,
+            "mb" => 1_000_000,
+            "mib" => 1 << 20,
+            "gb" => 1_000_000_000,
+            "gib" => 1 << 30,
+            _ => {
+                return Err(KonarrError::ConfigParseError(format!(
+                    "Invalid size unit `{}`",
+                    unit.trim()
+                )))
+            }
+        };
+        Ok(number * multiplier)
+    }
+}
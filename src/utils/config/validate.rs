@@ -0,0 +1,465 @@
+//! # Configuration Validation
+//!
+//! Reports the effective configuration values (and where they came from) and checks
+//! for suspicious combinations of settings.
+
+use super::Config;
+
+/// Keys which values are redacted when reported
+const SECRET_KEYS: [&str; 4] = [
+    "server.secret",
+    "server.encryption_key",
+    "database.token",
+    "agent.token",
+];
+
+/// Source of a configuration value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Configuration file
+    File,
+    /// Environment variable
+    Env,
+    /// Default value
+    Default,
+    /// Agent setting managed by the server
+    Server,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::File => write!(f, "file"),
+            ConfigSource::Env => write!(f, "env"),
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::Server => write!(f, "server"),
+        }
+    }
+}
+
+/// Effective configuration value
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigValue {
+    /// Dotted key (`server.domain`)
+    pub key: String,
+    /// Value (secrets are redacted)
+    pub value: String,
+    /// Where the value came from
+    pub source: ConfigSource,
+}
+
+/// Configuration issue level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigIssueLevel {
+    /// Suspicious but usable configuration
+    Warning,
+    /// Invalid configuration
+    Error,
+}
+
+/// Configuration issue found during validation
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    /// Level of the issue
+    pub level: ConfigIssueLevel,
+    /// Dotted key the issue is about
+    pub key: String,
+    /// Description of the issue
+    pub message: String,
+}
+
+impl ConfigIssue {
+    fn new(level: ConfigIssueLevel, key: &str, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            key: key.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Config {
+    /// Get the effective configuration values with their sources
+    pub fn sources(&self) -> Vec<ConfigValue> {
+        let Ok(effective) = serde_yaml::to_value(self) else {
+            return vec![];
+        };
+        let mut values = Vec::new();
+        flatten("", &effective, &mut values);
+
+        let file: Option<serde_yaml::Value> = if self.env_only {
+            None
+        } else {
+            std::fs::read_to_string(&self.path)
+                .ok()
+                .and_then(|data| serde_yaml::from_str(&data).ok())
+        };
+
+        values
+            .into_iter()
+            .map(|(key, value)| {
+                let source = if env_names(&key)
+                    .iter()
+                    .any(|name| std::env::var(name).is_ok())
+                {
+                    ConfigSource::Env
+                } else if file.as_ref().is_some_and(|f| lookup(f, &key).is_some()) {
+                    ConfigSource::File
+                } else {
+                    ConfigSource::Default
+                };
+                let value = if SECRET_KEYS.contains(&key.as_str()) && !value.is_empty() {
+                    "********".to_string()
+                } else {
+                    value
+                };
+                ConfigValue { key, value, source }
+            })
+            .collect()
+    }
+
+    /// Effective agent configuration values (`agent.*`)
+    ///
+    /// The `server` keys were set by the server (server-authoritative settings) and are
+    /// reported as such.
+    pub fn agent_sources(&self, server: &[&str]) -> Vec<ConfigValue> {
+        self.sources()
+            .into_iter()
+            .filter(|value| value.key.starts_with("agent."))
+            .map(|mut value| {
+                if server.contains(&value.key.as_str()) {
+                    value.source = ConfigSource::Server;
+                }
+                value
+            })
+            .collect()
+    }
+
+    /// Validate the configuration
+    ///
+    /// When `agent` is set, the configuration is checked for running in agent mode
+    /// (a database is not required but a token is).
+    pub fn validate(&self, agent: bool) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+
+        if agent {
+            if self.agent.token.as_deref().unwrap_or_default().is_empty() {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueLevel::Error,
+                    "agent.token",
+                    "No agent token is set, the agent can not authenticate with the server",
+                ));
+            }
+        } else if self.database.path.is_none() {
+            issues.push(ConfigIssue::new(
+                ConfigIssueLevel::Error,
+                "database.path",
+                "No database path is set, an in-memory database will be used and data will be lost",
+            ));
+        }
+
+        if !self.server.cors {
+            issues.push(ConfigIssue::new(
+                ConfigIssueLevel::Warning,
+                "server.cors",
+                "CORS is disabled which allows all origins with credentials",
+            ));
+        }
+
+        if let Some(scheme) = &self.server.scheme {
+            if !matches!(scheme.as_str(), "http" | "https") {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueLevel::Error,
+                    "server.scheme",
+                    format!("Unsupported scheme `{}`", scheme),
+                ));
+            } else if scheme == "http" && self.server.domain.is_some() {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueLevel::Warning,
+                    "server.scheme",
+                    "Insecure HTTP is being used",
+                ));
+            }
+        }
+        for (key, result) in [
+            ("server.limits.sbom", self.server.limits.sbom_bytes()),
+            ("server.limits.json", self.server.limits.json_bytes()),
+        ] {
+            if let Err(e) = result {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueLevel::Error,
+                    key,
+                    e.to_string(),
+                ));
+            }
+        }
+        if let Some(tls) = &self.server.tls {
+            if tls.agent_mtls && tls.client_ca.is_none() {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueLevel::Error,
+                    "server.tls.agent_mtls",
+                    "Agent mTLS requires `server.tls.client_ca` to be set",
+                ));
+            }
+        }
+        if agent && self.agent.client_cert.is_some() != self.agent.client_key.is_some() {
+            issues.push(ConfigIssue::new(
+                ConfigIssueLevel::Error,
+                "agent.client_cert",
+                "Both the client certificate and key need to be set for mutual TLS",
+            ));
+        }
+        if let Err(e) = self.server.url() {
+            issues.push(ConfigIssue::new(
+                ConfigIssueLevel::Error,
+                "server.domain",
+                format!("Invalid server URL: {}", e),
+            ));
+        }
+        issues.extend(self.validate_bind());
+
+        issues
+    }
+
+    /// Check the bind address / port are valid and consistent with the external URL
+    pub fn validate_bind(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+
+        let address = match self.server.bind_address() {
+            Ok(address) => address,
+            Err(e) => {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueLevel::Error,
+                    "server.bind_address",
+                    e.to_string(),
+                ));
+                None
+            }
+        };
+        let port = match self.server.bind_port() {
+            Ok(port) => port,
+            Err(e) => {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueLevel::Error,
+                    "server.port",
+                    e.to_string(),
+                ));
+                None
+            }
+        };
+        let Ok(url) = self.server.url() else {
+            return issues;
+        };
+
+        let local = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+        if address.is_some_and(|a| a.is_loopback()) && !local {
+            issues.push(ConfigIssue::new(
+                ConfigIssueLevel::Warning,
+                "server.bind_address",
+                format!(
+                    "The server binds to a loopback address but the external URL is `{}`",
+                    url
+                ),
+            ));
+        }
+        if let (Some(port), Some(external)) = (port, url.port_or_known_default()) {
+            if port != external {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueLevel::Warning,
+                    "server.bind_port",
+                    format!(
+                        "The external URL uses port {} but the server binds to port {} (expected behind a reverse proxy)",
+                        external, port
+                    ),
+                ));
+            } else if url.scheme() == "https" && self.server.tls.is_none() {
+                issues.push(ConfigIssue::new(
+                    ConfigIssueLevel::Warning,
+                    "server.scheme",
+                    format!(
+                        "The external URL uses HTTPS but TLS is not configured, the server serves HTTP on port {}",
+                        port
+                    ),
+                ));
+            }
+        }
+        issues
+    }
+}
+
+/// Flatten a YAML value into dotted keys
+fn flatten(prefix: &str, value: &serde_yaml::Value, values: &mut Vec<(String, String)>) {
+    match value {
+        serde_yaml::Value::Mapping(map) => {
+            for (key, value) in map.iter() {
+                let Some(key) = key.as_str() else {
+                    continue;
+                };
+                let key = if prefix.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, value, values);
+            }
+        }
+        serde_yaml::Value::Null => values.push((prefix.to_string(), String::new())),
+        serde_yaml::Value::String(s) => values.push((prefix.to_string(), s.clone())),
+        _ => values.push((
+            prefix.to_string(),
+            serde_yaml::to_string(value)
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+        )),
+    }
+}
+
+/// Lookup a dotted key in a YAML value
+fn lookup<'v>(value: &'v serde_yaml::Value, key: &str) -> Option<&'v serde_yaml::Value> {
+    key.split('.')
+        .try_fold(value, |value, part| value.as_mapping()?.get(part))
+}
+
+/// Environment variables which can set a dotted key
+fn env_names(key: &str) -> Vec<String> {
+    let Some((section, name)) = key.split_once('.') else {
+        return vec![format!("KONARR_{}", key.to_uppercase())];
+    };
+    let name = name.replace(['-', '.'], "_").to_uppercase();
+    match section {
+        "database" => vec![format!("KONARR_DB_{}", name)],
+        "server" if name == "FRONTEND" => vec!["KONARR_CLIENT_PATH".to_string()],
+        "server" => vec![format!("KONARR_SERVER_{}", name)],
+        "agent" => vec![format!("KONARR_AGENT_{}", name)],
+        "network" => match name.as_str() {
+            "PROXY_HTTP" => vec!["HTTP_PROXY".to_string(), "http_proxy".to_string()],
+            "PROXY_HTTPS" => vec!["HTTPS_PROXY".to_string(), "https_proxy".to_string()],
+            "NO_PROXY" => vec!["NO_PROXY".to_string(), "no_proxy".to_string()],
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sources() {
+        let path = std::env::temp_dir().join("konarr-test-sources.yml");
+        std::fs::write(&path, "server:\n  domain: konarr.example.com\n").unwrap();
+
+        let mut config = Config::default();
+        config.path = path.clone();
+        config.server.domain = Some("konarr.example.com".to_string());
+        config.server.secret = "secret".to_string();
+
+        let sources = config.sources();
+        let domain = sources.iter().find(|v| v.key == "server.domain").unwrap();
+        assert_eq!(domain.value, "konarr.example.com");
+        assert_eq!(domain.source, ConfigSource::File);
+
+        let secret = sources.iter().find(|v| v.key == "server.secret").unwrap();
+        assert_eq!(secret.value, "********");
+
+        let expires = sources
+            .iter()
+            .find(|v| v.key == "sessions.users.expires")
+            .unwrap();
+        assert_eq!(expires.value, "24");
+        assert_eq!(expires.source, ConfigSource::Default);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = Config::default();
+        config.database.path = Some("/data/konarr.db".to_string());
+        config.server.cors = false;
+
+        let issues = config.validate(false);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].level, ConfigIssueLevel::Warning);
+        assert_eq!(issues[0].key, "server.cors");
+
+        config.database.path = None;
+        config.server.cors = true;
+        config.agent.token = None;
+        let issues = config.validate(true);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].level, ConfigIssueLevel::Error);
+        assert_eq!(issues[0].key, "agent.token");
+
+        config.agent.token = Some("token".to_string());
+        config.agent.client_cert = Some("/etc/konarr/agent.crt".into());
+        let issues = config.validate(true);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].key, "agent.client_cert");
+
+        config.server.tls = Some(crate::utils::config::ServerTlsConfig {
+            agent_mtls: true,
+            ..Default::default()
+        });
+        let issues = config.validate(false);
+        assert!(issues.iter().any(|i| i.key == "server.tls.agent_mtls"));
+    }
+
+    #[test]
+    fn test_validate_bind() {
+        let mut config = Config::default();
+        assert!(config.validate_bind().is_empty());
+
+        config.server.bind_address = Some("::".to_string());
+        config.server.port = Some(9000);
+        assert!(config.validate_bind().is_empty());
+
+        config.server.bind_address = Some("not-an-address".to_string());
+        let issues = config.validate_bind();
+        assert_eq!(issues[0].level, ConfigIssueLevel::Error);
+        assert_eq!(issues[0].key, "server.bind_address");
+
+        config.server.bind_address = Some("127.0.0.1".to_string());
+        config.server.domain = Some("konarr.example.com".to_string());
+        let issues = config.validate_bind();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].key, "server.bind_address");
+
+        // Reverse proxy in front of the server
+        config.server.bind_address = None;
+        config.server.scheme = Some("https".to_string());
+        config.server.port = None;
+        config.server.bind_port = Some(9000);
+        let issues = config.validate_bind();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].key, "server.bind_port");
+
+        config.server.bind_port = Some(443);
+        let issues = config.validate_bind();
+        assert_eq!(issues[0].key, "server.scheme");
+    }
+
+    #[test]
+    fn test_env_only_autosave() {
+        let mut config = Config::default();
+        config.env_only = true;
+        // Path is empty, saving would fail if autosave was not disabled
+        assert!(config.autosave().is_ok());
+    }
+
+    #[test]
+    fn test_encryption_key_not_saved() {
+        let mut config = Config::default();
+        config.server.encryption_key = Some("0123456789abcdef0123456789abcdef".to_string());
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("encryption_key"));
+        assert!(!yaml.contains("0123456789abcdef"));
+
+        let config: Config =
+            serde_yaml::from_str("server:\n  encryption_key: 0123456789abcdef0123456789abcdef\n")
+                .unwrap();
+        assert!(config.server.encryption_key.is_some());
+    }
+}
@@ -0,0 +1,206 @@
+//! # Container Networking
+//!
+//! Ports and networks of a container reported by the agent on every run, stored in the
+//! `container.ports` (JSON array) and `container.networks` (comma separated names)
+//! snapshot metadata.
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of ports stored for a container (the published ports are kept first)
+pub const CONTAINER_PORTS_MAX: usize = 64;
+/// Maximum number of networks stored for a container
+pub const CONTAINER_NETWORKS_MAX: usize = 16;
+
+/// Port exposed by a container (and published on the host if `host_port` is set)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ContainerPort {
+    /// Protocol (`tcp`, `udp` or `sctp`)
+    pub protocol: String,
+    /// Port in the container
+    pub port: u16,
+    /// Host IP address the port is published on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_ip: Option<String>,
+    /// Port published on the host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_port: Option<u16>,
+}
+
+impl ContainerPort {
+    /// Exposed (not published) port
+    pub fn new(protocol: impl Into<String>, port: u16) -> Self {
+        let protocol = protocol.into().to_lowercase();
+        Self {
+            protocol: if protocol.is_empty() {
+                "tcp".to_string()
+            } else {
+                protocol
+            },
+            port,
+            host_ip: None,
+            host_port: None,
+        }
+    }
+
+    /// Publish the port on the host
+    pub fn published(mut self, host_ip: Option<String>, host_port: u16) -> Self {
+        self.host_ip = host_ip.filter(|ip| !ip.is_empty());
+        self.host_port = Some(host_port);
+        self
+    }
+
+    /// If the port is published on the host
+    pub fn is_published(&self) -> bool {
+        self.host_port.is_some()
+    }
+
+    /// Parse the `container.ports` metadata
+    ///
+    /// Older agents reported the ports in the `docker ps` format
+    /// (`0.0.0.0:8080->80/tcp,443/tcp`), invalid entries are skipped.
+    pub fn parse_list(value: &str) -> Vec<Self> {
+        let value = value.trim();
+        if value.starts_with('[') {
+            return serde_json::from_str(value).unwrap_or_default();
+        }
+        value
+            .split(',')
+            .filter_map(|port| Self::parse_docker(port.trim()))
+            .collect()
+    }
+
+    fn parse_docker(value: &str) -> Option<Self> {
+        let (host, container) = match value.split_once("->") {
+            Some((host, container)) => (Some(host), container),
+            None => (None, value),
+        };
+        let (port, protocol) = container.split_once('/').unwrap_or((container, "tcp"));
+        let port = Self::new(protocol, port.parse().ok()?);
+
+        match host {
+            Some(host) => {
+                let (ip, host_port) = host.rsplit_once(':').unwrap_or(("", host));
+                Some(port.published(Some(ip.to_string()), host_port.parse().ok()?))
+            }
+            None => Some(port),
+        }
+    }
+
+    /// Value of the `container.ports` metadata
+    ///
+    /// The ports are sorted (published first) and capped at [CONTAINER_PORTS_MAX].
+    pub fn to_metadata(ports: impl IntoIterator<Item = Self>) -> String {
+        let mut ports: Vec<Self> = ports.into_iter().collect();
+        ports.sort_by(|a, b| {
+            b.is_published()
+                .cmp(&a.is_published())
+                .then(a.port.cmp(&b.port))
+                .then(a.protocol.cmp(&b.protocol))
+                .then(a.host_port.cmp(&b.host_port))
+                .then(a.host_ip.cmp(&b.host_ip))
+        });
+        ports.dedup();
+        ports.truncate(CONTAINER_PORTS_MAX);
+        serde_json::to_string(&ports).unwrap_or_else(|_| "[]".to_string())
+    }
+}
+
+impl std::fmt::Display for ContainerPort {
+    /// Same format as `docker ps` (`0.0.0.0:8080->80/tcp`)
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(host_port) = self.host_port {
+            write!(
+                f,
+                "{}:{}->",
+                self.host_ip.as_deref().unwrap_or_default(),
+                host_port
+            )?;
+        }
+        write!(f, "{}/{}", self.port, self.protocol)
+    }
+}
+
+/// Parse the `container.networks` metadata
+pub fn parse_networks(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|network| network.trim())
+        .filter(|network| !network.is_empty())
+        .map(|network| network.to_string())
+        .collect()
+}
+
+/// Value of the `container.networks` metadata (sorted and capped at [CONTAINER_NETWORKS_MAX])
+pub fn networks_metadata(networks: impl IntoIterator<Item = impl Into<String>>) -> String {
+    let mut networks: Vec<String> = networks
+        .into_iter()
+        .map(|network| network.into().trim().to_string())
+        .filter(|network| !network.is_empty() && !network.contains(','))
+        .collect();
+    networks.sort();
+    networks.dedup();
+    networks.truncate(CONTAINER_NETWORKS_MAX);
+    networks.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ports_metadata() {
+        let ports = vec![
+            ContainerPort::new("tcp", 443),
+            ContainerPort::new("tcp", 80).published(Some("0.0.0.0".to_string()), 8080),
+            ContainerPort::new("", 53),
+            ContainerPort::new("tcp", 443),
+        ];
+        let metadata = ContainerPort::to_metadata(ports);
+        assert_eq!(
+            metadata,
+            r#"[{"protocol":"tcp","port":80,"host_ip":"0.0.0.0","host_port":8080},{"protocol":"tcp","port":53},{"protocol":"tcp","port":443}]"#
+        );
+
+        let parsed = ContainerPort::parse_list(&metadata);
+        assert_eq!(parsed.len(), 3);
+        assert!(parsed[0].is_published());
+        assert_eq!(parsed[0].to_string(), "0.0.0.0:8080->80/tcp");
+        assert_eq!(parsed[1].to_string(), "53/tcp");
+    }
+
+    #[test]
+    fn test_ports_capped() {
+        let mut ports: Vec<ContainerPort> = (1..=100)
+            .map(|port| ContainerPort::new("udp", port))
+            .collect();
+        ports.push(ContainerPort::new("tcp", 9000).published(None, 9000));
+
+        let parsed = ContainerPort::parse_list(&ContainerPort::to_metadata(ports));
+        assert_eq!(parsed.len(), CONTAINER_PORTS_MAX);
+        // Published ports are never dropped
+        assert_eq!(parsed[0].host_port, Some(9000));
+    }
+
+    #[test]
+    fn test_ports_docker_format() {
+        let ports = ContainerPort::parse_list("0.0.0.0:8080->80/tcp,:::8080->80/tcp,443/tcp,bad");
+        assert_eq!(ports.len(), 3);
+        assert_eq!(ports[0].host_ip.as_deref(), Some("0.0.0.0"));
+        assert_eq!(ports[1].host_ip.as_deref(), Some("::"));
+        assert_eq!(ports[1].host_port, Some(8080));
+        assert!(!ports[2].is_published());
+        assert!(ContainerPort::parse_list("").is_empty());
+    }
+
+    #[test]
+    fn test_networks() {
+        let metadata = networks_metadata(["frontend", "bridge", "", "frontend"]);
+        assert_eq!(metadata, "bridge,frontend");
+        assert_eq!(parse_networks(&metadata), vec!["bridge", "frontend"]);
+
+        let many: Vec<String> = (0..50).map(|i| format!("net-{:02}", i)).collect();
+        assert_eq!(
+            parse_networks(&networks_metadata(many)).len(),
+            CONTAINER_NETWORKS_MAX
+        );
+    }
+}
@@ -0,0 +1,334 @@
+//! # End-of-life (EOL)
+//!
+//! Support schedules of operating systems and language runtimes, keyed by the
+//! [endoflife.date](https://endoflife.date) product names. A copy of the schedules is
+//! bundled (`data.yml`) so the checks work offline, the products can be refreshed from
+//! the endoflife.date API (see [crate::tasks::eol]).
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::KonarrError;
+
+const EOL_DATA: &str = include_str!("data.yml");
+
+/// endoflife.date API URL (`{product}.json` is appended)
+pub const EOL_API_URL: &str = "https://endoflife.date/api/";
+
+/// Process wide schedules (the bundled copy until refreshed)
+static EOL_SCHEDULE: OnceLock<RwLock<Arc<EolSchedule>>> = OnceLock::new();
+
+/// End-of-life date of a cycle
+///
+/// endoflife.date uses a date or a boolean if the date is not known.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EolDate {
+    /// End-of-life date
+    Date(NaiveDate),
+    /// End-of-life (`true`) or supported (`false`) without a known date
+    Flag(bool),
+}
+
+/// Release cycle of a product (`3.19`, `12`, `22.04`, ...)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EolCycle {
+    /// Release cycle
+    pub cycle: String,
+    /// End-of-life date
+    pub eol: EolDate,
+}
+
+/// Support status of a cycle
+#[derive(Debug, Clone, PartialEq)]
+pub enum EolStatus {
+    /// Supported (outside of the warning window)
+    Supported,
+    /// Reaches end-of-life within the warning window
+    Warning(NaiveDate),
+    /// Past end-of-life (the date if known)
+    EndOfLife(Option<NaiveDate>),
+}
+
+impl EolCycle {
+    /// End-of-life date (if known)
+    pub fn date(&self) -> Option<NaiveDate> {
+        match self.eol {
+            EolDate::Date(date) => Some(date),
+            EolDate::Flag(_) => None,
+        }
+    }
+
+    /// Check if a version is part of the cycle (`3.19.1` is part of `3.19` but not `3.1`)
+    pub fn matches(&self, version: &str) -> bool {
+        let version = normalize_version(version);
+        match version.strip_prefix(self.cycle.as_str()) {
+            Some(rest) => !rest.starts_with(|c: char| c.is_ascii_digit()),
+            None => false,
+        }
+    }
+
+    /// Support status of the cycle on a date with a warning window (in days)
+    pub fn status(&self, today: NaiveDate, warning_days: i64) -> EolStatus {
+        match self.eol {
+            EolDate::Date(date) if date <= today => EolStatus::EndOfLife(Some(date)),
+            EolDate::Date(date) if (date - today).num_days() <= warning_days => {
+                EolStatus::Warning(date)
+            }
+            EolDate::Date(_) | EolDate::Flag(false) => EolStatus::Supported,
+            EolDate::Flag(true) => EolStatus::EndOfLife(None),
+        }
+    }
+}
+
+/// End-of-life schedules
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EolSchedule {
+    /// Component names which are a different product (`python3` -> `python`)
+    #[serde(default)]
+    aliases: BTreeMap<String, String>,
+    /// Cycles of each product (newest first)
+    #[serde(default)]
+    products: BTreeMap<String, Vec<EolCycle>>,
+}
+
+impl EolSchedule {
+    /// Schedules bundled with Konarr
+    pub fn bundled() -> Self {
+        #[cfg(debug_assertions)]
+        let data: Self = serde_yaml::from_str(EOL_DATA).expect("Failed to load EOL data");
+        #[cfg(not(debug_assertions))]
+        let data: Self = serde_yaml::from_str(EOL_DATA).unwrap_or_default();
+
+        log::debug!("Loaded EOL Data: {}", data.products.len());
+        data
+    }
+
+    /// Current process wide schedules
+    pub fn current() -> Arc<Self> {
+        EOL_SCHEDULE
+            .get_or_init(|| RwLock::new(Arc::new(Self::bundled())))
+            .read()
+            .map(|schedule| Arc::clone(&schedule))
+            .unwrap_or_else(|_| Arc::new(Self::bundled()))
+    }
+
+    /// Replace the process wide schedules
+    pub fn set_current(schedule: Self) {
+        let lock = EOL_SCHEDULE.get_or_init(|| RwLock::new(Arc::new(Self::bundled())));
+        if let Ok(mut current) = lock.write() {
+            *current = Arc::new(schedule);
+        }
+    }
+
+    /// Load the schedules cached on disk (on top of the bundled copy)
+    ///
+    /// The bundled copy is used if the cache does not exist or can not be read.
+    pub fn load(path: &Path) -> Self {
+        let mut schedule = Self::bundled();
+        if !path.exists() {
+            return schedule;
+        }
+        match Self::read(path) {
+            Ok(cached) => {
+                for (product, cycles) in cached.products {
+                    schedule.update_product(product, cycles);
+                }
+            }
+            Err(e) => log::warn!("Failed to load the EOL cache `{}` :: {}", path.display(), e),
+        }
+        schedule
+    }
+
+    /// Read the schedules from disk
+    fn read(path: &Path) -> Result<Self, KonarrError> {
+        let data = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Save the schedules to disk
+    pub fn save(&self, path: &Path) -> Result<(), KonarrError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    /// Names of the products
+    pub fn products(&self) -> Vec<String> {
+        self.products.keys().cloned().collect()
+    }
+
+    /// Replace the cycles of a product (empty cycles are ignored)
+    pub fn update_product(&mut self, product: impl Into<String>, cycles: Vec<EolCycle>) {
+        if !cycles.is_empty() {
+            self.products.insert(product.into(), cycles);
+        }
+    }
+
+    /// Product for a component name (aliases are resolved)
+    pub fn product(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        let product = self.aliases.get(&name).unwrap_or(&name);
+        self.products
+            .get_key_value(product)
+            .map(|(product, _)| product.as_str())
+    }
+
+    /// Lookup the cycle of a component version (most specific cycle first)
+    pub fn lookup(&self, name: &str, version: &str) -> Option<(&str, &EolCycle)> {
+        let product = self.product(name)?;
+        self.products
+            .get(product)?
+            .iter()
+            .filter(|cycle| cycle.matches(version))
+            .max_by_key(|cycle| cycle.cycle.len())
+            .map(|cycle| (product, cycle))
+    }
+
+    /// Parse the cycles of a product from the endoflife.date API (`/api/{product}.json`)
+    pub fn parse_api(data: &[u8]) -> Result<Vec<EolCycle>, KonarrError> {
+        Ok(serde_json::from_slice(data)?)
+    }
+
+    /// Fetch the cycles of a product from the endoflife.date API
+    ///
+    /// Requests are conditional so unchanged products are served from the HTTP cache,
+    /// returns if the product changed.
+    #[cfg(feature = "client")]
+    pub async fn fetch_product(&mut self, base: &str, product: &str) -> Result<bool, KonarrError> {
+        let client = crate::utils::config::client_builder().build()?;
+        let url = format!("{}{}.json", base, product);
+        let response = crate::utils::http::HttpCache::global()
+            .get_conditional(&client, &url)
+            .await?;
+        self.update_product(product, Self::parse_api(response.body())?);
+        Ok(response.is_modified())
+    }
+}
+
+/// Remove the epoch and prefixes from a version (`1:3.11.2-6` -> `3.11.2-6`, `go1.21` -> `1.21`)
+fn normalize_version(version: &str) -> &str {
+    let version = version.trim();
+    let version = match version.split_once(':') {
+        Some((epoch, rest)) if epoch.chars().all(|c| c.is_ascii_digit()) => rest,
+        _ => version,
+    };
+    version
+        .strip_prefix("go")
+        .or_else(|| version.strip_prefix('v'))
+        .unwrap_or(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_bundled() {
+        let schedule = EolSchedule::bundled();
+        assert!(schedule.products().contains(&"debian".to_string()));
+        assert_eq!(schedule.product("debian"), Some("debian"));
+        assert_eq!(schedule.product("Python3"), Some("python"));
+        assert_eq!(schedule.product("openssl"), None);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let schedule = EolSchedule::bundled();
+
+        let (product, cycle) = schedule.lookup("alpine", "3.19.1").unwrap();
+        assert_eq!(product, "alpine");
+        assert_eq!(cycle.cycle, "3.19");
+        // `3.1` is not a prefix of `3.19`
+        assert_eq!(schedule.lookup("ruby", "3.10.0"), None);
+        assert_eq!(
+            schedule
+                .lookup("python3", "3.11.2-6+deb12u1")
+                .unwrap()
+                .1
+                .cycle,
+            "3.11"
+        );
+        assert_eq!(schedule.lookup("debian", "12").unwrap().1.cycle, "12");
+        assert_eq!(
+            schedule.lookup("golang", "go1.21.5").unwrap().1.cycle,
+            "1.21"
+        );
+        assert_eq!(
+            schedule.lookup("nodejs", "1:18.19.0").unwrap().1.cycle,
+            "18"
+        );
+        assert_eq!(schedule.lookup("debian", "unknown"), None);
+    }
+
+    #[test]
+    fn test_status() {
+        let today = date("2024-06-01");
+        let cycle = EolCycle {
+            cycle: "3.19".to_string(),
+            eol: EolDate::Date(date("2024-07-01")),
+        };
+        assert_eq!(cycle.status(today, 0), EolStatus::Supported);
+        assert_eq!(
+            cycle.status(today, 30),
+            EolStatus::Warning(date("2024-07-01"))
+        );
+        assert_eq!(
+            cycle.status(date("2024-07-01"), 0),
+            EolStatus::EndOfLife(Some(date("2024-07-01")))
+        );
+
+        let cycle = EolCycle {
+            cycle: "1".to_string(),
+            eol: EolDate::Flag(true),
+        };
+        assert_eq!(cycle.status(today, 0), EolStatus::EndOfLife(None));
+        let cycle = EolCycle {
+            cycle: "2".to_string(),
+            eol: EolDate::Flag(false),
+        };
+        assert_eq!(cycle.status(today, 365), EolStatus::Supported);
+    }
+
+    #[test]
+    fn test_api_and_cache() {
+        let data = br#"[
+            {"cycle": "3.20", "releaseDate": "2024-05-22", "eol": "2026-04-01", "latest": "3.20.1"},
+            {"cycle": "edge", "eol": false, "lts": false}
+        ]"#;
+        let cycles = EolSchedule::parse_api(data).unwrap();
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(cycles[0].date(), Some(date("2026-04-01")));
+        assert_eq!(cycles[1].eol, EolDate::Flag(false));
+
+        let mut schedule = EolSchedule::default();
+        schedule.update_product("alpine", cycles);
+        schedule.update_product("debian", vec![]);
+        assert_eq!(schedule.products(), vec!["alpine".to_string()]);
+
+        let path = std::env::temp_dir()
+            .join("konarr-test-eol")
+            .join("eol.json");
+        schedule.save(&path).unwrap();
+        // Cached products replace the bundled products
+        let loaded = EolSchedule::load(&path);
+        assert_eq!(
+            loaded.lookup("alpine", "3.20.1").unwrap().1,
+            &schedule.products["alpine"][0]
+        );
+        assert_eq!(loaded.lookup("alpine", "3.19.1"), None);
+        assert!(loaded.lookup("debian", "12.5").is_some());
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
@@ -0,0 +1,170 @@
+//! # Feeds
+//!
+//! Atom feeds ([RFC 4287](https://www.rfc-editor.org/rfc/rfc4287)) so the alerts of a
+//! project can be followed from a feed reader.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::badges::escape;
+
+/// Atom feed
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtomFeed {
+    /// Unique and permanent ID (IRI) of the feed
+    pub id: String,
+    /// Title of the feed
+    pub title: String,
+    /// Link to the page of the feed (`alternate`)
+    pub link: String,
+    /// Link to the feed itself (`self`)
+    pub self_link: String,
+    /// Updated time when the feed has no entries
+    pub updated: DateTime<Utc>,
+    /// Entries (newest first)
+    pub entries: Vec<AtomEntry>,
+}
+
+/// Atom feed entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtomEntry {
+    /// Unique and permanent ID (IRI) of the entry
+    pub id: String,
+    /// Title of the entry
+    pub title: String,
+    /// Link to the page of the entry
+    pub link: String,
+    /// Time the entry was created / updated
+    pub updated: DateTime<Utc>,
+    /// Summary (plain text)
+    pub summary: Option<String>,
+    /// Categories (terms)
+    pub categories: Vec<String>,
+}
+
+impl AtomFeed {
+    /// Time the feed was last updated (latest entry)
+    pub fn last_updated(&self) -> DateTime<Utc> {
+        self.entries
+            .iter()
+            .map(|entry| entry.updated)
+            .max()
+            .unwrap_or(self.updated)
+    }
+
+    /// Render the feed as XML
+    pub fn render(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        xml.push_str(&format!("  <id>{}</id>\n", escape(&self.id)));
+        xml.push_str(&format!("  <title>{}</title>\n", escape(&self.title)));
+        xml.push_str(&format!(
+            "  <updated>{}</updated>\n",
+            timestamp(&self.last_updated())
+        ));
+        xml.push_str(&format!(
+            "  <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n",
+            escape(&self.link)
+        ));
+        xml.push_str(&format!(
+            "  <link rel=\"self\" type=\"application/atom+xml\" href=\"{}\"/>\n",
+            escape(&self.self_link)
+        ));
+        xml.push_str("  <author><name>Konarr</name></author>\n");
+        xml.push_str(&format!(
+            "  <generator version=\"{}\">Konarr</generator>\n",
+            crate::KONARR_VERSION
+        ));
+
+        for entry in self.entries.iter() {
+            xml.push_str("  <entry>\n");
+            xml.push_str(&format!("    <id>{}</id>\n", escape(&entry.id)));
+            xml.push_str(&format!("    <title>{}</title>\n", escape(&entry.title)));
+            xml.push_str(&format!(
+                "    <updated>{}</updated>\n",
+                timestamp(&entry.updated)
+            ));
+            xml.push_str(&format!(
+                "    <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n",
+                escape(&entry.link)
+            ));
+            for category in entry.categories.iter() {
+                xml.push_str(&format!("    <category term=\"{}\"/>\n", escape(category)));
+            }
+            if let Some(summary) = &entry.summary {
+                xml.push_str(&format!(
+                    "    <summary type=\"text\">{}</summary>\n",
+                    escape(summary)
+                ));
+            }
+            xml.push_str("  </entry>\n");
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+}
+
+/// RFC 3339 timestamp (as required by Atom)
+fn timestamp(datetime: &DateTime<Utc>) -> String {
+    datetime.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn feed() -> AtomFeed {
+        AtomFeed {
+            id: "https://konarr.example.com/projects/1".to_string(),
+            title: "Konarr - <app> alerts".to_string(),
+            link: "https://konarr.example.com/projects/1".to_string(),
+            self_link: "https://konarr.example.com/api/projects/1/alerts.atom?token=a&b"
+                .to_string(),
+            updated: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            entries: vec![],
+        }
+    }
+
+    #[test]
+    fn test_empty_feed() {
+        let xml = feed().render();
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed "));
+        assert!(xml.contains("xmlns=\"http://www.w3.org/2005/Atom\""));
+        assert!(xml.contains("<updated>2024-01-01T00:00:00Z</updated>"));
+        assert!(xml.contains("<title>Konarr - &lt;app&gt; alerts</title>"));
+        assert!(xml.contains(
+            "href=\"https://konarr.example.com/api/projects/1/alerts.atom?token=a&amp;b\""
+        ));
+        assert!(xml.contains("<author><name>Konarr</name></author>"));
+        assert!(!xml.contains("<entry>"));
+        assert!(xml.trim_end().ends_with("</feed>"));
+    }
+
+    #[test]
+    fn test_feed_entries() {
+        let mut feed = feed();
+        for (id, day) in [(2, 3), (1, 2)] {
+            feed.entries.push(AtomEntry {
+                id: format!("{}#alert-{}", feed.link, id),
+                title: format!("CVE-2024-000{}", id),
+                link: feed.link.clone(),
+                updated: Utc.with_ymd_and_hms(2024, 1, day, 12, 0, 0).unwrap(),
+                summary: Some("openssl@3.0.1".to_string()),
+                categories: vec!["High".to_string()],
+            });
+        }
+        let xml = feed.render();
+
+        assert_eq!(xml.matches("<entry>").count(), 2);
+        assert_eq!(xml.matches("</entry>").count(), 2);
+        // The feed is updated with the latest entry
+        assert!(xml.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n  <id>https://konarr.example.com/projects/1</id>\n  <title>Konarr - &lt;app&gt; alerts</title>\n  <updated>2024-01-03T12:00:00Z</updated>"));
+        // Newest first
+        let first = xml.find("CVE-2024-0002").unwrap();
+        let second = xml.find("CVE-2024-0001").unwrap();
+        assert!(first < second);
+        assert!(xml.contains("<id>https://konarr.example.com/projects/1#alert-2</id>"));
+        assert!(xml.contains("<category term=\"High\"/>"));
+        assert!(xml.contains("<summary type=\"text\">openssl@3.0.1</summary>"));
+    }
+}
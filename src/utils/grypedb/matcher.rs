@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use geekorm::prelude::*;
 use log::debug;
 
@@ -7,7 +5,7 @@ use super::GrypeDatabase;
 use crate::{
     models::{
         dependencies::snapshots::AlertsSummary,
-        security::{Advisories, AdvisorySource, Alerts, SecuritySeverity},
+        security::{Advisories, AdvisorySource, Alerts, SecuritySeverity, SecurityState},
         Dependencies, Snapshot,
     },
     utils::grypedb::GrypeVulnerabilityMetadata,
@@ -40,7 +38,7 @@ impl GrypeDatabase {
         debug!("Dependencies Count: {}", snapshot.components.len());
 
         // Summary of the Security Alerts (cached)
-        let mut summary: AlertsSummary = HashMap::new();
+        let mut summary = AlertsSummary::new();
 
         for dependency in snapshot.components.iter_mut() {
             log::debug!(
@@ -185,12 +183,23 @@ impl GrypeDatabase {
                     .add_metadata(connection, "data.source", "GrypeDB".to_string())
                     .await?;
 
-                let mut alert =
-                    Alerts::new(vuln.id.clone(), snapshot.id, dependency.id, advisory.id);
+                let mut alert = Alerts {
+                    dependency_id: Some(dependency.id.into()),
+                    ..Alerts::new(vuln.id.clone(), snapshot.id, advisory.id)
+                };
                 alert.find_or_create(connection).await?;
+                alert
+                    .apply_ignore_rules(
+                        connection,
+                        &dependency.component_id.data.purl(),
+                        Some(&dependency.component_version_id.data.version),
+                    )
+                    .await?;
                 debug!("Created Alert: {}", alert.id);
 
-                *summary.entry(severity).or_insert(0) += 1;
+                if alert.state != SecurityState::Ignored {
+                    *summary.entry(severity).or_insert(0) += 1;
+                }
 
                 results.push(alert);
             }
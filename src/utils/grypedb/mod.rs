@@ -1,6 +1,10 @@
 //! # Grype Database
 #![allow(missing_docs)]
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use chrono::Timelike;
 use geekorm::prelude::*;
@@ -19,6 +23,9 @@ use crate::{
 
 mod matcher;
 
+/// Grype database listing URL
+const GRYPE_LISTING_URL: &str = "https://toolbox-data.anchore.io/grype/databases/listing.json";
+
 /// Grype Database
 pub struct GrypeDatabase {
     /// Connection to the Grype database
@@ -51,21 +58,82 @@ impl GrypeDatabase {
         })
     }
 
+    /// Status of the Grype database at the path (without downloading it)
+    ///
+    /// Path can be a directory (with vulnerability.db) or the database file
+    pub async fn status(path: &Path) -> GrypeDatabaseStatus {
+        let dbpath = if path.is_dir() || path.extension().is_none() {
+            path.join("5").join("vulnerability.db")
+        } else {
+            path.to_path_buf()
+        };
+        let mut status = GrypeDatabaseStatus {
+            path: dbpath.clone(),
+            present: dbpath.is_file(),
+            ..Default::default()
+        };
+        if !status.present {
+            return status;
+        }
+
+        match GrypeDatabase::connect(&dbpath).await {
+            Ok(grype) => {
+                if let Ok(id) = grype.fetch_grype().await {
+                    status.schema_version = Some(id.schema_version);
+                    status.build = Some(id.build_timestamp);
+                }
+                match grype.count_vulnerabilities().await {
+                    Ok(count) => status.vulnerabilities = Some(count),
+                    Err(e) => warn!("Failed to count the Grype vulnerabilities: {}", e),
+                }
+            }
+            Err(e) => warn!("Failed to open the Grype DB `{}`: {}", dbpath.display(), e),
+        }
+        status
+    }
+
     /// Sync the Grype database
     ///
     /// The path is the directory where the Grype database is stored
     pub async fn sync(path: &PathBuf) -> Result<bool, KonarrError> {
+        Self::sync_from(path, GRYPE_LISTING_URL).await
+    }
+
+    /// Download the latest Grype database even if the current one is up to date
+    pub async fn sync_force(path: &PathBuf) -> Result<bool, KonarrError> {
+        debug!("Force downloading Grype DB");
+        let latest = GrypeDatabase::latest().await?;
+        std::fs::create_dir_all(path)?;
+        GrypeDatabase::download(path, &latest).await?;
+        Ok(true)
+    }
+
+    /// Sync the Grype database using the listing at the URL
+    ///
+    /// If the listing was not modified since the last sync (`304 Not Modified`), the
+    /// existing database is kept without checking the build.
+    async fn sync_from(path: &PathBuf, listing_url: &str) -> Result<bool, KonarrError> {
         debug!("Syncing Grype DB");
         let dbpath = path.join("5").join("vulnerability.db");
 
         // Fetch the latest Grype database listing
-        let latest = GrypeDatabase::latest().await?;
+        let (listing, modified) = GrypeDatabase::listings_conditional(listing_url).await?;
+        if !modified && dbpath.exists() {
+            debug!("Grype DB listing not modified, skipping update");
+            return Ok(false);
+        }
+        let latest = listing
+            .latest()
+            .ok_or(KonarrError::NotFound(
+                "Grype DB listing has no latest entry".into(),
+            ))?
+            .clone();
         debug!("Latest Grype DB: {}", latest.built);
         let latest_build = latest.built.with_nanosecond(0).unwrap();
 
         if !path.exists() || !dbpath.exists() {
             if let Some(_) = path.extension() {
-                return Err(KonarrError::UnknownError(
+                return Err(KonarrError::ConfigParseError(
                     "Grype path is a file, not a directory".into(),
                 ));
             }
@@ -101,19 +169,27 @@ impl GrypeDatabase {
 
     /// Get the Grype database listings
     pub async fn listings() -> Result<GrypeListingResponse, KonarrError> {
-        reqwest::get("https://toolbox-data.anchore.io/grype/databases/listing.json")
-            .await?
-            .json::<GrypeListingResponse>()
-            .await
-            .map_err(KonarrError::from)
+        Ok(Self::listings_conditional(GRYPE_LISTING_URL).await?.0)
+    }
+
+    /// Get the Grype database listings and if they changed since the last request
+    ///
+    /// The listing is only downloaded again if the `ETag` / `Last-Modified` changed.
+    async fn listings_conditional(url: &str) -> Result<(GrypeListingResponse, bool), KonarrError> {
+        let client = crate::utils::config::client_builder().build()?;
+        let response = crate::utils::http::HttpCache::global()
+            .get_conditional(&client, url)
+            .await?;
+        let listing = serde_json::from_slice(response.body())?;
+        Ok((listing, response.is_modified()))
     }
 
     /// Get the latest Grype database entry from the listings
     pub async fn latest() -> Result<GrypeDatabaseEntry, KonarrError> {
         let response = Self::listings().await?;
-        let latest = response
-            .latest()
-            .ok_or(KonarrError::UnknownError("No latest entry".into()))?;
+        let latest = response.latest().ok_or(KonarrError::NotFound(
+            "Grype DB listing has no latest entry".into(),
+        ))?;
         assert_eq!(latest.version, 5);
         Ok(latest.clone())
     }
@@ -129,11 +205,9 @@ impl GrypeDatabase {
 
         let archive_path = GrypeDatabase::download_archive(&path_version, &build.url).await?;
 
-        if !GrypeDatabase::verify(&archive_path, &build.checksum)? {
+        if let Err(e) = GrypeDatabase::verify(&archive_path, &build.checksum) {
             error!("Checksum verification failed, security risk!");
-            return Err(KonarrError::UnknownError(
-                "Checksum verification failed".into(),
-            ));
+            return Err(e);
         }
 
         GrypeDatabase::unarchive(&archive_path)?;
@@ -152,10 +226,10 @@ impl GrypeDatabase {
     /// Checksum is the SHA256 checksum provided by the Grype database listing
     ///
     /// Security: We validate the checksum to ensure the Grype database is not tampered with
-    fn verify(path: &PathBuf, checksum: &str) -> Result<bool, KonarrError> {
+    fn verify(path: &PathBuf, checksum: &str) -> Result<(), KonarrError> {
         // Decode the checksum from hex (remove the sha256: prefix)
-        let checksum_decode = hex::decode(&checksum[7..])
-            .map_err(|_| KonarrError::UnknownError("Unable to decode checksum".into()))?;
+        let checksum_decode = hex::decode(checksum.get(7..).unwrap_or_default())
+            .map_err(|_| KonarrError::InvalidData("Unable to decode checksum".into()))?;
         // Generate the SHA256 checksum of the file
         let file = std::fs::File::open(path)?;
         let mut reader = std::io::BufReader::new(file);
@@ -165,7 +239,14 @@ impl GrypeDatabase {
 
         debug!("GrypeDB Checksum - {} :: {}", hex::encode(result), checksum);
         // Compare the checksums
-        Ok(checksum_decode == result.as_slice())
+        if checksum_decode == result.as_slice() {
+            Ok(())
+        } else {
+            Err(KonarrError::ChecksumMismatch {
+                expected: checksum.to_string(),
+                actual: format!("sha256:{}", hex::encode(result)),
+            })
+        }
     }
 
     /// Download a Grype Database archive
@@ -178,7 +259,11 @@ impl GrypeDatabase {
             std::fs::remove_file(&path_archive)?;
         }
 
-        let response = reqwest::get(url.clone()).await?;
+        let response = crate::utils::config::client_builder()
+            .build()?
+            .get(url.clone())
+            .send()
+            .await?;
         let bytes = response.bytes().await?;
 
         debug!("Saving to: {:?}", path);
@@ -193,10 +278,14 @@ impl GrypeDatabase {
     /// Security: We trust the Grype database to not contain malicious files
     fn unarchive(path: &PathBuf) -> Result<(), KonarrError> {
         if !path.exists() {
-            return Err(KonarrError::UnknownError("Archive does not exist".into()));
+            return Err(KonarrError::NotFound(
+                "Grype DB archive does not exist".into(),
+            ));
         }
         if !path.is_file() {
-            return Err(KonarrError::UnknownError("Archive is not a file".into()));
+            return Err(KonarrError::InvalidData(
+                "Grype DB archive is not a file".into(),
+            ));
         }
 
         debug!("Unarchiving Grype DB to: {:?}", path.parent().unwrap());
@@ -228,6 +317,18 @@ impl GrypeDatabase {
         )
     }
 
+    /// Number of vulnerabilities in the Grype database
+    pub async fn count_vulnerabilities(&self) -> Result<u64, KonarrError> {
+        let connection = self.connection.lock().await;
+        let mut rows = connection
+            .query("SELECT COUNT(*) FROM vulnerability;", ())
+            .await?;
+        match rows.next().await? {
+            Some(row) => Ok(row.get::<u64>(0)?),
+            None => Ok(0),
+        }
+    }
+
     pub async fn fetch_vulnerabilities(&mut self) -> Result<&Vec<GrypeVulnerability>, KonarrError> {
         if self.vulnerabilities.is_empty() {
             debug!("Loading Grype vulnerabilities");
@@ -272,24 +373,30 @@ impl GrypeDatabase {
 
         let mut results = vec![];
 
-        // TODO: This is a issue, this has 4million+ entries
-        for vuln in self.vulnerabilities.iter() {
-            // Skip if no version constraint
-            if vuln.version_constraint.is_empty() {
-                continue;
-            }
-            // Name matching
-            if vuln.package_name != comp.name {
-                continue;
-            }
+        // The origin package is only used if the package name finds nothing
+        for name in comp.names() {
+            // TODO: This is a issue, this has 4million+ entries
+            for vuln in self.vulnerabilities.iter() {
+                // Skip if no version constraint
+                if vuln.version_constraint.is_empty() {
+                    continue;
+                }
+                // Name matching
+                if vuln.package_name != name {
+                    continue;
+                }
 
-            // Version matching
-            if let Ok(versions) = semver::VersionReq::parse(vuln.version_constraint.as_str()) {
-                if versions.matches(&version) {
-                    results.push(vuln.clone());
+                // Version matching
+                if let Ok(versions) = semver::VersionReq::parse(vuln.version_constraint.as_str()) {
+                    if versions.matches(&version) {
+                        results.push(vuln.clone());
+                    }
+                } else {
+                    trace!("Unable to parse version req: {}", vuln.version_constraint);
                 }
-            } else {
-                trace!("Unable to parse version req: {}", vuln.version_constraint);
+            }
+            if !results.is_empty() {
+                break;
             }
         }
 
@@ -333,6 +440,21 @@ pub struct GrypeId {
     pub schema_version: i32,
 }
 
+/// Status of a Grype database (see [GrypeDatabase::status])
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct GrypeDatabaseStatus {
+    /// Path of the database file
+    pub path: PathBuf,
+    /// If the database file exists
+    pub present: bool,
+    /// Schema version of the database
+    pub schema_version: Option<i32>,
+    /// When the database was built
+    pub build: Option<chrono::DateTime<chrono::Utc>>,
+    /// Number of vulnerability rows
+    pub vulnerabilities: Option<u64>,
+}
+
 #[derive(Table, Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
 #[cfg(feature = "models")]
 #[geekorm(rename = "vulnerability")]
@@ -393,14 +515,15 @@ impl GrypeVulnerability {
         let mut results: Vec<GrypeVulnerability> = vec![];
 
         // TODO: Manager?
-        let vulns =
-            GrypeVulnerability::fetch_by_package_name(connection, component.name.clone()).await?;
-
-        debug!(
-            "Found {} vulns for package: {}",
-            vulns.len(),
-            component.name
-        );
+        // The origin package is only used if the package name finds nothing
+        let mut vulns = vec![];
+        for name in component.names() {
+            vulns = GrypeVulnerability::fetch_by_package_name(connection, name.to_string()).await?;
+            debug!("Found {} vulns for package: {}", vulns.len(), name);
+            if !vulns.is_empty() {
+                break;
+            }
+        }
 
         for vuln in vulns.iter() {
             if vuln.version_constraint.is_empty() {
@@ -459,3 +582,110 @@ impl GrypeVulnerabilityMetadata {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::http::{tests::serve, HttpCache, HttpCacheEntry};
+
+    #[tokio::test]
+    async fn test_sync_not_modified() {
+        let path = std::env::temp_dir().join("konarr-test-grypedb-sync");
+        std::fs::create_dir_all(path.join("5")).unwrap();
+        // Not a valid database, the sync would fail if it was opened
+        std::fs::write(path.join("5").join("vulnerability.db"), b"").unwrap();
+
+        let (url, handle) = serve(vec![
+            "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string(),
+        ])
+        .await;
+        HttpCache::global().insert(
+            url.clone(),
+            HttpCacheEntry {
+                etag: Some("\"listing\"".to_string()),
+                last_modified: None,
+                body: br#"{"available": {}}"#.to_vec(),
+            },
+        );
+
+        let new = GrypeDatabase::sync_from(&path, &url).await.unwrap();
+        assert!(!new);
+
+        let requests = handle.await.unwrap();
+        assert!(requests[0].contains("if-none-match: \"listing\""));
+
+        std::fs::remove_dir_all(path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_find_vulnerabilities_origin() -> Result<(), KonarrError> {
+        let connection = libsql::Builder::new_local(":memory:")
+            .build()
+            .await?
+            .connect()?;
+        GrypeVulnerability::create_table(&connection).await?;
+        // Alpine advisory of the `openssl` origin package
+        let mut vuln = GrypeVulnerability {
+            id: "CVE-2024-5535".to_string(),
+            package_name: "openssl".to_string(),
+            namespace: "alpine:distro:alpine:3.20".to_string(),
+            version_constraint: "< 3.3.3".to_string(),
+            version_format: "apk".to_string(),
+            fix_state: "fixed".to_string(),
+            ..Default::default()
+        };
+        vuln.save(&connection).await?;
+
+        let (mut component, _) =
+            crate::models::Component::from_purl("pkg:apk/alpine/libcrypto3@3.3.2-r0")?;
+        let version = crate::models::ComponentVersion::new(0, "3.3.2".to_string());
+        let vulns =
+            GrypeVulnerability::find_vulnerabilities(&connection, &component, &version).await?;
+        assert!(vulns.is_empty());
+
+        component.origin = Some("openssl".to_string());
+        let vulns =
+            GrypeVulnerability::find_vulnerabilities(&connection, &component, &version).await?;
+        assert_eq!(vulns.len(), 1);
+        assert_eq!(vulns[0].id, "CVE-2024-5535");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_status() -> Result<(), KonarrError> {
+        let path = std::env::temp_dir().join("konarr-test-grypedb-status");
+        std::fs::remove_dir_all(&path).ok();
+
+        let status = GrypeDatabase::status(&path).await;
+        assert!(!status.present);
+        assert_eq!(status.path, path.join("5").join("vulnerability.db"));
+        assert_eq!(status.build, None);
+
+        std::fs::create_dir_all(path.join("5"))?;
+        let connection = libsql::Builder::new_local(&status.path)
+            .build()
+            .await?
+            .connect()?;
+        connection
+            .execute_batch(
+                "CREATE TABLE id (build_timestamp TEXT, schema_version INTEGER);
+                INSERT INTO id VALUES ('2026-10-01T04:12:00Z', 5);
+                CREATE TABLE vulnerability (id TEXT, package_name TEXT);
+                INSERT INTO vulnerability VALUES ('CVE-2026-0001', 'openssl');
+                INSERT INTO vulnerability VALUES ('CVE-2026-0002', 'zlib');",
+            )
+            .await?;
+
+        let status = GrypeDatabase::status(&path).await;
+        assert!(status.present);
+        assert_eq!(status.schema_version, Some(5));
+        assert_eq!(
+            status.build.map(|b| b.to_rfc3339()),
+            Some("2026-10-01T04:12:00+00:00".to_string())
+        );
+        assert_eq!(status.vulnerabilities, Some(2));
+
+        std::fs::remove_dir_all(path).ok();
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+//! # HTTP Conditional Requests
+//!
+//! Caches the `ETag` / `Last-Modified` validators (and body) of responses so repeated
+//! requests to the same URL send `If-None-Match` / `If-Modified-Since` and a
+//! `304 Not Modified` response can be served from the cache.
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use reqwest::{header, StatusCode};
+
+use crate::KonarrError;
+
+/// Process wide HTTP cache
+static HTTP_CACHE: OnceLock<HttpCache> = OnceLock::new();
+
+/// Cached HTTP response
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HttpCacheEntry {
+    /// `ETag` header of the response
+    pub etag: Option<String>,
+    /// `Last-Modified` header of the response
+    pub last_modified: Option<String>,
+    /// Body of the response
+    pub body: Vec<u8>,
+}
+
+/// Response of a conditional request
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionalResponse {
+    /// Resource changed (or was not cached)
+    Modified(Vec<u8>),
+    /// Resource did not change (`304 Not Modified`), body is from the cache
+    NotModified(Vec<u8>),
+}
+
+impl ConditionalResponse {
+    /// Check if the resource changed
+    pub fn is_modified(&self) -> bool {
+        matches!(self, ConditionalResponse::Modified(_))
+    }
+
+    /// Body of the response
+    pub fn body(&self) -> &[u8] {
+        match self {
+            ConditionalResponse::Modified(body) | ConditionalResponse::NotModified(body) => body,
+        }
+    }
+}
+
+/// HTTP Cache keyed by URL
+#[derive(Debug, Default)]
+pub struct HttpCache {
+    entries: Mutex<HashMap<String, HttpCacheEntry>>,
+}
+
+impl HttpCache {
+    /// Get the process wide HTTP cache
+    pub fn global() -> &'static HttpCache {
+        HTTP_CACHE.get_or_init(HttpCache::default)
+    }
+
+    /// Get the cached entry for a URL
+    pub fn get(&self, url: &str) -> Option<HttpCacheEntry> {
+        self.entries.lock().ok()?.get(url).cloned()
+    }
+
+    /// Store an entry for a URL
+    pub fn insert(&self, url: impl Into<String>, entry: HttpCacheEntry) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(url.into(), entry);
+        }
+    }
+
+    /// Send a conditional GET request
+    ///
+    /// Responses without validators are not cached.
+    pub async fn get_conditional(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+    ) -> Result<ConditionalResponse, KonarrError> {
+        let cached = self.get(url);
+
+        let mut request = client.get(url);
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                log::debug!("Not modified: {}", url);
+                return Ok(ConditionalResponse::NotModified(cached.body));
+            }
+            return Err(KonarrError::unknown(format!(
+                "Not modified response without a cached entry: {}",
+                url
+            )));
+        }
+        if !response.status().is_success() {
+            return Err(KonarrError::from_status(
+                response.status().as_u16(),
+                format!("Request failed ({}): {}", response.status(), url),
+            ));
+        }
+
+        let header_value = |name: header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let etag = header_value(header::ETAG);
+        let last_modified = header_value(header::LAST_MODIFIED);
+
+        let body = response.bytes().await?.to_vec();
+        if etag.is_some() || last_modified.is_some() {
+            self.insert(
+                url,
+                HttpCacheEntry {
+                    etag,
+                    last_modified,
+                    body: body.clone(),
+                },
+            );
+        }
+        Ok(ConditionalResponse::Modified(body))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve a single HTTP response per request, returning the URL and the received requests
+    pub(crate) async fn serve(
+        responses: Vec<String>,
+    ) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let mut requests = vec![];
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0; 4096];
+                let size = socket.read(&mut buffer).await.unwrap();
+                requests.push(String::from_utf8_lossy(&buffer[..size]).to_lowercase());
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.ok();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_conditional_get() {
+        let (url, handle) = serve(vec![
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello"
+                .to_string(),
+            "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n".to_string(),
+        ])
+        .await;
+
+        let cache = HttpCache::default();
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+
+        let first = cache.get_conditional(&client, &url).await.unwrap();
+        assert_eq!(first, ConditionalResponse::Modified(b"hello".to_vec()));
+
+        let second = cache.get_conditional(&client, &url).await.unwrap();
+        assert!(!second.is_modified());
+        assert_eq!(second.body(), b"hello");
+
+        let requests = handle.await.unwrap();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+    }
+}
@@ -1,8 +1,16 @@
 //! # Security Module
 
+pub mod badges;
 #[cfg(feature = "models")]
 pub mod catalogue;
 pub mod config;
+pub mod containers;
+pub mod eol;
+pub mod feeds;
 #[cfg(feature = "tools-grypedb")]
 pub mod grypedb;
+#[cfg(feature = "client")]
+pub mod http;
+pub mod names;
 pub mod rand;
+pub mod version;
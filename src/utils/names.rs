@@ -0,0 +1,163 @@
+//! # Project Names
+//!
+//! Project names act as the unique identifier of a project. Agents build them from
+//! hostnames, compose labels and container names so they are normalized before they are
+//! stored or looked up (the original string is kept as the project title).
+
+/// Separator between the segments of a project name (`server/stack/service`)
+pub const PROJECT_NAME_SEPARATOR: char = '/';
+
+/// Normalize a project name
+///
+/// - Trims and lowercases the name
+/// - Collapses whitespace (and any other character not in `[a-z0-9._-]`) into a single `-`
+/// - Keeps the `/` separators but removes the empty segments
+///
+/// ```rust
+/// use konarr::utils::names::normalize_project_name;
+///
+/// assert_eq!(normalize_project_name("  MyHost "), "myhost");
+/// assert_eq!(normalize_project_name("My Stack/Web  Server"), "my-stack/web-server");
+/// ```
+pub fn normalize_project_name(name: impl AsRef<str>) -> String {
+    name.as_ref()
+        .split(PROJECT_NAME_SEPARATOR)
+        .map(normalize_segment)
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// Display title of a project name (the last segment of the original name)
+pub fn project_name_title(name: impl AsRef<str>) -> String {
+    name.as_ref()
+        .split(PROJECT_NAME_SEPARATOR)
+        .map(str::trim)
+        .rfind(|segment| !segment.is_empty())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Name of a project relative to its parent (the name without the first segment)
+///
+/// Agents name the projects `<server>/<container>` so the relative name stays the same
+/// when a project moves to another server.
+pub fn project_relative_name(name: impl AsRef<str>) -> Option<String> {
+    normalize_project_name(name)
+        .split_once(PROJECT_NAME_SEPARATOR)
+        .map(|(_, rest)| rest.to_string())
+}
+
+/// Name of a project after it moved to another parent
+///
+/// The prefix of the old parent is replaced with the name of the new parent, names
+/// without the prefix are moved under the new parent.
+///
+/// ```rust
+/// use konarr::utils::names::transfer_project_name;
+///
+/// assert_eq!(transfer_project_name("server-a/web", Some("server-a"), "server-b"), "server-b/web");
+/// assert_eq!(transfer_project_name("web", None, "server-b"), "server-b/web");
+/// ```
+pub fn transfer_project_name(name: &str, old_parent: Option<&str>, new_parent: &str) -> String {
+    let name = normalize_project_name(name);
+    let rest = old_parent
+        .map(normalize_project_name)
+        .and_then(|prefix| {
+            name.strip_prefix(&format!("{}{}", prefix, PROJECT_NAME_SEPARATOR))
+                .map(|rest| rest.to_string())
+        })
+        .unwrap_or(name);
+    normalize_project_name(format!("{}{}{}", new_parent, PROJECT_NAME_SEPARATOR, rest))
+}
+
+fn normalize_segment(segment: &str) -> String {
+    let mut normalized = String::with_capacity(segment.len());
+    for c in segment.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+            normalized.push(c);
+        } else if !normalized.ends_with('-') {
+            normalized.push('-');
+        }
+    }
+    normalized.trim_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_project_name() {
+        assert_eq!(normalize_project_name("MyHost"), "myhost");
+        assert_eq!(normalize_project_name("myhost"), "myhost");
+        assert_eq!(normalize_project_name("  Main   Server\t"), "main-server");
+        assert_eq!(
+            normalize_project_name("host.example.com"),
+            "host.example.com"
+        );
+        assert_eq!(normalize_project_name("Café Übersicht"), "caf-bersicht");
+        assert_eq!(normalize_project_name("app@v1!"), "app-v1");
+        assert_eq!(normalize_project_name("   "), "");
+        // Already normalized names are not changed
+        let name = normalize_project_name("My Host/My App");
+        assert_eq!(normalize_project_name(&name), name);
+    }
+
+    #[test]
+    fn test_normalize_compose_names() {
+        // `{server}/{compose project}/{compose service}` (from the agent)
+        assert_eq!(
+            normalize_project_name("MyHost/My Stack/Web Server"),
+            "myhost/my-stack/web-server"
+        );
+        assert_eq!(
+            normalize_project_name("myhost/konarr/konarr_agent"),
+            "myhost/konarr/konarr_agent"
+        );
+        // Empty segments and spaces around the separators
+        assert_eq!(
+            normalize_project_name("/MyHost // Stack / Service/"),
+            "myhost/stack/service"
+        );
+        assert_eq!(
+            normalize_project_name("MyHost/stack"),
+            normalize_project_name("myhost/Stack")
+        );
+    }
+
+    #[test]
+    fn test_project_name_title() {
+        assert_eq!(
+            project_name_title("MyHost/My Stack/Web Server"),
+            "Web Server"
+        );
+        assert_eq!(project_name_title("MyHost"), "MyHost");
+        assert_eq!(project_name_title("MyHost/ "), "MyHost");
+        assert_eq!(project_name_title(""), "");
+    }
+
+    #[test]
+    fn test_transfer_project_name() {
+        assert_eq!(
+            transfer_project_name("server-a/stack/web", Some("server-a"), "server-b"),
+            "server-b/stack/web"
+        );
+        // Not using the prefix of the old parent
+        assert_eq!(
+            transfer_project_name("other/web", Some("server-a"), "Server B"),
+            "server-b/other/web"
+        );
+        // `server-ab` does not start with the `server-a` segment
+        assert_eq!(
+            transfer_project_name("server-ab/web", Some("server-a"), "server-b"),
+            "server-b/server-ab/web"
+        );
+
+        assert_eq!(
+            project_relative_name("Server-A/Stack/Web"),
+            Some("stack/web".to_string())
+        );
+        assert_eq!(project_relative_name("web"), None);
+    }
+}
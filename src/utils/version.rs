@@ -0,0 +1,83 @@
+//! # Version Utilities
+//!
+//! Lenient parsing and comparison of the Konarr versions reported by the agents
+//! (`v0.4.1`, `0.4`, `0.5.0-dev`, `0.5.0+abc123`, ...).
+
+/// Parse a version, ignoring a `v` prefix, missing patch and any pre-release / build
+/// suffix (development builds are treated as the release they are based on)
+pub fn parse_version(version: &str) -> Option<semver::Version> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let core = version.split(['-', '+', ' ']).next().unwrap_or_default();
+
+    let mut parts = core.split('.').map(|part| part.parse::<u64>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some(semver::Version::new(major, minor, patch))
+}
+
+/// Parse the Konarr version from a `User-Agent` header (`Konarr/0.4.1`)
+pub fn user_agent_version(user_agent: &str) -> Option<String> {
+    user_agent
+        .split_whitespace()
+        .find_map(|product| {
+            product
+                .split_once('/')
+                .filter(|(name, _)| name.eq_ignore_ascii_case("konarr"))
+        })
+        .map(|(_, version)| version.to_string())
+        .filter(|version| parse_version(version).is_some())
+}
+
+/// Check if an agent version is behind the server version by more than `max_minor_gap`
+/// minor versions (any older major version is outdated)
+///
+/// Agents newer than the server and unparsable versions are never outdated.
+pub fn is_outdated(agent: &str, server: &str, max_minor_gap: u64) -> bool {
+    let (Some(agent), Some(server)) = (parse_version(agent), parse_version(server)) else {
+        return false;
+    };
+    match agent.major.cmp(&server.major) {
+        std::cmp::Ordering::Less => true,
+        std::cmp::Ordering::Greater => false,
+        std::cmp::Ordering::Equal => server.minor.saturating_sub(agent.minor) > max_minor_gap,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        let version = |major, minor, patch| Some(semver::Version::new(major, minor, patch));
+        assert_eq!(parse_version("0.4.1"), version(0, 4, 1));
+        assert_eq!(parse_version("v0.4.1"), version(0, 4, 1));
+        assert_eq!(parse_version("0.4"), version(0, 4, 0));
+        assert_eq!(parse_version("0.5.0-dev"), version(0, 5, 0));
+        assert_eq!(parse_version("0.5.0-rc.1+abc123"), version(0, 5, 0));
+        assert_eq!(parse_version("1.2.3+build.5"), version(1, 2, 3));
+        assert_eq!(parse_version("main"), None);
+        assert_eq!(parse_version(""), None);
+
+        assert_eq!(
+            user_agent_version("Konarr/0.4.1 (linux)"),
+            Some("0.4.1".to_string())
+        );
+        assert_eq!(user_agent_version("curl/8.5.0"), None);
+        assert_eq!(user_agent_version("Konarr/unknown"), None);
+    }
+
+    #[test]
+    fn test_is_outdated() {
+        assert!(!is_outdated("0.4.1", "0.4.3", 2));
+        assert!(!is_outdated("0.2.0", "0.4.0", 2));
+        assert!(is_outdated("0.1.9", "0.4.0", 2));
+        assert!(is_outdated("0.4.0", "1.0.0", 2));
+        assert!(is_outdated("0.4.0", "0.5.0", 0));
+        // Development builds and newer agents
+        assert!(!is_outdated("0.5.0-dev", "0.5.0", 0));
+        assert!(!is_outdated("0.6.0", "0.4.0-dev", 0));
+        assert!(!is_outdated("dev", "0.4.0", 0));
+    }
+}